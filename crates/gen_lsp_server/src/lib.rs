@@ -62,9 +62,11 @@
 use failure::{bail, format_err};
 
 mod msg;
+mod socket;
 mod stdio;
 
 use crossbeam_channel::{Receiver, Sender};
+use serde_json::Value;
 use languageserver_types::{
     notification::{Exit, Initialized},
     request::{Initialize, Shutdown},
@@ -74,12 +76,17 @@ use languageserver_types::{
 pub type Result<T> = ::std::result::Result<T, failure::Error>;
 pub use crate::{
     msg::{ErrorCode, RawMessage, RawNotification, RawRequest, RawResponse, RawResponseError},
-    stdio::{stdio_transport, Threads},
+    socket::tcp_transport,
+    stdio::{io_transport, stdio_transport, Threads},
 };
 
+#[cfg(unix)]
+pub use crate::socket::pipe_transport;
+
 /// Main entry point: runs the server from initialization to shutdown.
 /// To attach server to standard input/output streams, use `stdio_transport`
-/// function to create corresponding `sender` and `receiver` pair.
+/// function to create corresponding `sender` and `receiver` pair. To talk to
+/// a client over a socket, use `tcp_transport` or `pipe_transport`.
 ///
 ///`server` should use `handle_shutdown` function to handle the `Shutdown`
 /// request.
@@ -88,22 +95,55 @@ pub fn run_server(
     receiver: Receiver<RawMessage>,
     sender: Sender<RawMessage>,
     server: impl FnOnce(InitializeParams, &Receiver<RawMessage>, &Sender<RawMessage>) -> Result<()>,
+) -> Result<()> {
+    let result = serde_json::to_value(InitializeResult { capabilities: caps }).unwrap();
+    run_server_with_init(|_| result, receiver, sender, server)
+}
+
+/// Like `run_server`, but the result of the `initialize` request is computed
+/// from the raw parameters of the request. This allows to negotiate protocol
+/// extensions which are not modeled by `languageserver_types`.
+pub fn run_server_with_init(
+    init: impl FnOnce(&Value) -> Value,
+    receiver: Receiver<RawMessage>,
+    sender: Sender<RawMessage>,
+    server: impl FnOnce(InitializeParams, &Receiver<RawMessage>, &Sender<RawMessage>) -> Result<()>,
 ) -> Result<()> {
     log::info!("lsp server initializes");
-    let params = initialize(&receiver, &sender, caps)?;
+    let params = initialize(&receiver, &sender, init)?;
     log::info!("lsp server initialized, serving requests");
     server(params, &receiver, &sender)?;
     log::info!("lsp server waiting for exit notification");
-    match receiver.recv() {
-        Ok(RawMessage::Notification(n)) => n
-            .cast::<Exit>()
-            .map_err(|n| format_err!("unexpected notification during shutdown: {:?}", n))?,
-        m => bail!("unexpected message during shutdown: {:?}", m),
-    }
+    wait_for_exit(&receiver, &sender)?;
     log::info!("lsp server shutdown complete");
     Ok(())
 }
 
+/// After the shutdown, requests are rejected and notifications other than
+/// `exit` are ignored.
+fn wait_for_exit(receiver: &Receiver<RawMessage>, sender: &Sender<RawMessage>) -> Result<()> {
+    for msg in receiver {
+        match msg {
+            RawMessage::Notification(n) => match n.cast::<Exit>() {
+                Ok(()) => return Ok(()),
+                Err(n) => log::warn!("unexpected notification during shutdown: {:?}", n),
+            },
+            RawMessage::Request(req) => {
+                let resp = RawResponse::err(
+                    req.id,
+                    ErrorCode::InvalidRequest as i32,
+                    "server is shut down".to_string(),
+                );
+                let _ = sender.send(RawMessage::Response(resp));
+            }
+            RawMessage::Response(resp) => {
+                log::warn!("unexpected response during shutdown: {:?}", resp)
+            }
+        }
+    }
+    bail!("client exited without the exit notification")
+}
+
 /// if `req` is `Shutdown`, respond to it and return `None`, otherwise return `Some(req)`
 pub fn handle_shutdown(req: RawRequest, sender: &Sender<RawMessage>) -> Option<RawRequest> {
     match req.cast::<Shutdown>() {
@@ -119,16 +159,23 @@ pub fn handle_shutdown(req: RawRequest, sender: &Sender<RawMessage>) -> Option<R
 fn initialize(
     receiver: &Receiver<RawMessage>,
     sender: &Sender<RawMessage>,
-    caps: ServerCapabilities,
+    init: impl FnOnce(&Value) -> Value,
 ) -> Result<InitializeParams> {
-    let (id, params) = match receiver.recv() {
-        Ok(RawMessage::Request(req)) => match req.cast::<Initialize>() {
-            Err(req) => bail!("expected initialize request, got {:?}", req),
-            Ok(req) => req,
-        },
+    let (id, params, result) = match receiver.recv() {
+        Ok(RawMessage::Request(req)) => {
+            let result = init(&req.params);
+            match req.cast::<Initialize>() {
+                Err(req) => bail!("expected initialize request, got {:?}", req),
+                Ok((id, params)) => (id, params, result),
+            }
+        }
         msg => bail!("expected initialize request, got {:?}", msg),
     };
-    let resp = RawResponse::ok::<Initialize>(id, &InitializeResult { capabilities: caps });
+    let resp = RawResponse {
+        id,
+        result: Some(result),
+        error: None,
+    };
     sender.send(RawMessage::Response(resp)).unwrap();
     match receiver.recv() {
         Ok(RawMessage::Notification(n)) => {
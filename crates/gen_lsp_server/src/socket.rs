@@ -0,0 +1,33 @@
+//! Transports for clients which can't spawn the server as a child process, and
+//! connect to it instead. The server waits for a single client to connect, and
+//! serves it until the connection is closed.
+use std::{io::BufReader, net::TcpListener};
+#[cfg(unix)]
+use std::{os::unix::net::UnixListener, path::Path};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{io_transport, RawMessage, Result, Threads};
+
+/// Listens on the TCP address `addr` and talks to the first client which
+/// connects to it.
+pub fn tcp_transport(addr: &str) -> Result<(Receiver<RawMessage>, Sender<RawMessage>, Threads)> {
+    let listener = TcpListener::bind(addr)?;
+    log::info!("waiting for a client on {}", listener.local_addr()?);
+    let (stream, peer) = listener.accept()?;
+    log::info!("client connected from {}", peer);
+    let reader = BufReader::new(stream.try_clone()?);
+    Ok(io_transport(reader, stream))
+}
+
+/// Listens on the unix domain socket `path` and talks to the first client
+/// which connects to it.
+#[cfg(unix)]
+pub fn pipe_transport(path: &Path) -> Result<(Receiver<RawMessage>, Sender<RawMessage>, Threads)> {
+    let listener = UnixListener::bind(path)?;
+    log::info!("waiting for a client on {}", path.display());
+    let (stream, _) = listener.accept()?;
+    log::info!("client connected");
+    let reader = BufReader::new(stream.try_clone()?);
+    Ok(io_transport(reader, stream))
+}
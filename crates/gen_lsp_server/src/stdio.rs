@@ -1,5 +1,5 @@
 use std::{
-    io::{stdin, stdout},
+    io::{stdin, stdout, BufRead, BufReader, Write},
     thread,
 };
 
@@ -9,20 +9,25 @@ use failure::bail;
 use crate::{RawMessage, Result};
 
 pub fn stdio_transport() -> (Receiver<RawMessage>, Sender<RawMessage>, Threads) {
+    io_transport(BufReader::new(stdin()), stdout())
+}
+
+/// Creates a `sender` and `receiver` pair which write messages to `writer`
+/// and read them from `reader` in background threads.
+pub fn io_transport(
+    mut reader: impl BufRead + Send + 'static,
+    mut writer: impl Write + Send + 'static,
+) -> (Receiver<RawMessage>, Sender<RawMessage>, Threads) {
     let (writer_sender, writer_receiver) = bounded::<RawMessage>(16);
     let writer = thread::spawn(move || {
-        let stdout = stdout();
-        let mut stdout = stdout.lock();
         writer_receiver
             .into_iter()
-            .try_for_each(|it| it.write(&mut stdout))?;
+            .try_for_each(|it| it.write(&mut writer))?;
         Ok(())
     });
     let (reader_sender, reader_receiver) = bounded::<RawMessage>(16);
     let reader = thread::spawn(move || {
-        let stdin = stdin();
-        let mut stdin = stdin.lock();
-        while let Some(msg) = RawMessage::read(&mut stdin)? {
+        while let Some(msg) = RawMessage::read(&mut reader)? {
             if let Err(_) = reader_sender.send(msg) {
                 break;
             }
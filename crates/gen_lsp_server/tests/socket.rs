@@ -0,0 +1,66 @@
+#![cfg(unix)]
+
+use std::{
+    io::BufReader,
+    net::Shutdown as SocketShutdown,
+    os::unix::net::UnixStream,
+    thread,
+    time::Duration,
+};
+
+use gen_lsp_server::{pipe_transport, RawMessage, RawNotification, RawRequest};
+use languageserver_types::{notification::Exit, request::Shutdown};
+use tempfile::tempdir;
+
+#[test]
+fn test_pipe_transport_round_trip() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("server.sock");
+
+    // A server which sends every message back, until the client goes away.
+    let server = {
+        let path = path.clone();
+        thread::spawn(move || {
+            let (receiver, sender, threads) = pipe_transport(&path).unwrap();
+            for msg in receiver {
+                sender.send(msg).unwrap();
+            }
+            drop(sender);
+            threads.join().unwrap();
+        })
+    };
+
+    // The socket is bound by the server thread, so it may not exist yet.
+    let stream = loop {
+        match UnixStream::connect(&path) {
+            Ok(it) => break it,
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+
+    RawMessage::Request(RawRequest::new::<Shutdown>(92, &()))
+        .write(&mut writer)
+        .unwrap();
+    match RawMessage::read(&mut reader).unwrap() {
+        Some(RawMessage::Request(req)) => {
+            assert_eq!(req.id, 92);
+            assert_eq!(req.method, "shutdown");
+        }
+        msg => panic!("expected the request back, got {:?}", msg),
+    }
+
+    RawMessage::Notification(RawNotification::new::<Exit>(&()))
+        .write(&mut writer)
+        .unwrap();
+    match RawMessage::read(&mut reader).unwrap() {
+        Some(RawMessage::Notification(not)) => assert_eq!(not.method, "exit"),
+        msg => panic!("expected the notification back, got {:?}", msg),
+    }
+
+    // Closing the connection stops the server.
+    writer.shutdown(SocketShutdown::Write).unwrap();
+    assert!(RawMessage::read(&mut reader).unwrap().is_none());
+    server.join().unwrap();
+}
@@ -23,31 +23,37 @@ pub(super) fn complete_dot(acc: &mut Completions, ctx: &CompletionContext) -> Ca
 }
 
 fn complete_fields(acc: &mut Completions, ctx: &CompletionContext, receiver: Ty) -> Cancelable<()> {
-    // TODO: autoderef etc.
-    match receiver {
-        Ty::Adt { def_id, .. } => {
-            match def_id.resolve(ctx.db)? {
-                Def::Struct(s) => {
-                    let variant_data = s.variant_data(ctx.db)?;
-                    for field in variant_data.fields() {
-                        CompletionItem::new(CompletionKind::Reference, field.name().to_string())
+    for receiver in receiver.autoderef(ctx.db) {
+        match receiver {
+            Ty::Adt { def_id, .. } => {
+                match def_id.resolve(ctx.db)? {
+                    Def::Struct(s) => {
+                        let variant_data = s.variant_data(ctx.db)?;
+                        for field in variant_data.fields() {
+                            CompletionItem::new(
+                                CompletionKind::Reference,
+                                field.name().to_string(),
+                            )
                             .kind(CompletionItemKind::Field)
                             .add_to(acc);
+                        }
                     }
+                    // TODO unions
+                    _ => {}
                 }
-                // TODO unions
-                _ => {}
             }
-        }
-        Ty::Tuple(fields) => {
-            for (i, _ty) in fields.iter().enumerate() {
-                CompletionItem::new(CompletionKind::Reference, i.to_string())
-                    .kind(CompletionItemKind::Field)
-                    .add_to(acc);
+            Ty::Tuple(fields) => {
+                for (i, _ty) in fields.iter().enumerate() {
+                    CompletionItem::new(CompletionKind::Reference, i.to_string())
+                        .kind(CompletionItemKind::Field)
+                        .add_to(acc);
+                }
             }
-        }
-        _ => {}
-    };
+            // only the fields of the first type with fields are accessible
+            _ => continue,
+        };
+        break;
+    }
     Ok(())
 }
 
@@ -87,6 +93,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_struct_field_completion_after_index() {
+        check_ref_completion(
+            r"
+            trait Index<Idx> { fn index(&self, index: Idx) -> &Self::Output; }
+            struct A { the_field: u32 }
+            struct Grid;
+            impl Index<usize> for Grid { fn index(&self, i: usize) -> &A {} }
+            fn foo(g: &Grid) {
+               g[0].<|>
+            }
+            ",
+            r#"the_field"#,
+        );
+    }
+
+    #[test]
+    fn test_struct_field_completion_through_type_alias() {
+        check_ref_completion(
+            r"
+            struct A { the_field: u32 }
+            type B = A;
+            fn foo(b: &B) {
+               b.<|>
+            }
+            ",
+            r#"the_field"#,
+        );
+    }
+
+    #[test]
+    fn test_struct_field_completion_in_for_loop() {
+        check_ref_completion(
+            r"
+            struct A { the_field: u32 }
+            fn foo(v: &[A]) {
+               for a in v { a.<|> }
+            }
+            ",
+            r#"the_field"#,
+        );
+    }
+
     #[test]
     fn test_no_struct_field_completion_for_method_call() {
         check_ref_completion(
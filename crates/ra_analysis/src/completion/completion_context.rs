@@ -137,7 +137,10 @@ impl<'a> CompletionContext<'a> {
             None => return,
         };
         if let Some(segment) = ast::PathSegment::cast(parent) {
-            let path = segment.parent_path();
+            let path = match segment.parent_path() {
+                Some(it) => it,
+                None => return,
+            };
             if let Some(mut path) = hir::Path::from_ast(path) {
                 if !path.is_ident() {
                     path.segments.pop().unwrap();
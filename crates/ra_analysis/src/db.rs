@@ -1,5 +1,10 @@
-use std::{fmt, sync::Arc};
-use salsa::{self, Database};
+use std::{
+    fmt, time,
+    sync::{Arc, atomic::{AtomicBool, Ordering}},
+};
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use salsa::{self, Database, ParallelDatabase};
 use ra_db::{LocationIntener, BaseDatabase};
 
 use crate::{
@@ -10,6 +15,13 @@ use crate::{
 pub(crate) struct RootDatabase {
     runtime: salsa::Runtime<RootDatabase>,
     id_maps: Arc<IdMaps>,
+    proc_macro_expander: Arc<dyn hir::ProcMacroExpander>,
+    pub(crate) last_gc: time::Instant,
+    /// Total execution time of queries, shared with snapshots.
+    pub(crate) query_times: Arc<Mutex<FxHashMap<&'static str, time::Duration>>>,
+    /// Cancels this snapshot and the snapshots made from it, see
+    /// `RootDatabase::cancelable_snapshot`.
+    canceled: Arc<AtomicBool>,
 }
 
 #[derive(Default)]
@@ -34,30 +46,83 @@ impl salsa::Database for RootDatabase {
 
 impl Default for RootDatabase {
     fn default() -> RootDatabase {
+        RootDatabase::new(Arc::new(hir::DeriveNamePassthrough))
+    }
+}
+
+impl RootDatabase {
+    pub(crate) fn new(proc_macro_expander: Arc<dyn hir::ProcMacroExpander>) -> RootDatabase {
         let mut db = RootDatabase {
             runtime: salsa::Runtime::default(),
             id_maps: Default::default(),
+            proc_macro_expander,
+            last_gc: time::Instant::now(),
+            query_times: Default::default(),
+            canceled: Default::default(),
         };
         db.query_mut(ra_db::CrateGraphQuery)
             .set((), Default::default());
-        db.query_mut(ra_db::LocalRootsQuery)
-            .set((), Default::default());
-        db.query_mut(ra_db::LibraryRootsQuery)
+        db.query_mut(ra_db::SourceRootsQuery)
             .set((), Default::default());
         db
     }
-}
 
-impl salsa::ParallelDatabase for RootDatabase {
-    fn snapshot(&self) -> salsa::Snapshot<RootDatabase> {
+    /// Like `snapshot`, but the snapshot gets its own flag, so that it can be
+    /// canceled without canceling the other snapshots.
+    pub(crate) fn cancelable_snapshot(&self) -> salsa::Snapshot<RootDatabase> {
         salsa::Snapshot::new(RootDatabase {
+            canceled: Default::default(),
+            ..self.fork()
+        })
+    }
+
+    pub(crate) fn cancelation_flag(&self) -> Arc<AtomicBool> {
+        self.canceled.clone()
+    }
+
+    fn fork(&self) -> RootDatabase {
+        RootDatabase {
             runtime: self.runtime.snapshot(self),
             id_maps: self.id_maps.clone(),
-        })
+            proc_macro_expander: self.proc_macro_expander.clone(),
+            last_gc: self.last_gc,
+            query_times: self.query_times.clone(),
+            canceled: self.canceled.clone(),
+        }
     }
 }
 
-impl BaseDatabase for RootDatabase {}
+impl ParallelDatabase for RootDatabase {
+    fn snapshot(&self) -> salsa::Snapshot<RootDatabase> {
+        salsa::Snapshot::new(self.fork())
+    }
+}
+
+/// A snapshot of the database which can be cloned, to hand out a separate
+/// snapshot to each rayon worker via `map_with`. The write path (applying
+/// changes) stays single-threaded: it cancels all outstanding snapshots.
+pub(crate) struct Snap(pub(crate) salsa::Snapshot<RootDatabase>);
+
+impl Snap {
+    pub(crate) fn new(db: &RootDatabase) -> Snap {
+        Snap(db.snapshot())
+    }
+}
+
+impl Clone for Snap {
+    fn clone(&self) -> Snap {
+        Snap(self.0.snapshot())
+    }
+}
+
+impl BaseDatabase for RootDatabase {
+    fn is_snapshot_canceled(&self) -> bool {
+        self.canceled.load(Ordering::SeqCst)
+    }
+    fn record_query_time(&self, query: &'static str, time: time::Duration) {
+        *self.query_times.lock().entry(query).or_default() += time;
+    }
+}
 
 impl AsRef<LocationIntener<hir::DefLoc, hir::DefId>> for RootDatabase {
     fn as_ref(&self) -> &LocationIntener<hir::DefLoc, hir::DefId> {
@@ -71,6 +136,12 @@ impl AsRef<LocationIntener<hir::MacroCallLoc, hir::MacroCallId>> for RootDatabas
     }
 }
 
+impl AsRef<dyn hir::ProcMacroExpander> for RootDatabase {
+    fn as_ref(&self) -> &(dyn hir::ProcMacroExpander + 'static) {
+        &*self.proc_macro_expander
+    }
+}
+
 salsa::database_storage! {
     pub(crate) struct RootDatabaseStorage for RootDatabase {
         impl ra_db::FilesDatabase {
@@ -78,9 +149,11 @@ salsa::database_storage! {
             fn file_relative_path() for ra_db::FileRelativePathQuery;
             fn file_source_root() for ra_db::FileSourceRootQuery;
             fn source_root() for ra_db::SourceRootQuery;
+            fn source_roots() for ra_db::SourceRootsQuery;
             fn local_roots() for ra_db::LocalRootsQuery;
             fn library_roots() for ra_db::LibraryRootsQuery;
             fn crate_graph() for ra_db::CrateGraphQuery;
+            fn crate_cfg() for ra_db::CrateCfgQuery;
         }
         impl ra_db::SyntaxDatabase {
             fn source_file() for ra_db::SourceFileQuery;
@@ -94,7 +167,7 @@ salsa::database_storage! {
             fn hir_source_file() for hir::db::HirSourceFileQuery;
             fn expand_macro_invocation() for hir::db::ExpandMacroCallQuery;
             fn module_tree() for hir::db::ModuleTreeQuery;
-            fn fn_scopes() for hir::db::FnScopesQuery;
+            fn expr_scopes() for hir::db::ExprScopesQuery;
             fn file_items() for hir::db::SourceFileItemsQuery;
             fn file_item() for hir::db::FileItemQuery;
             fn input_module_items() for hir::db::InputModuleItemsQuery;
@@ -105,9 +178,13 @@ salsa::database_storage! {
             fn type_for_field() for hir::db::TypeForFieldQuery;
             fn struct_data() for hir::db::StructDataQuery;
             fn enum_data() for hir::db::EnumDataQuery;
+            fn trait_data() for hir::db::TraitDataQuery;
             fn impls_in_module() for hir::db::ImplsInModuleQuery;
+            fn impls_in_crate() for hir::db::ImplsInCrateQuery;
             fn body_hir() for hir::db::BodyHirQuery;
             fn body_syntax_mapping() for hir::db::BodySyntaxMappingQuery;
+            fn body_parent_map() for hir::db::BodyParentMapQuery;
+            fn validate_body() for hir::db::ValidateBodyQuery;
             fn fn_signature() for hir::db::FnSignatureQuery;
         }
     }
@@ -1,5 +1,5 @@
-use ra_db::{FileId, Cancelable, SyntaxDatabase};
-use ra_syntax::{TextRange, AstNode, ast, SyntaxKind::{NAME, MODULE}};
+use ra_db::{FileId, Cancelable, SyntaxDatabase, LocalSyntaxPtr};
+use ra_syntax::{TextRange, AstNode, ast::{self, NameOwner}, SyntaxKind::{NAME, MODULE}};
 
 use ra_editor::find_node_at_offset;
 
@@ -40,6 +40,22 @@ pub(crate) fn reference_defenition(
             };
             return Ok(vec![nav]);
         };
+        // Then try to resolve a method call through type inference
+        if let Some(method_call) = name_ref
+            .syntax()
+            .parent()
+            .and_then(ast::MethodCallExpr::cast)
+        {
+            let syntax_mapping = fn_descr.body_syntax_mapping(db)?;
+            if let Some(expr) = syntax_mapping.node_expr(ast::Expr::MethodCallExpr(method_call)) {
+                let infer = fn_descr.infer(db)?;
+                if let Some(def_id) = infer.method_resolution(expr) {
+                    if let hir::Def::Function(f) = def_id.resolve(db)? {
+                        return Ok(vec![NavigationTarget::from_function(db, &f)]);
+                    }
+                }
+            }
+        }
     }
     // If that fails try the index based approach.
     let navs = db
@@ -79,6 +95,24 @@ fn name_defenition(
     Ok(None)
 }
 
+impl NavigationTarget {
+    fn from_function(db: &RootDatabase, function: &hir::Function) -> NavigationTarget {
+        let (file_id, fn_def) = function.source(db);
+        let fn_def = fn_def.borrowed();
+        let name = match fn_def.name() {
+            Some(name) => name.text().clone(),
+            None => "".into(),
+        };
+        NavigationTarget {
+            file_id,
+            name,
+            kind: fn_def.syntax().kind(),
+            range: fn_def.syntax().range(),
+            ptr: Some(LocalSyntaxPtr::new(fn_def.syntax())),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use test_utils::assert_eq_dbg;
@@ -135,4 +169,30 @@ mod tests {
             &symbols,
         );
     }
+
+    #[test]
+    fn goto_defenition_works_for_methods() {
+        let (analysis, pos) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod m {
+                pub trait Tr { fn frobnicate(&self) {} }
+            }
+            use self::m::Tr;
+            struct Foo;
+            impl Tr for Foo {}
+            fn bar(foo: &Foo) {
+                foo.frobnicate<|>();
+            }
+            ",
+        );
+
+        let symbols = analysis.goto_defenition(pos).unwrap().unwrap();
+        assert_eq_dbg(
+            r#"[NavigationTarget { file_id: FileId(1), name: "frobnicate",
+                                   kind: FN_DEF, range: [27; 50),
+                                   ptr: Some(LocalSyntaxPtr { range: [27; 50), kind: FN_DEF }) }]"#,
+            &symbols,
+        );
+    }
 }
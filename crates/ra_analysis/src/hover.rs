@@ -1,7 +1,8 @@
-use ra_db::{Cancelable, SyntaxDatabase};
+use hir::db::HirDatabase;
+use ra_db::{Cancelable, FileId, SyntaxDatabase};
 use ra_editor::find_node_at_offset;
 use ra_syntax::{
-    AstNode, SyntaxNode,
+    AstNode, SyntaxNode, SyntaxNodeRef,
     ast::{self, NameOwner},
     algo::{find_covering_node, find_leaf_at_offset, visit::{visitor, Visitor}},
 };
@@ -25,6 +26,24 @@ pub(crate) fn hover(
             range = Some(name_ref.syntax().range())
         }
     }
+    if range.is_none() {
+        // The name of a definition shows the same info as its references.
+        if let Some(name) = find_node_at_offset::<ast::Name>(file.syntax(), position.offset) {
+            if let Some(parent) = name.syntax().parent() {
+                let nav = NavigationTarget {
+                    file_id: position.file_id,
+                    name: name.text(),
+                    kind: parent.kind(),
+                    range: parent.range(),
+                    ptr: None,
+                };
+                res.extend(doc_text_for(db, nav)?);
+                if !res.is_empty() {
+                    range = Some(name.syntax().range())
+                }
+            }
+        }
+    }
     if range.is_none() {
         let node = find_leaf_at_offset(file.syntax(), position.offset).find_map(|leaf| {
             leaf.ancestors()
@@ -35,7 +54,7 @@ pub(crate) fn hover(
             file_id: position.file_id,
             range: node.range(),
         };
-        res.extend(type_of(db, frange)?);
+        res.extend(type_of(db, frange)?.map(|ty| rust_code_markup(&ty)));
         range = Some(node.range());
     };
 
@@ -47,6 +66,10 @@ pub(crate) fn hover(
     Ok(Some(res))
 }
 
+fn rust_code_markup(code: &str) -> String {
+    format!("```rust\n{}\n```", code)
+}
+
 pub(crate) fn type_of(db: &RootDatabase, frange: FileRange) -> Cancelable<Option<String>> {
     let file = db.source_file(frange.file_id);
     let syntax = file.syntax();
@@ -57,14 +80,9 @@ pub(crate) fn type_of(db: &RootDatabase, frange: FileRange) -> Cancelable<Option
         .take_while(|it| it.range() == leaf_node.range())
         .find(|&it| ast::Expr::cast(it).is_some() || ast::Pat::cast(it).is_some())
         .unwrap_or(leaf_node);
-    let parent_fn = ctry!(node.ancestors().find_map(ast::FnDef::cast));
-    let function = ctry!(hir::source_binder::function_from_source(
-        db,
-        frange.file_id,
-        parent_fn
-    )?);
-    let infer = function.infer(db)?;
-    let syntax_mapping = function.body_syntax_mapping(db)?;
+    let body_owner = ctry!(body_owner_of(db, frange.file_id, node)?);
+    let infer = db.infer(body_owner)?;
+    let syntax_mapping = db.body_syntax_mapping(body_owner)?;
     if let Some(expr) = ast::Expr::cast(node).and_then(|e| syntax_mapping.node_expr(e)) {
         Ok(Some(infer[expr].to_string()))
     } else if let Some(pat) = ast::Pat::cast(node).and_then(|p| syntax_mapping.node_pat(p)) {
@@ -74,12 +92,35 @@ pub(crate) fn type_of(db: &RootDatabase, frange: FileRange) -> Cancelable<Option
     }
 }
 
+/// The innermost function, const or static whose body contains `node`.
+fn body_owner_of(
+    db: &RootDatabase,
+    file_id: FileId,
+    node: SyntaxNodeRef,
+) -> Cancelable<Option<hir::DefId>> {
+    for node in node.ancestors() {
+        if let Some(fn_def) = ast::FnDef::cast(node) {
+            let function = hir::source_binder::function_from_source(db, file_id, fn_def)?;
+            return Ok(function.map(|it| it.def_id()));
+        }
+        if let Some(const_def) = ast::ConstDef::cast(node) {
+            let konst = hir::source_binder::const_from_source(db, file_id, const_def)?;
+            return Ok(konst.map(|it| it.def_id()));
+        }
+        if let Some(static_def) = ast::StaticDef::cast(node) {
+            let statik = hir::source_binder::static_from_source(db, file_id, static_def)?;
+            return Ok(statik.map(|it| it.def_id()));
+        }
+    }
+    Ok(None)
+}
+
 // FIXME: this should not really use navigation target. Rather, approximatelly
 // resovled symbol should return a `DefId`.
 fn doc_text_for(db: &RootDatabase, nav: NavigationTarget) -> Cancelable<Option<String>> {
     let result = match (nav.description(db), nav.docs(db)) {
-        (Some(desc), Some(docs)) => Some("```rust\n".to_string() + &*desc + "\n```\n\n" + &*docs),
-        (Some(desc), None) => Some("```rust\n".to_string() + &*desc + "\n```"),
+        (Some(desc), Some(docs)) => Some(rust_code_markup(&desc) + "\n\n" + &*docs),
+        (Some(desc), None) => Some(rust_code_markup(&desc)),
         (None, Some(docs)) => Some(docs),
         _ => None,
     };
@@ -193,21 +234,34 @@ mod tests {
         );
         let hover = analysis.hover(position).unwrap().unwrap();
         assert_eq!(hover.range, TextRange::from_to(95.into(), 100.into()));
-        assert_eq!(hover.info, "u32");
+        assert_eq!(hover.info, "```rust\nu32\n```");
+    }
+
+    #[test]
+    fn hover_for_definition_name() {
+        let (analysis, position) = single_file_with_position(
+            "
+            /// Does nothing.
+            fn fo<|>o() {}
+            ",
+        );
+        let hover = analysis.hover(position).unwrap().unwrap();
+        assert_eq!(hover.range, TextRange::from_to(46.into(), 49.into()));
+        assert_eq!(hover.info, "```rust\nfn foo\n```\n\nDoes nothing.");
     }
 
     #[test]
     fn hover_for_local_variable() {
         let (analysis, position) = single_file_with_position("fn func(foo: i32) { fo<|>o; }");
         let hover = analysis.hover(position).unwrap().unwrap();
-        assert_eq!(hover.info, "i32");
+        assert_eq!(hover.info, "```rust\ni32\n```");
     }
 
     #[test]
     fn hover_for_local_variable_pat() {
         let (analysis, position) = single_file_with_position("fn func(fo<|>o: i32) {}");
         let hover = analysis.hover(position).unwrap().unwrap();
-        assert_eq!(hover.info, "i32");
+        assert_eq!(hover.info, "```rust\ni32\n```");
     }
 
     #[test]
@@ -226,7 +280,6 @@ mod tests {
         assert_eq!("u32", &type_name);
     }
 
-    // FIXME: improve type_of to make this work
     #[test]
     fn test_type_of_for_expr_1() {
         let (analysis, range) = single_file_with_range(
@@ -238,10 +291,9 @@ mod tests {
         );
 
         let type_name = analysis.type_of(range).unwrap().unwrap();
-        assert_eq!("[unknown]", &type_name);
+        assert_eq!("i32", &type_name);
     }
 
-    // FIXME: improve type_of to make this work
     #[test]
     fn test_type_of_for_expr_2() {
         let (analysis, range) = single_file_with_range(
@@ -254,7 +306,33 @@ mod tests {
         );
 
         let type_name = analysis.type_of(range).unwrap().unwrap();
-        assert_eq!("[unknown]", &type_name);
+        assert_eq!("i32", &type_name);
+    }
+
+    #[test]
+    fn test_type_of_in_const() {
+        let (analysis, range) = single_file_with_range(
+            "
+            fn foo() -> u32 { 1 }
+
+            const FOO: u32 = <|>foo()<|>;
+            ",
+        );
+
+        let type_name = analysis.type_of(range).unwrap().unwrap();
+        assert_eq!("u32", &type_name);
+    }
+
+    #[test]
+    fn test_type_of_in_static() {
+        let (analysis, range) = single_file_with_range(
+            "
+            static FOO: bool = <|>true<|>;
+            ",
+        );
+
+        let type_name = analysis.type_of(range).unwrap().unwrap();
+        assert_eq!("bool", &type_name);
     }
 
 }
@@ -1,14 +1,17 @@
-use std::sync::Arc;
+use std::{sync::Arc, time};
 
-use salsa::Database;
+use salsa::{Database, debug::DebugQueryTable};
+use rayon::prelude::*;
 
 use hir::{
     self, FnSignatureInfo, Problem, source_binder,
+    db::HirDatabase,
 };
-use ra_db::{FilesDatabase, SourceRoot, SourceRootId, SyntaxDatabase};
-use ra_editor::{self, find_node_at_offset, assists, LocalEdit, Severity};
+use ra_db::{BaseDatabase, FilesDatabase, SourceRoot, SourceRootId, SourceRootOrigin, SyntaxDatabase};
+use ra_editor::{self, find_node_at_offset, assists, AssistKind, LocalEdit, Severity};
 use ra_syntax::{
     ast::{self, ArgListOwner, Expr, NameOwner},
+    algo::find_leaf_at_offset,
     AstNode, SourceFileNode,
     SyntaxKind::*,
     SyntaxNodeRef, TextRange, TextUnit,
@@ -18,49 +21,77 @@ use crate::{
     AnalysisChange,
     Cancelable, NavigationTarget,
     CrateId, db, Diagnostic, FileId, FilePosition, FileRange, FileSystemEdit,
-    Query, RootChange, SourceChange, SourceFileEdit,
-    symbol_index::{LibrarySymbolsQuery, FileSymbol},
+    Query, RangeInfo, RootChange, SourceChange, SourceFileEdit,
+    symbol_index::{LibrarySymbolsQuery, FileSymbol, SymbolsDatabase},
 };
 
+/// Minimal interval between two automatic garbage collections.
+const GC_COOLDOWN: time::Duration = time::Duration::from_secs(10);
+
 impl db::RootDatabase {
     pub(crate) fn apply_change(&mut self, change: AnalysisChange) {
         log::info!("apply_change {:?}", change);
-        // self.gc_syntax_trees();
+        self.maybe_collect_garbage();
         if !change.new_roots.is_empty() {
-            let mut local_roots = Vec::clone(&self.local_roots());
-            for (root_id, is_local) in change.new_roots {
+            let mut roots = Vec::clone(&self.source_roots());
+            for (root_id, origin) in change.new_roots {
+                let source_root = SourceRoot {
+                    is_library: origin != SourceRootOrigin::Workspace,
+                    origin,
+                    ..SourceRoot::default()
+                };
                 self.query_mut(ra_db::SourceRootQuery)
-                    .set(root_id, Default::default());
-                if is_local {
-                    local_roots.push(root_id);
-                }
+                    .set(root_id, Arc::new(source_root));
+                roots.push(root_id);
             }
-            self.query_mut(ra_db::LocalRootsQuery)
-                .set((), Arc::new(local_roots));
+            self.query_mut(ra_db::SourceRootsQuery)
+                .set((), Arc::new(roots));
         }
 
         for (root_id, root_change) in change.roots_changed {
+            if self.source_root(root_id).is_library {
+                log::warn!("ignoring change to a library root {:?}", root_id);
+                continue;
+            }
             self.apply_root_change(root_id, root_change);
         }
         for (file_id, text) in change.files_changed {
+            if self.query(ra_db::FileTextQuery).is_constant(file_id) {
+                log::warn!("ignoring change to a library file {:?}", file_id);
+                continue;
+            }
             self.query_mut(ra_db::FileTextQuery).set(file_id, text)
         }
         if !change.libraries_added.is_empty() {
-            let mut libraries = Vec::clone(&self.library_roots());
+            let mut roots = Vec::clone(&self.source_roots());
             for library in change.libraries_added {
-                libraries.push(library.root_id);
+                let origin = if roots.contains(&library.root_id) {
+                    self.source_root(library.root_id).origin
+                } else {
+                    roots.push(library.root_id);
+                    SourceRootOrigin::Dependency
+                };
+                let source_root = SourceRoot {
+                    is_library: true,
+                    origin,
+                    ..SourceRoot::default()
+                };
                 self.query_mut(ra_db::SourceRootQuery)
-                    .set(library.root_id, Default::default());
+                    .set(library.root_id, Arc::new(source_root));
                 self.query_mut(LibrarySymbolsQuery)
                     .set_constant(library.root_id, Arc::new(library.symbol_index));
-                self.apply_root_change(library.root_id, library.root_change);
+                self.apply_library_root_change(library.root_id, library.root_change);
             }
-            self.query_mut(ra_db::LibraryRootsQuery)
-                .set((), Arc::new(libraries));
+            self.query_mut(ra_db::SourceRootsQuery)
+                .set((), Arc::new(roots));
         }
+        // Reloading the project usually produces the same crate graph, and
+        // setting it would invalidate everything which depends on it.
         if let Some(crate_graph) = change.crate_graph {
-            self.query_mut(ra_db::CrateGraphQuery)
-                .set((), Arc::new(crate_graph))
+            if *self.crate_graph() != crate_graph {
+                self.query_mut(ra_db::CrateGraphQuery)
+                    .set((), Arc::new(crate_graph))
+            }
         }
     }
 
@@ -84,20 +115,74 @@ impl db::RootDatabase {
             .set(root_id, Arc::new(source_root));
     }
 
-    #[allow(unused)]
-    /// Ideally, we should call this function from time to time to collect heavy
-    /// syntax trees. However, if we actually do that, everything is recomputed
-    /// for some reason. Needs investigation.
-    fn gc_syntax_trees(&mut self) {
+    /// Libraries never change after they are added, so their inputs are set
+    /// as constants. Salsa never revalidates queries which read only constant
+    /// inputs, so edits in the workspace don't traverse library files.
+    fn apply_library_root_change(&mut self, root_id: SourceRootId, root_change: RootChange) {
+        let mut source_root = SourceRoot::clone(&self.source_root(root_id));
+        for add_file in root_change.added {
+            self.query_mut(ra_db::FileTextQuery)
+                .set_constant(add_file.file_id, add_file.text);
+            self.query_mut(ra_db::FileRelativePathQuery)
+                .set_constant(add_file.file_id, add_file.path.clone());
+            self.query_mut(ra_db::FileSourceRootQuery)
+                .set_constant(add_file.file_id, root_id);
+            source_root.files.insert(add_file.path, add_file.file_id);
+        }
+        self.query_mut(ra_db::SourceRootQuery)
+            .set_constant(root_id, Arc::new(source_root));
+    }
+
+    fn maybe_collect_garbage(&mut self) {
+        if self.last_gc.elapsed() > GC_COOLDOWN {
+            self.collect_garbage();
+        }
+    }
+
+    /// Drops heavy syntax trees which were not used in the current revision,
+    /// so that only the trees of recently used files are kept in memory. The
+    /// rest are re-parsed on demand.
+    ///
+    /// Note that this must be called *before* the revision is bumped by a
+    /// change: everything which is not yet verified in the new revision is
+    /// swept, so collecting garbage right after a change drops all the trees.
+    ///
+    /// Only the trees are swept: the item queries (`SourceFileItemsQuery` and
+    /// `FileItemQuery`) keep their memos and dependencies, so that the hir
+    /// built on top of them is verified again instead of being recomputed.
+    pub(crate) fn collect_garbage(&mut self) {
+        self.last_gc = time::Instant::now();
         self.query(ra_db::SourceFileQuery)
-            .sweep(salsa::SweepStrategy::default().discard_values());
-        self.query(hir::db::SourceFileItemsQuery)
-            .sweep(salsa::SweepStrategy::default().discard_values());
-        self.query(hir::db::FileItemQuery)
-            .sweep(salsa::SweepStrategy::default().discard_values());
+            .sweep(salsa::SweepStrategy::default());
+    }
+
+    pub(crate) fn request_cancellation(&mut self) {
+        self.salsa_runtime().next_revision();
+    }
+
+    /// Drops all memoized values, except for the syntax trees of `keep`
+    /// (usually, the files open in the editor). Everything else is recomputed
+    /// on demand. Returns an estimate of the number of freed bytes.
+    pub(crate) fn trim_memory(&mut self, keep: &[FileId]) -> usize {
+        let before = estimated_bytes(self);
+        // In a new revision, nothing is marked as used until we read it.
+        self.salsa_runtime().next_revision();
+        for &file_id in keep {
+            self.source_file(file_id);
+        }
+        self.sweep_all(salsa::SweepStrategy::default());
+        self.last_gc = time::Instant::now();
+        before.saturating_sub(estimated_bytes(self))
     }
 }
 
+fn estimated_bytes(db: &db::RootDatabase) -> usize {
+    crate::query_stats::query_stats(db)
+        .iter()
+        .filter_map(|it| it.estimated_bytes)
+        .sum()
+}
+
 impl db::RootDatabase {
     /// This returns `Vec` because a module may be included from several places. We
     /// don't handle this case yet though, so the Vec has length at most one.
@@ -145,6 +230,7 @@ impl db::RootDatabase {
             None => return Ok(Vec::new()),
             Some(it) => it,
         };
+        self.check_canceled()?;
 
         let mut ret = binding
             .name()
@@ -218,6 +304,7 @@ impl db::RootDatabase {
                             source_file_edits: Vec::new(),
                             file_system_edits: vec![create_file],
                             cursor_position: None,
+                            is_snippet: false,
                         };
                         Diagnostic {
                             range: name_node.range(),
@@ -241,6 +328,7 @@ impl db::RootDatabase {
                             source_file_edits: Vec::new(),
                             file_system_edits: vec![move_file, create_file],
                             cursor_position: None,
+                            is_snippet: false,
                         };
                         Diagnostic {
                             range: name_node.range(),
@@ -253,14 +341,103 @@ impl db::RootDatabase {
                 res.push(diag)
             }
         };
+        res.extend(self.body_diagnostics(file_id, &syntax)?);
+        Ok(res)
+    }
+
+    /// The problems found by validating the bodies of the functions, consts
+    /// and statics of the file.
+    fn body_diagnostics(
+        &self,
+        file_id: FileId,
+        syntax: &SourceFileNode,
+    ) -> Cancelable<Vec<Diagnostic>> {
+        let mut res = Vec::new();
+        for node in syntax.syntax().descendants() {
+            let def_id = if let Some(fn_def) = ast::FnDef::cast(node) {
+                source_binder::function_from_source(self, file_id, fn_def)?.map(|it| it.def_id())
+            } else if let Some(const_def) = ast::ConstDef::cast(node) {
+                source_binder::const_from_source(self, file_id, const_def)?.map(|it| it.def_id())
+            } else if let Some(static_def) = ast::StaticDef::cast(node) {
+                source_binder::static_from_source(self, file_id, static_def)?.map(|it| it.def_id())
+            } else {
+                None
+            };
+            let def_id = match def_id {
+                Some(it) => it,
+                None => continue,
+            };
+            let syntax_mapping = self.body_syntax_mapping(def_id)?;
+            for (expr, problem) in self.validate_body(def_id)?.iter() {
+                let ptr = match syntax_mapping.expr_syntax(*expr) {
+                    Some(it) => it,
+                    None => continue,
+                };
+                res.push(Diagnostic {
+                    range: ptr.range(),
+                    message: problem.message(),
+                    severity: Severity::Error,
+                    fix: None,
+                });
+            }
+        }
         Ok(res)
     }
 
-    pub(crate) fn assists(&self, frange: FileRange) -> Vec<SourceChange> {
+    /// Computes diagnostics for several files in parallel, each file on its
+    /// own database snapshot.
+    pub(crate) fn diagnostics_for_files(
+        &self,
+        files: &[FileId],
+    ) -> Cancelable<Vec<Vec<Diagnostic>>> {
+        files
+            .par_iter()
+            .map_with(db::Snap::new(self), |snap, &file_id| {
+                snap.0.check_canceled()?;
+                snap.0.diagnostics(file_id)
+            })
+            .collect()
+    }
+
+    /// Computes item maps for all local source roots in parallel, so that
+    /// the first requests after the workspace is loaded are fast.
+    pub(crate) fn prime_caches(&self) -> Cancelable<()> {
+        self.local_roots()
+            .par_iter()
+            .map_with(db::Snap::new(self), |snap, &root| {
+                snap.0.check_canceled()?;
+                snap.0.item_map(root).map(|_| ())
+            })
+            .collect()
+    }
+
+    /// Computes the symbol indices of all files in the source root of
+    /// `file_id` in parallel. Library roots are indexed when they are loaded.
+    pub(crate) fn prime_symbols(&self, file_id: FileId) -> Cancelable<()> {
+        let source_root = self.source_root(self.file_source_root(file_id));
+        if source_root.origin == SourceRootOrigin::Dependency {
+            return Ok(());
+        }
+        let files: Vec<FileId> = source_root.files.values().cloned().collect();
+        files
+            .par_iter()
+            .map_with(db::Snap::new(self), |snap, &file_id| {
+                snap.0.check_canceled()?;
+                snap.0.file_symbols(file_id).map(|_| ())
+            })
+            .collect()
+    }
+
+    pub(crate) fn assists(&self, frange: FileRange) -> Vec<(AssistKind, SourceChange)> {
         let file = self.source_file(frange.file_id);
         assists::assists(&file, frange.range)
             .into_iter()
-            .map(|local_edit| SourceChange::from_local_edit(frange.file_id, local_edit))
+            .map(|(kind, local_edit)| {
+                (
+                    kind,
+                    SourceChange::from_local_edit(frange.file_id, local_edit),
+                )
+            })
             .collect()
     }
 
@@ -352,15 +529,68 @@ impl db::RootDatabase {
             .collect::<Vec<_>>();
         Ok(res)
     }
+    /// Checks that the identifier at `position` can be renamed, and returns
+    /// its range and text.
+    pub(crate) fn prepare_rename(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<RangeInfo<String>>> {
+        let source_root = self.file_source_root(position.file_id);
+        if self.source_root(source_root).is_library {
+            return Ok(None);
+        }
+        let file = self.source_file(position.file_id);
+        // Keywords are never identifiers, so they are rejected here as well.
+        let ident =
+            ctry!(find_leaf_at_offset(file.syntax(), position.offset)
+                .find(|leaf| leaf.kind() == IDENT));
+        if let Some(name_ref) = ident.parent().and_then(ast::NameRef::cast) {
+            let in_type = name_ref
+                .syntax()
+                .ancestors()
+                .any(|it| it.kind() == PATH_TYPE);
+            if in_type && is_primitive_type(&name_ref.text()) {
+                return Ok(None);
+            }
+        }
+        let position = FilePosition {
+            file_id: position.file_id,
+            offset: ident.range().start(),
+        };
+        let is_renamed = self
+            .find_all_refs(position)?
+            .into_iter()
+            .any(|(file_id, range)| file_id == position.file_id && range == ident.range());
+        if !is_renamed {
+            return Ok(None);
+        }
+        let name = ident.leaf_text().unwrap().to_string();
+        Ok(Some(RangeInfo::new(ident.range(), name)))
+    }
     pub(crate) fn index_resolve(&self, name_ref: ast::NameRef) -> Cancelable<Vec<FileSymbol>> {
         let name = name_ref.text();
         let mut query = Query::new(name.to_string());
         query.exact();
         query.limit(4);
+        let res = crate::symbol_index::world_symbols(self, query.clone())?;
+        if !res.is_empty() {
+            return Ok(res);
+        }
+        // The definition may be in a library, for example in a path
+        // dependency outside of the workspace.
+        query.libs();
         crate::symbol_index::world_symbols(self, query)
     }
 }
 
+fn is_primitive_type(name: &str) -> bool {
+    match name {
+        "bool" | "char" | "str" | "f32" | "f64" | "i8" | "i16" | "i32" | "i64" | "i128"
+        | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => true,
+        _ => false,
+    }
+}
+
 impl SourceChange {
     pub(crate) fn from_local_edit(file_id: FileId, edit: LocalEdit) -> SourceChange {
         let file_edit = SourceFileEdit {
@@ -374,6 +604,7 @@ impl SourceChange {
             cursor_position: edit
                 .cursor_position
                 .map(|offset| FilePosition { offset, file_id }),
+            is_snippet: edit.is_snippet,
         }
     }
 }
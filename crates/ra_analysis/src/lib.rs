@@ -17,41 +17,50 @@ mod db;
 mod goto_defenition;
 mod imp;
 pub mod mock_analysis;
+mod query_stats;
 mod runnables;
+mod status;
 mod symbol_index;
 
 mod extend_selection;
 mod hover;
 mod syntax_highlighting;
 
-use std::{fmt, sync::Arc};
+use std::{
+    fmt, fs,
+    hash::Hasher,
+    path::Path,
+    sync::{Arc, atomic::{AtomicBool, Ordering}},
+};
 
 use ra_syntax::{SmolStr, SourceFileNode, SyntaxKind, TextRange, TextUnit};
 use ra_text_edit::TextEdit;
 use rayon::prelude::*;
 use relative_path::RelativePathBuf;
-use rustc_hash::FxHashMap;
-use salsa::ParallelDatabase;
+use rustc_hash::{FxHashMap, FxHasher};
 
 use crate::symbol_index::{FileSymbol, SymbolIndex};
 
 pub use crate::{
     completion::{CompletionItem, CompletionItemKind, InsertText},
+    query_stats::QueryStats,
     runnables::{Runnable, RunnableKind},
 };
-pub use hir::FnSignatureInfo;
-pub use ra_editor::{Fold, FoldKind, HighlightedRange, LineIndex, Severity, StructureNode};
+pub use hir::{FnSignatureInfo, ProcMacroExpander, DeriveNamePassthrough};
+pub use ra_editor::{
+    AssistKind, Fold, FoldKind, HighlightedRange, LineIndex, Severity, StructureNode,
+};
 
 pub use ra_db::{
-    Cancelable, Canceled, CrateGraph, CrateId, FileId, FilePosition, FileRange, FilesDatabase,
-    LocalSyntaxPtr, SourceRootId, SyntaxDatabase,
+    Cancelable, Canceled, CfgOptions, CrateGraph, CrateId, CyclicDependenciesError, Edition, Env, FileId, FilePosition, FileRange, FilesDatabase,
+    LocalSyntaxPtr, Rope, SourceRootId, SourceRootOrigin, SyntaxDatabase,
 };
 
 #[derive(Default)]
 pub struct AnalysisChange {
-    new_roots: Vec<(SourceRootId, bool)>,
+    new_roots: Vec<(SourceRootId, SourceRootOrigin)>,
     roots_changed: FxHashMap<SourceRootId, RootChange>,
-    files_changed: Vec<(FileId, Arc<String>)>,
+    files_changed: Vec<(FileId, Rope)>,
     libraries_added: Vec<LibraryData>,
     crate_graph: Option<CrateGraph>,
 }
@@ -66,7 +75,7 @@ struct RootChange {
 struct AddFile {
     file_id: FileId,
     path: RelativePathBuf,
-    text: Arc<String>,
+    text: Rope,
 }
 
 #[derive(Debug)]
@@ -110,15 +119,15 @@ impl AnalysisChange {
     pub fn new() -> AnalysisChange {
         AnalysisChange::default()
     }
-    pub fn add_root(&mut self, root_id: SourceRootId, is_local: bool) {
-        self.new_roots.push((root_id, is_local));
+    pub fn add_root(&mut self, root_id: SourceRootId, origin: SourceRootOrigin) {
+        self.new_roots.push((root_id, origin));
     }
     pub fn add_file(
         &mut self,
         root_id: SourceRootId,
         file_id: FileId,
         path: RelativePathBuf,
-        text: Arc<String>,
+        text: Rope,
     ) {
         let file = AddFile {
             file_id,
@@ -131,7 +140,7 @@ impl AnalysisChange {
             .added
             .push(file);
     }
-    pub fn change_file(&mut self, file_id: FileId, new_text: Arc<String>) {
+    pub fn change_file(&mut self, file_id: FileId, new_text: Rope) {
         self.files_changed.push((file_id, new_text))
     }
     pub fn remove_file(&mut self, root_id: SourceRootId, file_id: FileId, path: RelativePathBuf) {
@@ -156,6 +165,9 @@ pub struct SourceChange {
     pub source_file_edits: Vec<SourceFileEdit>,
     pub file_system_edits: Vec<FileSystemEdit>,
     pub cursor_position: Option<FilePosition>,
+    /// Whether the texts of `source_file_edits` are snippets, with tab stops
+    /// and placeholders.
+    pub is_snippet: bool,
 }
 
 #[derive(Debug)]
@@ -185,7 +197,7 @@ pub struct Diagnostic {
     pub severity: Severity,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Query {
     query: String,
     lowercased: String,
@@ -279,11 +291,18 @@ pub struct AnalysisHost {
 }
 
 impl AnalysisHost {
+    /// Creates a host which expands procedural macros with `expander`,
+    /// instead of `DeriveNamePassthrough`.
+    pub fn with_proc_macro_expander(expander: Arc<dyn ProcMacroExpander>) -> AnalysisHost {
+        AnalysisHost {
+            db: db::RootDatabase::new(expander),
+        }
+    }
     /// Returns a snapshot of the current state, which you can query for
     /// semantic information.
     pub fn analysis(&self) -> Analysis {
         Analysis {
-            db: self.db.snapshot(),
+            db: self.db.cancelable_snapshot(),
         }
     }
     /// Applies changes to the current state of the world. If there are
@@ -291,6 +310,26 @@ impl AnalysisHost {
     pub fn apply_change(&mut self, change: AnalysisChange) {
         self.db.apply_change(change)
     }
+    /// Cancels all outstanding snapshots, and waits until they are dropped.
+    pub fn request_cancellation(&mut self) {
+        self.db.request_cancellation()
+    }
+    /// Frees memory by dropping syntax trees of files which were not used
+    /// since the last change. This also happens periodically when changes are
+    /// applied.
+    pub fn collect_garbage(&mut self) {
+        self.db.collect_garbage()
+    }
+    /// Frees as much memory as possible, keeping only the syntax trees of
+    /// `open_files`. Returns an estimate of the number of freed bytes.
+    pub fn trim_memory(&mut self, open_files: &[FileId]) -> usize {
+        self.db.trim_memory(open_files)
+    }
+    /// Reports memoized entry counts, memory and time used by the main salsa
+    /// queries.
+    pub fn query_stats(&self) -> Vec<QueryStats> {
+        query_stats::query_stats(&self.db)
+    }
 }
 
 /// Analysis is a snapshot of a world state at a moment in time. It is the main
@@ -302,9 +341,37 @@ pub struct Analysis {
     db: salsa::Snapshot<db::RootDatabase>,
 }
 
+/// Cancels a single `Analysis`, see `Analysis::cancelation_token`.
+#[derive(Debug, Clone)]
+pub struct CancelationToken(Arc<AtomicBool>);
+
+impl CancelationToken {
+    /// Makes the operations of the `Analysis` return `Err(Canceled)`.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+}
+
 impl Analysis {
-    /// Gets the text of the source file.
-    pub fn file_text(&self, file_id: FileId) -> Arc<String> {
+    /// Returns a token which cancels this `Analysis` without affecting the
+    /// other snapshots. Unlike `AnalysisHost::request_cancellation`, it only
+    /// takes effect between queries, so a query which is already running is
+    /// completed first.
+    pub fn cancelation_token(&self) -> CancelationToken {
+        CancelationToken(self.db.cancelation_flag())
+    }
+    /// Same as `AnalysisHost::query_stats`, but for a snapshot.
+    pub fn query_stats(&self) -> Vec<QueryStats> {
+        query_stats::query_stats(&self.db)
+    }
+    /// Debug info about the current state of the analysis: the crate graph,
+    /// the source roots and the memoized queries.
+    pub fn status(&self) -> String {
+        status::status(&self.db)
+    }
+    /// Gets the text of the source file. Cloning a rope is cheap, so this
+    /// does not copy the text.
+    pub fn file_text(&self, file_id: FileId) -> Rope {
         self.db.file_text(file_id)
     }
     /// Gets the syntax tree of the file.
@@ -345,7 +412,8 @@ impl Analysis {
         Some(SourceChange::from_local_edit(position.file_id, edit))
     }
     /// Returns an edit which should be applied after `=` was typed. Primarily,
-    /// this works when adding `let =`.
+    /// this works when adding `let =`, and indents an `=` which continues a
+    /// `let` on the next line.
     // FIXME: use a snippet completion instead of this hack here.
     pub fn on_eq_typed(&self, position: FilePosition) -> Option<SourceChange> {
         let file = self.db.source_file(position.file_id);
@@ -358,6 +426,20 @@ impl Analysis {
         let edit = ra_editor::on_dot_typed(&file, position.offset)?;
         Some(SourceChange::from_local_edit(position.file_id, edit))
     }
+    /// Returns an edit which should be applied when a closing `}` or `>` is
+    /// typed on a blank line, dedenting it to the line of the opening one.
+    pub fn on_closing_typed(&self, position: FilePosition) -> Option<SourceChange> {
+        let file = self.db.source_file(position.file_id);
+        let edit = ra_editor::on_closing_typed(&file, position.offset)?;
+        Some(SourceChange::from_local_edit(position.file_id, edit))
+    }
+    /// Returns an edit which sorts, merges and groups the `use` items of the
+    /// file, if they are not organized yet.
+    pub fn organize_imports(&self, file_id: FileId) -> Option<SourceChange> {
+        let file = self.db.source_file(file_id);
+        let edit = ra_editor::organize_imports(&file)?;
+        Some(SourceChange::from_local_edit(file_id, edit))
+    }
     /// Returns a tree representation of symbols in the file. Useful to draw a
     /// file outline.
     pub fn file_structure(&self, file_id: FileId) -> Vec<StructureNode> {
@@ -403,6 +485,10 @@ impl Analysis {
     pub fn crate_root(&self, crate_id: CrateId) -> Cancelable<FileId> {
         Ok(self.db.crate_graph().crate_root(crate_id))
     }
+    /// Returns the edition of the given crate.
+    pub fn crate_edition(&self, crate_id: CrateId) -> Cancelable<Edition> {
+        Ok(self.db.crate_graph().edition(crate_id))
+    }
     /// Returns the set of possible targets to run for the current file.
     pub fn runnables(&self, file_id: FileId) -> Cancelable<Vec<Runnable>> {
         runnables::runnables(&*self.db, file_id)
@@ -418,13 +504,28 @@ impl Analysis {
     }
     /// Computes assists (aks code actons aka intentions) for the given
     /// position.
-    pub fn assists(&self, frange: FileRange) -> Cancelable<Vec<SourceChange>> {
+    pub fn assists(&self, frange: FileRange) -> Cancelable<Vec<(AssistKind, SourceChange)>> {
         Ok(self.db.assists(frange))
     }
     /// Computes the set of diagnostics for the given file.
     pub fn diagnostics(&self, file_id: FileId) -> Cancelable<Vec<Diagnostic>> {
         self.db.diagnostics(file_id)
     }
+    /// Computes diagnostics for each of the given files, in parallel. The
+    /// result is in the same order as `files`.
+    pub fn diagnostics_for_files(&self, files: &[FileId]) -> Cancelable<Vec<Vec<Diagnostic>>> {
+        self.db.diagnostics_for_files(files)
+    }
+    /// Warms up the caches by computing item maps of all local crates in
+    /// parallel.
+    pub fn prime_caches(&self) -> Cancelable<()> {
+        self.db.prime_caches()
+    }
+    /// Indexes the symbols of the source root of `file_id`, so that the next
+    /// symbol search does not have to.
+    pub fn prime_symbols(&self, file_id: FileId) -> Cancelable<()> {
+        self.db.prime_symbols(file_id)
+    }
     /// Computes parameter information for the given call expression.
     pub fn resolve_callable(
         &self,
@@ -436,6 +537,12 @@ impl Analysis {
     pub fn type_of(&self, frange: FileRange) -> Cancelable<Option<String>> {
         hover::type_of(&*self.db, frange)
     }
+    /// Returns the range and the text of the identifier at the position, if it
+    /// can be renamed. Keywords, primitive types and the items of libraries
+    /// can't be renamed.
+    pub fn prepare_rename(&self, position: FilePosition) -> Cancelable<Option<RangeInfo<String>>> {
+        self.db.prepare_rename(position)
+    }
     /// Returns the edit required to rename reference at the position to the new
     /// name.
     pub fn rename(
@@ -466,12 +573,67 @@ impl fmt::Debug for LibraryData {
 impl LibraryData {
     pub fn prepare(
         root_id: SourceRootId,
-        files: Vec<(FileId, RelativePathBuf, Arc<String>)>,
+        files: Vec<(FileId, RelativePathBuf, Rope)>,
     ) -> LibraryData {
         let symbol_index = SymbolIndex::for_files(files.par_iter().map(|(file_id, _, text)| {
-            let file = SourceFileNode::parse(text);
+            let file = SourceFileNode::parse(&text.to_string());
             (*file_id, file)
         }));
+        LibraryData::new(root_id, files, symbol_index)
+    }
+
+    /// Like `prepare`, but reuses the symbol index stored in `cache_dir` by a
+    /// previous run if the library's files have not changed since, and stores
+    /// a freshly built index there otherwise.
+    pub fn prepare_cached(
+        root_id: SourceRootId,
+        files: Vec<(FileId, RelativePathBuf, Rope)>,
+        cache_dir: &Path,
+    ) -> LibraryData {
+        let cache_file = cache_dir.join(format!("{:016x}.symbols", content_hash(&files)));
+        let file_ids = files
+            .iter()
+            .map(|(file_id, path, _)| (path.clone(), *file_id))
+            .collect::<FxHashMap<_, _>>();
+        let cached = fs::read_to_string(&cache_file)
+            .ok()
+            .and_then(|data| SymbolIndex::deserialize(&data, &file_ids));
+        if let Some(symbol_index) = cached {
+            log::info!(
+                "loaded symbols of {:?} from {}",
+                root_id,
+                cache_file.display()
+            );
+            return LibraryData::new(root_id, files, symbol_index);
+        }
+
+        let res = LibraryData::prepare(root_id, files);
+        let file_paths = file_ids
+            .into_iter()
+            .map(|(path, file_id)| (file_id, path))
+            .collect();
+        let data = res.symbol_index.serialize(&file_paths);
+        // Write to a temporary file first, so that concurrently running
+        // servers never observe a partially written cache.
+        let tmp_file = cache_file.with_extension(format!("tmp{}", std::process::id()));
+        let write_res = fs::create_dir_all(cache_dir)
+            .and_then(|()| fs::write(&tmp_file, data))
+            .and_then(|()| fs::rename(&tmp_file, &cache_file));
+        if let Err(e) = write_res {
+            log::warn!(
+                "failed to write symbol cache {}: {}",
+                cache_file.display(),
+                e
+            );
+        }
+        res
+    }
+
+    fn new(
+        root_id: SourceRootId,
+        files: Vec<(FileId, RelativePathBuf, Rope)>,
+        symbol_index: SymbolIndex,
+    ) -> LibraryData {
         let mut root_change = RootChange::default();
         root_change.added = files
             .into_iter()
@@ -489,8 +651,71 @@ impl LibraryData {
     }
 }
 
+/// Hashes paths and contents of the library files, so that the symbol cache is
+/// invalidated if any of them changes.
+fn content_hash(files: &[(FileId, RelativePathBuf, Rope)]) -> u64 {
+    let mut files = files.iter().collect::<Vec<_>>();
+    files.sort_by(|(_, p1, _), (_, p2, _)| p1.cmp(p2));
+    let mut hasher = FxHasher::default();
+    for (_, path, text) in files {
+        hasher.write(path.as_str().as_bytes());
+        hasher.write_u8(0);
+        for chunk in text.chunks() {
+            hasher.write(chunk.as_bytes());
+        }
+        hasher.write_u8(0);
+    }
+    hasher.finish()
+}
+
 #[test]
 fn analysis_is_send() {
     fn is_send<T: Send>() {}
     is_send::<Analysis>();
 }
+
+#[test]
+fn garbage_collection_does_not_invalidate_item_map() {
+    use hir::db::HirDatabase;
+
+    let mock = mock_analysis::MockAnalysis::with_files(
+        "
+        //- /lib.rs
+        mod foo;
+        fn main() { 1 + 1; }
+        //- /foo.rs
+        struct Foo;
+    ",
+    );
+    let lib_file = mock.id_of("/lib.rs");
+    let mut host = mock.analysis_host();
+    let source_root = SourceRootId(0);
+    // A query which is re-executed spends more time.
+    let item_map_time = |host: &AnalysisHost| host.db.query_times.lock().get("item_map").cloned();
+    host.db.item_map(source_root).unwrap();
+    let before = item_map_time(&host);
+    assert!(before.is_some());
+
+    // Right after a change, nothing is verified in the new revision yet, so
+    // the garbage collection sweeps all the syntax trees.
+    let mut change = AnalysisChange::new();
+    change.change_file(lib_file, Rope::from("mod foo;\nfn main() { 2 + 2; }"));
+    host.apply_change(change);
+    host.db.collect_garbage();
+
+    host.db.item_map(source_root).unwrap();
+    assert_eq!(item_map_time(&host), before);
+}
+
+#[test]
+fn dependency_roots_are_libraries() {
+    use ra_db::FilesDatabase;
+
+    let mut host = AnalysisHost::default();
+    let mut change = AnalysisChange::new();
+    change.add_root(SourceRootId(0), SourceRootOrigin::Workspace);
+    change.add_root(SourceRootId(1), SourceRootOrigin::Dependency);
+    host.apply_change(change);
+    assert!(!host.db.source_root(SourceRootId(0)).is_library);
+    assert!(host.db.source_root(SourceRootId(1)).is_library);
+}
@@ -1,10 +1,8 @@
-use std::sync::Arc;
-
 use relative_path::RelativePathBuf;
 use test_utils::{extract_offset, extract_range, parse_fixture, CURSOR_MARKER};
 use ra_db::mock::FileMap;
 
-use crate::{Analysis, AnalysisChange, AnalysisHost, CrateGraph, FileId, FilePosition, FileRange, SourceRootId};
+use crate::{Analysis, AnalysisChange, AnalysisHost, CfgOptions, CrateGraph, Edition, Env, FileId, FilePosition, FileRange, Rope, SourceRootId, SourceRootOrigin};
 
 /// Mock analysis is used in test to bootstrap an AnalysisHost/Analysis
 /// from a set of in-memory files.
@@ -86,16 +84,22 @@ impl MockAnalysis {
         let mut file_map = FileMap::default();
         let source_root = SourceRootId(0);
         let mut change = AnalysisChange::new();
-        change.add_root(source_root, true);
+        change.add_root(source_root, SourceRootOrigin::Workspace);
         let mut crate_graph = CrateGraph::default();
         for (path, contents) in self.files.into_iter() {
             assert!(path.starts_with('/'));
             let path = RelativePathBuf::from_path(&path[1..]).unwrap();
             let file_id = file_map.add(path.clone());
             if path == "/lib.rs" || path == "/main.rs" {
-                crate_graph.add_crate_root(file_id);
+                crate_graph.add_crate_root(
+                    file_id,
+                    Edition::Edition2018,
+                    None,
+                    CfgOptions::default(),
+                    Env::default(),
+                );
             }
-            change.add_file(source_root, file_id, path, Arc::new(contents));
+            change.add_file(source_root, file_id, path, Rope::from(contents));
         }
         change.set_crate_graph(crate_graph);
         // change.set_file_resolver(Arc::new(file_map));
@@ -0,0 +1,91 @@
+//! Introspection of the memoized state of the database: how many entries each
+//! query has, how much memory they take and how much time was spent computing
+//! them. This is useful to diagnose performance problems.
+use std::{fmt, time::Duration};
+
+use ra_db::FilesDatabase;
+use salsa::{debug::DebugQueryTable, Database};
+
+use crate::db::RootDatabase;
+
+/// Statistics about a single salsa query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryStats {
+    pub name: &'static str,
+    /// The number of memoized entries.
+    pub entries: usize,
+    /// A rough estimate of the memory used by the memoized values, if we know
+    /// how to compute one. Syntax trees are estimated by the size of their
+    /// text.
+    pub estimated_bytes: Option<usize>,
+    /// Total time spent executing the query, including the time of the queries
+    /// it called. Only some queries are timed, the rest report zero.
+    pub total_time: Duration,
+}
+
+impl fmt::Display for QueryStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<20} {:>6} entries", self.name, self.entries)?;
+        if let Some(bytes) = self.estimated_bytes {
+            write!(f, ", ~{}kb", bytes / 1024)?;
+        }
+        write!(f, ", {:?}", self.total_time)
+    }
+}
+
+pub(crate) fn query_stats(db: &RootDatabase) -> Vec<QueryStats> {
+    let query_times = db.query_times.lock().clone();
+    let stats = |name: &'static str, entries: usize, estimated_bytes: Option<usize>| QueryStats {
+        name,
+        entries,
+        estimated_bytes,
+        total_time: query_times.get(name).cloned().unwrap_or_default(),
+    };
+
+    let files: Vec<_> = db.query(ra_db::SourceFileQuery).keys();
+    let syntax_bytes = files.iter().map(|&it| db.file_text(it).len_bytes()).sum();
+    vec![
+        stats("source_file", files.len(), Some(syntax_bytes)),
+        stats(
+            "file_symbols",
+            entries(db.query(crate::symbol_index::FileSymbolsQuery)),
+            None,
+        ),
+        stats(
+            "file_items",
+            entries(db.query(hir::db::SourceFileItemsQuery)),
+            None,
+        ),
+        stats(
+            "module_tree",
+            entries(db.query(hir::db::ModuleTreeQuery)),
+            None,
+        ),
+        stats(
+            "input_module_items",
+            entries(db.query(hir::db::InputModuleItemsQuery)),
+            None,
+        ),
+        stats("item_map", entries(db.query(hir::db::ItemMapQuery)), None),
+        stats(
+            "body_syntax_mapping",
+            entries(db.query(hir::db::BodySyntaxMappingQuery)),
+            None,
+        ),
+        stats(
+            "body_parent_map",
+            entries(db.query(hir::db::BodyParentMapQuery)),
+            None,
+        ),
+        stats(
+            "expr_scopes",
+            entries(db.query(hir::db::ExprScopesQuery)),
+            None,
+        ),
+        stats("infer", entries(db.query(hir::db::InferQuery)), None),
+    ]
+}
+
+fn entries<T: DebugQueryTable>(table: T) -> usize {
+    table.keys::<Vec<_>>().len()
+}
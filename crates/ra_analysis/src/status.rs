@@ -0,0 +1,24 @@
+//! A human-readable summary of the state of the database, meant to be
+//! attached to performance bug reports.
+use std::fmt::Write;
+
+use ra_db::FilesDatabase;
+
+use crate::{db::RootDatabase, query_stats::query_stats};
+
+pub(crate) fn status(db: &RootDatabase) -> String {
+    let mut buf = String::new();
+    writeln!(buf, "crates: {}", db.crate_graph().len()).unwrap();
+    for (name, roots) in &[
+        ("workspace roots", db.local_roots()),
+        ("library roots", db.library_roots()),
+    ] {
+        let files: usize = roots.iter().map(|&it| db.source_root(it).files.len()).sum();
+        writeln!(buf, "{}: {} ({} files)", name, roots.len(), files).unwrap();
+    }
+    writeln!(buf, "queries:").unwrap();
+    for stats in query_stats(db) {
+        writeln!(buf, "    {}", stats).unwrap();
+    }
+    buf
+}
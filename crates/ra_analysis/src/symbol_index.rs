@@ -27,18 +27,19 @@ use std::{
 
 use fst::{self, Streamer};
 use ra_syntax::{
-    SyntaxNodeRef, SourceFileNode, SmolStr,
+    SyntaxNodeRef, SourceFileNode, SmolStr, TextRange, TextUnit,
     algo::{visit::{visitor, Visitor}, find_covering_node},
     SyntaxKind::{self, *},
     ast::{self, NameOwner},
 };
-use ra_db::{SourceRootId, FilesDatabase, LocalSyntaxPtr};
-use salsa::ParallelDatabase;
+use ra_db::{BaseDatabase, SourceRootId, FilesDatabase, LocalSyntaxPtr, QueryTimer};
 use rayon::prelude::*;
+use relative_path::{RelativePath, RelativePathBuf};
+use rustc_hash::FxHashMap;
 
 use crate::{
     Cancelable, FileId, Query,
-    db::RootDatabase,
+    db::{RootDatabase, Snap},
 };
 
 salsa::query_group! {
@@ -54,6 +55,7 @@ salsa::query_group! {
 }
 
 fn file_symbols(db: &impl SymbolsDatabase, file_id: FileId) -> Cancelable<Arc<SymbolIndex>> {
+    let _timer = QueryTimer::new(db, "file_symbols");
     db.check_canceled()?;
     let source_file = db.source_file(file_id);
     let mut symbols = source_file
@@ -73,16 +75,8 @@ fn file_symbols(db: &impl SymbolsDatabase, file_id: FileId) -> Cancelable<Arc<Sy
 }
 
 pub(crate) fn world_symbols(db: &RootDatabase, query: Query) -> Cancelable<Vec<FileSymbol>> {
-    /// Need to wrap Snapshot to provide `Clone` impl for `map_with`
-    struct Snap(salsa::Snapshot<RootDatabase>);
-    impl Clone for Snap {
-        fn clone(&self) -> Snap {
-            Snap(self.0.snapshot())
-        }
-    }
-
     let buf: Vec<Arc<SymbolIndex>> = if query.libs {
-        let snap = Snap(db.snapshot());
+        let snap = Snap::new(db);
         db.library_roots()
             .par_iter()
             .map_with(snap, |db, &lib_id| db.0.library_symbols(lib_id))
@@ -94,12 +88,19 @@ pub(crate) fn world_symbols(db: &RootDatabase, query: Query) -> Cancelable<Vec<F
             files.extend(sr.files.values().map(|&it| it))
         }
 
-        let snap = Snap(db.snapshot());
-        files
+        let snap = Snap::new(db);
+        let buf = files
             .par_iter()
-            .map_with(snap, |db, &file_id| db.0.file_symbols(file_id))
+            .map_with(snap, |db, &file_id| {
+                db.0.check_canceled()?;
+                db.0.file_symbols(file_id)
+            })
             .filter_map(|it| it.ok())
-            .collect()
+            .collect();
+        // Files are skipped once the snapshot is canceled, don't return the
+        // partial result.
+        db.check_canceled()?;
+        buf
     };
     Ok(query.search(&buf))
 }
@@ -140,6 +141,57 @@ impl SymbolIndex {
         self.symbols.len()
     }
 
+    /// Serializes the index into a compact text format, one symbol per line.
+    /// Files are identified by their paths, as `FileId`s are not stable
+    /// across runs.
+    pub(crate) fn serialize(&self, file_paths: &FxHashMap<FileId, RelativePathBuf>) -> String {
+        let mut res = String::from(CACHE_HEADER);
+        res.push('\n');
+        for symbol in self.symbols.iter() {
+            let kind = SYMBOL_KINDS
+                .iter()
+                .position(|&it| it == symbol.ptr.kind())
+                .unwrap();
+            let range = symbol.ptr.range();
+            res += &format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                file_paths[&symbol.file_id].as_str(),
+                symbol.name,
+                range.start().to_usize(),
+                range.end().to_usize(),
+                kind,
+            );
+        }
+        res
+    }
+
+    /// The inverse of `serialize`. Returns `None` if the data is malformed or
+    /// refers to unknown files.
+    pub(crate) fn deserialize(
+        data: &str,
+        file_ids: &FxHashMap<RelativePathBuf, FileId>,
+    ) -> Option<SymbolIndex> {
+        let mut lines = data.lines();
+        if lines.next()? != CACHE_HEADER {
+            return None;
+        }
+        let mut symbols = Vec::new();
+        for line in lines {
+            let mut fields = line.split('\t');
+            let file_id = *file_ids.get(RelativePath::new(fields.next()?))?;
+            let name = SmolStr::new(fields.next()?);
+            let start = TextUnit::from_usize(fields.next()?.parse().ok()?);
+            let end = TextUnit::from_usize(fields.next()?.parse().ok()?);
+            let kind = *SYMBOL_KINDS.get(fields.next()?.parse::<usize>().ok()?)?;
+            if start > end || fields.next().is_some() {
+                return None;
+            }
+            let ptr = LocalSyntaxPtr::from_parts(TextRange::from_to(start, end), kind);
+            symbols.push(FileSymbol { file_id, name, ptr });
+        }
+        Some(SymbolIndex::new(symbols))
+    }
+
     pub(crate) fn for_files(
         files: impl ParallelIterator<Item = (FileId, SourceFileNode)>,
     ) -> SymbolIndex {
@@ -189,7 +241,7 @@ impl Query {
 
 fn is_type(kind: SyntaxKind) -> bool {
     match kind {
-        STRUCT_DEF | ENUM_DEF | TRAIT_DEF | TYPE_DEF => true,
+        STRUCT_DEF | ENUM_DEF | TRAIT_DEF | TRAIT_ALIAS_DEF | TYPE_DEF => true,
         _ => false,
     }
 }
@@ -203,6 +255,21 @@ pub(crate) struct FileSymbol {
     pub(crate) ptr: LocalSyntaxPtr,
 }
 
+/// Kinds of nodes which `to_symbol` indexes.
+const SYMBOL_KINDS: &[SyntaxKind] = &[
+    FN_DEF,
+    STRUCT_DEF,
+    ENUM_DEF,
+    TRAIT_DEF,
+    TRAIT_ALIAS_DEF,
+    MODULE,
+    TYPE_DEF,
+    CONST_DEF,
+    STATIC_DEF,
+];
+
+const CACHE_HEADER: &str = "ra_symbols v1";
+
 fn to_symbol(node: SyntaxNodeRef) -> Option<(SmolStr, LocalSyntaxPtr)> {
     fn decl<'a, N: NameOwner<'a>>(node: N) -> Option<(SmolStr, LocalSyntaxPtr)> {
         let name = node.name()?.text();
@@ -214,6 +281,7 @@ fn to_symbol(node: SyntaxNodeRef) -> Option<(SmolStr, LocalSyntaxPtr)> {
         .visit(decl::<ast::StructDef>)
         .visit(decl::<ast::EnumDef>)
         .visit(decl::<ast::TraitDef>)
+        .visit(decl::<ast::TraitAliasDef>)
         .visit(decl::<ast::Module>)
         .visit(decl::<ast::TypeDef>)
         .visit(decl::<ast::ConstDef>)
@@ -1,11 +1,14 @@
 mod runnables;
 
+use std::fs;
+
 use ra_syntax::TextRange;
 use test_utils::{assert_eq_dbg, assert_eq_text};
 
 use ra_analysis::{
     mock_analysis::{analysis_and_position, single_file, single_file_with_position, MockAnalysis},
-    AnalysisChange, CrateGraph, FileId, FnSignatureInfo, Query
+    AnalysisChange, AnalysisHost, CfgOptions, CrateGraph, Edition, Env, FileId, FilePosition, FnSignatureInfo, LibraryData,
+    Query, Rope, SourceRootId, SourceRootOrigin
 };
 
 fn get_signature(text: &str) -> (FnSignatureInfo, Option<usize>) {
@@ -25,7 +28,8 @@ fn test_unresolved_module_diagnostic() {
                 label: "create module",
                 source_file_edits: [],
                 file_system_edits: [CreateFile { source_root: SourceRootId(0), path: "foo.rs" }],
-                cursor_position: None }),
+                cursor_position: None,
+                is_snippet: false }),
                 severity: Error }]"#,
         &diagnostics,
     );
@@ -39,6 +43,28 @@ fn test_unresolved_module_diagnostic_no_diag_for_inline_mode() {
     assert_eq_dbg(r#"[]"#, &diagnostics);
 }
 
+#[test]
+fn test_body_validation_diagnostics() {
+    let (analysis, file_id) = single_file(
+        "
+        struct S { a: u32 }
+        trait T { fn t(); }
+        static X: S = S { b: 1 };
+        fn f() {
+            fn g() { break; }
+            continue;
+        }
+        ",
+    );
+    let diagnostics = analysis.diagnostics(file_id).unwrap();
+    assert_eq_dbg(
+        r#"[Diagnostic { message: "no such field: `b`", range: [86; 87), fix: None, severity: Error },
+            Diagnostic { message: "`continue` outside of a loop", range: [150; 158), fix: None, severity: Error },
+            Diagnostic { message: "`break` outside of a loop", range: [129; 134), fix: None, severity: Error }]"#,
+        &diagnostics,
+    );
+}
+
 #[test]
 fn test_resolve_parent_module() {
     let (analysis, pos) = analysis_and_position(
@@ -91,7 +117,13 @@ fn test_resolve_crate_root() {
     assert!(host.analysis().crate_for(mod_file).unwrap().is_empty());
 
     let mut crate_graph = CrateGraph::default();
-    let crate_id = crate_graph.add_crate_root(root_file);
+    let crate_id = crate_graph.add_crate_root(
+        root_file,
+        Edition::Edition2018,
+        None,
+        CfgOptions::default(),
+        Env::default(),
+    );
     let mut change = AnalysisChange::new();
     change.set_crate_graph(crate_graph);
     host.apply_change(change);
@@ -99,6 +131,250 @@ fn test_resolve_crate_root() {
     assert_eq!(host.analysis().crate_for(mod_file).unwrap(), vec![crate_id]);
 }
 
+#[test]
+fn analysis_works_after_garbage_collection() {
+    let mock = MockAnalysis::with_files(
+        "
+        //- /lib.rs
+        mod foo;
+        //- /foo.rs
+        struct Foo;
+    ",
+    );
+    let lib_file = mock.id_of("/lib.rs");
+    let foo_file = mock.id_of("/foo.rs");
+    let mut host = mock.analysis_host();
+    let before = format!("{:?}", host.analysis().file_structure(foo_file));
+    assert!(host.analysis().diagnostics(lib_file).unwrap().is_empty());
+
+    host.collect_garbage();
+
+    assert_eq!(
+        format!("{:?}", host.analysis().file_structure(foo_file)),
+        before
+    );
+    assert!(host.analysis().diagnostics(lib_file).unwrap().is_empty());
+}
+
+#[test]
+fn diagnostics_for_files_match_sequential_diagnostics() {
+    let mock = MockAnalysis::with_files(
+        "
+        //- /lib.rs
+        mod foo;
+        mod bar;
+        //- /foo.rs
+        mod baz;
+    ",
+    );
+    let lib_file = mock.id_of("/lib.rs");
+    let foo_file = mock.id_of("/foo.rs");
+    let analysis = mock.analysis();
+    analysis.prime_caches().unwrap();
+
+    let files = [foo_file, lib_file];
+    let parallel = analysis.diagnostics_for_files(&files).unwrap();
+    assert_eq!(parallel.len(), 2);
+    for (&file_id, diagnostics) in files.iter().zip(parallel) {
+        assert_eq!(
+            format!("{:?}", diagnostics),
+            format!("{:?}", analysis.diagnostics(file_id).unwrap())
+        );
+    }
+}
+
+#[test]
+fn cancelation_token_cancels_only_its_analysis() {
+    let mock = MockAnalysis::with_files(
+        "
+        //- /lib.rs
+        mod foo;
+        fn bar() {}
+        //- /foo.rs
+        struct Foo;
+    ",
+    );
+    let lib_file = mock.id_of("/lib.rs");
+    let host = mock.analysis_host();
+    let canceled = host.analysis();
+    let analysis = host.analysis();
+    canceled.cancelation_token().cancel();
+
+    assert!(canceled.symbol_search(Query::new("Foo".into())).is_err());
+    assert!(canceled.diagnostics_for_files(&[lib_file]).is_err());
+    assert!(!analysis
+        .symbol_search(Query::new("Foo".into()))
+        .unwrap()
+        .is_empty());
+    assert!(analysis.diagnostics_for_files(&[lib_file]).is_ok());
+}
+
+#[test]
+fn query_stats_count_memoized_entries() {
+    let (analysis, file_id) = single_file("mod foo;");
+    let stats = analysis.query_stats();
+    let source_file = stats.iter().find(|it| it.name == "source_file").unwrap();
+    assert_eq!(source_file.entries, 0);
+
+    analysis.diagnostics(file_id).unwrap();
+    let stats = analysis.query_stats();
+    let source_file = stats.iter().find(|it| it.name == "source_file").unwrap();
+    assert_eq!(source_file.entries, 1);
+    assert_eq!(source_file.estimated_bytes, Some("mod foo;".len()));
+    let module_tree = stats.iter().find(|it| it.name == "module_tree").unwrap();
+    assert_eq!(module_tree.entries, 1);
+}
+
+#[test]
+fn trim_memory_keeps_only_open_files() {
+    let mock = MockAnalysis::with_files(
+        "
+        //- /lib.rs
+        mod foo;
+        //- /foo.rs
+        struct Foo;
+        ",
+    );
+    let lib = mock.id_of("/lib.rs");
+    let foo = mock.id_of("/foo.rs");
+    let mut host = mock.analysis_host();
+    host.analysis().diagnostics(lib).unwrap();
+    host.analysis().diagnostics(foo).unwrap();
+    let foo_len = host.analysis().file_text(foo).len_bytes();
+
+    let reclaimed = host.trim_memory(&[lib]);
+    assert_eq!(reclaimed, foo_len);
+    let stats = host.query_stats();
+    let source_file = stats.iter().find(|it| it.name == "source_file").unwrap();
+    assert_eq!(source_file.entries, 1);
+    assert!(host.analysis().diagnostics(foo).unwrap().is_empty());
+}
+
+#[test]
+fn status_reports_roots_and_queries() {
+    let analysis = MockAnalysis::with_files(
+        "
+        //- /lib.rs
+        mod foo;
+        //- /foo.rs
+        struct Foo;
+        ",
+    )
+    .analysis();
+    let status = analysis.status();
+    assert!(
+        status.contains("workspace roots: 1 (2 files)"),
+        "{}",
+        status
+    );
+    assert!(status.contains("library roots: 0 (0 files)"), "{}", status);
+    assert!(status.contains("module_tree"), "{}", status);
+}
+
+#[test]
+fn library_files_are_never_changed() {
+    let mut host = AnalysisHost::default();
+    let root = SourceRootId(0);
+    let file_id = FileId(1);
+    let mut change = AnalysisChange::new();
+    change.add_root(root, SourceRootOrigin::Dependency);
+    change.add_library(LibraryData::prepare(
+        root,
+        vec![(file_id, "lib.rs".into(), Rope::from("struct Foo;"))],
+    ));
+    host.apply_change(change);
+
+    let mut change = AnalysisChange::new();
+    change.change_file(file_id, Rope::from("struct Bar;"));
+    host.apply_change(change);
+    assert_eq!(host.analysis().file_text(file_id), "struct Foo;");
+}
+
+#[test]
+fn setting_the_same_crate_graph_keeps_memoized_queries() {
+    let file_id = FileId(1);
+    let crate_graph = || {
+        let mut crate_graph = CrateGraph::default();
+        crate_graph.add_crate_root(
+            file_id,
+            Edition::Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        crate_graph
+    };
+    let mut host = AnalysisHost::default();
+    let mut change = AnalysisChange::new();
+    change.add_root(SourceRootId(0), SourceRootOrigin::Workspace);
+    change.add_file(
+        SourceRootId(0),
+        file_id,
+        "main.rs".into(),
+        Rope::from("struct Foo;\nfn main() { F }"),
+    );
+    change.set_crate_graph(crate_graph());
+    host.apply_change(change);
+
+    let item_map_time = |host: &AnalysisHost| {
+        let position = FilePosition {
+            file_id,
+            offset: 25.into(),
+        };
+        host.analysis().completions(position).unwrap().unwrap();
+        host.query_stats()
+            .into_iter()
+            .find(|it| it.name == "item_map")
+            .unwrap()
+            .total_time
+    };
+    let before = item_map_time(&host);
+
+    let mut change = AnalysisChange::new();
+    change.set_crate_graph(crate_graph());
+    host.apply_change(change);
+    assert_eq!(item_map_time(&host), before);
+}
+
+#[test]
+fn library_symbols_are_loaded_from_cache() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let files = |first_id| {
+        vec![
+            (
+                FileId(first_id),
+                "lib.rs".into(),
+                Rope::from("mod m;\nfn bar() {}"),
+            ),
+            (
+                FileId(first_id + 1),
+                "m.rs".into(),
+                Rope::from("struct Foo;"),
+            ),
+        ]
+    };
+    LibraryData::prepare_cached(SourceRootId(0), files(1), cache_dir.path());
+    assert_eq!(fs::read_dir(cache_dir.path()).unwrap().count(), 1);
+
+    // `FileId`s differ between runs, the cache must use the new ones.
+    let mut host = AnalysisHost::default();
+    let mut change = AnalysisChange::new();
+    change.add_root(SourceRootId(0), SourceRootOrigin::Dependency);
+    change.add_library(LibraryData::prepare_cached(
+        SourceRootId(0),
+        files(10),
+        cache_dir.path(),
+    ));
+    host.apply_change(change);
+
+    let mut query = Query::new("Foo".to_string());
+    query.libs();
+    let symbols = host.analysis().symbol_search(query).unwrap();
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].name().as_str(), "Foo");
+    assert_eq!(symbols[0].file_id(), FileId(11));
+}
+
 #[test]
 fn test_fn_signature_two_args_first() {
     let (desc, param) = get_signature(
@@ -487,10 +763,52 @@ fn test_rename(text: &str, new_name: &str, expected: &str) {
     }
     let result = text_edit_bulder
         .finish()
-        .apply(&*analysis.file_text(file_id.unwrap()));
+        .apply(&analysis.file_text(file_id.unwrap()).to_string());
     assert_eq_text!(expected, &*result);
 }
 
+#[test]
+fn test_prepare_rename() {
+    fn check(text: &str, expected: Option<&str>) {
+        let (analysis, position) = single_file_with_position(text);
+        let target = analysis.prepare_rename(position).unwrap();
+        let actual = target.map(|it| {
+            let text = analysis.file_text(position.file_id).to_string();
+            assert_eq!(&text[it.range], it.info);
+            it.info
+        });
+        assert_eq!(actual, expected.map(String::from));
+    }
+    check("fn foo(x: u32) -> u32 { x<|> + 1 }", Some("x"));
+    check("fn foo(x: u32) -> u32 { <|>x + 1 }", Some("x"));
+    check("fn foo() { le<|>t x = 1; }", None);
+    check("fn foo(x: u3<|>2) {}", None);
+    check("fn foo() { let u32 = 1; u3<|>2; }", Some("u32"));
+}
+
+#[test]
+fn prepare_rename_rejects_library_files() {
+    let mut host = AnalysisHost::default();
+    let root = SourceRootId(0);
+    let file_id = FileId(1);
+    let mut change = AnalysisChange::new();
+    change.add_root(root, SourceRootOrigin::Dependency);
+    change.add_library(LibraryData::prepare(
+        root,
+        vec![(
+            file_id,
+            "lib.rs".into(),
+            Rope::from("fn foo(x: u32) { x; }"),
+        )],
+    ));
+    host.apply_change(change);
+    let position = FilePosition {
+        file_id,
+        offset: 17.into(),
+    };
+    assert!(host.analysis().prepare_rename(position).unwrap().is_none());
+}
+
 #[test]
 fn world_symbols_include_stuff_from_macros() {
     let (analysis, _) = single_file(
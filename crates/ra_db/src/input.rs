@@ -8,6 +8,7 @@
 use std::sync::Arc;
 
 use relative_path::RelativePathBuf;
+use ropey::Rope;
 use rustc_hash::FxHashMap;
 use salsa;
 
@@ -34,16 +35,39 @@ pub struct SourceRootId(pub u32);
 
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub struct SourceRoot {
+    /// Files in libraries are assumed to never change, while files in the
+    /// workspace change frequently.
+    pub is_library: bool,
+    pub origin: SourceRootOrigin,
     pub files: FxHashMap<RelativePathBuf, FileId>,
 }
 
+/// Where the files of a source root come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SourceRootOrigin {
+    /// A package of the current workspace.
+    Workspace,
+    /// A dependency of the workspace, like a crates.io library.
+    Dependency,
+}
+
+impl Default for SourceRootOrigin {
+    fn default() -> SourceRootOrigin {
+        SourceRootOrigin::Workspace
+    }
+}
+
 /// `CrateGraph` is a bit of information which turns a set of text files into a
 /// number of Rust crates. Each Crate is the `FileId` of it's root module, the
-/// set of cfg flags (not yet implemented) and the set of dependencies. Note
-/// that, due to cfg's, there might be several crates for a single `FileId`! As
-/// in the rust-lang proper, a crate does not have a name. Instead, names are
-/// specified on dependency edges. That is, a crate might be known under
-/// different names in different dependant crates.
+/// edition, the set of cfg flags (`CfgOptions`), the environment (`Env`) and
+/// the set of dependencies. Note that, due to cfg's, there might in principle
+/// be several crates for a single `FileId`, but for now the graph holds at
+/// most one: `crate_id_for_crate_root` and `extend` look crates up by their
+/// root file. As in the rust-lang proper, a crate does not have a name.
+/// Instead, names are specified on dependency edges. That is, a crate might be
+/// known under different names in different dependant crates. A crate might
+/// have a display name though, which is used only to show the crate to the
+/// user.
 ///
 /// Note that `CrateGraph` is build-system agnostic: it's a concept of the Rust
 /// langauge proper, not a concept of the build system. In practice, we get
@@ -56,22 +80,104 @@ pub struct CrateGraph {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CrateId(pub u32);
 
+/// The Rust edition a crate is compiled with. Editions differ in keywords
+/// (`async`, `dyn`) and in path resolution rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edition {
+    Edition2015,
+    Edition2018,
+}
+
+impl Edition {
+    /// Parses the `edition` field of `Cargo.toml`. Unknown editions are
+    /// treated as the latest one we know about.
+    pub fn from_string(s: &str) -> Edition {
+        match s {
+            "2015" => Edition::Edition2015,
+            _ => Edition::Edition2018,
+        }
+    }
+}
+
+/// The set of cfg flags enabled for a crate: atoms, like `test` or `unix`, and
+/// key-value pairs, like `feature = "serde"` or `target_os = "linux"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CfgOptions {
+    atoms: FxHashSet<SmolStr>,
+    key_values: FxHashSet<(SmolStr, SmolStr)>,
+}
+
+impl CfgOptions {
+    pub fn insert_atom(&mut self, key: SmolStr) {
+        self.atoms.insert(key);
+    }
+    pub fn insert_key_value(&mut self, key: SmolStr, value: SmolStr) {
+        self.key_values.insert((key, value));
+    }
+    /// Is `#[cfg(key)]` enabled?
+    pub fn check_atom(&self, key: &str) -> bool {
+        self.atoms.contains(&SmolStr::new(key))
+    }
+    /// Is `#[cfg(key = "value")]` enabled?
+    pub fn check_key_value(&self, key: &str, value: &str) -> bool {
+        self.key_values
+            .contains(&(SmolStr::new(key), SmolStr::new(value)))
+    }
+}
+
+/// Environment variables visible to `env!` and `option_env!` in a crate, like
+/// `CARGO_PKG_VERSION` or `OUT_DIR`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Env {
+    entries: FxHashMap<String, String>,
+}
+
+impl Env {
+    /// Sets the variable, overriding the previous value, if any.
+    pub fn set(&mut self, env: &str, value: String) {
+        self.entries.insert(env.to_string(), value);
+    }
+    pub fn get(&self, env: &str) -> Option<&str> {
+        self.entries.get(env).map(|it| it.as_str())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct CrateData {
     file_id: FileId,
+    edition: Edition,
+    display_name: Option<SmolStr>,
+    cfg_options: CfgOptions,
+    env: Env,
+    is_proc_macro: bool,
     dependencies: Vec<Dependency>,
 }
 
 impl CrateData {
-    fn new(file_id: FileId) -> CrateData {
+    fn new(
+        file_id: FileId,
+        edition: Edition,
+        display_name: Option<SmolStr>,
+        cfg_options: CfgOptions,
+        env: Env,
+    ) -> CrateData {
         CrateData {
             file_id,
+            edition,
+            display_name,
+            cfg_options,
+            env,
+            is_proc_macro: false,
             dependencies: Vec::new(),
         }
     }
 
-    fn add_dep(&mut self, name: SmolStr, crate_id: CrateId) {
-        self.dependencies.push(Dependency { name, crate_id })
+    fn add_dep(&mut self, name: SmolStr, crate_id: CrateId, is_dev: bool) {
+        self.dependencies.push(Dependency {
+            name,
+            crate_id,
+            is_dev,
+        })
     }
 }
 
@@ -79,6 +185,9 @@ impl CrateData {
 pub struct Dependency {
     pub crate_id: CrateId,
     pub name: SmolStr,
+    /// Dev-dependencies are visible only to the test code of the crate: to the
+    /// `#[cfg(test)]` modules.
+    pub is_dev: bool,
 }
 
 impl Dependency {
@@ -88,25 +197,134 @@ impl Dependency {
 }
 
 impl CrateGraph {
-    pub fn add_crate_root(&mut self, file_id: FileId) -> CrateId {
+    pub fn add_crate_root(
+        &mut self,
+        file_id: FileId,
+        edition: Edition,
+        display_name: Option<SmolStr>,
+        cfg_options: CfgOptions,
+        env: Env,
+    ) -> CrateId {
         let crate_id = CrateId(self.arena.len() as u32);
-        let prev = self.arena.insert(crate_id, CrateData::new(file_id));
+        let prev = self.arena.insert(
+            crate_id,
+            CrateData::new(file_id, edition, display_name, cfg_options, env),
+        );
         assert!(prev.is_none());
         crate_id
     }
-    pub fn add_dep(&mut self, from: CrateId, name: SmolStr, to: CrateId) {
-        let mut visited = FxHashSet::default();
-        if self.dfs_find(from, to, &mut visited) {
-            panic!("Cycle dependencies found.")
+    /// Adds a dependency edge, unless it would create a cycle.
+    pub fn add_dep(
+        &mut self,
+        from: CrateId,
+        name: SmolStr,
+        to: CrateId,
+    ) -> Result<(), CyclicDependenciesError> {
+        self.add_dep_impl(from, name, to, false)
+    }
+    /// Adds a dev-dependency edge, which is only visible to the test code of
+    /// `from`.
+    pub fn add_dev_dep(
+        &mut self,
+        from: CrateId,
+        name: SmolStr,
+        to: CrateId,
+    ) -> Result<(), CyclicDependenciesError> {
+        self.add_dep_impl(from, name, to, true)
+    }
+    fn add_dep_impl(
+        &mut self,
+        from: CrateId,
+        name: SmolStr,
+        to: CrateId,
+        is_dev: bool,
+    ) -> Result<(), CyclicDependenciesError> {
+        if let Some(path) = self.find_path(&mut FxHashSet::default(), to, from) {
+            let path = std::iter::once(from)
+                .chain(path.into_iter().rev())
+                .map(|it| (it, self.display_name(it).cloned()))
+                .collect();
+            return Err(CyclicDependenciesError { path });
         }
-        self.arena.get_mut(&from).unwrap().add_dep(name, to)
+        self.arena.get_mut(&from).unwrap().add_dep(name, to, is_dev);
+        Ok(())
+    }
+    /// Marks the crate as a procedural macro crate. Such crates are compiled
+    /// for the host and only export macros, so their items are not visible
+    /// to the crates depending on them.
+    pub fn mark_proc_macro(&mut self, crate_id: CrateId) {
+        self.arena.get_mut(&crate_id).unwrap().is_proc_macro = true;
     }
     pub fn is_empty(&self) -> bool {
         self.arena.is_empty()
     }
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+    /// Appends the crates of `other` to this graph. Crates of `other` with the
+    /// same root file as an existing crate are merged into the existing one:
+    /// it gains their dependencies, except the ones under a name it already
+    /// depends on and the ones which would create a cycle. Returns the mapping
+    /// from the `CrateId`s of `other` to the `CrateId`s in this graph.
+    pub fn extend(&mut self, other: CrateGraph) -> FxHashMap<CrateId, CrateId> {
+        let mut other_crates = other.arena.into_iter().collect::<Vec<_>>();
+        other_crates.sort_by_key(|(crate_id, _)| *crate_id);
+
+        let mut mapping = FxHashMap::default();
+        let mut added = Vec::new();
+        let mut merged = Vec::new();
+        for (other_id, data) in other_crates {
+            let crate_id = match self.crate_id_for_crate_root(data.file_id) {
+                Some(existing) => {
+                    merged.push((existing, data.dependencies));
+                    existing
+                }
+                None => {
+                    let crate_id = CrateId((self.arena.len() + added.len()) as u32);
+                    added.push((crate_id, data));
+                    crate_id
+                }
+            };
+            mapping.insert(other_id, crate_id);
+        }
+        for (crate_id, mut data) in added {
+            for dep in data.dependencies.iter_mut() {
+                dep.crate_id = mapping[&dep.crate_id];
+            }
+            self.arena.insert(crate_id, data);
+        }
+        for (crate_id, dependencies) in merged {
+            for dep in dependencies {
+                if self.dependencies(crate_id).any(|it| it.name == dep.name) {
+                    continue;
+                }
+                let to = mapping[&dep.crate_id];
+                // A cycle means that the graphs disagree, keep our version.
+                let _ = self.add_dep_impl(crate_id, dep.name, to, dep.is_dev);
+            }
+        }
+        mapping
+    }
     pub fn crate_root(&self, crate_id: CrateId) -> FileId {
         self.arena[&crate_id].file_id
     }
+    pub fn edition(&self, crate_id: CrateId) -> Edition {
+        self.arena[&crate_id].edition
+    }
+    /// The name of the crate for the user, like the name of the Cargo target.
+    /// This is not the name under which other crates refer to this one.
+    pub fn display_name(&self, crate_id: CrateId) -> Option<&SmolStr> {
+        self.arena[&crate_id].display_name.as_ref()
+    }
+    pub fn cfg_options(&self, crate_id: CrateId) -> &CfgOptions {
+        &self.arena[&crate_id].cfg_options
+    }
+    pub fn env(&self, crate_id: CrateId) -> &Env {
+        &self.arena[&crate_id].env
+    }
+    pub fn is_proc_macro(&self, crate_id: CrateId) -> bool {
+        self.arena[&crate_id].is_proc_macro
+    }
     pub fn crate_id_for_crate_root(&self, file_id: FileId) -> Option<CrateId> {
         let (&crate_id, _) = self
             .arena
@@ -120,39 +338,104 @@ impl CrateGraph {
     ) -> impl Iterator<Item = &'a Dependency> + 'a {
         self.arena[&crate_id].dependencies.iter()
     }
-    fn dfs_find(&self, target: CrateId, from: CrateId, visited: &mut FxHashSet<CrateId>) -> bool {
+    /// Finds a path of dependency edges from `from` to `to`. The path
+    /// includes both ends and is listed backwards, starting with `to`.
+    fn find_path(
+        &self,
+        visited: &mut FxHashSet<CrateId>,
+        from: CrateId,
+        to: CrateId,
+    ) -> Option<Vec<CrateId>> {
         if !visited.insert(from) {
-            return false;
+            return None;
+        }
+        if from == to {
+            return Some(vec![to]);
         }
-
         for dep in self.dependencies(from) {
-            let crate_id = dep.crate_id();
-            if crate_id == target {
-                return true;
+            if let Some(mut path) = self.find_path(visited, dep.crate_id(), to) {
+                path.push(from);
+                return Some(path);
             }
+        }
+        None
+    }
+}
+
+/// Adding a dependency edge would have created a cycle in the `CrateGraph`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CyclicDependenciesError {
+    path: Vec<(CrateId, Option<SmolStr>)>,
+}
 
-            if self.dfs_find(target, crate_id, visited) {
-                return true;
+impl CyclicDependenciesError {
+    /// The crates of the cycle, starting and ending with the same crate.
+    pub fn path(&self) -> impl Iterator<Item = CrateId> + '_ {
+        self.path.iter().map(|(crate_id, _)| *crate_id)
+    }
+}
+
+impl std::fmt::Display for CyclicDependenciesError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("cyclic dependencies:")?;
+        for (i, (crate_id, name)) in self.path.iter().enumerate() {
+            let sep = if i == 0 { " " } else { " -> " };
+            match name {
+                Some(name) => write!(fmt, "{}{}", sep, name)?,
+                None => write!(fmt, "{}{:?}", sep, crate_id)?,
             }
         }
-        return false;
+        Ok(())
     }
 }
 
+impl std::error::Error for CyclicDependenciesError {}
+
 #[cfg(test)]
 mod tests {
-    use super::{CrateGraph, FxHashMap, FileId, SmolStr};
+    use super::{CfgOptions, CrateGraph, Edition, Env, Edition::Edition2018, FxHashMap, FileId, SmolStr};
 
     #[test]
-    #[should_panic]
-    fn it_should_painc_because_of_cycle_dependencies() {
+    fn it_should_reject_cyclic_dependencies() {
         let mut graph = CrateGraph::default();
-        let crate1 = graph.add_crate_root(FileId(1u32));
-        let crate2 = graph.add_crate_root(FileId(2u32));
-        let crate3 = graph.add_crate_root(FileId(3u32));
-        graph.add_dep(crate1, SmolStr::new("crate2"), crate2);
-        graph.add_dep(crate2, SmolStr::new("crate3"), crate3);
-        graph.add_dep(crate3, SmolStr::new("crate1"), crate1);
+        let crate1 = graph.add_crate_root(
+            FileId(1u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        let crate2 = graph.add_crate_root(
+            FileId(2u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        let crate3 = graph.add_crate_root(
+            FileId(3u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        assert!(graph
+            .add_dep(crate1, SmolStr::new("crate2"), crate2)
+            .is_ok());
+        assert!(graph
+            .add_dep(crate2, SmolStr::new("crate3"), crate3)
+            .is_ok());
+        let err = graph
+            .add_dep(crate3, SmolStr::new("crate1"), crate1)
+            .unwrap_err();
+        assert_eq!(
+            err.path().collect::<Vec<_>>(),
+            vec![crate3, crate1, crate2, crate3]
+        );
+        assert_eq!(graph.dependencies(crate3).count(), 0);
+        assert!(graph
+            .add_dep(crate1, SmolStr::new("crate1"), crate1)
+            .is_err());
     }
 
     #[test]
@@ -160,18 +443,176 @@ mod tests {
         let mut graph = CrateGraph {
             arena: FxHashMap::default(),
         };
-        let crate1 = graph.add_crate_root(FileId(1u32));
-        let crate2 = graph.add_crate_root(FileId(2u32));
-        let crate3 = graph.add_crate_root(FileId(3u32));
-        graph.add_dep(crate1, SmolStr::new("crate2"), crate2);
-        graph.add_dep(crate2, SmolStr::new("crate3"), crate3);
+        let crate1 = graph.add_crate_root(
+            FileId(1u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        let crate2 = graph.add_crate_root(
+            FileId(2u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        let crate3 = graph.add_crate_root(
+            FileId(3u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        assert!(graph
+            .add_dep(crate1, SmolStr::new("crate2"), crate2)
+            .is_ok());
+        assert!(graph
+            .add_dep(crate2, SmolStr::new("crate3"), crate3)
+            .is_ok());
+    }
+
+    #[test]
+    fn extend_remaps_and_deduplicates_crates() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(
+            FileId(1u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+
+        let mut other = CrateGraph::default();
+        let other2 = other.add_crate_root(
+            FileId(2u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        let other1 = other.add_crate_root(
+            FileId(1u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        let other3 = other.add_crate_root(
+            FileId(3u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        other.add_dep(other3, "other2".into(), other2).unwrap();
+        other.add_dep(other1, "other3".into(), other3).unwrap();
+
+        let mapping = graph.extend(other);
+        assert_eq!(mapping[&other1], crate1);
+        let crate2 = mapping[&other2];
+        let crate3 = mapping[&other3];
+        assert_eq!(graph.crate_root(crate2), FileId(2u32));
+        assert_eq!(graph.crate_root(crate3), FileId(3u32));
+        let deps = graph
+            .dependencies(crate3)
+            .map(|it| it.crate_id())
+            .collect::<Vec<_>>();
+        assert_eq!(deps, vec![crate2]);
+        let deps = graph
+            .dependencies(crate1)
+            .map(|it| it.crate_id())
+            .collect::<Vec<_>>();
+        assert_eq!(deps, vec![crate3]);
+    }
+
+    #[test]
+    fn crates_have_cfg_options() {
+        let mut graph = CrateGraph::default();
+        let mut cfg_options = CfgOptions::default();
+        cfg_options.insert_atom("unix".into());
+        cfg_options.insert_key_value("feature".into(), "serde".into());
+        let crate1 =
+            graph.add_crate_root(FileId(1u32), Edition2018, None, cfg_options, Env::default());
+        let crate2 = graph.add_crate_root(
+            FileId(2u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+
+        let cfg = graph.cfg_options(crate1);
+        assert!(cfg.check_atom("unix"));
+        assert!(!cfg.check_atom("windows"));
+        assert!(cfg.check_key_value("feature", "serde"));
+        assert!(!cfg.check_key_value("feature", "std"));
+        assert!(!graph.cfg_options(crate2).check_atom("unix"));
+    }
+
+    #[test]
+    fn crates_have_editions() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(
+            FileId(1u32),
+            Edition::from_string("2015"),
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        let crate2 = graph.add_crate_root(
+            FileId(2u32),
+            Edition::from_string("2018"),
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        assert_eq!(graph.edition(crate1), Edition::Edition2015);
+        assert_eq!(graph.edition(crate2), Edition::Edition2018);
+    }
+
+    #[test]
+    fn crates_have_display_names() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(
+            FileId(1u32),
+            Edition2018,
+            Some("foo".into()),
+            CfgOptions::default(),
+            Env::default(),
+        );
+        let crate2 = graph.add_crate_root(
+            FileId(2u32),
+            Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
+        assert_eq!(
+            graph.display_name(crate1).map(|it| it.as_str()),
+            Some("foo")
+        );
+        assert_eq!(graph.display_name(crate2), None);
+    }
+
+    #[test]
+    fn crates_have_env() {
+        let mut graph = CrateGraph::default();
+        let mut env = Env::default();
+        env.set("CARGO_PKG_VERSION", "0.1.0".to_string());
+        env.set("CARGO_PKG_VERSION", "0.2.0".to_string());
+        let crate1 =
+            graph.add_crate_root(FileId(1u32), Edition2018, None, CfgOptions::default(), env);
+        assert_eq!(graph.env(crate1).get("CARGO_PKG_VERSION"), Some("0.2.0"));
+        assert_eq!(graph.env(crate1).get("OUT_DIR"), None);
     }
 }
 
 salsa::query_group! {
     pub trait FilesDatabase: salsa::Database {
-        /// Text of the file.
-        fn file_text(file_id: FileId) -> Arc<String> {
+        /// Text of the file. It is stored as a rope, so that an edit produces a
+        /// new version of the text without copying the whole file.
+        fn file_text(file_id: FileId) -> Rope {
             type FileTextQuery;
             storage input;
         }
@@ -190,22 +631,52 @@ salsa::query_group! {
             type SourceRootQuery;
             storage input;
         }
-        /// The set of "local" (that is, from the current workspace) roots.
-        /// Files in local roots are assumed to change frequently.
+        /// All source roots, both from the workspace and from libraries.
+        fn source_roots() -> Arc<Vec<SourceRootId>> {
+            type SourceRootsQuery;
+            storage input;
+        }
+        /// The set of "local" (that is, not library) roots.
         fn local_roots() -> Arc<Vec<SourceRootId>> {
             type LocalRootsQuery;
-            storage input;
         }
-        /// The set of roots for crates.io libraries.
-        /// Files in libraries are assumed to never change.
+        /// The set of roots for which `SourceRoot::is_library` is set.
         fn library_roots() -> Arc<Vec<SourceRootId>> {
             type LibraryRootsQuery;
-            storage input;
         }
         /// The crate graph.
         fn crate_graph() -> Arc<CrateGraph> {
             type CrateGraphQuery;
             storage input;
         }
+        /// The cfg flags of the crate. This is a separate query so that cfg
+        /// evaluation is not invalidated by unrelated changes to the crate graph.
+        fn crate_cfg(crate_id: CrateId) -> Arc<CfgOptions> {
+            type CrateCfgQuery;
+        }
     }
 }
+
+fn local_roots(db: &impl FilesDatabase) -> Arc<Vec<SourceRootId>> {
+    let roots = db.source_roots();
+    let res = roots
+        .iter()
+        .cloned()
+        .filter(|&id| !db.source_root(id).is_library)
+        .collect();
+    Arc::new(res)
+}
+
+fn library_roots(db: &impl FilesDatabase) -> Arc<Vec<SourceRootId>> {
+    let roots = db.source_roots();
+    let res = roots
+        .iter()
+        .cloned()
+        .filter(|&id| db.source_root(id).is_library)
+        .collect();
+    Arc::new(res)
+}
+
+fn crate_cfg(db: &impl FilesDatabase, crate_id: CrateId) -> Arc<CfgOptions> {
+    Arc::new(db.crate_graph().cfg_options(crate_id).clone())
+}
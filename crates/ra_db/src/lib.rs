@@ -5,7 +5,7 @@ mod input;
 mod loc2id;
 pub mod mock;
 
-use std::sync::Arc;
+use std::{sync::Arc, time};
 
 use ra_editor::LineIndex;
 use ra_syntax::{TextUnit, TextRange, SourceFileNode};
@@ -14,12 +14,13 @@ pub use crate::{
     cancelation::{Canceled, Cancelable},
     syntax_ptr::LocalSyntaxPtr,
     input::{
-        FilesDatabase, FileId, CrateId, SourceRoot, SourceRootId, CrateGraph, Dependency,
-        FileTextQuery, FileSourceRootQuery, SourceRootQuery, LocalRootsQuery, LibraryRootsQuery, CrateGraphQuery,
-        FileRelativePathQuery
+        FilesDatabase, FileId, CrateId, SourceRoot, SourceRootId, SourceRootOrigin, CrateGraph, Dependency, CfgOptions, Edition, Env, CyclicDependenciesError,
+        FileTextQuery, FileSourceRootQuery, SourceRootQuery, SourceRootsQuery, LocalRootsQuery, LibraryRootsQuery, CrateGraphQuery,
+        FileRelativePathQuery, CrateCfgQuery
     },
     loc2id::{LocationIntener, NumericId},
 };
+pub use ropey::Rope;
 
 #[macro_export]
 macro_rules! impl_numeric_id {
@@ -37,12 +38,58 @@ macro_rules! impl_numeric_id {
 
 pub trait BaseDatabase: salsa::Database {
     fn check_canceled(&self) -> Cancelable<()> {
-        if self.salsa_runtime().is_current_revision_canceled() {
+        let runtime = self.salsa_runtime();
+        if runtime.is_current_revision_canceled()
+            || (runtime.active_query().is_none() && self.is_snapshot_canceled())
+        {
             Err(Canceled::new())
         } else {
             Ok(())
         }
     }
+    /// Whether this particular snapshot was canceled, while the other ones
+    /// keep running. It is only checked between queries: a query which
+    /// stopped because of it would memoize `Canceled` for all other snapshots
+    /// of the current revision. By default, snapshots can't be canceled one
+    /// by one.
+    fn is_snapshot_canceled(&self) -> bool {
+        false
+    }
+    /// Called with the time it took to execute a query, to collect per-query
+    /// statistics. By default, the time is ignored.
+    fn record_query_time(&self, query: &'static str, time: time::Duration) {
+        let _ = (query, time);
+    }
+}
+
+/// Reports the time from its creation till drop as the execution time of a
+/// query. Put it at the start of a query function:
+///
+/// ```ignore
+/// let _timer = QueryTimer::new(db, "infer");
+/// ```
+///
+/// Note that the time includes the time of the queries called from this one.
+pub struct QueryTimer<'a, DB: BaseDatabase> {
+    db: &'a DB,
+    query: &'static str,
+    start: time::Instant,
+}
+
+impl<'a, DB: BaseDatabase> QueryTimer<'a, DB> {
+    pub fn new(db: &'a DB, query: &'static str) -> QueryTimer<'a, DB> {
+        QueryTimer {
+            db,
+            query,
+            start: time::Instant::now(),
+        }
+    }
+}
+
+impl<DB: BaseDatabase> Drop for QueryTimer<'_, DB> {
+    fn drop(&mut self) {
+        self.db.record_query_time(self.query, self.start.elapsed());
+    }
 }
 
 salsa::query_group! {
@@ -57,12 +104,13 @@ salsa::query_group! {
 }
 
 fn source_file(db: &impl SyntaxDatabase, file_id: FileId) -> SourceFileNode {
-    let text = db.file_text(file_id);
-    SourceFileNode::parse(&*text)
+    let _timer = QueryTimer::new(db, "source_file");
+    let text = db.file_text(file_id).to_string();
+    SourceFileNode::parse(&text)
 }
 fn file_lines(db: &impl SyntaxDatabase, file_id: FileId) -> Arc<LineIndex> {
-    let text = db.file_text(file_id);
-    Arc::new(LineIndex::new(&*text))
+    let text = db.file_text(file_id).to_string();
+    Arc::new(LineIndex::new(&text))
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -15,16 +15,29 @@ impl LocalSyntaxPtr {
         }
     }
 
+    /// Creates a pointer from its parts, for example when loading it from an
+    /// on-disk cache.
+    pub fn from_parts(range: TextRange, kind: SyntaxKind) -> LocalSyntaxPtr {
+        LocalSyntaxPtr { range, kind }
+    }
+
     pub fn resolve(self, file: &SourceFileNode) -> SyntaxNode {
+        self.try_resolve(file)
+            .unwrap_or_else(|| panic!("can't resolve local ptr to SyntaxNode: {:?}", self))
+    }
+
+    /// Like `resolve`, but returns `None` if the pointer does not point into
+    /// `file` (for example, if it was created for a different version of the
+    /// file).
+    pub fn try_resolve(self, file: &SourceFileNode) -> Option<SyntaxNode> {
         let mut curr = file.syntax();
         loop {
             if curr.range() == self.range && curr.kind() == self.kind {
-                return curr.owned();
+                return Some(curr.owned());
             }
             curr = curr
                 .children()
-                .find(|it| self.range.is_subrange(&it.range()))
-                .unwrap_or_else(|| panic!("can't resolve local ptr to SyntaxNode: {:?}", self))
+                .find(|it| self.range.is_subrange(&it.range()))?;
         }
     }
 
@@ -49,4 +62,7 @@ fn test_local_syntax_ptr() {
     let ptr = LocalSyntaxPtr::new(field.syntax());
     let field_syntax = ptr.resolve(&file);
     assert_eq!(field.syntax(), field_syntax);
+
+    let other_file = SourceFileNode::parse("struct Foo;");
+    assert!(ptr.try_resolve(&other_file).is_none());
 }
@@ -10,13 +10,13 @@ mod introduce_variable;
 mod change_visibility;
 mod split_import;
 
-use ra_text_edit::{TextEdit, TextEditBuilder};
+use ra_text_edit::{AtomTextEdit, TextEdit, TextEditBuilder};
 use ra_syntax::{
     Direction, SyntaxNodeRef, TextUnit, TextRange,SourceFileNode, AstNode,
     algo::{find_leaf_at_offset, find_covering_node, LeafAtOffset},
 };
 
-use crate::find_node_at_offset;
+use crate::{escape_snippet, find_node_at_offset};
 
 pub use self::{
     flip_comma::flip_comma,
@@ -28,26 +28,41 @@ pub use self::{
 };
 
 /// Return all the assists applicable at the given position.
-pub fn assists(file: &SourceFileNode, range: TextRange) -> Vec<LocalEdit> {
+pub fn assists(file: &SourceFileNode, range: TextRange) -> Vec<(AssistKind, LocalEdit)> {
     let ctx = AssistCtx::new(file, range);
     [
-        flip_comma,
-        add_derive,
-        add_impl,
-        introduce_variable,
-        change_visibility,
-        split_import,
+        (
+            flip_comma as fn(AssistCtx) -> Option<Assist>,
+            AssistKind::RefactorRewrite,
+        ),
+        (add_derive, AssistKind::RefactorRewrite),
+        (add_impl, AssistKind::RefactorRewrite),
+        (introduce_variable, AssistKind::RefactorExtract),
+        (change_visibility, AssistKind::RefactorRewrite),
+        (split_import, AssistKind::RefactorRewrite),
     ]
     .iter()
-    .filter_map(|&assist| ctx.clone().apply(assist))
+    .filter_map(|&(assist, kind)| Some((kind, ctx.clone().apply(assist)?)))
     .collect()
 }
 
+/// The kind of an assist, which allows editors to group and filter them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssistKind {
+    /// Moves a part of the code into a new item or binding.
+    RefactorExtract,
+    /// Changes the code without moving it around.
+    RefactorRewrite,
+}
+
 #[derive(Debug)]
 pub struct LocalEdit {
     pub label: String,
     pub edit: TextEdit,
     pub cursor_position: Option<TextUnit>,
+    /// Whether the inserted texts are snippets, see `strip_snippet`. The
+    /// cursor of a snippet edit is given by its tab stops.
+    pub is_snippet: bool,
 }
 
 fn non_trivia_sibling(node: SyntaxNodeRef, direction: Direction) -> Option<SyntaxNodeRef> {
@@ -101,7 +116,8 @@ pub enum Assist {
 
 #[derive(Default)]
 struct AssistBuilder {
-    edit: TextEditBuilder,
+    /// The edits, and whether their texts are snippets.
+    atoms: Vec<(AtomTextEdit, bool)>,
     cursor_position: Option<TextUnit>,
 }
 
@@ -138,11 +154,7 @@ impl<'a> AssistCtx<'a> {
         }
         let mut edit = AssistBuilder::default();
         f(&mut edit);
-        Some(Assist::Edit(LocalEdit {
-            label: label.into(),
-            edit: edit.edit.finish(),
-            cursor_position: edit.cursor_position,
-        }))
+        Some(Assist::Edit(edit.finish(label.into())))
     }
 
     pub(crate) fn leaf_at_offset(&self) -> LeafAtOffset<SyntaxNodeRef<'a>> {
@@ -158,18 +170,50 @@ impl<'a> AssistCtx<'a> {
 
 impl AssistBuilder {
     fn replace(&mut self, range: TextRange, replace_with: impl Into<String>) {
-        self.edit.replace(range, replace_with.into())
+        let atom = AtomTextEdit::replace(range, replace_with.into());
+        self.atoms.push((atom, false))
     }
     #[allow(unused)]
     fn delete(&mut self, range: TextRange) {
-        self.edit.delete(range)
+        self.atoms.push((AtomTextEdit::delete(range), false))
     }
     fn insert(&mut self, offset: TextUnit, text: impl Into<String>) {
-        self.edit.insert(offset, text.into())
+        self.atoms
+            .push((AtomTextEdit::insert(offset, text.into()), false))
+    }
+    /// Inserts a snippet, which may have tab stops and placeholders. Clients
+    /// which don't support snippets get the text with the cursor at the final
+    /// tab stop.
+    fn insert_snippet(&mut self, offset: TextUnit, snippet: impl Into<String>) {
+        self.atoms
+            .push((AtomTextEdit::insert(offset, snippet.into()), true))
     }
     fn set_cursor(&mut self, offset: TextUnit) {
         self.cursor_position = Some(offset)
     }
+    fn finish(self, label: String) -> LocalEdit {
+        let is_snippet = self.atoms.iter().any(|&(_, snippet)| snippet);
+        let mut edit = TextEditBuilder::default();
+        for (atom, snippet) in self.atoms {
+            // In a snippet edit, all texts are interpreted as snippets.
+            let insert = if is_snippet && !snippet {
+                escape_snippet(&atom.insert)
+            } else {
+                atom.insert
+            };
+            edit.replace(atom.delete, insert);
+        }
+        LocalEdit {
+            label,
+            edit: edit.finish(),
+            cursor_position: if is_snippet {
+                None
+            } else {
+                self.cursor_position
+            },
+            is_snippet,
+        }
+    }
 }
 
 #[cfg(test)]
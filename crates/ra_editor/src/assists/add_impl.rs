@@ -1,10 +1,10 @@
 use join_to_string::join;
-use ra_syntax::{
-    ast::{self, AstNode, NameOwner, TypeParamsOwner},
-    TextUnit,
-};
+use ra_syntax::ast::{self, AstNode, NameOwner, TypeParamsOwner};
 
-use crate::assists::{AssistCtx, Assist};
+use crate::{
+    assists::{AssistCtx, Assist},
+    escape_snippet,
+};
 
 pub fn add_impl(ctx: AssistCtx) -> Option<Assist> {
     let nominal = ctx.node_at_offset::<ast::NominalDef>()?;
@@ -33,9 +33,7 @@ pub fn add_impl(ctx: AssistCtx) -> Option<Assist> {
                 .to_buf(&mut buf);
         }
         buf.push_str(" {\n");
-        edit.set_cursor(start_offset + TextUnit::of_str(&buf));
-        buf.push_str("\n}");
-        edit.insert(start_offset, buf);
+        edit.insert_snippet(start_offset, format!("{}$0\n}}", escape_snippet(&buf)));
     })
 }
 
@@ -49,17 +47,17 @@ mod tests {
         check_assist(
             add_impl,
             "struct Foo {<|>}\n",
-            "struct Foo {}\n\nimpl Foo {\n<|>\n}\n",
+            "struct Foo {}\n\nimpl Foo {\n$0\n}\n",
         );
         check_assist(
             add_impl,
             "struct Foo<T: Clone> {<|>}",
-            "struct Foo<T: Clone> {}\n\nimpl<T: Clone> Foo<T> {\n<|>\n}",
+            "struct Foo<T: Clone> {}\n\nimpl<T: Clone> Foo<T> {\n$0\n}",
         );
         check_assist(
             add_impl,
             "struct Foo<'a, T: Foo<'a>> {<|>}",
-            "struct Foo<'a, T: Foo<'a>> {}\n\nimpl<'a, T: Foo<'a>> Foo<'a, T> {\n<|>\n}",
+            "struct Foo<'a, T: Foo<'a>> {}\n\nimpl<'a, T: Foo<'a>> Foo<'a, T> {\n$0\n}",
         );
     }
 
@@ -71,6 +71,7 @@ fn check_unnecessary_braces_in_use_statement(
                 label: "Remove unnecessary braces".to_string(),
                 edit,
                 cursor_position: None,
+                is_snippet: false,
             }),
         });
     }
@@ -124,6 +125,7 @@ fn check_struct_shorthand_initialization(
                         label: "use struct shorthand initialization".to_string(),
                         edit,
                         cursor_position: None,
+                        is_snippet: false,
                     }),
                 });
             }
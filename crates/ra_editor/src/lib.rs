@@ -3,6 +3,8 @@ mod extend_selection;
 mod folding_ranges;
 mod line_index;
 mod line_index_utils;
+mod organize_imports;
+mod snippet;
 mod structure;
 #[cfg(test)]
 mod test_utils;
@@ -10,13 +12,15 @@ mod typing;
 mod diagnostics;
 
 pub use self::{
-    assists::LocalEdit,
+    assists::{AssistKind, LocalEdit},
     extend_selection::extend_selection,
     folding_ranges::{folding_ranges, Fold, FoldKind},
-    line_index::{LineCol, LineIndex},
-    line_index_utils::translate_offset_with_edit,
+    line_index::{EncodedLineCol, LineCol, LineIndex, OffsetEncoding},
+    line_index_utils::{translate_offset_back, translate_offset_with_edit},
+    organize_imports::organize_imports,
+    snippet::{escape_snippet, strip_snippet, strip_snippet_edit},
     structure::{file_structure, StructureNode},
-    typing::{join_lines, on_enter, on_dot_typed, on_eq_typed},
+    typing::{join_lines, on_closing_typed, on_enter, on_dot_typed, on_eq_typed},
     diagnostics::diagnostics
 };
 use ra_text_edit::TextEditBuilder;
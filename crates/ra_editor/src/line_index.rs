@@ -1,11 +1,16 @@
 use crate::TextUnit;
-use rustc_hash::FxHashMap;
 use superslice::Ext;
 
+/// Converts offsets to lines and columns and back.
+///
+/// Besides the starts of the lines, only the positions of the multi-byte
+/// characters are stored, which are needed to count columns in UTF-16. Most
+/// lines are ASCII, which is checked for a whole line at once.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LineIndex {
-    pub(crate) newlines: Vec<TextUnit>,
-    pub(crate) utf16_lines: FxHashMap<u32, Vec<Utf16Char>>,
+    pub(crate) newlines: Box<[TextUnit]>,
+    /// The multi-byte characters of the lines which have any, sorted by line.
+    utf16_lines: Box<[(u32, Box<[Utf16Char]>)]>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -14,6 +19,40 @@ pub struct LineCol {
     pub col_utf16: u32,
 }
 
+/// The unit in which the columns of positions are counted. LSP counts UTF-16
+/// code units by default, but clients may negotiate a different encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OffsetEncoding {
+    Utf8,
+    Utf16,
+    Utf32,
+}
+
+impl Default for OffsetEncoding {
+    fn default() -> OffsetEncoding {
+        OffsetEncoding::Utf16
+    }
+}
+
+impl OffsetEncoding {
+    /// The number of code units of a char which takes `utf8_len` bytes.
+    fn char_len(self, utf8_len: TextUnit) -> TextUnit {
+        match self {
+            OffsetEncoding::Utf8 => utf8_len,
+            OffsetEncoding::Utf16 if utf8_len == TextUnit::from(4) => 2.into(),
+            OffsetEncoding::Utf16 | OffsetEncoding::Utf32 => 1.into(),
+        }
+    }
+}
+
+/// Like `LineCol`, but the column is counted in code units of some
+/// `OffsetEncoding`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EncodedLineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub(crate) struct Utf16Char {
     pub(crate) start: TextUnit,
@@ -28,52 +67,51 @@ impl Utf16Char {
 
 impl LineIndex {
     pub fn new(text: &str) -> LineIndex {
-        let mut utf16_lines = FxHashMap::default();
-        let mut utf16_chars = Vec::new();
-
-        let mut newlines = vec![0.into()];
-        let mut curr_row = 0.into();
-        let mut curr_col = 0.into();
-        let mut line = 0;
-        for c in text.chars() {
-            curr_row += TextUnit::of_char(c);
-            if c == '\n' {
-                newlines.push(curr_row);
-
-                // Save any utf-16 characters seen in the previous line
-                if utf16_chars.len() > 0 {
-                    utf16_lines.insert(line, utf16_chars);
-                    utf16_chars = Vec::new();
-                }
-
-                // Prepare for processing the next line
-                curr_col = 0.into();
-                line += 1;
-                continue;
+        let mut newlines = vec![TextUnit::from(0)];
+        let mut utf16_lines = Vec::new();
+        let mut line_start = 0;
+        loop {
+            let line_end =
+                memchr::memchr(b'\n', &text.as_bytes()[line_start..]).map(|idx| line_start + idx);
+            let line = &text[line_start..line_end.unwrap_or(text.len())];
+            if !line.is_ascii() {
+                let chars = line
+                    .char_indices()
+                    .filter(|(_, c)| c.len_utf8() > 1)
+                    .map(|(i, c)| {
+                        let start = TextUnit::from_usize(i);
+                        Utf16Char {
+                            start,
+                            end: start + TextUnit::of_char(c),
+                        }
+                    })
+                    .collect();
+                utf16_lines.push(((newlines.len() - 1) as u32, chars));
             }
-
-            let char_len = TextUnit::of_char(c);
-            if char_len.to_usize() > 1 {
-                utf16_chars.push(Utf16Char {
-                    start: curr_col,
-                    end: curr_col + char_len,
-                });
+            match line_end {
+                Some(line_end) => {
+                    line_start = line_end + 1;
+                    newlines.push(TextUnit::from_usize(line_start));
+                }
+                None => break,
             }
-
-            curr_col += char_len;
-        }
-
-        // Save any utf-16 characters seen in the last line
-        if utf16_chars.len() > 0 {
-            utf16_lines.insert(line, utf16_chars);
         }
 
         LineIndex {
-            newlines,
-            utf16_lines,
+            newlines: newlines.into_boxed_slice(),
+            utf16_lines: utf16_lines.into_boxed_slice(),
         }
     }
 
+    /// The multi-byte characters of the line, if there are any.
+    pub(crate) fn utf16_chars(&self, line: u32) -> Option<&[Utf16Char]> {
+        let idx = self
+            .utf16_lines
+            .binary_search_by_key(&line, |&(line, _)| line)
+            .ok()?;
+        Some(&self.utf16_lines[idx].1)
+    }
+
     pub fn line_col(&self, offset: TextUnit) -> LineCol {
         let line = self.newlines.upper_bound(&offset) - 1;
         let line_start_offset = self.newlines[line];
@@ -91,10 +129,42 @@ impl LineIndex {
         self.newlines[line_col.line as usize] + col
     }
 
+    /// Like `line_col`, but counts the column in code units of `encoding`.
+    /// Unlike `line_col`, this counts characters outside of the basic
+    /// multilingual plane as two UTF-16 code units.
+    pub fn line_col_in(&self, offset: TextUnit, encoding: OffsetEncoding) -> EncodedLineCol {
+        let line = self.newlines.upper_bound(&offset) - 1;
+        let col = offset - self.newlines[line];
+        let mut res = col;
+        if let Some(chars) = self.utf16_chars(line as u32) {
+            for c in chars.iter().take_while(|c| col >= c.end) {
+                res -= c.len() - encoding.char_len(c.len());
+            }
+        }
+        EncodedLineCol {
+            line: line as u32,
+            col: res.to_usize() as u32,
+        }
+    }
+
+    /// The inverse of `line_col_in`.
+    pub fn offset_in(&self, line_col: EncodedLineCol, encoding: OffsetEncoding) -> TextUnit {
+        let mut col: TextUnit = line_col.col.into();
+        if let Some(chars) = self.utf16_chars(line_col.line) {
+            for c in chars.iter() {
+                if col <= c.start {
+                    break;
+                }
+                col += c.len() - encoding.char_len(c.len());
+            }
+        }
+        self.newlines[line_col.line as usize] + col
+    }
+
     fn utf8_to_utf16_col(&self, line: u32, mut col: TextUnit) -> usize {
-        if let Some(utf16_chars) = self.utf16_lines.get(&line) {
+        if let Some(utf16_chars) = self.utf16_chars(line) {
             let mut correction = TextUnit::from_usize(0);
-            for c in utf16_chars {
+            for c in utf16_chars.iter() {
                 if col >= c.end {
                     correction += c.len() - TextUnit::from_usize(1);
                 } else {
@@ -112,8 +182,8 @@ impl LineIndex {
 
     fn utf16_to_utf8_col(&self, line: u32, col: u32) -> TextUnit {
         let mut col: TextUnit = col.into();
-        if let Some(utf16_chars) = self.utf16_lines.get(&line) {
-            for c in utf16_chars {
+        if let Some(utf16_chars) = self.utf16_chars(line) {
+            for c in utf16_chars.iter() {
                 if col >= c.start {
                     col += c.len() - TextUnit::from_usize(1);
                 } else {
@@ -291,6 +361,34 @@ mod test_line_index {
         res
     }
 
+    #[test]
+    fn test_line_index_of_a_large_file() {
+        let text = (0..20_000)
+            .map(|i| {
+                if i % 100 == 0 {
+                    "    let s = \"メ😀\"; // non-ASCII\n"
+                } else {
+                    "    let s = \"hello\"; // ASCII\n"
+                }
+            })
+            .collect::<String>();
+        let index = LineIndex::new(&text);
+        assert_eq!(index.newlines.len(), 20_001);
+        assert_eq!(index.utf16_lines.len(), 200);
+
+        for (offset, _) in text.char_indices().step_by(97) {
+            let offset = TextUnit::from_usize(offset);
+            for &encoding in &[
+                OffsetEncoding::Utf8,
+                OffsetEncoding::Utf16,
+                OffsetEncoding::Utf32,
+            ] {
+                let line_col = index.line_col_in(offset, encoding);
+                assert_eq!(index.offset_in(line_col, encoding), offset);
+            }
+        }
+    }
+
     proptest! {
         #[test]
         fn test_line_index_proptest((offset, text) in arb_text_with_offset()) {
@@ -321,6 +419,7 @@ const C: char = 'x';
 ",
         );
         assert_eq!(col_index.utf16_lines.len(), 0);
+        assert_eq!(col_index.utf16_chars(1), None);
     }
 
     #[test]
@@ -332,9 +431,10 @@ const C: char = 'メ';
         );
 
         assert_eq!(col_index.utf16_lines.len(), 1);
-        assert_eq!(col_index.utf16_lines[&1].len(), 1);
+        let utf16_chars = col_index.utf16_chars(1).unwrap();
+        assert_eq!(utf16_chars.len(), 1);
         assert_eq!(
-            col_index.utf16_lines[&1][0],
+            utf16_chars[0],
             Utf16Char {
                 start: 17.into(),
                 end: 20.into()
@@ -363,16 +463,17 @@ const C: char = \"メ メ\";
         );
 
         assert_eq!(col_index.utf16_lines.len(), 1);
-        assert_eq!(col_index.utf16_lines[&1].len(), 2);
+        let utf16_chars = col_index.utf16_chars(1).unwrap();
+        assert_eq!(utf16_chars.len(), 2);
         assert_eq!(
-            col_index.utf16_lines[&1][0],
+            utf16_chars[0],
             Utf16Char {
                 start: 17.into(),
                 end: 20.into()
             }
         );
         assert_eq!(
-            col_index.utf16_lines[&1][1],
+            utf16_chars[1],
             Utf16Char {
                 start: 21.into(),
                 end: 24.into()
@@ -396,4 +497,30 @@ const C: char = \"メ メ\";
         assert_eq!(col_index.utf16_to_utf8_col(2, 15), TextUnit::from_usize(15));
     }
 
+    #[test]
+    fn test_offset_encodings() {
+        // `メ` is 3 UTF-8 bytes and one UTF-16 code unit, `😀` is 4 bytes and
+        // two code units.
+        let text = "x\nlet s = \"メ😀\";";
+        let index = LineIndex::new(text);
+        let end_of_string = TextUnit::of_str(text) - TextUnit::of_str("\";");
+        for &(encoding, col) in &[
+            (OffsetEncoding::Utf8, 16),
+            (OffsetEncoding::Utf16, 12),
+            (OffsetEncoding::Utf32, 11),
+        ] {
+            let line_col = index.line_col_in(end_of_string, encoding);
+            assert_eq!(line_col, EncodedLineCol { line: 1, col });
+            assert_eq!(index.offset_in(line_col, encoding), end_of_string);
+        }
+
+        let before_emoji = TextUnit::of_str("x\nlet s = \"メ");
+        let line_col = index.line_col_in(before_emoji, OffsetEncoding::Utf16);
+        assert_eq!(line_col, EncodedLineCol { line: 1, col: 10 });
+        assert_eq!(
+            index.offset_in(line_col, OffsetEncoding::Utf16),
+            before_emoji
+        );
+    }
+
 }
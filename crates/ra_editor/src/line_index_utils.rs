@@ -44,9 +44,8 @@ impl<'a> Iterator for LineIndexStepIter<'a> {
                 let next_newline = *self.line_index.newlines.get(self.next_newline_idx)?;
                 self.utf16_chars = self
                     .line_index
-                    .utf16_lines
-                    .get(&(self.next_newline_idx as u32))
-                    .map(|x| (next_newline, x.iter()));
+                    .utf16_chars(self.next_newline_idx as u32)
+                    .map(|chars| (next_newline, chars.iter()));
                 self.next_newline_idx += 1;
                 Some(Step::Newline(next_newline))
             })
@@ -317,6 +316,25 @@ pub fn translate_offset_with_edit(
     res.to_line_col(offset)
 }
 
+/// The inverse of `TextEdit::apply_to_offset`: maps an offset in the edited
+/// text back to the original text. Returns `None` for the offsets inside
+/// inserted text.
+pub fn translate_offset_back(offset: TextUnit, text_edit: &TextEdit) -> Option<TextUnit> {
+    // The offsets of the edited text minus the offsets of the original one.
+    let mut diff = 0i64;
+    for atom in text_edit.as_atoms() {
+        let start = (atom.delete.start().to_usize() as i64 + diff) as u32;
+        if offset <= TextUnit::from(start) {
+            break;
+        }
+        if offset < TextUnit::from(start) + TextUnit::of_str(&atom.insert) {
+            return None;
+        }
+        diff += atom.insert.len() as i64 - atom.delete.len().to_usize() as i64;
+    }
+    Some(TextUnit::from((offset.to_usize() as i64 - diff) as u32))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -350,7 +368,36 @@ mod test {
             .boxed()
     }
 
+    #[test]
+    fn test_translate_offset_back() {
+        let text = "0123456789";
+        let mut builder = ra_text_edit::TextEditBuilder::default();
+        builder.replace(TextRange::from_to(2.into(), 4.into()), "abc".to_string());
+        builder.delete(TextRange::from_to(6.into(), 8.into()));
+        let edit = builder.finish();
+        assert_eq!(edit.apply(text), "01abc4589");
+
+        let back = |offset: u32| translate_offset_back(offset.into(), &edit);
+        assert_eq!(back(1), Some(1.into()));
+        assert_eq!(back(2), Some(2.into()));
+        assert_eq!(back(3), None);
+        assert_eq!(back(5), Some(4.into()));
+        assert_eq!(back(7), Some(6.into()));
+        assert_eq!(back(8), Some(9.into()));
+    }
+
     proptest! {
+        #[test]
+        fn test_translate_offset_back_proptest(x in arb_text_with_edit_and_offset()) {
+            if let Some(offset) = translate_offset_back(x.offset, &x.edit) {
+                // An offset right after inserted text maps back to the start
+                // of the insertion, which `apply_to_offset` keeps before it.
+                let forward = x.edit.apply_to_offset(offset).unwrap();
+                assert!(forward <= x.offset);
+                assert_eq!(translate_offset_back(forward, &x.edit), Some(offset));
+            }
+        }
+
         #[test]
         fn test_translate_offset_with_edit(x in arb_text_with_edit_and_offset()) {
             let expected = line_index::to_line_col(&x.edited_text, x.offset);
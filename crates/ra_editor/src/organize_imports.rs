@@ -0,0 +1,324 @@
+//! Sorts, merges and groups the `use` items of a file.
+//!
+//! Every run of adjacent `use` items is rewritten, so that each crate is
+//! imported by a single item, and the items are grouped into the standard
+//! library, external crates and the current crate, in this order. Runs are
+//! broken by anything except whitespace, so that `use` items with comments,
+//! attributes or visibility stay where they are.
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use ra_text_edit::TextEditBuilder;
+use ra_syntax::{
+    ast::{self, AstNode},
+    SourceFileNode,
+    SyntaxKind::*,
+    SyntaxNodeRef, TextRange,
+};
+
+use crate::LocalEdit;
+
+pub fn organize_imports(file: &SourceFileNode) -> Option<LocalEdit> {
+    let mut edit = TextEditBuilder::default();
+    let mut changed = false;
+    let containers = file
+        .syntax()
+        .descendants()
+        .filter(|node| match node.kind() {
+            SOURCE_FILE => true,
+            ITEM_LIST => node.parent().map(|it| it.kind()) == Some(MODULE),
+            _ => false,
+        });
+    for container in containers {
+        for run in use_item_runs(container) {
+            let range = TextRange::from_to(
+                run[0].syntax().range().start(),
+                run[run.len() - 1].syntax().range().end(),
+            );
+            let organized = match organize_run(&run) {
+                Some(it) => it,
+                None => continue,
+            };
+            if organized != file.syntax().text().slice(range).to_string() {
+                edit.replace(range, organized);
+                changed = true;
+            }
+        }
+    }
+    if !changed {
+        return None;
+    }
+    Some(LocalEdit {
+        label: "organize imports".to_string(),
+        edit: edit.finish(),
+        cursor_position: None,
+        is_snippet: false,
+    })
+}
+
+fn use_item_runs(container: SyntaxNodeRef) -> Vec<Vec<ast::UseItem>> {
+    let mut res = Vec::new();
+    let mut run = Vec::new();
+    for child in container.children() {
+        if child.kind() == WHITESPACE {
+            continue;
+        }
+        match ast::UseItem::cast(child) {
+            Some(use_item) if is_plain(use_item) => run.push(use_item),
+            _ => {
+                if !run.is_empty() {
+                    res.push(run);
+                    run = Vec::new();
+                }
+            }
+        }
+    }
+    if !run.is_empty() {
+        res.push(run);
+    }
+    res
+}
+
+/// Whether the item is a private `use`, without comments and attributes.
+fn is_plain(use_item: ast::UseItem) -> bool {
+    use_item
+        .syntax()
+        .descendants()
+        .all(|it| it.kind() != COMMENT && it.kind() != ATTR && it.kind() != VISIBILITY)
+}
+
+/// The imported paths, as a tree of path segments.
+#[derive(Default)]
+struct ImportTree {
+    /// Whether the path leading to this node is imported itself.
+    is_imported: bool,
+    children: BTreeMap<String, ImportTree>,
+}
+
+impl ImportTree {
+    fn insert(&mut self, path: &[String]) {
+        match path.split_first() {
+            None => self.is_imported = true,
+            Some((first, rest)) => self.children.entry(first.clone()).or_default().insert(rest),
+        }
+    }
+
+    fn render(&self, segment: &str, buf: &mut String) {
+        buf.push_str(segment);
+        if self.children.is_empty() {
+            return;
+        }
+        buf.push_str("::");
+        if !self.is_imported && self.children.len() == 1 {
+            let (child_segment, child) = self.children.iter().next().unwrap();
+            child.render(child_segment, buf);
+            return;
+        }
+        buf.push('{');
+        if self.is_imported {
+            buf.push_str("self, ");
+        }
+        for (idx, (child_segment, child)) in self.children.iter().enumerate() {
+            if idx > 0 {
+                buf.push_str(", ");
+            }
+            child.render(child_segment, buf);
+        }
+        buf.push('}');
+    }
+}
+
+/// Renders the organized `use` items of the run, or returns `None` if some
+/// of the items are too unusual to be rewritten.
+fn organize_run(run: &[ast::UseItem]) -> Option<String> {
+    let mut imports = ImportTree::default();
+    for use_item in run {
+        let mut paths = Vec::new();
+        flatten_use_tree(use_item.use_tree()?, Vec::new(), &mut paths)?;
+        for path in paths {
+            imports.insert(&path);
+        }
+    }
+    let indent = run[0]
+        .syntax()
+        .prev_sibling()
+        .filter(|it| it.kind() == WHITESPACE)
+        .and_then(|it| it.leaf_text())
+        .and_then(|text| text.rfind('\n').map(|pos| text[pos + 1..].to_string()))
+        .unwrap_or_default();
+    let mut groups = BTreeMap::new();
+    for (segment, tree) in imports.children.iter() {
+        groups
+            .entry(import_group(segment))
+            .or_insert_with(Vec::new)
+            .push((segment, tree));
+    }
+    let mut groups = groups.values().map(|items| {
+        items
+            .iter()
+            .map(|(segment, tree)| {
+                let mut buf = String::from("use ");
+                tree.render(segment, &mut buf);
+                buf.push(';');
+                buf
+            })
+            .join(&format!("\n{}", indent))
+    });
+    Some(groups.join(&format!("\n\n{}", indent)))
+}
+
+/// Imports from `std`, from other crates and from the current crate go to
+/// separate groups.
+fn import_group(first_segment: &str) -> u8 {
+    match first_segment {
+        "std" | "core" | "alloc" => 0,
+        "crate" | "self" | "super" => 2,
+        _ => 1,
+    }
+}
+
+fn flatten_use_tree(
+    tree: ast::UseTree,
+    mut prefix: Vec<String>,
+    acc: &mut Vec<Vec<String>>,
+) -> Option<()> {
+    if let Some(path) = tree.path() {
+        let text = path.syntax().text().to_string();
+        let segments = text.split("::").map(|it| it.trim().to_string());
+        if text.trim() == "self" {
+            // `use foo::{self}` imports `foo` itself.
+            if prefix.is_empty() {
+                return None;
+            }
+        } else {
+            for segment in segments {
+                if segment.is_empty() {
+                    return None;
+                }
+                prefix.push(segment);
+            }
+        }
+    }
+    if tree.has_star() {
+        prefix.push("*".to_string());
+        acc.push(prefix);
+        return Some(());
+    }
+    if let Some(use_tree_list) = tree.use_tree_list() {
+        for tree in use_tree_list.use_trees() {
+            flatten_use_tree(tree, prefix.clone(), acc)?;
+        }
+        return Some(());
+    }
+    if let Some(alias) = tree.syntax().children().find(|it| it.kind() == ALIAS) {
+        let alias = alias.text().to_string();
+        let last = prefix.last_mut()?;
+        last.push(' ');
+        last.push_str(&alias.split_whitespace().join(" "));
+    }
+    if prefix.is_empty() {
+        return None;
+    }
+    acc.push(prefix);
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::assert_eq_text;
+
+    use super::*;
+
+    fn check(before: &str, after: &str) {
+        let file = SourceFileNode::parse(before);
+        let actual = match organize_imports(&file) {
+            Some(edit) => edit.edit.apply(before),
+            None => before.to_string(),
+        };
+        assert_eq_text!(after, &actual);
+    }
+
+    #[test]
+    fn sorts_merges_and_groups_imports() {
+        check(
+            "
+use crate::foo::Bar;
+use std::sync::Arc;
+use itertools::Itertools;
+use std::{fmt, collections::HashMap};
+use super::baz;
+use std::sync::Arc;
+
+fn main() {}
+",
+            "
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use itertools::Itertools;
+
+use crate::foo::Bar;
+use super::baz;
+
+fn main() {}
+",
+        );
+    }
+
+    #[test]
+    fn keeps_self_globs_and_aliases() {
+        check(
+            "
+use foo::bar::Baz as Quux;
+use foo::{self, bar::*};
+use foo::bar;
+",
+            "
+use foo::{self, bar::{self, *, Baz as Quux}};
+",
+        );
+    }
+
+    #[test]
+    fn organizes_imports_in_inline_modules() {
+        check(
+            "
+mod tests {
+    use super::*;
+    use crate::b;
+    use crate::a;
+}
+",
+            "
+mod tests {
+    use crate::{a, b};
+    use super::*;
+}
+",
+        );
+    }
+
+    #[test]
+    fn leaves_imports_with_comments_and_visibility_alone() {
+        check(
+            "
+pub use foo::b;
+pub use foo::a;
+// about bar
+use bar::b;
+use bar::a;
+",
+            "
+pub use foo::b;
+pub use foo::a;
+// about bar
+use bar::{a, b};
+",
+        );
+    }
+
+    #[test]
+    fn does_nothing_for_organized_imports() {
+        let file = SourceFileNode::parse("use std::{fmt, sync::Arc};\n\nuse crate::foo;\n");
+        assert!(organize_imports(&file).is_none());
+    }
+}
@@ -0,0 +1,137 @@
+//! Snippets are texts with tab stops and placeholders, in the syntax of the
+//! LSP: `$0`, `$1`, `${1}` or `${1:placeholder}`. Clients which can't expand
+//! snippets get the plain text instead, with the cursor at the final tab stop.
+use ra_text_edit::{TextEdit, TextEditBuilder};
+use ra_syntax::TextUnit;
+
+/// Escapes `text`, so that it is inserted verbatim as a part of a snippet.
+pub fn escape_snippet(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\\' || c == '$' || c == '}' {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Removes the tab stops from `snippet`, keeping the text of placeholders.
+/// Returns the plain text and the offset of the cursor in it: the final tab
+/// stop, `$0`, or the first one if there's no final tab stop.
+pub fn strip_snippet(snippet: &str) -> (String, Option<TextUnit>) {
+    let mut res = String::with_capacity(snippet.len());
+    let mut stops: Vec<(u32, TextUnit)> = Vec::new();
+    let mut open_placeholders = 0;
+    let mut chars = snippet.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&next) if next == '\\' || next == '$' || next == '}' => {
+                    res.push(next);
+                    chars.next();
+                }
+                _ => res.push(c),
+            },
+            '$' => {
+                let braced = chars.peek() == Some(&'{');
+                if braced {
+                    chars.next();
+                }
+                let mut idx = String::new();
+                while let Some(&d) = chars.peek() {
+                    if !d.is_ascii_digit() {
+                        break;
+                    }
+                    idx.push(d);
+                    chars.next();
+                }
+                let idx = match idx.parse() {
+                    Ok(it) => it,
+                    Err(_) => {
+                        res.push('$');
+                        if braced {
+                            res.push('{');
+                        }
+                        continue;
+                    }
+                };
+                stops.push((idx, TextUnit::of_str(&res)));
+                if braced && chars.next() == Some(':') {
+                    open_placeholders += 1;
+                }
+            }
+            '}' if open_placeholders > 0 => open_placeholders -= 1,
+            c => res.push(c),
+        }
+    }
+    let cursor = stops
+        .into_iter()
+        .min_by_key(|&(idx, _)| (idx != 0, idx))
+        .map(|(_, offset)| offset);
+    (res, cursor)
+}
+
+/// Strips the snippets inserted by `edit`. The returned offset of the cursor
+/// refers to the text after the edit.
+pub fn strip_snippet_edit(edit: &TextEdit) -> (TextEdit, Option<TextUnit>) {
+    let mut builder = TextEditBuilder::default();
+    let mut cursor = None;
+    let mut deleted = TextUnit::from(0);
+    let mut inserted = TextUnit::from(0);
+    for atom in edit.as_atoms() {
+        let (text, offset) = strip_snippet(&atom.insert);
+        if cursor.is_none() {
+            cursor = offset.map(|offset| atom.delete.start() - deleted + inserted + offset);
+        }
+        deleted += atom.delete.len();
+        inserted += TextUnit::of_str(&text);
+        builder.replace(atom.delete, text);
+    }
+    (builder.finish(), cursor)
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::TextRange;
+
+    use crate::test_utils::{add_cursor, assert_eq_text};
+
+    use super::*;
+
+    #[test]
+    fn test_strip_snippet() {
+        fn check(snippet: &str, expected: &str) {
+            let (text, cursor) = strip_snippet(snippet);
+            let actual = match cursor {
+                Some(offset) => add_cursor(&text, offset),
+                None => text,
+            };
+            assert_eq_text!(expected, &actual);
+        }
+        check("impl Foo {\n    $0\n}", "impl Foo {\n    <|>\n}");
+        check("fn ${1:foo}() { $0 }", "fn foo() { <|> }");
+        check("fn ${2:foo}(${1}) {}", "fn foo(<|>) {}");
+        check("let \\$x = \\{\\}; $", "let $x = \\{}; $");
+        check("no tab stops", "no tab stops");
+    }
+
+    #[test]
+    fn test_escape_snippet() {
+        let text = "macro_rules! m { ($x:expr) => { \\ } }";
+        let (stripped, cursor) = strip_snippet(&escape_snippet(text));
+        assert_eq!(stripped, text);
+        assert_eq!(cursor, None);
+    }
+
+    #[test]
+    fn test_strip_snippet_edit() {
+        let mut builder = TextEditBuilder::default();
+        builder.replace(TextRange::from_to(0.into(), 3.into()), "x".to_string());
+        builder.insert(5.into(), "{$0}".to_string());
+        let (edit, cursor) = strip_snippet_edit(&builder.finish());
+        let text = edit.apply("abcdefg");
+        assert_eq!(text, "xde{}fg");
+        assert_eq!(cursor, Some(4.into()));
+    }
+}
@@ -56,6 +56,7 @@ fn structure_node(node: SyntaxNodeRef) -> Option<StructureNode> {
         .visit(decl::<ast::NamedFieldDef>)
         .visit(decl::<ast::EnumDef>)
         .visit(decl::<ast::TraitDef>)
+        .visit(decl::<ast::TraitAliasDef>)
         .visit(decl::<ast::Module>)
         .visit(decl::<ast::TypeDef>)
         .visit(decl::<ast::ConstDef>)
@@ -12,6 +12,11 @@ pub fn check_action<F: Fn(&SourceFileNode, TextUnit) -> Option<LocalEdit>>(
     let file = SourceFileNode::parse(&before);
     let result = f(&file, before_cursor_pos).expect("code action is not applicable");
     let actual = result.edit.apply(&before);
+    if result.is_snippet {
+        // The cursor is given by the tab stops of the snippet.
+        assert_eq_text!(after, &actual);
+        return;
+    }
     let actual_cursor_pos = match result.cursor_position {
         None => result.edit.apply_to_offset(before_cursor_pos).unwrap(),
         Some(off) => off,
@@ -29,6 +34,11 @@ pub fn check_action_range<F: Fn(&SourceFileNode, TextRange) -> Option<LocalEdit>
     let file = SourceFileNode::parse(&before);
     let result = f(&file, range).expect("code action is not applicable");
     let actual = result.edit.apply(&before);
+    if result.is_snippet {
+        // The cursor is given by the tab stops of the snippet.
+        assert_eq_text!(after, &actual);
+        return;
+    }
     let actual_cursor_pos = match result.cursor_position {
         None => result.edit.apply_to_offset(range.start()).unwrap(),
         Some(off) => off,
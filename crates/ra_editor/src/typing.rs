@@ -5,7 +5,7 @@ use ra_syntax::{
     algo::{find_covering_node, find_leaf_at_offset, LeafAtOffset},
     ast,
     text_utils::intersect,
-    AstNode, Direction, SourceFileNode, SyntaxKind,
+    AstNode, SourceFileNode, SyntaxKind,
     SyntaxKind::*,
     SyntaxNodeRef, TextRange, TextUnit,
 };
@@ -23,6 +23,7 @@ pub fn join_lines(file: &SourceFileNode, range: TextRange) -> LocalEdit {
                     label: "join lines".to_string(),
                     edit: TextEditBuilder::default().finish(),
                     cursor_position: None,
+                    is_snippet: false,
                 };
             }
             Some(pos) => pos,
@@ -56,6 +57,7 @@ pub fn join_lines(file: &SourceFileNode, range: TextRange) -> LocalEdit {
         label: "join lines".to_string(),
         edit: edit.finish(),
         cursor_position: None,
+        is_snippet: false,
     }
 }
 
@@ -82,6 +84,7 @@ pub fn on_enter(file: &SourceFileNode, offset: TextUnit) -> Option<LocalEdit> {
         label: "on enter".to_string(),
         edit: edit.finish(),
         cursor_position: Some(cursor_position),
+        is_snippet: false,
     })
 }
 
@@ -107,83 +110,158 @@ fn node_indent<'a>(file: &'a SourceFileNode, node: SyntaxNodeRef) -> Option<&'a
 
 pub fn on_eq_typed(file: &SourceFileNode, offset: TextUnit) -> Option<LocalEdit> {
     let let_stmt: ast::LetStmt = find_node_at_offset(file.syntax(), offset)?;
-    if let_stmt.has_semi() {
+    let mut edit = TextEditBuilder::default();
+    // `=` which starts a line continues the `let`, so it is indented.
+    let eq_offset = offset - TextUnit::of_char('=');
+    let eq = find_leaf_at_offset(file.syntax(), eq_offset).right_biased()?;
+    let is_continuation = eq.kind() == EQ && eq.parent() == Some(let_stmt.syntax());
+    let reindented = is_continuation && reindent_line(&mut edit, file, eq, let_stmt.syntax());
+    if needs_semicolon(file, let_stmt, offset) {
+        edit.insert(let_stmt.syntax().range().end(), ";".to_string());
+    } else if !reindented {
         return None;
     }
-    if let Some(expr) = let_stmt.initializer() {
-        let expr_range = expr.syntax().range();
-        if contains_offset_nonstrict(expr_range, offset) && offset != expr_range.start() {
-            return None;
-        }
-        if file
-            .syntax()
-            .text()
-            .slice(offset..expr_range.start())
-            .contains('\n')
-        {
-            return None;
-        }
+    let label = if reindented {
+        "indent let continuation"
     } else {
-        return None;
-    }
-    let offset = let_stmt.syntax().range().end();
-    let mut edit = TextEditBuilder::default();
-    edit.insert(offset, ";".to_string());
+        "add semicolon"
+    };
     Some(LocalEdit {
-        label: "add semicolon".to_string(),
+        label: label.to_string(),
         edit: edit.finish(),
         cursor_position: None,
+        is_snippet: false,
     })
 }
 
-pub fn on_dot_typed(file: &SourceFileNode, offset: TextUnit) -> Option<LocalEdit> {
-    let before_dot_offset = offset - TextUnit::of_char('.');
-
-    let whitespace = find_leaf_at_offset(file.syntax(), before_dot_offset).left_biased()?;
-
-    // find whitespace just left of the dot
-    ast::Whitespace::cast(whitespace)?;
-
-    // make sure there is a method call
-    let method_call = whitespace
-        .siblings(Direction::Prev)
-        // first is whitespace
-        .skip(1)
-        .next()?;
-
-    ast::MethodCallExprNode::cast(method_call)?;
-
-    // find how much the _method call is indented
-    let method_chain_indent = method_call
-        .parent()?
-        .siblings(Direction::Prev)
-        .skip(1)
-        .next()?
-        .leaf_text()
-        .map(|x| last_line_indent_in_whitespace(x))?;
-
-    let current_indent = TextUnit::of_str(last_line_indent_in_whitespace(whitespace.leaf_text()?));
-    // TODO: indent is always 4 spaces now. A better heuristic could look on the previous line(s)
-
-    let target_indent = TextUnit::of_str(method_chain_indent) + TextUnit::from_usize(4);
-
-    let diff = target_indent - current_indent;
-
-    let indent = "".repeat(diff.to_usize());
+fn needs_semicolon(file: &SourceFileNode, let_stmt: ast::LetStmt, offset: TextUnit) -> bool {
+    if let_stmt.has_semi() {
+        return false;
+    }
+    let expr = match let_stmt.initializer() {
+        Some(it) => it,
+        None => return false,
+    };
+    let expr_range = expr.syntax().range();
+    if contains_offset_nonstrict(expr_range, offset) && offset != expr_range.start() {
+        return false;
+    }
+    !file
+        .syntax()
+        .text()
+        .slice(offset..expr_range.start())
+        .contains('\n')
+}
 
-    let cursor_position = offset + diff;
+/// Indents a `.` which starts a line and continues a method chain one level
+/// deeper than the line on which the chain starts.
+pub fn on_dot_typed(file: &SourceFileNode, offset: TextUnit) -> Option<LocalEdit> {
+    let dot_offset = offset - TextUnit::of_char('.');
+    let dot = find_leaf_at_offset(file.syntax(), dot_offset).right_biased()?;
+    if dot.kind() != DOT {
+        return None;
+    }
+    let receiver = dot.prev_sibling()?.prev_sibling()?;
+    ast::Expr::cast(receiver)?;
     let mut edit = TextEditBuilder::default();
-    edit.insert(before_dot_offset, indent);
+    if !reindent_line(&mut edit, file, dot, receiver) {
+        return None;
+    }
+    let edit = edit.finish();
+    // The reindented whitespace ends right before the dot.
+    let mut cursor_position = offset;
+    for atom in edit.as_atoms() {
+        cursor_position += TextUnit::of_str(&atom.insert);
+        cursor_position -= atom.delete.len();
+    }
     Some(LocalEdit {
         label: "indent dot".to_string(),
-        edit: edit.finish(),
+        edit,
         cursor_position: Some(cursor_position),
+        is_snippet: false,
+    })
+}
+
+/// Dedents a closing `}` or `>` which starts a line to the indent of the line
+/// with the opening one.
+pub fn on_closing_typed(file: &SourceFileNode, offset: TextUnit) -> Option<LocalEdit> {
+    let closing_offset = offset - TextUnit::of_char('}');
+    let closing = find_leaf_at_offset(file.syntax(), closing_offset).right_biased()?;
+    let opening = match closing.kind() {
+        R_CURLY => L_CURLY,
+        R_ANGLE => L_ANGLE,
+        _ => return None,
+    };
+    let parent = closing.parent()?;
+    if parent.first_child()?.kind() != opening {
+        return None;
+    }
+    let mut edit = TextEditBuilder::default();
+    if !reindent_line(&mut edit, file, closing, parent) {
+        return None;
+    }
+    Some(LocalEdit {
+        label: "dedent closing delimiter".to_string(),
+        edit: edit.finish(),
+        cursor_position: None,
+        is_snippet: false,
     })
 }
 
-/// Finds the last line in the whitespace
-fn last_line_indent_in_whitespace(ws: &str) -> &str {
-    ws.split('\n').last().unwrap_or("")
+/// If `token` starts a line, changes the indent of the line to the one which
+/// `token` should have given the `anchor` node: one level deeper than the line
+/// of `anchor` for continuations, the same for closing delimiters. Returns
+/// `false` if the indent is fine already.
+fn reindent_line(
+    edit: &mut TextEditBuilder,
+    file: &SourceFileNode,
+    token: SyntaxNodeRef,
+    anchor: SyntaxNodeRef,
+) -> bool {
+    let ws = match token.prev_sibling() {
+        Some(it) if it.kind() == WHITESPACE => it,
+        _ => return false,
+    };
+    let ws_text = ws.leaf_text().unwrap();
+    let indent_start = match ws_text.rfind('\n') {
+        Some(it) => it + 1,
+        None => return false,
+    };
+    let current_indent = &ws_text[indent_start..];
+    let mut target_indent = line_indent(file, anchor);
+    if token.kind() != R_CURLY && token.kind() != R_ANGLE {
+        target_indent.push_str(INDENT);
+    }
+    if current_indent == target_indent {
+        return false;
+    }
+    let indent_range = TextRange::from_to(
+        ws.range().start() + TextUnit::from_usize(indent_start),
+        ws.range().end(),
+    );
+    edit.replace(indent_range, target_indent);
+    true
+}
+
+const INDENT: &str = "    ";
+
+/// The indent of the line on which `node` starts.
+fn line_indent(file: &SourceFileNode, node: SyntaxNodeRef) -> String {
+    for node in node.ancestors() {
+        let prev = match find_leaf_at_offset(file.syntax(), node.range().start()) {
+            LeafAtOffset::Between(prev, _) => prev,
+            // The node starts the file.
+            _ => break,
+        };
+        if prev.kind() != WHITESPACE {
+            continue;
+        }
+        let text = prev.leaf_text().unwrap();
+        if let Some(pos) = text.rfind('\n') {
+            return text[pos + 1..].to_string();
+        }
+    }
+    String::new()
 }
 
 fn remove_newline(
@@ -666,16 +744,57 @@ fn foo() {
         // ");
     }
 
+    fn check_on_typed(
+        f: fn(&SourceFileNode, TextUnit) -> Option<LocalEdit>,
+        before: &str,
+        after: &str,
+    ) {
+        let (offset, before) = extract_offset(before);
+        let file = SourceFileNode::parse(&before);
+        let actual = match f(&file, offset) {
+            Some(result) => result.edit.apply(&before),
+            None => before.to_string(),
+        };
+        assert_eq_text!(after, &actual);
+    }
+
+    #[test]
+    fn test_on_eq_typed_indents_let_continuation() {
+        check_on_typed(
+            on_eq_typed,
+            r"
+fn foo() {
+    let foo
+=<|> 1 + 1;
+}
+",
+            r"
+fn foo() {
+    let foo
+        = 1 + 1;
+}
+",
+        );
+        check_on_typed(
+            on_eq_typed,
+            r"
+fn foo() {
+    let foo
+        =<|> 1 + 1;
+}
+",
+            r"
+fn foo() {
+    let foo
+        = 1 + 1;
+}
+",
+        );
+    }
+
     #[test]
     fn test_on_dot_typed() {
-        fn do_check(before: &str, after: &str) {
-            let (offset, before) = extract_offset(before);
-            let file = SourceFileNode::parse(&before);
-            if let Some(result) = on_eq_typed(&file, offset) {
-                let actual = result.edit.apply(&before);
-                assert_eq_text!(after, &actual);
-            };
-        }
+        let do_check = |before, after| check_on_typed(on_dot_typed, before, after);
         // indent if continuing chain call
         do_check(
             r"
@@ -771,6 +890,109 @@ fn foo() {
         self.child_impl(db, name)
             .
     }
+",
+        );
+
+        // indent relative to the statement, not to the receiver
+        do_check(
+            r"
+fn foo() {
+    let x = foo
+    .<|>
+}
+",
+            r"
+fn foo() {
+    let x = foo
+        .
+}
+",
+        );
+    }
+
+    #[test]
+    fn test_on_dot_typed_moves_cursor() {
+        let (offset, before) = extract_offset("fn foo() {\n    foo\n.<|>\n}\n");
+        let file = SourceFileNode::parse(&before);
+        let result = on_dot_typed(&file, offset).unwrap();
+        let actual = result.edit.apply(&before);
+        let actual = add_cursor(&actual, result.cursor_position.unwrap());
+        assert_eq_text!("fn foo() {\n    foo\n        .<|>\n}\n", &actual);
+    }
+
+    #[test]
+    fn test_on_closing_typed() {
+        let do_check = |before, after| check_on_typed(on_closing_typed, before, after);
+        do_check(
+            r"
+fn foo() {
+    if true {
+        92
+        }<|>
+}
+",
+            r"
+fn foo() {
+    if true {
+        92
+    }
+}
+",
+        );
+        do_check(
+            r"
+fn foo() {
+    foo(|x| {
+        x
+}<|>)
+}
+",
+            r"
+fn foo() {
+    foo(|x| {
+        x
+    })
+}
+",
+        );
+        do_check(
+            r"
+struct Foo<
+    T,
+    ><|>;
+",
+            r"
+struct Foo<
+    T,
+>;
+",
+        );
+        // not at the start of a line
+        do_check(
+            r"
+fn foo() {
+    if true { 92 }<|>
+}
+",
+            r"
+fn foo() {
+    if true { 92 }
+}
+",
+        );
+        // comparisons are not delimiters
+        do_check(
+            r"
+fn foo() {
+    1
+        ><|> 2
+}
+",
+            r"
+fn foo() {
+    1
+        > 2
+}
 ",
         );
     }
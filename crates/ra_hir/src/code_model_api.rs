@@ -1,6 +1,6 @@
 use relative_path::RelativePathBuf;
-use ra_db::{CrateId, Cancelable, FileId};
-use ra_syntax::{ast, SyntaxNode};
+use ra_db::{CrateId, Cancelable, FileId, Edition};
+use ra_syntax::{ast, SmolStr, SyntaxNode};
 
 use crate::{Name, db::HirDatabase, DefId, Path, PerNs, nameres::ModuleScope};
 
@@ -16,12 +16,28 @@ pub struct Crate {
 pub struct CrateDependency {
     pub krate: Crate,
     pub name: Name,
+    /// Visible only to the `#[cfg(test)]` modules.
+    pub is_dev: bool,
 }
 
 impl Crate {
     pub fn crate_id(&self) -> CrateId {
         self.crate_id
     }
+    /// The name of the crate to show to the user. Note that crates are
+    /// referred to by dependency names, which might differ from this one.
+    pub fn display_name(&self, db: &impl HirDatabase) -> Option<SmolStr> {
+        db.crate_graph().display_name(self.crate_id).cloned()
+    }
+    /// The edition of the crate, which affects keywords and path resolution.
+    pub fn edition(&self, db: &impl HirDatabase) -> Edition {
+        db.crate_graph().edition(self.crate_id)
+    }
+    /// Procedural macro crates only export macros, so their items can't be
+    /// referred to from the dependent crates.
+    pub fn is_proc_macro(&self, db: &impl HirDatabase) -> bool {
+        db.crate_graph().is_proc_macro(self.crate_id)
+    }
     pub fn dependencies(&self, db: &impl HirDatabase) -> Cancelable<Vec<CrateDependency>> {
         Ok(self.dependencies_impl(db))
     }
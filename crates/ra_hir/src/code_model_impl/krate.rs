@@ -16,7 +16,11 @@ impl Crate {
             .map(|dep| {
                 let krate = Crate::new(dep.crate_id());
                 let name = dep.as_name();
-                CrateDependency { krate, name }
+                CrateDependency {
+                    krate,
+                    name,
+                    is_dev: dep.is_dev,
+                }
             })
             .collect()
     }
@@ -126,7 +126,8 @@ impl Module {
         );
 
         let segments = &path.segments;
-        for name in segments.iter() {
+        for segment in segments.iter() {
+            let name = &segment.name;
             let curr = if let Some(r) = curr_per_ns.as_ref().take_types() {
                 r
             } else {
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use ra_db::Cancelable;
+use ra_syntax::ast::{self, AstNode};
+
+use crate::{
+    DefId, DefKind, HirDatabase, Module,
+    ty::InferenceResult,
+    expr::{Body, BodySyntaxMapping, BodyParentMap},
+    type_ref::TypeRef,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Const {
+    def_id: DefId,
+}
+
+impl Const {
+    pub(crate) fn new(def_id: DefId) -> Const {
+        Const { def_id }
+    }
+
+    pub fn def_id(&self) -> DefId {
+        self.def_id
+    }
+
+    pub fn syntax(&self, db: &impl HirDatabase) -> ast::ConstDefNode {
+        let def_loc = self.def_id.loc(db);
+        assert!(def_loc.kind == DefKind::Const);
+        let syntax = db.file_item(def_loc.source_item_id);
+        ast::ConstDef::cast(syntax.borrowed()).unwrap().owned()
+    }
+
+    /// The declared type of the constant.
+    pub fn type_ref(&self, db: &impl HirDatabase) -> TypeRef {
+        TypeRef::from_ast_opt(self.syntax(db).borrowed().type_ref())
+    }
+
+    pub fn body(&self, db: &impl HirDatabase) -> Cancelable<Arc<Body>> {
+        db.body_hir(self.def_id)
+    }
+
+    pub fn body_syntax_mapping(&self, db: &impl HirDatabase) -> Cancelable<Arc<BodySyntaxMapping>> {
+        db.body_syntax_mapping(self.def_id)
+    }
+
+    pub fn body_parent_map(&self, db: &impl HirDatabase) -> Cancelable<Arc<BodyParentMap>> {
+        db.body_parent_map(self.def_id)
+    }
+
+    pub fn infer(&self, db: &impl HirDatabase) -> Cancelable<Arc<InferenceResult>> {
+        db.infer(self.def_id)
+    }
+
+    pub fn module(&self, db: &impl HirDatabase) -> Cancelable<Module> {
+        self.def_id.module(db)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Static {
+    def_id: DefId,
+}
+
+impl Static {
+    pub(crate) fn new(def_id: DefId) -> Static {
+        Static { def_id }
+    }
+
+    pub fn def_id(&self) -> DefId {
+        self.def_id
+    }
+
+    pub fn syntax(&self, db: &impl HirDatabase) -> ast::StaticDefNode {
+        let def_loc = self.def_id.loc(db);
+        assert!(def_loc.kind == DefKind::Static);
+        let syntax = db.file_item(def_loc.source_item_id);
+        ast::StaticDef::cast(syntax.borrowed()).unwrap().owned()
+    }
+
+    /// The declared type of the static.
+    pub fn type_ref(&self, db: &impl HirDatabase) -> TypeRef {
+        TypeRef::from_ast_opt(self.syntax(db).borrowed().type_ref())
+    }
+
+    pub fn body(&self, db: &impl HirDatabase) -> Cancelable<Arc<Body>> {
+        db.body_hir(self.def_id)
+    }
+
+    pub fn body_syntax_mapping(&self, db: &impl HirDatabase) -> Cancelable<Arc<BodySyntaxMapping>> {
+        db.body_syntax_mapping(self.def_id)
+    }
+
+    pub fn body_parent_map(&self, db: &impl HirDatabase) -> Cancelable<Arc<BodyParentMap>> {
+        db.body_parent_map(self.def_id)
+    }
+
+    pub fn infer(&self, db: &impl HirDatabase) -> Cancelable<Arc<InferenceResult>> {
+        db.infer(self.def_id)
+    }
+
+    pub fn module(&self, db: &impl HirDatabase) -> Cancelable<Module> {
+        self.def_id.module(db)
+    }
+}
@@ -4,16 +4,18 @@ use ra_syntax::{SyntaxNode, SourceFileNode};
 use ra_db::{SourceRootId, LocationIntener, SyntaxDatabase, Cancelable};
 
 use crate::{
-    DefLoc, DefId, MacroCallLoc, MacroCallId, Name, HirFileId,
+    DefLoc, DefId, MacroCallLoc, MacroCallId, Name, HirFileId, Crate,
     SourceFileItems, SourceItemId,
     query_definitions,
-    FnSignature, FnScopes,
+    FnSignature, ExprScopes,
     macros::MacroExpansion,
     module_tree::{ModuleId, ModuleTree},
     nameres::{ItemMap, InputModuleItems},
-    ty::{InferenceResult, Ty},
+    ty::{InferenceResult, Ty, method_resolution::CrateImplBlocks},
     adt::{StructData, EnumData},
+    traits::TraitData,
     impl_block::ModuleImplBlocks,
+    proc_macro::ProcMacroExpander,
 };
 
 salsa::query_group! {
@@ -21,6 +23,7 @@ salsa::query_group! {
 pub trait HirDatabase: SyntaxDatabase
     + AsRef<LocationIntener<DefLoc, DefId>>
     + AsRef<LocationIntener<MacroCallLoc, MacroCallId>>
+    + AsRef<dyn ProcMacroExpander>
 {
     fn hir_source_file(file_id: HirFileId) -> SourceFileNode {
         type HirSourceFileQuery;
@@ -31,9 +34,9 @@ pub trait HirDatabase: SyntaxDatabase
         use fn crate::macros::expand_macro_invocation;
     }
 
-    fn fn_scopes(def_id: DefId) -> Cancelable<Arc<FnScopes>> {
-        type FnScopesQuery;
-        use fn query_definitions::fn_scopes;
+    fn expr_scopes(def_id: DefId) -> Cancelable<Arc<ExprScopes>> {
+        type ExprScopesQuery;
+        use fn query_definitions::expr_scopes;
     }
 
     fn struct_data(def_id: DefId) -> Cancelable<Arc<StructData>> {
@@ -46,6 +49,11 @@ pub trait HirDatabase: SyntaxDatabase
         use fn query_definitions::enum_data;
     }
 
+    fn trait_data(def_id: DefId) -> Cancelable<Arc<TraitData>> {
+        type TraitDataQuery;
+        use fn crate::traits::trait_data;
+    }
+
     fn infer(def_id: DefId) -> Cancelable<Arc<InferenceResult>> {
         type InferQuery;
         use fn crate::ty::infer;
@@ -94,6 +102,11 @@ pub trait HirDatabase: SyntaxDatabase
         use fn crate::impl_block::impls_in_module;
     }
 
+    fn impls_in_crate(krate: Crate) -> Cancelable<Arc<CrateImplBlocks>> {
+        type ImplsInCrateQuery;
+        use fn crate::ty::method_resolution::impls_in_crate;
+    }
+
     fn body_hir(def_id: DefId) -> Cancelable<Arc<crate::expr::Body>> {
         type BodyHirQuery;
         use fn crate::expr::body_hir;
@@ -104,6 +117,16 @@ pub trait HirDatabase: SyntaxDatabase
         use fn crate::expr::body_syntax_mapping;
     }
 
+    fn body_parent_map(def_id: DefId) -> Cancelable<Arc<crate::expr::BodyParentMap>> {
+        type BodyParentMapQuery;
+        use fn crate::expr::body_parent_map;
+    }
+
+    fn validate_body(def_id: DefId) -> Cancelable<Arc<Vec<(crate::expr::ExprId, crate::expr::BodyProblem)>>> {
+        type ValidateBodyQuery;
+        use fn crate::expr::validate_body;
+    }
+
     fn fn_signature(def_id: DefId) -> Arc<FnSignature> {
         type FnSignatureQuery;
         use fn crate::function::fn_signature;
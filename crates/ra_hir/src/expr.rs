@@ -1,13 +1,22 @@
+mod validation;
+
 use std::ops::Index;
 use std::sync::Arc;
 
 use rustc_hash::FxHashMap;
 
 use ra_arena::{Arena, RawId, impl_arena_id, map::ArenaMap};
-use ra_db::{LocalSyntaxPtr, Cancelable};
-use ra_syntax::ast::{self, AstNode, LoopBodyOwner, ArgListOwner, NameOwner};
+use ra_db::{LocalSyntaxPtr, Cancelable, QueryTimer};
+use ra_syntax::{
+    SourceFileNode, TextRange, TextUnit,
+    SyntaxKind::*,
+    ast::{self, AstNode, LoopBodyOwner, ArgListOwner, NameOwner},
+};
 
-use crate::{Path, type_ref::{Mutability, TypeRef}, Name, HirDatabase, DefId, Def, name::AsName};
+use crate::{
+    Path, GenericArgs, type_ref::{Mutability, TypeRef}, Name, HirDatabase, DefId, Def, name::AsName,
+    ty::primitive::{UncertainIntTy, UncertainFloatTy, UintTy},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExprId(RawId);
@@ -27,6 +36,10 @@ pub struct Body {
     args: Vec<PatId>,
     /// The `ExprId` of the actual body expression.
     body_expr: ExprId,
+    /// The loops which `break` and `continue` expressions refer to.
+    loop_targets: ArenaMap<ExprId, ExprId>,
+    /// The values of the well-formed literals.
+    literal_values: ArenaMap<ExprId, LiteralValue>,
 }
 
 /// An item body together with the mapping from syntax nodes to HIR expression
@@ -42,6 +55,10 @@ pub struct BodySyntaxMapping {
     expr_syntax_mapping_back: ArenaMap<ExprId, LocalSyntaxPtr>,
     pat_syntax_mapping: FxHashMap<LocalSyntaxPtr, PatId>,
     pat_syntax_mapping_back: ArenaMap<PatId, LocalSyntaxPtr>,
+    /// The fields of struct literals, as the struct literal and the index of
+    /// the field in it.
+    field_syntax_mapping: FxHashMap<LocalSyntaxPtr, (ExprId, usize)>,
+    field_syntax_mapping_back: FxHashMap<(ExprId, usize), LocalSyntaxPtr>,
 }
 
 impl Body {
@@ -52,6 +69,17 @@ impl Body {
     pub fn body_expr(&self) -> ExprId {
         self.body_expr
     }
+
+    /// The loop which the `break` or `continue` expression refers to, if it's
+    /// inside of one.
+    pub fn loop_target(&self, expr: ExprId) -> Option<ExprId> {
+        self.loop_targets.get(expr).cloned()
+    }
+
+    /// The value of the literal expression, if it's well-formed.
+    pub fn literal_value(&self, expr: ExprId) -> Option<&LiteralValue> {
+        self.literal_values.get(expr)
+    }
 }
 
 impl Index<ExprId> for Body {
@@ -93,12 +121,66 @@ impl BodySyntaxMapping {
             .get(&LocalSyntaxPtr::new(node.syntax()))
             .cloned()
     }
+    /// The syntax of the field with the index `field_idx` of the struct
+    /// literal `expr`.
+    pub fn field_syntax(&self, expr: ExprId, field_idx: usize) -> Option<LocalSyntaxPtr> {
+        self.field_syntax_mapping_back
+            .get(&(expr, field_idx))
+            .cloned()
+    }
+    pub fn syntax_field(&self, ptr: LocalSyntaxPtr) -> Option<(ExprId, usize)> {
+        self.field_syntax_mapping.get(&ptr).cloned()
+    }
+    pub fn node_field(&self, node: ast::NamedField) -> Option<(ExprId, usize)> {
+        self.field_syntax_mapping
+            .get(&LocalSyntaxPtr::new(node.syntax()))
+            .cloned()
+    }
 
     pub fn body(&self) -> &Arc<Body> {
         &self.body
     }
 }
 
+/// The expression or pattern which directly contains an expression or a
+/// pattern of a body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyParent {
+    Expr(ExprId),
+    Pat(PatId),
+}
+
+/// The parents of the expressions and patterns of a body. The body expression
+/// and the patterns of the function's arguments have no parent.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct BodyParentMap {
+    expr_parents: ArenaMap<ExprId, BodyParent>,
+    pat_parents: ArenaMap<PatId, BodyParent>,
+}
+
+impl BodyParentMap {
+    pub(crate) fn new(body: &Body) -> BodyParentMap {
+        let mut res = BodyParentMap::default();
+        for (id, expr) in body.exprs.iter() {
+            expr.walk_child_exprs(|it| res.expr_parents.insert(it, BodyParent::Expr(id)));
+            expr.walk_child_pats(|it| res.pat_parents.insert(it, BodyParent::Expr(id)));
+        }
+        for (id, pat) in body.pats.iter() {
+            pat.walk_child_exprs(|it| res.expr_parents.insert(it, BodyParent::Pat(id)));
+            pat.walk_child_pats(|it| res.pat_parents.insert(it, BodyParent::Pat(id)));
+        }
+        res
+    }
+
+    pub fn parent_expr(&self, expr: ExprId) -> Option<BodyParent> {
+        self.expr_parents.get(expr).cloned()
+    }
+
+    pub fn parent_pat(&self, pat: PatId) -> Option<BodyParent> {
+        self.pat_parents.get(pat).cloned()
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Expr {
     /// This is produced if syntax tree does not have a required expression piece.
@@ -115,15 +197,18 @@ pub enum Expr {
     },
     Loop {
         body: ExprId,
+        label: Option<Name>,
     },
     While {
         condition: ExprId,
         body: ExprId,
+        label: Option<Name>,
     },
     For {
         iterable: ExprId,
         pat: PatId,
         body: ExprId,
+        label: Option<Name>,
     },
     Call {
         callee: ExprId,
@@ -133,14 +218,18 @@ pub enum Expr {
         receiver: ExprId,
         method_name: Name,
         args: Vec<ExprId>,
+        generic_args: Option<GenericArgs>,
     },
     Match {
         expr: ExprId,
         arms: Vec<MatchArm>,
     },
-    Continue,
+    Continue {
+        label: Option<Name>,
+    },
     Break {
         expr: Option<ExprId>,
+        label: Option<Name>,
     },
     Return {
         expr: Option<ExprId>,
@@ -157,6 +246,17 @@ pub enum Expr {
     Try {
         expr: ExprId,
     },
+    /// `async { ... }`, the body is the block.
+    Async {
+        body: ExprId,
+    },
+    Await {
+        expr: ExprId,
+    },
+    /// `box expr`
+    Box {
+        expr: ExprId,
+    },
     Cast {
         expr: ExprId,
         type_ref: TypeRef,
@@ -177,17 +277,78 @@ pub enum Expr {
     Lambda {
         args: Vec<PatId>,
         arg_types: Vec<Option<TypeRef>>,
+        ret_type: Option<TypeRef>,
         body: ExprId,
+        is_move: bool,
+    },
+    Index {
+        base: ExprId,
+        index: ExprId,
+    },
+    /// `lhs..rhs` or `lhs..=rhs`, both sides may be missing.
+    Range {
+        lhs: Option<ExprId>,
+        rhs: Option<ExprId>,
+        inclusive: bool,
+    },
+    Literal(Literal),
+    Array(Array),
+    Tuple {
+        exprs: Vec<ExprId>,
+    },
+    /// A macro call, which is not expanded. The arguments are the expressions
+    /// found in its token tree, if it can be parsed as a list of expressions.
+    MacroCall {
+        path: Option<Path>,
+        args: Vec<ExprId>,
     },
 }
 
 pub use ra_syntax::ast::PrefixOp as UnaryOp;
 pub use ra_syntax::ast::BinOp as BinaryOp;
+pub use self::validation::BodyProblem;
+pub(crate) use self::validation::validate_body;
+
+/// A literal. The values don't affect the types, so they are stored separately
+/// in the `Body`, see `LiteralValue`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Literal {
+    String,
+    ByteString,
+    Char,
+    Bool(bool),
+    /// Also byte literals, which are `u8`.
+    Int(UncertainIntTy),
+    Float(UncertainFloatTy),
+}
+
+/// The value of a literal: strings and chars are unescaped, and integers are
+/// parsed with their radix.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LiteralValue {
+    String(String),
+    ByteString(Vec<u8>),
+    Char(char),
+    Bool(bool),
+    /// Also byte literals.
+    Int(u128),
+    /// Floats are kept as the text without the suffix and the underscores,
+    /// which is a valid input of `f64::from_str`, as `f64` is not `Eq`.
+    Float(String),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Array {
+    /// `[a, b, c]`
+    ElementList(Vec<ExprId>),
+    /// `[initializer; repeat]`
+    Repeat { initializer: ExprId, repeat: ExprId },
+}
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct MatchArm {
     pub pats: Vec<PatId>,
-    // guard: Option<ExprId>, // TODO
+    pub guard: Option<ExprId>,
     pub expr: ExprId,
 }
 
@@ -212,6 +373,7 @@ impl Expr {
         match self {
             Expr::Missing => {}
             Expr::Path(_) => {}
+            Expr::Literal(_) => {}
             Expr::If {
                 condition,
                 then_branch,
@@ -238,8 +400,10 @@ impl Expr {
                     f(*expr);
                 }
             }
-            Expr::Loop { body } => f(*body),
-            Expr::While { condition, body } => {
+            Expr::Loop { body, .. } => f(*body),
+            Expr::While {
+                condition, body, ..
+            } => {
                 f(*condition);
                 f(*body);
             }
@@ -262,11 +426,14 @@ impl Expr {
             Expr::Match { expr, arms } => {
                 f(*expr);
                 for arm in arms {
+                    if let Some(guard) = arm.guard {
+                        f(guard);
+                    }
                     f(arm.expr);
                 }
             }
-            Expr::Continue => {}
-            Expr::Break { expr } | Expr::Return { expr } => {
+            Expr::Continue { .. } => {}
+            Expr::Break { expr, .. } | Expr::Return { expr } => {
                 if let Some(expr) = expr {
                     f(*expr);
                 }
@@ -279,15 +446,46 @@ impl Expr {
                     f(*expr);
                 }
             }
-            Expr::Lambda { body, .. } => {
+            Expr::Lambda { body, .. } | Expr::Async { body } => {
                 f(*body);
             }
+            Expr::MacroCall { args, .. } => {
+                for arg in args {
+                    f(*arg);
+                }
+            }
+            Expr::Array(Array::ElementList(exprs)) | Expr::Tuple { exprs } => {
+                for expr in exprs {
+                    f(*expr);
+                }
+            }
+            Expr::Array(Array::Repeat {
+                initializer,
+                repeat,
+            }) => {
+                f(*initializer);
+                f(*repeat);
+            }
             Expr::BinaryOp { lhs, rhs, .. } => {
                 f(*lhs);
                 f(*rhs);
             }
+            Expr::Index { base, index } => {
+                f(*base);
+                f(*index);
+            }
+            Expr::Range { lhs, rhs, .. } => {
+                if let Some(lhs) = lhs {
+                    f(*lhs);
+                }
+                if let Some(rhs) = rhs {
+                    f(*rhs);
+                }
+            }
             Expr::Field { expr, .. }
             | Expr::Try { expr }
+            | Expr::Await { expr }
+            | Expr::Box { expr }
             | Expr::Cast { expr, .. }
             | Expr::Ref { expr, .. }
             | Expr::UnaryOp { expr, .. } => {
@@ -295,6 +493,28 @@ impl Expr {
             }
         }
     }
+
+    /// Walks the patterns which are direct children of the expression, like
+    /// the patterns of `let` statements or of match arms.
+    pub fn walk_child_pats(&self, mut f: impl FnMut(PatId)) {
+        match self {
+            Expr::Block { statements, .. } => {
+                for stmt in statements {
+                    if let Statement::Let { pat, .. } = stmt {
+                        f(*pat);
+                    }
+                }
+            }
+            Expr::For { pat, .. } => f(*pat),
+            Expr::Match { arms, .. } => {
+                for arm in arms {
+                    arm.pats.iter().cloned().for_each(&mut f);
+                }
+            }
+            Expr::Lambda { args, .. } => args.iter().cloned().for_each(f),
+            _ => {}
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -303,23 +523,124 @@ impl_arena_id!(PatId);
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Pat {
+    /// This is produced if syntax tree does not have a required pattern piece.
     Missing,
+    /// `_`, or the `..` of a slice pattern.
+    Wild,
     Bind {
         name: Name,
+        mode: BindingAnnotation,
+        /// The `subpat` of `name @ subpat`.
+        subpat: Option<PatId>,
     },
+    /// A unit struct, unit variant or constant, like `Option::None`. A single
+    /// identifier is parsed as a `Bind`, as it's only known to refer to an
+    /// item once it's resolved.
+    Path(Path),
     TupleStruct {
         path: Option<Path>,
         args: Vec<PatId>,
     },
+    Tuple {
+        args: Vec<PatId>,
+        /// The number of patterns before the `..`, if there is one.
+        ellipsis: Option<usize>,
+    },
+    /// `&pat` or `&mut pat`
+    Ref { pat: PatId, mutability: Mutability },
+    Struct {
+        path: Option<Path>,
+        fields: Vec<FieldPat>,
+        /// Whether the pattern ends with `..`.
+        ellipsis: bool,
+    },
+    /// `[prefix.., slice @ .., suffix..]`, everything is in the prefix if
+    /// there's no `..`.
+    Slice {
+        prefix: Vec<PatId>,
+        slice: Option<PatId>,
+        suffix: Vec<PatId>,
+    },
+    /// A literal like `1` or `-1`, as an expression.
+    Literal(ExprId),
+    /// `box pat`
+    Box { inner: PatId },
+    /// `start..=end`, the ends are literal or path expressions.
+    Range {
+        start: ExprId,
+        end: ExprId,
+        inclusive: bool,
+    },
+}
+
+/// The `ref` and `mut` annotations of a binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindingAnnotation {
+    /// No annotation, the binding moves or copies the value.
+    Unannotated,
+    /// `mut x`
+    Mutable,
+    /// `ref x`
+    Ref,
+    /// `ref mut x`
+    RefMut,
+}
+
+impl BindingAnnotation {
+    fn new(is_mutable: bool, is_ref: bool) -> Self {
+        match (is_mutable, is_ref) {
+            (false, false) => BindingAnnotation::Unannotated,
+            (true, false) => BindingAnnotation::Mutable,
+            (false, true) => BindingAnnotation::Ref,
+            (true, true) => BindingAnnotation::RefMut,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FieldPat {
+    pub name: Name,
+    pub pat: PatId,
 }
 
 impl Pat {
-    pub fn walk_child_pats(&self, f: impl FnMut(PatId)) {
+    pub fn walk_child_pats(&self, mut f: impl FnMut(PatId)) {
         match self {
-            Pat::Missing | Pat::Bind { .. } => {}
-            Pat::TupleStruct { args, .. } => {
+            Pat::Missing | Pat::Wild | Pat::Path(_) | Pat::Literal(_) | Pat::Range { .. } => {}
+            Pat::Bind { subpat, .. } => {
+                subpat.iter().cloned().for_each(f);
+            }
+            Pat::Box { inner } | Pat::Ref { pat: inner, .. } => f(*inner),
+            Pat::TupleStruct { args, .. } | Pat::Tuple { args, .. } => {
                 args.iter().map(|pat| *pat).for_each(f);
             }
+            Pat::Struct { fields, .. } => {
+                fields.iter().map(|field| field.pat).for_each(f);
+            }
+            Pat::Slice {
+                prefix,
+                slice,
+                suffix,
+            } => {
+                prefix
+                    .iter()
+                    .chain(slice)
+                    .chain(suffix)
+                    .cloned()
+                    .for_each(f);
+            }
+        }
+    }
+
+    /// Walks the expressions of literal and range patterns.
+    pub fn walk_child_exprs(&self, mut f: impl FnMut(ExprId)) {
+        match self {
+            Pat::Literal(expr) => f(*expr),
+            Pat::Range { start, end, .. } => {
+                f(*start);
+                f(*end);
+            }
+            _ => {}
         }
     }
 }
@@ -337,6 +658,13 @@ struct ExprCollector {
     expr_syntax_mapping_back: ArenaMap<ExprId, LocalSyntaxPtr>,
     pat_syntax_mapping: FxHashMap<LocalSyntaxPtr, PatId>,
     pat_syntax_mapping_back: ArenaMap<PatId, LocalSyntaxPtr>,
+    field_syntax_mapping: FxHashMap<LocalSyntaxPtr, (ExprId, usize)>,
+    field_syntax_mapping_back: FxHashMap<(ExprId, usize), LocalSyntaxPtr>,
+    /// Set while lowering the arguments of a macro call, which are parsed
+    /// separately: the start of the arguments in the reparsed text and in
+    /// the original source.
+    macro_args_offset: Option<(TextUnit, TextUnit)>,
+    literal_values: ArenaMap<ExprId, LiteralValue>,
 }
 
 impl ExprCollector {
@@ -348,10 +676,15 @@ impl ExprCollector {
             expr_syntax_mapping_back: ArenaMap::default(),
             pat_syntax_mapping: FxHashMap::default(),
             pat_syntax_mapping_back: ArenaMap::default(),
+            field_syntax_mapping: FxHashMap::default(),
+            field_syntax_mapping_back: FxHashMap::default(),
+            macro_args_offset: None,
+            literal_values: ArenaMap::default(),
         }
     }
 
     fn alloc_expr(&mut self, expr: Expr, syntax_ptr: LocalSyntaxPtr) -> ExprId {
+        let syntax_ptr = self.map_syntax_ptr(syntax_ptr);
         let id = self.exprs.alloc(expr);
         self.expr_syntax_mapping.insert(syntax_ptr, id);
         self.expr_syntax_mapping_back.insert(id, syntax_ptr);
@@ -359,12 +692,28 @@ impl ExprCollector {
     }
 
     fn alloc_pat(&mut self, pat: Pat, syntax_ptr: LocalSyntaxPtr) -> PatId {
+        let syntax_ptr = self.map_syntax_ptr(syntax_ptr);
         let id = self.pats.alloc(pat);
         self.pat_syntax_mapping.insert(syntax_ptr, id);
         self.pat_syntax_mapping_back.insert(id, syntax_ptr);
         id
     }
 
+    /// Maps the syntax of the reparsed macro arguments back to the source.
+    fn map_syntax_ptr(&self, syntax_ptr: LocalSyntaxPtr) -> LocalSyntaxPtr {
+        match self.macro_args_offset {
+            Some((parsed_start, source_start)) => {
+                let range = syntax_ptr.range();
+                let start = range.start() - parsed_start + source_start;
+                LocalSyntaxPtr::from_parts(
+                    TextRange::offset_len(start, range.len()),
+                    syntax_ptr.kind(),
+                )
+            }
+            None => syntax_ptr,
+        }
+    }
+
     fn empty_block(&mut self) -> ExprId {
         let block = Expr::Block {
             statements: Vec::new(),
@@ -373,6 +722,13 @@ impl ExprCollector {
         self.exprs.alloc(block)
     }
 
+    fn collect_else_branch(&mut self, else_branch: ast::ElseBranch) -> ExprId {
+        match else_branch {
+            ast::ElseBranch::Block(block) => self.collect_block(block),
+            ast::ElseBranch::IfExpr(e) => self.collect_expr(ast::Expr::IfExpr(e)),
+        }
+    }
+
     fn collect_expr(&mut self, expr: ast::Expr) -> ExprId {
         let syntax_ptr = LocalSyntaxPtr::new(expr.syntax());
         match expr {
@@ -385,16 +741,18 @@ impl ExprCollector {
                     let then_branch = self.collect_block_opt(e.then_branch());
                     let else_branch = e
                         .else_branch()
-                        .map(|e| self.collect_block(e))
+                        .map(|b| self.collect_else_branch(b))
                         .unwrap_or_else(|| self.empty_block());
-                    let placeholder_pat = self.pats.alloc(Pat::Missing);
+                    let placeholder_pat = self.pats.alloc(Pat::Wild);
                     let arms = vec![
                         MatchArm {
                             pats: vec![pat],
+                            guard: None,
                             expr: then_branch,
                         },
                         MatchArm {
                             pats: vec![placeholder_pat],
+                            guard: None,
                             expr: else_branch,
                         },
                     ];
@@ -408,7 +766,7 @@ impl ExprCollector {
                 } else {
                     let condition = self.collect_expr_opt(e.condition().and_then(|c| c.expr()));
                     let then_branch = self.collect_block_opt(e.then_branch());
-                    let else_branch = e.else_branch().map(|e| self.collect_block(e));
+                    let else_branch = e.else_branch().map(|b| self.collect_else_branch(b));
                     self.alloc_expr(
                         Expr::If {
                             condition,
@@ -419,10 +777,18 @@ impl ExprCollector {
                     )
                 }
             }
-            ast::Expr::BlockExpr(e) => self.collect_block_opt(e.block()),
+            ast::Expr::BlockExpr(e) => {
+                let body = self.collect_block_opt(e.block());
+                if e.is_async() {
+                    self.alloc_expr(Expr::Async { body }, syntax_ptr)
+                } else {
+                    body
+                }
+            }
             ast::Expr::LoopExpr(e) => {
                 let body = self.collect_block_opt(e.loop_body());
-                self.alloc_expr(Expr::Loop { body }, syntax_ptr)
+                let label = loop_label(e);
+                self.alloc_expr(Expr::Loop { body, label }, syntax_ptr)
             }
             ast::Expr::WhileExpr(e) => {
                 let condition = if let Some(condition) = e.condition() {
@@ -436,17 +802,27 @@ impl ExprCollector {
                     self.exprs.alloc(Expr::Missing)
                 };
                 let body = self.collect_block_opt(e.loop_body());
-                self.alloc_expr(Expr::While { condition, body }, syntax_ptr)
+                let label = loop_label(e);
+                self.alloc_expr(
+                    Expr::While {
+                        condition,
+                        body,
+                        label,
+                    },
+                    syntax_ptr,
+                )
             }
             ast::Expr::ForExpr(e) => {
                 let iterable = self.collect_expr_opt(e.iterable());
                 let pat = self.collect_pat_opt(e.pat());
                 let body = self.collect_block_opt(e.loop_body());
+                let label = loop_label(e);
                 self.alloc_expr(
                     Expr::For {
                         iterable,
                         pat,
                         body,
+                        label,
                     },
                     syntax_ptr,
                 )
@@ -471,11 +847,13 @@ impl ExprCollector {
                     .name_ref()
                     .map(|nr| nr.as_name())
                     .unwrap_or_else(Name::missing);
+                let generic_args = e.type_arg_list().and_then(GenericArgs::from_ast);
                 self.alloc_expr(
                     Expr::MethodCall {
                         receiver,
                         method_name,
                         args,
+                        generic_args,
                     },
                     syntax_ptr,
                 )
@@ -487,6 +865,7 @@ impl ExprCollector {
                         .arms()
                         .map(|arm| MatchArm {
                             pats: arm.pats().map(|p| self.collect_pat(p)).collect(),
+                            guard: arm.guard().map(|guard| self.collect_expr_opt(guard.expr())),
                             expr: self.collect_expr_opt(arm.expr()),
                         })
                         .collect()
@@ -503,13 +882,14 @@ impl ExprCollector {
                     .unwrap_or(Expr::Missing);
                 self.alloc_expr(path, syntax_ptr)
             }
-            ast::Expr::ContinueExpr(_e) => {
-                // TODO: labels
-                self.alloc_expr(Expr::Continue, syntax_ptr)
+            ast::Expr::ContinueExpr(e) => {
+                let label = e.lifetime().map(|it| it.as_name());
+                self.alloc_expr(Expr::Continue { label }, syntax_ptr)
             }
             ast::Expr::BreakExpr(e) => {
                 let expr = e.expr().map(|e| self.collect_expr(e));
-                self.alloc_expr(Expr::Break { expr }, syntax_ptr)
+                let label = e.lifetime().map(|it| it.as_name());
+                self.alloc_expr(Expr::Break { expr, label }, syntax_ptr)
             }
             ast::Expr::ParenExpr(e) => {
                 let inner = self.collect_expr_opt(e.expr());
@@ -523,53 +903,70 @@ impl ExprCollector {
             }
             ast::Expr::StructLit(e) => {
                 let path = e.path().and_then(Path::from_ast);
+                let mut field_ptrs = Vec::new();
                 let fields = if let Some(nfl) = e.named_field_list() {
                     nfl.fields()
-                        .map(|field| StructLitField {
-                            name: field
-                                .name_ref()
-                                .map(|nr| nr.as_name())
-                                .unwrap_or_else(Name::missing),
-                            expr: if let Some(e) = field.expr() {
-                                self.collect_expr(e)
-                            } else if let Some(nr) = field.name_ref() {
-                                // field shorthand
-                                let id = self.exprs.alloc(Expr::Path(Path::from_name_ref(nr)));
-                                self.expr_syntax_mapping
-                                    .insert(LocalSyntaxPtr::new(nr.syntax()), id);
-                                self.expr_syntax_mapping_back
-                                    .insert(id, LocalSyntaxPtr::new(nr.syntax()));
-                                id
-                            } else {
-                                self.exprs.alloc(Expr::Missing)
-                            },
+                        .map(|field| {
+                            field_ptrs.push(LocalSyntaxPtr::new(field.syntax()));
+                            StructLitField {
+                                name: field
+                                    .name_ref()
+                                    .map(|nr| nr.as_name())
+                                    .unwrap_or_else(Name::missing),
+                                expr: if let Some(e) = field.expr() {
+                                    self.collect_expr(e)
+                                } else if let Some(nr) = field.name_ref() {
+                                    // field shorthand
+                                    self.alloc_expr(
+                                        Expr::Path(Path::from_name_ref(nr)),
+                                        LocalSyntaxPtr::new(nr.syntax()),
+                                    )
+                                } else {
+                                    self.exprs.alloc(Expr::Missing)
+                                },
+                            }
                         })
                         .collect()
                 } else {
                     Vec::new()
                 };
                 let spread = e.spread().map(|s| self.collect_expr(s));
-                self.alloc_expr(
+                let id = self.alloc_expr(
                     Expr::StructLit {
                         path,
                         fields,
                         spread,
                     },
                     syntax_ptr,
-                )
+                );
+                for (idx, ptr) in field_ptrs.into_iter().enumerate() {
+                    let ptr = self.map_syntax_ptr(ptr);
+                    self.field_syntax_mapping.insert(ptr, (id, idx));
+                    self.field_syntax_mapping_back.insert((id, idx), ptr);
+                }
+                id
             }
             ast::Expr::FieldExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
-                let name = e
-                    .name_ref()
-                    .map(|nr| nr.as_name())
-                    .unwrap_or_else(Name::missing);
+                let name = match (e.name_ref(), e.index()) {
+                    (Some(name_ref), _) => name_ref.as_name(),
+                    (None, Some(index)) => Name::tuple_field_name(index),
+                    (None, None) => Name::missing(),
+                };
                 self.alloc_expr(Expr::Field { expr, name }, syntax_ptr)
             }
             ast::Expr::TryExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
                 self.alloc_expr(Expr::Try { expr }, syntax_ptr)
             }
+            ast::Expr::AwaitExpr(e) => {
+                let expr = self.collect_expr_opt(e.expr());
+                self.alloc_expr(Expr::Await { expr }, syntax_ptr)
+            }
+            ast::Expr::BoxExpr(e) => {
+                let expr = self.collect_expr_opt(e.expr());
+                self.alloc_expr(Expr::Box { expr }, syntax_ptr)
+            }
             ast::Expr::CastExpr(e) => {
                 let expr = self.collect_expr_opt(e.expr());
                 let type_ref = TypeRef::from_ast_opt(e.type_ref());
@@ -596,12 +993,18 @@ impl ExprCollector {
                         arg_types.push(type_ref);
                     }
                 }
+                let ret_type = e
+                    .ret_type()
+                    .and_then(|rt| rt.type_ref())
+                    .map(TypeRef::from_ast);
                 let body = self.collect_expr_opt(e.body());
                 self.alloc_expr(
                     Expr::Lambda {
                         args,
                         arg_types,
+                        ret_type,
                         body,
+                        is_move: e.is_move(),
                     },
                     syntax_ptr,
                 )
@@ -612,17 +1015,101 @@ impl ExprCollector {
                 let op = e.op();
                 self.alloc_expr(Expr::BinaryOp { lhs, rhs, op }, syntax_ptr)
             }
+            ast::Expr::IndexExpr(e) => {
+                let base = self.collect_expr_opt(e.base());
+                let index = self.collect_expr_opt(e.index());
+                self.alloc_expr(Expr::Index { base, index }, syntax_ptr)
+            }
+            ast::Expr::RangeExpr(e) => {
+                let lhs = e.lhs().map(|e| self.collect_expr(e));
+                let rhs = e.rhs().map(|e| self.collect_expr(e));
+                let inclusive = e.is_inclusive();
+                self.alloc_expr(
+                    Expr::Range {
+                        lhs,
+                        rhs,
+                        inclusive,
+                    },
+                    syntax_ptr,
+                )
+            }
+            ast::Expr::ArrayExpr(e) => {
+                let array = match e.kind() {
+                    ast::ArrayExprKind::ElementList(exprs) => {
+                        Array::ElementList(exprs.map(|e| self.collect_expr(e)).collect())
+                    }
+                    ast::ArrayExprKind::Repeat {
+                        initializer,
+                        repeat,
+                    } => Array::Repeat {
+                        initializer: self.collect_expr_opt(initializer),
+                        repeat: self.collect_expr_opt(repeat),
+                    },
+                };
+                self.alloc_expr(Expr::Array(array), syntax_ptr)
+            }
+            ast::Expr::Literal(e) => {
+                let expr = lower_literal(e).map_or(Expr::Missing, Expr::Literal);
+                let id = self.alloc_expr(expr, syntax_ptr);
+                if let Some(value) = literal_value(e) {
+                    self.literal_values.insert(id, value);
+                }
+                id
+            }
+
+            ast::Expr::TupleExpr(e) => {
+                let exprs = e.exprs().map(|expr| self.collect_expr(expr)).collect();
+                self.alloc_expr(Expr::Tuple { exprs }, syntax_ptr)
+            }
 
-            // TODO implement HIR for these:
-            ast::Expr::Label(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::Expr::IndexExpr(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::Expr::TupleExpr(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::Expr::ArrayExpr(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::Expr::RangeExpr(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
-            ast::Expr::Literal(_e) => self.alloc_expr(Expr::Missing, syntax_ptr),
+            ast::Expr::MacroCall(e) => {
+                let path = e.path().and_then(Path::from_ast);
+                let args = if let Some(token_tree) = e.token_tree() {
+                    self.collect_macro_args(token_tree)
+                } else {
+                    Vec::new()
+                };
+                self.alloc_expr(Expr::MacroCall { path, args }, syntax_ptr)
+            }
         }
     }
 
+    /// Most macros called in bodies (`vec!`, `println!`, `assert_eq!`) take a
+    /// list of expressions, so the token tree is reparsed as the arguments of
+    /// a call to lower them. The delimiters are replaced by parentheses, which
+    /// keeps the offsets of the arguments within the token tree.
+    fn collect_macro_args(&mut self, token_tree: ast::TokenTree) -> Vec<ExprId> {
+        let syntax = token_tree.syntax();
+        let mut text = syntax.text().to_string();
+        text.replace_range(..1, "(");
+        let is_closed = syntax.children().count() > 1
+            && match syntax.last_child().map(|it| it.kind()) {
+                Some(R_PAREN) | Some(R_BRACK) | Some(R_CURLY) => true,
+                _ => false,
+            };
+        if is_closed {
+            text.pop();
+        }
+        text.push(')');
+        let prefix = "fn dummy() { dummy";
+        let file = SourceFileNode::parse(&format!("{}{}; }}", prefix, text));
+        let arg_list = match file.syntax().descendants().find_map(ast::ArgList::cast) {
+            Some(it) => it,
+            None => return Vec::new(),
+        };
+
+        let source_start = self
+            .map_syntax_ptr(LocalSyntaxPtr::new(syntax))
+            .range()
+            .start();
+        let outer_offset = self
+            .macro_args_offset
+            .replace((TextUnit::of_str(prefix), source_start));
+        let args = arg_list.args().map(|e| self.collect_expr(e)).collect();
+        self.macro_args_offset = outer_offset;
+        args
+    }
+
     fn collect_expr_opt(&mut self, expr: Option<ast::Expr>) -> ExprId {
         if let Some(expr) = expr {
             self.collect_expr(expr)
@@ -671,13 +1158,135 @@ impl ExprCollector {
                     .name()
                     .map(|nr| nr.as_name())
                     .unwrap_or_else(Name::missing);
-                self.alloc_pat(Pat::Bind { name }, syntax_ptr)
+                let mode = BindingAnnotation::new(bp.is_mut(), bp.is_ref());
+                let subpat = bp.pat().map(|subpat| self.collect_pat(subpat));
+                self.alloc_pat(Pat::Bind { name, mode, subpat }, syntax_ptr)
             }
             ast::Pat::TupleStructPat(p) => {
                 let path = p.path().and_then(Path::from_ast);
                 let args = p.args().map(|p| self.collect_pat(p)).collect();
                 self.alloc_pat(Pat::TupleStruct { path, args }, syntax_ptr)
             }
+            ast::Pat::TuplePat(p) => {
+                let args = p.args().map(|p| self.collect_pat(p)).collect();
+                let ellipsis = p.ellipsis_position();
+                self.alloc_pat(Pat::Tuple { args, ellipsis }, syntax_ptr)
+            }
+            ast::Pat::RefPat(p) => {
+                let pat = self.collect_pat_opt(p.pat());
+                let mutability = Mutability::from_mutable(p.is_mut());
+                self.alloc_pat(Pat::Ref { pat, mutability }, syntax_ptr)
+            }
+            ast::Pat::PlaceholderPat(_) => self.alloc_pat(Pat::Wild, syntax_ptr),
+            ast::Pat::BoxPat(p) => {
+                let inner = self.collect_pat_opt(p.pat());
+                self.alloc_pat(Pat::Box { inner }, syntax_ptr)
+            }
+            ast::Pat::PathPat(p) => {
+                let pat = p
+                    .path()
+                    .and_then(Path::from_ast)
+                    .map(Pat::Path)
+                    .unwrap_or(Pat::Missing);
+                self.alloc_pat(pat, syntax_ptr)
+            }
+            ast::Pat::LiteralPat(p) => {
+                let literal = self.collect_literal_pat(p);
+                self.alloc_pat(Pat::Literal(literal), syntax_ptr)
+            }
+            ast::Pat::RangePat(p) => {
+                let start = self.collect_range_pat_end(p.start());
+                let end = self.collect_range_pat_end(p.end());
+                let inclusive = p.is_inclusive();
+                self.alloc_pat(
+                    Pat::Range {
+                        start,
+                        end,
+                        inclusive,
+                    },
+                    syntax_ptr,
+                )
+            }
+            ast::Pat::SlicePat(p) => {
+                let mut prefix = Vec::new();
+                let mut slice = None;
+                let mut suffix = Vec::new();
+                for element in p.elements() {
+                    match element {
+                        ast::SlicePatElement::Pat(pat) => {
+                            let pat = self.collect_pat(pat);
+                            if slice.is_none() {
+                                prefix.push(pat);
+                            } else {
+                                suffix.push(pat);
+                            }
+                        }
+                        // only one `..` is allowed, ignore the rest
+                        ast::SlicePatElement::Rest(_) if slice.is_some() => {}
+                        ast::SlicePatElement::Rest(Some(bind_pat)) => {
+                            slice = Some(self.collect_pat(ast::Pat::BindPat(bind_pat)));
+                        }
+                        ast::SlicePatElement::Rest(None) => {
+                            slice = Some(self.pats.alloc(Pat::Wild));
+                        }
+                    }
+                }
+                self.alloc_pat(
+                    Pat::Slice {
+                        prefix,
+                        slice,
+                        suffix,
+                    },
+                    syntax_ptr,
+                )
+            }
+            ast::Pat::StructPat(p) => {
+                let path = p.path().and_then(Path::from_ast);
+                let (fields, ellipsis) = if let Some(field_pat_list) = p.field_pat_list() {
+                    let mut fields = field_pat_list
+                        .field_pats()
+                        .map(|field| FieldPat {
+                            name: field
+                                .name_ref()
+                                .map(|nr| nr.as_name())
+                                .unwrap_or_else(Name::missing),
+                            pat: self.collect_pat_opt(field.pat()),
+                        })
+                        .collect::<Vec<_>>();
+                    // field shorthand, `ref mut a` binds the field `a`
+                    for bind_pat in field_pat_list.bind_pats() {
+                        fields.push(FieldPat {
+                            name: bind_pat
+                                .name()
+                                .map(|nr| nr.as_name())
+                                .unwrap_or_else(Name::missing),
+                            pat: self.collect_pat(ast::Pat::BindPat(bind_pat)),
+                        });
+                    }
+                    // `box a` binds the field `a` too
+                    for box_pat in field_pat_list.box_pats() {
+                        let name = match box_pat.pat() {
+                            Some(ast::Pat::BindPat(bind_pat)) => bind_pat.name(),
+                            _ => None,
+                        };
+                        fields.push(FieldPat {
+                            name: name.map(|nr| nr.as_name()).unwrap_or_else(Name::missing),
+                            pat: self.collect_pat(ast::Pat::BoxPat(box_pat)),
+                        });
+                    }
+                    (fields, field_pat_list.has_ellipsis())
+                } else {
+                    (Vec::new(), false)
+                };
+                self.alloc_pat(
+                    Pat::Struct {
+                        path,
+                        fields,
+                        ellipsis,
+                    },
+                    syntax_ptr,
+                )
+            }
             _ => {
                 // TODO
                 self.alloc_pat(Pat::Missing, syntax_ptr)
@@ -685,6 +1294,30 @@ impl ExprCollector {
         }
     }
 
+    fn collect_literal_pat(&mut self, pat: ast::LiteralPat) -> ExprId {
+        let literal = self.collect_expr_opt(pat.literal().map(ast::Expr::Literal));
+        if pat.is_negative() {
+            self.exprs.alloc(Expr::UnaryOp {
+                expr: literal,
+                op: Some(UnaryOp::Neg),
+            })
+        } else {
+            literal
+        }
+    }
+
+    fn collect_range_pat_end(&mut self, pat: Option<ast::Pat>) -> ExprId {
+        match pat {
+            Some(ast::Pat::LiteralPat(p)) => self.collect_literal_pat(p),
+            Some(ast::Pat::PathPat(p)) => {
+                let path = p.path().and_then(Path::from_ast);
+                let expr = path.map(Expr::Path).unwrap_or(Expr::Missing);
+                self.exprs.alloc(expr)
+            }
+            _ => self.exprs.alloc(Expr::Missing),
+        }
+    }
+
     fn collect_pat_opt(&mut self, pat: Option<ast::Pat>) -> PatId {
         if let Some(pat) = pat {
             self.collect_pat(pat)
@@ -694,11 +1327,14 @@ impl ExprCollector {
     }
 
     fn into_body_syntax_mapping(self, args: Vec<PatId>, body_expr: ExprId) -> BodySyntaxMapping {
+        let loop_targets = LoopResolver::resolve(&self.exprs, body_expr);
         let body = Body {
             exprs: self.exprs,
             pats: self.pats,
             args,
             body_expr,
+            loop_targets,
+            literal_values: self.literal_values,
         };
         BodySyntaxMapping {
             body: Arc::new(body),
@@ -706,8 +1342,184 @@ impl ExprCollector {
             expr_syntax_mapping_back: self.expr_syntax_mapping_back,
             pat_syntax_mapping: self.pat_syntax_mapping,
             pat_syntax_mapping_back: self.pat_syntax_mapping_back,
+            field_syntax_mapping: self.field_syntax_mapping,
+            field_syntax_mapping_back: self.field_syntax_mapping_back,
+        }
+    }
+}
+
+fn loop_label<'a>(loop_expr: impl LoopBodyOwner<'a>) -> Option<Name> {
+    loop_expr
+        .label()
+        .and_then(|label| label.lifetime())
+        .map(|it| it.as_name())
+}
+
+/// Finds the loops which `break` and `continue` expressions refer to.
+struct LoopResolver<'a> {
+    exprs: &'a Arena<ExprId, Expr>,
+    /// The loops enclosing the current expression, innermost last.
+    loops: Vec<(ExprId, Option<&'a Name>)>,
+    targets: ArenaMap<ExprId, ExprId>,
+}
+
+impl<'a> LoopResolver<'a> {
+    fn resolve(exprs: &'a Arena<ExprId, Expr>, body_expr: ExprId) -> ArenaMap<ExprId, ExprId> {
+        let mut resolver = LoopResolver {
+            exprs,
+            loops: Vec::new(),
+            targets: ArenaMap::default(),
+        };
+        resolver.resolve_expr(body_expr);
+        resolver.targets
+    }
+
+    fn resolve_expr(&mut self, expr: ExprId) {
+        let exprs = self.exprs;
+        match &exprs[expr] {
+            Expr::Loop { body, label } => self.resolve_loop_body(expr, label, *body),
+            Expr::While {
+                condition,
+                body,
+                label,
+            } => {
+                self.resolve_expr(*condition);
+                self.resolve_loop_body(expr, label, *body);
+            }
+            Expr::For {
+                iterable,
+                body,
+                label,
+                ..
+            } => {
+                self.resolve_expr(*iterable);
+                self.resolve_loop_body(expr, label, *body);
+            }
+            Expr::Break { label, .. } | Expr::Continue { label } => {
+                let target = match label {
+                    Some(label) => self.loops.iter().rev().find(|(_, it)| *it == Some(label)),
+                    None => self.loops.last(),
+                };
+                if let Some(&(target, _)) = target {
+                    self.targets.insert(expr, target);
+                }
+                exprs[expr].walk_child_exprs(|it| self.resolve_expr(it));
+            }
+            Expr::Lambda { body, .. } | Expr::Async { body } => {
+                // A closure or an async block can't break out of the loops
+                // around it.
+                let loops = std::mem::replace(&mut self.loops, Vec::new());
+                self.resolve_expr(*body);
+                self.loops = loops;
+            }
+            e => e.walk_child_exprs(|it| self.resolve_expr(it)),
+        }
+    }
+
+    fn resolve_loop_body(&mut self, loop_expr: ExprId, label: &'a Option<Name>, body: ExprId) {
+        self.loops.push((loop_expr, label.as_ref()));
+        self.resolve_expr(body);
+        self.loops.pop();
+    }
+}
+
+fn lower_literal(literal: ast::Literal) -> Option<Literal> {
+    let token = literal.syntax().first_child()?;
+    let text = token.leaf_text()?;
+    let res = match token.kind() {
+        TRUE_KW => Literal::Bool(true),
+        FALSE_KW => Literal::Bool(false),
+        INT_NUMBER | FLOAT_NUMBER => {
+            let suffix = number_suffix(text, token.kind() == FLOAT_NUMBER)
+                .map(|suffix| Name::new(suffix.into()));
+            // `1f32` is a float.
+            match suffix.as_ref().and_then(UncertainFloatTy::from_name) {
+                Some(float_ty) => Literal::Float(float_ty),
+                None if token.kind() == FLOAT_NUMBER => Literal::Float(UncertainFloatTy::Unknown),
+                None => Literal::Int(
+                    suffix
+                        .as_ref()
+                        .and_then(UncertainIntTy::from_name)
+                        .unwrap_or(UncertainIntTy::Unknown),
+                ),
+            }
         }
+        BYTE => Literal::Int(UncertainIntTy::Unsigned(UintTy::U8)),
+        CHAR => Literal::Char,
+        STRING | RAW_STRING => Literal::String,
+        BYTE_STRING | RAW_BYTE_STRING => Literal::ByteString,
+        _ => return None,
+    };
+    Some(res)
+}
+
+fn literal_value(literal: ast::Literal) -> Option<LiteralValue> {
+    let token = literal.syntax().first_child()?;
+    let text = token.leaf_text()?;
+    let res = match token.kind() {
+        TRUE_KW => LiteralValue::Bool(true),
+        FALSE_KW => LiteralValue::Bool(false),
+        INT_NUMBER | FLOAT_NUMBER => {
+            let suffix = number_suffix(text, token.kind() == FLOAT_NUMBER);
+            let number = &text[..text.len() - suffix.map_or(0, |it| it.len())];
+            let number = number.replace('_', "");
+            let is_float = token.kind() == FLOAT_NUMBER
+                || suffix.map_or(false, |it| it == "f32" || it == "f64");
+            if is_float {
+                LiteralValue::Float(number)
+            } else {
+                let (radix, digits) = match number.get(..2) {
+                    Some("0x") => (16, &number[2..]),
+                    Some("0o") => (8, &number[2..]),
+                    Some("0b") => (2, &number[2..]),
+                    _ => (10, &number[..]),
+                };
+                LiteralValue::Int(u128::from_str_radix(digits, radix).ok()?)
+            }
+        }
+        BYTE => LiteralValue::Int(ast::Byte::cast(token)?.value()?.into()),
+        CHAR => LiteralValue::Char(ast::Char::cast(token)?.value()?),
+        STRING => LiteralValue::String(ast::String::cast(token)?.value()?),
+        BYTE_STRING => LiteralValue::ByteString(ast::ByteString::cast(token)?.value()?),
+        RAW_STRING => LiteralValue::String(raw_string_value(text)?.to_string()),
+        RAW_BYTE_STRING => LiteralValue::ByteString(raw_string_value(text)?.as_bytes().to_vec()),
+        _ => return None,
+    };
+    Some(res)
+}
+
+/// The contents of a raw string literal, like `foo` in `r#"foo"#`.
+fn raw_string_value(text: &str) -> Option<&str> {
+    let text = text.trim_start_matches('b').trim_start_matches('r');
+    let hashes = &text[..text.len() - text.trim_start_matches('#').len()];
+    let quoted = &text[hashes.len()..];
+    if !quoted.ends_with(hashes) {
+        return None;
+    }
+    let quoted = &quoted[..quoted.len() - hashes.len()];
+    if quoted.len() < 2 || !quoted.starts_with('"') || !quoted.ends_with('"') {
+        return None;
     }
+    Some(&quoted[1..quoted.len() - 1])
+}
+
+/// The suffix of a number literal, like `u32` in `92u32`.
+fn number_suffix(text: &str, is_float: bool) -> Option<&str> {
+    let digits = if text.starts_with("0x") || text.starts_with("0o") || text.starts_with("0b") {
+        2
+    } else {
+        0
+    };
+    let is_hex = text.starts_with("0x");
+    let idx = text[digits..].find(|c: char| match c {
+        '0'..='9' | '_' => false,
+        // The exponent of floats, `e` is also a hex digit.
+        'e' | 'E' if is_float => false,
+        '.' | '+' | '-' if is_float => false,
+        'a'..='f' | 'A'..='F' if is_hex => false,
+        _ => true,
+    })?;
+    Some(&text[digits + idx..])
 }
 
 pub(crate) fn collect_fn_body_syntax(node: ast::FnDef) -> BodySyntaxMapping {
@@ -720,12 +1532,14 @@ pub(crate) fn collect_fn_body_syntax(node: ast::FnDef) -> BodySyntaxMapping {
             let self_param = LocalSyntaxPtr::new(
                 self_param
                     .self_kw()
-                    .expect("self param without self keyword")
-                    .syntax(),
+                    .map(|it| it.syntax())
+                    .unwrap_or_else(|| self_param.syntax()),
             );
             let arg = collector.alloc_pat(
                 Pat::Bind {
                     name: Name::self_param(),
+                    mode: BindingAnnotation::Unannotated,
+                    subpat: None,
                 },
                 self_param,
             );
@@ -749,10 +1563,28 @@ pub(crate) fn collect_fn_body_syntax(node: ast::FnDef) -> BodySyntaxMapping {
     collector.into_body_syntax_mapping(args, body)
 }
 
+/// Lowers the initializer of a `const` or `static` item, which is a body
+/// without arguments.
+pub(crate) fn collect_initializer_body_syntax(initializer: Option<ast::Expr>) -> BodySyntaxMapping {
+    let mut collector = ExprCollector::new();
+    let body = collector.collect_expr_opt(initializer);
+    collector.into_body_syntax_mapping(Vec::new(), body)
+}
+
+pub(crate) fn body_parent_map(
+    db: &impl HirDatabase,
+    def_id: DefId,
+) -> Cancelable<Arc<BodyParentMap>> {
+    let _timer = QueryTimer::new(db, "body_parent_map");
+    let body = db.body_hir(def_id)?;
+    Ok(Arc::new(BodyParentMap::new(&body)))
+}
+
 pub(crate) fn body_syntax_mapping(
     db: &impl HirDatabase,
     def_id: DefId,
 ) -> Cancelable<Arc<BodySyntaxMapping>> {
+    let _timer = QueryTimer::new(db, "body_syntax_mapping");
     let def = def_id.resolve(db)?;
 
     let body_syntax_mapping = match def {
@@ -762,9 +1594,899 @@ pub(crate) fn body_syntax_mapping(
 
             collect_fn_body_syntax(node)
         }
-        // TODO: consts, etc.
+        Def::Const(c) => {
+            let node = c.syntax(db);
+            collect_initializer_body_syntax(node.borrowed().body())
+        }
+        Def::Static(s) => {
+            let node = s.syntax(db);
+            collect_initializer_body_syntax(node.borrowed().body())
+        }
         _ => panic!("Trying to get body for item type without body"),
     };
 
     Ok(Arc::new(body_syntax_mapping))
 }
+
+#[cfg(test)]
+mod tests {
+    use ra_syntax::SourceFileNode;
+
+    use crate::{PathKind, PathSegment, GenericArg, ty::primitive::{IntTy, FloatTy}};
+
+    use super::*;
+
+    fn lower(code: &str) -> (SourceFileNode, BodySyntaxMapping) {
+        let file = SourceFileNode::parse(code);
+        let fn_def = file
+            .syntax()
+            .descendants()
+            .find_map(ast::FnDef::cast)
+            .unwrap();
+        let body_syntax = collect_fn_body_syntax(fn_def);
+        (file, body_syntax)
+    }
+
+    /// The expression whose syntax is exactly `text`.
+    fn expr_id(file: &SourceFileNode, body_syntax: &BodySyntaxMapping, text: &str) -> ExprId {
+        let node = file
+            .syntax()
+            .descendants()
+            .filter_map(ast::Expr::cast)
+            .find(|it| it.syntax().text() == text)
+            .unwrap();
+        body_syntax.node_expr(node).unwrap()
+    }
+
+    /// The pattern whose syntax is exactly `text`.
+    fn pat_id(file: &SourceFileNode, body_syntax: &BodySyntaxMapping, text: &str) -> PatId {
+        let node = file
+            .syntax()
+            .descendants()
+            .filter_map(ast::Pat::cast)
+            .find(|it| it.syntax().text() == text)
+            .unwrap();
+        body_syntax.node_pat(node).unwrap()
+    }
+
+    fn check_literals(code: &str, expected: &[(&str, Expr)]) {
+        let (file, body_syntax) = lower(code);
+        let actual = file
+            .syntax()
+            .descendants()
+            .filter_map(ast::Literal::cast)
+            .map(|lit| {
+                let expr = ast::Expr::cast(lit.syntax()).unwrap();
+                let id = body_syntax.node_expr(expr).unwrap();
+                assert_eq!(
+                    body_syntax.expr_syntax(id),
+                    Some(LocalSyntaxPtr::new(lit.syntax()))
+                );
+                (
+                    lit.syntax().text().to_string(),
+                    body_syntax.body()[id].clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let expected = expected
+            .iter()
+            .map(|(text, expr)| (text.to_string(), expr.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_lower_literal_values() {
+        let (file, body_syntax) = lower(
+            r##"
+fn f() {
+    1_000; 0x1fi64; 0b101; 2_5.5f32; 1f64;
+    'c'; '\n'; b'\x7f'; "a\tb"; r#"a\tb"#; b"\x00b"; true;
+    '\q'; "x"y;
+}"##,
+        );
+        let body = body_syntax.body();
+        let cases = [
+            ("1_000", Some(LiteralValue::Int(1000))),
+            ("0x1fi64", Some(LiteralValue::Int(31))),
+            ("0b101", Some(LiteralValue::Int(5))),
+            ("2_5.5f32", Some(LiteralValue::Float("25.5".to_string()))),
+            ("1f64", Some(LiteralValue::Float("1".to_string()))),
+            ("'c'", Some(LiteralValue::Char('c'))),
+            ("'\\n'", Some(LiteralValue::Char('\n'))),
+            ("b'\\x7f'", Some(LiteralValue::Int(0x7f))),
+            ("\"a\\tb\"", Some(LiteralValue::String("a\tb".to_string()))),
+            (
+                "r#\"a\\tb\"#",
+                Some(LiteralValue::String("a\\tb".to_string())),
+            ),
+            ("b\"\\x00b\"", Some(LiteralValue::ByteString(vec![0, b'b']))),
+            ("true", Some(LiteralValue::Bool(true))),
+            ("'\\q'", None),
+            ("\"x\"y", None),
+        ];
+        for (text, value) in cases.iter() {
+            let expr = expr_id(&file, &body_syntax, text);
+            assert_eq!(body.literal_value(expr), value.as_ref(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_lower_arrays() {
+        let (file, body_syntax) = lower("fn f() { [1, 2]; [0; 3]; }");
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let body = body_syntax.body();
+
+        assert_eq!(
+            body[expr("[1, 2]")],
+            Expr::Array(Array::ElementList(vec![expr("1"), expr("2")]))
+        );
+        assert_eq!(
+            body[expr("[0; 3]")],
+            Expr::Array(Array::Repeat {
+                initializer: expr("0"),
+                repeat: expr("3"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_lower_tuples() {
+        let (file, body_syntax) = lower("fn f() { (1, 2); (3,); (); }");
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let body = body_syntax.body();
+
+        assert_eq!(
+            body[expr("(1, 2)")],
+            Expr::Tuple {
+                exprs: vec![expr("1"), expr("2")]
+            }
+        );
+        assert_eq!(
+            body[expr("(3,)")],
+            Expr::Tuple {
+                exprs: vec![expr("3")]
+            }
+        );
+        assert_eq!(body[expr("()")], Expr::Tuple { exprs: Vec::new() });
+    }
+
+    #[test]
+    fn test_lower_index() {
+        let (file, body_syntax) = lower("fn f() { a[b][0]; }");
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let body = body_syntax.body();
+
+        assert_eq!(
+            body[expr("a[b][0]")],
+            Expr::Index {
+                base: expr("a[b]"),
+                index: expr("0"),
+            }
+        );
+        assert_eq!(
+            body[expr("a[b]")],
+            Expr::Index {
+                base: expr("a"),
+                index: expr("b"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_lower_ranges() {
+        let (file, body_syntax) = lower("fn f() { a..b; c..=d; e..; ..f; ..=g; ..; }");
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let body = body_syntax.body();
+
+        let cases = [
+            ("a..b", Some("a"), Some("b"), false),
+            ("c..=d", Some("c"), Some("d"), true),
+            ("e..", Some("e"), None, false),
+            ("..f", None, Some("f"), false),
+            ("..=g", None, Some("g"), true),
+            ("..", None, None, false),
+        ];
+        for &(range, lhs, rhs, inclusive) in cases.iter() {
+            assert_eq!(
+                body[expr(range)],
+                Expr::Range {
+                    lhs: lhs.map(&expr),
+                    rhs: rhs.map(&expr),
+                    inclusive,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_lower_assignment_ops() {
+        let (file, body_syntax) =
+            lower("fn f() { a = 1; b += 2; c %= 3; d <<= 4; e |= 5; f != 6; }");
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let body = body_syntax.body();
+
+        let cases = [
+            ("a = 1", "a", "1", BinaryOp::Assignment),
+            ("b += 2", "b", "2", BinaryOp::AddAssign),
+            ("c %= 3", "c", "3", BinaryOp::RemAssign),
+            ("d <<= 4", "d", "4", BinaryOp::ShlAssign),
+            ("e |= 5", "e", "5", BinaryOp::BitOrAssign),
+            ("f != 6", "f", "6", BinaryOp::NegatedEqualityTest),
+        ];
+        for &(bin_expr, lhs, rhs, op) in cases.iter() {
+            assert_eq!(
+                body[expr(bin_expr)],
+                Expr::BinaryOp {
+                    lhs: expr(lhs),
+                    rhs: expr(rhs),
+                    op: Some(op),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_lower_macro_call_args() {
+        let code =
+            r#"fn f() { let v = vec![a, b + 1]; println!("{}", format!("{}", c)); foo!{}; }"#;
+        let (file, body_syntax) = lower(code);
+        let body = body_syntax.body();
+        let macro_args = |expr: ExprId| match &body[expr] {
+            Expr::MacroCall { args, .. } => args.clone(),
+            _ => panic!("not a macro call: {:?}", body[expr]),
+        };
+        let text = |expr: ExprId| {
+            let range = body_syntax.expr_syntax(expr).unwrap().range();
+            &code[range.start().to_usize()..range.end().to_usize()]
+        };
+
+        let vec = expr_id(&file, &body_syntax, "vec![a, b + 1]");
+        let args = macro_args(vec);
+        assert_eq!(
+            args.iter().map(|&it| text(it)).collect::<Vec<_>>(),
+            ["a", "b + 1"]
+        );
+        assert!(match &body[args[1]] {
+            Expr::BinaryOp { .. } => true,
+            _ => false,
+        });
+
+        let println = expr_id(&file, &body_syntax, r#"println!("{}", format!("{}", c))"#);
+        let args = macro_args(println);
+        assert_eq!(
+            args.iter().map(|&it| text(it)).collect::<Vec<_>>(),
+            [r#""{}""#, r#"format!("{}", c)"#]
+        );
+        let nested_args = macro_args(args[1]);
+        assert_eq!(
+            nested_args.iter().map(|&it| text(it)).collect::<Vec<_>>(),
+            [r#""{}""#, "c"]
+        );
+
+        let foo = expr_id(&file, &body_syntax, "foo!{}");
+        assert_eq!(macro_args(foo), []);
+    }
+
+    #[test]
+    fn test_lower_async() {
+        let (file, body_syntax) =
+            lower("async fn f() { let a = async move { fut.await }; a.await; }");
+        let body = body_syntax.body();
+
+        let async_block = expr_id(&file, &body_syntax, "async move { fut.await }");
+        let block = match &body[async_block] {
+            Expr::Async { body } => *body,
+            _ => panic!("not an async block: {:?}", body[async_block]),
+        };
+        let fut_await = expr_id(&file, &body_syntax, "fut.await");
+        assert_eq!(
+            body[block],
+            Expr::Block {
+                statements: Vec::new(),
+                tail: Some(fut_await),
+            }
+        );
+        let fut = expr_id(&file, &body_syntax, "fut");
+        assert_eq!(body[fut_await], Expr::Await { expr: fut });
+
+        let a_await = expr_id(&file, &body_syntax, "a.await");
+        let a = expr_id(&file, &body_syntax, "a");
+        assert_eq!(body[a_await], Expr::Await { expr: a });
+    }
+
+    #[test]
+    fn test_body_parent_map() {
+        let (file, body_syntax) =
+            lower("fn f(a: u32) { let b = a + 1; match b { 1..=2 => (), c => { c; } } }");
+        let body = body_syntax.body();
+        let parents = BodyParentMap::new(body);
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let pat = |text| pat_id(&file, &body_syntax, text);
+
+        assert_eq!(parents.parent_expr(body.body_expr()), None);
+        assert_eq!(parents.parent_pat(body.args()[0]), None);
+        let block = body.body_expr();
+        assert_eq!(parents.parent_pat(pat("b")), Some(BodyParent::Expr(block)));
+        assert_eq!(
+            parents.parent_expr(expr("a + 1")),
+            Some(BodyParent::Expr(block))
+        );
+        assert_eq!(
+            parents.parent_expr(expr("a")),
+            Some(BodyParent::Expr(expr("a + 1")))
+        );
+
+        let match_expr = expr("match b { 1..=2 => (), c => { c; } }");
+        assert_eq!(
+            parents.parent_pat(pat("1..=2")),
+            Some(BodyParent::Expr(match_expr))
+        );
+        assert_eq!(
+            parents.parent_expr(expr("2")),
+            Some(BodyParent::Pat(pat("1..=2")))
+        );
+        let arm_block = match &body[match_expr] {
+            Expr::Match { arms, .. } => arms[1].expr,
+            _ => panic!("not a match: {:?}", body[match_expr]),
+        };
+        assert_eq!(
+            parents.parent_expr(expr("c")),
+            Some(BodyParent::Expr(arm_block))
+        );
+        assert_eq!(
+            parents.parent_expr(arm_block),
+            Some(BodyParent::Expr(match_expr))
+        );
+    }
+
+    #[test]
+    fn test_struct_lit_field_syntax() {
+        let code = "fn f() { S { a: 1, b }; foo!(T { c: 2 }); }";
+        let (file, body_syntax) = lower(code);
+        let text = |ptr: LocalSyntaxPtr| {
+            let range = ptr.range();
+            &code[range.start().to_usize()..range.end().to_usize()]
+        };
+
+        let s = expr_id(&file, &body_syntax, "S { a: 1, b }");
+        let a = body_syntax.field_syntax(s, 0).unwrap();
+        assert_eq!(text(a), "a: 1");
+        assert_eq!(body_syntax.syntax_field(a), Some((s, 0)));
+        let a_node = file
+            .syntax()
+            .descendants()
+            .find_map(ast::NamedField::cast)
+            .unwrap();
+        assert_eq!(body_syntax.node_field(a_node), Some((s, 0)));
+        assert_eq!(text(body_syntax.field_syntax(s, 1).unwrap()), "b");
+        assert_eq!(body_syntax.field_syntax(s, 2), None);
+        // The value of a shorthand field is mapped to the name of the field.
+        let b = match &body_syntax.body()[s] {
+            Expr::StructLit { fields, .. } => fields[1].expr,
+            _ => panic!("not a struct literal"),
+        };
+        assert_eq!(text(body_syntax.expr_syntax(b).unwrap()), "b");
+
+        // The fields in macro arguments are mapped to the original source.
+        let foo = expr_id(&file, &body_syntax, "foo!(T { c: 2 })");
+        let t = match &body_syntax.body()[foo] {
+            Expr::MacroCall { args, .. } => args[0],
+            _ => panic!("not a macro call"),
+        };
+        assert_eq!(text(body_syntax.field_syntax(t, 0).unwrap()), "c: 2");
+    }
+
+    #[test]
+    fn test_lower_else_if() {
+        let code = "fn f() { if a { 1 } else if let Some(x) = b { 2 } else if c { 3 } else { 4 } }";
+        let (file, body_syntax) = lower(code);
+        let body = body_syntax.body();
+        let else_branch = |expr: ExprId| match &body[expr] {
+            Expr::If { else_branch, .. } => *else_branch,
+            Expr::Match { arms, .. } => Some(arms[1].expr),
+            _ => panic!("not an if: {:?}", body[expr]),
+        };
+
+        let if_a = expr_id(
+            &file,
+            &body_syntax,
+            "if a { 1 } else if let Some(x) = b { 2 } else if c { 3 } else { 4 }",
+        );
+        let if_let_b = expr_id(
+            &file,
+            &body_syntax,
+            "if let Some(x) = b { 2 } else if c { 3 } else { 4 }",
+        );
+        let if_c = expr_id(&file, &body_syntax, "if c { 3 } else { 4 }");
+        assert_eq!(else_branch(if_a), Some(if_let_b));
+        assert_eq!(else_branch(if_let_b), Some(if_c));
+        let last = else_branch(if_c).unwrap();
+        let four = expr_id(&file, &body_syntax, "4");
+        assert_eq!(
+            body[last],
+            Expr::Block {
+                statements: Vec::new(),
+                tail: Some(four),
+            }
+        );
+    }
+
+    #[test]
+    fn test_lower_closures() {
+        let (file, body_syntax) = lower("fn f() { |x| x; move |y: u32| -> u64 { 1 }; }");
+        let body = body_syntax.body();
+        let closure = |text| match &body[expr_id(&file, &body_syntax, text)] {
+            Expr::Lambda {
+                arg_types,
+                ret_type,
+                is_move,
+                ..
+            } => (arg_types.clone(), ret_type.clone(), *is_move),
+            e => panic!("not a closure: {:?}", e),
+        };
+        let ty = |name: &str| TypeRef::Path(Name::new(name.into()).into());
+
+        assert_eq!(closure("|x| x"), (vec![None], None, false));
+        assert_eq!(
+            closure("move |y: u32| -> u64 { 1 }"),
+            (vec![Some(ty("u32"))], Some(ty("u64")), true)
+        );
+    }
+
+    #[test]
+    fn test_lower_generic_args() {
+        let (file, body_syntax) =
+            lower("fn f() { foo::<u32>; Vec::<i32>::new; x.collect::<Vec<_>>(); }");
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let body = body_syntax.body();
+
+        let generic_args = |args: Vec<TypeRef>| GenericArgs {
+            args: args.into_iter().map(GenericArg::Type).collect(),
+            bindings: Vec::new(),
+        };
+        let segment = |name: &str, args: Option<Vec<TypeRef>>| PathSegment {
+            name: Name::new(name.into()),
+            args_and_bindings: args.map(|args| Arc::new(generic_args(args))),
+        };
+        let path = |segments: Vec<PathSegment>| Path {
+            kind: PathKind::Plain,
+            segments,
+        };
+        let type_path = |name: &str, args| TypeRef::Path(path(vec![segment(name, args)]));
+
+        assert_eq!(
+            body[expr("foo::<u32>")],
+            Expr::Path(path(vec![segment(
+                "foo",
+                Some(vec![type_path("u32", None)])
+            )]))
+        );
+        assert_eq!(
+            body[expr("Vec::<i32>::new")],
+            Expr::Path(path(vec![
+                segment("Vec", Some(vec![type_path("i32", None)])),
+                segment("new", None),
+            ]))
+        );
+        assert_eq!(
+            body[expr("x.collect::<Vec<_>>()")],
+            Expr::MethodCall {
+                receiver: expr("x"),
+                method_name: Name::new("collect".into()),
+                args: Vec::new(),
+                generic_args: Some(generic_args(vec![type_path(
+                    "Vec",
+                    Some(vec![TypeRef::Placeholder])
+                )])),
+            }
+        );
+    }
+
+    #[test]
+    fn test_loop_labels() {
+        let (file, body_syntax) = lower(
+            r#"
+fn f() {
+    'outer: loop {
+        'inner: while x {
+            break 'outer;
+            continue 'inner;
+            break;
+            for y in z {
+                continue 'outer;
+                continue;
+            }
+            || break;
+            break 'missing;
+        }
+    }
+}"#,
+        );
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let body = body_syntax.body();
+        let loop_expr = |kind| {
+            let node = file
+                .syntax()
+                .descendants()
+                .find(|it| it.kind() == kind)
+                .unwrap();
+            body_syntax
+                .node_expr(ast::Expr::cast(node).unwrap())
+                .unwrap()
+        };
+        let (outer, inner, for_loop) = (
+            loop_expr(LOOP_EXPR),
+            loop_expr(WHILE_EXPR),
+            loop_expr(FOR_EXPR),
+        );
+
+        match &body[outer] {
+            Expr::Loop { label, .. } => assert_eq!(label, &Some(Name::new("'outer".into()))),
+            e => panic!("not a loop: {:?}", e),
+        }
+        match &body[for_loop] {
+            Expr::For { label, .. } => assert_eq!(label, &None),
+            e => panic!("not a loop: {:?}", e),
+        }
+        assert_eq!(
+            body[expr("break 'outer")],
+            Expr::Break {
+                expr: None,
+                label: Some(Name::new("'outer".into())),
+            }
+        );
+
+        assert_eq!(body.loop_target(expr("break 'outer")), Some(outer));
+        assert_eq!(body.loop_target(expr("continue 'inner")), Some(inner));
+        assert_eq!(body.loop_target(expr("break")), Some(inner));
+        assert_eq!(body.loop_target(expr("continue 'outer")), Some(outer));
+        assert_eq!(body.loop_target(expr("continue")), Some(for_loop));
+        assert_eq!(body.loop_target(expr("break 'missing")), None);
+        let closure_break = expr("|| break");
+        match &body[closure_break] {
+            Expr::Lambda {
+                body: lambda_body, ..
+            } => assert_eq!(body.loop_target(*lambda_body), None),
+            e => panic!("not a closure: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_lower_struct_pat() {
+        let (file, body_syntax) = lower("fn f() { let Foo { a, b: x, ref mut c, .. } = foo; }");
+        let body = body_syntax.body();
+        let pat_id = |text: &str| pat_id(&file, &body_syntax, text);
+        let field = |name: &str, pat| FieldPat {
+            name: Name::new(name.into()),
+            pat: pat_id(pat),
+        };
+
+        assert_eq!(
+            body[pat_id("Foo { a, b: x, ref mut c, .. }")],
+            Pat::Struct {
+                path: Some(Name::new("Foo".into()).into()),
+                fields: vec![field("b", "x"), field("a", "a"), field("c", "ref mut c")],
+                ellipsis: true,
+            }
+        );
+        assert_eq!(
+            body[pat_id("ref mut c")],
+            Pat::Bind {
+                name: Name::new("c".into()),
+                mode: BindingAnnotation::RefMut,
+                subpat: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_lower_bind_pat() {
+        let (file, body_syntax) = lower(
+            r#"
+fn f() {
+    let a = x;
+    let mut b = x;
+    let ref c = x;
+    let ref mut d = x;
+    let e @ Some(f) = x;
+}"#,
+        );
+        let body = body_syntax.body();
+        let pat_id = |text: &str| pat_id(&file, &body_syntax, text);
+        let bind = |name: &str, mode, subpat: Option<&str>| Pat::Bind {
+            name: Name::new(name.into()),
+            mode,
+            subpat: subpat.map(&pat_id),
+        };
+
+        assert_eq!(
+            body[pat_id("a")],
+            bind("a", BindingAnnotation::Unannotated, None)
+        );
+        assert_eq!(
+            body[pat_id("mut b")],
+            bind("b", BindingAnnotation::Mutable, None)
+        );
+        assert_eq!(
+            body[pat_id("ref c")],
+            bind("c", BindingAnnotation::Ref, None)
+        );
+        assert_eq!(
+            body[pat_id("ref mut d")],
+            bind("d", BindingAnnotation::RefMut, None)
+        );
+        assert_eq!(
+            body[pat_id("e @ Some(f)")],
+            bind("e", BindingAnnotation::Unannotated, Some("Some(f)"))
+        );
+    }
+
+    #[test]
+    fn test_lower_literal_and_range_pats() {
+        let (file, body_syntax) = lower(
+            r#"
+fn f() {
+    match x {
+        1 => (),
+        -2 => (),
+        'a'..='z' => (),
+        3...4 => (),
+        5..6 => (),
+        Foo::A..=Foo::B => (),
+    }
+}"#,
+        );
+        let body = body_syntax.body();
+        let expr = |text| expr_id(&file, &body_syntax, text);
+        let pat_id = |text: &str| pat_id(&file, &body_syntax, text);
+        let range = |start, end, inclusive| Pat::Range {
+            start: expr(start),
+            end: expr(end),
+            inclusive,
+        };
+
+        assert_eq!(body[pat_id("1")], Pat::Literal(expr("1")));
+        match &body[pat_id("-2")] {
+            Pat::Literal(literal) => assert_eq!(
+                body[*literal],
+                Expr::UnaryOp {
+                    expr: expr("2"),
+                    op: Some(UnaryOp::Neg),
+                }
+            ),
+            p => panic!("not a literal: {:?}", p),
+        }
+        assert_eq!(body[pat_id("'a'..='z'")], range("'a'", "'z'", true));
+        assert_eq!(body[pat_id("3...4")], range("3", "4", true));
+        assert_eq!(body[pat_id("5..6")], range("5", "6", false));
+        match &body[pat_id("Foo::A..=Foo::B")] {
+            Pat::Range { start, end, .. } => {
+                assert!(match body[*start] {
+                    Expr::Path(_) => true,
+                    _ => false,
+                });
+                assert!(match body[*end] {
+                    Expr::Path(_) => true,
+                    _ => false,
+                });
+            }
+            p => panic!("not a range: {:?}", p),
+        }
+    }
+
+    #[test]
+    fn test_lower_path_pat() {
+        let (file, body_syntax) = lower(
+            r#"
+fn f() {
+    match x {
+        Option::None => (),
+        ::Foo => (),
+        None => (),
+    }
+}"#,
+        );
+        let body = body_syntax.body();
+        let pat_id = |text: &str| pat_id(&file, &body_syntax, text);
+        let path = |text| {
+            let node = file
+                .syntax()
+                .descendants()
+                .filter_map(ast::Path::cast)
+                .find(|it| it.syntax().text() == text)
+                .unwrap();
+            Path::from_ast(node).unwrap()
+        };
+
+        assert_eq!(
+            body[pat_id("Option::None")],
+            Pat::Path(path("Option::None"))
+        );
+        assert_eq!(body[pat_id("::Foo")], Pat::Path(path("::Foo")));
+        assert_eq!(
+            body[pat_id("None")],
+            Pat::Bind {
+                name: Name::new("None".into()),
+                mode: BindingAnnotation::Unannotated,
+                subpat: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_lower_wild_pat() {
+        let (file, body_syntax) = lower("fn f() { let _ = x; if let Some(y) = x {} }");
+        let body = body_syntax.body();
+
+        assert_eq!(body[pat_id(&file, &body_syntax, "_")], Pat::Wild);
+        match &body[expr_id(&file, &body_syntax, "if let Some(y) = x {}")] {
+            Expr::Match { arms, .. } => {
+                assert_eq!(arms.len(), 2);
+                assert_eq!(arms[1].pats.len(), 1);
+                assert_eq!(body[arms[1].pats[0]], Pat::Wild);
+            }
+            e => panic!("if let is not desugared to a match: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_lower_box() {
+        let (file, body_syntax) = lower("fn f() { let box Foo { box a, b } = box x; }");
+        let body = body_syntax.body();
+        let pat_id = |text: &str| pat_id(&file, &body_syntax, text);
+
+        assert_eq!(
+            body[expr_id(&file, &body_syntax, "box x")],
+            Expr::Box {
+                expr: expr_id(&file, &body_syntax, "x"),
+            }
+        );
+        assert_eq!(
+            body[pat_id("box Foo { box a, b }")],
+            Pat::Box {
+                inner: pat_id("Foo { box a, b }"),
+            }
+        );
+        match &body[pat_id("Foo { box a, b }")] {
+            Pat::Struct { fields, .. } => {
+                let fields = fields
+                    .iter()
+                    .map(|field| (field.name.to_string(), field.pat))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    fields,
+                    [
+                        ("b".to_string(), pat_id("b")),
+                        ("a".to_string(), pat_id("box a"))
+                    ]
+                );
+            }
+            p => panic!("not a struct pattern: {:?}", p),
+        }
+        assert_eq!(body[pat_id("box a")], Pat::Box { inner: pat_id("a") });
+    }
+
+    #[test]
+    fn test_lower_tuple_and_ref_pats() {
+        let (file, body_syntax) = lower("fn f() { let (a, &mut b, .., c) = x; let (&d,) = y; }");
+        let body = body_syntax.body();
+        let pat_id = |text: &str| pat_id(&file, &body_syntax, text);
+
+        assert_eq!(
+            body[pat_id("(a, &mut b, .., c)")],
+            Pat::Tuple {
+                args: vec![pat_id("a"), pat_id("&mut b"), pat_id("c")],
+                ellipsis: Some(2),
+            }
+        );
+        assert_eq!(
+            body[pat_id("&mut b")],
+            Pat::Ref {
+                pat: pat_id("b"),
+                mutability: Mutability::Mut,
+            }
+        );
+        assert_eq!(
+            body[pat_id("(&d,)")],
+            Pat::Tuple {
+                args: vec![pat_id("&d")],
+                ellipsis: None,
+            }
+        );
+        assert_eq!(
+            body[pat_id("&d")],
+            Pat::Ref {
+                pat: pat_id("d"),
+                mutability: Mutability::Shared,
+            }
+        );
+    }
+
+    #[test]
+    fn test_lower_slice_pat() {
+        let (file, body_syntax) =
+            lower("fn f() { let [a, rest @ .., b] = x; let [c, ..] = x; let [d, _] = x; }");
+        let body = body_syntax.body();
+        let pat_id = |text: &str| pat_id(&file, &body_syntax, text);
+
+        assert_eq!(
+            body[pat_id("[a, rest @ .., b]")],
+            Pat::Slice {
+                prefix: vec![pat_id("a")],
+                slice: Some(pat_id("rest @ ..")),
+                suffix: vec![pat_id("b")],
+            }
+        );
+        assert_eq!(
+            body[pat_id("rest @ ..")],
+            Pat::Bind {
+                name: Name::new("rest".into()),
+                mode: BindingAnnotation::Unannotated,
+                subpat: None,
+            }
+        );
+        match &body[pat_id("[c, ..]")] {
+            Pat::Slice {
+                prefix,
+                slice: Some(slice),
+                suffix,
+            } => {
+                assert_eq!(prefix, &vec![pat_id("c")]);
+                assert_eq!(body[*slice], Pat::Wild);
+                assert!(suffix.is_empty());
+            }
+            p => panic!("not a slice with a rest pattern: {:?}", p),
+        }
+        assert_eq!(
+            body[pat_id("[d, _]")],
+            Pat::Slice {
+                prefix: vec![pat_id("d"), pat_id("_")],
+                slice: None,
+                suffix: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_lower_literals() {
+        check_literals(
+            r#"
+fn f() {
+    1; 2u32; 0x1fi64; 1f32; 2.5; 1e3f64;
+    'c'; b'c'; "s"; br"bs"; false;
+}"#,
+            &[
+                ("1", Expr::Literal(Literal::Int(UncertainIntTy::Unknown))),
+                (
+                    "2u32",
+                    Expr::Literal(Literal::Int(UncertainIntTy::Unsigned(UintTy::U32))),
+                ),
+                (
+                    "0x1fi64",
+                    Expr::Literal(Literal::Int(UncertainIntTy::Signed(IntTy::I64))),
+                ),
+                (
+                    "1f32",
+                    Expr::Literal(Literal::Float(UncertainFloatTy::Known(FloatTy::F32))),
+                ),
+                (
+                    "2.5",
+                    Expr::Literal(Literal::Float(UncertainFloatTy::Unknown)),
+                ),
+                (
+                    "1e3f64",
+                    Expr::Literal(Literal::Float(UncertainFloatTy::Known(FloatTy::F64))),
+                ),
+                ("'c'", Expr::Literal(Literal::Char)),
+                (
+                    "b'c'",
+                    Expr::Literal(Literal::Int(UncertainIntTy::Unsigned(UintTy::U8))),
+                ),
+                ("\"s\"", Expr::Literal(Literal::String)),
+                ("br\"bs\"", Expr::Literal(Literal::ByteString)),
+                ("false", Expr::Literal(Literal::Bool(false))),
+            ],
+        );
+    }
+}
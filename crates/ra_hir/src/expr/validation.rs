@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use ra_db::{Cancelable, QueryTimer};
+
+use crate::{
+    DefId, Def, HirDatabase, Module, Name,
+    expr::{Body, Expr, ExprId},
+};
+
+/// A structural error in a body, which is found without type inference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodyProblem {
+    BreakOutsideOfLoop,
+    ContinueOutsideOfLoop,
+    ReturnOutsideOfFunction,
+    /// The expression is the value of the field of a struct literal.
+    NoSuchField {
+        name: Name,
+    },
+}
+
+impl BodyProblem {
+    pub fn message(&self) -> String {
+        match self {
+            BodyProblem::BreakOutsideOfLoop => "`break` outside of a loop".to_string(),
+            BodyProblem::ContinueOutsideOfLoop => "`continue` outside of a loop".to_string(),
+            BodyProblem::ReturnOutsideOfFunction => "`return` outside of a function".to_string(),
+            BodyProblem::NoSuchField { name } => format!("no such field: `{}`", name),
+        }
+    }
+}
+
+pub(crate) fn validate_body(
+    db: &impl HirDatabase,
+    def_id: DefId,
+) -> Cancelable<Arc<Vec<(ExprId, BodyProblem)>>> {
+    let _timer = QueryTimer::new(db, "validate_body");
+    let body = db.body_hir(def_id)?;
+    let in_fn = match def_id.resolve(db)? {
+        Def::Function(_) => true,
+        _ => false,
+    };
+    let mut validator = BodyValidator {
+        db,
+        body: &body,
+        module: def_id.module(db)?,
+        problems: Vec::new(),
+    };
+    validator.validate_expr(body.body_expr(), in_fn)?;
+    Ok(Arc::new(validator.problems))
+}
+
+struct BodyValidator<'a, DB: HirDatabase> {
+    db: &'a DB,
+    body: &'a Body,
+    module: Module,
+    problems: Vec<(ExprId, BodyProblem)>,
+}
+
+impl<'a, DB: HirDatabase> BodyValidator<'a, DB> {
+    /// `in_fn` is whether the expression can return, that is whether it's in
+    /// the body of a function or of a closure.
+    fn validate_expr(&mut self, expr: ExprId, in_fn: bool) -> Cancelable<()> {
+        let body = self.body;
+        match &body[expr] {
+            Expr::Break { .. } if body.loop_target(expr).is_none() => {
+                self.problems.push((expr, BodyProblem::BreakOutsideOfLoop));
+            }
+            Expr::Continue { .. } if body.loop_target(expr).is_none() => {
+                self.problems
+                    .push((expr, BodyProblem::ContinueOutsideOfLoop));
+            }
+            Expr::Return { .. } if !in_fn => {
+                self.problems
+                    .push((expr, BodyProblem::ReturnOutsideOfFunction));
+            }
+            Expr::StructLit {
+                path: Some(path),
+                fields,
+                ..
+            } => {
+                let def_id = self.module.resolve_path(self.db, path)?.take_types();
+                if let Some(def_id) = def_id {
+                    if let Def::Struct(s) = def_id.resolve(self.db)? {
+                        let variant_data = s.variant_data(self.db)?;
+                        for field in fields {
+                            if variant_data.get_field_type_ref(&field.name).is_none() {
+                                let problem = BodyProblem::NoSuchField {
+                                    name: field.name.clone(),
+                                };
+                                self.problems.push((field.expr, problem));
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        let in_fn = match &body[expr] {
+            Expr::Lambda { .. } => true,
+            _ => in_fn,
+        };
+        let mut res = Ok(());
+        body[expr].walk_child_exprs(|it| {
+            if res.is_ok() {
+                res = self.validate_expr(it, in_fn);
+            }
+        });
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ra_db::SyntaxDatabase;
+    use ra_syntax::ast::{self, AstNode};
+
+    use crate::{HirDatabase, source_binder, mock::MockDatabase};
+
+    /// The problems of all bodies of the file, with the text of their
+    /// expressions.
+    fn check_problems(code: &str, expected: &[(&str, &str)]) {
+        let (db, _, file_id) = MockDatabase::with_single_file(code);
+        let source_file = db.source_file(file_id);
+        let mut def_ids = Vec::new();
+        for node in source_file.syntax().descendants() {
+            let def_id = if let Some(fn_def) = ast::FnDef::cast(node) {
+                source_binder::function_from_source(&db, file_id, fn_def)
+                    .unwrap()
+                    .map(|it| it.def_id())
+            } else if let Some(const_def) = ast::ConstDef::cast(node) {
+                source_binder::const_from_source(&db, file_id, const_def)
+                    .unwrap()
+                    .map(|it| it.def_id())
+            } else {
+                None
+            };
+            def_ids.extend(def_id);
+        }
+
+        let mut actual = Vec::new();
+        for def_id in def_ids {
+            let syntax_mapping = db.body_syntax_mapping(def_id).unwrap();
+            for (expr, problem) in db.validate_body(def_id).unwrap().iter() {
+                let ptr = syntax_mapping.expr_syntax(*expr).unwrap();
+                let text = ptr.resolve(&source_file).text().to_string();
+                actual.push((text, problem.message()));
+            }
+        }
+        let expected = expected
+            .iter()
+            .map(|(text, message)| (text.to_string(), message.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_break_and_continue_outside_of_loop() {
+        check_problems(
+            r"
+fn f() {
+    break;
+    loop { break; continue; }
+    while true { let g = || { continue; }; }
+    loop { break 'a; }
+}",
+            &[
+                ("break", "`break` outside of a loop"),
+                ("continue", "`continue` outside of a loop"),
+                ("break 'a", "`break` outside of a loop"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_return_outside_of_function() {
+        check_problems(
+            r"
+const A: u32 = { return 1; };
+const B: fn() -> u32 = || { return 1; };
+fn f() -> u32 { return 1; }",
+            &[("return 1", "`return` outside of a function")],
+        );
+    }
+
+    #[test]
+    fn test_no_such_field() {
+        check_problems(
+            r"
+struct S { a: u32 }
+fn f(b: u32) {
+    S { a: 1, b };
+    S { c: 2, ..S { a: 3 } };
+}",
+            &[("b", "no such field: `b`"), ("2", "no such field: `c`")],
+        );
+    }
+}
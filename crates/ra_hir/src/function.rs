@@ -5,15 +5,15 @@ use std::{
     sync::Arc,
 };
 
-use ra_db::Cancelable;
+use ra_db::{Cancelable, FileId};
 use ra_syntax::{
     TextRange, TextUnit,
     ast::{self, AstNode, DocCommentsOwner, NameOwner},
 };
 
-use crate::{DefId, DefKind, HirDatabase, ty::InferenceResult, Module, Crate, impl_block::ImplBlock, expr::{Body, BodySyntaxMapping}, type_ref::{TypeRef, Mutability}, Name};
+use crate::{DefId, DefKind, HirDatabase, ty::InferenceResult, Module, Crate, impl_block::ImplBlock, expr::{Body, BodySyntaxMapping, BodyParentMap}, type_ref::{TypeRef, Mutability}, Name, AsName};
 
-pub use self::scope::{FnScopes, ScopesWithSyntaxMapping};
+pub use self::scope::{ExprScopes, ScopeId, ScopesWithSyntaxMapping};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Function {
@@ -36,6 +36,17 @@ impl Function {
         ast::FnDef::cast(syntax.borrowed()).unwrap().owned()
     }
 
+    /// The file of the function, with its syntax.
+    pub fn source(&self, db: &impl HirDatabase) -> (FileId, ast::FnDefNode) {
+        let file_id = self
+            .def_id
+            .loc(db)
+            .source_item_id
+            .file_id
+            .as_original_file();
+        (file_id, self.syntax(db))
+    }
+
     pub fn body(&self, db: &impl HirDatabase) -> Cancelable<Arc<Body>> {
         db.body_hir(self.def_id)
     }
@@ -44,8 +55,12 @@ impl Function {
         db.body_syntax_mapping(self.def_id)
     }
 
+    pub fn body_parent_map(&self, db: &impl HirDatabase) -> Cancelable<Arc<BodyParentMap>> {
+        db.body_parent_map(self.def_id)
+    }
+
     pub fn scopes(&self, db: &impl HirDatabase) -> Cancelable<ScopesWithSyntaxMapping> {
-        let scopes = db.fn_scopes(self.def_id)?;
+        let scopes = db.expr_scopes(self.def_id)?;
         let syntax_mapping = db.body_syntax_mapping(self.def_id)?;
         Ok(ScopesWithSyntaxMapping {
             scopes,
@@ -83,11 +98,18 @@ impl Function {
 /// The declared signature of a function.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FnSignature {
+    name: Name,
     args: Vec<TypeRef>,
     ret_type: TypeRef,
+    /// Whether the first argument is `self`, which makes the function a method.
+    has_self_param: bool,
 }
 
 impl FnSignature {
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
     pub fn args(&self) -> &[TypeRef] {
         &self.args
     }
@@ -95,13 +117,22 @@ impl FnSignature {
     pub fn ret_type(&self) -> &TypeRef {
         &self.ret_type
     }
+
+    pub fn has_self_param(&self) -> bool {
+        self.has_self_param
+    }
 }
 
 pub(crate) fn fn_signature(db: &impl HirDatabase, def_id: DefId) -> Arc<FnSignature> {
     let func = Function::new(def_id);
     let syntax = func.syntax(db);
     let node = syntax.borrowed();
+    let name = node
+        .name()
+        .map(|n| n.as_name())
+        .unwrap_or_else(Name::missing);
     let mut args = Vec::new();
+    let mut has_self_param = false;
     if let Some(param_list) = node.param_list() {
         if let Some(self_param) = param_list.self_param() {
             let self_type = if let Some(type_ref) = self_param.type_ref() {
@@ -119,6 +150,7 @@ pub(crate) fn fn_signature(db: &impl HirDatabase, def_id: DefId) -> Arc<FnSignat
                 }
             };
             args.push(self_type);
+            has_self_param = true;
         }
         for param in param_list.params() {
             let type_ref = TypeRef::from_ast_opt(param.type_ref());
@@ -130,7 +162,12 @@ pub(crate) fn fn_signature(db: &impl HirDatabase, def_id: DefId) -> Arc<FnSignat
     } else {
         TypeRef::unit()
     };
-    let sig = FnSignature { args, ret_type };
+    let sig = FnSignature {
+        name,
+        args,
+        ret_type,
+        has_self_param,
+    };
     Arc::new(sig)
 }
 
@@ -17,7 +17,7 @@ pub struct ScopeId(RawId);
 impl_arena_id!(ScopeId);
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct FnScopes {
+pub struct ExprScopes {
     body: Arc<Body>,
     scopes: Arena<ScopeId, ScopeData>,
     scope_for: FxHashMap<ExprId, ScopeId>,
@@ -35,9 +35,9 @@ pub struct ScopeData {
     entries: Vec<ScopeEntry>,
 }
 
-impl FnScopes {
-    pub(crate) fn new(body: Arc<Body>) -> FnScopes {
-        let mut scopes = FnScopes {
+impl ExprScopes {
+    pub(crate) fn new(body: Arc<Body>) -> ExprScopes {
+        let mut scopes = ExprScopes {
             body: body.clone(),
             scopes: Arena::default(),
             scope_for: FxHashMap::default(),
@@ -51,9 +51,26 @@ impl FnScopes {
         &self.scopes[scope].entries
     }
     pub fn scope_chain_for<'a>(&'a self, expr: ExprId) -> impl Iterator<Item = ScopeId> + 'a {
-        generate(self.scope_for(expr), move |&scope| {
-            self.scopes[scope].parent
-        })
+        self.scope_chain(self.scope_for(expr))
+    }
+
+    /// The scope and its ancestors, innermost first.
+    pub fn scope_chain<'a>(&'a self, scope: Option<ScopeId>) -> impl Iterator<Item = ScopeId> + 'a {
+        generate(scope, move |&scope| self.scopes[scope].parent)
+    }
+
+    /// The innermost scope containing the expression.
+    pub fn scope_for(&self, expr: ExprId) -> Option<ScopeId> {
+        self.scope_for.get(&expr).map(|&scope| scope)
+    }
+
+    /// The binding which `name` refers to in `scope`. Bindings of inner
+    /// scopes and later bindings of the same scope shadow the other ones.
+    pub fn resolve_name_in_scope(&self, scope: ScopeId, name: &Name) -> Option<PatId> {
+        self.scope_chain(Some(scope))
+            .flat_map(|scope| self.entries(scope).iter().rev())
+            .find(|entry| entry.name() == name)
+            .map(|entry| entry.pat())
     }
 
     pub fn resolve_local_name<'a>(
@@ -84,10 +101,15 @@ impl FnScopes {
     }
     fn add_bindings(&mut self, body: &Body, scope: ScopeId, pat: PatId) {
         match &body[pat] {
-            Pat::Bind { name } => self.scopes[scope].entries.push(ScopeEntry {
-                name: name.clone(),
-                pat,
-            }),
+            Pat::Bind { name, subpat, .. } => {
+                self.scopes[scope].entries.push(ScopeEntry {
+                    name: name.clone(),
+                    pat,
+                });
+                if let Some(subpat) = subpat {
+                    self.add_bindings(body, scope, *subpat);
+                }
+            }
             p => p.walk_child_pats(|pat| self.add_bindings(body, scope, pat)),
         }
     }
@@ -100,15 +122,12 @@ impl FnScopes {
     fn set_scope(&mut self, node: ExprId, scope: ScopeId) {
         self.scope_for.insert(node, scope);
     }
-    fn scope_for(&self, expr: ExprId) -> Option<ScopeId> {
-        self.scope_for.get(&expr).map(|&scope| scope)
-    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ScopesWithSyntaxMapping {
     pub syntax_mapping: Arc<BodySyntaxMapping>,
-    pub scopes: Arc<FnScopes>,
+    pub scopes: Arc<ExprScopes>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -234,7 +253,7 @@ fn compute_block_scopes(
     statements: &[Statement],
     tail: Option<ExprId>,
     body: &Body,
-    scopes: &mut FnScopes,
+    scopes: &mut ExprScopes,
     mut scope: ScopeId,
 ) {
     for stmt in statements {
@@ -260,7 +279,7 @@ fn compute_block_scopes(
     }
 }
 
-fn compute_expr_scopes(expr: ExprId, body: &Body, scopes: &mut FnScopes, scope: ScopeId) {
+fn compute_expr_scopes(expr: ExprId, body: &Body, scopes: &mut ExprScopes, scope: ScopeId) {
     scopes.set_scope(expr, scope);
     match &body[expr] {
         Expr::Block { statements, tail } => {
@@ -270,6 +289,7 @@ fn compute_expr_scopes(expr: ExprId, body: &Body, scopes: &mut FnScopes, scope:
             iterable,
             pat,
             body: body_expr,
+            ..
         } => {
             compute_expr_scopes(*iterable, body, scopes, scope);
             let scope = scopes.new_scope(scope);
@@ -292,6 +312,9 @@ fn compute_expr_scopes(expr: ExprId, body: &Body, scopes: &mut FnScopes, scope:
                 for pat in &arm.pats {
                     scopes.add_bindings(body, scope, *pat);
                 }
+                if let Some(guard) = arm.guard {
+                    compute_expr_scopes(guard, body, scopes, scope);
+                }
                 scopes.set_scope(arm.expr, scope);
                 compute_expr_scopes(arm.expr, body, scopes, scope);
             }
@@ -330,7 +353,7 @@ mod tests {
         let marker: ast::PathExpr = find_node_at_offset(file.syntax(), off).unwrap();
         let fn_def: ast::FnDef = find_node_at_offset(file.syntax(), off).unwrap();
         let body_hir = expr::collect_fn_body_syntax(fn_def);
-        let scopes = FnScopes::new(Arc::clone(body_hir.body()));
+        let scopes = ExprScopes::new(Arc::clone(body_hir.body()));
         let scopes = ScopesWithSyntaxMapping {
             scopes: Arc::new(scopes),
             syntax_mapping: Arc::new(body_hir),
@@ -409,6 +432,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_struct_pat_scope() {
+        do_check(
+            r"
+            fn quux() {
+                let Foo { a, b: x, ref c, .. } = foo;
+                <|>
+            }",
+            &["x", "a", "c"],
+        );
+    }
+
+    #[test]
+    fn test_bind_pat_subpat_scope() {
+        do_check(
+            r"
+            fn quux() {
+                match foo {
+                    x @ Some(y) => <|>,
+                }
+            }",
+            &["x", "y"],
+        );
+    }
+
+    #[test]
+    fn test_slice_pat_scope() {
+        do_check(
+            r"
+            fn quux() {
+                let [first, rest @ .., last] = foo;
+                <|>
+            }",
+            &["first", "rest", "last"],
+        );
+    }
+
+    #[test]
+    fn test_match_guard() {
+        do_check(
+            r"
+            fn quux() {
+                match () {
+                    Some(x) if <|> => {}
+                };
+            }",
+            &["x"],
+        );
+    }
+
     #[test]
     fn test_shadow_variable() {
         do_check(
@@ -427,7 +500,7 @@ mod tests {
         let name_ref: ast::NameRef = find_node_at_offset(file.syntax(), off).unwrap();
 
         let body_hir = expr::collect_fn_body_syntax(fn_def);
-        let scopes = FnScopes::new(Arc::clone(body_hir.body()));
+        let scopes = ExprScopes::new(Arc::clone(body_hir.body()));
         let scopes = ScopesWithSyntaxMapping {
             scopes: Arc::new(scopes),
             syntax_mapping: Arc::new(body_hir),
@@ -456,6 +529,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_local_name_in_slice_pat() {
+        do_check_local_name(
+            r#"
+            fn foo(x: &[i32]) {
+                match x {
+                    [first, rest @ ..] => first<|>,
+                }
+            }"#,
+            82,
+        );
+    }
+
+    #[test]
+    fn test_resolve_local_name_in_box_pat() {
+        do_check_local_name(
+            r#"
+            fn foo(x: Box<Foo>) {
+                let box Foo { box a } = x;
+                a<|>
+            }"#,
+            69,
+        );
+    }
+
     #[test]
     fn test_resolve_local_name_declaration() {
         do_check_local_name(
@@ -478,4 +576,55 @@ mod tests {
             46,
         );
     }
+
+    fn do_check_name_in_scope(code: &str, expected_offset: u32) {
+        let (off, code) = extract_offset(code);
+        let file = SourceFileNode::parse(&code);
+        let fn_def: ast::FnDef = find_node_at_offset(file.syntax(), off).unwrap();
+        let path_expr: ast::PathExpr = find_node_at_offset(file.syntax(), off).unwrap();
+        let name = path_expr.syntax().text().to_string();
+
+        let body_hir = expr::collect_fn_body_syntax(fn_def);
+        let scopes = ExprScopes::new(Arc::clone(body_hir.body()));
+        let expr = body_hir
+            .node_expr(ast::Expr::cast(path_expr.syntax()).unwrap())
+            .unwrap();
+        let scope = scopes.scope_for(expr).unwrap();
+        let pat = scopes
+            .resolve_name_in_scope(scope, &Name::new(name.into()))
+            .unwrap();
+
+        let expected_name =
+            find_node_at_offset::<ast::Name>(file.syntax(), expected_offset.into()).unwrap();
+        assert_eq!(
+            body_hir.pat_syntax(pat).unwrap().range(),
+            expected_name.syntax().range()
+        );
+    }
+
+    #[test]
+    fn test_resolve_name_in_scope() {
+        do_check_name_in_scope(
+            r"
+            fn foo(x: i32) {
+                let y = 1;
+                match y {
+                    x => { let y = x; y<|> }
+                }
+            }",
+            114,
+        );
+    }
+
+    #[test]
+    fn test_resolve_name_in_scope_shadowing_param() {
+        do_check_name_in_scope(
+            r"
+            fn foo(x: i32) {
+                let x = x;
+                |y| x<|>
+            }",
+            51,
+        );
+    }
 }
@@ -2,7 +2,7 @@ use ra_db::{SourceRootId, LocationIntener, Cancelable, FileId};
 use ra_syntax::{SourceFileNode, SyntaxKind, SyntaxNode, SyntaxNodeRef, SourceFile, AstNode, ast};
 use ra_arena::{Arena, RawId, impl_arena_id};
 
-use crate::{HirDatabase, PerNs, ModuleId, Def, Function, Struct, Enum, ImplBlock, Crate};
+use crate::{HirDatabase, PerNs, ModuleId, Def, Function, Struct, Enum, Const, Static, Trait, TypeAlias, ImplBlock, Crate};
 
 use crate::code_model_api::Module;
 
@@ -139,6 +139,10 @@ pub(crate) enum DefKind {
     Function,
     Struct,
     Enum,
+    Const,
+    Static,
+    Trait,
+    TypeAlias,
     Item,
 
     StructCtor,
@@ -168,6 +172,10 @@ impl DefId {
                 let enum_def = Enum::new(self);
                 Def::Enum(enum_def)
             }
+            DefKind::Const => Def::Const(Const::new(self)),
+            DefKind::Static => Def::Static(Static::new(self)),
+            DefKind::Trait => Def::Trait(Trait::new(self)),
+            DefKind::TypeAlias => Def::TypeAlias(TypeAlias::new(self)),
             DefKind::StructCtor => Def::Item,
             DefKind::Item => Def::Item,
         };
@@ -206,11 +214,12 @@ impl DefKind {
             SyntaxKind::MODULE => PerNs::types(DefKind::Module),
             SyntaxKind::STRUCT_DEF => PerNs::both(DefKind::Struct, DefKind::StructCtor),
             SyntaxKind::ENUM_DEF => PerNs::types(DefKind::Enum),
+            SyntaxKind::CONST_DEF => PerNs::values(DefKind::Const),
+            SyntaxKind::STATIC_DEF => PerNs::values(DefKind::Static),
+            SyntaxKind::TRAIT_DEF => PerNs::types(DefKind::Trait),
+            SyntaxKind::TYPE_DEF => PerNs::types(DefKind::TypeAlias),
             // These define items, but don't have their own DefKinds yet:
-            SyntaxKind::TRAIT_DEF => PerNs::types(DefKind::Item),
-            SyntaxKind::TYPE_DEF => PerNs::types(DefKind::Item),
-            SyntaxKind::CONST_DEF => PerNs::values(DefKind::Item),
-            SyntaxKind::STATIC_DEF => PerNs::values(DefKind::Item),
+            SyntaxKind::TRAIT_ALIAS_DEF => PerNs::types(DefKind::Item),
             _ => PerNs::none(),
         }
     }
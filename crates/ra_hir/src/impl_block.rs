@@ -2,15 +2,16 @@ use std::sync::Arc;
 use rustc_hash::FxHashMap;
 
 use ra_arena::{Arena, RawId, impl_arena_id};
-use ra_syntax::ast::{self, AstNode};
+use ra_syntax::ast::{self, AstNode, NameOwner};
 use ra_db::{LocationIntener, Cancelable, SourceRootId};
 
 use crate::{
     DefId, DefLoc, DefKind, SourceItemId, SourceFileItems,
-    Function,
+    Function, Path, AsName,
     db::HirDatabase,
     type_ref::TypeRef,
     module_tree::ModuleId,
+    proc_macro::{self, ProcMacroExpander},
 };
 
 use crate::code_model_api::{Module, ModuleSource};
@@ -33,6 +34,13 @@ impl ImplBlock {
         })
     }
 
+    pub(crate) fn from_id(module_impl_blocks: Arc<ModuleImplBlocks>, impl_id: ImplId) -> ImplBlock {
+        ImplBlock {
+            module_impl_blocks,
+            impl_id,
+        }
+    }
+
     fn impl_data(&self) -> &ImplData {
         &self.module_impl_blocks.impls[self.impl_id]
     }
@@ -58,13 +66,21 @@ pub struct ImplData {
 }
 
 impl ImplData {
+    pub(crate) fn target_trait(&self) -> Option<&TypeRef> {
+        self.target_trait.as_ref()
+    }
+
+    pub(crate) fn target_type(&self) -> &TypeRef {
+        &self.target_type
+    }
+
     pub(crate) fn from_ast(
         db: &impl AsRef<LocationIntener<DefLoc, DefId>>,
         file_items: &SourceFileItems,
         module: &Module,
         node: ast::ImplBlock,
     ) -> Self {
-        let target_trait = node.target_type().map(TypeRef::from_ast);
+        let target_trait = node.target_trait().map(TypeRef::from_ast);
         let target_type = TypeRef::from_ast_opt(node.target_type());
         let module_loc = module.def_id.loc(db);
         let items = if let Some(item_list) = node.item_list() {
@@ -73,7 +89,7 @@ impl ImplData {
                 .map(|item_node| {
                     let kind = match item_node {
                         ast::ImplItem::FnDef(..) => DefKind::Function,
-                        ast::ImplItem::ConstDef(..) => DefKind::Item,
+                        ast::ImplItem::ConstDef(..) => DefKind::Const,
                         ast::ImplItem::TypeDef(..) => DefKind::Item,
                     };
                     let item_id = file_items.id_of_unchecked(item_node.syntax());
@@ -103,6 +119,25 @@ impl ImplData {
             items,
         }
     }
+
+    /// The impls generated by the derives of a struct or an enum. We don't
+    /// know their items, only the implemented traits.
+    fn derived(expander: &dyn ProcMacroExpander, node: ast::NominalDef) -> Vec<ImplData> {
+        let name = match node.name() {
+            Some(it) => it.as_name(),
+            None => return Vec::new(),
+        };
+        let target_type = TypeRef::Path(Path::from(name));
+        proc_macro::derives(node)
+            .iter()
+            .flat_map(|derive| expander.expand_derive(derive, node))
+            .map(|target_trait| ImplData {
+                target_trait: Some(TypeRef::Path(target_trait)),
+                target_type: target_type.clone(),
+                items: Vec::new(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -132,9 +167,8 @@ impl_arena_id!(ImplId);
 /// per-module; then we build an index of all impl blocks in the crate. This
 /// way, we avoid having to do this process for the whole crate whenever someone
 /// types in any file; as long as the impl blocks in the file don't change, we
-/// don't need to do the second step again.
-///
-/// (The second step does not yet exist currently.)
+/// don't need to do the second step again. The second step is in
+/// `ty::method_resolution`.
 #[derive(Debug, PartialEq, Eq)]
 pub struct ModuleImplBlocks {
     impls: Arena<ImplId, ImplData>,
@@ -149,6 +183,10 @@ impl ModuleImplBlocks {
         }
     }
 
+    pub(crate) fn impls<'a>(&'a self) -> impl Iterator<Item = (ImplId, &'a ImplData)> + 'a {
+        self.impls.iter()
+    }
+
     fn collect(&mut self, db: &impl HirDatabase, module: Module) -> Cancelable<()> {
         let (file_id, module_source) = module.defenition_source(db)?;
         let node = match &module_source {
@@ -166,6 +204,13 @@ impl ModuleImplBlocks {
             }
         }
 
+        let expander: &dyn ProcMacroExpander = db.as_ref();
+        for nominal_def in node.children().filter_map(ast::NominalDef::cast) {
+            for impl_block in ImplData::derived(expander, nominal_def) {
+                self.impls.alloc(impl_block);
+            }
+        }
+
         Ok(())
     }
 }
@@ -180,3 +225,53 @@ pub(crate) fn impls_in_module(
     result.collect(db, module)?;
     Ok(Arc::new(result))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Name, Path, PathKind, PathSegment, source_binder, type_ref::TypeRef};
+    use crate::{db::HirDatabase, mock::{MockDatabase, WORKSPACE}};
+
+    fn path(segments: &[&str]) -> TypeRef {
+        TypeRef::Path(Path {
+            kind: PathKind::Plain,
+            segments: segments
+                .iter()
+                .map(|&it| PathSegment {
+                    name: Name::new(it.into()),
+                    args_and_bindings: None,
+                })
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn derives_register_impls() {
+        let (db, _, file_id) = MockDatabase::with_single_file(
+            "
+            #[derive(Clone, serde::Serialize)]
+            struct Foo;
+
+            #[derive(Debug)]
+            enum Bar {}
+            ",
+        );
+        let module = source_binder::module_from_file_id(&db, file_id)
+            .unwrap()
+            .unwrap();
+        let module_id = module.def_id.loc(&db).module_id;
+        let impls = db.impls_in_module(WORKSPACE, module_id).unwrap();
+        let impls = impls
+            .impls
+            .iter()
+            .map(|(_, it)| (it.target_trait.clone().unwrap(), it.target_type.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            impls,
+            vec![
+                (path(&["Clone"]), path(&["Foo"])),
+                (path(&["serde", "Serialize"]), path(&["Foo"])),
+                (path(&["Debug"]), path(&["Bar"])),
+            ]
+        );
+    }
+}
@@ -27,10 +27,14 @@ mod name;
 mod module_tree;
 mod nameres;
 mod function;
+mod consts;
+mod traits;
+mod type_alias;
 mod adt;
 mod type_ref;
 mod ty;
 mod impl_block;
+mod proc_macro;
 mod expr;
 
 mod code_model_api;
@@ -43,16 +47,20 @@ use crate::{
 };
 
 pub use self::{
-    path::{Path, PathKind},
+    path::{Path, PathKind, PathSegment, GenericArgs, GenericArg},
     name::Name,
     ids::{HirFileId, DefId, DefLoc, MacroCallId, MacroCallLoc},
     macros::{MacroDef, MacroInput, MacroExpansion},
     module_tree::ModuleId,
     nameres::{ItemMap, PerNs, Namespace, Resolution},
-    function::{Function, FnSignature, FnScopes, ScopesWithSyntaxMapping},
+    function::{Function, FnSignature, ExprScopes, ScopeId, ScopesWithSyntaxMapping},
     adt::{Struct, Enum},
+    consts::{Const, Static},
+    traits::Trait,
+    type_alias::TypeAlias,
     ty::Ty,
     impl_block::{ImplBlock, ImplItem},
+    proc_macro::{ProcMacroExpander, DeriveNamePassthrough},
 };
 
 pub use self::function::FnSignatureInfo;
@@ -67,5 +75,9 @@ pub enum Def {
     Function(Function),
     Struct(Struct),
     Enum(Enum),
+    Const(Const),
+    Static(Static),
+    Trait(Trait),
+    TypeAlias(TypeAlias),
     Item,
 }
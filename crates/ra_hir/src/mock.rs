@@ -2,11 +2,11 @@ use std::sync::Arc;
 
 use parking_lot::Mutex;
 use salsa::{self, Database};
-use ra_db::{LocationIntener, BaseDatabase, FilePosition, FileId, CrateGraph, SourceRoot, SourceRootId};
+use ra_db::{LocationIntener, BaseDatabase, FilePosition, FileId, CfgOptions, CrateGraph, Edition, Env, Rope, SourceRoot, SourceRootId};
 use relative_path::RelativePathBuf;
 use test_utils::{parse_fixture, CURSOR_MARKER, extract_offset};
 
-use crate::{db, DefId, DefLoc, MacroCallId, MacroCallLoc};
+use crate::{db, DefId, DefLoc, MacroCallId, MacroCallLoc, DeriveNamePassthrough, ProcMacroExpander};
 
 pub const WORKSPACE: SourceRootId = SourceRootId(0);
 
@@ -15,6 +15,7 @@ pub(crate) struct MockDatabase {
     events: Mutex<Option<Vec<salsa::Event<MockDatabase>>>>,
     runtime: salsa::Runtime<MockDatabase>,
     id_maps: Arc<IdMaps>,
+    proc_macro_expander: Arc<dyn ProcMacroExpander>,
 }
 
 impl MockDatabase {
@@ -32,7 +33,13 @@ impl MockDatabase {
             .set(WORKSPACE, Arc::new(source_root.clone()));
 
         let mut crate_graph = CrateGraph::default();
-        crate_graph.add_crate_root(file_id);
+        crate_graph.add_crate_root(
+            file_id,
+            Edition::Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        );
         db.set_crate_graph(crate_graph);
         (db, source_root, file_id)
     }
@@ -74,7 +81,7 @@ impl MockDatabase {
         assert!(path.starts_with('/'));
         let path = RelativePathBuf::from_path(&path[1..]).unwrap();
         let file_id = FileId(source_root.files.len() as u32);
-        let text = Arc::new(text.to_string());
+        let text = Rope::from_str(text);
         self.query_mut(ra_db::FileTextQuery).set(file_id, text);
         self.query_mut(ra_db::FileRelativePathQuery)
             .set(file_id, path.clone());
@@ -121,12 +128,11 @@ impl Default for MockDatabase {
             events: Default::default(),
             runtime: salsa::Runtime::default(),
             id_maps: Default::default(),
+            proc_macro_expander: Arc::new(DeriveNamePassthrough),
         };
         db.query_mut(ra_db::CrateGraphQuery)
             .set((), Default::default());
-        db.query_mut(ra_db::LocalRootsQuery)
-            .set((), Default::default());
-        db.query_mut(ra_db::LibraryRootsQuery)
+        db.query_mut(ra_db::SourceRootsQuery)
             .set((), Default::default());
         db
     }
@@ -138,6 +144,7 @@ impl salsa::ParallelDatabase for MockDatabase {
             events: Default::default(),
             runtime: self.runtime.snapshot(self),
             id_maps: self.id_maps.clone(),
+            proc_macro_expander: self.proc_macro_expander.clone(),
         })
     }
 }
@@ -155,6 +162,12 @@ impl AsRef<LocationIntener<MacroCallLoc, MacroCallId>> for MockDatabase {
     }
 }
 
+impl AsRef<dyn ProcMacroExpander> for MockDatabase {
+    fn as_ref(&self) -> &(dyn ProcMacroExpander + 'static) {
+        &*self.proc_macro_expander
+    }
+}
+
 impl MockDatabase {
     pub(crate) fn log(&self, f: impl FnOnce()) -> Vec<salsa::Event<MockDatabase>> {
         *self.events.lock() = Some(Vec::new());
@@ -184,9 +197,11 @@ salsa::database_storage! {
             fn file_relative_path() for ra_db::FileRelativePathQuery;
             fn file_source_root() for ra_db::FileSourceRootQuery;
             fn source_root() for ra_db::SourceRootQuery;
+            fn source_roots() for ra_db::SourceRootsQuery;
             fn local_roots() for ra_db::LocalRootsQuery;
             fn library_roots() for ra_db::LibraryRootsQuery;
             fn crate_graph() for ra_db::CrateGraphQuery;
+            fn crate_cfg() for ra_db::CrateCfgQuery;
         }
         impl ra_db::SyntaxDatabase {
             fn source_file() for ra_db::SourceFileQuery;
@@ -196,7 +211,7 @@ salsa::database_storage! {
             fn hir_source_file() for db::HirSourceFileQuery;
             fn expand_macro_invocation() for db::ExpandMacroCallQuery;
             fn module_tree() for db::ModuleTreeQuery;
-            fn fn_scopes() for db::FnScopesQuery;
+            fn expr_scopes() for db::ExprScopesQuery;
             fn file_items() for db::SourceFileItemsQuery;
             fn file_item() for db::FileItemQuery;
             fn input_module_items() for db::InputModuleItemsQuery;
@@ -207,9 +222,13 @@ salsa::database_storage! {
             fn type_for_field() for db::TypeForFieldQuery;
             fn struct_data() for db::StructDataQuery;
             fn enum_data() for db::EnumDataQuery;
+            fn trait_data() for db::TraitDataQuery;
             fn impls_in_module() for db::ImplsInModuleQuery;
+            fn impls_in_crate() for db::ImplsInCrateQuery;
             fn body_hir() for db::BodyHirQuery;
             fn body_syntax_mapping() for db::BodySyntaxMappingQuery;
+            fn body_parent_map() for db::BodyParentMapQuery;
+            fn validate_body() for db::ValidateBodyQuery;
             fn fn_signature() for db::FnSignatureQuery;
         }
     }
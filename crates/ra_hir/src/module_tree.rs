@@ -3,10 +3,10 @@ use std::sync::Arc;
 use rustc_hash::{FxHashMap, FxHashSet};
 use arrayvec::ArrayVec;
 use relative_path::RelativePathBuf;
-use ra_db::{FileId, SourceRootId, Cancelable, SourceRoot};
+use ra_db::{FileId, SourceRootId, Cancelable, SourceRoot, QueryTimer};
 use ra_syntax::{
     algo::generate,
-    ast::{self, AstNode, NameOwner},
+    ast::{self, AstNode, AttrsOwner, NameOwner},
     SyntaxNode,
 };
 use ra_arena::{Arena, RawId, impl_arena_id};
@@ -35,6 +35,8 @@ impl ModuleSource {
 pub struct Submodule {
     name: Name,
     is_declaration: bool,
+    /// Declared with `#[cfg(test)]`.
+    is_cfg_test: bool,
     source: SourceItemId,
 }
 
@@ -67,6 +69,7 @@ impl Submodule {
                 .map(|(name, m)| Submodule {
                     name,
                     is_declaration: m.has_semi(),
+                    is_cfg_test: is_cfg_test(m),
                     source: SourceItemId {
                         file_id,
                         item_id: Some(file_items.id_of(file_id, m.syntax())),
@@ -110,6 +113,7 @@ struct LinkData {
     source: SourceItemId,
     owner: ModuleId,
     name: Name,
+    is_cfg_test: bool,
     points_to: Vec<ModuleId>,
     problem: Option<Problem>,
 }
@@ -119,6 +123,7 @@ impl ModuleTree {
         db: &impl HirDatabase,
         source_root: SourceRootId,
     ) -> Cancelable<Arc<ModuleTree>> {
+        let _timer = QueryTimer::new(db, "module_tree");
         db.check_canceled()?;
         let res = create_module_tree(db, source_root)?;
         Ok(Arc::new(res))
@@ -150,6 +155,14 @@ impl ModuleId {
             .last()
             .unwrap()
     }
+    /// Checks if the module is test code: if it, or one of its parents, is
+    /// declared with `#[cfg(test)]`.
+    pub(crate) fn is_cfg_test(self, tree: &ModuleTree) -> bool {
+        generate(self.parent_link(tree), |&link| {
+            link.owner(tree).parent_link(tree)
+        })
+        .any(|link| tree.links[link].is_cfg_test)
+    }
     pub(crate) fn child(self, tree: &ModuleTree, name: &Name) -> Option<ModuleId> {
         let link = tree.mods[self]
             .children
@@ -213,6 +226,13 @@ impl ModuleTree {
     }
 }
 
+fn is_cfg_test(module: ast::Module) -> bool {
+    module.attrs().any(|attr| match attr.as_call() {
+        Some((name, args)) => name == "cfg" && args.syntax().text() == "(test)",
+        None => false,
+    })
+}
+
 fn modules<'a>(
     root: impl ast::ModuleItemOwner<'a>,
 ) -> impl Iterator<Item = (Name, ast::Module<'a>)> {
@@ -238,6 +258,7 @@ fn create_module_tree<'a>(
 
     let source_root = db.source_root(source_root);
     for &file_id in source_root.files.values() {
+        db.check_canceled()?;
         let source = SourceItemId {
             file_id: file_id.into(),
             item_id: None,
@@ -279,6 +300,7 @@ fn build_subtree(
         let link = tree.push_link(LinkData {
             source: sub.source,
             name: sub.name.clone(),
+            is_cfg_test: sub.is_cfg_test,
             owner: id,
             points_to: Vec::new(),
             problem: None,
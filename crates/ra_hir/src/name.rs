@@ -23,7 +23,7 @@ impl fmt::Debug for Name {
 }
 
 impl Name {
-    fn new(text: SmolStr) -> Name {
+    pub(crate) fn new(text: SmolStr) -> Name {
         Name { text }
     }
 
@@ -59,6 +59,9 @@ impl Name {
             "u128" => KnownName::U128,
             "f32" => KnownName::F32,
             "f64" => KnownName::F64,
+            "bool" => KnownName::Bool,
+            "char" => KnownName::Char,
+            "str" => KnownName::Str,
             "Self" => KnownName::SelfType,
             "self" => KnownName::SelfParam,
             _ => return None,
@@ -83,6 +86,12 @@ impl AsName for ast::Name<'_> {
     }
 }
 
+impl AsName for ast::Lifetime<'_> {
+    fn as_name(&self) -> Name {
+        Name::new(self.text())
+    }
+}
+
 impl AsName for ra_db::Dependency {
     fn as_name(&self) -> Name {
         Name::new(self.name.clone())
@@ -113,6 +122,10 @@ pub(crate) enum KnownName {
     F32,
     F64,
 
+    Bool,
+    Char,
+    Str,
+
     SelfType,
     SelfParam,
 }
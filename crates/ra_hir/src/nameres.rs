@@ -230,6 +230,9 @@ impl InputModuleItems {
             ast::ModuleItem::TraitDef(it) => {
                 self.items.push(ModuleItem::new(file_id, file_items, it)?)
             }
+            ast::ModuleItem::TraitAliasDef(it) => {
+                self.items.push(ModuleItem::new(file_id, file_items, it)?)
+            }
             ast::ModuleItem::TypeDef(it) => {
                 self.items.push(ModuleItem::new(file_id, file_items, it)?)
             }
@@ -319,11 +322,11 @@ where
 
     pub(crate) fn resolve(mut self) -> Cancelable<ItemMap> {
         for (&module_id, items) in self.input.iter() {
+            self.db.check_canceled()?;
             self.populate_module(module_id, Arc::clone(items))?;
         }
 
         for &module_id in self.input.keys() {
-            self.db.check_canceled()?;
             self.resolve_imports(module_id)?;
         }
         Ok(self.result)
@@ -344,7 +347,14 @@ where
             if let Some(crate_id) = crate_graph.crate_id_for_crate_root(file_id.as_original_file())
             {
                 let krate = Crate::new(crate_id);
+                let is_test_code = crate_graph.cfg_options(crate_id).check_atom("test")
+                    || module_id.is_cfg_test(&self.module_tree);
                 for dep in krate.dependencies(self.db)? {
+                    // The macros of proc-macro crates are not supported yet,
+                    // and nothing else can be imported from them.
+                    if dep.krate.is_proc_macro(self.db) || (dep.is_dev && !is_test_code) {
+                        continue;
+                    }
                     if let Some(module) = dep.krate.root_module(self.db)? {
                         let def_id = module.def_id;
                         self.add_module_item(
@@ -357,10 +367,10 @@ where
             };
         }
         for import in input.imports.iter() {
-            if let Some(name) = import.path.segments.iter().last() {
+            if let Some(segment) = import.path.segments.iter().last() {
                 if let ImportKind::Named(import) = import.kind {
                     module_items.items.insert(
-                        name.clone(),
+                        segment.name.clone(),
                         Resolution {
                             def_id: PerNs::none(),
                             import: Some(import),
@@ -419,6 +429,7 @@ where
 
     fn resolve_imports(&mut self, module_id: ModuleId) -> Cancelable<()> {
         for import in self.input[&module_id].imports.iter() {
+            self.db.check_canceled()?;
             self.resolve_import(module_id, import)?;
         }
         Ok(())
@@ -442,7 +453,8 @@ where
             PathKind::Crate => module_id.crate_root(&self.module_tree),
         };
 
-        for (i, name) in import.path.segments.iter().enumerate() {
+        for (i, segment) in import.path.segments.iter().enumerate() {
+            let name = &segment.name;
             let is_last = i == import.path.segments.len() - 1;
 
             let def_id = match self.result.per_module[&curr].items.get(name) {
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use salsa::Database;
-use ra_db::{FilesDatabase, CrateGraph};
+use ra_db::{FilesDatabase, CfgOptions, CrateGraph, CrateId, Edition, Env, FileId, Rope};
 use relative_path::RelativePath;
 use test_utils::assert_eq_text;
 
@@ -50,6 +50,24 @@ fn check_module_item_map(map: &hir::ItemMap, module_id: hir::ModuleId, expected:
     }
 }
 
+/// A crate graph with a crate for each of the files, which don't depend on
+/// each other yet.
+fn two_crates(first: FileId, second: FileId) -> (CrateGraph, CrateId, CrateId) {
+    let mut crate_graph = CrateGraph::default();
+    let mut add_crate_root = |file_id| {
+        crate_graph.add_crate_root(
+            file_id,
+            Edition::Edition2018,
+            None,
+            CfgOptions::default(),
+            Env::default(),
+        )
+    };
+    let first = add_crate_root(first);
+    let second = add_crate_root(second);
+    (crate_graph, first, second)
+}
+
 #[test]
 fn item_map_smoke_test() {
     let (item_map, module_id) = item_map(
@@ -106,6 +124,30 @@ fn item_map_contains_items_from_expansions() {
     );
 }
 
+#[test]
+fn item_map_contains_trait_aliases() {
+    let (item_map, module_id) = item_map(
+        "
+        //- /lib.rs
+        mod foo;
+
+        use crate::foo::Alias;
+        <|>
+
+        //- /foo.rs
+        pub trait Alias = Clone + Copy;
+    ",
+    );
+    check_module_item_map(
+        &item_map,
+        module_id,
+        "
+            Alias: t
+            foo: t
+        ",
+    );
+}
+
 #[test]
 fn item_map_using_self() {
     let (item_map, module_id) = item_map(
@@ -144,10 +186,49 @@ fn item_map_across_crates() {
     let main_id = sr.files[RelativePath::new("/main.rs")];
     let lib_id = sr.files[RelativePath::new("/lib.rs")];
 
-    let mut crate_graph = CrateGraph::default();
-    let main_crate = crate_graph.add_crate_root(main_id);
-    let lib_crate = crate_graph.add_crate_root(lib_id);
-    crate_graph.add_dep(main_crate, "test_crate".into(), lib_crate);
+    let (mut crate_graph, main_crate, lib_crate) = two_crates(main_id, lib_id);
+    crate_graph
+        .add_dep(main_crate, "test_crate".into(), lib_crate)
+        .unwrap();
+
+    db.set_crate_graph(crate_graph);
+
+    let source_root = db.file_source_root(main_id);
+    let module = hir::source_binder::module_from_file_id(&db, main_id)
+        .unwrap()
+        .unwrap();
+    let module_id = module.def_id.loc(&db).module_id;
+    let item_map = db.item_map(source_root).unwrap();
+
+    check_module_item_map(
+        &item_map,
+        module_id,
+        "
+            Baz: t v
+            test_crate: t
+        ",
+    );
+}
+
+#[test]
+fn item_map_skips_proc_macro_crates() {
+    let (mut db, sr) = MockDatabase::with_files(
+        "
+        //- /main.rs
+        use test_derive::helper;
+
+        //- /derive.rs
+        pub fn helper() {}
+    ",
+    );
+    let main_id = sr.files[RelativePath::new("/main.rs")];
+    let derive_id = sr.files[RelativePath::new("/derive.rs")];
+
+    let (mut crate_graph, main_crate, derive_crate) = two_crates(main_id, derive_id);
+    crate_graph.mark_proc_macro(derive_crate);
+    crate_graph
+        .add_dep(main_crate, "test_derive".into(), derive_crate)
+        .unwrap();
 
     db.set_crate_graph(crate_graph);
 
@@ -161,6 +242,59 @@ fn item_map_across_crates() {
     check_module_item_map(
         &item_map,
         module_id,
+        "
+            helper: _
+        ",
+    );
+}
+
+#[test]
+fn dev_dependencies_are_visible_in_test_modules() {
+    let (mut db, sr) = MockDatabase::with_files(
+        "
+        //- /main.rs
+        use test_crate::Baz;
+
+        #[cfg(test)]
+        mod tests {
+            use test_crate::Baz;
+        }
+
+        //- /lib.rs
+        pub struct Baz;
+    ",
+    );
+    let main_id = sr.files[RelativePath::new("/main.rs")];
+    let lib_id = sr.files[RelativePath::new("/lib.rs")];
+
+    let (mut crate_graph, main_crate, lib_crate) = two_crates(main_id, lib_id);
+    crate_graph
+        .add_dev_dep(main_crate, "test_crate".into(), lib_crate)
+        .unwrap();
+
+    db.set_crate_graph(crate_graph);
+
+    let source_root = db.file_source_root(main_id);
+    let module = hir::source_binder::module_from_file_id(&db, main_id)
+        .unwrap()
+        .unwrap();
+    let tests = module
+        .child(&db, &hir::Name::new("tests".into()))
+        .unwrap()
+        .unwrap();
+    let item_map = db.item_map(source_root).unwrap();
+
+    check_module_item_map(
+        &item_map,
+        module.def_id.loc(&db).module_id,
+        "
+            Baz: _
+            tests: t
+        ",
+    );
+    check_module_item_map(
+        &item_map,
+        tests.def_id.loc(&db).module_id,
         "
             Baz: t v
             test_crate: t
@@ -207,7 +341,7 @@ fn typing_inside_a_function_should_not_invalidate_item_map() {
     .to_string();
 
     db.query_mut(ra_db::FileTextQuery)
-        .set(pos.file_id, Arc::new(new_text));
+        .set(pos.file_id, Rope::from(new_text));
 
     {
         let events = db.log_executed(|| {
@@ -258,7 +392,7 @@ fn typing_inside_a_function_inside_a_macro_should_not_invalidate_item_map() {
     .to_string();
 
     db.query_mut(ra_db::FileTextQuery)
-        .set(pos.file_id, Arc::new(new_text));
+        .set(pos.file_id, Rope::from(new_text));
 
     {
         let events = db.log_executed(|| {
@@ -1,11 +1,35 @@
+use std::sync::Arc;
+
 use ra_syntax::{ast, AstNode, TextRange};
 
-use crate::{Name, AsName};
+use crate::{Name, AsName, type_ref::TypeRef};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Path {
     pub kind: PathKind,
-    pub segments: Vec<Name>,
+    pub segments: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PathSegment {
+    pub name: Name,
+    pub args_and_bindings: Option<Arc<GenericArgs>>,
+}
+
+/// Generic arguments to a path segment (e.g. the `i32` in `Option<i32>`) or
+/// a method call (e.g. the `Vec<_>` in `x.collect::<Vec<_>>()`). Lifetimes
+/// are not lowered yet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GenericArgs {
+    pub args: Vec<GenericArg>,
+    /// Bindings of associated types, like `Item = Foo` in
+    /// `Iterator<Item = Foo>`.
+    pub bindings: Vec<(Name, TypeRef)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GenericArg {
+    Type(TypeRef),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -31,7 +55,16 @@ impl Path {
         loop {
             let segment = path.segment()?;
             match segment.kind()? {
-                ast::PathSegmentKind::Name(name) => segments.push(name.as_name()),
+                ast::PathSegmentKind::Name(name) => {
+                    let args = segment
+                        .type_arg_list()
+                        .and_then(GenericArgs::from_ast)
+                        .map(Arc::new);
+                    segments.push(PathSegment {
+                        name: name.as_name(),
+                        args_and_bindings: args,
+                    });
+                }
                 ast::PathSegmentKind::CrateKw => {
                     kind = PathKind::Crate;
                     break;
@@ -60,7 +93,7 @@ impl Path {
             // TODO: this bottom up traversal is not too precise.
             // Should we handle do a top-down analysiss, recording results?
             let use_tree_list = path.syntax().ancestors().find_map(ast::UseTreeList::cast)?;
-            let use_tree = use_tree_list.parent_use_tree();
+            let use_tree = use_tree_list.parent_use_tree()?;
             use_tree.path()
         }
     }
@@ -85,7 +118,28 @@ impl Path {
         if self.kind != PathKind::Plain || self.segments.len() > 1 {
             return None;
         }
-        self.segments.first()
+        self.segments.first().map(|s| &s.name)
+    }
+}
+
+impl GenericArgs {
+    pub(crate) fn from_ast(node: ast::TypeArgList) -> Option<GenericArgs> {
+        let args = node
+            .type_args()
+            .map(|type_arg| GenericArg::Type(TypeRef::from_ast_opt(type_arg.type_ref())))
+            .collect::<Vec<_>>();
+        let bindings = node
+            .assoc_type_args()
+            .filter_map(|assoc_type_arg| {
+                let name = assoc_type_arg.name_ref()?.as_name();
+                Some((name, TypeRef::from_ast_opt(assoc_type_arg.type_ref())))
+            })
+            .collect::<Vec<_>>();
+        if args.is_empty() && bindings.is_empty() {
+            None
+        } else {
+            Some(GenericArgs { args, bindings })
+        }
     }
 }
 
@@ -93,7 +147,10 @@ impl From<Name> for Path {
     fn from(name: Name) -> Path {
         Path {
             kind: PathKind::Plain,
-            segments: vec![name],
+            segments: vec![PathSegment {
+                name,
+                args_and_bindings: None,
+            }],
         }
     }
 }
@@ -159,7 +216,10 @@ fn convert_path(prefix: Option<Path>, path: ast::Path) -> Option<Path> {
                 kind: PathKind::Plain,
                 segments: Vec::with_capacity(1),
             });
-            res.segments.push(name.as_name());
+            res.segments.push(PathSegment {
+                name: name.as_name(),
+                args_and_bindings: None,
+            });
             res
         }
         ast::PathSegmentKind::CrateKw => {
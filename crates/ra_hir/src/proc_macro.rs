@@ -0,0 +1,68 @@
+//! Procedural macros are compiled for the host, so expanding them requires
+//! loading their dylibs, or running them in a separate process. The database
+//! provides a `ProcMacroExpander` for that, which an IDE can replace with a
+//! real one.
+//!
+//! Only derives are supported at the moment, and we are only interested in
+//! the traits they implement, not in the items of the generated impls.
+use std::fmt;
+
+use ra_syntax::{
+    SyntaxKind::{COMMA, IDENT},
+    AstNode,
+    ast::{self, AttrsOwner},
+};
+
+use crate::{Name, Path, PathKind, PathSegment};
+
+pub trait ProcMacroExpander: fmt::Debug + Send + Sync {
+    /// Expands `#[derive(derive)]` on the struct or enum `item`, returning the
+    /// traits it implements for the item.
+    fn expand_derive(&self, derive: &Path, item: ast::NominalDef) -> Vec<Path>;
+}
+
+/// The default expander, which doesn't expand anything and assumes that
+/// `#[derive(Foo)]` implements the trait `Foo`, as most derives do.
+#[derive(Debug, Default)]
+pub struct DeriveNamePassthrough;
+
+impl ProcMacroExpander for DeriveNamePassthrough {
+    fn expand_derive(&self, derive: &Path, _item: ast::NominalDef) -> Vec<Path> {
+        vec![derive.clone()]
+    }
+}
+
+/// Returns the paths listed in the `#[derive(...)]` attributes of `item`.
+pub(crate) fn derives(item: ast::NominalDef) -> Vec<Path> {
+    let mut res = Vec::new();
+    for attr in item.attrs() {
+        let args = match attr.as_call() {
+            Some((name, args)) if name == "derive" => args,
+            _ => continue,
+        };
+        let mut segments = Vec::new();
+        for token in args.syntax().children() {
+            match token.kind() {
+                IDENT => segments.push(PathSegment {
+                    name: Name::new(token.leaf_text().unwrap().clone()),
+                    args_and_bindings: None,
+                }),
+                COMMA => push_path(&mut res, &mut segments),
+                // `::` and the delimiters
+                _ => (),
+            }
+        }
+        push_path(&mut res, &mut segments);
+    }
+    res
+}
+
+fn push_path(paths: &mut Vec<Path>, segments: &mut Vec<PathSegment>) {
+    if segments.is_empty() {
+        return;
+    }
+    paths.push(Path {
+        kind: PathKind::Plain,
+        segments: std::mem::replace(segments, Vec::new()),
+    });
+}
@@ -8,21 +8,22 @@ use ra_syntax::{
     AstNode, SyntaxNode,
     ast::{self, ModuleItemOwner}
 };
-use ra_db::{SourceRootId, Cancelable,};
+use ra_db::{SourceRootId, Cancelable, QueryTimer};
 
 use crate::{
     SourceFileItems, SourceItemId, DefKind, DefId, HirFileId, ModuleSource,
     MacroCallLoc,
     db::HirDatabase,
-    function::FnScopes,
+    function::ExprScopes,
     module_tree::ModuleId,
     nameres::{InputModuleItems, ItemMap, Resolver},
     adt::{StructData, EnumData},
 };
 
-pub(super) fn fn_scopes(db: &impl HirDatabase, def_id: DefId) -> Cancelable<Arc<FnScopes>> {
+pub(super) fn expr_scopes(db: &impl HirDatabase, def_id: DefId) -> Cancelable<Arc<ExprScopes>> {
+    let _timer = QueryTimer::new(db, "expr_scopes");
     let body = db.body_hir(def_id)?;
-    let res = FnScopes::new(body);
+    let res = ExprScopes::new(body);
     Ok(Arc::new(res))
 }
 
@@ -45,6 +46,7 @@ pub(super) fn enum_data(db: &impl HirDatabase, def_id: DefId) -> Cancelable<Arc<
 }
 
 pub(super) fn file_items(db: &impl HirDatabase, file_id: HirFileId) -> Arc<SourceFileItems> {
+    let _timer = QueryTimer::new(db, "file_items");
     let source_file = db.hir_source_file(file_id);
     let source_file = source_file.borrowed();
     let res = SourceFileItems::new(file_id, source_file);
@@ -63,6 +65,7 @@ pub(super) fn input_module_items(
     source_root_id: SourceRootId,
     module_id: ModuleId,
 ) -> Cancelable<Arc<InputModuleItems>> {
+    let _timer = QueryTimer::new(db, "input_module_items");
     let module_tree = db.module_tree(source_root_id)?;
     let source = module_id.source(&module_tree);
     let file_id = source.file_id;
@@ -117,6 +120,7 @@ pub(super) fn item_map(
     let input = module_tree
         .modules()
         .map(|id| {
+            db.check_canceled()?;
             let items = db.input_module_items(source_root, id)?;
             Ok((id, items))
         })
@@ -126,5 +130,6 @@ pub(super) fn item_map(
     let res = resolver.resolve()?;
     let elapsed = start.elapsed();
     log::info!("item_map: {:?}", elapsed);
+    db.record_query_time("item_map", elapsed);
     Ok(Arc::new(res))
 }
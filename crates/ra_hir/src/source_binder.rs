@@ -13,8 +13,8 @@ use ra_syntax::{
 };
 
 use crate::{
-    HirDatabase, Function, SourceItemId,
-    DefKind, DefLoc, AsName, Module,
+    HirDatabase, Function, Const, Static, SourceItemId,
+    DefId, DefKind, DefLoc, AsName, Module,
 };
 
 /// Locates the module by `FileId`. Picks topmost module in the file.
@@ -124,30 +124,64 @@ pub fn function_from_module(
     module: &Module,
     fn_def: ast::FnDef,
 ) -> Function {
+    Function::new(def_id_from_module(
+        db,
+        module,
+        DefKind::Function,
+        fn_def.syntax(),
+    ))
+}
+
+pub fn function_from_child_node(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    node: SyntaxNodeRef,
+) -> Cancelable<Option<Function>> {
+    let fn_def = ctry!(node.ancestors().find_map(ast::FnDef::cast));
+    function_from_source(db, file_id, fn_def)
+}
+
+pub fn const_from_source(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    const_def: ast::ConstDef,
+) -> Cancelable<Option<Const>> {
+    let module = ctry!(module_from_child_node(db, file_id, const_def.syntax())?);
+    let def_id = def_id_from_module(db, &module, DefKind::Const, const_def.syntax());
+    Ok(Some(Const::new(def_id)))
+}
+
+pub fn static_from_source(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    static_def: ast::StaticDef,
+) -> Cancelable<Option<Static>> {
+    let module = ctry!(module_from_child_node(db, file_id, static_def.syntax())?);
+    let def_id = def_id_from_module(db, &module, DefKind::Static, static_def.syntax());
+    Ok(Some(Static::new(def_id)))
+}
+
+fn def_id_from_module(
+    db: &impl HirDatabase,
+    module: &Module,
+    kind: DefKind,
+    item: SyntaxNodeRef,
+) -> DefId {
     let loc = module.def_id.loc(db);
     let file_id = loc.source_item_id.file_id;
     let file_items = db.file_items(file_id);
-    let item_id = file_items.id_of(file_id, fn_def.syntax());
+    let item_id = file_items.id_of(file_id, item);
     let source_item_id = SourceItemId {
         file_id,
         item_id: Some(item_id),
     };
     let def_loc = DefLoc {
-        kind: DefKind::Function,
+        kind,
         source_root_id: loc.source_root_id,
         module_id: loc.module_id,
         source_item_id,
     };
-    Function::new(def_loc.id(db))
-}
-
-pub fn function_from_child_node(
-    db: &impl HirDatabase,
-    file_id: FileId,
-    node: SyntaxNodeRef,
-) -> Cancelable<Option<Function>> {
-    let fn_def = ctry!(node.ancestors().find_map(ast::FnDef::cast));
-    function_from_source(db, file_id, fn_def)
+    def_loc.id(db)
 }
 
 pub fn macro_symbols(
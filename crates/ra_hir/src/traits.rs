@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use ra_db::Cancelable;
+use ra_syntax::ast::{self, AstNode, FnDefOwner, NameOwner};
+
+use crate::{
+    DefId, DefLoc, DefKind, SourceItemId, Function, Name, AsName,
+    db::HirDatabase,
+};
+
+/// A trait, defined with `trait Foo { .. }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trait {
+    def_id: DefId,
+}
+
+impl Trait {
+    pub(crate) fn new(def_id: DefId) -> Trait {
+        Trait { def_id }
+    }
+
+    pub fn def_id(&self) -> DefId {
+        self.def_id
+    }
+
+    pub fn name(&self, db: &impl HirDatabase) -> Cancelable<Option<Name>> {
+        Ok(db.trait_data(self.def_id)?.name.clone())
+    }
+
+    /// The functions declared in the trait, with or without a default body.
+    pub fn functions(&self, db: &impl HirDatabase) -> Cancelable<Vec<Function>> {
+        Ok(db.trait_data(self.def_id)?.functions.clone())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraitData {
+    name: Option<Name>,
+    functions: Vec<Function>,
+}
+
+pub(crate) fn trait_data(db: &impl HirDatabase, def_id: DefId) -> Cancelable<Arc<TraitData>> {
+    let def_loc = def_id.loc(db);
+    assert!(def_loc.kind == DefKind::Trait);
+    let syntax = db.file_item(def_loc.source_item_id);
+    let trait_def =
+        ast::TraitDef::cast(syntax.borrowed()).expect("trait def should point to TraitDef node");
+    let name = trait_def.name().map(|n| n.as_name());
+    let file_items = db.file_items(def_loc.source_item_id.file_id);
+    let functions = trait_def
+        .item_list()
+        .into_iter()
+        .flat_map(|it| it.functions())
+        .map(|fn_def| {
+            let item_id = file_items.id_of_unchecked(fn_def.syntax());
+            let source_item_id = SourceItemId {
+                file_id: def_loc.source_item_id.file_id,
+                item_id: Some(item_id),
+            };
+            let fn_loc = DefLoc {
+                kind: DefKind::Function,
+                source_item_id,
+                ..def_loc
+            };
+            Function::new(fn_loc.id(db))
+        })
+        .collect();
+    Ok(Arc::new(TraitData { name, functions }))
+}
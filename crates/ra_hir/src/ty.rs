@@ -13,28 +13,34 @@
 //! the union-find implementation from the `ena` crate, which is extracted from
 //! rustc.
 
-mod primitive;
+mod autoderef;
+mod projection;
+pub(crate) mod primitive;
+pub(crate) mod method_resolution;
 #[cfg(test)]
 mod tests;
 
 use std::ops::Index;
 use std::sync::Arc;
-use std::{fmt, mem};
+use std::{borrow::Cow, fmt, mem};
 
 use log;
 use ena::unify::{InPlaceUnificationTable, UnifyKey, UnifyValue, NoError};
+use rustc_hash::FxHashMap;
 use ra_arena::map::ArenaMap;
 
-use ra_db::Cancelable;
+use ra_db::{Cancelable, QueryTimer};
 
 use crate::{
-    Def, DefId, Module, Function, Struct, Enum, Path, Name, ImplBlock,
-    FnSignature, FnScopes,
+    Def, DefId, Module, Function, Struct, Enum, TypeAlias, Path, Name, ImplBlock,
+    FnSignature, ExprScopes,
     db::HirDatabase,
     type_ref::{TypeRef, Mutability},
     name::KnownName,
-    expr::{Body, Expr, ExprId, PatId, UnaryOp, BinaryOp, Statement},
+    path::{GenericArg, PathKind},
+    expr::{Body, Expr, Array, Literal, ExprId, Pat, PatId, BindingAnnotation, UnaryOp, BinaryOp, Statement},
 };
+use self::primitive::{UncertainIntTy, UncertainFloatTy};
 
 /// The ID of a type variable.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
@@ -94,13 +100,33 @@ impl UnifyValue for TypeVarValue {
     }
 }
 
-/// The kinds of placeholders we need during type inference. Currently, we only
-/// have type variables; in the future, we will probably also need int and float
-/// variables, for inference of literal values (e.g. `100` could be one of
-/// several integer types).
+/// The kinds of placeholders we need during type inference. Integer and float
+/// variables are the types of literals without a suffix (e.g. `100` could be
+/// one of several integer types); they can only be unified with integer and
+/// float types respectively, and default to `i32` and `f64`.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum InferTy {
     TypeVar(TypeVarId),
+    IntVar(TypeVarId),
+    FloatVar(TypeVarId),
+}
+
+impl InferTy {
+    fn to_inner(&self) -> TypeVarId {
+        match self {
+            InferTy::TypeVar(tv) | InferTy::IntVar(tv) | InferTy::FloatVar(tv) => *tv,
+        }
+    }
+
+    /// The type which the variable defaults to if it's not known at the end
+    /// of the inference.
+    fn fallback_value(&self) -> Ty {
+        match self {
+            InferTy::TypeVar(..) => Ty::Unknown,
+            InferTy::IntVar(..) => Ty::Int(primitive::IntTy::I32),
+            InferTy::FloatVar(..) => Ty::Float(primitive::FloatTy::F64),
+        }
+    }
 }
 
 /// When inferring an expression, we propagate downward whatever type hint we
@@ -126,6 +152,25 @@ impl Expectation {
     }
 }
 
+/// How a binding without `ref` binds its value: by value, or by reference
+/// when a non-reference pattern matches a reference ("default binding
+/// modes").
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BindingMode {
+    Move,
+    Ref(Mutability),
+}
+
+impl BindingMode {
+    fn convert(annotation: BindingAnnotation) -> BindingMode {
+        match annotation {
+            BindingAnnotation::Unannotated | BindingAnnotation::Mutable => BindingMode::Move,
+            BindingAnnotation::Ref => BindingMode::Ref(Mutability::Shared),
+            BindingAnnotation::RefMut => BindingMode::Ref(Mutability::Mut),
+        }
+    }
+}
+
 /// A type. This is based on the `TyKind` enum in rustc (librustc/ty/sty.rs).
 ///
 /// This should be cheap to clone.
@@ -153,14 +198,20 @@ pub enum Ty {
         def_id: DefId,
         /// The name, for displaying.
         name: Name,
-        // later we'll need generic substitutions here
+        /// The generic arguments, as far as they are written in the type, like
+        /// `u32` in `Option<u32>`.
+        // TODO: the generic parameters of the struct/enum, to substitute them
+        // in the types of the fields
+        substs: Arc<[Ty]>,
     },
 
     /// The pointee of a string slice. Written as `str`.
     Str,
 
-    // An array with the given length. Written as `[T; n]`.
-    // Array(Ty, ty::Const),
+    /// An array with the given length. Written as `[T; n]`.
+    // TODO: the length, once we can evaluate constants
+    Array(Arc<Ty>),
+
     /// The pointee of an array slice.  Written as `[T]`.
     Slice(Arc<Ty>),
 
@@ -181,6 +232,11 @@ pub enum Ty {
     /// ```
     FnPtr(Arc<FnSig>),
 
+    /// The anonymous type of a closure, with the signature of its call. Used
+    /// to represent the type of `|a: u32| a`. Unlike rustc, we don't
+    /// distinguish the closures with the same signature.
+    Closure(Arc<FnSig>),
+
     // rustc has a separate type for each function, which just coerces to the
     // above function pointer type. Once we implement generics, we will probably
     // need this as well.
@@ -204,12 +260,21 @@ pub enum Ty {
     /// A tuple type.  For example, `(i32, bool)`.
     Tuple(Arc<[Ty]>),
 
-    // The projection of an associated type.  For example,
-    // `<T as Trait<..>>::N`.pub
-    // Projection(ProjectionTy),
+    /// The projection of an associated type which we couldn't normalize to
+    /// the type given in an impl.  For example, `<T as Trait<..>>::N`.
+    Projection {
+        /// The DefId of the trait.
+        trait_def_id: DefId,
+        /// The name of the trait, for displaying.
+        trait_name: Name,
+        self_ty: Arc<Ty>,
+        /// The name of the associated type.
+        name: Name,
+    },
 
-    // Opaque (`impl Trait`) type found in a return type.
-    // Opaque(DefId, Substs),
+    /// An opaque `impl Trait` type found in a return type. All we know about
+    /// it are the traits it implements.
+    Opaque(Arc<[TraitBound]>),
 
     // A type parameter; for example, `T` in `fn f<T>(x: T) {}
     // Param(ParamTy),
@@ -225,6 +290,34 @@ pub enum Ty {
     Unknown,
 }
 
+/// A trait which an opaque type implements, like `Iterator` in
+/// `impl Iterator<Item = u32>`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TraitBound {
+    /// The DefId of the trait.
+    trait_def_id: DefId,
+    /// The name of the trait, for displaying.
+    name: Name,
+    /// The types given to associated types of the trait, like `u32` to `Item`.
+    bindings: Vec<(Name, Ty)>,
+}
+
+/// The type aliases and associated type projections whose expansion is in
+/// progress while lowering a type. Expanding one of them again would recurse
+/// forever, so it's unknown instead.
+#[derive(Default)]
+struct Expansions {
+    aliases: Vec<DefId>,
+    projections: Vec<Ty>,
+    /// Whether projections are left unnormalized. Normalizing needs the impls
+    /// index, so this is the case while building it.
+    keep_projections: bool,
+    /// The trait, with its name, and the type which `Self` stands for while
+    /// lowering the signature of a trait method called on that type. Unlike
+    /// the methods in impls, it has no impl block to take them from.
+    trait_self: Option<(DefId, Name, Ty)>,
+}
+
 /// A function signature.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct FnSig {
@@ -238,35 +331,64 @@ impl Ty {
         module: &Module,
         impl_block: Option<&ImplBlock>,
         type_ref: &TypeRef,
+    ) -> Cancelable<Self> {
+        Ty::from_hir_expanding(db, module, impl_block, type_ref, &mut Expansions::default())
+    }
+
+    /// Lowers the type ref without normalizing the projections in it.
+    fn from_hir_unnormalized(
+        db: &impl HirDatabase,
+        module: &Module,
+        impl_block: Option<&ImplBlock>,
+        type_ref: &TypeRef,
+    ) -> Cancelable<Self> {
+        let mut expanding = Expansions {
+            keep_projections: true,
+            ..Expansions::default()
+        };
+        Ty::from_hir_expanding(db, module, impl_block, type_ref, &mut expanding)
+    }
+
+    /// Lowers the type ref, with the expansions which are in progress to
+    /// detect cycles.
+    fn from_hir_expanding(
+        db: &impl HirDatabase,
+        module: &Module,
+        impl_block: Option<&ImplBlock>,
+        type_ref: &TypeRef,
+        expanding: &mut Expansions,
     ) -> Cancelable<Self> {
         Ok(match type_ref {
             TypeRef::Never => Ty::Never,
             TypeRef::Tuple(inner) => {
                 let inner_tys = inner
                     .iter()
-                    .map(|tr| Ty::from_hir(db, module, impl_block, tr))
+                    .map(|tr| Ty::from_hir_expanding(db, module, impl_block, tr, expanding))
                     .collect::<Cancelable<Vec<_>>>()?;
                 Ty::Tuple(inner_tys.into())
             }
-            TypeRef::Path(path) => Ty::from_hir_path(db, module, impl_block, path)?,
+            TypeRef::Path(path) => Ty::from_hir_path(db, module, impl_block, path, expanding)?,
             TypeRef::RawPtr(inner, mutability) => {
-                let inner_ty = Ty::from_hir(db, module, impl_block, inner)?;
+                let inner_ty = Ty::from_hir_expanding(db, module, impl_block, inner, expanding)?;
                 Ty::RawPtr(Arc::new(inner_ty), *mutability)
             }
-            TypeRef::Array(_inner) => Ty::Unknown, // TODO
+            TypeRef::Array(inner) => {
+                let inner_ty = Ty::from_hir_expanding(db, module, impl_block, inner, expanding)?;
+                Ty::Array(Arc::new(inner_ty))
+            }
             TypeRef::Slice(inner) => {
-                let inner_ty = Ty::from_hir(db, module, impl_block, inner)?;
+                let inner_ty = Ty::from_hir_expanding(db, module, impl_block, inner, expanding)?;
                 Ty::Slice(Arc::new(inner_ty))
             }
             TypeRef::Reference(inner, mutability) => {
-                let inner_ty = Ty::from_hir(db, module, impl_block, inner)?;
+                let inner_ty = Ty::from_hir_expanding(db, module, impl_block, inner, expanding)?;
                 Ty::Ref(Arc::new(inner_ty), *mutability)
             }
             TypeRef::Placeholder => Ty::Unknown,
             TypeRef::Fn(params) => {
                 let mut inner_tys = params
                     .iter()
-                    .map(|tr| Ty::from_hir(db, module, impl_block, tr))
+                    .map(|tr| Ty::from_hir_expanding(db, module, impl_block, tr, expanding))
                     .collect::<Cancelable<Vec<_>>>()?;
                 let return_ty = inner_tys
                     .pop()
@@ -277,6 +399,50 @@ impl Ty {
                 };
                 Ty::FnPtr(Arc::new(sig))
             }
+            TypeRef::Projection {
+                self_type,
+                trait_path,
+                name,
+            } => {
+                // TODO: `<T>::Name`, which looks the associated type up in
+                // all the traits `T` implements
+                let trait_path = match trait_path {
+                    Some(it) => it,
+                    None => return Ok(Ty::Unknown),
+                };
+                let self_ty = Ty::from_hir_expanding(db, module, impl_block, self_type, expanding)?;
+                Ty::projection(db, module, trait_path, self_ty, name.clone(), expanding)?
+            }
+            TypeRef::ImplTrait(trait_paths) => {
+                let mut bounds = Vec::new();
+                for trait_path in trait_paths {
+                    if let Some((trait_def_id, name)) = resolve_trait(db, module, trait_path)? {
+                        let bindings = match trait_path
+                            .segments
+                            .last()
+                            .and_then(|s| s.args_and_bindings.as_ref())
+                        {
+                            Some(generic_args) => generic_args
+                                .bindings
+                                .iter()
+                                .map(|(name, type_ref)| {
+                                    let ty = Ty::from_hir_expanding(
+                                        db, module, impl_block, type_ref, expanding,
+                                    )?;
+                                    Ok((name.clone(), ty))
+                                })
+                                .collect::<Cancelable<Vec<_>>>()?,
+                            None => Vec::new(),
+                        };
+                        bounds.push(TraitBound {
+                            trait_def_id,
+                            name,
+                            bindings,
+                        });
+                    }
+                }
+                Ty::Opaque(bounds.into())
+            }
             TypeRef::Error => Ty::Unknown,
         })
     }
@@ -292,11 +458,12 @@ impl Ty {
             .unwrap_or(Ok(Ty::Unknown))
     }
 
-    pub(crate) fn from_hir_path(
+    fn from_hir_path(
         db: &impl HirDatabase,
         module: &Module,
         impl_block: Option<&ImplBlock>,
         path: &Path,
+        expanding: &mut Expansions,
     ) -> Cancelable<Self> {
         if let Some(name) = path.as_ident() {
             if let Some(int_ty) = primitive::IntTy::from_name(name) {
@@ -305,8 +472,57 @@ impl Ty {
                 return Ok(Ty::Uint(uint_ty));
             } else if let Some(float_ty) = primitive::FloatTy::from_name(name) {
                 return Ok(Ty::Float(float_ty));
-            } else if name.as_known_name() == Some(KnownName::SelfType) {
-                return Ty::from_hir_opt(db, module, None, impl_block.map(|i| i.target_type()));
+            }
+            match name.as_known_name() {
+                Some(KnownName::Bool) => return Ok(Ty::Bool),
+                Some(KnownName::Char) => return Ok(Ty::Char),
+                Some(KnownName::Str) => return Ok(Ty::Str),
+                Some(KnownName::SelfType) => {
+                    if let (None, Some((_, _, self_ty))) = (impl_block, &expanding.trait_self) {
+                        return Ok(self_ty.clone());
+                    }
+                    return Ty::from_hir_opt(db, module, None, impl_block.map(|i| i.target_type()));
+                }
+                _ => {}
+            }
+        }
+
+        // `Self::Name` in a trait impl is the associated type of the trait
+        if let Some(impl_block) = impl_block {
+            if let (PathKind::Plain, [self_segment, name_segment]) = (path.kind, &path.segments[..])
+            {
+                if self_segment.name.as_known_name() == Some(KnownName::SelfType) {
+                    if let Some(TypeRef::Path(trait_path)) = impl_block.target_trait() {
+                        let self_ty = Ty::from_hir_expanding(
+                            db,
+                            module,
+                            None,
+                            impl_block.target_type(),
+                            expanding,
+                        )?;
+                        let name = name_segment.name.clone();
+                        return Ty::projection(db, module, trait_path, self_ty, name, expanding);
+                    }
+                }
+            }
+        }
+
+        // `Self::Name` in a trait method is the associated type of the trait for
+        // the type the method is called on
+        if let (None, Some((trait_def_id, trait_name, self_ty))) =
+            (impl_block, expanding.trait_self.clone())
+        {
+            if let (PathKind::Plain, [self_segment, name_segment]) = (path.kind, &path.segments[..])
+            {
+                if self_segment.name.as_known_name() == Some(KnownName::SelfType) {
+                    let projection = Ty::Projection {
+                        trait_def_id,
+                        trait_name,
+                        self_ty: Arc::new(self_ty),
+                        name: name_segment.name.clone(),
+                    };
+                    return projection.normalize_expanding(db, expanding);
+                }
             }
         }
 
@@ -316,10 +532,82 @@ impl Ty {
         } else {
             return Ok(Ty::Unknown);
         };
+        if let Def::TypeAlias(alias) = resolved.resolve(db)? {
+            // TODO: substitute the generic arguments of the alias
+            return Ty::from_type_alias(db, &alias, expanding);
+        }
         let ty = db.type_for_def(resolved)?;
+        let ty = match ty {
+            Ty::Adt { def_id, name, .. } => {
+                let substs: Arc<[Ty]> = match path
+                    .segments
+                    .last()
+                    .and_then(|s| s.args_and_bindings.as_ref())
+                {
+                    Some(generic_args) => generic_args
+                        .args
+                        .iter()
+                        .map(|arg| match arg {
+                            GenericArg::Type(type_ref) => {
+                                Ty::from_hir_expanding(db, module, impl_block, type_ref, expanding)
+                            }
+                        })
+                        .collect::<Cancelable<Vec<_>>>()?
+                        .into(),
+                    None => Arc::new([]),
+                };
+                Ty::Adt {
+                    def_id,
+                    name,
+                    substs,
+                }
+            }
+            ty => ty,
+        };
         Ok(ty)
     }
 
+    /// Expands the type alias to the type it stands for. An alias which
+    /// refers to itself, directly or through other aliases and associated
+    /// types, is unknown.
+    fn from_type_alias(
+        db: &impl HirDatabase,
+        alias: &TypeAlias,
+        expanding: &mut Expansions,
+    ) -> Cancelable<Self> {
+        if expanding.aliases.contains(&alias.def_id()) {
+            return Ok(Ty::Unknown);
+        }
+        let module = alias.module(db)?;
+        expanding.aliases.push(alias.def_id());
+        let ty = Ty::from_hir_expanding(db, &module, None, &alias.type_ref(db), expanding);
+        expanding.aliases.pop();
+        ty
+    }
+
+    /// The associated type `name` of the trait at `trait_path` for `self_ty`,
+    /// normalized if possible.
+    fn projection(
+        db: &impl HirDatabase,
+        module: &Module,
+        trait_path: &Path,
+        self_ty: Ty,
+        name: Name,
+        expanding: &mut Expansions,
+    ) -> Cancelable<Self> {
+        let (trait_def_id, trait_name) = match resolve_trait(db, module, trait_path)? {
+            Some(it) => it,
+            None => return Ok(Ty::Unknown),
+        };
+        let projection = Ty::Projection {
+            trait_def_id,
+            trait_name,
+            self_ty: Arc::new(self_ty),
+            name,
+        };
+        projection.normalize_expanding(db, expanding)
+    }
+
     pub fn unit() -> Self {
         Ty::Tuple(Arc::new([]))
     }
@@ -327,9 +615,10 @@ impl Ty {
     fn walk_mut(&mut self, f: &mut impl FnMut(&mut Ty)) {
         f(self);
         match self {
-            Ty::Slice(t) => Arc::make_mut(t).walk_mut(f),
+            Ty::Slice(t) | Ty::Array(t) => Arc::make_mut(t).walk_mut(f),
             Ty::RawPtr(t, _) => Arc::make_mut(t).walk_mut(f),
             Ty::Ref(t, _) => Arc::make_mut(t).walk_mut(f),
+            Ty::Projection { self_ty, .. } => Arc::make_mut(self_ty).walk_mut(f),
             Ty::Tuple(ts) => {
                 // Without an Arc::make_mut_slice, we can't avoid the clone here:
                 let mut v: Vec<_> = ts.iter().cloned().collect();
@@ -338,14 +627,20 @@ impl Ty {
                 }
                 *ts = v.into();
             }
-            Ty::FnPtr(sig) => {
+            Ty::FnPtr(sig) | Ty::Closure(sig) => {
                 let sig_mut = Arc::make_mut(sig);
                 for input in &mut sig_mut.input {
                     input.walk_mut(f);
                 }
                 sig_mut.output.walk_mut(f);
             }
-            Ty::Adt { .. } => {} // need to walk type parameters later
+            Ty::Adt { substs, .. } => {
+                let mut v: Vec<_> = substs.iter().cloned().collect();
+                for t in &mut v {
+                    t.walk_mut(f);
+                }
+                *substs = v.into();
+            }
             _ => {}
         }
     }
@@ -369,6 +664,7 @@ impl fmt::Display for Ty {
             Ty::Float(t) => write!(f, "{}", t.ty_to_string()),
             Ty::Str => write!(f, "str"),
             Ty::Slice(t) => write!(f, "[{}]", t),
+            Ty::Array(t) => write!(f, "[{}; _]", t),
             Ty::RawPtr(t, m) => write!(f, "*{}{}", m.as_keyword_for_ptr(), t),
             Ty::Ref(t, m) => write!(f, "&{}{}", m.as_keyword_for_ref(), t),
             Ty::Never => write!(f, "!"),
@@ -386,28 +682,122 @@ impl fmt::Display for Ty {
                 }
                 write!(f, ") -> {}", sig.output)
             }
-            Ty::Adt { name, .. } => write!(f, "{}", name),
+            Ty::Closure(sig) => {
+                write!(f, "|")?;
+                for (i, t) in sig.input.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", t)?;
+                }
+                write!(f, "| -> {}", sig.output)
+            }
+            Ty::Adt { name, substs, .. } => {
+                write!(f, "{}", name)?;
+                if !substs.is_empty() {
+                    write!(f, "<")?;
+                    for (i, t) in substs.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{}", t)?;
+                    }
+                    write!(f, ">")?;
+                }
+                Ok(())
+            }
+            Ty::Projection {
+                trait_name,
+                self_ty,
+                name,
+                ..
+            } => write!(f, "<{} as {}>::{}", self_ty, trait_name, name),
+            Ty::Opaque(bounds) => {
+                write!(f, "impl ")?;
+                for (i, bound) in bounds.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " + ")?;
+                    }
+                    write!(f, "{}", bound.name)?;
+                    if !bound.bindings.is_empty() {
+                        write!(f, "<")?;
+                        for (j, (name, ty)) in bound.bindings.iter().enumerate() {
+                            if j > 0 {
+                                write!(f, ", ")?;
+                            }
+                            write!(f, "{} = {}", name, ty)?;
+                        }
+                        write!(f, ">")?;
+                    }
+                }
+                Ok(())
+            }
             Ty::Unknown => write!(f, "[unknown]"),
-            Ty::Infer(..) => write!(f, "_"),
+            Ty::Infer(InferTy::TypeVar(..)) => write!(f, "_"),
+            Ty::Infer(InferTy::IntVar(..)) => write!(f, "{{integer}}"),
+            Ty::Infer(InferTy::FloatVar(..)) => write!(f, "{{float}}"),
         }
     }
 }
 
+/// Resolves the path of a trait in a type, like in `impl Trait` or
+/// `<T as Trait>::Name`, to the trait and its name.
+fn resolve_trait(
+    db: &impl HirDatabase,
+    module: &Module,
+    trait_path: &Path,
+) -> Cancelable<Option<(DefId, Name)>> {
+    let def_id = ctry!(module.resolve_path(db, trait_path)?.take_types());
+    Ok(match (def_id.resolve(db)?, trait_path.segments.last()) {
+        (Def::Trait(..), Some(segment)) => Some((def_id, segment.name.clone())),
+        _ => None,
+    })
+}
+
 // Functions returning declared types for items
 
 /// Compute the declared type of a function. This should not need to look at the
 /// function body.
 fn type_for_fn(db: &impl HirDatabase, f: Function) -> Cancelable<Ty> {
+    let impl_block = f.impl_block(db)?;
+    fn_ptr_ty(db, &f, impl_block.as_ref(), None)
+}
+
+/// The type of a trait method called on `self_ty`, where `Self` in the
+/// signature is `self_ty` and `Self::Name` the associated type of the trait
+/// for it.
+fn type_for_trait_method(
+    db: &impl HirDatabase,
+    f: &Function,
+    trait_bound: TraitBound,
+    self_ty: Ty,
+) -> Cancelable<Ty> {
+    let trait_self = (trait_bound.trait_def_id, trait_bound.name, self_ty);
+    fn_ptr_ty(db, f, None, Some(trait_self))
+}
+
+fn fn_ptr_ty(
+    db: &impl HirDatabase,
+    f: &Function,
+    impl_block: Option<&ImplBlock>,
+    trait_self: Option<(DefId, Name, Ty)>,
+) -> Cancelable<Ty> {
     let signature = f.signature(db);
     let module = f.module(db)?;
-    let impl_block = f.impl_block(db)?;
+    let lower = |type_ref: &TypeRef| {
+        let mut expanding = Expansions {
+            trait_self: trait_self.clone(),
+            ..Expansions::default()
+        };
+        Ty::from_hir_expanding(db, &module, impl_block, type_ref, &mut expanding)
+    };
     // TODO we ignore type parameters for now
     let input = signature
         .args()
         .iter()
-        .map(|tr| Ty::from_hir(db, &module, impl_block.as_ref(), tr))
+        .map(lower)
         .collect::<Cancelable<Vec<_>>>()?;
-    let output = Ty::from_hir(db, &module, impl_block.as_ref(), signature.ret_type())?;
+    let output = lower(signature.ret_type())?;
     let sig = FnSig { input, output };
     Ok(Ty::FnPtr(Arc::new(sig)))
 }
@@ -416,6 +806,7 @@ fn type_for_struct(db: &impl HirDatabase, s: Struct) -> Cancelable<Ty> {
     Ok(Ty::Adt {
         def_id: s.def_id(),
         name: s.name(db)?.unwrap_or_else(Name::missing),
+        substs: Arc::new([]),
     })
 }
 
@@ -423,6 +814,7 @@ pub fn type_for_enum(db: &impl HirDatabase, s: Enum) -> Cancelable<Ty> {
     Ok(Ty::Adt {
         def_id: s.def_id(),
         name: s.name(db)?.unwrap_or_else(Name::missing),
+        substs: Arc::new([]),
     })
 }
 
@@ -436,6 +828,19 @@ pub(super) fn type_for_def(db: &impl HirDatabase, def_id: DefId) -> Cancelable<T
         Def::Function(f) => type_for_fn(db, f),
         Def::Struct(s) => type_for_struct(db, s),
         Def::Enum(e) => type_for_enum(db, e),
+        Def::Const(c) => {
+            let module = c.module(db)?;
+            Ty::from_hir(db, &module, None, &c.type_ref(db))
+        }
+        Def::Static(s) => {
+            let module = s.module(db)?;
+            Ty::from_hir(db, &module, None, &s.type_ref(db))
+        }
+        Def::TypeAlias(a) => Ty::from_type_alias(db, &a, &mut Expansions::default()),
+        Def::Trait(..) => {
+            log::debug!("trying to get type for trait {:?}", def_id);
+            Ok(Ty::Unknown)
+        }
         Def::Item => {
             log::debug!("trying to get type for item of unknown type {:?}", def_id);
             Ok(Ty::Unknown)
@@ -470,10 +875,24 @@ pub(super) fn type_for_field(db: &impl HirDatabase, def_id: DefId, field: Name)
 /// The result of type inference: A mapping from expressions and patterns to types.
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct InferenceResult {
+    /// For each method call expression, the method it resolves to.
+    method_resolutions: FxHashMap<ExprId, DefId>,
+    /// For each `?` expression on a `Result`, the type of the error.
+    try_error_types: FxHashMap<ExprId, Ty>,
     type_of_expr: ArenaMap<ExprId, Ty>,
     type_of_pat: ArenaMap<PatId, Ty>,
 }
 
+impl InferenceResult {
+    pub fn method_resolution(&self, expr: ExprId) -> Option<DefId> {
+        self.method_resolutions.get(&expr).cloned()
+    }
+
+    pub fn try_error_type(&self, expr: ExprId) -> Option<&Ty> {
+        self.try_error_types.get(&expr)
+    }
+}
+
 impl Index<ExprId> for InferenceResult {
     type Output = Ty;
 
@@ -495,10 +914,12 @@ impl Index<PatId> for InferenceResult {
 struct InferenceContext<'a, D: HirDatabase> {
     db: &'a D,
     body: Arc<Body>,
-    scopes: Arc<FnScopes>,
+    scopes: Arc<ExprScopes>,
     module: Module,
     impl_block: Option<ImplBlock>,
     var_unification_table: InPlaceUnificationTable<TypeVarId>,
+    method_resolutions: FxHashMap<ExprId, DefId>,
+    try_error_types: FxHashMap<ExprId, Ty>,
     type_of_expr: ArenaMap<ExprId, Ty>,
     type_of_pat: ArenaMap<PatId, Ty>,
     /// The return type of the function being inferred.
@@ -515,7 +936,119 @@ fn is_boolean_operator(op: BinaryOp) -> bool {
         | BinaryOp::LesserEqualTest
         | BinaryOp::GreaterEqualTest
         | BinaryOp::LesserTest
-        | BinaryOp::GreaterTest => true,
+        | BinaryOp::GreaterTest
+        | BinaryOp::NegatedEqualityTest => true,
+        _ => false,
+    }
+}
+
+/// The expected type of the right operand of a binary operator, given the type
+/// of the left one. Only the builtin operators of primitive types are known;
+/// operands of other types may use overloaded operators.
+fn binary_op_rhs_expectation(op: BinaryOp, lhs_ty: &Ty) -> Ty {
+    match op {
+        BinaryOp::BooleanAnd | BinaryOp::BooleanOr => Ty::Bool,
+        BinaryOp::Assignment => lhs_ty.clone(),
+        // The right operand of a shift can be of any integer type.
+        BinaryOp::LeftShift | BinaryOp::RightShift | BinaryOp::ShlAssign | BinaryOp::ShrAssign => {
+            Ty::Unknown
+        }
+        _ if is_builtin_binary_op(op, lhs_ty) => lhs_ty.clone(),
+        _ => Ty::Unknown,
+    }
+}
+
+/// Whether the macro never returns, like `panic!`. As long as macros aren't
+/// expanded, the ones from `std` are recognized by their names.
+fn is_diverging_macro(name: &Name) -> bool {
+    match name.to_string().as_str() {
+        "panic" | "unreachable" | "unimplemented" => true,
+        _ => false,
+    }
+}
+
+/// The success type of `x?`, and the type of the error if there is one: `T`
+/// and `E` for `Result<T, E>`, and `T` for `Option<T>`. As long as there are
+/// no lang items, `Result` and `Option` are recognized by their names.
+fn try_output_types(ty: &Ty) -> Option<(Ty, Option<Ty>)> {
+    let (name, substs) = match ty {
+        Ty::Adt { name, substs, .. } => (name, substs),
+        _ => return None,
+    };
+    let subst = |i: usize| substs.get(i).cloned().unwrap_or(Ty::Unknown);
+    match name.to_string().as_str() {
+        "Result" => Some((subst(0), Some(subst(1)))),
+        "Option" => Some((subst(0), None)),
+        _ => None,
+    }
+}
+
+/// The element type `T` of a `Vec<T>`. As long as there are no lang items,
+/// `Vec` is recognized by its name.
+fn vec_elem_ty(ty: &Ty) -> Option<Ty> {
+    match ty {
+        Ty::Adt { name, substs, .. } if name.to_string() == "Vec" => {
+            Some(substs.first().cloned().unwrap_or(Ty::Unknown))
+        }
+        _ => None,
+    }
+}
+
+/// The type of a binary expression, given the type of its left operand.
+fn binary_op_return_ty(op: BinaryOp, lhs_ty: &Ty) -> Ty {
+    if is_boolean_operator(op) {
+        Ty::Bool
+    } else if op.is_assignment() {
+        Ty::unit()
+    } else if is_builtin_binary_op(op, lhs_ty) {
+        lhs_ty.clone()
+    } else {
+        Ty::Unknown
+    }
+}
+
+/// Whether the operator is builtin for the type of the left operand: the
+/// arithmetic operators for numbers, the bitwise ones for integers and
+/// booleans, and the comparisons for all of those and chars.
+fn is_builtin_binary_op(op: BinaryOp, lhs_ty: &Ty) -> bool {
+    let is_integer = match lhs_ty {
+        Ty::Int(..) | Ty::Uint(..) | Ty::Infer(InferTy::IntVar(..)) => true,
+        _ => false,
+    };
+    let is_float = match lhs_ty {
+        Ty::Float(..) | Ty::Infer(InferTy::FloatVar(..)) => true,
+        _ => false,
+    };
+    match op {
+        BinaryOp::Addition
+        | BinaryOp::Subtraction
+        | BinaryOp::Multiplication
+        | BinaryOp::Division
+        | BinaryOp::Remainder
+        | BinaryOp::AddAssign
+        | BinaryOp::SubAssign
+        | BinaryOp::MulAssign
+        | BinaryOp::DivAssign
+        | BinaryOp::RemAssign => is_integer || is_float,
+        BinaryOp::LeftShift | BinaryOp::RightShift | BinaryOp::ShlAssign | BinaryOp::ShrAssign => {
+            is_integer
+        }
+        BinaryOp::BitwiseXor
+        | BinaryOp::BitwiseOr
+        | BinaryOp::BitwiseAnd
+        | BinaryOp::BitXorAssign
+        | BinaryOp::BitOrAssign
+        | BinaryOp::BitAndAssign => is_integer || *lhs_ty == Ty::Bool,
+        BinaryOp::EqualityTest
+        | BinaryOp::NegatedEqualityTest
+        | BinaryOp::LesserEqualTest
+        | BinaryOp::GreaterEqualTest
+        | BinaryOp::LesserTest
+        | BinaryOp::GreaterTest => match lhs_ty {
+            Ty::Bool | Ty::Char => true,
+            _ => is_integer || is_float,
+        },
+        BinaryOp::BooleanAnd | BinaryOp::BooleanOr | BinaryOp::Assignment => false,
     }
 }
 
@@ -523,11 +1056,13 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
     fn new(
         db: &'a D,
         body: Arc<Body>,
-        scopes: Arc<FnScopes>,
+        scopes: Arc<ExprScopes>,
         module: Module,
         impl_block: Option<ImplBlock>,
     ) -> Self {
         InferenceContext {
+            method_resolutions: FxHashMap::default(),
+            try_error_types: FxHashMap::default(),
             type_of_expr: ArenaMap::default(),
             type_of_pat: ArenaMap::default(),
             var_unification_table: InPlaceUnificationTable::new(),
@@ -551,7 +1086,14 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             let resolved = self.resolve_ty_completely(mem::replace(ty, Ty::Unknown));
             *ty = resolved;
         }
+        let mut try_error_types = mem::replace(&mut self.try_error_types, FxHashMap::default());
+        for ty in try_error_types.values_mut() {
+            let resolved = self.resolve_ty_completely(mem::replace(ty, Ty::Unknown));
+            *ty = resolved;
+        }
         InferenceResult {
+            method_resolutions: mem::replace(&mut self.method_resolutions, FxHashMap::default()),
+            try_error_types,
             type_of_expr: expr_types,
             type_of_pat: pat_types,
         }
@@ -561,6 +1103,14 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         self.type_of_expr.insert(expr, ty);
     }
 
+    fn write_method_resolution(&mut self, expr: ExprId, def_id: DefId) {
+        self.method_resolutions.insert(expr, def_id);
+    }
+
+    fn write_try_error_ty(&mut self, expr: ExprId, ty: Ty) {
+        self.try_error_types.insert(expr, ty);
+    }
+
     fn write_pat_ty(&mut self, pat: PatId, ty: Ty) {
         self.type_of_pat.insert(pat, ty);
     }
@@ -570,42 +1120,152 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
     }
 
     fn unify(&mut self, ty1: &Ty, ty2: &Ty) -> bool {
-        match (ty1, ty2) {
+        // try to resolve type vars first
+        let ty1 = self.resolve_ty_shallow(ty1);
+        let ty2 = self.resolve_ty_shallow(ty2);
+        match (&*ty1, &*ty2) {
             (Ty::Unknown, ..) => true,
             (.., Ty::Unknown) => true,
+            // the type variables come first, since e.g. `(Ty::Bool, _)` below
+            // would match them otherwise
+            (Ty::Infer(InferTy::TypeVar(tv1)), Ty::Infer(InferTy::TypeVar(tv2)))
+            | (Ty::Infer(InferTy::IntVar(tv1)), Ty::Infer(InferTy::IntVar(tv2)))
+            | (Ty::Infer(InferTy::FloatVar(tv1)), Ty::Infer(InferTy::FloatVar(tv2))) => {
+                // both type vars are unknown since we tried to resolve them
+                self.var_unification_table.union(*tv1, *tv2);
+                true
+            }
+            (Ty::Infer(InferTy::TypeVar(tv)), other)
+            | (other, Ty::Infer(InferTy::TypeVar(tv)))
+            | (Ty::Infer(InferTy::IntVar(tv)), other @ Ty::Int(_))
+            | (other @ Ty::Int(_), Ty::Infer(InferTy::IntVar(tv)))
+            | (Ty::Infer(InferTy::IntVar(tv)), other @ Ty::Uint(_))
+            | (other @ Ty::Uint(_), Ty::Infer(InferTy::IntVar(tv)))
+            | (Ty::Infer(InferTy::FloatVar(tv)), other @ Ty::Float(_))
+            | (other @ Ty::Float(_), Ty::Infer(InferTy::FloatVar(tv))) => {
+                // the type var is unknown since we tried to resolve it
+                self.var_unification_table
+                    .union_value(*tv, TypeVarValue::Known(other.clone()));
+                true
+            }
             (Ty::Bool, _)
             | (Ty::Str, _)
             | (Ty::Never, _)
             | (Ty::Char, _)
+            | (Ty::Opaque(..), _)
             | (Ty::Int(..), Ty::Int(..))
             | (Ty::Uint(..), Ty::Uint(..))
             | (Ty::Float(..), Ty::Float(..)) => ty1 == ty2,
             (
                 Ty::Adt {
-                    def_id: def_id1, ..
+                    def_id: def_id1,
+                    substs: substs1,
+                    ..
                 },
                 Ty::Adt {
-                    def_id: def_id2, ..
+                    def_id: def_id2,
+                    substs: substs2,
+                    ..
+                },
+            ) if def_id1 == def_id2 => {
+                // the generic arguments are missing where they aren't written
+                substs1.is_empty()
+                    || substs2.is_empty()
+                    || substs1.len() == substs2.len()
+                        && substs1
+                            .iter()
+                            .zip(substs2.iter())
+                            .all(|(t1, t2)| self.unify(t1, t2))
+            }
+            (
+                Ty::Projection {
+                    trait_def_id: trait_def_id1,
+                    self_ty: self_ty1,
+                    name: name1,
+                    ..
+                },
+                Ty::Projection {
+                    trait_def_id: trait_def_id2,
+                    self_ty: self_ty2,
+                    name: name2,
+                    ..
                 },
-            ) if def_id1 == def_id2 => true,
-            (Ty::Slice(t1), Ty::Slice(t2)) => self.unify(t1, t2),
+            ) if trait_def_id1 == trait_def_id2 && name1 == name2 => self.unify(self_ty1, self_ty2),
+            (Ty::Slice(t1), Ty::Slice(t2)) | (Ty::Array(t1), Ty::Array(t2)) => self.unify(t1, t2),
             (Ty::RawPtr(t1, m1), Ty::RawPtr(t2, m2)) if m1 == m2 => self.unify(t1, t2),
             (Ty::Ref(t1, m1), Ty::Ref(t2, m2)) if m1 == m2 => self.unify(t1, t2),
-            (Ty::FnPtr(sig1), Ty::FnPtr(sig2)) if sig1 == sig2 => true,
+            (Ty::FnPtr(sig1), Ty::FnPtr(sig2)) | (Ty::Closure(sig1), Ty::Closure(sig2)) => {
+                self.unify_sigs(sig1, sig2)
+            }
             (Ty::Tuple(ts1), Ty::Tuple(ts2)) if ts1.len() == ts2.len() => ts1
                 .iter()
                 .zip(ts2.iter())
                 .all(|(t1, t2)| self.unify(t1, t2)),
-            (Ty::Infer(InferTy::TypeVar(tv1)), Ty::Infer(InferTy::TypeVar(tv2))) => {
-                self.var_unification_table.union(*tv1, *tv2);
-                true
+            _ => false,
+        }
+    }
+
+    /// Unifies `from_ty` with `to_ty`, allowing the coercions which happen at
+    /// coercion sites: `!` to any type, `&mut T` to `&T`, references to raw
+    /// pointers, deref coercion like `&&T` to `&T`, and unsizing `&[T; n]` to
+    /// `&[T]`.
+    fn coerce(&mut self, from_ty: &Ty, to_ty: &Ty) -> bool {
+        let from_ty = self.resolve_ty_shallow(from_ty).into_owned();
+        let to_ty = self.resolve_ty_shallow(to_ty).into_owned();
+        match (&from_ty, &to_ty) {
+            (Ty::Never, _) => true,
+            (Ty::Ref(from_inner, from_mut), Ty::Ref(to_inner, to_mut))
+                if from_mut.coerces_to(*to_mut) =>
+            {
+                self.coerce_pointee(from_inner, to_inner)
             }
-            (Ty::Infer(InferTy::TypeVar(tv)), other) | (other, Ty::Infer(InferTy::TypeVar(tv))) => {
-                self.var_unification_table
-                    .union_value(*tv, TypeVarValue::Known(other.clone()));
-                true
+            (Ty::Ref(from_inner, from_mut), Ty::RawPtr(to_inner, to_mut))
+            | (Ty::RawPtr(from_inner, from_mut), Ty::RawPtr(to_inner, to_mut))
+                if from_mut.coerces_to(*to_mut) =>
+            {
+                self.unify(from_inner, to_inner)
             }
-            _ => false,
+            _ => self.unify(&from_ty, &to_ty),
+        }
+    }
+
+    /// Coerces the pointee of a reference: either unsizes an array to a
+    /// slice, or unifies the first type of the autoderef chain which matches.
+    fn coerce_pointee(&mut self, from_inner: &Ty, to_inner: &Ty) -> bool {
+        let from_inner = self.resolve_ty_as_possible(from_inner.clone());
+        if let (Ty::Array(elem), Ty::Slice(to_elem)) =
+            (&from_inner, &*self.resolve_ty_shallow(to_inner))
+        {
+            return self.unify(elem, to_elem);
+        }
+        let derefs: Vec<Ty> = from_inner.autoderef(self.db).collect();
+        for derefed_ty in derefs {
+            // unification may fail halfway, so undo it in that case
+            let snapshot = self.var_unification_table.snapshot();
+            if self.unify(&derefed_ty, to_inner) {
+                self.var_unification_table.commit(snapshot);
+                return true;
+            }
+            self.var_unification_table.rollback_to(snapshot);
+        }
+        false
+    }
+
+    fn unify_sigs(&mut self, sig1: &FnSig, sig2: &FnSig) -> bool {
+        sig1.input.len() == sig2.input.len()
+            && sig1
+                .input
+                .iter()
+                .zip(sig2.input.iter())
+                .all(|(t1, t2)| self.unify(t1, t2))
+            && self.unify(&sig1.output, &sig2.output)
+    }
+
+    /// The expected type, or a new type variable if there is no expectation.
+    fn expected_ty_or_new_var(&mut self, expected: &Expectation) -> Ty {
+        match expected.ty {
+            Ty::Unknown => self.new_type_var(),
+            ref ty => ty.clone(),
         }
     }
 
@@ -615,6 +1275,18 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         ))
     }
 
+    fn new_integer_var(&mut self) -> Ty {
+        Ty::Infer(InferTy::IntVar(
+            self.var_unification_table.new_key(TypeVarValue::Unknown),
+        ))
+    }
+
+    fn new_float_var(&mut self) -> Ty {
+        Ty::Infer(InferTy::FloatVar(
+            self.var_unification_table.new_key(TypeVarValue::Unknown),
+        ))
+    }
+
     /// Replaces Ty::Unknown by a new type var, so we can maybe still infer it.
     fn insert_type_vars_shallow(&mut self, ty: Ty) -> Ty {
         match ty {
@@ -633,28 +1305,55 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
     /// known type.
     fn resolve_ty_as_possible(&mut self, ty: Ty) -> Ty {
         ty.fold(&mut |ty| match ty {
-            Ty::Infer(InferTy::TypeVar(tv)) => {
-                if let Some(known_ty) = self.var_unification_table.probe_value(tv).known() {
+            Ty::Infer(tv) => {
+                let inner = tv.to_inner();
+                if let Some(known_ty) = self.var_unification_table.probe_value(inner).known() {
                     // known_ty may contain other variables that are known by now
                     self.resolve_ty_as_possible(known_ty.clone())
                 } else {
-                    Ty::Infer(InferTy::TypeVar(tv))
+                    Ty::Infer(tv)
                 }
             }
             _ => ty,
         })
     }
 
+    /// If `ty` is a type variable with known type, returns that type;
+    /// otherwise, return ty.
+    fn resolve_ty_shallow<'b>(&mut self, ty: &'b Ty) -> Cow<'b, Ty> {
+        let mut ty = Cow::Borrowed(ty);
+        // The type variable could resolve to an int/float variable. Hence try
+        // resolving up to three times; each type of variable shouldn't occur
+        // more than once.
+        for _ in 0..3 {
+            let known_ty = match &*ty {
+                Ty::Infer(tv) => self
+                    .var_unification_table
+                    .probe_value(tv.to_inner())
+                    .known()
+                    .cloned(),
+                _ => None,
+            };
+            match known_ty {
+                Some(known_ty) => ty = Cow::Owned(known_ty),
+                None => return ty,
+            }
+        }
+        ty
+    }
+
     /// Resolves the type completely; type variables without known type are
-    /// replaced by Ty::Unknown.
+    /// replaced by Ty::Unknown, and integer and float variables by their
+    /// default types.
     fn resolve_ty_completely(&mut self, ty: Ty) -> Ty {
         ty.fold(&mut |ty| match ty {
-            Ty::Infer(InferTy::TypeVar(tv)) => {
-                if let Some(known_ty) = self.var_unification_table.probe_value(tv).known() {
+            Ty::Infer(tv) => {
+                let inner = tv.to_inner();
+                if let Some(known_ty) = self.var_unification_table.probe_value(inner).known() {
                     // known_ty may contain other variables that are known by now
                     self.resolve_ty_completely(known_ty.clone())
                 } else {
-                    Ty::Unknown
+                    tv.fallback_value()
                 }
             }
             _ => ty,
@@ -700,6 +1399,30 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
     }
 
     fn infer_expr(&mut self, expr: ExprId, expected: &Expectation) -> Cancelable<Ty> {
+        let ty = self.infer_expr_inner(expr, expected)?;
+        self.unify(&ty, &expected.ty);
+        let ty = self.resolve_ty_as_possible(ty);
+        Ok(ty)
+    }
+
+    /// Infers the type of an expression at a coercion site, where its type
+    /// only needs to be coercible to the expected type. Returns the expected
+    /// type if the coercion succeeded, except for diverging expressions which
+    /// keep the type `!`, so that the callers can tell that they diverge.
+    fn infer_expr_coerce(&mut self, expr: ExprId, expected: &Expectation) -> Cancelable<Ty> {
+        let ty = self.infer_expr_inner(expr, expected)?;
+        let ty = match ty {
+            Ty::Never => Ty::Never,
+            _ if expected.ty != Ty::Unknown && self.coerce(&ty, &expected.ty) => {
+                expected.ty.clone()
+            }
+            _ => ty,
+        };
+        let ty = self.resolve_ty_as_possible(ty);
+        Ok(ty)
+    }
+
+    fn infer_expr_inner(&mut self, expr: ExprId, expected: &Expectation) -> Cancelable<Ty> {
         let body = Arc::clone(&self.body); // avoid borrow checker problem
         let ty = match &body[expr] {
             Expr::Missing => Ty::Unknown,
@@ -710,42 +1433,114 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             } => {
                 // if let is desugared to match, so this is always simple if
                 self.infer_expr(*condition, &Expectation::has_type(Ty::Bool))?;
-                let then_ty = self.infer_expr(*then_branch, expected)?;
-                if let Some(else_branch) = else_branch {
-                    self.infer_expr(*else_branch, expected)?;
-                } else {
-                    // no else branch -> unit
-                    self.unify(&expected.ty, &Ty::unit()); // actually coerce
+                match else_branch {
+                    Some(else_branch) => {
+                        let result_ty = self.expected_ty_or_new_var(expected);
+                        let branch_expected = Expectation::has_type(result_ty.clone());
+                        let then_ty = self.infer_expr_coerce(*then_branch, &branch_expected)?;
+                        let else_ty = self.infer_expr_coerce(*else_branch, &branch_expected)?;
+                        if then_ty == Ty::Never && else_ty == Ty::Never {
+                            Ty::Never
+                        } else {
+                            result_ty
+                        }
+                    }
+                    None => {
+                        // no else branch -> unit
+                        self.infer_expr_coerce(*then_branch, &Expectation::has_type(Ty::unit()))?;
+                        Ty::unit()
+                    }
                 }
-                then_ty
             }
             Expr::Block { statements, tail } => self.infer_block(statements, *tail, expected)?,
-            Expr::Loop { body } => {
+            Expr::Loop { body, .. } => {
                 self.infer_expr(*body, &Expectation::has_type(Ty::unit()))?;
                 // TODO handle break with value
                 Ty::Never
             }
-            Expr::While { condition, body } => {
+            Expr::While {
+                condition, body, ..
+            } => {
                 // while let is desugared to a match loop, so this is always simple while
                 self.infer_expr(*condition, &Expectation::has_type(Ty::Bool))?;
                 self.infer_expr(*body, &Expectation::has_type(Ty::unit()))?;
                 Ty::unit()
             }
-            Expr::For { iterable, body, .. } => {
-                let _iterable_ty = self.infer_expr(*iterable, &Expectation::none());
-                // TODO write type for pat
+            Expr::For {
+                iterable,
+                pat,
+                body,
+                ..
+            } => {
+                let iterable_ty = self.infer_expr(*iterable, &Expectation::none())?;
+                let item_ty = self.iterator_item_ty(*iterable, &iterable_ty);
+                self.infer_pat(*pat, &item_ty, BindingMode::Move)?;
                 self.infer_expr(*body, &Expectation::has_type(Ty::unit()))?;
                 Ty::unit()
             }
-            Expr::Lambda { body, .. } => {
-                // TODO write types for args, infer lambda type etc.
-                let _body_ty = self.infer_expr(*body, &Expectation::none())?;
-                Ty::Unknown
+            Expr::Lambda {
+                args,
+                arg_types,
+                ret_type,
+                body,
+                ..
+            } => {
+                // The parameters and the return type which are not annotated
+                // are taken from the expected signature, if the closure is
+                // passed where a function or a closure type is known.
+                // TODO: expectations from `Fn*` trait bounds need generics.
+                let expected_sig = match &*self.resolve_ty_shallow(&expected.ty) {
+                    Ty::FnPtr(sig) | Ty::Closure(sig) if sig.input.len() == args.len() => {
+                        Some(Arc::clone(sig))
+                    }
+                    _ => None,
+                };
+                let mut input = Vec::with_capacity(args.len());
+                for (i, (arg, arg_type)) in args.iter().zip(arg_types).enumerate() {
+                    let ty = Ty::from_hir_opt(
+                        self.db,
+                        &self.module,
+                        self.impl_block.as_ref(),
+                        arg_type.as_ref(),
+                    )?;
+                    let ty = self.insert_type_vars(ty);
+                    if let Some(sig) = &expected_sig {
+                        self.unify(&ty, &sig.input[i]);
+                    }
+                    let ty = self.infer_pat(*arg, &ty, BindingMode::Move)?;
+                    input.push(ty);
+                }
+                let ret_ty = Ty::from_hir_opt(
+                    self.db,
+                    &self.module,
+                    self.impl_block.as_ref(),
+                    ret_type.as_ref(),
+                )?;
+                let ret_ty = self.insert_type_vars(ret_ty);
+                if let Some(sig) = &expected_sig {
+                    self.unify(&ret_ty, &sig.output);
+                }
+                // `return` in the body returns from the closure
+                let outer_return_ty = mem::replace(&mut self.return_ty, ret_ty.clone());
+                let output = self.infer_expr(*body, &Expectation::has_type(ret_ty));
+                self.return_ty = outer_return_ty;
+                let output = output?;
+                Ty::Closure(Arc::new(FnSig { input, output }))
+            }
+            Expr::MacroCall { path, args } => {
+                for arg in args {
+                    self.infer_expr(*arg, &Expectation::none())?;
+                }
+                // TODO: the type of the expansion
+                match path.as_ref().and_then(|path| path.as_ident()) {
+                    Some(name) if is_diverging_macro(name) => Ty::Never,
+                    _ => Ty::Unknown,
+                }
             }
             Expr::Call { callee, args } => {
                 let callee_ty = self.infer_expr(*callee, &Expectation::none())?;
                 let (arg_tys, ret_ty) = match &callee_ty {
-                    Ty::FnPtr(sig) => (&sig.input[..], sig.output.clone()),
+                    Ty::FnPtr(sig) | Ty::Closure(sig) => (&sig.input[..], sig.output.clone()),
                     _ => {
                         // not callable
                         // TODO report an error?
@@ -753,35 +1548,85 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                     }
                 };
                 for (i, arg) in args.iter().enumerate() {
-                    self.infer_expr(
+                    self.infer_expr_coerce(
                         *arg,
                         &Expectation::has_type(arg_tys.get(i).cloned().unwrap_or(Ty::Unknown)),
                     )?;
                 }
                 ret_ty
             }
-            Expr::MethodCall { receiver, args, .. } => {
-                let _receiver_ty = self.infer_expr(*receiver, &Expectation::none())?;
-                // TODO resolve method...
-                for (_i, arg) in args.iter().enumerate() {
-                    // TODO unify / expect argument type
-                    self.infer_expr(*arg, &Expectation::none())?;
+            Expr::MethodCall {
+                receiver,
+                args,
+                method_name,
+                ..
+            } => {
+                let receiver_ty = self.infer_expr(*receiver, &Expectation::none())?;
+                let resolved =
+                    receiver_ty
+                        .clone()
+                        .lookup_method(self.db, method_name, &self.module)?;
+                let method_ty = match resolved {
+                    Some(func) => {
+                        self.write_method_resolution(expr, func.def_id());
+                        match receiver_ty.clone().bound_of_method(self.db, &func)? {
+                            Some((trait_bound, self_ty)) => {
+                                type_for_trait_method(self.db, &func, trait_bound, self_ty)?
+                            }
+                            None => self.db.type_for_def(func.def_id())?,
+                        }
+                    }
+                    None => Ty::Unknown,
+                };
+                let method_ty = self.insert_type_vars(method_ty);
+                let (expected_receiver_ty, arg_tys, ret_ty) = match &method_ty {
+                    Ty::FnPtr(sig) => {
+                        if !sig.input.is_empty() {
+                            (sig.input[0].clone(), &sig.input[1..], sig.output.clone())
+                        } else {
+                            (Ty::Unknown, &sig.input[..], sig.output.clone())
+                        }
+                    }
+                    _ => (Ty::Unknown, &[][..], Ty::Unknown),
+                };
+                // TODO we would have to apply the autoderef/autoref steps here
+                // to get the correct receiver type to unify...
+                self.unify(&expected_receiver_ty, &receiver_ty);
+                for (i, arg) in args.iter().enumerate() {
+                    self.infer_expr_coerce(
+                        *arg,
+                        &Expectation::has_type(arg_tys.get(i).cloned().unwrap_or(Ty::Unknown)),
+                    )?;
                 }
-                Ty::Unknown
+                ret_ty
             }
             Expr::Match { expr, arms } => {
-                let _ty = self.infer_expr(*expr, &Expectation::none())?;
+                let input_ty = self.infer_expr(*expr, &Expectation::none())?;
+                let result_ty = self.expected_ty_or_new_var(expected);
+                let arm_expected = Expectation::has_type(result_ty.clone());
+                // a match without arms diverges, too
+                let mut all_arms_diverge = true;
                 for arm in arms {
-                    // TODO type the bindings in pats
-                    // TODO type the guard
-                    let _ty = self.infer_expr(arm.expr, &Expectation::none())?;
+                    for pat in &arm.pats {
+                        self.infer_pat(*pat, &input_ty, BindingMode::Move)?;
+                    }
+                    if let Some(guard) = arm.guard {
+                        self.infer_expr(guard, &Expectation::has_type(Ty::Bool))?;
+                    }
+                    let arm_ty = self.infer_expr_coerce(arm.expr, &arm_expected)?;
+                    if arm_ty != Ty::Never {
+                        all_arms_diverge = false;
+                    }
+                }
+                if all_arms_diverge {
+                    Ty::Never
+                } else {
+                    result_ty
                 }
-                // TODO unify all the match arm types
-                Ty::Unknown
             }
             Expr::Path(p) => self.infer_path_expr(expr, p)?.unwrap_or(Ty::Unknown),
-            Expr::Continue => Ty::Never,
-            Expr::Break { expr } => {
+            Expr::Continue { .. } => Ty::Never,
+            Expr::Break { expr, .. } => {
                 if let Some(expr) = expr {
                     // TODO handle break with value
                     self.infer_expr(*expr, &Expectation::none())?;
@@ -790,7 +1635,8 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             }
             Expr::Return { expr } => {
                 if let Some(expr) = expr {
-                    self.infer_expr(*expr, &Expectation::has_type(self.return_ty.clone()))?;
+                    let return_ty = self.return_ty.clone();
+                    self.infer_expr_coerce(*expr, &Expectation::has_type(return_ty))?;
                 }
                 Ty::Never
             }
@@ -806,7 +1652,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                     } else {
                         Ty::Unknown
                     };
-                    self.infer_expr(field.expr, &Expectation::has_type(field_ty))?;
+                    self.infer_expr_coerce(field.expr, &Expectation::has_type(field_ty))?;
                 }
                 if let Some(expr) = spread {
                     self.infer_expr(*expr, &Expectation::has_type(ty.clone()))?;
@@ -815,18 +1661,56 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             }
             Expr::Field { expr, name } => {
                 let receiver_ty = self.infer_expr(*expr, &Expectation::none())?;
-                let ty = match receiver_ty {
-                    Ty::Tuple(fields) => {
-                        let i = name.to_string().parse::<usize>().ok();
-                        i.and_then(|i| fields.get(i).cloned())
-                            .unwrap_or(Ty::Unknown)
-                    }
-                    Ty::Adt { def_id, .. } => self.db.type_for_field(def_id, name.clone())?,
-                    _ => Ty::Unknown,
-                };
+                let mut ty = Ty::Unknown;
+                for derefed_ty in receiver_ty.autoderef(self.db) {
+                    ty = match derefed_ty {
+                        Ty::Tuple(fields) => {
+                            let i = name.to_string().parse::<usize>().ok();
+                            i.and_then(|i| fields.get(i).cloned())
+                                .unwrap_or(Ty::Unknown)
+                        }
+                        // a `Box` doesn't have the field, but its content may
+                        Ty::Adt { def_id, .. } => {
+                            match self.db.type_for_field(def_id, name.clone())? {
+                                Ty::Unknown => continue,
+                                field_ty => field_ty,
+                            }
+                        }
+                        _ => continue,
+                    };
+                    break;
+                }
                 self.insert_type_vars(ty)
             }
-            Expr::Try { expr } => {
+            Expr::Try { expr: inner } => {
+                let inner_ty = self.infer_expr(*inner, &Expectation::none())?;
+                let inner_ty = self.resolve_ty_shallow(&inner_ty).into_owned();
+                match try_output_types(&inner_ty) {
+                    Some((ok_ty, err_ty)) => {
+                        // The error is converted with `From::from` into the
+                        // error type of the function, so it's not unified
+                        // with it.
+                        if let Some(err_ty) = err_ty {
+                            self.write_try_error_ty(expr, err_ty);
+                        }
+                        ok_ty
+                    }
+                    // TODO: the `Try` trait
+                    None => Ty::Unknown,
+                }
+            }
+            Expr::Async { body } => {
+                // TODO: `impl Future<Output = T>`
+                let _body_ty = self.infer_expr(*body, &Expectation::none())?;
+                Ty::Unknown
+            }
+            Expr::Box { expr } => {
+                // TODO: `Box<T>`
+                let _inner_ty = self.infer_expr(*expr, &Expectation::none())?;
+                Ty::Unknown
+            }
+            Expr::Await { expr } => {
+                // TODO: the `Output` of the `Future`
                 let _inner_ty = self.infer_expr(*expr, &Expectation::none())?;
                 Ty::Unknown
             }
@@ -839,13 +1723,23 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 cast_ty
             }
             Expr::Ref { expr, mutability } => {
-                // TODO pass the expectation down
-                let inner_ty = self.infer_expr(*expr, &Expectation::none())?;
-                // TODO reference coercions etc.
+                let inner_expected = match &*self.resolve_ty_shallow(&expected.ty) {
+                    Ty::Ref(inner, _) | Ty::RawPtr(inner, _) => {
+                        Expectation::has_type((**inner).clone())
+                    }
+                    _ => Expectation::none(),
+                };
+                let inner_ty = self.infer_expr(*expr, &inner_expected)?;
                 Ty::Ref(Arc::new(inner_ty), *mutability)
             }
             Expr::UnaryOp { expr, op } => {
-                let inner_ty = self.infer_expr(*expr, &Expectation::none())?;
+                // `-x` and `!x` have the type of `x` for the builtin types
+                let inner_expected = match op {
+                    Some(UnaryOp::Neg) | Some(UnaryOp::Not) => expected.clone(),
+                    _ => Expectation::none(),
+                };
+                let inner_ty = self.infer_expr(*expr, &inner_expected)?;
+                let inner_ty = self.resolve_ty_shallow(&inner_ty).into_owned();
                 match op {
                     Some(UnaryOp::Deref) => {
                         match inner_ty {
@@ -856,7 +1750,22 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                             _ => Ty::Unknown,
                         }
                     }
-                    _ => Ty::Unknown,
+                    Some(UnaryOp::Neg) => match inner_ty {
+                        Ty::Int(..)
+                        | Ty::Float(..)
+                        | Ty::Infer(InferTy::IntVar(..))
+                        | Ty::Infer(InferTy::FloatVar(..)) => inner_ty,
+                        // TODO: the `Neg` trait
+                        _ => Ty::Unknown,
+                    },
+                    Some(UnaryOp::Not) => match inner_ty {
+                        Ty::Bool | Ty::Int(..) | Ty::Uint(..) | Ty::Infer(InferTy::IntVar(..)) => {
+                            inner_ty
+                        }
+                        // TODO: the `Not` trait
+                        _ => Ty::Unknown,
+                    },
+                    None => Ty::Unknown,
                 }
             }
             Expr::BinaryOp { lhs, rhs, op } => match op {
@@ -867,33 +1776,315 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                         }
                         _ => Expectation::none(),
                     };
-                    let _lhs_ty = self.infer_expr(*lhs, &subtype_expectation)?;
-                    let _rhs_ty = self.infer_expr(*rhs, &subtype_expectation)?;
-
-                    if is_boolean_operator(*op) {
-                        Ty::Bool
-                    } else {
-                        Ty::Unknown
-                    }
+                    let lhs_ty = self.infer_expr(*lhs, &subtype_expectation)?;
+                    let lhs_ty = self.resolve_ty_shallow(&lhs_ty).into_owned();
+                    let rhs_expectation = binary_op_rhs_expectation(*op, &lhs_ty);
+                    let _rhs_ty = self.infer_expr(*rhs, &Expectation::has_type(rhs_expectation))?;
+                    binary_op_return_ty(*op, &lhs_ty)
                 }
                 _ => Ty::Unknown,
             },
+            Expr::Index { base, index } => {
+                let base_ty = self.infer_expr(*base, &Expectation::none())?;
+                let elem_ty = base_ty
+                    .clone()
+                    .autoderef(self.db)
+                    .filter_map(|derefed_ty| match derefed_ty {
+                        Ty::Array(elem_ty) | Ty::Slice(elem_ty) => Some((*elem_ty).clone()),
+                        // `Vec<T>` derefs to `[T]`
+                        ty => vec_elem_ty(&ty),
+                    })
+                    .next();
+                match elem_ty {
+                    // builtin indexing of arrays and slices
+                    Some(elem_ty) => {
+                        let usize_ty = Ty::Uint(primitive::UintTy::Usize);
+                        self.infer_expr(*index, &Expectation::has_type(usize_ty))?;
+                        elem_ty
+                    }
+                    // `IndexMut` requires `Index` with the same output, so it's
+                    // enough to look at `Index`
+                    None => {
+                        let index_method = base_ty.lookup_op_method(
+                            self.db,
+                            &Name::new("Index".into()),
+                            &Name::new("index".into()),
+                        )?;
+                        let method_ty = match index_method {
+                            Some(func) => self.db.type_for_def(func.def_id())?,
+                            None => Ty::Unknown,
+                        };
+                        let method_ty = self.insert_type_vars(method_ty);
+                        let (index_ty, output_ty) = match &method_ty {
+                            Ty::FnPtr(sig) if sig.input.len() == 2 => {
+                                (sig.input[1].clone(), sig.output.clone())
+                            }
+                            _ => (Ty::Unknown, Ty::Unknown),
+                        };
+                        self.infer_expr(*index, &Expectation::has_type(index_ty))?;
+                        // `a[i]` is `*a.index(i)`
+                        match output_ty {
+                            Ty::Ref(inner, _) => (*inner).clone(),
+                            _ => Ty::Unknown,
+                        }
+                    }
+                }
+            }
+            Expr::Range { lhs, rhs, .. } => {
+                let lhs_ty = match lhs {
+                    Some(lhs) => self.infer_expr(*lhs, &Expectation::none())?,
+                    None => Ty::Unknown,
+                };
+                if let Some(rhs) = rhs {
+                    self.infer_expr(*rhs, &Expectation::has_type(lhs_ty))?;
+                }
+                // TODO: the `Range*` structs of `core::ops`
+                Ty::Unknown
+            }
+            Expr::Array(array) => {
+                // an array literal may be unsized to an expected slice, too
+                let elem_ty = match &*self.resolve_ty_shallow(&expected.ty) {
+                    Ty::Array(elem_ty) | Ty::Slice(elem_ty) => (**elem_ty).clone(),
+                    _ => self.new_type_var(),
+                };
+                match array {
+                    Array::ElementList(items) => {
+                        // All the elements have the same type.
+                        for item in items {
+                            self.infer_expr(*item, &Expectation::has_type(elem_ty.clone()))?;
+                        }
+                    }
+                    Array::Repeat {
+                        initializer,
+                        repeat,
+                    } => {
+                        self.infer_expr(*initializer, &Expectation::has_type(elem_ty.clone()))?;
+                        let usize_ty = Ty::Uint(primitive::UintTy::Usize);
+                        self.infer_expr(*repeat, &Expectation::has_type(usize_ty))?;
+                    }
+                }
+                Ty::Array(Arc::new(elem_ty))
+            }
+            Expr::Tuple { exprs } => {
+                let expected_tys = match &*self.resolve_ty_shallow(&expected.ty) {
+                    Ty::Tuple(tys) if tys.len() == exprs.len() => Some(Arc::clone(tys)),
+                    _ => None,
+                };
+                let mut tys = Vec::with_capacity(exprs.len());
+                for (i, expr) in exprs.iter().enumerate() {
+                    let expected_ty = expected_tys
+                        .as_ref()
+                        .map_or(Ty::Unknown, |tys| tys[i].clone());
+                    tys.push(self.infer_expr(*expr, &Expectation::has_type(expected_ty))?);
+                }
+                Ty::Tuple(tys.into())
+            }
+            Expr::Literal(lit) => match lit {
+                Literal::Bool(..) => Ty::Bool,
+                Literal::String => Ty::Ref(Arc::new(Ty::Str), Mutability::Shared),
+                Literal::ByteString => {
+                    let slice = Ty::Slice(Arc::new(Ty::Uint(primitive::UintTy::U8)));
+                    Ty::Ref(Arc::new(slice), Mutability::Shared)
+                }
+                Literal::Char => Ty::Char,
+                Literal::Int(UncertainIntTy::Signed(ty)) => Ty::Int(*ty),
+                Literal::Int(UncertainIntTy::Unsigned(ty)) => Ty::Uint(*ty),
+                Literal::Float(UncertainFloatTy::Known(ty)) => Ty::Float(*ty),
+                Literal::Int(UncertainIntTy::Unknown) => self.new_integer_var(),
+                Literal::Float(UncertainFloatTy::Unknown) => self.new_float_var(),
+            },
         };
         // use a new type variable if we got Ty::Unknown here
         let ty = self.insert_type_vars_shallow(ty);
-        self.unify(&ty, &expected.ty);
-        let ty = self.resolve_ty_as_possible(ty);
         self.write_expr_ty(expr, ty.clone());
         Ok(ty)
     }
 
+    /// The `IntoIterator::Item` of the iterable of a `for` loop. As long as
+    /// there are no associated types, this only knows about ranges, arrays,
+    /// `Vec`s and references to them and to slices.
+    // TODO: the `IntoIterator` impls of other types
+    fn iterator_item_ty(&mut self, iterable: ExprId, iterable_ty: &Ty) -> Ty {
+        if let Expr::Range { lhs, rhs, .. } = &self.body[iterable] {
+            // ranges don't have a type yet, but their items have the type of
+            // the bounds
+            return lhs
+                .iter()
+                .chain(rhs)
+                .filter_map(|bound| self.type_of_expr.get(*bound).cloned())
+                .next()
+                .unwrap_or(Ty::Unknown);
+        }
+        match &*self.resolve_ty_shallow(iterable_ty) {
+            Ty::Array(elem_ty) => (**elem_ty).clone(),
+            Ty::Ref(inner, mutability) => match &*self.resolve_ty_shallow(inner) {
+                Ty::Array(elem_ty) | Ty::Slice(elem_ty) => {
+                    Ty::Ref(Arc::clone(elem_ty), *mutability)
+                }
+                inner => match vec_elem_ty(inner) {
+                    Some(elem_ty) => Ty::Ref(Arc::new(elem_ty), *mutability),
+                    None => Ty::Unknown,
+                },
+            },
+            ty => vec_elem_ty(ty).unwrap_or(Ty::Unknown),
+        }
+    }
+
+    /// Infers the type of a pattern which matches a value of the `expected`
+    /// type, and the types of the bindings in it.
+    fn infer_pat(&mut self, pat: PatId, expected: &Ty, default_bm: BindingMode) -> Cancelable<Ty> {
+        let body = Arc::clone(&self.body); // avoid borrow checker problem
+
+        let is_non_ref_pat = match &body[pat] {
+            Pat::Tuple { .. }
+            | Pat::TupleStruct { .. }
+            | Pat::Struct { .. }
+            | Pat::Slice { .. }
+            | Pat::Path(..) => true,
+            _ => false,
+        };
+        let mut expected = self.resolve_ty_shallow(expected).into_owned();
+        let mut default_bm = default_bm;
+        if is_non_ref_pat {
+            // a non-reference pattern matching a reference binds by reference
+            while let Ty::Ref(inner, mutability) = expected {
+                expected = self.resolve_ty_shallow(&inner).into_owned();
+                default_bm = match default_bm {
+                    BindingMode::Move | BindingMode::Ref(Mutability::Mut) => {
+                        BindingMode::Ref(mutability)
+                    }
+                    BindingMode::Ref(Mutability::Shared) => BindingMode::Ref(Mutability::Shared),
+                };
+            }
+        }
+
+        let ty = match &body[pat] {
+            Pat::Missing | Pat::Wild => expected.clone(),
+            Pat::Bind { mode, subpat, .. } => {
+                let binding_mode = match mode {
+                    BindingAnnotation::Unannotated | BindingAnnotation::Mutable => default_bm,
+                    _ => BindingMode::convert(*mode),
+                };
+                let inner_ty = match subpat {
+                    Some(subpat) => self.infer_pat(*subpat, &expected, default_bm)?,
+                    None => expected.clone(),
+                };
+                let inner_ty = self.insert_type_vars_shallow(inner_ty);
+                let bound_ty = match binding_mode {
+                    BindingMode::Ref(mutability) => Ty::Ref(Arc::new(inner_ty.clone()), mutability),
+                    BindingMode::Move => inner_ty.clone(),
+                };
+                let bound_ty = self.resolve_ty_as_possible(bound_ty);
+                self.write_pat_ty(pat, bound_ty);
+                return Ok(inner_ty);
+            }
+            Pat::Tuple { args, ellipsis } => {
+                let expected_tys = match &expected {
+                    Ty::Tuple(tys) => Some(Arc::clone(tys)),
+                    _ => None,
+                };
+                let mut tys = Vec::with_capacity(args.len());
+                for (i, arg) in args.iter().enumerate() {
+                    // the patterns after `..` match the last fields
+                    let field_idx = match (ellipsis, &expected_tys) {
+                        (Some(ellipsis), Some(expected_tys)) if i >= *ellipsis => {
+                            (expected_tys.len() + i).checked_sub(args.len())
+                        }
+                        _ => Some(i),
+                    };
+                    let expected_ty = expected_tys
+                        .as_ref()
+                        .and_then(|tys| tys.get(field_idx?).cloned())
+                        .unwrap_or(Ty::Unknown);
+                    tys.push(self.infer_pat(*arg, &expected_ty, default_bm)?);
+                }
+                match ellipsis {
+                    None => Ty::Tuple(tys.into()),
+                    Some(_) => expected.clone(),
+                }
+            }
+            Pat::Ref { pat, mutability } => {
+                let expected_inner = match &expected {
+                    Ty::Ref(inner, _) => (**inner).clone(),
+                    _ => Ty::Unknown,
+                };
+                let inner_ty = self.infer_pat(*pat, &expected_inner, BindingMode::Move)?;
+                Ty::Ref(Arc::new(inner_ty), *mutability)
+            }
+            Pat::TupleStruct { path, args } => {
+                let (ty, def_id) = self.resolve_variant(path.as_ref())?;
+                for (i, arg) in args.iter().enumerate() {
+                    let field_ty = match def_id {
+                        Some(def_id) => {
+                            self.db.type_for_field(def_id, Name::tuple_field_name(i))?
+                        }
+                        None => Ty::Unknown,
+                    };
+                    self.infer_pat(*arg, &field_ty, default_bm)?;
+                }
+                ty
+            }
+            Pat::Struct { path, fields, .. } => {
+                let (ty, def_id) = self.resolve_variant(path.as_ref())?;
+                for field in fields {
+                    let field_ty = match def_id {
+                        Some(def_id) => self.db.type_for_field(def_id, field.name.clone())?,
+                        None => Ty::Unknown,
+                    };
+                    self.infer_pat(field.pat, &field_ty, default_bm)?;
+                }
+                ty
+            }
+            Pat::Path(path) => self.resolve_variant(Some(path))?.0,
+            Pat::Slice {
+                prefix,
+                slice,
+                suffix,
+            } => {
+                let elem_ty = match &expected {
+                    Ty::Array(elem_ty) | Ty::Slice(elem_ty) => (**elem_ty).clone(),
+                    _ => Ty::Unknown,
+                };
+                for elem in prefix.iter().chain(suffix) {
+                    self.infer_pat(*elem, &elem_ty, default_bm)?;
+                }
+                if let Some(slice) = slice {
+                    // the rest has the type of the matched array or slice
+                    self.infer_pat(*slice, &expected, default_bm)?;
+                }
+                expected.clone()
+            }
+            Pat::Literal(expr) => {
+                self.infer_expr(*expr, &Expectation::has_type(expected.clone()))?
+            }
+            Pat::Range { start, end, .. } => {
+                let start_ty = self.infer_expr(*start, &Expectation::has_type(expected.clone()))?;
+                self.infer_expr(*end, &Expectation::has_type(start_ty.clone()))?;
+                start_ty
+            }
+            Pat::Box { inner } => {
+                // TODO: `Box<T>`
+                self.infer_pat(*inner, &Ty::Unknown, default_bm)?;
+                Ty::Unknown
+            }
+        };
+        // use a new type variable if we got Ty::Unknown here
+        let ty = self.insert_type_vars_shallow(ty);
+        self.unify(&ty, &expected);
+        let ty = self.resolve_ty_as_possible(ty);
+        self.write_pat_ty(pat, ty.clone());
+        Ok(ty)
+    }
+
     fn infer_block(
         &mut self,
         statements: &[Statement],
         tail: Option<ExprId>,
         expected: &Expectation,
     ) -> Cancelable<Ty> {
+        let mut diverges = false;
         for stmt in statements {
+            self.db.check_canceled()?;
             match stmt {
                 Statement::Let {
                     pat,
@@ -907,22 +2098,26 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                         type_ref.as_ref(),
                     )?;
                     let decl_ty = self.insert_type_vars(decl_ty);
-                    let ty = if let Some(expr) = initializer {
-                        let expr_ty = self.infer_expr(*expr, &Expectation::has_type(decl_ty))?;
-                        expr_ty
-                    } else {
-                        decl_ty
-                    };
-
-                    self.write_pat_ty(*pat, ty);
+                    if let Some(expr) = initializer {
+                        let expected = Expectation::has_type(decl_ty.clone());
+                        if self.infer_expr_coerce(*expr, &expected)? == Ty::Never {
+                            diverges = true;
+                        }
+                    }
+                    self.infer_pat(*pat, &decl_ty, BindingMode::Move)?;
                 }
                 Statement::Expr(expr) => {
-                    self.infer_expr(*expr, &Expectation::none())?;
+                    if self.infer_expr(*expr, &Expectation::none())? == Ty::Never {
+                        diverges = true;
+                    }
                 }
             }
         }
         let ty = if let Some(expr) = tail {
-            self.infer_expr(expr, expected)?
+            self.infer_expr_coerce(expr, expected)?
+        } else if diverges {
+            // e.g. a block ending with `return x;`
+            Ty::Never
         } else {
             Ty::unit()
         };
@@ -934,7 +2129,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         for (type_ref, pat) in signature.args().iter().zip(body.args()) {
             let ty = self.make_ty(type_ref)?;
             let ty = self.insert_type_vars(ty);
-            self.write_pat_ty(*pat, ty);
+            self.infer_pat(*pat, &ty, BindingMode::Move)?;
         }
         self.return_ty = {
             let ty = self.make_ty(signature.ret_type())?;
@@ -944,6 +2139,12 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         Ok(())
     }
 
+    fn collect_const_signature(&mut self, type_ref: &TypeRef) -> Cancelable<()> {
+        let ty = self.make_ty(type_ref)?;
+        self.return_ty = self.insert_type_vars(ty);
+        Ok(())
+    }
+
     fn infer_body(&mut self) -> Cancelable<()> {
         self.infer_expr(
             self.body.body_expr(),
@@ -954,15 +2155,19 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
 }
 
 pub fn infer(db: &impl HirDatabase, def_id: DefId) -> Cancelable<Arc<InferenceResult>> {
-    let function = Function::new(def_id); // TODO: consts also need inference
-    let body = function.body(db)?;
-    let scopes = db.fn_scopes(def_id)?;
-    let module = function.module(db)?;
-    let impl_block = function.impl_block(db)?;
+    let _timer = QueryTimer::new(db, "infer");
+    let body = db.body_hir(def_id)?;
+    let scopes = db.expr_scopes(def_id)?;
+    let module = def_id.module(db)?;
+    let impl_block = def_id.impl_block(db)?;
     let mut ctx = InferenceContext::new(db, body, scopes, module, impl_block);
 
-    let signature = function.signature(db);
-    ctx.collect_fn_signature(&signature)?;
+    match def_id.resolve(db)? {
+        Def::Function(function) => ctx.collect_fn_signature(&function.signature(db))?,
+        Def::Const(c) => ctx.collect_const_signature(&c.type_ref(db))?,
+        Def::Static(s) => ctx.collect_const_signature(&s.type_ref(db))?,
+        _ => panic!("Trying to infer types for item type without body"),
+    }
 
     ctx.infer_body()?;
 
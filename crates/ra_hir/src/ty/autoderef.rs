@@ -0,0 +1,32 @@
+//! In certain situations, rust automatically inserts derefs as necessary: for
+//! example, field accesses `foo.bar` and method calls `foo.bar()` still work
+//! when `foo` is actually a reference to a type with the field or method
+//! `bar`. This is an approximation of the logic in rustc (which lives in
+//! librustc_typeck/check/autoderef.rs).
+
+use ra_syntax::algo::generate;
+
+use crate::HirDatabase;
+use super::Ty;
+
+impl Ty {
+    /// Iterates over the type and the types it successively derefs to,
+    /// starting with the type itself.
+    pub fn autoderef<'a>(self, db: &'a impl HirDatabase) -> impl Iterator<Item = Ty> + 'a {
+        generate(Some(self), move |ty| ty.autoderef_step(db))
+    }
+
+    fn autoderef_step(&self, _db: &impl HirDatabase) -> Option<Ty> {
+        match self {
+            Ty::Ref(inner, _) => Some((**inner).clone()),
+            // As long as there are no lang items, `Box` is recognized by its
+            // name.
+            Ty::Adt { name, substs, .. } if name.to_string() == "Box" => {
+                Some(substs.first().cloned().unwrap_or(Ty::Unknown))
+            }
+            // TODO: `Deref::deref` needs generic substitutions, which `Ty`
+            // doesn't have yet.
+            _ => None,
+        }
+    }
+}
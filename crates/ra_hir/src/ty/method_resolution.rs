@@ -0,0 +1,406 @@
+//! This module is concerned with finding methods that a given type provides.
+//! For details about how this works in rustc, see the method lookup page in the
+//! [rustc guide](https://rust-lang.github.io/rustc-guide/method-lookup.html)
+//! and the corresponding code mostly in librustc_typeck/check/method/probe.rs.
+use std::sync::Arc;
+
+use rustc_hash::FxHashMap;
+
+use ra_db::{Cancelable, SourceRootId};
+
+use crate::{
+    HirDatabase, DefId, Def, Module, Crate, Name, Function, Trait,
+    module_tree::ModuleId,
+    impl_block::{ImplId, ImplBlock, ImplItem},
+    type_ref::TypeRef,
+};
+use super::{Ty, TraitBound, Expansions};
+
+/// This is used as a key for indexing impls.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TyFingerprint {
+    Adt(DefId),
+    // we'll also want to index impls for primitive types etc.
+}
+
+impl TyFingerprint {
+    /// Creates a TyFingerprint for looking up an impl. Only certain types can
+    /// have impls: if we have some `struct S`, we can have an `impl S`, but not
+    /// `impl &S`. Hence, this will return `None` for reference types and such.
+    fn for_impl(ty: &Ty) -> Option<TyFingerprint> {
+        match ty {
+            Ty::Adt { def_id, .. } => Some(TyFingerprint::Adt(*def_id)),
+            _ => None,
+        }
+    }
+}
+
+/// The impls of a crate: the inherent ones indexed by their target types, and
+/// the trait impls indexed by their traits.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CrateImplBlocks {
+    /// To make sense of the ModuleIds, we need the source root.
+    source_root_id: SourceRootId,
+    impls: FxHashMap<TyFingerprint, Vec<(ModuleId, ImplId)>>,
+    /// The impls of each trait, with the types they are for.
+    impls_by_trait: FxHashMap<DefId, Vec<(Ty, ModuleId, ImplId)>>,
+}
+
+impl CrateImplBlocks {
+    pub fn lookup_impl_blocks<'a>(
+        &'a self,
+        db: &'a impl HirDatabase,
+        ty: &Ty,
+    ) -> impl Iterator<Item = Cancelable<ImplBlock>> + 'a {
+        let fingerprint = TyFingerprint::for_impl(ty);
+        fingerprint
+            .and_then(|f| self.impls.get(&f))
+            .into_iter()
+            .flat_map(|i| i.iter())
+            .map(move |(module_id, impl_id)| {
+                let module_impl_blocks = db.impls_in_module(self.source_root_id, *module_id)?;
+                Ok(ImplBlock::from_id(module_impl_blocks, *impl_id))
+            })
+    }
+
+    /// The impls of the trait for exactly the type `ty`.
+    pub(super) fn lookup_trait_impl_blocks(
+        &self,
+        db: &impl HirDatabase,
+        trait_def_id: DefId,
+        ty: &Ty,
+        expanding: &mut Expansions,
+    ) -> Cancelable<Vec<ImplBlock>> {
+        let mut res = Vec::new();
+        let impls = match self.impls_by_trait.get(&trait_def_id) {
+            Some(it) => it,
+            None => return Ok(res),
+        };
+        for (target_ty, module_id, impl_id) in impls {
+            if is_impl_for(db, target_ty, ty, expanding)? {
+                let module_impl_blocks = db.impls_in_module(self.source_root_id, *module_id)?;
+                res.push(ImplBlock::from_id(module_impl_blocks, *impl_id));
+            }
+        }
+        Ok(res)
+    }
+
+    /// The trait impls for exactly the type `ty`, with their traits.
+    pub(super) fn lookup_trait_impls_for_type(
+        &self,
+        db: &impl HirDatabase,
+        ty: &Ty,
+    ) -> Cancelable<Vec<(DefId, ImplBlock)>> {
+        let mut res = Vec::new();
+        for (trait_def_id, impls) in self.impls_by_trait.iter() {
+            for (target_ty, module_id, impl_id) in impls {
+                if is_impl_for(db, target_ty, ty, &mut Expansions::default())? {
+                    let module_impl_blocks = db.impls_in_module(self.source_root_id, *module_id)?;
+                    res.push((
+                        *trait_def_id,
+                        ImplBlock::from_id(module_impl_blocks, *impl_id),
+                    ));
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    fn collect(&mut self, db: &impl HirDatabase, module_id: ModuleId) -> Cancelable<()> {
+        let module = Module::from_module_id(db, self.source_root_id, module_id)?;
+        let module_impl_blocks = db.impls_in_module(self.source_root_id, module_id)?;
+
+        for (impl_id, impl_data) in module_impl_blocks.impls() {
+            let impl_block = ImplBlock::from_id(Arc::clone(&module_impl_blocks), impl_id);
+            // Normalizing the target type would need the index we are
+            // building, so projections are normalized when looking impls up.
+            let target_ty =
+                Ty::from_hir_unnormalized(db, &module, Some(&impl_block), impl_data.target_type())?;
+            if let Some(target_trait) = impl_data.target_trait() {
+                let trait_def_id = match resolve_trait(db, &module, target_trait)? {
+                    Some(it) => it,
+                    None => continue,
+                };
+                self.impls_by_trait
+                    .entry(trait_def_id)
+                    .or_default()
+                    .push((target_ty, module_id, impl_id));
+            } else if let Some(fingerprint) = TyFingerprint::for_impl(&target_ty) {
+                self.impls
+                    .entry(fingerprint)
+                    .or_default()
+                    .push((module_id, impl_id));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether an impl for `target_ty` is an impl for `ty`. A projection as the
+/// target type is normalized first, with the expansions which are in
+/// progress. The generic arguments of structs and enums aren't compared yet.
+fn is_impl_for(
+    db: &impl HirDatabase,
+    target_ty: &Ty,
+    ty: &Ty,
+    expanding: &mut Expansions,
+) -> Cancelable<bool> {
+    let target_ty = target_ty.clone().normalize_expanding(db, expanding)?;
+    Ok(match TyFingerprint::for_impl(&target_ty) {
+        Some(fingerprint) => TyFingerprint::for_impl(ty) == Some(fingerprint),
+        None => target_ty != Ty::Unknown && target_ty == *ty,
+    })
+}
+
+fn resolve_trait(
+    db: &impl HirDatabase,
+    module: &Module,
+    type_ref: &TypeRef,
+) -> Cancelable<Option<DefId>> {
+    let path = match type_ref {
+        TypeRef::Path(path) => path,
+        _ => return Ok(None),
+    };
+    let def_id = ctry!(module.resolve_path(db, path)?.take_types());
+    match def_id.resolve(db)? {
+        Def::Trait(_) => Ok(Some(def_id)),
+        _ => Ok(None),
+    }
+}
+
+pub(crate) fn impls_in_crate(
+    db: &impl HirDatabase,
+    krate: Crate,
+) -> Cancelable<Arc<CrateImplBlocks>> {
+    let crate_graph = db.crate_graph();
+    let file_id = crate_graph.crate_root(krate.crate_id);
+    let source_root_id = db.file_source_root(file_id);
+    let mut crate_impl_blocks = CrateImplBlocks {
+        source_root_id,
+        impls: FxHashMap::default(),
+        impls_by_trait: FxHashMap::default(),
+    };
+    let root_module = match krate.root_module(db)? {
+        Some(it) => it,
+        None => return Ok(Arc::new(crate_impl_blocks)),
+    };
+    let root_module_id = root_module.def_id.loc(db).module_id;
+    // The module tree of a source root may contain several crates.
+    let module_tree = db.module_tree(source_root_id)?;
+    for module_id in module_tree.modules() {
+        if module_id.crate_root(&module_tree) == root_module_id {
+            crate_impl_blocks.collect(db, module_id)?;
+        }
+    }
+    Ok(Arc::new(crate_impl_blocks))
+}
+
+fn def_crate(db: &impl HirDatabase, ty: &Ty) -> Cancelable<Option<Crate>> {
+    match ty {
+        Ty::Adt { def_id, .. } => def_id.krate(db),
+        _ => Ok(None),
+    }
+}
+
+/// The crates which may contain the impls of the trait for `ty`: the impl is
+/// either in the crate of the trait or in the one of the type.
+pub(super) fn trait_impl_crates(
+    db: &impl HirDatabase,
+    trait_def_id: DefId,
+    ty: &Ty,
+) -> Cancelable<Vec<Crate>> {
+    let mut crates = Vec::new();
+    crates.extend(trait_def_id.krate(db)?);
+    if let Some(krate) = def_crate(db, ty)? {
+        if !crates.contains(&krate) {
+            crates.push(krate);
+        }
+    }
+    Ok(crates)
+}
+
+/// The traits whose methods can be called in `module`: the ones which are
+/// defined or imported there.
+fn traits_in_scope(db: &impl HirDatabase, module: &Module) -> Cancelable<Vec<Trait>> {
+    let mut res = Vec::new();
+    for (_, resolution) in module.scope(db)?.entries() {
+        if let Some(def_id) = resolution.def_id.types {
+            if let Def::Trait(t) = def_id.resolve(db)? {
+                res.push(t);
+            }
+        }
+    }
+    Ok(res)
+}
+
+fn find_method<'a>(
+    db: &impl HirDatabase,
+    mut functions: impl Iterator<Item = &'a Function>,
+    name: &Name,
+) -> Option<Function> {
+    functions
+        .find(|f| {
+            let sig = f.signature(db);
+            sig.name() == name && sig.has_self_param()
+        })
+        .cloned()
+}
+
+fn lookup_inherent_method(
+    db: &impl HirDatabase,
+    ty: &Ty,
+    name: &Name,
+) -> Cancelable<Option<Function>> {
+    let krate = ctry!(def_crate(db, ty)?);
+    let impls = db.impls_in_crate(krate)?;
+    for impl_block in impls.lookup_impl_blocks(db, ty) {
+        let impl_block = impl_block?;
+        if let Some(f) = find_method(db, impl_methods(&impl_block), name) {
+            return Ok(Some(f));
+        }
+    }
+    Ok(None)
+}
+
+fn lookup_trait_method(
+    db: &impl HirDatabase,
+    ty: &Ty,
+    name: &Name,
+    traits: &[Trait],
+) -> Cancelable<Option<Function>> {
+    for tr in traits {
+        for krate in trait_impl_crates(db, tr.def_id(), ty)? {
+            let impls = db.impls_in_crate(krate)?;
+            let mut expanding = Expansions::default();
+            for impl_block in impls.lookup_trait_impl_blocks(db, tr.def_id(), ty, &mut expanding)? {
+                if let Some(f) = find_method(db, impl_methods(&impl_block), name) {
+                    return Ok(Some(f));
+                }
+                // The method may have a default implementation in the trait.
+                if let Some(f) = find_method(db, tr.functions(db)?.iter(), name) {
+                    return Ok(Some(f));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Looks up the method in the traits an opaque `impl Trait` type is known to
+/// implement. These don't need to be in scope.
+fn lookup_bound_method(
+    db: &impl HirDatabase,
+    ty: &Ty,
+    name: &Name,
+) -> Cancelable<Option<Function>> {
+    let bounds = match ty {
+        Ty::Opaque(bounds) => bounds,
+        _ => return Ok(None),
+    };
+    for bound in bounds.iter() {
+        let tr = Trait::new(bound.trait_def_id);
+        if let Some(f) = find_method(db, tr.functions(db)?.iter(), name) {
+            return Ok(Some(f));
+        }
+    }
+    Ok(None)
+}
+
+fn lookup_op_method(
+    db: &impl HirDatabase,
+    ty: &Ty,
+    trait_name: &Name,
+    name: &Name,
+) -> Cancelable<Option<Function>> {
+    let krate = ctry!(def_crate(db, ty)?);
+    let impls = db.impls_in_crate(krate)?;
+    for (trait_def_id, impl_block) in impls.lookup_trait_impls_for_type(db, ty)? {
+        let tr = Trait::new(trait_def_id);
+        if tr.name(db)?.as_ref() != Some(trait_name) {
+            continue;
+        }
+        if let Some(f) = find_method(db, impl_methods(&impl_block), name) {
+            return Ok(Some(f));
+        }
+        if let Some(f) = find_method(db, tr.functions(db)?.iter(), name) {
+            return Ok(Some(f));
+        }
+    }
+    Ok(None)
+}
+
+fn impl_methods<'a>(impl_block: &'a ImplBlock) -> impl Iterator<Item = &'a Function> + 'a {
+    impl_block.items().iter().filter_map(|item| match item {
+        ImplItem::Method(f) => Some(f),
+        _ => None,
+    })
+}
+
+impl Ty {
+    /// Looks up the method `name` of the type and of the types it autoderefs
+    /// to, in that order. For each of them, inherent methods take precedence
+    /// over the methods of the traits of an `impl Trait` type, which take
+    /// precedence over the methods of the traits in scope in `module`.
+    pub fn lookup_method(
+        self,
+        db: &impl HirDatabase,
+        name: &Name,
+        module: &Module,
+    ) -> Cancelable<Option<Function>> {
+        let traits = traits_in_scope(db, module)?;
+        for derefed_ty in self.autoderef(db) {
+            if let Some(f) = lookup_inherent_method(db, &derefed_ty, name)? {
+                return Ok(Some(f));
+            }
+            if let Some(f) = lookup_bound_method(db, &derefed_ty, name)? {
+                return Ok(Some(f));
+            }
+            if let Some(f) = lookup_trait_method(db, &derefed_ty, name, &traits)? {
+                return Ok(Some(f));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The bound of an opaque `impl Trait` type, among the type and the types
+    /// it autoderefs to, whose trait has the method `func`, together with that
+    /// opaque type.
+    pub(super) fn bound_of_method(
+        self,
+        db: &impl HirDatabase,
+        func: &Function,
+    ) -> Cancelable<Option<(TraitBound, Ty)>> {
+        for derefed_ty in self.autoderef(db) {
+            let bounds = match &derefed_ty {
+                Ty::Opaque(bounds) => Arc::clone(bounds),
+                _ => continue,
+            };
+            for bound in bounds.iter() {
+                let tr = Trait::new(bound.trait_def_id);
+                if tr.functions(db)?.contains(func) {
+                    return Ok(Some((bound.clone(), derefed_ty)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Looks up the method `name` of an operator trait, like `index` of
+    /// `Index`, for the type and the types it autoderefs to. Unlike other
+    /// traits, operator traits don't need to be in scope.
+    // TODO: identify the traits by their lang items instead of by their names;
+    // for now, only the impls in the crate of the type are found.
+    pub fn lookup_op_method(
+        self,
+        db: &impl HirDatabase,
+        trait_name: &Name,
+        name: &Name,
+    ) -> Cancelable<Option<Function>> {
+        for derefed_ty in self.autoderef(db) {
+            if let Some(f) = lookup_op_method(db, &derefed_ty, trait_name, name)? {
+                return Ok(Some(f));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -2,6 +2,37 @@ use std::fmt;
 
 use crate::{Name, KnownName};
 
+/// The type of an integer literal, known only if the literal has a suffix.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum UncertainIntTy {
+    Unknown,
+    Signed(IntTy),
+    Unsigned(UintTy),
+}
+
+impl UncertainIntTy {
+    pub fn from_name(name: &Name) -> Option<UncertainIntTy> {
+        if let Some(ty) = IntTy::from_name(name) {
+            Some(UncertainIntTy::Signed(ty))
+        } else {
+            UintTy::from_name(name).map(UncertainIntTy::Unsigned)
+        }
+    }
+}
+
+/// The type of a float literal, known only if the literal has a suffix.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum UncertainFloatTy {
+    Unknown,
+    Known(FloatTy),
+}
+
+impl UncertainFloatTy {
+    pub fn from_name(name: &Name) -> Option<UncertainFloatTy> {
+        FloatTy::from_name(name).map(UncertainFloatTy::Known)
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Copy)]
 pub enum IntTy {
     Isize,
@@ -0,0 +1,121 @@
+//! Normalization of associated type projections: `<S as Trait>::Name` is
+//! replaced by the type which the impl of `Trait` for `S` gives to `Name`.
+//! This is a much simplified version of the projection logic in rustc (which
+//! lives in librustc/traits/project.rs).
+
+use ra_db::Cancelable;
+use ra_syntax::ast::{self, AstNode, NameOwner};
+
+use crate::{
+    HirDatabase, DefId, Name, AsName,
+    impl_block::{ImplBlock, ImplItem},
+    type_ref::TypeRef,
+};
+use super::{Ty, Expansions, method_resolution::trait_impl_crates};
+
+impl Ty {
+    /// Normalizes the type if it is a projection whose trait has an impl for
+    /// the self type. Other types, and projections we can't resolve, are
+    /// returned unchanged.
+    pub fn normalize(self, db: &impl HirDatabase) -> Cancelable<Ty> {
+        self.normalize_expanding(db, &mut Expansions::default())
+    }
+
+    /// Normalizes the type, with the expansions which are in progress. A
+    /// projection whose normalization needs itself is unknown.
+    pub(super) fn normalize_expanding(
+        self,
+        db: &impl HirDatabase,
+        expanding: &mut Expansions,
+    ) -> Cancelable<Ty> {
+        let (trait_def_id, self_ty, name) = match &self {
+            Ty::Projection {
+                trait_def_id,
+                self_ty,
+                name,
+                ..
+            } => (*trait_def_id, self_ty, name),
+            _ => return Ok(self),
+        };
+        if expanding.keep_projections {
+            return Ok(self);
+        }
+        if expanding.projections.contains(&self) {
+            return Ok(Ty::Unknown);
+        }
+        if let Some(ty) = bound_associated_type(trait_def_id, self_ty, name) {
+            return Ok(ty);
+        }
+        expanding.projections.push(self.clone());
+        let ty = find_associated_type(db, trait_def_id, self_ty, name, expanding);
+        expanding.projections.pop();
+        Ok(ty?.unwrap_or(self))
+    }
+}
+
+/// The type which a bound of the opaque `impl Trait` type `self_ty` gives to
+/// the associated type `name` of the trait, like `u32` to `Item` in
+/// `impl Iterator<Item = u32>`.
+fn bound_associated_type(trait_def_id: DefId, self_ty: &Ty, name: &Name) -> Option<Ty> {
+    let bounds = match self_ty {
+        Ty::Opaque(bounds) => bounds,
+        _ => return None,
+    };
+    bounds
+        .iter()
+        .filter(|bound| bound.trait_def_id == trait_def_id)
+        .flat_map(|bound| bound.bindings.iter())
+        .find(|(binding_name, _)| binding_name == name)
+        .map(|(_, ty)| ty.clone())
+}
+
+/// The type which the impl of the trait for `self_ty` gives to the associated
+/// type `name`.
+fn find_associated_type(
+    db: &impl HirDatabase,
+    trait_def_id: DefId,
+    self_ty: &Ty,
+    name: &Name,
+    expanding: &mut Expansions,
+) -> Cancelable<Option<Ty>> {
+    for krate in trait_impl_crates(db, trait_def_id, self_ty)? {
+        let impls = db.impls_in_crate(krate)?;
+        for impl_block in impls.lookup_trait_impl_blocks(db, trait_def_id, self_ty, expanding)? {
+            if let Some(ty) = associated_type(db, &impl_block, name, expanding)? {
+                return Ok(Some(ty));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The type of the associated type `name` in the impl, if it defines it.
+fn associated_type(
+    db: &impl HirDatabase,
+    impl_block: &ImplBlock,
+    name: &Name,
+    expanding: &mut Expansions,
+) -> Cancelable<Option<Ty>> {
+    for item in impl_block.items() {
+        let def_id = match item {
+            ImplItem::Type(def_id) => *def_id,
+            _ => continue,
+        };
+        if let Some(type_ref) = type_def_type_ref(db, def_id, name) {
+            let module = def_id.module(db)?;
+            let ty = Ty::from_hir_expanding(db, &module, Some(impl_block), &type_ref, expanding)?;
+            return Ok(Some(ty));
+        }
+    }
+    Ok(None)
+}
+
+/// The type of the type item, if it has the given name.
+fn type_def_type_ref(db: &impl HirDatabase, def_id: DefId, name: &Name) -> Option<TypeRef> {
+    let syntax = db.file_item(def_id.loc(db).source_item_id);
+    let type_def = ast::TypeDef::cast(syntax.borrowed())?;
+    if type_def.name()?.as_name() != *name {
+        return None;
+    }
+    Some(TypeRef::from_ast_opt(type_def.type_ref()))
+}
@@ -1,11 +1,10 @@
-use std::sync::Arc;
 use std::fmt::Write;
 use std::path::{PathBuf, Path};
 use std::fs;
 
 use salsa::Database;
 
-use ra_db::SyntaxDatabase;
+use ra_db::{Rope, SyntaxDatabase};
 use ra_syntax::ast::{self, AstNode};
 use test_utils::{project_dir, assert_eq_text, read_text};
 
@@ -180,6 +179,577 @@ fn test() {
     );
 }
 
+#[test]
+fn infer_literals() {
+    check_inference(
+        r##"
+fn test() {
+    5i32;
+    0x1fu8;
+    1_000_usize;
+    2.5f64;
+    1f32;
+    1e10;
+    92;
+    'a';
+    b'a';
+    "hello";
+    r#"raw"#;
+    b"bytes";
+    true;
+}"##,
+        "literals.txt",
+    );
+}
+
+#[test]
+fn infer_array() {
+    check_inference(
+        r#"
+fn test(x: u8) {
+    [x, 1, 2];
+    [1i32; 3];
+    [];
+}"#,
+        "array.txt",
+    );
+}
+
+#[test]
+fn infer_index() {
+    check_inference(
+        r#"
+fn test(a: &[u32], i: usize) {
+    a[i];
+    a[0][1u8];
+}"#,
+        "index.txt",
+    );
+}
+
+#[test]
+fn infer_match_guard() {
+    check_inference(
+        r#"
+fn test(a: u32) {
+    match a {
+        x if y => {}
+        _ if a == 0 => {}
+    }
+}"#,
+        "match_guard.txt",
+    );
+}
+
+#[test]
+fn infer_range() {
+    check_inference(
+        r#"
+fn test(a: u32) {
+    a..5;
+    1u8..=2;
+    ..a;
+    a..;
+    ..;
+}"#,
+        "range.txt",
+    );
+}
+
+#[test]
+fn infer_assignment_ops() {
+    check_inference(
+        r#"
+fn test(a: u32, b: bool) {
+    let x;
+    x = a;
+    let y = a += 1;
+    let z = b != true;
+}"#,
+        "assignment_ops.txt",
+    );
+}
+
+#[test]
+fn infer_closure_ret_type() {
+    check_inference(
+        r#"
+fn test() {
+    let f = move |x: u32| -> u64 { 1 };
+    let g = || 1;
+}"#,
+        "closure_ret_type.txt",
+    );
+}
+
+#[test]
+fn infer_closure_args() {
+    check_inference(
+        r#"
+fn apply(f: fn(u32) -> i64) {}
+
+fn test() {
+    apply(|x| { x; 1 });
+    let c = |a: u8| a;
+    c(1);
+}"#,
+        "closure_args.txt",
+    );
+}
+
+#[test]
+fn infer_coercions() {
+    check_inference(
+        r#"
+fn takes_ref(x: &u32) {}
+fn takes_ptr(p: *const u32) {}
+fn takes_slice(s: &[u8]) {}
+
+fn test(a: &mut u32, b: &&u32, arr: &[u8; 3]) -> &[u8] {
+    let r: &u32 = a;
+    takes_ref(a);
+    takes_ref(b);
+    takes_ptr(r);
+    let p: *const u32 = a;
+    takes_slice(arr);
+    let s: &[u8] = &[1, 2];
+    if true {
+        return arr;
+    }
+    { arr }
+}"#,
+        "coercions.txt",
+    );
+}
+
+#[test]
+fn infer_never() {
+    check_inference(
+        r#"
+fn exit() -> ! {}
+
+fn test(a: u32, b: bool) -> u32 {
+    let x = match a {
+        0 => 1u64,
+        1 => panic!(),
+        _ => return 2,
+    };
+    let y = if b { exit() } else { x };
+    let z = if b { return a; } else { unreachable!() };
+    loop {}
+}"#,
+        "never.txt",
+    );
+}
+
+#[test]
+fn infer_tuples_and_arrays() {
+    check_inference(
+        r#"
+fn test(a: [u8; 2], s: &[(u32, &str)], i: usize) {
+    let t = (1, "two", (3.0,));
+    t.2;
+    t.2 .0;
+    let u: (u64, bool) = (1, true);
+    ();
+    a[i];
+    s[0].1;
+    let b: [i16; 3] = [1, 2, 3];
+    b[1];
+}"#,
+        "tuples_and_arrays.txt",
+    );
+}
+
+#[test]
+fn infer_patterns() {
+    check_inference(
+        r#"
+struct S { a: u32, b: bool }
+struct T(u64, S);
+
+fn test(pair: (u8, &str), t: &T, (x, y): (i8, i16), arr: [u16; 4]) {
+    let (a, b) = pair;
+    let (c, ..) = pair;
+    let T(d, S { a: e, b }) = t;
+    let &T(ref f, _) = t;
+    let [g, rest @ .., h] = arr;
+    match t {
+        T(0, _) => {}
+        T(n @ 1...9, s) => {}
+        _ => {}
+    }
+    let add = |(l, r): (u32, u32)| l + r;
+}"#,
+        "patterns.txt",
+    );
+}
+
+#[test]
+fn infer_expectations() {
+    check_inference(
+        r#"
+struct S { a: u64, b: &[u8], c: &u16 }
+
+fn test(b: bool) {
+    let x: i8 = -1;
+    let y: u32 = !0;
+    let z: &(u64, f32) = &(1, 2.0);
+    let s: &[u16] = &[1, 2];
+    let w = if b { 1 } else { 2u16 };
+    S { a: 1, b: &[3], c: &4 };
+}"#,
+        "expectations.txt",
+    );
+}
+
+#[test]
+fn infer_index_trait() {
+    check_inference(
+        r#"
+trait Index<Idx> {
+    type Output;
+    fn index(&self, index: Idx) -> &Self::Output;
+}
+struct A { x: u32 }
+struct Grid;
+impl Index<u16> for Grid {
+    type Output = A;
+    fn index(&self, i: u16) -> &A {}
+}
+impl A {
+    fn foo(&self) -> i64 {}
+}
+
+fn test(g: Grid, r: &Grid) {
+    g[1];
+    g[2].x;
+    r[3].foo();
+}"#,
+        "index_trait.txt",
+    );
+}
+
+#[test]
+fn infer_for_loops() {
+    check_inference(
+        r#"
+struct Vec<T>;
+fn test(n: u64, a: [u8; 3], s: &mut [(i8, bool)], v: Vec<char>, w: &mut Vec<u16>) {
+    for i in 0..n {}
+    for j in (1..) {}
+    for x in a {}
+    for y in &a {}
+    for (z, b) in s {}
+    for c in v {}
+    for d in &v {}
+    for e in w {}
+}"#,
+        "for_loops.txt",
+    );
+}
+
+#[test]
+fn infer_try() {
+    check_inference(
+        r#"
+enum Result<T, E> { Ok(T), Err(E) }
+enum Option<T> { Some(T), None }
+struct Error;
+
+fn test(r: Result<u32, Error>, o: Option<&str>) -> Result<(), Error> {
+    let a = r?;
+    let b = o?;
+    let c = r? + 1;
+}"#,
+        "try.txt",
+    );
+}
+
+#[test]
+fn infer_associated_type_projections() {
+    check_inference(
+        r#"
+trait Index<Idx> {
+    type Output;
+    fn index(&self, index: Idx) -> &Self::Output;
+}
+trait Iterator {
+    type Item;
+    fn next(&mut self) -> Option<Self::Item>;
+}
+enum Option<T> { Some(T), None }
+struct Cell { value: u32 }
+struct Grid;
+struct Cells;
+struct Unknown;
+impl Index<u16> for Grid {
+    type Output = Cell;
+    fn index(&self, i: u16) -> &Self::Output {}
+}
+impl Iterator for Cells {
+    type Item = Cell;
+    fn next(&mut self) -> Option<Self::Item> {}
+}
+
+fn test(
+    grid: Grid,
+    cells: &mut Cells,
+    cell: <Grid as Index<u16>>::Output,
+    unknown: <Unknown as Iterator>::Item,
+) {
+    let a = grid.index(1);
+    let b = a.value;
+    let c = cell.value;
+    let d = cells.next();
+    unknown;
+}
+"#,
+        "associated_types.txt",
+    );
+}
+
+#[test]
+fn infer_impl_trait() {
+    check_inference(
+        r#"
+enum Option<T> { Some(T), None }
+mod iter {
+    pub trait Iterator {
+        type Item;
+        fn count(self) -> usize;
+        fn next(&mut self) -> super::Option<Self::Item>;
+    }
+}
+trait Len {
+    fn len(&self) -> u32;
+}
+struct Range { start: u32, end: u32 }
+impl iter::Iterator for Range {
+    type Item = u32;
+    fn count(self) -> usize {}
+    fn next(&mut self) -> Option<u32> {}
+}
+
+fn numbers() -> impl iter::Iterator<Item = u32> + Len {
+    Range { start: 0, end: 10 }
+}
+
+fn test() {
+    let a = numbers();
+    let b = a.count();
+    let c = numbers().len();
+    let d = numbers().next();
+}
+"#,
+        "impl_trait.txt",
+    );
+}
+
+#[test]
+fn infer_type_aliases() {
+    check_inference(
+        r#"
+struct Vec<T> { len: usize }
+struct Point { x: u32 }
+mod geometry {
+    pub type Coord = u32;
+}
+type Points = Vec<Point>;
+type Origin = &'static Point;
+type Position = Origin;
+type X = geometry::Coord;
+type Cycle = (u32, Loop);
+type Loop = Cycle;
+trait Tr { type A; }
+struct S;
+impl Tr for S { type A = Through; }
+type Through = <S as Tr>::A;
+
+fn test(points: Points, origin: Position, x: X, cycle: Cycle, through: Through) {
+    let a = points.len;
+    let b = origin.x;
+    let c: X = origin.x;
+    let d = x;
+    cycle;
+    through;
+}
+"#,
+        "type_aliases.txt",
+    );
+}
+
+#[test]
+fn infer_cyclic_associated_types() {
+    check_inference(
+        r#"
+trait Tr { type A; }
+struct S;
+struct T;
+impl Tr for S { type A = <S as Tr>::A; }
+impl Tr for T { type A = Self::A; }
+
+fn test(x: <S as Tr>::A, y: <T as Tr>::A) {
+    x;
+    y;
+}
+"#,
+        "cyclic_associated_types.txt",
+    );
+}
+
+#[test]
+fn infer_impl_for_projection() {
+    check_inference(
+        r#"
+trait Tr { type A; }
+trait Foo { fn foo(&self) -> u32; }
+struct S;
+struct Inner;
+impl Tr for S { type A = Inner; }
+impl Foo for <S as Tr>::A { fn foo(&self) -> u32 {} }
+
+fn test(x: <S as Tr>::A) {
+    x.foo();
+}
+"#,
+        "impl_for_projection.txt",
+    );
+}
+
+#[test]
+fn infer_impl_for_cyclic_projection() {
+    check_inference(
+        r#"
+trait Tr { type A; }
+struct S;
+impl Tr for <S as Tr>::A { type A = S; }
+
+fn test(x: <S as Tr>::A) {
+    x;
+}
+"#,
+        "impl_for_cyclic_projection.txt",
+    );
+}
+
+#[test]
+fn infer_vec_index() {
+    check_inference(
+        r#"
+struct Vec<T>;
+fn test(v: Vec<u32>, r: &Vec<&str>, i: usize) {
+    v[i];
+    r[0];
+    v[0] + 1;
+}"#,
+        "vec_index.txt",
+    );
+}
+
+#[test]
+fn infer_literal_vars() {
+    check_inference(
+        r#"
+fn test(a: u32, b: f32) {
+    let x = 1;
+    let y = x + 2u8;
+    a + 1;
+    b * 2.0;
+    let z = 1.5;
+    x << 1u64;
+    let f: u64 = 1;
+}"#,
+        "literal_vars.txt",
+    );
+}
+
+#[test]
+fn infer_binary_ops() {
+    check_inference(
+        r#"
+struct S;
+fn test(a: u32, b: i64, c: f32, d: bool, s: S) {
+    let x = a + 1 * 2;
+    let y = x % a - 3;
+    b / 2 << a;
+    x >> 1u8;
+    c * 1.5 + c;
+    d & true | false ^ d;
+    c & 1;
+    s + 1;
+    let z = 1;
+    z <<= 2u16;
+}"#,
+        "binary_ops.txt",
+    );
+}
+
+#[test]
+fn infer_method_calls() {
+    check_inference(
+        r#"
+struct A { b: B }
+struct B;
+impl A {
+    fn foo(&self, x: u32) -> i64 {
+        self.b.bar();
+        self.b.baz()
+    }
+    fn new() -> A {}
+}
+impl B {
+    fn bar(&self) -> u8 {}
+    fn baz(self) -> i64 {}
+}
+struct Box<T>;
+fn test(a: A, r: &&A, b: Box<A>, bb: &Box<Box<B>>) {
+    a.foo(1);
+    (&a).foo(2);
+    r.foo(3).bar();
+    r.b.bar();
+    a.new();
+    b.foo(4);
+    b.b.bar();
+    bb.bar();
+}"#,
+        "method_calls.txt",
+    );
+}
+
+#[test]
+fn infer_trait_method_calls() {
+    check_inference(
+        r#"
+mod m {
+    pub trait Tr {
+        fn f(&self) -> u32;
+        fn g(&self) -> i64 { 1 }
+    }
+}
+struct S;
+impl m::Tr for S {
+    fn f(&self) -> u32 {}
+}
+impl m::Tr for u8 {
+    fn f(&self) -> u32 {}
+}
+fn not_in_scope(s: S) {
+    s.f();
+}
+mod n {
+    use super::m::Tr;
+    use super::S;
+    fn in_scope(s: &S, x: u8) {
+        s.f();
+        s.g();
+        x.f();
+    }
+}"#,
+        "trait_method_calls.txt",
+    );
+}
+
 fn infer(content: &str) -> String {
     let (db, _, file_id) = MockDatabase::with_single_file(content);
     let source_file = db.source_file(file_id);
@@ -293,7 +863,7 @@ fn typing_whitespace_inside_a_function_should_not_invalidate_types() {
     .to_string();
 
     db.query_mut(ra_db::FileTextQuery)
-        .set(pos.file_id, Arc::new(new_text));
+        .set(pos.file_id, Rope::from(new_text));
 
     {
         let events = db.log_executed(|| {
@@ -302,3 +872,37 @@ fn typing_whitespace_inside_a_function_should_not_invalidate_types() {
         assert!(!format!("{:?}", events).contains("infer"), "{:#?}", events)
     }
 }
+
+#[test]
+fn try_error_types_are_recorded() {
+    let (db, pos) = MockDatabase::with_position(
+        "
+        //- /lib.rs
+        enum Result<T, E> { Ok(T), Err(E) }
+        struct Error;
+        fn foo(r: Result<u32, Error>) -> Result<u32, Error> {
+            <|>r?;
+        }
+    ",
+    );
+    let func = source_binder::function_from_position(&db, pos)
+        .unwrap()
+        .unwrap();
+    let source_file = db.source_file(pos.file_id);
+    let try_expr = source_file
+        .syntax()
+        .descendants()
+        .find_map(ast::TryExpr::cast)
+        .unwrap();
+    let syntax_mapping = func.body_syntax_mapping(&db).unwrap();
+    let expr = syntax_mapping
+        .node_expr(ast::Expr::TryExpr(try_expr))
+        .unwrap();
+    let inference_result = func.infer(&db).unwrap();
+    assert_eq!(
+        inference_result
+            .try_error_type(expr)
+            .map(|ty| ty.to_string()),
+        Some("Error".to_string())
+    );
+}
@@ -0,0 +1,40 @@
+use ra_db::Cancelable;
+use ra_syntax::ast::{self, AstNode};
+
+use crate::{
+    DefId, DefKind, HirDatabase, Module,
+    type_ref::TypeRef,
+};
+
+/// A type alias, defined with `type Foo = Bar;`. The associated types in impls
+/// and traits are not type aliases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeAlias {
+    def_id: DefId,
+}
+
+impl TypeAlias {
+    pub(crate) fn new(def_id: DefId) -> TypeAlias {
+        TypeAlias { def_id }
+    }
+
+    pub fn def_id(&self) -> DefId {
+        self.def_id
+    }
+
+    pub fn syntax(&self, db: &impl HirDatabase) -> ast::TypeDefNode {
+        let def_loc = self.def_id.loc(db);
+        assert!(def_loc.kind == DefKind::TypeAlias);
+        let syntax = db.file_item(def_loc.source_item_id);
+        ast::TypeDef::cast(syntax.borrowed()).unwrap().owned()
+    }
+
+    /// The aliased type.
+    pub fn type_ref(&self, db: &impl HirDatabase) -> TypeRef {
+        TypeRef::from_ast_opt(self.syntax(db).borrowed().type_ref())
+    }
+
+    pub fn module(&self, db: &impl HirDatabase) -> Cancelable<Module> {
+        self.def_id.module(db)
+    }
+}
@@ -3,7 +3,7 @@
 
 use ra_syntax::ast;
 
-use crate::Path;
+use crate::{Path, Name, AsName};
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Mutability {
@@ -27,6 +27,12 @@ impl Mutability {
         }
     }
 
+    /// Whether a pointer with this mutability can be coerced to one with the
+    /// `target` mutability: `&mut T` coerces to `&T`, but not the reverse.
+    pub fn coerces_to(self, target: Mutability) -> bool {
+        self == target || self == Mutability::Mut
+    }
+
     pub fn as_keyword_for_ptr(self) -> &'static str {
         match self {
             Mutability::Shared => "const ",
@@ -48,8 +54,16 @@ pub enum TypeRef {
     Slice(Box<TypeRef>),
     /// A fn pointer. Last element of the vector is the return type.
     Fn(Vec<TypeRef>),
+    /// An associated type of a trait, like `<T as Trait>::Name`. The trait is
+    /// missing in `<T>::Name`.
+    Projection {
+        self_type: Box<TypeRef>,
+        trait_path: Option<Path>,
+        name: Name,
+    },
+    /// An `impl Trait` type, with the paths of its traits.
+    ImplTrait(Vec<Path>),
     // For
-    // ImplTrait,
     // DynTrait,
     Error,
 }
@@ -62,11 +76,12 @@ impl TypeRef {
             ParenType(inner) => TypeRef::from_ast_opt(inner.type_ref()),
             TupleType(inner) => TypeRef::Tuple(inner.fields().map(TypeRef::from_ast).collect()),
             NeverType(..) => TypeRef::Never,
-            PathType(inner) => inner
-                .path()
-                .and_then(Path::from_ast)
-                .map(TypeRef::Path)
-                .unwrap_or(TypeRef::Error),
+            PathType(inner) => match inner.path() {
+                Some(path) => TypeRef::projection_from_ast(path)
+                    .or_else(|| Path::from_ast(path).map(TypeRef::Path))
+                    .unwrap_or(TypeRef::Error),
+                None => TypeRef::Error,
+            },
             PointerType(inner) => {
                 let inner_ty = TypeRef::from_ast_opt(inner.type_ref());
                 let mutability = Mutability::from_mutable(inner.is_mut());
@@ -95,11 +110,28 @@ impl TypeRef {
             }
             // for types are close enough for our purposes to the inner type for now...
             ForType(inner) => TypeRef::from_ast_opt(inner.type_ref()),
-            ImplTraitType(_inner) => TypeRef::Error,
+            ImplTraitType(inner) => {
+                TypeRef::ImplTrait(inner.bounds().filter_map(Path::from_ast).collect())
+            }
             DynTraitType(_inner) => TypeRef::Error,
         }
     }
 
+    /// Converts a path like `<T as Trait>::Name`.
+    fn projection_from_ast(path: ast::Path) -> Option<Self> {
+        let qualifier = path.qualifier()?;
+        if qualifier.qualifier().is_some() {
+            return None;
+        }
+        let (self_type, trait_type) = qualifier.segment()?.qualified_type()?;
+        let name = path.segment()?.name_ref()?.as_name();
+        Some(TypeRef::Projection {
+            self_type: Box::new(TypeRef::from_ast(self_type)),
+            trait_path: trait_type.and_then(|it| it.path()).and_then(Path::from_ast),
+            name,
+        })
+    }
+
     pub(crate) fn from_ast_opt(node: Option<ast::TypeRef>) -> Self {
         if let Some(node) = node {
             TypeRef::from_ast(node)
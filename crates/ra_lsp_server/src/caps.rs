@@ -1,51 +1,150 @@
 use languageserver_types::{
-    CodeActionProviderCapability, CompletionOptions, DocumentOnTypeFormattingOptions,
-    ExecuteCommandOptions, FoldingRangeProviderCapability, RenameOptions, RenameProviderCapability,
+    code_action_kind, CodeActionOptions, CodeActionProviderCapability, CompletionOptions, DocumentOnTypeFormattingOptions,
+    ExecuteCommandOptions, FoldingRangeProviderCapability, RenameOptions, RenameProviderCapability, SaveOptions,
     ServerCapabilities, SignatureHelpOptions, TextDocumentSyncCapability, TextDocumentSyncKind,
     TextDocumentSyncOptions,
 };
 
-pub fn server_capabilities() -> ServerCapabilities {
+use ra_editor::OffsetEncoding;
+use serde_json::{json, Value};
+
+use crate::{req, FeaturesConfig, ServerConfig};
+
+/// The result of the `initialize` request, and the negotiated encoding of
+/// position columns. Clients may announce the encodings they support, in the
+/// order of their preference, either with `general.positionEncodings` or with
+/// the older `offsetEncoding` extension. Otherwise, UTF-16 is used. The
+/// chosen encoding is reported in the fields of both ways the client used.
+///
+/// The features disabled in the `initializationOptions` are not advertised.
+pub fn initialize_result(params: &Value) -> (Value, OffsetEncoding) {
+    let caps = &params["capabilities"];
+    let features = serde_json::from_value::<ServerConfig>(params["initializationOptions"].clone())
+        .map(|it| it.features)
+        .unwrap_or_default();
+    let position_encodings = caps["general"]["positionEncodings"].as_array();
+    let offset_encodings = caps["offsetEncoding"].as_array();
+    let encoding = position_encodings
+        .into_iter()
+        .chain(offset_encodings)
+        .flatten()
+        .filter_map(|it| it.as_str().and_then(encoding_from_str))
+        .next()
+        .unwrap_or(OffsetEncoding::Utf16);
+
+    let mut res = json!({ "capabilities": server_capabilities(&features) });
+    let name = encoding_name(encoding);
+    if position_encodings.is_some() {
+        res["capabilities"]["positionEncoding"] = json!(name);
+    }
+    if offset_encodings.is_some() {
+        res["offsetEncoding"] = json!(name);
+    }
+    (res, encoding)
+}
+
+fn encoding_from_str(name: &str) -> Option<OffsetEncoding> {
+    match name {
+        "utf-8" => Some(OffsetEncoding::Utf8),
+        "utf-16" => Some(OffsetEncoding::Utf16),
+        "utf-32" => Some(OffsetEncoding::Utf32),
+        _ => None,
+    }
+}
+
+fn encoding_name(encoding: OffsetEncoding) -> &'static str {
+    match encoding {
+        OffsetEncoding::Utf8 => "utf-8",
+        OffsetEncoding::Utf16 => "utf-16",
+        OffsetEncoding::Utf32 => "utf-32",
+    }
+}
+
+/// The capability, if its feature is enabled.
+fn enabled<T>(enabled: bool, capability: T) -> Option<T> {
+    if enabled {
+        Some(capability)
+    } else {
+        None
+    }
+}
+
+pub fn server_capabilities(features: &FeaturesConfig) -> ServerCapabilities {
     ServerCapabilities {
         text_document_sync: Some(TextDocumentSyncCapability::Options(
             TextDocumentSyncOptions {
                 open_close: Some(true),
-                change: Some(TextDocumentSyncKind::Full),
+                change: Some(TextDocumentSyncKind::Incremental),
                 will_save: None,
                 will_save_wait_until: None,
-                save: None,
+                save: Some(SaveOptions {
+                    include_text: Some(false),
+                }),
             },
         )),
-        hover_provider: Some(true),
-        completion_provider: Some(CompletionOptions {
-            resolve_provider: None,
-            trigger_characters: Some(vec![":".to_string(), ".".to_string()]),
-        }),
-        signature_help_provider: Some(SignatureHelpOptions {
-            trigger_characters: Some(vec!["(".to_string(), ",".to_string(), ")".to_string()]),
-        }),
+        hover_provider: enabled(features.hover, true),
+        completion_provider: enabled(
+            features.completion,
+            CompletionOptions {
+                resolve_provider: None,
+                trigger_characters: Some(vec![":".to_string(), ".".to_string()]),
+            },
+        ),
+        signature_help_provider: enabled(
+            features.signature_help,
+            SignatureHelpOptions {
+                trigger_characters: Some(vec!["(".to_string(), ",".to_string(), ")".to_string()]),
+            },
+        ),
         definition_provider: Some(true),
         type_definition_provider: None,
         implementation_provider: None,
         references_provider: Some(true),
-        document_highlight_provider: Some(true),
+        document_highlight_provider: enabled(features.document_highlight, true),
         document_symbol_provider: Some(true),
         workspace_symbol_provider: Some(true),
-        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        code_action_provider: enabled(
+            features.code_actions,
+            CodeActionProviderCapability::Options(CodeActionOptions {
+                code_action_kinds: Some(vec![
+                    code_action_kind::QUICKFIX.to_string(),
+                    code_action_kind::REFACTOR_EXTRACT.to_string(),
+                    code_action_kind::REFACTOR_REWRITE.to_string(),
+                    code_action_kind::SOURCE_ORGANIZE_IMPORTS.to_string(),
+                ]),
+            }),
+        ),
         code_lens_provider: None,
-        document_formatting_provider: Some(true),
-        document_range_formatting_provider: None,
-        document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
-            first_trigger_character: "=".to_string(),
-            more_trigger_character: Some(vec![".".to_string()]),
-        }),
-        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
-        rename_provider: Some(RenameProviderCapability::Options(RenameOptions {
-            prepare_provider: Some(true),
-        })),
+        document_formatting_provider: enabled(features.formatting, true),
+        document_range_formatting_provider: enabled(features.formatting, true),
+        document_on_type_formatting_provider: enabled(
+            features.on_type_formatting,
+            DocumentOnTypeFormattingOptions {
+                first_trigger_character: "=".to_string(),
+                more_trigger_character: Some(vec![
+                    ".".to_string(),
+                    "}".to_string(),
+                    ">".to_string(),
+                ]),
+            },
+        ),
+        folding_range_provider: enabled(
+            features.folding_ranges,
+            FoldingRangeProviderCapability::Simple(true),
+        ),
+        rename_provider: enabled(
+            features.rename,
+            RenameProviderCapability::Options(RenameOptions {
+                prepare_provider: Some(true),
+            }),
+        ),
         color_provider: None,
         execute_command_provider: Some(ExecuteCommandOptions {
-            commands: vec!["apply_code_action".to_string()],
+            commands: vec![
+                "apply_code_action".to_string(),
+                req::APPLY_SOURCE_CHANGE_COMMAND.to_string(),
+                req::RELOAD_WORKSPACE_COMMAND.to_string(),
+            ],
         }),
         workspace: None,
     }
@@ -0,0 +1,164 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use failure::bail;
+use languageserver_types::DiagnosticSeverity;
+use rustc_hash::FxHashMap;
+use serde_json::Value;
+use thread_worker::{Worker, WorkerHandle};
+
+use crate::{CargoFeatures, Result};
+
+/// A diagnostic reported by `cargo check`, at the primary span of the
+/// compiler message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckDiagnostic {
+    pub path: PathBuf,
+    /// Zero-based lines and columns of the span, the columns are counted in
+    /// chars.
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// Runs `cargo check` in the workspace roots it receives, and sends back the
+/// diagnostics of the whole workspace.
+pub fn check_watcher() -> (Worker<PathBuf, Result<Vec<CheckDiagnostic>>>, WorkerHandle) {
+    thread_worker::spawn::<PathBuf, Result<Vec<CheckDiagnostic>>, _>(
+        "cargo check",
+        1,
+        |input_receiver, output_sender| {
+            input_receiver
+                .into_iter()
+                .map(|root| cargo_check(root.as_path()))
+                .try_for_each(|it| output_sender.send(it))
+                .unwrap()
+        },
+    )
+}
+
+fn cargo_check(root: &Path) -> Result<Vec<CheckDiagnostic>> {
+    let output = Command::new("cargo")
+        .arg("check")
+        .arg("--all-targets")
+        .arg("--message-format=json")
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let diagnostics: Vec<CheckDiagnostic> = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|message| parse_compiler_message(root, &message))
+        .collect();
+    // A failed build without errors means that cargo itself has failed, for
+    // example because of a broken manifest.
+    if !output.status.success() && diagnostics.is_empty() {
+        bail!(
+            "cargo check failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(diagnostics)
+}
+
+fn parse_compiler_message(root: &Path, message: &Value) -> Option<CheckDiagnostic> {
+    if message["reason"] != "compiler-message" {
+        return None;
+    }
+    let message = &message["message"];
+    let severity = match message["level"].as_str()? {
+        "error" | "error: internal compiler error" => DiagnosticSeverity::Error,
+        "warning" => DiagnosticSeverity::Warning,
+        "note" => DiagnosticSeverity::Information,
+        _ => DiagnosticSeverity::Hint,
+    };
+    let span = message["spans"]
+        .as_array()?
+        .iter()
+        .find(|span| span["is_primary"] == true)?;
+    let position = |line: &str, col: &str| -> Option<(u32, u32)> {
+        let line = span[line].as_u64()?.checked_sub(1)?;
+        let col = span[col].as_u64()?.checked_sub(1)?;
+        Some((line as u32, col as u32))
+    };
+    Some(CheckDiagnostic {
+        path: root.join(span["file_name"].as_str()?),
+        start: position("line_start", "column_start")?,
+        end: position("line_end", "column_end")?,
+        severity,
+        message: message["message"].as_str()?.to_string(),
+    })
+}
+
+/// What the build script of a package has told cargo.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildScriptOutput {
+    pub out_dir: Option<PathBuf>,
+    /// Like the lines of `rustc --print cfg`: `foo` or `feature="bar"`.
+    pub cfgs: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+/// Runs `cargo check` in the workspace of `cargo_toml`, so that the build
+/// scripts are executed, and collects their outputs by package id. Outputs of
+/// the build scripts which have succeeded are used even if the build fails.
+pub fn build_script_outputs(
+    cargo_toml: &Path,
+    cargo_features: &CargoFeatures,
+) -> Result<FxHashMap<String, BuildScriptOutput>> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("check")
+        .arg("--all-targets")
+        .arg("--message-format=json")
+        .arg("--manifest-path")
+        .arg(cargo_toml);
+    if cargo_features.all_features {
+        cmd.arg("--all-features");
+    } else {
+        if cargo_features.no_default_features {
+            cmd.arg("--no-default-features");
+        }
+        if !cargo_features.features.is_empty() {
+            cmd.arg("--features").arg(cargo_features.features.join(" "));
+        }
+    }
+    let output = cmd.stdin(Stdio::null()).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let res = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|message| parse_build_script_message(&message))
+        .collect();
+    Ok(res)
+}
+
+fn parse_build_script_message(message: &Value) -> Option<(String, BuildScriptOutput)> {
+    if message["reason"] != "build-script-executed" {
+        return None;
+    }
+    let package_id = message["package_id"].as_str()?.to_string();
+    let out_dir = message["out_dir"].as_str().map(PathBuf::from);
+    let cfgs = message["cfgs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|it| it.as_str())
+        .map(|it| it.to_string())
+        .collect();
+    let env = message["env"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|pair| {
+            let key = pair.get(0)?.as_str()?;
+            let value = pair.get(1)?.as_str()?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect();
+    let output = BuildScriptOutput { out_dir, cfgs, env };
+    Some((package_id, output))
+}
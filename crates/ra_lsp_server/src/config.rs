@@ -0,0 +1,222 @@
+use languageserver_types::{ClientCapabilities, MarkupKind};
+use ra_editor::OffsetEncoding;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    req::{self, Request},
+    Result,
+};
+
+/// User settings of the server. The initial values come from the
+/// `initializationOptions`, and are refreshed on
+/// `workspace/didChangeConfiguration`. Settings missing from the client's
+/// payload keep their current values.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServerConfig {
+    /// Whether the client supports our custom highlighting publishing decorations.
+    /// This is different to the highlightingOn setting, which is whether the user
+    /// wants our custom highlighting to be used.
+    pub publish_decorations: bool,
+    /// Globs of paths, relative to the source roots, which should not be
+    /// scanned and indexed.
+    pub exclude_globs: Vec<String>,
+    pub cargo_features: CargoFeatures,
+    pub on_save: OnSaveConfig,
+    pub features: FeaturesConfig,
+}
+
+/// Which features are enabled when querying `cargo metadata`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CargoFeatures {
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub features: Vec<String>,
+    /// Run `cargo check` when loading the workspace, to learn the `OUT_DIR`s
+    /// and the cfgs set by the build scripts.
+    pub load_out_dirs_from_check: bool,
+}
+
+impl Default for CargoFeatures {
+    fn default() -> CargoFeatures {
+        CargoFeatures {
+            all_features: true,
+            no_default_features: false,
+            features: Vec::new(),
+            load_out_dirs_from_check: false,
+        }
+    }
+}
+
+/// What happens when a document is saved. The diagnostics of the open
+/// documents are republished in any case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OnSaveConfig {
+    /// Run `cargo check` in the workspace root, and publish its diagnostics
+    /// alongside ours.
+    pub cargo_check: bool,
+    /// Index the symbols of the saved file's source root ahead of the next
+    /// `workspace/symbol` request.
+    pub reindex_symbols: bool,
+}
+
+impl Default for OnSaveConfig {
+    fn default() -> OnSaveConfig {
+        OnSaveConfig {
+            cargo_check: true,
+            reindex_symbols: false,
+        }
+    }
+}
+
+/// Toggles for the features of the server. Disabled features are not
+/// advertised in the capabilities, and their requests are answered with empty
+/// results, as the client might send them anyway, or the settings might have
+/// changed since the initialization.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FeaturesConfig {
+    pub hover: bool,
+    pub completion: bool,
+    pub signature_help: bool,
+    pub on_enter: bool,
+    pub on_type_formatting: bool,
+    pub formatting: bool,
+    pub folding_ranges: bool,
+    pub rename: bool,
+    pub code_actions: bool,
+    pub document_highlight: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> FeaturesConfig {
+        FeaturesConfig {
+            hover: true,
+            completion: true,
+            signature_help: true,
+            on_enter: true,
+            on_type_formatting: true,
+            formatting: true,
+            folding_ranges: true,
+            rename: true,
+            code_actions: true,
+            document_highlight: true,
+        }
+    }
+}
+
+impl FeaturesConfig {
+    /// Whether the requests with `method` should be handled.
+    pub fn allows(&self, method: &str) -> bool {
+        let toggles = [
+            (req::HoverRequest::METHOD, self.hover),
+            (req::Completion::METHOD, self.completion),
+            (req::SignatureHelpRequest::METHOD, self.signature_help),
+            (req::OnEnter::METHOD, self.on_enter),
+            (req::OnTypeFormatting::METHOD, self.on_type_formatting),
+            (req::Formatting::METHOD, self.formatting),
+            (req::RangeFormatting::METHOD, self.formatting),
+            (req::FoldingRangeRequest::METHOD, self.folding_ranges),
+            (req::PrepareRenameRequest::METHOD, self.rename),
+            (req::Rename::METHOD, self.rename),
+            (req::CodeActionRequest::METHOD, self.code_actions),
+            (
+                req::DocumentHighlightRequest::METHOD,
+                self.document_highlight,
+            ),
+        ];
+        toggles
+            .iter()
+            .find(|(it, _)| *it == method)
+            .map_or(true, |&(_, enabled)| enabled)
+    }
+}
+
+impl ServerConfig {
+    /// Applies the settings from `changes` on top of the current ones.
+    pub fn update(&mut self, changes: Value) -> Result<()> {
+        let mut value = serde_json::to_value(&*self)?;
+        merge(&mut value, changes);
+        *self = serde_json::from_value(value)?;
+        Ok(())
+    }
+}
+
+/// Features of the client which change how we present results, taken from the
+/// capabilities in the `initialize` request. Unlike `ServerConfig`, these never
+/// change during a session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientCaps {
+    /// Whether hover contents may be markdown, rather than plain text.
+    pub hover_markdown: bool,
+    /// Whether we can ask the client to watch files for us.
+    pub watched_files_dynamic_registration: bool,
+    /// How the client counts the columns of positions. This is negotiated
+    /// with an extension to the capabilities, see `caps::initialize_result`.
+    pub offset_encoding: OffsetEncoding,
+    /// Whether the client can apply text edits with snippets, which is
+    /// announced with the `experimental.snippetTextEdit` capability.
+    pub snippet_text_edit: bool,
+    /// Whether code actions may be sent as `CodeAction` literals, with a
+    /// kind, rather than as bare commands.
+    pub code_action_literals: bool,
+    /// Whether hovers may have actions, which is announced with the
+    /// `experimental.hoverActions` capability.
+    pub hover_actions: bool,
+}
+
+impl ClientCaps {
+    pub fn new(caps: &ClientCapabilities, offset_encoding: OffsetEncoding) -> ClientCaps {
+        let watched_files_dynamic_registration = caps
+            .workspace
+            .as_ref()
+            .and_then(|it| it.did_change_watched_files.as_ref())
+            .and_then(|it| it.dynamic_registration)
+            == Some(true);
+        let text_document = caps.text_document.as_ref();
+        let hover_markdown = text_document
+            .and_then(|it| it.hover.as_ref())
+            .and_then(|it| it.content_format.as_ref())
+            .map(|formats| formats.contains(&MarkupKind::Markdown))
+            == Some(true);
+        let code_action_literals = text_document
+            .and_then(|it| it.code_action.as_ref())
+            .map(|it| it.code_action_literal_support.is_some())
+            == Some(true);
+        let experimental = |name: &str| {
+            caps.experimental
+                .as_ref()
+                .and_then(|it| it.get(name))
+                .and_then(|it| it.as_bool())
+                == Some(true)
+        };
+        ClientCaps {
+            hover_markdown,
+            watched_files_dynamic_registration,
+            offset_encoding,
+            snippet_text_edit: experimental("snippetTextEdit"),
+            code_action_literals,
+            hover_actions: experimental("hoverActions"),
+        }
+    }
+}
+
+fn merge(dst: &mut Value, src: Value) {
+    match (dst, src) {
+        (Value::Object(dst), Value::Object(src)) => {
+            for (key, value) in src {
+                match dst.get_mut(&key) {
+                    Some(it) => merge(it, value),
+                    None => {
+                        dst.insert(key, value);
+                    }
+                }
+            }
+        }
+        (_, Value::Null) => (),
+        (dst, src) => *dst = src,
+    }
+}
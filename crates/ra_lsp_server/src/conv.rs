@@ -1,19 +1,28 @@
+use std::sync::Arc;
+
 use languageserver_types::{
-    self, CreateFile, DocumentChangeOperation, DocumentChanges, InsertTextFormat, Location,
-    Position, Range, RenameFile, ResourceOp, SymbolKind, TextDocumentEdit, TextDocumentIdentifier,
-    TextDocumentItem, TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier,
-    WorkspaceEdit,
+    self, code_action_kind, CreateFile, InsertTextFormat, Location, Position, Range, RenameFile, ResourceOp,
+    SymbolKind, TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams, Url,
+    VersionedTextDocumentIdentifier,
 };
 use ra_analysis::{
-    CompletionItem, CompletionItemKind, FileId, FilePosition, FileRange, FileSystemEdit,
+    AssistKind, CompletionItem, CompletionItemKind, FileId, FilePosition, FileRange, FileSystemEdit,
     InsertText, NavigationTarget, SourceChange, SourceFileEdit,
 };
-use ra_editor::{translate_offset_with_edit, LineCol, LineIndex};
+use ra_editor::{strip_snippet_edit, translate_offset_with_edit, EncodedLineCol, LineIndex, OffsetEncoding};
 use ra_syntax::{SyntaxKind, TextRange, TextUnit};
 use ra_text_edit::{AtomTextEdit, TextEdit};
 
 use crate::{req, server_world::ServerWorld, Result};
 
+/// The line index of a file, together with the encoding in which the client
+/// counts columns. This is the context for converting offsets to and from
+/// LSP positions.
+pub struct LspLineIndex {
+    pub index: Arc<LineIndex>,
+    pub encoding: OffsetEncoding,
+}
+
 pub trait Conv {
     type Output;
     fn conv(self) -> Self::Output;
@@ -40,6 +49,7 @@ impl Conv for SyntaxKind {
             SyntaxKind::STRUCT_DEF => SymbolKind::Struct,
             SyntaxKind::ENUM_DEF => SymbolKind::Enum,
             SyntaxKind::TRAIT_DEF => SymbolKind::Interface,
+            SyntaxKind::TRAIT_ALIAS_DEF => SymbolKind::Interface,
             SyntaxKind::MODULE => SymbolKind::Module,
             SyntaxKind::TYPE_DEF => SymbolKind::TypeParameter,
             SyntaxKind::STATIC_DEF => SymbolKind::Constant,
@@ -93,34 +103,45 @@ impl Conv for CompletionItem {
     }
 }
 
+impl Conv for AssistKind {
+    type Output = &'static str;
+
+    fn conv(self) -> &'static str {
+        match self {
+            AssistKind::RefactorExtract => code_action_kind::REFACTOR_EXTRACT,
+            AssistKind::RefactorRewrite => code_action_kind::REFACTOR_REWRITE,
+        }
+    }
+}
+
 impl ConvWith for Position {
-    type Ctx = LineIndex;
+    type Ctx = LspLineIndex;
     type Output = TextUnit;
 
-    fn conv_with(self, line_index: &LineIndex) -> TextUnit {
-        let line_col = LineCol {
+    fn conv_with(self, line_index: &LspLineIndex) -> TextUnit {
+        let line_col = EncodedLineCol {
             line: self.line as u32,
-            col_utf16: self.character as u32,
+            col: self.character as u32,
         };
-        line_index.offset(line_col)
+        line_index.index.offset_in(line_col, line_index.encoding)
     }
 }
 
 impl ConvWith for TextUnit {
-    type Ctx = LineIndex;
+    type Ctx = LspLineIndex;
     type Output = Position;
 
-    fn conv_with(self, line_index: &LineIndex) -> Position {
-        let line_col = line_index.line_col(self);
-        Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16))
+    fn conv_with(self, line_index: &LspLineIndex) -> Position {
+        let line_col = line_index.index.line_col_in(self, line_index.encoding);
+        Position::new(u64::from(line_col.line), u64::from(line_col.col))
     }
 }
 
 impl ConvWith for TextRange {
-    type Ctx = LineIndex;
+    type Ctx = LspLineIndex;
     type Output = Range;
 
-    fn conv_with(self, line_index: &LineIndex) -> Range {
+    fn conv_with(self, line_index: &LspLineIndex) -> Range {
         Range::new(
             self.start().conv_with(line_index),
             self.end().conv_with(line_index),
@@ -129,10 +150,10 @@ impl ConvWith for TextRange {
 }
 
 impl ConvWith for Range {
-    type Ctx = LineIndex;
+    type Ctx = LspLineIndex;
     type Output = TextRange;
 
-    fn conv_with(self, line_index: &LineIndex) -> TextRange {
+    fn conv_with(self, line_index: &LspLineIndex) -> TextRange {
         TextRange::from_to(
             self.start.conv_with(line_index),
             self.end.conv_with(line_index),
@@ -141,10 +162,10 @@ impl ConvWith for Range {
 }
 
 impl ConvWith for TextEdit {
-    type Ctx = LineIndex;
+    type Ctx = LspLineIndex;
     type Output = Vec<languageserver_types::TextEdit>;
 
-    fn conv_with(self, line_index: &LineIndex) -> Vec<languageserver_types::TextEdit> {
+    fn conv_with(self, line_index: &LspLineIndex) -> Vec<languageserver_types::TextEdit> {
         self.as_atoms()
             .into_iter()
             .map_conv_with(line_index)
@@ -153,10 +174,10 @@ impl ConvWith for TextEdit {
 }
 
 impl<'a> ConvWith for &'a AtomTextEdit {
-    type Ctx = LineIndex;
+    type Ctx = LspLineIndex;
     type Output = languageserver_types::TextEdit;
 
-    fn conv_with(self, line_index: &LineIndex) -> languageserver_types::TextEdit {
+    fn conv_with(self, line_index: &LspLineIndex) -> languageserver_types::TextEdit {
         languageserver_types::TextEdit {
             range: self.delete.conv_with(line_index),
             new_text: self.insert.clone(),
@@ -217,7 +238,7 @@ impl<'a> TryConvWith for &'a TextDocumentPositionParams {
     type Output = FilePosition;
     fn try_conv_with(self, world: &ServerWorld) -> Result<FilePosition> {
         let file_id = self.text_document.try_conv_with(world)?;
-        let line_index = world.analysis().file_line_index(file_id);
+        let line_index = world.line_index(file_id);
         let offset = self.position.conv_with(&line_index);
         Ok(FilePosition { file_id, offset })
     }
@@ -228,7 +249,7 @@ impl<'a> TryConvWith for (&'a TextDocumentIdentifier, Range) {
     type Output = FileRange;
     fn try_conv_with(self, world: &ServerWorld) -> Result<FileRange> {
         let file_id = self.0.try_conv_with(world)?;
-        let line_index = world.analysis().file_line_index(file_id);
+        let line_index = world.line_index(file_id);
         let range = self.1.conv_with(&line_index);
         Ok(FileRange { file_id, range })
     }
@@ -249,39 +270,78 @@ impl<T: TryConvWith> TryConvWith for Vec<T> {
 impl TryConvWith for SourceChange {
     type Ctx = ServerWorld;
     type Output = req::SourceChange;
-    fn try_conv_with(self, world: &ServerWorld) -> Result<req::SourceChange> {
+    fn try_conv_with(mut self, world: &ServerWorld) -> Result<req::SourceChange> {
+        if self.is_snippet && !world.client_caps.snippet_text_edit {
+            for file_edit in self.source_file_edits.iter_mut() {
+                let (edit, cursor) = strip_snippet_edit(&file_edit.edit);
+                file_edit.edit = edit;
+                if let (None, Some(offset)) = (self.cursor_position, cursor) {
+                    let file_id = file_edit.file_id;
+                    self.cursor_position = Some(FilePosition { file_id, offset });
+                }
+            }
+            self.is_snippet = false;
+        }
         let cursor_position = match self.cursor_position {
             None => None,
             Some(pos) => {
-                let line_index = world.analysis().file_line_index(pos.file_id);
-                let edit = self
-                    .source_file_edits
-                    .iter()
-                    .find(|it| it.file_id == pos.file_id)
-                    .map(|it| &it.edit);
-                let line_col = match edit {
-                    Some(edit) => translate_offset_with_edit(&*line_index, pos.offset, edit),
-                    None => line_index.line_col(pos.offset),
+                let line_index = world.line_index(pos.file_id);
+                // All edits of a file refer to its current text, so the
+                // cursor is translated through all of them at once.
+                let mut edit: Option<TextEdit> = None;
+                for file_edit in self.source_file_edits.iter() {
+                    if file_edit.file_id != pos.file_id {
+                        continue;
+                    }
+                    edit = Some(match edit {
+                        None => file_edit.edit.clone(),
+                        Some(edit) => match edit.clone().merge(file_edit.edit.clone()) {
+                            Ok(it) => it,
+                            Err(err) => {
+                                log::error!("can't translate the cursor position: {}", err);
+                                edit
+                            }
+                        },
+                    });
+                }
+                let position = match &edit {
+                    Some(edit) if line_index.encoding == OffsetEncoding::Utf16 => {
+                        let line_col =
+                            translate_offset_with_edit(&line_index.index, pos.offset, edit);
+                        Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16))
+                    }
+                    Some(edit) => {
+                        let mut text = world.analysis().file_text(pos.file_id);
+                        edit.apply_to_rope(&mut text);
+                        let line_index = LspLineIndex {
+                            index: Arc::new(LineIndex::new(&text.to_string())),
+                            encoding: line_index.encoding,
+                        };
+                        pos.offset.conv_with(&line_index)
+                    }
+                    None => pos.offset.conv_with(&line_index),
                 };
-                let position =
-                    Position::new(u64::from(line_col.line), u64::from(line_col.col_utf16));
                 Some(TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier::new(pos.file_id.try_conv_with(world)?),
                     position,
                 })
             }
         };
-        let mut document_changes: Vec<DocumentChangeOperation> = Vec::new();
+        let mut document_changes = Vec::new();
         for resource_op in self.file_system_edits.try_conv_with(world)? {
-            document_changes.push(DocumentChangeOperation::Op(resource_op));
+            document_changes.push(req::SnippetDocumentChangeOperation::Op(resource_op));
         }
-        for text_document_edit in self.source_file_edits.try_conv_with(world)? {
-            document_changes.push(DocumentChangeOperation::Edit(text_document_edit));
+        for mut text_document_edit in self.source_file_edits.try_conv_with(world)? {
+            if self.is_snippet {
+                for edit in text_document_edit.edits.iter_mut() {
+                    edit.insert_text_format = Some(InsertTextFormat::Snippet);
+                }
+            }
+            document_changes.push(req::SnippetDocumentChangeOperation::Edit(
+                text_document_edit,
+            ));
         }
-        let workspace_edit = WorkspaceEdit {
-            changes: None,
-            document_changes: Some(DocumentChanges::Operations(document_changes)),
-        };
+        let workspace_edit = req::SnippetWorkspaceEdit { document_changes };
         Ok(req::SourceChange {
             label: self.label,
             workspace_edit,
@@ -292,20 +352,27 @@ impl TryConvWith for SourceChange {
 
 impl TryConvWith for SourceFileEdit {
     type Ctx = ServerWorld;
-    type Output = TextDocumentEdit;
-    fn try_conv_with(self, world: &ServerWorld) -> Result<TextDocumentEdit> {
+    type Output = req::SnippetTextDocumentEdit;
+    fn try_conv_with(self, world: &ServerWorld) -> Result<req::SnippetTextDocumentEdit> {
         let text_document = VersionedTextDocumentIdentifier {
             uri: self.file_id.try_conv_with(world)?,
             version: None,
         };
-        let line_index = world.analysis().file_line_index(self.file_id);
+        let line_index = world.line_index(self.file_id);
         let edits = self
             .edit
             .as_atoms()
             .iter()
             .map_conv_with(&line_index)
+            .map(
+                |edit: languageserver_types::TextEdit| req::SnippetTextEdit {
+                    range: edit.range,
+                    new_text: edit.new_text,
+                    insert_text_format: None,
+                },
+            )
             .collect();
-        Ok(TextDocumentEdit {
+        Ok(req::SnippetTextDocumentEdit {
             text_document,
             edits,
         })
@@ -343,7 +410,7 @@ impl TryConvWith for &NavigationTarget {
     type Ctx = ServerWorld;
     type Output = Location;
     fn try_conv_with(self, world: &ServerWorld) -> Result<Location> {
-        let line_index = world.analysis().file_line_index(self.file_id());
+        let line_index = world.line_index(self.file_id());
         to_location(self.file_id(), self.range(), &world, &line_index)
     }
 }
@@ -352,7 +419,7 @@ pub fn to_location(
     file_id: FileId,
     range: TextRange,
     world: &ServerWorld,
-    line_index: &LineIndex,
+    line_index: &LspLineIndex,
 ) -> Result<Location> {
     let url = file_id.try_conv_with(world)?;
     let loc = Location::new(url, range.conv_with(line_index));
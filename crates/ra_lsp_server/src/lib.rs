@@ -1,4 +1,6 @@
 mod caps;
+mod cargo_check;
+mod config;
 mod conv;
 mod main_loop;
 mod project_model;
@@ -6,4 +8,12 @@ pub mod req;
 mod server_world;
 
 pub type Result<T> = ::std::result::Result<T, ::failure::Error>;
-pub use crate::{caps::server_capabilities, main_loop::main_loop, main_loop::LspError};
+pub use crate::{
+    caps::{initialize_result, server_capabilities},
+    config::{
+        CargoFeatures, ClientCaps, FeaturesConfig, OnSaveConfig, ServerConfig,
+    },
+    main_loop::main_loop,
+    main_loop::LspError,
+    server_world::uri_to_file_path,
+};
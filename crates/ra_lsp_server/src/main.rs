@@ -1,8 +1,14 @@
+use std::{cell::Cell, path::PathBuf};
+
 use serde::Deserialize;
 use flexi_logger::{Duplicate, Logger};
-use gen_lsp_server::{run_server, stdio_transport};
+use failure::bail;
+use gen_lsp_server::{run_server_with_init, stdio_transport, tcp_transport};
+#[cfg(unix)]
+use gen_lsp_server::pipe_transport;
 
-use ra_lsp_server::Result;
+use ra_editor::OffsetEncoding;
+use ra_lsp_server::{ClientCaps, Result, ServerConfig};
 
 fn main() -> Result<()> {
     ::std::env::set_var("RUST_BACKTRACE", "short");
@@ -11,6 +17,11 @@ fn main() -> Result<()> {
         Ok(ref v) if v == "1" => logger.log_to_file().directory("log").start()?,
         _ => logger.start()?,
     };
+    // The panics of requests are caught, so log them with the backtrace,
+    // which would be lost otherwise.
+    ::std::panic::set_hook(Box::new(|info| {
+        log::error!("{}\n{:?}", info, failure::Backtrace::new())
+    }));
     log::info!("lifecycle: server started");
     match ::std::panic::catch_unwind(main_inner) {
         Ok(res) => {
@@ -24,33 +35,87 @@ fn main() -> Result<()> {
     }
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct InitializationOptions {
-    // Whether the client supports our custom highlighting publishing decorations.
-    // This is different to the highlightingOn setting, which is whether the user
-    // wants our custom highlighting to be used.
-    publish_decorations: Option<bool>,
+/// How the server talks to the client, selected with the command line flags.
+#[derive(Debug, PartialEq, Eq)]
+enum Transport {
+    /// The default, for clients which spawn the server.
+    Stdio,
+    /// `--tcp <addr>`, where the address may be just a port on localhost.
+    Tcp(String),
+    /// `--pipe <path>`, a unix domain socket. There is no support for the
+    /// named pipes of Windows, so other platforms reject the flag.
+    Pipe(PathBuf),
+}
+
+impl Transport {
+    fn from_args(args: impl Iterator<Item = String>) -> Result<Transport> {
+        let mut res = Transport::Stdio;
+        let mut args = args.skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || match args.next() {
+                Some(it) => Ok(it),
+                None => bail!("missing value for `{}`", arg),
+            };
+            res = match arg.as_str() {
+                "--tcp" => {
+                    let addr = value()?;
+                    if addr.chars().all(|c| c.is_ascii_digit()) {
+                        Transport::Tcp(format!("127.0.0.1:{}", addr))
+                    } else {
+                        Transport::Tcp(addr)
+                    }
+                }
+                "--pipe" if cfg!(unix) => Transport::Pipe(value()?.into()),
+                "--pipe" => bail!("`--pipe` is only supported on unix"),
+                _ => bail!("unknown argument: `{}`", arg),
+            };
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(not(unix))]
+fn pipe_transport(
+    _path: &std::path::Path,
+) -> Result<(
+    crossbeam_channel::Receiver<gen_lsp_server::RawMessage>,
+    crossbeam_channel::Sender<gen_lsp_server::RawMessage>,
+    gen_lsp_server::Threads,
+)> {
+    unreachable!("`--pipe` is rejected when parsing the arguments")
 }
 
 fn main_inner() -> Result<()> {
-    let (receiver, sender, threads) = stdio_transport();
+    let (receiver, sender, threads) = match Transport::from_args(::std::env::args())? {
+        Transport::Stdio => stdio_transport(),
+        Transport::Tcp(addr) => tcp_transport(&addr)?,
+        Transport::Pipe(path) => pipe_transport(&path)?,
+    };
     let cwd = ::std::env::current_dir()?;
-    run_server(
-        ra_lsp_server::server_capabilities(),
+    let encoding = Cell::new(OffsetEncoding::default());
+    run_server_with_init(
+        |params| {
+            let (result, negotiated) = ra_lsp_server::initialize_result(params);
+            encoding.set(negotiated);
+            result
+        },
         receiver,
         sender,
         |params, r, s| {
             let root = params
                 .root_uri
-                .and_then(|it| it.to_file_path().ok())
+                .and_then(|it| ra_lsp_server::uri_to_file_path(&it))
                 .unwrap_or(cwd);
-            let supports_decorations = params
+            let config = params
                 .initialization_options
-                .and_then(|v| InitializationOptions::deserialize(v).ok())
-                .and_then(|it| it.publish_decorations)
-                == Some(true);
-            ra_lsp_server::main_loop(false, root, supports_decorations, r, s)
+                .and_then(|v| {
+                    ServerConfig::deserialize(v)
+                        .map_err(|e| log::error!("invalid initialization options: {}", e))
+                        .ok()
+                })
+                .unwrap_or_default();
+            let caps = ClientCaps::new(&params.capabilities, encoding.get());
+            ra_lsp_server::main_loop(false, root, config, caps, r, s)
         },
     )?;
     log::info!("shutting down IO...");
@@ -58,3 +123,39 @@ fn main_inner() -> Result<()> {
     log::info!("... IO is down");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Transport;
+
+    fn parse(args: &[&str]) -> Option<Transport> {
+        let args = Some("ra_lsp_server")
+            .into_iter()
+            .chain(args.iter().cloned())
+            .map(String::from);
+        Transport::from_args(args).ok()
+    }
+
+    #[test]
+    fn transport_from_args() {
+        assert_eq!(parse(&[]), Some(Transport::Stdio));
+        assert_eq!(
+            parse(&["--tcp", "9257"]),
+            Some(Transport::Tcp("127.0.0.1:9257".to_string()))
+        );
+        assert_eq!(
+            parse(&["--tcp", "example.com:1"]),
+            Some(Transport::Tcp("example.com:1".to_string()))
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            parse(&["--pipe", "/tmp/ra.sock"]),
+            Some(Transport::Pipe("/tmp/ra.sock".into()))
+        );
+        #[cfg(not(unix))]
+        assert_eq!(parse(&["--pipe", "/tmp/ra.sock"]), None);
+        assert_eq!(parse(&["--tcp"]), None);
+        assert_eq!(parse(&["--pipe"]), None);
+        assert_eq!(parse(&["--stdio"]), None);
+    }
+}
@@ -1,28 +1,41 @@
 mod handlers;
 mod subscriptions;
 
-use std::{fmt, path::PathBuf, sync::Arc};
+use std::{
+    any::Any,
+    fmt, mem,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use crossbeam_channel::{select, unbounded, Receiver, RecvError, Sender};
+use crossbeam_channel::{after, bounded, never, select, unbounded, Receiver, RecvError, Sender};
 use failure::{bail, format_err};
 use failure_derive::Fail;
-use gen_lsp_server::{
-    handle_shutdown, ErrorCode, RawMessage, RawNotification, RawRequest, RawResponse,
-};
-use languageserver_types::NumberOrString;
-use ra_analysis::{Canceled, FileId, LibraryData};
+use gen_lsp_server::{ErrorCode, RawMessage, RawNotification, RawRequest, RawResponse};
+use languageserver_types::{NumberOrString, Position, TextDocumentContentChangeEvent, Url};
+use ra_analysis::{AnalysisHost, CancelationToken, Canceled, FileId, LibraryData, Rope};
+use ra_editor::OffsetEncoding;
 use ra_vfs::VfsTask;
+use thread_worker::Worker;
 use rayon;
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{de::DeserializeOwned, Serialize};
 use threadpool::ThreadPool;
 
 use crate::{
+    cargo_check::{check_watcher, CheckDiagnostic},
     main_loop::subscriptions::Subscriptions,
-    project_model::workspace_loader,
+    config::ClientCaps,
+    project_model::{workspace_loader, ProjectWorkspace},
     req,
-    server_world::{ServerWorld, ServerWorldState},
-    Result,
+    server_world::{uri_to_file_path, DocumentVersions, ServerWorld, ServerWorldState},
+    Result, ServerConfig,
 };
 
 #[derive(Debug, Fail)]
@@ -45,21 +58,28 @@ impl LspError {
 enum Task {
     Respond(RawResponse),
     Notify(RawNotification),
+    /// Diagnostics are published by the main loop, which drops them if the
+    /// document has changed since.
+    PublishDiagnostics(req::PublishDiagnosticsParams),
 }
 
 pub fn main_loop(
     internal_mode: bool,
     ws_root: PathBuf,
-    supports_decorations: bool,
+    config: ServerConfig,
+    client_caps: ClientCaps,
     msg_receiver: &Receiver<RawMessage>,
     msg_sender: &Sender<RawMessage>,
 ) -> Result<()> {
-    let pool = ThreadPool::new(8);
+    let pools = Pools {
+        latency: ThreadPool::new(4),
+        heavy: ThreadPool::new(8),
+    };
     let (task_sender, task_receiver) = unbounded::<Task>();
-    let (ws_worker, ws_watcher) = workspace_loader();
+    let (ws_worker, ws_watcher) = workspace_loader(config.cargo_features.clone());
+    let (check_worker, check_watcher) = check_watcher();
 
     ws_worker.send(ws_root.clone()).unwrap();
-    // FIXME: support dynamic workspace loading.
     let workspaces = match ws_worker.recv().unwrap() {
         Ok(ws) => vec![ws],
         Err(e) => {
@@ -67,52 +87,132 @@ pub fn main_loop(
             Vec::new()
         }
     };
-    ws_worker.shutdown();
-    ws_watcher
-        .shutdown()
-        .map_err(|_| format_err!("ws watcher died"))?;
-    let mut state = ServerWorldState::new(ws_root.clone(), workspaces);
+    let mut state = ServerWorldState::new(ws_root.clone(), workspaces, config, client_caps.clone());
 
     log::info!("server initialized, serving requests");
 
-    let mut pending_requests = FxHashSet::default();
+    let mut pending_requests = PendingRequests::default();
     let mut subs = Subscriptions::new();
+    let mut outgoing = OutgoingRequests::default();
+    if client_caps.watched_files_dynamic_registration {
+        register_manifest_watchers(msg_sender, &mut outgoing);
+    }
     let main_res = main_loop_inner(
         internal_mode,
-        supports_decorations,
-        &pool,
+        &pools,
         msg_sender,
         msg_receiver,
         task_sender,
         task_receiver.clone(),
+        &ws_worker,
+        &check_worker,
         &mut state,
         &mut pending_requests,
         &mut subs,
+        &mut outgoing,
     );
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
 
-    log::info!("waiting for tasks to finish...");
-    task_receiver
-        .into_iter()
-        .for_each(|task| on_task(task, msg_sender, &mut pending_requests));
-    log::info!("...tasks have finished");
-    log::info!("joining threadpool...");
-    drop(pool);
-    log::info!("...threadpool has finished");
+    log::info!("canceling tasks...");
+    let host = mem::replace(&mut state.analysis_host, AnalysisHost::default());
+    let canceled = cancel_analysis(host);
+    log::info!("waiting for pending requests...");
+    while !pending_requests.is_empty() {
+        select! {
+            recv(task_receiver) -> task => match task {
+                Ok(task) => on_task(
+                    task,
+                    msg_sender,
+                    &mut pending_requests,
+                    &state.document_versions,
+                ),
+                Err(RecvError) => break,
+            },
+            recv(after(time_left(deadline))) -> _ => break,
+        }
+    }
+    // The client might wait for these responses, so don't leave it hanging.
+    for (id, _) in pending_requests.drain() {
+        log::warn!("request {} is still running, giving up on it", id);
+        let resp = RawResponse::err(
+            id,
+            ErrorCode::RequestCancelled as i32,
+            "server is shutting down".to_string(),
+        );
+        msg_sender.send(RawMessage::Response(resp)).unwrap();
+    }
+    select! {
+        recv(canceled) -> _ => log::info!("...tasks have finished"),
+        recv(after(time_left(deadline))) -> _ => log::warn!("some tasks are still running"),
+    }
+    // Tasks which are still running are left alone, the process exits
+    // shortly anyway.
+    drop(pools);
 
-    let vfs = Arc::try_unwrap(state.vfs).expect("all snapshots should be dead");
-    let vfs_res = vfs.into_inner().shutdown();
+    ws_worker.shutdown();
+    ws_watcher
+        .shutdown_timeout(time_left(deadline))
+        .map_err(|_| format_err!("ws watcher died"))?;
+    check_worker.shutdown();
+    check_watcher
+        .shutdown_timeout(time_left(deadline))
+        .map_err(|_| format_err!("check watcher died"))?;
 
-    main_res?;
+    let vfs_res = match Arc::try_unwrap(state.vfs) {
+        Ok(vfs) => vfs.into_inner().shutdown(),
+        Err(_) => {
+            log::warn!("vfs is still used by running tasks");
+            Ok(())
+        }
+    };
+
+    // Answer the shutdown request only now, so that the client does not
+    // kill the process while we are cleaning up.
+    let shutdown_id = main_res?;
+    let resp = RawResponse::ok::<req::Shutdown>(shutdown_id, &());
+    msg_sender.send(RawMessage::Response(resp)).unwrap();
     vfs_res.map_err(|_| format_err!("fs watcher died"))?;
 
     Ok(())
 }
 
+/// How long the shutdown waits for in-flight tasks and worker threads, in
+/// total.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+fn time_left(deadline: Instant) -> Duration {
+    let now = Instant::now();
+    if now < deadline {
+        deadline - now
+    } else {
+        Duration::from_secs(0)
+    }
+}
+
+/// How long the diagnostics wait for the user to stop typing, before they are
+/// recomputed.
+const DIAGNOSTICS_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Cancels the queries of all snapshots of `host`. As this blocks until the
+/// snapshots are dropped, it happens in a separate thread, and the returned
+/// channel is disconnected when it's done.
+fn cancel_analysis(mut host: AnalysisHost) -> Receiver<()> {
+    let (sender, receiver) = bounded::<()>(0);
+    thread::spawn(move || {
+        host.request_cancellation();
+        drop(sender);
+    });
+    receiver
+}
+
 enum Event {
     Msg(RawMessage),
     Task(Task),
     Vfs(VfsTask),
     Lib(LibraryData),
+    Workspace(Result<ProjectWorkspace>),
+    Check(Result<Vec<CheckDiagnostic>>),
+    DiagnosticsDue,
 }
 
 impl fmt::Debug for Event {
@@ -134,6 +234,13 @@ impl fmt::Debug for Event {
                     return debug_verbose_not(not, f);
                 }
             }
+            Event::Task(Task::PublishDiagnostics(params)) => {
+                return f
+                    .debug_struct("PublishDiagnosticsParams")
+                    .field("uri", &params.uri)
+                    .field("version", &params.version)
+                    .finish();
+            }
             Event::Task(Task::Respond(resp)) => {
                 return f
                     .debug_struct("RawResponse")
@@ -148,25 +255,49 @@ impl fmt::Debug for Event {
             Event::Task(it) => fmt::Debug::fmt(it, f),
             Event::Vfs(it) => fmt::Debug::fmt(it, f),
             Event::Lib(it) => fmt::Debug::fmt(it, f),
+            Event::Workspace(it) => fmt::Debug::fmt(it, f),
+            Event::Check(Ok(it)) => write!(f, "{} cargo check diagnostics", it.len()),
+            Event::Check(Err(it)) => fmt::Debug::fmt(it, f),
+            Event::DiagnosticsDue => write!(f, "DiagnosticsDue"),
         }
     }
 }
 
+/// Serves the client until the shutdown request, and returns its id.
 fn main_loop_inner(
     internal_mode: bool,
-    supports_decorations: bool,
-    pool: &ThreadPool,
+    pools: &Pools,
     msg_sender: &Sender<RawMessage>,
     msg_receiver: &Receiver<RawMessage>,
     task_sender: Sender<Task>,
     task_receiver: Receiver<Task>,
+    ws_worker: &Worker<PathBuf, Result<ProjectWorkspace>>,
+    check_worker: &Worker<PathBuf, Result<Vec<CheckDiagnostic>>>,
     state: &mut ServerWorldState,
-    pending_requests: &mut FxHashSet<u64>,
+    pending_requests: &mut PendingRequests,
     subs: &mut Subscriptions,
-) -> Result<()> {
+    outgoing: &mut OutgoingRequests,
+) -> Result<u64> {
     let (libdata_sender, libdata_receiver) = unbounded();
+    let mut caches_primed = false;
+    let mut pending_reloads = Vec::new();
+    // Files which had diagnostics of `cargo check`, which the next
+    // publication should clear.
+    let mut stale_check_files = Vec::new();
+    // While the user is typing, the diagnostics are not recomputed until
+    // this deadline.
+    let mut diagnostics_due: Option<Instant> = None;
+    let symbol_cache_dir = if internal_mode {
+        None
+    } else {
+        symbol_cache_dir()
+    };
     loop {
         log::trace!("selecting");
+        let diagnostics_timer = match diagnostics_due {
+            Some(due) => after(time_left(due)),
+            None => never(),
+        };
         let event = select! {
             recv(msg_receiver) -> msg => match msg {
                 Ok(msg) => Event::Msg(msg),
@@ -177,13 +308,18 @@ fn main_loop_inner(
                 Ok(task) => Event::Vfs(task),
                 Err(RecvError) => bail!("vfs died"),
             },
-            recv(libdata_receiver) -> data => Event::Lib(data.unwrap())
+            recv(libdata_receiver) -> data => Event::Lib(data.unwrap()),
+            recv(ws_worker.out) -> ws => Event::Workspace(ws.unwrap()),
+            recv(check_worker.out) -> check => Event::Check(check.unwrap()),
+            recv(diagnostics_timer) -> _ => Event::DiagnosticsDue,
         };
         log::info!("loop_turn = {:?}", event);
         let start = std::time::Instant::now();
         let mut state_changed = false;
         match event {
-            Event::Task(task) => on_task(task, msg_sender, pending_requests),
+            Event::Task(task) => {
+                on_task(task, msg_sender, pending_requests, &state.document_versions)
+            }
             Event::Vfs(task) => {
                 state.vfs.write().handle_task(task);
                 state_changed = true;
@@ -192,127 +328,402 @@ fn main_loop_inner(
                 feedback(internal_mode, "library loaded", msg_sender);
                 state.add_lib(lib);
             }
+            Event::Workspace(ws) => {
+                let resp = match ws {
+                    Ok(ws) => {
+                        state.set_workspaces(vec![ws]);
+                        state_changed = true;
+                        feedback(internal_mode, "workspace reloaded", msg_sender);
+                        Ok(())
+                    }
+                    Err(e) => {
+                        log::warn!("reloading workspace failed: {}", e);
+                        Err(e.to_string())
+                    }
+                };
+                for id in pending_reloads.drain(..) {
+                    let resp = match &resp {
+                        Ok(()) => RawResponse::ok::<req::ReloadWorkspace>(id, &()),
+                        Err(message) => {
+                            RawResponse::err(id, ErrorCode::InternalError as i32, message.clone())
+                        }
+                    };
+                    msg_sender.send(RawMessage::Response(resp)).unwrap();
+                }
+            }
+            Event::Check(check) => match check {
+                Ok(diagnostics) => {
+                    stale_check_files.extend(state.set_check_diagnostics(diagnostics));
+                    state_changed = true;
+                }
+                Err(e) => log::error!("{}", e),
+            },
+            Event::DiagnosticsDue => {
+                diagnostics_due = None;
+                state_changed = true;
+            }
             Event::Msg(msg) => match msg {
                 RawMessage::Request(req) => {
-                    let req = match handle_shutdown(req, msg_sender) {
-                        Some(req) => req,
-                        None => return Ok(()),
+                    // The shutdown request is answered by `main_loop`, once
+                    // the server has cleaned up.
+                    let req = match req.cast::<req::Shutdown>() {
+                        Ok((id, ())) => return Ok(id),
+                        Err(req) => req,
+                    };
+                    // These requests need the main loop, the rest run on
+                    // the thread pool.
+                    let req = match req.cast::<req::AnalyzerStatus>() {
+                        Ok((id, ())) => {
+                            let status = analyzer_status(state, pending_requests, pools);
+                            let resp = RawResponse::ok::<req::AnalyzerStatus>(id, &status);
+                            msg_sender.send(RawMessage::Response(resp)).unwrap();
+                            None
+                        }
+                        Err(req) => Some(req),
+                    };
+                    let req = match req.map(|req| req.cast::<req::CollectGarbage>()) {
+                        Some(Ok((id, ()))) => {
+                            let open_files = subs.subscriptions();
+                            let reclaimed_bytes = state.analysis_host.trim_memory(&open_files);
+                            let result = req::CollectGarbageResult { reclaimed_bytes };
+                            let resp = RawResponse::ok::<req::CollectGarbage>(id, &result);
+                            msg_sender.send(RawMessage::Response(resp)).unwrap();
+                            None
+                        }
+                        Some(Err(req)) => Some(req),
+                        None => None,
+                    };
+                    let req = match req.map(reload_request_id) {
+                        Some(Ok(id)) => {
+                            // A full queue means that a reload is pending
+                            // already, and it will answer this request too.
+                            let _ = ws_worker.inp.try_send(state.root.clone());
+                            pending_reloads.push(id);
+                            None
+                        }
+                        Some(Err(req)) => Some(req),
+                        None => None,
                     };
-                    match on_request(state, pending_requests, pool, &task_sender, req)? {
-                        None => (),
-                        Some(req) => {
-                            log::error!("unknown request: {:?}", req);
+                    let req = match req.map(apply_source_change_request) {
+                        Some(Ok((id, Ok(change)))) => {
+                            send_apply_edit(msg_sender, outgoing, id, change);
+                            None
+                        }
+                        Some(Ok((id, Err(e)))) => {
                             let resp = RawResponse::err(
-                                req.id,
-                                ErrorCode::MethodNotFound as i32,
-                                "unknown request".to_string(),
+                                id,
+                                ErrorCode::InvalidParams as i32,
+                                e.to_string(),
                             );
-                            msg_sender.send(RawMessage::Response(resp)).unwrap()
+                            msg_sender.send(RawMessage::Response(resp)).unwrap();
+                            None
                         }
+                        Some(Err(req)) => Some(req),
+                        None => None,
+                    };
+                    let req = match req {
+                        Some(req) => on_request(state, pending_requests, pools, &task_sender, req)?,
+                        None => None,
+                    };
+                    if let Some(req) = req {
+                        log::error!("unknown request: {:?}", req);
+                        let resp = RawResponse::err(
+                            req.id,
+                            ErrorCode::MethodNotFound as i32,
+                            "unknown request".to_string(),
+                        );
+                        msg_sender.send(RawMessage::Response(resp)).unwrap()
                     }
                 }
                 RawMessage::Notification(not) => {
-                    on_notification(msg_sender, state, pending_requests, subs, not)?;
+                    if not.is::<req::Exit>() {
+                        bail!("client exited without shutdown");
+                    }
+                    if not.is::<req::DidChangeTextDocument>() {
+                        diagnostics_due = Some(Instant::now() + DIAGNOSTICS_DEBOUNCE);
+                    }
+                    on_notification(
+                        msg_sender,
+                        &pools.heavy,
+                        state,
+                        pending_requests,
+                        subs,
+                        outgoing,
+                        ws_worker,
+                        check_worker,
+                        not,
+                    )?;
                     state_changed = true;
                 }
-                RawMessage::Response(resp) => log::error!("unexpected response: {:?}", resp),
+                RawMessage::Response(resp) => {
+                    if outgoing.config.remove(&resp.id) {
+                        on_config_response(state, resp);
+                        state_changed = true;
+                    } else if let Some((id, cursor)) = outgoing.edits.remove(&resp.id) {
+                        on_apply_edit_response(msg_sender, id, cursor, resp);
+                    } else if outgoing.registrations.remove(&resp.id) {
+                        if let Some(err) = resp.error {
+                            log::error!("failed to register a capability: {:?}", err);
+                        }
+                    } else {
+                        log::error!("unexpected response: {:?}", resp)
+                    }
+                }
             },
         };
 
         for lib in state.process_changes() {
             let (root, files) = lib;
             let sender = libdata_sender.clone();
-            pool.execute(move || {
+            let cache_dir = symbol_cache_dir.clone();
+            pools.heavy.execute(move || {
                 let start = ::std::time::Instant::now();
                 log::info!("indexing {:?} ... ", root);
-                let data = LibraryData::prepare(root, files);
+                let data = match cache_dir {
+                    Some(dir) => LibraryData::prepare_cached(root, files, &dir),
+                    None => LibraryData::prepare(root, files),
+                };
                 log::info!("indexed {:?} {:?}", start.elapsed(), root);
-                sender.send(data).unwrap();
+                // The main loop is gone if the indexing outlived the shutdown.
+                let _ = sender.send(data);
             });
         }
         if state.roots_to_scan == 0 {
             feedback(internal_mode, "workspace loaded", msg_sender);
+            if !caches_primed {
+                caches_primed = true;
+                let world = state.snapshot();
+                pools.heavy.execute(move || {
+                    // Canceled priming is fine: the caches are filled lazily.
+                    let _ = world.analysis().prime_caches();
+                });
+            }
         }
 
-        if state_changed {
+        if state_changed && diagnostics_due.is_none() {
+            // The diagnostics of `cargo check` are published even for the
+            // files which are not open.
+            let mut files = subs.subscriptions();
+            files.extend(state.check_diagnostics.keys().cloned());
+            files.append(&mut stale_check_files);
+            files.sort();
+            files.dedup();
             update_file_notifications_on_threadpool(
-                pool,
+                &pools.heavy,
                 state.snapshot(),
-                supports_decorations,
+                &state.document_versions,
                 task_sender.clone(),
-                subs.subscriptions(),
+                files,
             )
         }
         log::info!("loop_turn = {:?}", start.elapsed());
     }
 }
 
-fn on_task(task: Task, msg_sender: &Sender<RawMessage>, pending_requests: &mut FxHashSet<u64>) {
+fn on_task(
+    task: Task,
+    msg_sender: &Sender<RawMessage>,
+    pending_requests: &mut PendingRequests,
+    versions: &DocumentVersions,
+) {
     match task {
         Task::Respond(response) => {
-            if pending_requests.remove(&response.id) {
+            if pending_requests.remove(&response.id).is_some() {
                 msg_sender.send(RawMessage::Response(response)).unwrap();
             }
         }
         Task::Notify(n) => {
             msg_sender.send(RawMessage::Notification(n)).unwrap();
         }
+        Task::PublishDiagnostics(params) => {
+            if params.version != versions.version(&params.uri) {
+                log::debug!("dropping outdated diagnostics for {}", params.uri);
+                return;
+            }
+            let not = RawNotification::new::<req::PublishDiagnostics>(&params);
+            msg_sender.send(RawMessage::Notification(not)).unwrap();
+        }
     }
 }
 
 fn on_request(
     world: &mut ServerWorldState,
-    pending_requests: &mut FxHashSet<u64>,
-    pool: &ThreadPool,
+    pending_requests: &mut PendingRequests,
+    pools: &Pools,
     sender: &Sender<Task>,
     req: RawRequest,
 ) -> Result<Option<RawRequest>> {
     let mut pool_dispatcher = PoolDispatcher {
         req: Some(req),
         res: None,
-        pool,
+        pools,
         world,
         sender,
     };
     let req = pool_dispatcher
+        .on_latency_sensitive::<req::ExtendSelection>(handlers::handle_extend_selection)?
+        .on_latency_sensitive::<req::FindMatchingBrace>(handlers::handle_find_matching_brace)?
+        .on_latency_sensitive::<req::JoinLines>(handlers::handle_join_lines)?
+        .on_latency_sensitive::<req::OnEnter>(handlers::handle_on_enter)?
+        .on_latency_sensitive::<req::OnTypeFormatting>(handlers::handle_on_type_formatting)?
+        .on_latency_sensitive::<req::Completion>(handlers::handle_completion)?
+        .on_latency_sensitive::<req::SignatureHelpRequest>(handlers::handle_signature_help)?
+        .on_latency_sensitive::<req::HoverRequest>(handlers::handle_hover)?
         .on::<req::SyntaxTree>(handlers::handle_syntax_tree)?
-        .on::<req::ExtendSelection>(handlers::handle_extend_selection)?
-        .on::<req::FindMatchingBrace>(handlers::handle_find_matching_brace)?
-        .on::<req::JoinLines>(handlers::handle_join_lines)?
-        .on::<req::OnEnter>(handlers::handle_on_enter)?
-        .on::<req::OnTypeFormatting>(handlers::handle_on_type_formatting)?
         .on::<req::DocumentSymbolRequest>(handlers::handle_document_symbol)?
         .on::<req::WorkspaceSymbol>(handlers::handle_workspace_symbol)?
         .on::<req::GotoDefinition>(handlers::handle_goto_definition)?
         .on::<req::ParentModule>(handlers::handle_parent_module)?
         .on::<req::Runnables>(handlers::handle_runnables)?
         .on::<req::DecorationsRequest>(handlers::handle_decorations)?
-        .on::<req::Completion>(handlers::handle_completion)?
         .on::<req::CodeActionRequest>(handlers::handle_code_action)?
         .on::<req::FoldingRangeRequest>(handlers::handle_folding_range)?
-        .on::<req::SignatureHelpRequest>(handlers::handle_signature_help)?
-        .on::<req::HoverRequest>(handlers::handle_hover)?
         .on::<req::PrepareRenameRequest>(handlers::handle_prepare_rename)?
         .on::<req::Rename>(handlers::handle_rename)?
         .on::<req::References>(handlers::handle_references)?
         .on::<req::Formatting>(handlers::handle_formatting)?
+        .on::<req::RangeFormatting>(handlers::handle_range_formatting)?
         .on::<req::DocumentHighlightRequest>(handlers::handle_document_highlight)?
         .finish();
     match req {
-        Ok(id) => {
-            let inserted = pending_requests.insert(id);
-            assert!(inserted, "duplicate request: {}", id);
+        Ok((id, task_state)) => {
+            let prev = pending_requests.insert(id, task_state);
+            assert!(prev.is_none(), "duplicate request: {}", id);
             Ok(None)
         }
         Err(req) => Ok(Some(req)),
     }
 }
 
+/// The status of the world, followed by the load of the main loop.
+fn analyzer_status(
+    state: &ServerWorldState,
+    pending_requests: &PendingRequests,
+    pools: &Pools,
+) -> String {
+    let mut buf = state.snapshot().status();
+    buf.push_str(&format!(
+        "tasks: {} pending requests\n",
+        pending_requests.len()
+    ));
+    for (name, pool) in &[("latency", &pools.latency), ("heavy", &pools.heavy)] {
+        buf.push_str(&format!(
+            "    {} pool: {} queued, {} active\n",
+            name,
+            pool.queued_count(),
+            pool.active_count(),
+        ));
+    }
+    buf
+}
+
+/// Recognizes the requests to reload the project model, which are answered
+/// only once the workspace has been loaded.
+fn reload_request_id(req: RawRequest) -> std::result::Result<u64, RawRequest> {
+    let req = match req.cast::<req::ReloadWorkspace>() {
+        Ok((id, ())) => return Ok(id),
+        Err(req) => req,
+    };
+    match req.clone().cast::<req::ExecuteCommand>() {
+        Ok((id, params)) if params.command == req::RELOAD_WORKSPACE_COMMAND => Ok(id),
+        _ => Err(req),
+    }
+}
+
+/// Recognizes the `APPLY_SOURCE_CHANGE_COMMAND` requests, and parses their
+/// source change.
+fn apply_source_change_request(
+    req: RawRequest,
+) -> std::result::Result<(u64, Result<req::SourceChange>), RawRequest> {
+    match req.clone().cast::<req::ExecuteCommand>() {
+        Ok((id, params)) if params.command == req::APPLY_SOURCE_CHANGE_COMMAND => {
+            let change = match params.arguments.into_iter().next() {
+                Some(arg) => serde_json::from_value(arg).map_err(Into::into),
+                None => Err(format_err!("missing source change")),
+            };
+            Ok((id, change))
+        }
+        _ => Err(req),
+    }
+}
+
+/// Asks the client to apply the edit of `change`. The command request `id` is
+/// answered once the client has responded.
+fn send_apply_edit(
+    msg_sender: &Sender<RawMessage>,
+    outgoing: &mut OutgoingRequests,
+    id: u64,
+    change: req::SourceChange,
+) {
+    let apply_id = outgoing.next_id();
+    let params = req::ApplySnippetWorkspaceEditParams {
+        label: change.label,
+        edit: change.workspace_edit,
+    };
+    let request = RawRequest::new::<req::ApplySnippetWorkspaceEdit>(apply_id, &params);
+    msg_sender.send(RawMessage::Request(request)).unwrap();
+    outgoing
+        .edits
+        .insert(apply_id, (id, change.cursor_position));
+}
+
+fn on_apply_edit_response(
+    msg_sender: &Sender<RawMessage>,
+    id: u64,
+    cursor: Option<req::TextDocumentPositionParams>,
+    resp: RawResponse,
+) {
+    let applied = match (resp.error, resp.result) {
+        (Some(err), _) => {
+            log::error!("failed to apply an edit: {:?}", err);
+            false
+        }
+        (None, Some(result)) => serde_json::from_value::<req::ApplyWorkspaceEditResponse>(result)
+            .map(|it| it.applied)
+            .unwrap_or(false),
+        (None, None) => false,
+    };
+    // The cursor position refers to the text after the edit, so it's only
+    // meaningful if the edit went through.
+    if let (true, Some(cursor)) = (applied, cursor) {
+        let not = RawNotification::new::<req::MoveCursor>(&cursor);
+        msg_sender.send(RawMessage::Notification(not)).unwrap();
+    }
+    if !applied {
+        log::info!("the client did not apply the edit of request {}", id);
+    }
+    let resp = RawResponse::ok::<req::ExecuteCommand>(id, &None);
+    msg_sender.send(RawMessage::Response(resp)).unwrap();
+}
+
+/// Requests which the server has sent to the client, and which await a
+/// response.
+#[derive(Default)]
+struct OutgoingRequests {
+    next_id: u64,
+    config: FxHashSet<u64>,
+    registrations: FxHashSet<u64>,
+    /// The `workspace/applyEdit` requests, with the command requests which
+    /// they answer, and the cursor positions to move to afterwards.
+    edits: FxHashMap<u64, (u64, Option<req::TextDocumentPositionParams>)>,
+}
+
+impl OutgoingRequests {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
 fn on_notification(
     msg_sender: &Sender<RawMessage>,
+    pool: &ThreadPool,
     state: &mut ServerWorldState,
-    pending_requests: &mut FxHashSet<u64>,
+    pending_requests: &mut PendingRequests,
     subs: &mut Subscriptions,
+    outgoing: &mut OutgoingRequests,
+    ws_worker: &Worker<PathBuf, Result<ProjectWorkspace>>,
+    check_worker: &Worker<PathBuf, Result<Vec<CheckDiagnostic>>>,
     not: RawNotification,
 ) -> Result<()> {
     let not = match not.cast::<req::Cancel>() {
@@ -323,7 +734,12 @@ fn on_notification(
                     panic!("string id's not supported: {:?}", id);
                 }
             };
-            if pending_requests.remove(&id) {
+            if let Some(task_state) = pending_requests.remove(&id) {
+                // Only this task's snapshot is canceled: canceling the salsa
+                // revision would take down the other requests too. The
+                // response of the task is dropped, as the request is no
+                // longer pending.
+                task_state.cancel();
                 let response = RawResponse::err(
                     id,
                     ErrorCode::RequestCancelled as i32,
@@ -338,32 +754,41 @@ fn on_notification(
     let not = match not.cast::<req::DidOpenTextDocument>() {
         Ok(params) => {
             let uri = params.text_document.uri;
-            let path = uri
-                .to_file_path()
-                .map_err(|()| format_err!("invalid uri: {}", uri))?;
+            // Documents without a path on disk, like `untitled:` buffers, are
+            // analyzed as standalone files.
+            let (path, is_untitled) = match uri_to_file_path(&uri) {
+                Some(it) => (it, false),
+                None => (state.untitled.add(&uri), true),
+            };
+            state
+                .document_versions
+                .set(&uri, Some(params.text_document.version));
             if let Some(file_id) = state
                 .vfs
                 .write()
-                .add_file_overlay(&path, params.text_document.text)
+                .set_overlay(&path, params.text_document.text)
             {
                 subs.add_sub(FileId(file_id.0.into()));
             }
+            if is_untitled {
+                state.update_untitled_crates();
+            }
             return Ok(());
         }
         Err(not) => not,
     };
     let not = match not.cast::<req::DidChangeTextDocument>() {
-        Ok(mut params) => {
+        Ok(params) => {
             let uri = params.text_document.uri;
-            let path = uri
-                .to_file_path()
-                .map_err(|()| format_err!("invalid uri: {}", uri))?;
-            let text = params
-                .content_changes
-                .pop()
-                .ok_or_else(|| format_err!("empty changes"))?
-                .text;
-            state.vfs.write().change_file_overlay(path.as_path(), text);
+            let path = state.uri_to_path(&uri)?;
+            state
+                .document_versions
+                .set(&uri, params.text_document.version);
+            let changes = params.content_changes;
+            let encoding = state.client_caps.offset_encoding;
+            state.vfs.write().edit_overlay(path.as_path(), |text| {
+                apply_document_changes(text, changes, encoding)
+            });
             return Ok(());
         }
         Err(not) => not,
@@ -371,15 +796,18 @@ fn on_notification(
     let not = match not.cast::<req::DidCloseTextDocument>() {
         Ok(params) => {
             let uri = params.text_document.uri;
-            let path = uri
-                .to_file_path()
-                .map_err(|()| format_err!("invalid uri: {}", uri))?;
-            if let Some(file_id) = state.vfs.write().remove_file_overlay(path.as_path()) {
+            let path = state.uri_to_path(&uri)?;
+            if let Some(file_id) = state.vfs.write().remove_overlay(path.as_path()) {
                 subs.remove_sub(FileId(file_id.0.into()));
             }
+            if state.untitled.remove(&uri) {
+                state.update_untitled_crates();
+            }
+            state.document_versions.remove(&uri);
             let params = req::PublishDiagnosticsParams {
                 uri,
                 diagnostics: Vec::new(),
+                version: None,
             };
             let not = RawNotification::new::<req::PublishDiagnostics>(&params);
             msg_sender.send(RawMessage::Notification(not)).unwrap();
@@ -387,14 +815,272 @@ fn on_notification(
         }
         Err(not) => not,
     };
+    let not = match not.cast::<req::DidSaveTextDocument>() {
+        Ok(params) => {
+            let uri = params.text_document.uri;
+            let path = state.uri_to_path(&uri)?;
+            let file_id = state
+                .vfs
+                .read()
+                .path2file(&path)
+                .map(|it| FileId(it.0.into()));
+            // Only open documents have an overlay; the text of the others is
+            // read from disk.
+            let is_open = state.document_versions.version(&uri).is_some();
+            if let (Some(file_id), Some(text), true) = (file_id, params.text, is_open) {
+                // The included text should match ours, unless we have missed
+                // some changes.
+                if state.analysis_host.analysis().file_text(file_id) != text {
+                    state.vfs.write().set_overlay(&path, text);
+                }
+            }
+            let on_save = &state.config.on_save;
+            let has_cargo_workspace = state.workspaces.iter().any(|ws| ws.cargo().is_some());
+            if on_save.cargo_check && has_cargo_workspace {
+                // If the queue is full, a check is pending already and will
+                // see this save as well.
+                let _ = check_worker.inp.try_send(state.root.clone());
+            }
+            match file_id {
+                Some(file_id) if on_save.reindex_symbols => {
+                    let world = state.snapshot();
+                    pool.execute(move || {
+                        // Canceled indexing is fine: the symbols are indexed
+                        // lazily.
+                        let _ = world.analysis().prime_symbols(file_id);
+                    });
+                }
+                _ => (),
+            }
+            return Ok(());
+        }
+        Err(not) => not,
+    };
+    let not = match not.cast::<req::DidChangeConfiguration>() {
+        Ok(params) => {
+            if params.settings.is_null() {
+                // The client does not push the settings, so pull them.
+                let id = outgoing.next_id();
+                let params = req::ConfigurationParams {
+                    items: vec![req::ConfigurationItem {
+                        section: Some(CONFIG_SECTION.to_string()),
+                    }],
+                };
+                let request = RawRequest::new::<req::WorkspaceConfiguration>(id, &params);
+                msg_sender.send(RawMessage::Request(request)).unwrap();
+                outgoing.config.insert(id);
+            } else {
+                let settings = match params.settings.get(CONFIG_SECTION) {
+                    Some(it) => it.clone(),
+                    None => params.settings,
+                };
+                update_config(state, settings);
+            }
+            return Ok(());
+        }
+        Err(not) => not,
+    };
+    let not = match not.cast::<req::DidChangeWatchedFiles>() {
+        Ok(params) => {
+            let manifest_changed = params.changes.iter().any(|change| {
+                let file_name = change
+                    .uri
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back());
+                match file_name {
+                    Some(name) => MANIFEST_FILES.contains(&name),
+                    None => false,
+                }
+            });
+            if manifest_changed {
+                // If the queue is full, a reload is pending already and will
+                // pick up this change as well.
+                let _ = ws_worker.inp.try_send(state.root.clone());
+            }
+            let mut vfs = state.vfs.write();
+            for change in params.changes.iter() {
+                if let Some(path) = uri_to_file_path(&change.uri) {
+                    vfs.notify_changed(&path);
+                }
+            }
+            return Ok(());
+        }
+        Err(not) => not,
+    };
     log::error!("unhandled notification: {:?}", not);
     Ok(())
 }
 
+/// Files which affect the project model: changing any of them triggers a
+/// reload of the workspace.
+const MANIFEST_FILES: &[&str] = &[
+    "Cargo.toml",
+    "Cargo.lock",
+    "rust-toolchain",
+    "rust-project.json",
+];
+
+/// Asks the client to notify us about changes to the manifests, which the
+/// VFS does not track.
+fn register_manifest_watchers(msg_sender: &Sender<RawMessage>, outgoing: &mut OutgoingRequests) {
+    let watchers = MANIFEST_FILES
+        .iter()
+        .map(|name| req::FileSystemWatcher {
+            glob_pattern: format!("**/{}", name),
+            kind: None,
+        })
+        .collect();
+    let options = req::DidChangeWatchedFilesRegistrationOptions { watchers };
+    let id = outgoing.next_id();
+    let params = req::RegistrationParams {
+        registrations: vec![req::Registration {
+            id: "manifest-watcher".to_string(),
+            method: <req::DidChangeWatchedFiles as req::Notification>::METHOD.to_string(),
+            register_options: Some(serde_json::to_value(options).unwrap()),
+        }],
+    };
+    let request = RawRequest::new::<req::RegisterCapability>(id, &params);
+    msg_sender.send(RawMessage::Request(request)).unwrap();
+    outgoing.registrations.insert(id);
+}
+
+/// The section of the client's settings which holds `ServerConfig`.
+const CONFIG_SECTION: &str = "ra-lsp";
+
+fn on_config_response(state: &mut ServerWorldState, resp: RawResponse) {
+    if let Some(err) = resp.error {
+        log::error!("failed to fetch the configuration: {:?}", err);
+        return;
+    }
+    let settings = resp
+        .result
+        .and_then(|it| serde_json::from_value::<Vec<serde_json::Value>>(it).ok())
+        .and_then(|it| it.into_iter().next());
+    match settings {
+        Some(settings) => update_config(state, settings),
+        None => log::error!("invalid configuration response"),
+    }
+}
+
+fn update_config(state: &mut ServerWorldState, settings: serde_json::Value) {
+    let mut config = ServerConfig::clone(&state.config);
+    if let Err(e) = config.update(settings) {
+        log::error!("invalid configuration: {}", e);
+        return;
+    }
+    if config.exclude_globs != state.config.exclude_globs
+        || config.cargo_features != state.config.cargo_features
+    {
+        log::info!("changes to excluded files and cargo features apply after a restart");
+    }
+    state.config = Arc::new(config);
+}
+
+/// Applies incremental changes from `textDocument/didChange` to the text of
+/// the document, in order.
+fn apply_document_changes(
+    text: &mut Rope,
+    changes: Vec<TextDocumentContentChangeEvent>,
+    encoding: OffsetEncoding,
+) {
+    for change in changes {
+        match change.range {
+            Some(range) => {
+                let start = position_to_char(text, range.start, encoding);
+                let end = position_to_char(text, range.end, encoding);
+                text.remove(start..end);
+                text.insert(start, &change.text);
+            }
+            None => *text = Rope::from(change.text),
+        }
+    }
+}
+
+/// Converts an LSP position to a char index. The column is counted in the
+/// units of the negotiated `encoding`.
+fn position_to_char(text: &Rope, position: Position, encoding: OffsetEncoding) -> usize {
+    let line = (position.line as usize).min(text.len_lines() - 1);
+    let col = position.character as usize;
+    match encoding {
+        OffsetEncoding::Utf8 => {
+            let offset = text.line_to_byte(line) + col;
+            text.byte_to_char(offset.min(text.len_bytes()))
+        }
+        OffsetEncoding::Utf16 => {
+            let line_start = text.char_to_utf16_cu(text.line_to_char(line));
+            let offset = line_start + col;
+            text.utf16_cu_to_char(offset.min(text.len_utf16_cu()))
+        }
+        OffsetEncoding::Utf32 => (text.line_to_char(line) + col).min(text.len_chars()),
+    }
+}
+
+/// The thread pools of the server. Requests which the user waits for while
+/// typing run on their own threads, so that they don't queue up behind heavy
+/// requests, diagnostics and indexing.
+struct Pools {
+    latency: ThreadPool,
+    heavy: ThreadPool,
+}
+
+/// Requests which were sent to the thread pools, and which haven't been
+/// answered yet.
+type PendingRequests = FxHashMap<u64, Arc<TaskState>>;
+
+/// The progress of a request on a thread pool. It is shared by the main
+/// loop and the task, so that canceling the request stops the task.
+#[derive(Debug, Default)]
+struct TaskState {
+    state: AtomicUsize,
+    /// Cancels the queries of the task once it is running. `None` if the
+    /// request is answered without a task.
+    token: Option<CancelationToken>,
+}
+
+impl TaskState {
+    const QUEUED: usize = 0;
+    const RUNNING: usize = 1;
+    const FINISHED: usize = 2;
+    const CANCELED: usize = 3;
+
+    fn new(token: CancelationToken) -> TaskState {
+        TaskState {
+            state: AtomicUsize::new(TaskState::QUEUED),
+            token: Some(token),
+        }
+    }
+
+    /// Called by the task before it runs. Returns `false` if the request
+    /// has been canceled while it was queued.
+    fn start(&self) -> bool {
+        self.state
+            .compare_exchange(
+                TaskState::QUEUED,
+                TaskState::RUNNING,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+    }
+
+    fn finish(&self) {
+        self.state.store(TaskState::FINISHED, Ordering::SeqCst);
+    }
+
+    /// Marks the request as canceled, so that the task doesn't run if it is
+    /// still queued, and stops at the next query if it is already running.
+    fn cancel(&self) {
+        self.state.store(TaskState::CANCELED, Ordering::SeqCst);
+        if let Some(token) = &self.token {
+            token.cancel();
+        }
+    }
+}
+
 struct PoolDispatcher<'a> {
     req: Option<RawRequest>,
-    res: Option<u64>,
-    pool: &'a ThreadPool,
+    res: Option<(u64, Arc<TaskState>)>,
+    pools: &'a Pools,
     world: &'a ServerWorldState,
     sender: &'a Sender<Task>,
 }
@@ -404,6 +1090,33 @@ impl<'a> PoolDispatcher<'a> {
         &'b mut self,
         f: fn(ServerWorld, R::Params) -> Result<R::Result>,
     ) -> Result<&'b mut Self>
+    where
+        R: req::Request,
+        R::Params: DeserializeOwned + Send + 'static,
+        R::Result: Serialize + 'static,
+    {
+        let pool = &self.pools.heavy;
+        self.on_pool::<R>(pool, f)
+    }
+
+    fn on_latency_sensitive<'b, R>(
+        &'b mut self,
+        f: fn(ServerWorld, R::Params) -> Result<R::Result>,
+    ) -> Result<&'b mut Self>
+    where
+        R: req::Request,
+        R::Params: DeserializeOwned + Send + 'static,
+        R::Result: Serialize + 'static,
+    {
+        let pool = &self.pools.latency;
+        self.on_pool::<R>(pool, f)
+    }
+
+    fn on_pool<'b, R>(
+        &'b mut self,
+        pool: &ThreadPool,
+        f: fn(ServerWorld, R::Params) -> Result<R::Result>,
+    ) -> Result<&'b mut Self>
     where
         R: req::Request,
         R::Params: DeserializeOwned + Send + 'static,
@@ -413,45 +1126,81 @@ impl<'a> PoolDispatcher<'a> {
             None => return Ok(self),
             Some(req) => req,
         };
+        if req.method != R::METHOD {
+            self.req = Some(req);
+            return Ok(self);
+        }
+        if !self.world.config.features.allows(R::METHOD) {
+            let resp = RawResponse {
+                id: req.id,
+                result: Some(serde_json::Value::Null),
+                error: None,
+            };
+            self.sender.send(Task::Respond(resp)).unwrap();
+            self.res = Some((req.id, Arc::new(TaskState::default())));
+            return Ok(self);
+        }
+        // The version of the document at the time of the request. If the
+        // document changes before the request is executed, the result would
+        // be useless.
+        let document = req
+            .params
+            .pointer("/textDocument/uri")
+            .and_then(|it| it.as_str())
+            .and_then(|it| Url::parse(it).ok())
+            .map(|uri| {
+                let version = self.world.document_versions.version(&uri);
+                (uri, version)
+            });
         match req.cast::<R>() {
             Ok((id, params)) => {
                 let world = self.world.snapshot();
                 let sender = self.sender.clone();
-                self.pool.execute(move || {
-                    let resp = match f(world, params) {
-                        Ok(resp) => RawResponse::ok::<R>(id, &resp),
-                        Err(e) => match e.downcast::<LspError>() {
-                            Ok(lsp_error) => {
-                                RawResponse::err(id, lsp_error.code, lsp_error.message)
-                            }
-                            Err(e) => {
-                                if is_canceled(&e) {
-                                    RawResponse::err(
-                                        id,
-                                        ErrorCode::ContentModified as i32,
-                                        "content modified".to_string(),
-                                    )
-                                } else {
-                                    RawResponse::err(
-                                        id,
-                                        ErrorCode::InternalError as i32,
-                                        format!("{}\n{}", e, e.backtrace()),
-                                    )
-                                }
+                let versions = Arc::clone(&self.world.document_versions);
+                let task_state = Arc::new(TaskState::new(world.analysis().cancelation_token()));
+                let task = Arc::clone(&task_state);
+                pool.execute(move || {
+                    if !task.start() {
+                        return;
+                    }
+                    let is_outdated = || match &document {
+                        Some((uri, version)) => versions.version(uri) != *version,
+                        None => false,
+                    };
+                    let resp = if is_outdated() {
+                        content_modified(id)
+                    } else {
+                        // A bug in one of the handlers should not take the
+                        // whole server down.
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| f(world, params)));
+                        match result {
+                            // The document might have changed while the
+                            // handler was running.
+                            Ok(_) if is_outdated() => content_modified(id),
+                            Ok(result) => result_to_response::<R>(id, result),
+                            Err(panic) => {
+                                // The backtrace is logged by the panic hook.
+                                let message = panic_message(&*panic);
+                                log::error!("{} panicked: {}", R::METHOD, message);
+                                RawResponse::err(
+                                    id,
+                                    ErrorCode::InternalError as i32,
+                                    format!("{} panicked: {}", R::METHOD, message),
+                                )
                             }
-                        },
+                        }
                     };
-                    let task = Task::Respond(resp);
-                    sender.send(task).unwrap();
+                    task.finish();
+                    sender.send(Task::Respond(resp)).unwrap();
                 });
-                self.res = Some(id);
+                self.res = Some((id, task_state));
             }
             Err(req) => self.req = Some(req),
         }
         Ok(self)
     }
 
-    fn finish(&mut self) -> ::std::result::Result<u64, RawRequest> {
+    fn finish(&mut self) -> ::std::result::Result<(u64, Arc<TaskState>), RawRequest> {
         match (self.res.take(), self.req.take()) {
             (Some(res), None) => Ok(res),
             (None, Some(req)) => Err(req),
@@ -460,27 +1209,92 @@ impl<'a> PoolDispatcher<'a> {
     }
 }
 
+fn result_to_response<R>(id: u64, result: Result<R::Result>) -> RawResponse
+where
+    R: req::Request,
+    R::Result: Serialize,
+{
+    match result {
+        Ok(resp) => RawResponse::ok::<R>(id, &resp),
+        Err(e) => match e.downcast::<LspError>() {
+            Ok(lsp_error) => RawResponse::err(id, lsp_error.code, lsp_error.message),
+            Err(e) => {
+                if is_canceled(&e) {
+                    content_modified(id)
+                } else {
+                    RawResponse::err(
+                        id,
+                        ErrorCode::InternalError as i32,
+                        format!("{}\n{}", e, e.backtrace()),
+                    )
+                }
+            }
+        },
+    }
+}
+
+/// The answer to a request whose result would be computed against an
+/// outdated text.
+fn content_modified(id: u64) -> RawResponse {
+    RawResponse::err(
+        id,
+        ErrorCode::ContentModified as i32,
+        "content modified".to_string(),
+    )
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Directory where symbol indices of libraries are cached between runs.
+fn symbol_cache_dir() -> Option<PathBuf> {
+    let cache_home = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache_home.join("rust-analyzer").join("symbols"))
+}
+
 fn update_file_notifications_on_threadpool(
     pool: &ThreadPool,
     world: ServerWorld,
-    publish_decorations: bool,
+    versions: &DocumentVersions,
     sender: Sender<Task>,
     subscriptions: Vec<FileId>,
 ) {
+    // The versions of the documents in the snapshot, which the diagnostics
+    // are computed against.
+    let versions: Vec<Option<u64>> = subscriptions
+        .iter()
+        .map(|&file_id| {
+            world
+                .file_id_to_uri(file_id)
+                .ok()
+                .and_then(|uri| versions.version(&uri))
+        })
+        .collect();
     pool.execute(move || {
-        for file_id in subscriptions {
-            match handlers::publish_diagnostics(&world, file_id) {
-                Err(e) => {
-                    if !is_canceled(&e) {
-                        log::error!("failed to compute diagnostics: {:?}", e);
+        match world.analysis().diagnostics_for_files(&subscriptions) {
+            Err(e) => log::debug!("diagnostics canceled: {:?}", e),
+            Ok(diagnostics) => {
+                let files = subscriptions.iter().zip(versions).zip(diagnostics);
+                for ((&file_id, version), diagnostics) in files {
+                    match handlers::publish_diagnostics(&world, file_id, version, diagnostics) {
+                        Err(e) => log::error!("failed to publish diagnostics: {:?}", e),
+                        Ok(params) => sender.send(Task::PublishDiagnostics(params)).unwrap(),
                     }
                 }
-                Ok(params) => {
-                    let not = RawNotification::new::<req::PublishDiagnostics>(&params);
-                    sender.send(Task::Notify(not)).unwrap();
-                }
             }
-            if publish_decorations {
+        }
+        for file_id in subscriptions {
+            if world.config.publish_decorations {
                 match handlers::publish_decorations(&world, file_id) {
                     Err(e) => {
                         if !is_canceled(&e) {
@@ -508,3 +1322,174 @@ fn feedback(intrnal_mode: bool, msg: &str, sender: &Sender<RawMessage>) {
 fn is_canceled(e: &failure::Error) -> bool {
     e.downcast_ref::<Canceled>().is_some()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use languageserver_types::{TextDocumentIdentifier, TextDocumentPositionParams};
+    use ra_analysis::Query;
+
+    use super::*;
+
+    static HOVER_STARTED: AtomicBool = AtomicBool::new(false);
+    static HOVER_MAY_FINISH: AtomicBool = AtomicBool::new(false);
+    static SEARCH_STARTED: AtomicBool = AtomicBool::new(false);
+    static SEARCH_MAY_FINISH: AtomicBool = AtomicBool::new(false);
+
+    fn hover(
+        _world: ServerWorld,
+        _params: TextDocumentPositionParams,
+    ) -> Result<Option<req::Hover>> {
+        Ok(None)
+    }
+
+    fn unreachable_hover(
+        _world: ServerWorld,
+        _params: TextDocumentPositionParams,
+    ) -> Result<Option<req::Hover>> {
+        panic!("the request should have been dropped")
+    }
+
+    /// Like `hover`, but waits for the test before answering.
+    fn slow_hover(
+        _world: ServerWorld,
+        _params: TextDocumentPositionParams,
+    ) -> Result<Option<req::Hover>> {
+        HOVER_STARTED.store(true, Ordering::SeqCst);
+        while !HOVER_MAY_FINISH.load(Ordering::SeqCst) {
+            thread::yield_now();
+        }
+        Ok(None)
+    }
+
+    /// Waits for the test, then searches the symbols of the world.
+    fn slow_symbol_search(
+        world: ServerWorld,
+        _params: TextDocumentPositionParams,
+    ) -> Result<Option<req::Hover>> {
+        SEARCH_STARTED.store(true, Ordering::SeqCst);
+        while !SEARCH_MAY_FINISH.load(Ordering::SeqCst) {
+            thread::yield_now();
+        }
+        world.analysis().symbol_search(Query::new(String::new()))?;
+        Ok(None)
+    }
+
+    /// Sends a hover request for `uri` to the latency-sensitive pool, which
+    /// has a single thread.
+    fn dispatch(
+        pools: &Pools,
+        world: &ServerWorldState,
+        sender: &Sender<Task>,
+        uri: &Url,
+        f: fn(ServerWorld, TextDocumentPositionParams) -> Result<Option<req::Hover>>,
+    ) -> Arc<TaskState> {
+        let params = TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri.clone()),
+            Position::new(0, 0),
+        );
+        let mut pool_dispatcher = PoolDispatcher {
+            req: Some(RawRequest::new::<req::HoverRequest>(1, &params)),
+            res: None,
+            pools,
+            world,
+            sender,
+        };
+        pool_dispatcher
+            .on_latency_sensitive::<req::HoverRequest>(f)
+            .unwrap();
+        let (_id, task_state) = pool_dispatcher.finish().unwrap();
+        task_state
+    }
+
+    fn check_content_modified(receiver: &Receiver<Task>) {
+        match receiver.recv_timeout(Duration::from_secs(10)) {
+            Ok(Task::Respond(resp)) => {
+                let error = resp.error.expect("expected an error");
+                assert_eq!(error.code, ErrorCode::ContentModified as i32);
+            }
+            _ => panic!("expected a response"),
+        }
+    }
+
+    fn with_world(f: impl FnOnce(&Pools, &ServerWorldState, &Sender<Task>, &Receiver<Task>)) {
+        let dir = tempfile::tempdir().unwrap();
+        let world = ServerWorldState::new(
+            dir.path().to_path_buf(),
+            Vec::new(),
+            ServerConfig::default(),
+            ClientCaps::default(),
+        );
+        let pools = Pools {
+            latency: ThreadPool::new(1),
+            heavy: ThreadPool::new(1),
+        };
+        let (sender, receiver) = unbounded();
+        f(&pools, &world, &sender, &receiver);
+        // The tasks hold snapshots of the world until they are done.
+        pools.latency.join();
+        let vfs = Arc::try_unwrap(world.vfs).unwrap().into_inner();
+        vfs.shutdown().unwrap();
+    }
+
+    #[test]
+    fn requests_are_content_modified_if_the_document_changes_before_they_start() {
+        with_world(|pools, world, sender, receiver| {
+            let uri = Url::parse("file:///lib.rs").unwrap();
+            world.document_versions.set(&uri, Some(1));
+            // Keep the only thread of the pool busy, so that the request
+            // stays queued until the document has changed.
+            let (unblock, blocked) = bounded::<()>(0);
+            pools.latency.execute(move || blocked.recv().unwrap());
+            dispatch(pools, world, sender, &uri, unreachable_hover);
+            world.document_versions.set(&uri, Some(2));
+            unblock.send(()).unwrap();
+            check_content_modified(receiver);
+        });
+    }
+
+    #[test]
+    fn requests_are_content_modified_if_the_document_changes_while_they_run() {
+        with_world(|pools, world, sender, receiver| {
+            let uri = Url::parse("file:///lib.rs").unwrap();
+            world.document_versions.set(&uri, Some(1));
+            dispatch(pools, world, sender, &uri, slow_hover);
+            while !HOVER_STARTED.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+            world.document_versions.set(&uri, Some(2));
+            HOVER_MAY_FINISH.store(true, Ordering::SeqCst);
+            check_content_modified(receiver);
+        });
+    }
+
+    #[test]
+    fn canceling_a_running_request_cancels_its_analysis() {
+        with_world(|pools, world, sender, receiver| {
+            let uri = Url::parse("file:///lib.rs").unwrap();
+            world.document_versions.set(&uri, Some(1));
+            let task_state = dispatch(pools, world, sender, &uri, slow_symbol_search);
+            while !SEARCH_STARTED.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+            task_state.cancel();
+            SEARCH_MAY_FINISH.store(true, Ordering::SeqCst);
+            // The analysis of the task returns `Canceled`.
+            check_content_modified(receiver);
+        });
+    }
+
+    #[test]
+    fn requests_for_the_current_version_are_answered() {
+        with_world(|pools, world, sender, receiver| {
+            let uri = Url::parse("file:///lib.rs").unwrap();
+            world.document_versions.set(&uri, Some(1));
+            dispatch(pools, world, sender, &uri, hover);
+            match receiver.recv_timeout(Duration::from_secs(10)) {
+                Ok(Task::Respond(resp)) => assert!(resp.error.is_none(), "{:?}", resp),
+                _ => panic!("expected a response"),
+            }
+        });
+    }
+}
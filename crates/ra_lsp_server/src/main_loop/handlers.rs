@@ -2,20 +2,22 @@ use std::collections::HashMap;
 
 use gen_lsp_server::ErrorCode;
 use languageserver_types::{
-    CodeActionResponse, Command, Diagnostic, DiagnosticSeverity, DocumentFormattingParams,
+    code_action_kind, CodeAction, CodeActionResponse, Command, Diagnostic, DiagnosticSeverity, DocumentFormattingParams, DocumentRangeFormattingParams,
     DocumentHighlight, DocumentSymbol, Documentation, FoldingRange, FoldingRangeKind,
-    FoldingRangeParams, Hover, HoverContents, Location, MarkedString, MarkupContent, MarkupKind,
-    ParameterInformation, ParameterLabel, Position, PrepareRenameResponse, Range, RenameParams,
+    FoldingRangeParams, Hover, HoverContents, Location, MarkupContent, MarkupKind,
+    ParameterInformation, ParameterLabel, Position, PrepareRenameResponse, RenameParams,
     SignatureInformation, SymbolInformation, TextDocumentIdentifier, TextEdit, WorkspaceEdit,
 };
 use ra_analysis::{
-    FileId, FilePosition, FileRange, FoldKind, Query, RunnableKind, Severity, SourceChange,
+    Edition, FileId, FilePosition, FileRange, FoldKind, Query, Rope, Runnable, RunnableKind,
+    Severity, SourceChange,
 };
-use ra_syntax::{text_utils::intersect, TextUnit};
+use ra_syntax::{text_utils::intersect, TextRange, TextUnit};
 use ra_text_edit::text_utils::contains_offset_nonstrict;
 use rustc_hash::FxHashMap;
 use serde_json::to_value;
-use std::io::Write;
+use std::process;
+use failure::format_err;
 
 use crate::{
     conv::{to_location, Conv, ConvWith, MapConvWith, TryConvWith},
@@ -36,7 +38,7 @@ pub fn handle_extend_selection(
     params: req::ExtendSelectionParams,
 ) -> Result<req::ExtendSelectionResult> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let selections = params
         .selections
         .into_iter()
@@ -54,7 +56,7 @@ pub fn handle_find_matching_brace(
 ) -> Result<Vec<Position>> {
     let file_id = params.text_document.try_conv_with(&world)?;
     let file = world.analysis().file_syntax(file_id);
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let res = params
         .offsets
         .into_iter()
@@ -96,12 +98,13 @@ pub fn handle_on_type_formatting(
     let analysis: Option<Box<Fn(FilePosition) -> Option<SourceChange>>> = match params.ch.as_str() {
         "=" => Some(Box::new(|pos| world.analysis().on_eq_typed(pos))),
         "." => Some(Box::new(|pos| world.analysis().on_dot_typed(pos))),
+        "}" | ">" => Some(Box::new(|pos| world.analysis().on_closing_typed(pos))),
         _ => None,
     };
 
     if let Some(ana) = analysis {
         let file_id = params.text_document.try_conv_with(&world)?;
-        let line_index = world.analysis().file_line_index(file_id);
+        let line_index = world.line_index(file_id);
         let position = FilePosition {
             file_id,
             offset: params.position.conv_with(&line_index),
@@ -129,7 +132,7 @@ pub fn handle_document_symbol(
     params: req::DocumentSymbolParams,
 ) -> Result<Option<req::DocumentSymbolResponse>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
 
     let mut parents: Vec<(DocumentSymbol, Option<usize>)> = Vec::new();
 
@@ -243,7 +246,7 @@ pub fn handle_runnables(
     params: req::RunnablesParams,
 ) -> Result<Vec<req::Runnable>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let offset = params.position.map(|it| it.conv_with(&line_index));
     let mut res = Vec::new();
     for runnable in world.analysis().runnables(file_id)? {
@@ -253,24 +256,7 @@ pub fn handle_runnables(
             }
         }
 
-        let args = runnable_args(&world, file_id, &runnable.kind)?;
-
-        let r = req::Runnable {
-            range: runnable.range.conv_with(&line_index),
-            label: match &runnable.kind {
-                RunnableKind::Test { name } => format!("test {}", name),
-                RunnableKind::TestMod { path } => format!("test-mod {}", path),
-                RunnableKind::Bin => "run binary".to_string(),
-            },
-            bin: "cargo".to_string(),
-            args,
-            env: {
-                let mut m = FxHashMap::default();
-                m.insert("RUST_BACKTRACE".to_string(), "short".to_string());
-                m
-            },
-        };
-        res.push(r);
+        res.push(to_lsp_runnable(&world, file_id, &runnable)?);
     }
     let mut check_args = vec!["check".to_string()];
     let label;
@@ -292,98 +278,119 @@ pub fn handle_runnables(
         args: check_args,
         env: FxHashMap::default(),
     });
-    return Ok(res);
-
-    fn runnable_args(
-        world: &ServerWorld,
-        file_id: FileId,
-        kind: &RunnableKind,
-    ) -> Result<Vec<String>> {
-        let spec = CargoTargetSpec::for_file(world, file_id)?;
-        let mut res = Vec::new();
-        match kind {
-            RunnableKind::Test { name } => {
-                res.push("test".to_string());
-                if let Some(spec) = spec {
-                    spec.push_to(&mut res);
-                }
-                res.push("--".to_string());
-                res.push(name.to_string());
-                res.push("--nocapture".to_string());
+    Ok(res)
+}
+
+fn to_lsp_runnable(
+    world: &ServerWorld,
+    file_id: FileId,
+    runnable: &Runnable,
+) -> Result<req::Runnable> {
+    let line_index = world.line_index(file_id);
+    let args = runnable_args(world, file_id, &runnable.kind)?;
+    Ok(req::Runnable {
+        range: runnable.range.conv_with(&line_index),
+        label: match &runnable.kind {
+            RunnableKind::Test { name } => format!("test {}", name),
+            RunnableKind::TestMod { path } => format!("test-mod {}", path),
+            RunnableKind::Bin => "run binary".to_string(),
+        },
+        bin: "cargo".to_string(),
+        args,
+        env: {
+            let mut m = FxHashMap::default();
+            m.insert("RUST_BACKTRACE".to_string(), "short".to_string());
+            m
+        },
+    })
+}
+
+fn runnable_args(world: &ServerWorld, file_id: FileId, kind: &RunnableKind) -> Result<Vec<String>> {
+    let spec = CargoTargetSpec::for_file(world, file_id)?;
+    let mut res = Vec::new();
+    match kind {
+        RunnableKind::Test { name } => {
+            res.push("test".to_string());
+            if let Some(spec) = spec {
+                spec.push_to(&mut res);
             }
-            RunnableKind::TestMod { path } => {
-                res.push("test".to_string());
-                if let Some(spec) = spec {
-                    spec.push_to(&mut res);
-                }
-                res.push("--".to_string());
-                res.push(path.to_string());
-                res.push("--nocapture".to_string());
+            res.push("--".to_string());
+            res.push(name.to_string());
+            res.push("--nocapture".to_string());
+        }
+        RunnableKind::TestMod { path } => {
+            res.push("test".to_string());
+            if let Some(spec) = spec {
+                spec.push_to(&mut res);
             }
-            RunnableKind::Bin => {
-                res.push("run".to_string());
-                if let Some(spec) = spec {
-                    spec.push_to(&mut res);
-                }
+            res.push("--".to_string());
+            res.push(path.to_string());
+            res.push("--nocapture".to_string());
+        }
+        RunnableKind::Bin => {
+            res.push("run".to_string());
+            if let Some(spec) = spec {
+                spec.push_to(&mut res);
             }
         }
-        Ok(res)
     }
+    Ok(res)
+}
 
-    struct CargoTargetSpec {
-        package: String,
-        target: String,
-        target_kind: TargetKind,
-    }
+struct CargoTargetSpec {
+    package: String,
+    target: String,
+    target_kind: TargetKind,
+}
 
-    impl CargoTargetSpec {
-        fn for_file(world: &ServerWorld, file_id: FileId) -> Result<Option<CargoTargetSpec>> {
-            let &crate_id = match world.analysis().crate_for(file_id)?.first() {
-                Some(crate_id) => crate_id,
-                None => return Ok(None),
+impl CargoTargetSpec {
+    fn for_file(world: &ServerWorld, file_id: FileId) -> Result<Option<CargoTargetSpec>> {
+        let &crate_id = match world.analysis().crate_for(file_id)?.first() {
+            Some(crate_id) => crate_id,
+            None => return Ok(None),
+        };
+        let file_id = world.analysis().crate_root(crate_id)?;
+        let path = world
+            .vfs
+            .read()
+            .file2path(ra_vfs::VfsFile(file_id.0.into()));
+        let mut cargo_workspaces = world.workspaces.iter().filter_map(|ws| ws.cargo());
+        let res = cargo_workspaces.find_map(|ws| {
+            let tgt = ws.target_by_root(&path)?;
+            let res = CargoTargetSpec {
+                package: tgt.package(ws).name(ws).to_string(),
+                target: tgt.name(ws).to_string(),
+                target_kind: tgt.kind(ws),
             };
-            let file_id = world.analysis().crate_root(crate_id)?;
-            let path = world
-                .vfs
-                .read()
-                .file2path(ra_vfs::VfsFile(file_id.0.into()));
-            let res = world.workspaces.iter().find_map(|ws| {
-                let tgt = ws.target_by_root(&path)?;
-                let res = CargoTargetSpec {
-                    package: tgt.package(ws).name(ws).to_string(),
-                    target: tgt.name(ws).to_string(),
-                    target_kind: tgt.kind(ws),
-                };
-                Some(res)
-            });
-            Ok(res)
-        }
+            Some(res)
+        });
+        Ok(res)
+    }
 
-        fn push_to(self, buf: &mut Vec<String>) {
-            buf.push("--package".to_string());
-            buf.push(self.package);
-            match self.target_kind {
-                TargetKind::Bin => {
-                    buf.push("--bin".to_string());
-                    buf.push(self.target);
-                }
-                TargetKind::Test => {
-                    buf.push("--test".to_string());
-                    buf.push(self.target);
-                }
-                TargetKind::Bench => {
-                    buf.push("--bench".to_string());
-                    buf.push(self.target);
-                }
-                TargetKind::Example => {
-                    buf.push("--example".to_string());
-                    buf.push(self.target);
-                }
-                TargetKind::Lib => {
-                    buf.push("--lib".to_string());
-                }
-                TargetKind::Other => (),
+    fn push_to(self, buf: &mut Vec<String>) {
+        buf.push("--package".to_string());
+        buf.push(self.package);
+        match self.target_kind {
+            TargetKind::Bin => {
+                buf.push("--bin".to_string());
+                buf.push(self.target);
             }
+            TargetKind::Test => {
+                buf.push("--test".to_string());
+                buf.push(self.target);
+            }
+            TargetKind::Bench => {
+                buf.push("--bench".to_string());
+                buf.push(self.target);
+            }
+            TargetKind::Example => {
+                buf.push("--example".to_string());
+                buf.push(self.target);
+            }
+            TargetKind::Lib | TargetKind::ProcMacro => {
+                buf.push("--lib".to_string());
+            }
+            TargetKind::Other => (),
         }
     }
 }
@@ -402,7 +409,7 @@ pub fn handle_completion(
 ) -> Result<Option<req::CompletionResponse>> {
     let position = {
         let file_id = params.text_document.try_conv_with(&world)?;
-        let line_index = world.analysis().file_line_index(file_id);
+        let line_index = world.line_index(file_id);
         let offset = params.position.conv_with(&line_index);
         FilePosition { file_id, offset }
     };
@@ -442,7 +449,7 @@ pub fn handle_folding_range(
     params: FoldingRangeParams,
 ) -> Result<Option<Vec<FoldingRange>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
 
     let res = Some(
         world
@@ -514,45 +521,121 @@ pub fn handle_signature_help(
 pub fn handle_hover(
     world: ServerWorld,
     params: req::TextDocumentPositionParams,
-) -> Result<Option<Hover>> {
+) -> Result<Option<req::Hover>> {
     let position = params.try_conv_with(&world)?;
     let info = match world.analysis().hover(position)? {
         None => return Ok(None),
         Some(info) => info,
     };
-    let line_index = world.analysis.file_line_index(position.file_id);
+    let line_index = world.line_index(position.file_id);
     let range = info.range.conv_with(&line_index);
-    let res = Hover {
-        contents: HoverContents::Scalar(MarkedString::String(info.info)),
-        range: Some(range),
+    let contents = if world.client_caps.hover_markdown {
+        MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: info.info,
+        }
+    } else {
+        MarkupContent {
+            kind: MarkupKind::PlainText,
+            value: markdown_to_plaintext(&info.info),
+        }
+    };
+    let actions = if world.client_caps.hover_actions {
+        hover_actions(&world, position, info.range)?
+    } else {
+        Vec::new()
+    };
+    let res = req::Hover {
+        hover: Hover {
+            contents: HoverContents::Markup(contents),
+            range: Some(range),
+        },
+        actions,
     };
     Ok(Some(res))
 }
 
+/// Runs the test whose name is hovered, and shows the references of the
+/// hovered binding. The arguments of the commands are computed here, so the
+/// client just passes them on.
+fn hover_actions(
+    world: &ServerWorld,
+    position: FilePosition,
+    range: TextRange,
+) -> Result<Vec<Command>> {
+    let mut res = Vec::new();
+    let text = world.analysis().file_text(position.file_id);
+    let hovered = text.slice(
+        text.byte_to_char(range.start().to_usize())..text.byte_to_char(range.end().to_usize()),
+    );
+    for runnable in world.analysis().runnables(position.file_id)? {
+        let is_hovered = match &runnable.kind {
+            RunnableKind::Test { name } => {
+                hovered == *name && contains_offset_nonstrict(runnable.range, range.start())
+            }
+            _ => false,
+        };
+        if is_hovered {
+            let runnable = to_lsp_runnable(world, position.file_id, &runnable)?;
+            res.push(Command {
+                title: "Run test".to_string(),
+                command: "ra-lsp.runSingle".to_string(),
+                arguments: Some(vec![to_value(runnable)?]),
+            });
+        }
+    }
+    let line_index = world.line_index(position.file_id);
+    let locations: Vec<Location> = world
+        .analysis()
+        .find_all_refs(position)?
+        .into_iter()
+        .filter_map(|(file_id, range)| to_location(file_id, range, world, &line_index).ok())
+        .collect();
+    if !locations.is_empty() {
+        let uri = world.file_id_to_uri(position.file_id)?;
+        res.push(Command {
+            title: "Show references".to_string(),
+            command: "ra-lsp.showReferences".to_string(),
+            arguments: Some(vec![
+                to_value(uri.as_str())?,
+                to_value(position.offset.conv_with(&line_index))?,
+                to_value(locations)?,
+            ]),
+        });
+    }
+    Ok(res)
+}
+
+/// Removes the code fences, which clients without markdown support would show
+/// verbatim.
+fn markdown_to_plaintext(markdown: &str) -> String {
+    markdown
+        .lines()
+        .filter(|line| !line.starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Test doc comment
 pub fn handle_prepare_rename(
     world: ServerWorld,
     params: req::TextDocumentPositionParams,
 ) -> Result<Option<PrepareRenameResponse>> {
     let position = params.try_conv_with(&world)?;
-
-    // We support renaming references like handle_rename does.
-    // In the future we may want to reject the renaming of things like keywords here too.
-    let refs = world.analysis().find_all_refs(position)?;
-    let r = match refs.first() {
-        Some(r) => r,
+    let target = match world.analysis().prepare_rename(position)? {
+        Some(it) => it,
         None => return Ok(None),
     };
-    let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
-    let loc = to_location(r.0, r.1, &world, &line_index)?;
-
-    Ok(Some(PrepareRenameResponse::Range(loc.range)))
+    let line_index = world.line_index(position.file_id);
+    Ok(Some(PrepareRenameResponse::RangeWithPlaceholder {
+        range: target.range.conv_with(&line_index),
+        placeholder: target.info,
+    }))
 }
 
 pub fn handle_rename(world: ServerWorld, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let offset = params.position.conv_with(&line_index);
 
     if params.new_name.is_empty() {
@@ -591,7 +674,7 @@ pub fn handle_references(
     params: req::ReferenceParams,
 ) -> Result<Option<Vec<Location>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let offset = params.position.conv_with(&line_index);
 
     let refs = world
@@ -610,33 +693,72 @@ pub fn handle_formatting(
     params: DocumentFormattingParams,
 ) -> Result<Option<Vec<TextEdit>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let file = world.analysis().file_text(file_id);
+    let line_index = world.line_index(file_id);
+    let edit = rustfmt_edit(&world, file_id)?;
+    Ok(Some(edit.conv_with(&line_index)))
+}
 
-    let file_line_index = world.analysis().file_line_index(file_id);
-    let end_position = TextUnit::of_str(&file).conv_with(&file_line_index);
+pub fn handle_range_formatting(
+    world: ServerWorld,
+    params: DocumentRangeFormattingParams,
+) -> Result<Option<Vec<TextEdit>>> {
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let line_index = world.line_index(file_id);
+    let range = params.range.conv_with(&line_index);
+    // Stable rustfmt can't format a part of a file, so we format the whole
+    // file and keep only the changes which touch the range.
+    let edit = rustfmt_edit(&world, file_id)?;
+    let edits = edit
+        .as_atoms()
+        .iter()
+        .filter(|atom| intersect(atom.delete, range).is_some())
+        .map_conv_with(&line_index)
+        .collect();
+    Ok(Some(edits))
+}
+
+/// Runs `rustfmt` on the file, and returns the minimal edit which turns the
+/// current text into the formatted one.
+fn rustfmt_edit(world: &ServerWorld, file_id: FileId) -> Result<ra_text_edit::TextEdit> {
+    let text = world.analysis().file_text(file_id);
 
-    use std::process;
-    let mut rustfmt = process::Command::new("rustfmt")
+    let mut rustfmt = process::Command::new("rustfmt");
+    if let Some(&crate_id) = world.analysis().crate_for(file_id)?.first() {
+        let edition = match world.analysis().crate_edition(crate_id)? {
+            Edition::Edition2015 => "2015",
+            Edition::Edition2018 => "2018",
+        };
+        rustfmt.arg("--edition").arg(edition);
+    }
+    // When reading stdin, rustfmt looks for `rustfmt.toml` starting from the
+    // current directory.
+    if let Some(dir) = world.file_id_to_path(file_id).parent() {
+        if dir.is_dir() {
+            rustfmt.current_dir(dir);
+        }
+    }
+    let mut rustfmt = rustfmt
         .stdin(process::Stdio::piped())
         .stdout(process::Stdio::piped())
-        .spawn()?;
-
-    rustfmt.stdin.as_mut().unwrap().write_all(file.as_bytes())?;
+        .stderr(process::Stdio::piped())
+        .spawn()
+        .map_err(|err| format_err!("failed to run rustfmt: {}", err))?;
+    text.write_to(rustfmt.stdin.as_mut().unwrap())?;
 
     let output = rustfmt.wait_with_output()?;
-    let captured_stdout = String::from_utf8(output.stdout)?;
     if !output.status.success() {
-        failure::bail!(
-            "rustfmt exited with error code {}: {}.",
-            output.status,
-            captured_stdout,
-        );
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // rustfmt exits with 1 if it can't parse the file.
+        let message = if output.status.code() == Some(1) {
+            format!("rustfmt failed to parse the file: {}", stderr.trim())
+        } else {
+            format!("rustfmt exited with {}: {}", output.status, stderr.trim())
+        };
+        return Err(LspError::new(ErrorCode::InternalError as i32, message).into());
     }
+    let formatted = String::from_utf8(output.stdout)?;
 
-    Ok(Some(vec![TextEdit {
-        range: Range::new(Position::new(0, 0), end_position),
-        new_text: captured_stdout,
-    }]))
+    Ok(ra_text_edit::TextEdit::diff(&text.to_string(), &formatted))
 }
 
 pub fn handle_code_action(
@@ -644,34 +766,63 @@ pub fn handle_code_action(
     params: req::CodeActionParams,
 ) -> Result<Option<CodeActionResponse>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let range = params.range.conv_with(&line_index);
 
     let assists = world
         .analysis()
         .assists(FileRange { file_id, range })?
-        .into_iter();
+        .into_iter()
+        .map(|(kind, assist)| (kind.conv(), assist));
     let fixes = world
         .analysis()
         .diagnostics(file_id)?
         .into_iter()
         .filter_map(|d| Some((d.range, d.fix?)))
         .filter(|(diag_range, _fix)| intersect(*diag_range, range).is_some())
-        .map(|(_range, fix)| fix);
-
-    let mut res = Vec::new();
-    for source_edit in assists.chain(fixes) {
+        .map(|(_range, fix)| (code_action_kind::QUICKFIX, fix));
+    let organize_imports = world
+        .analysis()
+        .organize_imports(file_id)
+        .map(|edit| (code_action_kind::SOURCE_ORGANIZE_IMPORTS, edit));
+
+    let only = params.context.only.unwrap_or_default();
+    let mut commands = Vec::new();
+    let mut actions = Vec::new();
+    for (kind, source_edit) in assists.chain(fixes).chain(organize_imports) {
+        // Kinds are hierarchical, `refactor` includes `refactor.extract`.
+        let is_requested = only.is_empty()
+            || only
+                .iter()
+                .any(|it| kind == it || kind.starts_with(&format!("{}.", it)));
+        if !is_requested {
+            continue;
+        }
         let title = source_edit.label.clone();
         let edit = source_edit.try_conv_with(&world)?;
         let cmd = Command {
-            title,
-            command: "ra-lsp.applySourceChange".to_string(),
+            title: title.clone(),
+            command: req::APPLY_SOURCE_CHANGE_COMMAND.to_string(),
             arguments: Some(vec![to_value(edit).unwrap()]),
         };
-        res.push(cmd);
+        if world.client_caps.code_action_literals {
+            actions.push(CodeAction {
+                title,
+                kind: Some(kind.to_string()),
+                diagnostics: None,
+                edit: None,
+                command: Some(cmd),
+            });
+        } else {
+            commands.push(cmd);
+        }
     }
 
-    Ok(Some(CodeActionResponse::Commands(res)))
+    if world.client_caps.code_action_literals {
+        Ok(Some(CodeActionResponse::Actions(actions)))
+    } else {
+        Ok(Some(CodeActionResponse::Commands(commands)))
+    }
 }
 
 pub fn handle_document_highlight(
@@ -679,7 +830,7 @@ pub fn handle_document_highlight(
     params: req::TextDocumentPositionParams,
 ) -> Result<Option<Vec<DocumentHighlight>>> {
     let file_id = params.text_document.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
 
     let refs = world
         .analysis()
@@ -698,12 +849,12 @@ pub fn handle_document_highlight(
 pub fn publish_diagnostics(
     world: &ServerWorld,
     file_id: FileId,
+    version: Option<u64>,
+    diagnostics: Vec<ra_analysis::Diagnostic>,
 ) -> Result<req::PublishDiagnosticsParams> {
     let uri = world.file_id_to_uri(file_id)?;
-    let line_index = world.analysis().file_line_index(file_id);
-    let diagnostics = world
-        .analysis()
-        .diagnostics(file_id)?
+    let line_index = world.line_index(file_id);
+    let mut diagnostics: Vec<Diagnostic> = diagnostics
         .into_iter()
         .map(|d| Diagnostic {
             range: d.range.conv_with(&line_index),
@@ -714,7 +865,59 @@ pub fn publish_diagnostics(
             related_information: None,
         })
         .collect();
-    Ok(req::PublishDiagnosticsParams { uri, diagnostics })
+    // Turning `cargo check` off hides the diagnostics of the previous runs.
+    let check_diagnostics = world
+        .check_diagnostics
+        .get(&file_id)
+        .filter(|_| world.config.on_save.cargo_check);
+    if let Some(check_diagnostics) = check_diagnostics {
+        let text = world.analysis().file_text(file_id);
+        diagnostics.extend(check_diagnostics.iter().map(|d| {
+            let range = TextRange::from_to(
+                char_position_to_offset(&text, d.start),
+                char_position_to_offset(&text, d.end),
+            );
+            Diagnostic {
+                range: range.conv_with(&line_index),
+                severity: Some(d.severity),
+                code: None,
+                source: Some("rustc".to_string()),
+                message: d.message.clone(),
+                related_information: None,
+            }
+        }));
+    }
+    Ok(req::PublishDiagnosticsParams {
+        uri,
+        diagnostics,
+        version,
+    })
+}
+
+/// Converts a zero-based line and a column in chars to an offset in `text`.
+/// The text might have changed since the position was computed, so positions
+/// past the end of a line or of the file are clamped.
+fn char_position_to_offset(text: &Rope, (line, col): (u32, u32)) -> TextUnit {
+    let mut offset = 0;
+    let mut chars = text.chars();
+    for _ in 0..line {
+        loop {
+            let c = match chars.next() {
+                Some(c) => c,
+                None => return TextUnit::from_usize(offset),
+            };
+            offset += c.len_utf8();
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+    let col: usize = chars
+        .take_while(|&c| c != '\n')
+        .take(col as usize)
+        .map(char::len_utf8)
+        .sum();
+    TextUnit::from_usize(offset + col)
 }
 
 pub fn publish_decorations(
@@ -729,7 +932,7 @@ pub fn publish_decorations(
 }
 
 fn highlight(world: &ServerWorld, file_id: FileId) -> Result<Vec<Decoration>> {
-    let line_index = world.analysis().file_line_index(file_id);
+    let line_index = world.line_index(file_id);
     let res = world
         .analysis()
         .highlight(file_id)?
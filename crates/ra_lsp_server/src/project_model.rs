@@ -1,12 +1,136 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-use cargo_metadata::{metadata_run, CargoOpt};
+use cargo_metadata::{metadata_run, CargoOpt, DependencyKind};
+use ra_analysis::{CfgOptions, Edition, Env};
 use ra_syntax::SmolStr;
 use rustc_hash::{FxHashMap, FxHashSet};
 use failure::{format_err, bail};
 use thread_worker::{WorkerHandle, Worker};
 
-use crate::Result;
+use crate::{
+    cargo_check::{build_script_outputs, BuildScriptOutput},
+    CargoFeatures, Result,
+};
+
+mod json_project;
+mod sysroot;
+
+pub use self::{json_project::JsonProject, sysroot::Sysroot};
+
+/// The project model of the workspace: either a Cargo workspace, or the
+/// crates listed in `rust-project.json`, for other build systems. Both depend
+/// on the standard library of the sysroot.
+#[derive(Debug, Clone)]
+pub enum ProjectWorkspace {
+    Cargo {
+        cargo: CargoWorkspace,
+        sysroot: Sysroot,
+    },
+    Json {
+        project: JsonProject,
+        sysroot: Sysroot,
+    },
+}
+
+impl ProjectWorkspace {
+    /// Loads the project at `path`. A `rust-project.json` in the directory or
+    /// in one of its parents is preferred to a Cargo workspace.
+    pub fn discover(path: &Path, cargo_features: &CargoFeatures) -> Result<ProjectWorkspace> {
+        let res = match find_rust_project_json(path) {
+            Some(project_json) => ProjectWorkspace::Json {
+                project: JsonProject::load(&project_json)?,
+                sysroot: load_sysroot(),
+            },
+            None => ProjectWorkspace::Cargo {
+                cargo: CargoWorkspace::from_cargo_metadata(path, cargo_features)?,
+                sysroot: load_sysroot(),
+            },
+        };
+        Ok(res)
+    }
+
+    /// The directories with the sources of all crates of the workspace,
+    /// including the standard library. Each package gets its own root.
+    pub fn roots(&self) -> Vec<PackageRoot> {
+        let mut res = match self {
+            ProjectWorkspace::Cargo { cargo, .. } => {
+                let mut res = Vec::new();
+                for pkg in cargo.packages() {
+                    let is_member = pkg.is_member(cargo);
+                    res.push(PackageRoot::new(pkg.root(cargo), is_member));
+                    res.extend(pkg.out_dir(cargo).map(|it| PackageRoot::new(it, is_member)));
+                }
+                res
+            }
+            ProjectWorkspace::Json { project, .. } => project
+                .roots()
+                .into_iter()
+                .map(|it| PackageRoot::new(&it, true))
+                .collect(),
+        };
+        let sysroot = self.sysroot();
+        res.extend(
+            sysroot
+                .crates()
+                .map(|it| PackageRoot::new(it.root_dir(sysroot), false)),
+        );
+        res
+    }
+
+    pub fn cargo(&self) -> Option<&CargoWorkspace> {
+        match self {
+            ProjectWorkspace::Cargo { cargo, .. } => Some(cargo),
+            ProjectWorkspace::Json { .. } => None,
+        }
+    }
+
+    pub fn sysroot(&self) -> &Sysroot {
+        match self {
+            ProjectWorkspace::Cargo { sysroot, .. } | ProjectWorkspace::Json { sysroot, .. } => {
+                sysroot
+            }
+        }
+    }
+}
+
+/// A directory with the sources of a package, which becomes a source root.
+/// The roots of the workspace members are local, and their files are expected
+/// to change; the other ones are libraries.
+#[derive(Debug, Clone)]
+pub struct PackageRoot {
+    pub path: PathBuf,
+    pub is_member: bool,
+}
+
+impl PackageRoot {
+    pub fn new(path: &Path, is_member: bool) -> PackageRoot {
+        PackageRoot {
+            path: ra_vfs::normalize_path(path),
+            is_member,
+        }
+    }
+}
+
+/// A missing sysroot is not fatal: we just can't resolve the items of `std`.
+fn load_sysroot() -> Sysroot {
+    Sysroot::discover().unwrap_or_else(|err| {
+        log::warn!("failed to load the sysroot: {}", err);
+        Sysroot::default()
+    })
+}
+
+fn find_rust_project_json(path: &Path) -> Option<PathBuf> {
+    if path.ends_with("rust-project.json") {
+        return Some(path.to_path_buf());
+    }
+    path.ancestors()
+        .map(|it| it.join("rust-project.json"))
+        .find(|it| it.exists())
+}
 
 /// `CargoWorksapce` represents the logical structure of, well, a Cargo
 /// workspace. It pretty closely mirrors `cargo metadata` output.
@@ -19,6 +143,7 @@ use crate::Result;
 pub struct CargoWorkspace {
     packages: Vec<PackageData>,
     targets: Vec<TargetData>,
+    cfg_options: CfgOptions,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -29,16 +154,31 @@ pub struct Target(usize);
 #[derive(Debug, Clone)]
 struct PackageData {
     name: SmolStr,
+    version: String,
     manifest: PathBuf,
     targets: Vec<Target>,
     is_member: bool,
     dependencies: Vec<PackageDependency>,
+    features: Vec<SmolStr>,
+    build_script: BuildScriptOutput,
 }
 
 #[derive(Debug, Clone)]
 pub struct PackageDependency {
     pub pkg: Package,
     pub name: SmolStr,
+    pub kind: DepKind,
+}
+
+/// The section of `Cargo.toml` a dependency comes from. A package listed in
+/// several sections counts as a normal dependency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepKind {
+    Normal,
+    /// Used by tests, benches and examples.
+    Dev,
+    /// Used by the build script.
+    Build,
 }
 
 #[derive(Debug, Clone)]
@@ -47,12 +187,14 @@ struct TargetData {
     name: SmolStr,
     root: PathBuf,
     kind: TargetKind,
+    edition: Edition,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TargetKind {
     Bin,
     Lib,
+    ProcMacro,
     Example,
     Test,
     Bench,
@@ -79,6 +221,59 @@ impl Package {
     ) -> impl Iterator<Item = &'a PackageDependency> + 'a {
         ws.pkg(self).dependencies.iter()
     }
+    /// Features enabled for this package. For dependencies, this is the set of
+    /// all features, as cargo does not report the resolved ones.
+    pub fn features(self, ws: &CargoWorkspace) -> &[SmolStr] {
+        ws.pkg(self).features.as_slice()
+    }
+    /// cfg flags for the crates of this package: target cfgs plus enabled
+    /// features.
+    pub fn cfg_options(self, ws: &CargoWorkspace) -> CfgOptions {
+        let mut res = ws.cfg_options.clone();
+        for feature in self.features(ws) {
+            res.insert_key_value("feature".into(), feature.clone());
+        }
+        for cfg in ws.pkg(self).build_script.cfgs.iter() {
+            parse_cfg(cfg, &mut res);
+        }
+        res
+    }
+    /// The directory with the files generated by the build script, if the
+    /// workspace was loaded with `load_out_dirs_from_check`.
+    pub fn out_dir(self, ws: &CargoWorkspace) -> Option<&Path> {
+        ws.pkg(self)
+            .build_script
+            .out_dir
+            .as_ref()
+            .map(|it| it.as_path())
+    }
+    /// Environment variables which cargo sets when compiling the crates of
+    /// this package.
+    pub fn env(self, ws: &CargoWorkspace) -> Env {
+        let data = ws.pkg(self);
+        let mut env = Env::default();
+        env.set("CARGO_PKG_NAME", data.name.to_string());
+        env.set("CARGO_PKG_VERSION", data.version.clone());
+        let mut parts = data.version.split(&['.', '-'][..]);
+        for &key in [
+            "CARGO_PKG_VERSION_MAJOR",
+            "CARGO_PKG_VERSION_MINOR",
+            "CARGO_PKG_VERSION_PATCH",
+        ]
+        .iter()
+        {
+            env.set(key, parts.next().unwrap_or_default().to_string());
+        }
+        env.set("CARGO_MANIFEST_DIR", self.root(ws).display().to_string());
+        let build_script = &data.build_script;
+        if let Some(out_dir) = &build_script.out_dir {
+            env.set("OUT_DIR", out_dir.display().to_string());
+        }
+        for (key, value) in build_script.env.iter() {
+            env.set(key, value.clone());
+        }
+        env
+    }
 }
 
 impl Target {
@@ -94,17 +289,37 @@ impl Target {
     pub fn kind(self, ws: &CargoWorkspace) -> TargetKind {
         ws.tgt(self).kind
     }
+    pub fn edition(self, ws: &CargoWorkspace) -> Edition {
+        ws.tgt(self).edition
+    }
 }
 
 impl CargoWorkspace {
-    pub fn from_cargo_metadata(path: &Path) -> Result<CargoWorkspace> {
+    pub fn from_cargo_metadata(
+        path: &Path,
+        cargo_features: &CargoFeatures,
+    ) -> Result<CargoWorkspace> {
         let cargo_toml = find_cargo_toml(path)?;
-        let meta = metadata_run(
-            Some(cargo_toml.as_path()),
-            true,
-            Some(CargoOpt::AllFeatures),
-        )
-        .map_err(|e| format_err!("cargo metadata failed: {}", e))?;
+        // `cargo_metadata` accepts only one of the feature flags.
+        let opt = if cargo_features.all_features {
+            Some(CargoOpt::AllFeatures)
+        } else if !cargo_features.features.is_empty() {
+            Some(CargoOpt::SomeFeatures(cargo_features.features.clone()))
+        } else if cargo_features.no_default_features {
+            Some(CargoOpt::NoDefaultFeatures)
+        } else {
+            None
+        };
+        let meta = metadata_run(Some(cargo_toml.as_path()), true, opt)
+            .map_err(|e| format_err!("cargo metadata failed: {}", e))?;
+        let mut build_scripts = if cargo_features.load_out_dirs_from_check {
+            build_script_outputs(&cargo_toml, cargo_features).unwrap_or_else(|err| {
+                log::warn!("failed to run the build scripts: {}", err);
+                FxHashMap::default()
+            })
+        } else {
+            FxHashMap::default()
+        };
         let mut pkg_by_id = FxHashMap::default();
         let mut packages = Vec::new();
         let mut targets = Vec::new();
@@ -115,16 +330,30 @@ impl CargoWorkspace {
             .map(|it| it.raw)
             .collect();
 
+        let mut dep_kinds = Vec::new();
         for meta_pkg in meta.packages {
             let pkg = Package(packages.len());
+            dep_kinds.push(dependency_kinds(&meta_pkg.dependencies));
             let is_member = ws_members.contains(&meta_pkg.id);
             pkg_by_id.insert(meta_pkg.id.clone(), pkg);
+            let features = if is_member && !cargo_features.all_features {
+                enabled_features(&meta_pkg.features, cargo_features)
+            } else {
+                meta_pkg
+                    .features
+                    .keys()
+                    .map(|it| it.as_str().into())
+                    .collect()
+            };
             let mut pkg_data = PackageData {
                 name: meta_pkg.name.into(),
+                version: meta_pkg.version,
                 manifest: PathBuf::from(meta_pkg.manifest_path),
                 targets: Vec::new(),
                 is_member,
                 dependencies: Vec::new(),
+                features,
+                build_script: build_scripts.remove(&meta_pkg.id).unwrap_or_default(),
             };
             for meta_tgt in meta_pkg.targets {
                 let tgt = Target(targets.len());
@@ -133,6 +362,7 @@ impl CargoWorkspace {
                     name: meta_tgt.name.into(),
                     root: PathBuf::from(meta_tgt.src_path),
                     kind: TargetKind::new(meta_tgt.kind.as_slice()),
+                    edition: Edition::from_string(&meta_tgt.edition),
                 });
                 pkg_data.targets.push(tgt);
             }
@@ -143,13 +373,30 @@ impl CargoWorkspace {
             let source = pkg_by_id[&node.id];
             for id in node.dependencies {
                 let target = pkg_by_id[&id];
+                let kind = dep_kinds[source.0]
+                    .get(packages[target.0].name.as_str())
+                    .cloned()
+                    .unwrap_or(DepKind::Normal);
                 let name: SmolStr = packages[target.0].name.replace('-', "_").into();
-                let dep = PackageDependency { name, pkg: target };
+                let dep = PackageDependency {
+                    name,
+                    pkg: target,
+                    kind,
+                };
                 packages[source.0].dependencies.push(dep);
             }
         }
 
-        Ok(CargoWorkspace { packages, targets })
+        let cfg_options = rustc_cfg_options().unwrap_or_else(|err| {
+            log::warn!("failed to get target cfgs: {}", err);
+            CfgOptions::default()
+        });
+
+        Ok(CargoWorkspace {
+            packages,
+            targets,
+            cfg_options,
+        })
     }
     pub fn packages<'a>(&'a self) -> impl Iterator<Item = Package> + 'a {
         (0..self.packages.len()).map(Package)
@@ -182,6 +429,57 @@ fn find_cargo_toml(path: &Path) -> Result<PathBuf> {
     bail!("can't find Cargo.toml at {}", path.display())
 }
 
+/// Target cfgs of the host, like `unix` or `target_os = "linux"`, as reported
+/// by `rustc --print cfg`.
+fn rustc_cfg_options() -> Result<CfgOptions> {
+    let output = Command::new("rustc").arg("--print").arg("cfg").output()?;
+    if !output.status.success() {
+        bail!(
+            "rustc --print cfg failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(parse_cfg_options(&String::from_utf8(output.stdout)?))
+}
+
+/// The kinds of the dependencies of a package, by package name.
+fn dependency_kinds(deps: &[cargo_metadata::Dependency]) -> FxHashMap<String, DepKind> {
+    let mut res = FxHashMap::default();
+    for dep in deps {
+        let kind = match dep.kind {
+            DependencyKind::Development => DepKind::Dev,
+            DependencyKind::Build => DepKind::Build,
+            _ => DepKind::Normal,
+        };
+        let prev = res.entry(dep.name.clone()).or_insert(kind);
+        if *prev != kind {
+            *prev = DepKind::Normal;
+        }
+    }
+    res
+}
+
+fn parse_cfg_options(text: &str) -> CfgOptions {
+    let mut res = CfgOptions::default();
+    for line in text.lines().map(|it| it.trim()).filter(|it| !it.is_empty()) {
+        parse_cfg(line, &mut res);
+    }
+    res
+}
+
+/// Adds a cfg in the syntax of `rustc --print cfg`, `unix` or
+/// `target_os="linux"`.
+fn parse_cfg(line: &str, cfg_options: &mut CfgOptions) {
+    match line.find('=') {
+        None => cfg_options.insert_atom(line.trim().into()),
+        Some(idx) => {
+            let key = line[..idx].trim();
+            let value = line[idx + 1..].trim().trim_matches('"');
+            cfg_options.insert_key_value(key.into(), value.into());
+        }
+    }
+}
+
 impl TargetKind {
     fn new(kinds: &[String]) -> TargetKind {
         for kind in kinds {
@@ -190,6 +488,7 @@ impl TargetKind {
                 "test" => TargetKind::Test,
                 "bench" => TargetKind::Bench,
                 "example" => TargetKind::Example,
+                "proc-macro" => TargetKind::ProcMacro,
                 _ if kind.contains("lib") => TargetKind::Lib,
                 _ => continue,
             };
@@ -198,14 +497,41 @@ impl TargetKind {
     }
 }
 
-pub fn workspace_loader() -> (Worker<PathBuf, Result<CargoWorkspace>>, WorkerHandle) {
-    thread_worker::spawn::<PathBuf, Result<CargoWorkspace>, _>(
+/// Expands the features requested by the user through the feature table of a
+/// package.
+fn enabled_features(
+    declared: &HashMap<String, Vec<String>>,
+    cargo_features: &CargoFeatures,
+) -> Vec<SmolStr> {
+    let mut todo = cargo_features.features.clone();
+    if !cargo_features.no_default_features {
+        todo.push("default".to_string());
+    }
+    let mut res = FxHashSet::default();
+    while let Some(feature) = todo.pop() {
+        if let Some(implied) = declared.get(&feature) {
+            if res.insert(feature.clone()) {
+                todo.extend(implied.iter().cloned());
+            }
+        }
+    }
+    let mut res = res.into_iter().collect::<Vec<_>>();
+    res.sort();
+    res.into_iter()
+        .map(|it| SmolStr::from(it.as_str()))
+        .collect()
+}
+
+pub fn workspace_loader(
+    features: CargoFeatures,
+) -> (Worker<PathBuf, Result<ProjectWorkspace>>, WorkerHandle) {
+    thread_worker::spawn::<PathBuf, Result<ProjectWorkspace>, _>(
         "workspace loader",
         1,
-        |input_receiver, output_sender| {
+        move |input_receiver, output_sender| {
             input_receiver
                 .into_iter()
-                .map(|path| CargoWorkspace::from_cargo_metadata(path.as_path()))
+                .map(|path| ProjectWorkspace::discover(path.as_path(), &features))
                 .try_for_each(|it| output_sender.send(it))
                 .unwrap()
         },
@@ -0,0 +1,158 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use failure::bail;
+use ra_analysis::{CfgOptions, Edition, Env};
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+
+use crate::Result;
+
+/// A project which is described by a `rust-project.json` file, for the build
+/// systems other than Cargo. Unlike `CargoWorkspace`, it lists the crates
+/// directly, with the paths relative to the directory of the file.
+#[derive(Debug, Clone)]
+pub struct JsonProject {
+    roots: Vec<PathBuf>,
+    crates: Vec<CrateData>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Crate(usize);
+
+#[derive(Debug, Clone)]
+struct CrateData {
+    name: Option<String>,
+    root_module: PathBuf,
+    edition: Edition,
+    deps: Vec<Dep>,
+    cfg_options: CfgOptions,
+    env: Env,
+}
+
+#[derive(Debug, Clone)]
+pub struct Dep {
+    pub krate: Crate,
+    pub name: String,
+}
+
+/// The contents of `rust-project.json`.
+#[derive(Deserialize)]
+struct RawProject {
+    /// Directories with the sources of the crates, in addition to the
+    /// directories of the root modules.
+    #[serde(default)]
+    roots: Vec<PathBuf>,
+    crates: Vec<RawCrate>,
+}
+
+#[derive(Deserialize)]
+struct RawCrate {
+    name: Option<String>,
+    root_module: PathBuf,
+    edition: String,
+    #[serde(default)]
+    deps: Vec<RawDep>,
+    /// Like the lines of `rustc --print cfg`: `unix` or `feature="serde"`.
+    #[serde(default)]
+    cfg: Vec<String>,
+    #[serde(default)]
+    env: FxHashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct RawDep {
+    /// The index of the crate in the `crates` list.
+    #[serde(rename = "crate")]
+    krate: usize,
+    name: String,
+}
+
+impl Crate {
+    pub fn name(self, project: &JsonProject) -> Option<&str> {
+        project.krate(self).name.as_ref().map(|it| it.as_str())
+    }
+    pub fn root_module(self, project: &JsonProject) -> &Path {
+        project.krate(self).root_module.as_path()
+    }
+    pub fn edition(self, project: &JsonProject) -> Edition {
+        project.krate(self).edition
+    }
+    pub fn deps<'a>(self, project: &'a JsonProject) -> impl Iterator<Item = &'a Dep> + 'a {
+        project.krate(self).deps.iter()
+    }
+    pub fn cfg_options(self, project: &JsonProject) -> CfgOptions {
+        project.krate(self).cfg_options.clone()
+    }
+    pub fn env(self, project: &JsonProject) -> Env {
+        project.krate(self).env.clone()
+    }
+}
+
+impl JsonProject {
+    pub fn load(path: &Path) -> Result<JsonProject> {
+        let text = fs::read_to_string(path)?;
+        let raw: RawProject = serde_json::from_str(&text)?;
+        let base = path.parent().unwrap();
+        JsonProject::from_raw(base, raw)
+    }
+
+    fn from_raw(base: &Path, raw: RawProject) -> Result<JsonProject> {
+        let n_crates = raw.crates.len();
+        let mut crates = Vec::with_capacity(n_crates);
+        for krate in raw.crates {
+            let mut deps = Vec::new();
+            for dep in krate.deps {
+                if dep.krate >= n_crates {
+                    bail!("dependency {} on a missing crate {}", dep.name, dep.krate);
+                }
+                deps.push(Dep {
+                    krate: Crate(dep.krate),
+                    name: dep.name,
+                });
+            }
+            let mut cfg_options = CfgOptions::default();
+            for line in krate.cfg.iter() {
+                super::parse_cfg(line, &mut cfg_options);
+            }
+            let mut env = Env::default();
+            for (key, value) in krate.env {
+                env.set(&key, value);
+            }
+            crates.push(CrateData {
+                name: krate.name,
+                root_module: base.join(krate.root_module),
+                edition: Edition::from_string(&krate.edition),
+                deps,
+                cfg_options,
+                env,
+            });
+        }
+        let roots = raw.roots.into_iter().map(|it| base.join(it)).collect();
+        Ok(JsonProject { roots, crates })
+    }
+
+    pub fn crates<'a>(&'a self) -> impl Iterator<Item = Crate> + 'a {
+        (0..self.crates.len()).map(Crate)
+    }
+
+    /// The directories with the sources of the project: the explicitly listed
+    /// roots and the directories of the root modules.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        let mut res = self.roots.clone();
+        for krate in self.crates() {
+            if let Some(dir) = krate.root_module(self).parent() {
+                res.push(dir.to_path_buf());
+            }
+        }
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    fn krate(&self, krate: Crate) -> &CrateData {
+        &self.crates[krate.0]
+    }
+}
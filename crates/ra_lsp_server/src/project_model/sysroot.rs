@@ -0,0 +1,143 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use failure::{bail, format_err};
+use ra_analysis::CfgOptions;
+
+use crate::Result;
+
+/// The sources of the standard library crates of the active toolchain, which
+/// are a dependency of every crate of the workspace.
+#[derive(Debug, Clone, Default)]
+pub struct Sysroot {
+    crates: Vec<SysrootCrateData>,
+    cfg_options: CfgOptions,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SysrootCrate(usize);
+
+#[derive(Debug, Clone)]
+struct SysrootCrateData {
+    name: &'static str,
+    root: PathBuf,
+    deps: Vec<SysrootCrate>,
+}
+
+/// The crates we load, with their dependencies, in dependency order.
+const SYSROOT_CRATES: &[(&str, &[&str])] = &[
+    ("core", &[]),
+    ("alloc", &["core"]),
+    ("std", &["core", "alloc"]),
+];
+
+impl SysrootCrate {
+    pub fn name(self, sysroot: &Sysroot) -> &str {
+        sysroot.krate(self).name
+    }
+    pub fn root(self, sysroot: &Sysroot) -> &Path {
+        sysroot.krate(self).root.as_path()
+    }
+    pub fn root_dir(self, sysroot: &Sysroot) -> &Path {
+        self.root(sysroot).parent().unwrap()
+    }
+    pub fn deps<'a>(self, sysroot: &'a Sysroot) -> impl Iterator<Item = SysrootCrate> + 'a {
+        sysroot.krate(self).deps.iter().cloned()
+    }
+    pub fn cfg_options(self, sysroot: &Sysroot) -> CfgOptions {
+        sysroot.cfg_options.clone()
+    }
+}
+
+impl Sysroot {
+    /// Finds the sources of the standard library: `RUST_SRC_PATH` if it's
+    /// set, or the `rust-src` component of the toolchain otherwise.
+    pub fn discover() -> Result<Sysroot> {
+        let src = match env::var_os("RUST_SRC_PATH") {
+            Some(it) => PathBuf::from(it),
+            None => rust_src_dir()?,
+        };
+        if !src.exists() {
+            bail!(
+                "can't find the sources of the standard library at {}, \
+                 try `rustup component add rust-src`",
+                src.display()
+            );
+        }
+        let mut crates: Vec<SysrootCrateData> = Vec::new();
+        for &(name, deps) in SYSROOT_CRATES {
+            let root = match crate_root(&src, name) {
+                Some(it) => it,
+                None => {
+                    log::warn!("can't find `{}` in {}", name, src.display());
+                    continue;
+                }
+            };
+            let deps = deps
+                .iter()
+                .filter_map(|dep| crates.iter().position(|it| it.name == *dep))
+                .map(SysrootCrate)
+                .collect();
+            crates.push(SysrootCrateData { name, root, deps });
+        }
+        let cfg_options = super::rustc_cfg_options().unwrap_or_else(|err| {
+            log::warn!("failed to get target cfgs: {}", err);
+            CfgOptions::default()
+        });
+        Ok(Sysroot {
+            crates,
+            cfg_options,
+        })
+    }
+
+    pub fn crates<'a>(&'a self) -> impl Iterator<Item = SysrootCrate> + 'a {
+        (0..self.crates.len()).map(SysrootCrate)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<SysrootCrate> {
+        self.crates().find(|&it| it.name(self) == name)
+    }
+
+    fn krate(&self, krate: SysrootCrate) -> &SysrootCrateData {
+        &self.crates[krate.0]
+    }
+}
+
+fn rust_src_dir() -> Result<PathBuf> {
+    let output = Command::new("rustc")
+        .arg("--print")
+        .arg("sysroot")
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "rustc --print sysroot failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let sysroot = String::from_utf8(output.stdout)?;
+    let rust_src = Path::new(sysroot.trim()).join("lib/rustlib/src/rust");
+    // Newer toolchains keep the crates in `library`.
+    ["src", "library"]
+        .iter()
+        .map(|dir| rust_src.join(dir))
+        .find(|it| it.exists())
+        .ok_or_else(|| {
+            format_err!(
+                "can't find the rust-src component in {}",
+                rust_src.display()
+            )
+        })
+}
+
+/// The layout is `libcore/lib.rs` in older toolchains, and `core/src/lib.rs`
+/// in newer ones.
+fn crate_root(src: &Path, name: &str) -> Option<PathBuf> {
+    let candidates = [
+        src.join(format!("lib{}", name)).join("lib.rs"),
+        src.join(name).join("src").join("lib.rs"),
+    ];
+    candidates.iter().find(|it| it.exists()).cloned()
+}
@@ -1,16 +1,96 @@
-use languageserver_types::{Location, Position, Range, TextDocumentIdentifier, Url};
+use languageserver_types::{
+    Command, Diagnostic, InsertTextFormat, Location, Position, Range, ResourceOp, TextDocumentIdentifier,
+    Url, VersionedTextDocumentIdentifier,
+};
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use url_serde;
 
 pub use languageserver_types::{
-    notification::*, request::*, ApplyWorkspaceEditParams, CodeActionParams, CompletionParams,
-    CompletionResponse, DocumentOnTypeFormattingParams, DocumentSymbolParams,
-    DocumentSymbolResponse, ExecuteCommandParams, Hover, InitializeResult,
-    PublishDiagnosticsParams, ReferenceParams, SignatureHelp, TextDocumentEdit,
+    notification::*, request::*, ApplyWorkspaceEditParams, ApplyWorkspaceEditResponse,
+    CodeActionParams, CompletionParams, CompletionResponse, DocumentOnTypeFormattingParams,
+    DocumentSymbolParams,
+    DidChangeWatchedFilesRegistrationOptions, DocumentSymbolResponse, ExecuteCommandParams,
+    FileSystemWatcher, Registration, RegistrationParams, InitializeResult,
+    ReferenceParams, SignatureHelp, TextDocumentEdit,
     TextDocumentPositionParams, TextEdit, WorkspaceEdit, WorkspaceSymbolParams,
 };
 
+/// Like the notification of `languageserver_types`, but with the text which
+/// the client may include.
+pub enum DidSaveTextDocument {}
+
+impl Notification for DidSaveTextDocument {
+    type Params = DidSaveTextDocumentParams;
+    const METHOD: &'static str = "textDocument/didSave";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DidSaveTextDocumentParams {
+    pub text_document: TextDocumentIdentifier,
+    pub text: Option<String>,
+}
+
+/// Like the notification of `languageserver_types`, but with the version of
+/// the document which the diagnostics were computed for.
+pub enum PublishDiagnostics {}
+
+impl Notification for PublishDiagnostics {
+    type Params = PublishDiagnosticsParams;
+    const METHOD: &'static str = "textDocument/publishDiagnostics";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishDiagnosticsParams {
+    #[serde(with = "url_serde")]
+    pub uri: Url,
+    pub diagnostics: Vec<Diagnostic>,
+    /// `None` for the documents which are not open.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+/// Like the request of `languageserver_types`, but the hover may have actions,
+/// for the clients with the `experimental.hoverActions` capability.
+pub enum HoverRequest {}
+
+impl Request for HoverRequest {
+    type Params = TextDocumentPositionParams;
+    type Result = Option<Hover>;
+    const METHOD: &'static str = "textDocument/hover";
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Hover {
+    #[serde(flatten)]
+    pub hover: languageserver_types::Hover,
+    /// Commands which the client shows as links below the contents.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<Command>,
+}
+
+pub enum WorkspaceConfiguration {}
+
+impl Request for WorkspaceConfiguration {
+    type Params = ConfigurationParams;
+    type Result = Vec<serde_json::Value>;
+    const METHOD: &'static str = "workspace/configuration";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigurationParams {
+    pub items: Vec<ConfigurationItem>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigurationItem {
+    pub section: Option<String>,
+}
+
 pub enum SyntaxTree {}
 
 impl Request for SyntaxTree {
@@ -147,14 +227,110 @@ pub struct Runnable {
     pub env: FxHashMap<String, String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SourceChange {
     pub label: String,
-    pub workspace_edit: WorkspaceEdit,
+    pub workspace_edit: SnippetWorkspaceEdit,
     pub cursor_position: Option<TextDocumentPositionParams>,
 }
 
+/// A `WorkspaceEdit` with `documentChanges`, whose text edits may be snippets.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetWorkspaceEdit {
+    pub document_changes: Vec<SnippetDocumentChangeOperation>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum SnippetDocumentChangeOperation {
+    Op(ResourceOp),
+    Edit(SnippetTextDocumentEdit),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetTextDocumentEdit {
+    pub text_document: VersionedTextDocumentIdentifier,
+    pub edits: Vec<SnippetTextEdit>,
+}
+
+/// A `TextEdit` whose `newText` is a snippet if `insertTextFormat` is
+/// `Snippet`. Snippet edits are only sent to clients which set the
+/// `experimental.snippetTextEdit` capability.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetTextEdit {
+    pub range: Range,
+    pub new_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insert_text_format: Option<InsertTextFormat>,
+}
+
+/// The command which applies a `SourceChange`, its only argument. The server
+/// sends the edit back with `workspace/applyEdit`, and then moves the cursor
+/// with `MoveCursor`.
+pub const APPLY_SOURCE_CHANGE_COMMAND: &str = "rust-analyzer.applySourceChange";
+
+/// Like the request of `languageserver_types`, but with a label and with an
+/// edit which may contain snippets.
+pub enum ApplySnippetWorkspaceEdit {}
+
+impl Request for ApplySnippetWorkspaceEdit {
+    type Params = ApplySnippetWorkspaceEditParams;
+    type Result = ApplyWorkspaceEditResponse;
+    const METHOD: &'static str = "workspace/applyEdit";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplySnippetWorkspaceEditParams {
+    pub label: String,
+    pub edit: SnippetWorkspaceEdit,
+}
+
+pub enum MoveCursor {}
+
+impl Notification for MoveCursor {
+    type Params = TextDocumentPositionParams;
+    const METHOD: &'static str = "rust-analyzer/moveCursor";
+}
+
+pub enum AnalyzerStatus {}
+
+impl Request for AnalyzerStatus {
+    type Params = ();
+    type Result = String;
+    const METHOD: &'static str = "rust-analyzer/analyzerStatus";
+}
+
+pub enum CollectGarbage {}
+
+impl Request for CollectGarbage {
+    type Params = ();
+    type Result = CollectGarbageResult;
+    const METHOD: &'static str = "rust-analyzer/collectGarbage";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectGarbageResult {
+    /// An estimate, based on the size of the dropped syntax trees.
+    pub reclaimed_bytes: usize,
+}
+
+pub enum ReloadWorkspace {}
+
+impl Request for ReloadWorkspace {
+    type Params = ();
+    type Result = ();
+    const METHOD: &'static str = "rust-analyzer/reloadWorkspace";
+}
+
+/// The `workspace/executeCommand` equivalent of `ReloadWorkspace`.
+pub const RELOAD_WORKSPACE_COMMAND: &str = "reload_workspace";
+
 pub enum InternalFeedback {}
 
 impl Notification for InternalFeedback {
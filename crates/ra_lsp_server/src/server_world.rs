@@ -1,105 +1,250 @@
 use std::{
-    path::{PathBuf},
+    fmt::Write,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
 use languageserver_types::Url;
 use ra_analysis::{
-    Analysis, AnalysisChange, AnalysisHost, CrateGraph, FileId, LibraryData,
-    SourceRootId
+    Analysis, AnalysisChange, AnalysisHost, CfgOptions, CrateGraph, CrateId, Edition, Env, FileId,
+    LibraryData, Rope, SourceRootId, SourceRootOrigin,
 };
-use ra_vfs::{Vfs, VfsChange, VfsFile, VfsRoot};
-use rustc_hash::FxHashMap;
+use ra_syntax::SmolStr;
+use ra_vfs::{Vfs, VfsChange, VfsFile, VfsFilter, VfsRoot};
+use rustc_hash::{FxHashMap, FxHashSet};
 use relative_path::RelativePathBuf;
 use parking_lot::RwLock;
 use failure::{format_err};
 
 use crate::{
-    project_model::{CargoWorkspace, TargetKind},
-    Result,
+    cargo_check::CheckDiagnostic,
+    project_model::{
+        CargoWorkspace, DepKind, JsonProject, Package, PackageRoot, ProjectWorkspace, Sysroot,
+        TargetKind,
+    },
+    config::ClientCaps,
+    conv::LspLineIndex,
+    Result, ServerConfig,
 };
 
 #[derive(Debug)]
 pub struct ServerWorldState {
     pub roots_to_scan: usize,
     pub root: PathBuf,
-    pub workspaces: Arc<Vec<CargoWorkspace>>,
+    /// The source roots of the workspace members, and the workspace root
+    /// itself. All other roots are loaded as libraries.
+    pub local_roots: FxHashSet<PathBuf>,
+    pub workspaces: Arc<Vec<ProjectWorkspace>>,
     pub analysis_host: AnalysisHost,
     pub vfs: Arc<RwLock<Vfs>>,
+    pub config: Arc<ServerConfig>,
+    pub client_caps: Arc<ClientCaps>,
+    /// Diagnostics of the last `cargo check`, by file.
+    pub check_diagnostics: Arc<FxHashMap<FileId, Vec<CheckDiagnostic>>>,
+    pub document_versions: Arc<DocumentVersions>,
+    pub untitled: Arc<UntitledDocuments>,
 }
 
 pub struct ServerWorld {
-    pub workspaces: Arc<Vec<CargoWorkspace>>,
+    pub workspaces: Arc<Vec<ProjectWorkspace>>,
     pub analysis: Analysis,
     pub vfs: Arc<RwLock<Vfs>>,
+    pub config: Arc<ServerConfig>,
+    pub client_caps: Arc<ClientCaps>,
+    pub check_diagnostics: Arc<FxHashMap<FileId, Vec<CheckDiagnostic>>>,
+    pub untitled: Arc<UntitledDocuments>,
 }
 
-impl ServerWorldState {
-    pub fn new(root: PathBuf, workspaces: Vec<CargoWorkspace>) -> ServerWorldState {
-        let mut change = AnalysisChange::new();
+/// The versions of the open documents, as reported by the client, so that
+/// requests and diagnostics which were computed against an outdated text can
+/// be dropped.
+#[derive(Debug, Default)]
+pub struct DocumentVersions {
+    versions: RwLock<FxHashMap<Url, u64>>,
+}
+
+impl DocumentVersions {
+    /// Returns `None` if the document is not open.
+    pub fn version(&self, uri: &Url) -> Option<u64> {
+        self.versions.read().get(uri).cloned()
+    }
+
+    /// Records a new version of the document. If the client didn't send the
+    /// version, we count the changes ourselves.
+    pub fn set(&self, uri: &Url, version: Option<u64>) {
+        let mut versions = self.versions.write();
+        let version = match version {
+            Some(it) => it,
+            None => versions.get(uri).map_or(0, |it| it + 1),
+        };
+        versions.insert(uri.clone(), version);
+    }
+
+    pub fn remove(&self, uri: &Url) {
+        self.versions.write().remove(uri);
+    }
+}
 
-        let mut roots = Vec::new();
-        roots.push(root.clone());
-        for ws in workspaces.iter() {
-            for pkg in ws.packages() {
-                roots.push(pkg.root(&ws).to_path_buf());
+/// The documents which don't exist on disk, like the `untitled:` buffers of
+/// the editor. Each one is mapped to a file of a synthetic root, which never
+/// has any files on disk, and becomes a crate of its own.
+#[derive(Debug)]
+pub struct UntitledDocuments {
+    root: PathBuf,
+    /// The document of each file, by the index in the file name. Closed
+    /// documents leave a hole, so that the names of files are not reused.
+    uris: RwLock<Vec<Option<Url>>>,
+}
+
+impl UntitledDocuments {
+    fn new(workspace_root: &Path) -> UntitledDocuments {
+        UntitledDocuments {
+            root: workspace_root.join(".untitled"),
+            uris: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Returns the path of the file of `uri`, mapping it to a new file if
+    /// it doesn't have one yet.
+    pub fn add(&self, uri: &Url) -> PathBuf {
+        if let Some(path) = self.path(uri) {
+            return path;
+        }
+        let mut uris = self.uris.write();
+        uris.push(Some(uri.clone()));
+        self.file_path(uris.len() - 1)
+    }
+
+    /// Returns `false` if `uri` is not an untitled document.
+    pub fn remove(&self, uri: &Url) -> bool {
+        let mut uris = self.uris.write();
+        match uris.iter_mut().find(|it| it.as_ref() == Some(uri)) {
+            Some(it) => {
+                *it = None;
+                true
             }
+            None => false,
         }
+    }
+
+    pub fn path(&self, uri: &Url) -> Option<PathBuf> {
+        let idx = self
+            .uris
+            .read()
+            .iter()
+            .position(|it| it.as_ref() == Some(uri))?;
+        Some(self.file_path(idx))
+    }
+
+    pub fn uri(&self, path: &Path) -> Option<Url> {
+        if path.parent() != Some(self.root.as_path()) {
+            return None;
+        }
+        let idx: usize = path.file_stem()?.to_str()?.parse().ok()?;
+        self.uris.read().get(idx)?.clone()
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        self.uris
+            .read()
+            .iter()
+            .enumerate()
+            .filter(|(_, uri)| uri.is_some())
+            .map(|(idx, _)| self.file_path(idx))
+            .collect()
+    }
+
+    fn file_path(&self, idx: usize) -> PathBuf {
+        self.root.join(format!("{}.rs", idx))
+    }
+}
+
+impl ServerWorldState {
+    pub fn new(
+        root: PathBuf,
+        workspaces: Vec<ProjectWorkspace>,
+        config: ServerConfig,
+        client_caps: ClientCaps,
+    ) -> ServerWorldState {
+        let mut change = AnalysisChange::new();
+
+        let untitled = UntitledDocuments::new(&root);
+        let roots = source_roots(&root, &workspaces, &untitled);
+        let local_roots = local_roots(&roots);
+        let roots: Vec<PathBuf> = roots.into_iter().map(|it| it.path).collect();
         let roots_to_scan = roots.len();
-        let (mut vfs, roots) = Vfs::new(roots);
+        let filter = VfsFilter::new(&config.exclude_globs).unwrap_or_else(|e| {
+            log::error!("invalid exclude globs: {}", e);
+            VfsFilter::default()
+        });
+        let (mut vfs, roots) = Vfs::new(roots, filter);
         for r in roots {
-            let is_local = vfs.root2path(r).starts_with(&root);
-            change.add_root(SourceRootId(r.0.into()), is_local);
-        }
-
-        let mut crate_graph = CrateGraph::default();
-        let mut pkg_to_lib_crate = FxHashMap::default();
-        let mut pkg_crates = FxHashMap::default();
-        for ws in workspaces.iter() {
-            for pkg in ws.packages() {
-                for tgt in pkg.targets(ws) {
-                    let root = tgt.root(ws);
-                    if let Some(file_id) = vfs.load(root) {
-                        let file_id = FileId(file_id.0.into());
-                        let crate_id = crate_graph.add_crate_root(file_id);
-                        if tgt.kind(ws) == TargetKind::Lib {
-                            pkg_to_lib_crate.insert(pkg, crate_id);
-                        }
-                        pkg_crates
-                            .entry(pkg)
-                            .or_insert_with(Vec::new)
-                            .push(crate_id);
-                    }
-                }
-            }
-            for pkg in ws.packages() {
-                for dep in pkg.dependencies(ws) {
-                    if let Some(&to) = pkg_to_lib_crate.get(&dep.pkg) {
-                        for &from in pkg_crates.get(&pkg).into_iter().flatten() {
-                            crate_graph.add_dep(from, dep.name.clone(), to);
-                        }
-                    }
-                }
-            }
+            let origin = root_origin(&local_roots, &vfs.root2path(r));
+            change.add_root(SourceRootId(r.0.into()), origin);
         }
-        change.set_crate_graph(crate_graph);
+        change.set_crate_graph(crate_graph(&mut vfs, &workspaces, &untitled));
 
         let mut analysis_host = AnalysisHost::default();
         analysis_host.apply_change(change);
         ServerWorldState {
             roots_to_scan,
             root,
+            local_roots,
             workspaces: Arc::new(workspaces),
             analysis_host,
             vfs: Arc::new(RwLock::new(vfs)),
+            config: Arc::new(config),
+            client_caps: Arc::new(client_caps),
+            check_diagnostics: Arc::new(FxHashMap::default()),
+            document_versions: Arc::new(DocumentVersions::default()),
+            untitled: Arc::new(untitled),
         }
     }
 
+    /// Replaces the project model, for example after a manifest has changed.
+    /// Adds source roots for new packages and rebuilds the crate graph, while
+    /// keeping the files, including the open documents, of the existing roots.
+    /// If the crate graph is unchanged, the analysis keeps its memoized state.
+    pub fn set_workspaces(&mut self, workspaces: Vec<ProjectWorkspace>) {
+        let mut change = AnalysisChange::new();
+        {
+            let mut vfs = self.vfs.write();
+            let roots = source_roots(&self.root, &workspaces, &self.untitled);
+            self.local_roots = local_roots(&roots);
+            for root in roots {
+                if let Some(r) = vfs.add_root(root.path) {
+                    self.roots_to_scan += 1;
+                    let origin = root_origin(&self.local_roots, &vfs.root2path(r));
+                    change.add_root(SourceRootId(r.0.into()), origin);
+                }
+            }
+            change.set_crate_graph(crate_graph(&mut vfs, &workspaces, &self.untitled));
+        }
+        self.analysis_host.apply_change(change);
+        self.workspaces = Arc::new(workspaces);
+    }
+
+    /// Rebuilds the crate graph after an untitled document was opened or
+    /// closed.
+    pub fn update_untitled_crates(&mut self) {
+        let mut change = AnalysisChange::new();
+        let crate_graph = crate_graph(&mut self.vfs.write(), &self.workspaces, &self.untitled);
+        change.set_crate_graph(crate_graph);
+        self.analysis_host.apply_change(change);
+    }
+
+    /// The path of the document at `uri` in the VFS: the file path for the
+    /// `file:` URIs, and the synthetic path for the untitled documents.
+    pub fn uri_to_path(&self, uri: &Url) -> Result<PathBuf> {
+        if let Some(path) = self.untitled.path(uri) {
+            return Ok(path);
+        }
+        uri_to_file_path(uri).ok_or_else(|| format_err!("invalid uri: {}", uri))
+    }
+
     /// Returns a vec of libraries
     /// FIXME: better API here
-    pub fn process_changes(
-        &mut self,
-    ) -> Vec<(SourceRootId, Vec<(FileId, RelativePathBuf, Arc<String>)>)> {
+    pub fn process_changes(&mut self) -> Vec<(SourceRootId, Vec<(FileId, RelativePathBuf, Rope)>)> {
         let changes = self.vfs.write().commit_changes();
         if changes.is_empty() {
             return Vec::new();
@@ -110,7 +255,7 @@ impl ServerWorldState {
             match c {
                 VfsChange::AddRoot { root, files } => {
                     let root_path = self.vfs.read().root2path(root);
-                    if root_path.starts_with(&self.root) {
+                    if self.local_roots.contains(&root_path) {
                         self.roots_to_scan -= 1;
                         for (file, path, text) in files {
                             change.add_file(
@@ -160,24 +305,382 @@ impl ServerWorldState {
         self.analysis_host.apply_change(change);
     }
 
+    /// Replaces the diagnostics of `cargo check`. Diagnostics in files outside
+    /// of the VFS are dropped. Returns the files which had diagnostics before,
+    /// so that these can be cleared.
+    pub fn set_check_diagnostics(&mut self, diagnostics: Vec<CheckDiagnostic>) -> Vec<FileId> {
+        let mut by_file = FxHashMap::default();
+        {
+            let vfs = self.vfs.read();
+            for d in diagnostics {
+                if let Some(file) = vfs.path2file(&d.path) {
+                    by_file
+                        .entry(FileId(file.0.into()))
+                        .or_insert_with(Vec::new)
+                        .push(d);
+                }
+            }
+        }
+        let old = std::mem::replace(&mut self.check_diagnostics, Arc::new(by_file));
+        old.keys().cloned().collect()
+    }
+
     pub fn snapshot(&self) -> ServerWorld {
         ServerWorld {
             workspaces: Arc::clone(&self.workspaces),
             analysis: self.analysis_host.analysis(),
             vfs: Arc::clone(&self.vfs),
+            config: Arc::clone(&self.config),
+            client_caps: Arc::clone(&self.client_caps),
+            check_diagnostics: Arc::clone(&self.check_diagnostics),
+            untitled: Arc::clone(&self.untitled),
+        }
+    }
+}
+
+/// Converts a `file:` URI to a path, normalized in the same way as the paths
+/// of the VFS, so that a file gets the same `FileId` whichever way the client
+/// spells its URI.
+pub fn uri_to_file_path(uri: &Url) -> Option<PathBuf> {
+    let path = uri.to_file_path().ok()?;
+    Some(ra_vfs::normalize_path(&path))
+}
+
+/// The roots of all packages, the workspace root itself and the root of the
+/// untitled documents. A directory which is the root of several packages
+/// becomes a single root, which is local if any of them is.
+fn source_roots(
+    root: &Path,
+    workspaces: &[ProjectWorkspace],
+    untitled: &UntitledDocuments,
+) -> Vec<PackageRoot> {
+    let mut res = vec![
+        PackageRoot::new(root, true),
+        PackageRoot::new(&untitled.root, true),
+    ];
+    for ws in workspaces.iter() {
+        res.extend(ws.roots());
+    }
+    res.sort_by(|a, b| a.path.cmp(&b.path).then(b.is_member.cmp(&a.is_member)));
+    res.dedup_by(|a, b| a.path == b.path);
+    res
+}
+
+fn local_roots(roots: &[PackageRoot]) -> FxHashSet<PathBuf> {
+    roots
+        .iter()
+        .filter(|it| it.is_member)
+        .map(|it| it.path.clone())
+        .collect()
+}
+
+fn root_origin(local_roots: &FxHashSet<PathBuf>, root: &Path) -> SourceRootOrigin {
+    if local_roots.contains(root) {
+        SourceRootOrigin::Workspace
+    } else {
+        SourceRootOrigin::Dependency
+    }
+}
+
+fn crate_graph(
+    vfs: &mut Vfs,
+    workspaces: &[ProjectWorkspace],
+    untitled: &UntitledDocuments,
+) -> CrateGraph {
+    let mut crate_graph = CrateGraph::default();
+    let mut untitled_deps = None;
+    for ws in workspaces.iter() {
+        // `Package`s and the other ids of the project model are indices into
+        // their own workspace, so each workspace gets a graph of its own.
+        let (ws_graph, std_deps) = workspace_crate_graph(vfs, ws);
+        let mapping = crate_graph.extend(ws_graph);
+        if untitled_deps.is_none() {
+            let std_deps = std_deps
+                .into_iter()
+                .map(|(name, crate_id)| (name, mapping[&crate_id]))
+                .collect::<Vec<_>>();
+            untitled_deps = Some(std_deps);
+        }
+    }
+    // Untitled documents depend only on the standard library of the first
+    // workspace.
+    let std_deps = untitled_deps.unwrap_or_default();
+    for path in untitled.paths() {
+        if let Some(file_id) = vfs.path2file(&path) {
+            let crate_id = crate_graph.add_crate_root(
+                FileId(file_id.0.into()),
+                Edition::Edition2018,
+                None,
+                CfgOptions::default(),
+                Env::default(),
+            );
+            add_deps(&mut crate_graph, crate_id, &std_deps);
+        }
+    }
+    crate_graph
+}
+
+/// Lowers a single workspace, together with its sysroot, and returns the
+/// crates of the standard library which its crates depend on.
+fn workspace_crate_graph(
+    vfs: &mut Vfs,
+    ws: &ProjectWorkspace,
+) -> (CrateGraph, Vec<(SmolStr, CrateId)>) {
+    let mut crate_graph = CrateGraph::default();
+    let std_deps = add_sysroot_crates(vfs, ws.sysroot(), &mut crate_graph);
+    let ws = match ws {
+        ProjectWorkspace::Cargo { cargo, .. } => cargo,
+        ProjectWorkspace::Json { project, .. } => {
+            add_json_crates(vfs, project, &std_deps, &mut crate_graph);
+            return (crate_graph, std_deps);
+        }
+    };
+    let mut pkg_to_lib_crate = FxHashMap::default();
+    let mut pkg_crates = FxHashMap::default();
+    for pkg in ws.packages() {
+        for tgt in pkg.targets(ws) {
+            let root = tgt.root(ws);
+            if let Some(file_id) = vfs.load(root) {
+                let file_id = FileId(file_id.0.into());
+                let kind = tgt.kind(ws);
+                let mut cfg_options = pkg.cfg_options(ws);
+                if kind == TargetKind::Test || kind == TargetKind::Bench {
+                    cfg_options.insert_atom("test".into());
+                }
+                let crate_id = crate_graph.add_crate_root(
+                    file_id,
+                    tgt.edition(ws),
+                    Some(tgt.name(ws).into()),
+                    cfg_options,
+                    pkg.env(ws),
+                );
+                add_deps(&mut crate_graph, crate_id, &std_deps);
+                match kind {
+                    TargetKind::Lib => {
+                        pkg_to_lib_crate.insert(pkg, crate_id);
+                    }
+                    TargetKind::ProcMacro => {
+                        crate_graph.mark_proc_macro(crate_id);
+                        pkg_to_lib_crate.insert(pkg, crate_id);
+                    }
+                    _ => (),
+                }
+                pkg_crates
+                    .entry(pkg)
+                    .or_insert_with(Vec::new)
+                    .push((crate_id, kind));
+            }
+        }
+    }
+    for pkg in ws.packages() {
+        // Binaries, tests, examples and benches use the library of their
+        // own package, under the name of its target.
+        if let Some(&lib) = pkg_to_lib_crate.get(&pkg) {
+            let name: SmolStr = lib_name(ws, pkg).replace('-', "_").into();
+            for &(from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
+                if from == lib || kind == TargetKind::Other {
+                    continue;
+                }
+                if let Err(err) = crate_graph.add_dep(from, name.clone(), lib) {
+                    log::error!("skipping dependency {}: {}", name, err);
+                }
+            }
+        }
+        for dep in pkg.dependencies(ws) {
+            if let Some(&to) = pkg_to_lib_crate.get(&dep.pkg) {
+                for &(from, kind) in pkg_crates.get(&pkg).into_iter().flatten() {
+                    let res = match dep_edge(dep.kind, kind) {
+                        Some(DepEdge::Normal) => crate_graph.add_dep(from, dep.name.clone(), to),
+                        Some(DepEdge::Dev) => crate_graph.add_dev_dep(from, dep.name.clone(), to),
+                        None => continue,
+                    };
+                    if let Err(err) = res {
+                        log::error!("skipping dependency {}: {}", dep.name, err);
+                    }
+                }
+            }
+        }
+    }
+    (crate_graph, std_deps)
+}
+
+enum DepEdge {
+    Normal,
+    /// Only for the unit tests of the target.
+    Dev,
+}
+
+/// How a dependency of a package is visible to one of its targets. The build
+/// script (the `Other` target) sees only the build dependencies; dev
+/// dependencies are visible to whole tests, benches and examples, but only to
+/// the unit tests of the library and binaries.
+fn dep_edge(dep: DepKind, target: TargetKind) -> Option<DepEdge> {
+    let res = match (dep, target) {
+        (DepKind::Build, TargetKind::Other) => DepEdge::Normal,
+        (DepKind::Build, _) | (_, TargetKind::Other) => return None,
+        (DepKind::Normal, _) => DepEdge::Normal,
+        (DepKind::Dev, TargetKind::Test)
+        | (DepKind::Dev, TargetKind::Bench)
+        | (DepKind::Dev, TargetKind::Example) => DepEdge::Normal,
+        (DepKind::Dev, _) => DepEdge::Dev,
+    };
+    Some(res)
+}
+
+fn lib_name(ws: &CargoWorkspace, pkg: Package) -> &str {
+    pkg.targets(ws)
+        .find(|tgt| tgt.kind(ws) == TargetKind::Lib || tgt.kind(ws) == TargetKind::ProcMacro)
+        .map(|tgt| tgt.name(ws))
+        .unwrap_or_else(|| pkg.name(ws))
+}
+
+/// Adds the crates of the standard library, and returns the ones which every
+/// crate of the workspace depends on implicitly.
+fn add_sysroot_crates(
+    vfs: &mut Vfs,
+    sysroot: &Sysroot,
+    crate_graph: &mut CrateGraph,
+) -> Vec<(SmolStr, CrateId)> {
+    let mut crates = FxHashMap::default();
+    for krate in sysroot.crates() {
+        if let Some(file_id) = vfs.load(krate.root(sysroot)) {
+            let file_id = FileId(file_id.0.into());
+            let crate_id = crate_graph.add_crate_root(
+                file_id,
+                Edition::Edition2018,
+                Some(krate.name(sysroot).into()),
+                krate.cfg_options(sysroot),
+                Env::default(),
+            );
+            crates.insert(krate, crate_id);
+        }
+    }
+    for krate in sysroot.crates() {
+        if let Some(&from) = crates.get(&krate) {
+            for dep in krate.deps(sysroot) {
+                if let Some(&to) = crates.get(&dep) {
+                    add_deps(crate_graph, from, &[(dep.name(sysroot).into(), to)]);
+                }
+            }
+        }
+    }
+    ["std", "core"]
+        .iter()
+        .filter_map(|&name| {
+            let krate = sysroot.by_name(name)?;
+            Some((SmolStr::from(name), *crates.get(&krate)?))
+        })
+        .collect()
+}
+
+fn add_deps(crate_graph: &mut CrateGraph, from: CrateId, deps: &[(SmolStr, CrateId)]) {
+    for (name, to) in deps.iter() {
+        if let Err(err) = crate_graph.add_dep(from, name.clone(), *to) {
+            log::error!("skipping dependency {}: {}", name, err);
+        }
+    }
+}
+
+fn add_json_crates(
+    vfs: &mut Vfs,
+    project: &JsonProject,
+    std_deps: &[(SmolStr, CrateId)],
+    crate_graph: &mut CrateGraph,
+) {
+    let mut crates = FxHashMap::default();
+    for krate in project.crates() {
+        if let Some(file_id) = vfs.load(krate.root_module(project)) {
+            let file_id = FileId(file_id.0.into());
+            let crate_id = crate_graph.add_crate_root(
+                file_id,
+                krate.edition(project),
+                krate.name(project).map(|it| it.into()),
+                krate.cfg_options(project),
+                krate.env(project),
+            );
+            add_deps(crate_graph, crate_id, std_deps);
+            crates.insert(krate, crate_id);
+        }
+    }
+    for krate in project.crates() {
+        let from = match crates.get(&krate) {
+            Some(&it) => it,
+            None => continue,
+        };
+        for dep in krate.deps(project) {
+            if let Some(&to) = crates.get(&dep.krate) {
+                if let Err(err) = crate_graph.add_dep(from, dep.name.as_str().into(), to) {
+                    log::error!("skipping dependency {}: {}", dep.name, err);
+                }
+            }
         }
     }
 }
 
 impl ServerWorld {
+    /// A report about the project model, the VFS and the analysis, for bug
+    /// reports.
+    pub fn status(&self) -> String {
+        let mut buf = String::new();
+        for ws in self.workspaces.iter() {
+            let sysroot = ws.sysroot();
+            if sysroot.crates().next().is_some() {
+                let crates = sysroot
+                    .crates()
+                    .map(|it| it.name(sysroot))
+                    .collect::<Vec<_>>();
+                writeln!(buf, "sysroot: {}", crates.join(", ")).unwrap();
+            }
+            let ws = match ws {
+                ProjectWorkspace::Cargo { cargo, .. } => cargo,
+                ProjectWorkspace::Json { project, .. } => {
+                    writeln!(
+                        buf,
+                        "rust-project.json: {} crates",
+                        project.crates().count()
+                    )
+                    .unwrap();
+                    continue;
+                }
+            };
+            let packages = ws.packages().filter(|pkg| pkg.is_member(ws)).count();
+            writeln!(
+                buf,
+                "workspace: {} packages ({} members)",
+                ws.packages().count(),
+                packages
+            )
+            .unwrap();
+            for pkg in ws.packages().filter(|pkg| pkg.is_member(ws)) {
+                writeln!(buf, "    {} {}", pkg.name(ws), pkg.root(ws).display()).unwrap();
+            }
+        }
+        buf.push_str(&self.analysis.status());
+        writeln!(buf, "vfs:").unwrap();
+        for (root, files) in self.vfs.read().root_file_counts() {
+            writeln!(buf, "    {} ({} files)", root.display(), files).unwrap();
+        }
+        buf
+    }
+
+    /// The line index of `file_id`, to convert offsets to and from the
+    /// positions of the client.
+    pub fn line_index(&self, file_id: FileId) -> LspLineIndex {
+        LspLineIndex {
+            index: self.analysis.file_line_index(file_id),
+            encoding: self.client_caps.offset_encoding,
+        }
+    }
+
     pub fn analysis(&self) -> &Analysis {
         &self.analysis
     }
 
     pub fn uri_to_file_id(&self, uri: &Url) -> Result<FileId> {
-        let path = uri
-            .to_file_path()
-            .map_err(|()| format_err!("invalid uri: {}", uri))?;
+        let path = match self.untitled.path(uri) {
+            Some(it) => it,
+            None => uri_to_file_path(uri).ok_or_else(|| format_err!("invalid uri: {}", uri))?,
+        };
         let file = self
             .vfs
             .read()
@@ -186,8 +689,17 @@ impl ServerWorld {
         Ok(FileId(file.0.into()))
     }
 
+    /// The path of the file in the VFS. Untitled documents have a synthetic
+    /// path, which does not exist on disk.
+    pub fn file_id_to_path(&self, id: FileId) -> PathBuf {
+        self.vfs.read().file2path(VfsFile(id.0.into()))
+    }
+
     pub fn file_id_to_uri(&self, id: FileId) -> Result<Url> {
         let path = self.vfs.read().file2path(VfsFile(id.0.into()));
+        if let Some(uri) = self.untitled.uri(&path) {
+            return Ok(uri);
+        }
         let url = Url::from_file_path(&path)
             .map_err(|_| format_err!("can't convert path to url: {}", path.display()))?;
         Ok(url)
@@ -196,6 +708,9 @@ impl ServerWorld {
     pub fn path_to_uri(&self, root: SourceRootId, path: &RelativePathBuf) -> Result<Url> {
         let base = self.vfs.read().root2path(VfsRoot(root.0.into()));
         let path = path.to_path(base);
+        if let Some(uri) = self.untitled.uri(&path) {
+            return Ok(uri);
+        }
         let url = Url::from_file_path(&path)
             .map_err(|_| format_err!("can't convert path to url: {}", path.display()))?;
         Ok(url)
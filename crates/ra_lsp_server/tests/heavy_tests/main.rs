@@ -1,14 +1,30 @@
 mod support;
 
+use std::fs;
+
 use languageserver_types::{
-    CodeActionContext, DocumentFormattingParams, FormattingOptions, Position, Range,
+    notification::{
+        Cancel, DidChangeConfiguration, DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument,
+        DidOpenTextDocument,
+    },
+    code_action_kind, CancelParams, ClientCapabilities, CodeActionCapability, CodeActionContext,
+    CodeActionKindLiteralSupport, CodeActionLiteralSupport, DiagnosticSeverity, DidChangeConfigurationParams, DidChangeTextDocumentParams,
+    DidChangeWatchedFilesParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentFormattingParams,
+    DocumentRangeFormattingParams, ExecuteCommandParams, FileChangeType, FileEvent,
+    FormattingOptions, HoverCapability, MarkupKind, NumberOrString, Position, Range,
+    TextDocumentClientCapabilities, TextDocumentContentChangeEvent, TextDocumentIdentifier,
+    TextDocumentItem, TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier,
 };
+use gen_lsp_server::ErrorCode;
+use ra_editor::OffsetEncoding;
+use ra_lsp_server::{CargoFeatures, ClientCaps, ServerConfig};
 use ra_lsp_server::req::{
-    CodeActionParams, CodeActionRequest, Formatting, Runnables, RunnablesParams,
+    AnalyzerStatus, CodeActionParams, CodeActionRequest, Completion, CompletionParams, DidSaveTextDocument, DidSaveTextDocumentParams,
+    Formatting, GotoDefinition, HoverRequest, RangeFormatting, ReloadWorkspace, Runnables, RunnablesParams,
 };
 use serde_json::json;
 
-use crate::support::project;
+use crate::support::{project, project_in, project_with_caps, project_with_config, Server};
 
 const LOG: &'static str = "";
 
@@ -62,6 +78,73 @@ fn foo() {
     );
 }
 
+#[test]
+fn test_incremental_document_changes() {
+    let server = project(
+        r"
+//- lib.rs
+// 🦀
+#[test]
+fn foo() {
+}
+",
+    );
+    server.wait_for_feedback("workspace loaded");
+    let change = |line, character, len, text: &str| TextDocumentContentChangeEvent {
+        range: Some(Range::new(
+            Position::new(line, character),
+            Position::new(line, character + len),
+        )),
+        range_length: Some(len),
+        text: text.to_string(),
+    };
+    server.notification::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier {
+            uri: server.doc_id("lib.rs").uri,
+            version: Some(1),
+        },
+        // Columns are in UTF-16 code units, the crab takes two of them.
+        content_changes: vec![change(2, 3, 3, "bar"), change(0, 5, 0, "!")],
+    });
+    server.request::<Runnables>(
+        RunnablesParams {
+            text_document: server.doc_id("lib.rs"),
+            position: None,
+        },
+        json!([
+          {
+            "args": [ "test", "--", "bar", "--nocapture" ],
+            "bin": "cargo",
+            "env": { "RUST_BACKTRACE": "short" },
+            "label": "test bar",
+            "range": {
+              "end": { "character": 1, "line": 3 },
+              "start": { "character": 0, "line": 0 }
+            }
+          },
+          {
+            "args": [
+              "check",
+              "--all"
+            ],
+            "bin": "cargo",
+            "env": {},
+            "label": "cargo check --all",
+            "range": {
+              "end": {
+                "character": 0,
+                "line": 0
+              },
+              "start": {
+                "character": 0,
+                "line": 0
+              }
+            }
+          }
+        ]),
+    );
+}
+
 #[test]
 fn test_runnables_project() {
     let server = project(
@@ -122,62 +205,95 @@ fn test_eggs() {}
     );
 }
 
-use std::collections::HashMap;
 #[test]
-fn test_format_document() {
-    tools::install_rustfmt().unwrap();
-
+fn test_reload_workspace_on_manifest_change() {
     let server = project(
         r#"
+//- Cargo.toml
 [package]
 name = "foo"
 version = "0.0.0"
 
 //- src/lib.rs
-mod bar;
-
-fn main() {
-}
-
-pub use std::collections::HashMap;
+pub fn foo() {}
 "#,
     );
     server.wait_for_feedback("workspace loaded");
-
-    server.request::<Formatting>(
-        DocumentFormattingParams {
+    let manifest = server.doc_id("Cargo.toml").uri;
+    fs::write(
+        manifest.to_file_path().unwrap(),
+        "[package]\nname = \"bar\"\nversion = \"0.0.0\"\n",
+    )
+    .unwrap();
+    server.notification::<DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: manifest,
+            typ: FileChangeType::Changed,
+        }],
+    });
+    server.wait_for_feedback("workspace reloaded");
+    server.request::<Runnables>(
+        RunnablesParams {
             text_document: server.doc_id("src/lib.rs"),
-            options: FormattingOptions {
-                tab_size: 4,
-                insert_spaces: false,
-                properties: HashMap::new(),
-            },
+            position: None,
         },
         json!([
-            {
-                "newText": r#"mod bar;
+          {
+            "args": [ "check", "--package", "bar", "--lib" ],
+            "bin": "cargo",
+            "env": {},
+            "label": "cargo check -p bar",
+            "range": {
+              "end": { "character": 0, "line": 0 },
+              "start": { "character": 0, "line": 0 }
+            }
+          }
+        ]),
+    );
+}
 
-fn main() {}
+#[test]
+fn test_reload_workspace_request() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
 
-pub use std::collections::HashMap;
+//- src/lib.rs
+pub fn foo() {}
 "#,
-                "range": {
-                    "end": {
-                        "character": 0,
-                        "line": 6
-                    },
-                    "start": {
-                        "character": 0,
-                        "line": 0
-                    }
-                }
+    );
+    server.wait_for_feedback("workspace loaded");
+    fs::write(
+        server.doc_id("Cargo.toml").uri.to_file_path().unwrap(),
+        "[package]\nname = \"bar\"\nversion = \"0.0.0\"\n",
+    )
+    .unwrap();
+    server.request::<ReloadWorkspace>((), json!(null));
+    server.request::<Runnables>(
+        RunnablesParams {
+            text_document: server.doc_id("src/lib.rs"),
+            position: None,
+        },
+        json!([
+          {
+            "args": [ "check", "--package", "bar", "--lib" ],
+            "bin": "cargo",
+            "env": {},
+            "label": "cargo check -p bar",
+            "range": {
+              "end": { "character": 0, "line": 0 },
+              "start": { "character": 0, "line": 0 }
             }
+          }
         ]),
     );
 }
 
 #[test]
-fn test_missing_module_code_action() {
+fn test_cargo_check_on_save() {
     let server = project(
         r#"
 //- Cargo.toml
@@ -186,50 +302,1083 @@ name = "foo"
 version = "0.0.0"
 
 //- src/lib.rs
-mod bar;
+pub fn foo() -> u32 {
+    "92"
+}
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    server.notification::<DidSaveTextDocument>(DidSaveTextDocumentParams {
+        text_document: server.doc_id("src/lib.rs"),
+        text: None,
+    });
+    let params = server.wait_for_diagnostics("rustc");
+    assert_eq!(params.uri, server.doc_id("src/lib.rs").uri);
+    let diagnostic = params
+        .diagnostics
+        .iter()
+        .find(|d| d.source == Some("rustc".to_string()))
+        .unwrap();
+    assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::Error));
+    assert_eq!(
+        diagnostic.range,
+        Range::new(Position::new(1, 4), Position::new(1, 8))
+    );
+}
 
-fn main() {}
+#[test]
+fn test_diagnostics_carry_document_version() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+pub fn foo() {}
 "#,
     );
     server.wait_for_feedback("workspace loaded");
-    let empty_context = || CodeActionContext {
-        diagnostics: Vec::new(),
-        only: None,
+    server.notification::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier {
+            uri: server.doc_id("src/lib.rs").uri,
+            version: Some(3),
+        },
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "pub fn foo( {}\n".to_string(),
+        }],
+    });
+    let params = server.wait_for_diagnostics("rust-analyzer");
+    assert_eq!(params.uri, server.doc_id("src/lib.rs").uri);
+    assert_eq!(params.version, Some(3));
+}
+
+#[test]
+fn test_untitled_documents() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+pub fn foo() {}
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    let open = |uri: &Url, text: &str| {
+        server.notification::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "rust".to_string(),
+                version: 0,
+                text: text.to_string(),
+            },
+        })
     };
-    server.request::<CodeActionRequest>(
-        CodeActionParams {
-            text_document: server.doc_id("src/lib.rs"),
-            range: Range::new(Position::new(0, 4), Position::new(0, 7)),
-            context: empty_context(),
+
+    let scratch = Url::parse("untitled:Untitled-1").unwrap();
+    open(
+        &scratch,
+        "mod m { pub fn frobnicate() {} }\nfn main() { m::f }\n",
+    );
+    server.request::<Completion>(
+        CompletionParams {
+            text_document: TextDocumentIdentifier {
+                uri: scratch.clone(),
+            },
+            position: Position::new(1, 16),
+            context: None,
         },
         json!([
             {
-              "arguments": [
+                "filterText": "frobnicate",
+                "insertText": "frobnicate()$0",
+                "insertTextFormat": 2,
+                "kind": 3,
+                "label": "frobnicate"
+            }
+        ]),
+    );
+    server.notification::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+        text_document: TextDocumentIdentifier { uri: scratch },
+    });
+
+    let broken = Url::parse("untitled:Untitled-2").unwrap();
+    open(&broken, "fn foo( {}\n");
+    let params = server.wait_for_diagnostics("rust-analyzer");
+    assert_eq!(params.uri, broken);
+}
+
+#[test]
+fn test_hover_content_format() {
+    let code = r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+/// Does nothing.
+pub fn foo() {}
+fn bar() { foo(); }
+"#;
+    let server = project(code);
+    server.wait_for_feedback("workspace loaded");
+    server.request::<HoverRequest>(
+        TextDocumentPositionParams {
+            text_document: server.doc_id("src/lib.rs"),
+            position: Position::new(2, 12),
+        },
+        json!({
+            "contents": { "kind": "plaintext", "value": "fn foo\n\nDoes nothing." },
+            "range": {
+                "end": { "character": 14, "line": 2 },
+                "start": { "character": 11, "line": 2 }
+            }
+        }),
+    );
+
+    let caps = ClientCapabilities {
+        text_document: Some(TextDocumentClientCapabilities {
+            hover: Some(HoverCapability {
+                dynamic_registration: None,
+                content_format: Some(vec![MarkupKind::Markdown, MarkupKind::PlainText]),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let server = project_with_caps(code, ClientCaps::new(&caps, OffsetEncoding::Utf16));
+    server.wait_for_feedback("workspace loaded");
+    server.request::<HoverRequest>(
+        TextDocumentPositionParams {
+            text_document: server.doc_id("src/lib.rs"),
+            position: Position::new(2, 12),
+        },
+        json!({
+            "contents": { "kind": "markdown", "value": "```rust\nfn foo\n```\n\nDoes nothing." },
+            "range": {
+                "end": { "character": 14, "line": 2 },
+                "start": { "character": 11, "line": 2 }
+            }
+        }),
+    );
+}
+
+#[test]
+fn test_hover_actions() {
+    let code = r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+#[test]
+fn foo() { let x = 92; x; }
+"#;
+    let caps = ClientCaps {
+        hover_actions: true,
+        ..ClientCaps::default()
+    };
+    let server = project_with_caps(code, caps);
+    server.wait_for_feedback("workspace loaded");
+    server.request::<HoverRequest>(
+        TextDocumentPositionParams {
+            text_document: server.doc_id("src/lib.rs"),
+            position: Position::new(1, 4),
+        },
+        json!({
+            "actions": [
                 {
-                  "cursorPosition": null,
-                  "workspaceEdit": {
-                    "documentChanges": [
-                      {
-                        "kind": "create",
-                        "uri": "file:///[..]/src/bar.rs"
-                      }
-                    ]
-                  },
-                  "label": "create module"
+                    "arguments": [
+                        {
+                            "args": [ "test", "--package", "foo", "--lib", "--", "foo", "--nocapture" ],
+                            "bin": "cargo",
+                            "env": { "RUST_BACKTRACE": "short" },
+                            "label": "test foo",
+                            "range": {
+                                "end": { "character": 27, "line": 1 },
+                                "start": { "character": 0, "line": 0 }
+                            }
+                        }
+                    ],
+                    "command": "ra-lsp.runSingle",
+                    "title": "Run test"
                 }
-              ],
-              "command": "ra-lsp.applySourceChange",
-              "title": "create module"
+            ],
+            "contents": { "kind": "plaintext", "value": "fn foo" },
+            "range": {
+                "end": { "character": 6, "line": 1 },
+                "start": { "character": 3, "line": 1 }
             }
-        ]),
+        }),
+    );
+    server.request::<HoverRequest>(
+        TextDocumentPositionParams {
+            text_document: server.doc_id("src/lib.rs"),
+            position: Position::new(1, 23),
+        },
+        json!({
+            "actions": [
+                {
+                    "arguments": [
+                        "file:///[..]/src/lib.rs",
+                        { "character": 23, "line": 1 },
+                        [
+                            {
+                                "range": {
+                                    "end": { "character": 16, "line": 1 },
+                                    "start": { "character": 15, "line": 1 }
+                                },
+                                "uri": "file:///[..]/src/lib.rs"
+                            },
+                            {
+                                "range": {
+                                    "end": { "character": 24, "line": 1 },
+                                    "start": { "character": 23, "line": 1 }
+                                },
+                                "uri": "file:///[..]/src/lib.rs"
+                            }
+                        ]
+                    ],
+                    "command": "ra-lsp.showReferences",
+                    "title": "Show references"
+                }
+            ],
+            "contents": { "kind": "plaintext", "value": "i32" },
+            "range": {
+                "end": { "character": 24, "line": 1 },
+                "start": { "character": 23, "line": 1 }
+            }
+        }),
     );
+}
 
-    server.request::<CodeActionRequest>(
-        CodeActionParams {
+#[test]
+fn test_disabled_feature() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+pub fn foo() {}
+fn bar() { foo(); }
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    server.notification::<DidChangeConfiguration>(DidChangeConfigurationParams {
+        settings: json!({ "ra-lsp": { "features": { "hover": false } } }),
+    });
+    server.request::<HoverRequest>(
+        TextDocumentPositionParams {
             text_document: server.doc_id("src/lib.rs"),
-            range: Range::new(Position::new(2, 4), Position::new(2, 7)),
-            context: empty_context(),
+            position: Position::new(1, 12),
         },
-        json!([]),
+        json!(null),
     );
 }
+
+#[test]
+fn test_utf8_offsets() {
+    let code = r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+/// Does nothing.
+pub fn foo() {}
+fn bar() { "ü"; foo(); }
+"#;
+    let caps = ClientCaps {
+        offset_encoding: OffsetEncoding::Utf8,
+        ..ClientCaps::default()
+    };
+    let server = project_with_caps(code, caps);
+    server.wait_for_feedback("workspace loaded");
+    // Columns are in bytes, the `ü` takes two of them.
+    server.request::<HoverRequest>(
+        TextDocumentPositionParams {
+            text_document: server.doc_id("src/lib.rs"),
+            position: Position::new(2, 18),
+        },
+        json!({
+            "contents": { "kind": "plaintext", "value": "fn foo\n\nDoes nothing." },
+            "range": {
+                "end": { "character": 20, "line": 2 },
+                "start": { "character": 17, "line": 2 }
+            }
+        }),
+    );
+
+    server.notification::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier {
+            uri: server.doc_id("src/lib.rs").uri,
+            version: Some(1),
+        },
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: Some(Range::new(Position::new(2, 14), Position::new(2, 14))),
+            range_length: None,
+            text: "ü".to_string(),
+        }],
+    });
+    server.request::<HoverRequest>(
+        TextDocumentPositionParams {
+            text_document: server.doc_id("src/lib.rs"),
+            position: Position::new(2, 20),
+        },
+        json!({
+            "contents": { "kind": "plaintext", "value": "fn foo\n\nDoes nothing." },
+            "range": {
+                "end": { "character": 22, "line": 2 },
+                "start": { "character": 19, "line": 2 }
+            }
+        }),
+    );
+}
+
+use std::collections::HashMap;
+#[test]
+fn test_format_document() {
+    tools::install_rustfmt().unwrap();
+
+    let server = project(
+        r#"
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+mod bar;
+
+fn main() {
+}
+
+pub use std::collections::HashMap;
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+
+    server.request::<Formatting>(
+        DocumentFormattingParams {
+            text_document: server.doc_id("src/lib.rs"),
+            options: FormattingOptions {
+                tab_size: 4,
+                insert_spaces: false,
+                properties: HashMap::new(),
+            },
+        },
+        json!([
+            {
+                "newText": "",
+                "range": {
+                    "end": { "character": 0, "line": 3 },
+                    "start": { "character": 11, "line": 2 }
+                }
+            }
+        ]),
+    );
+}
+
+#[test]
+fn test_format_range() {
+    tools::install_rustfmt().unwrap();
+
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+fn foo(  ) {}
+
+fn bar(  ) {}
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+
+    server.request::<RangeFormatting>(
+        DocumentRangeFormattingParams {
+            text_document: server.doc_id("src/lib.rs"),
+            range: Range::new(Position::new(2, 0), Position::new(2, 13)),
+            options: FormattingOptions {
+                tab_size: 4,
+                insert_spaces: false,
+                properties: HashMap::new(),
+            },
+        },
+        json!([
+            {
+                "newText": "",
+                "range": {
+                    "end": { "character": 9, "line": 2 },
+                    "start": { "character": 7, "line": 2 }
+                }
+            }
+        ]),
+    );
+}
+
+#[test]
+fn test_missing_module_code_action() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+mod bar;
+
+fn main() {}
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    let empty_context = || CodeActionContext {
+        diagnostics: Vec::new(),
+        only: None,
+    };
+    server.request::<CodeActionRequest>(
+        CodeActionParams {
+            text_document: server.doc_id("src/lib.rs"),
+            range: Range::new(Position::new(0, 4), Position::new(0, 7)),
+            context: empty_context(),
+        },
+        json!([
+            {
+              "arguments": [
+                {
+                  "cursorPosition": null,
+                  "workspaceEdit": {
+                    "documentChanges": [
+                      {
+                        "kind": "create",
+                        "uri": "file:///[..]/src/bar.rs"
+                      }
+                    ]
+                  },
+                  "label": "create module"
+                }
+              ],
+              "command": "rust-analyzer.applySourceChange",
+              "title": "create module"
+            }
+        ]),
+    );
+
+    server.request::<CodeActionRequest>(
+        CodeActionParams {
+            text_document: server.doc_id("src/lib.rs"),
+            range: Range::new(Position::new(2, 4), Position::new(2, 7)),
+            context: empty_context(),
+        },
+        json!([]),
+    );
+}
+
+#[test]
+fn test_snippet_code_action() {
+    let code = r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+struct Foo {}
+"#;
+    let params = |server: &Server| CodeActionParams {
+        text_document: server.doc_id("src/lib.rs"),
+        range: Range::new(Position::new(0, 12), Position::new(0, 12)),
+        context: CodeActionContext {
+            diagnostics: Vec::new(),
+            only: None,
+        },
+    };
+    let code_actions = |add_impl: serde_json::Value| {
+        json!([
+            {
+              "arguments": [
+                {
+                  "cursorPosition": {
+                    "position": { "character": 9, "line": 0 },
+                    "textDocument": { "uri": "file:///[..]/src/lib.rs" }
+                  },
+                  "label": "add `#[derive]`",
+                  "workspaceEdit": {
+                    "documentChanges": [
+                      {
+                        "edits": [
+                          {
+                            "newText": "#[derive()]\n",
+                            "range": {
+                              "end": { "character": 0, "line": 0 },
+                              "start": { "character": 0, "line": 0 }
+                            }
+                          }
+                        ],
+                        "textDocument": { "uri": "file:///[..]/src/lib.rs", "version": null }
+                      }
+                    ]
+                  }
+                }
+              ],
+              "command": "rust-analyzer.applySourceChange",
+              "title": "add `#[derive]`"
+            },
+            {
+              "arguments": [ add_impl ],
+              "command": "rust-analyzer.applySourceChange",
+              "title": "add impl"
+            }
+        ])
+    };
+    let end_of_struct = json!({
+        "end": { "character": 13, "line": 0 },
+        "start": { "character": 13, "line": 0 }
+    });
+
+    let server = project(code);
+    server.wait_for_feedback("workspace loaded");
+    server.request::<CodeActionRequest>(
+        params(&server),
+        code_actions(json!({
+          "cursorPosition": {
+            "position": { "character": 0, "line": 3 },
+            "textDocument": { "uri": "file:///[..]/src/lib.rs" }
+          },
+          "label": "add impl",
+          "workspaceEdit": {
+            "documentChanges": [
+              {
+                "edits": [ { "newText": "\n\nimpl Foo {\n\n}", "range": end_of_struct } ],
+                "textDocument": { "uri": "file:///[..]/src/lib.rs", "version": null }
+              }
+            ]
+          }
+        })),
+    );
+
+    let caps = ClientCaps {
+        snippet_text_edit: true,
+        ..ClientCaps::default()
+    };
+    let server = project_with_caps(code, caps);
+    server.wait_for_feedback("workspace loaded");
+    server.request::<CodeActionRequest>(
+        params(&server),
+        code_actions(json!({
+          "cursorPosition": null,
+          "label": "add impl",
+          "workspaceEdit": {
+            "documentChanges": [
+              {
+                "edits": [
+                  {
+                    "insertTextFormat": 2,
+                    "newText": "\n\nimpl Foo {\n$0\n}",
+                    "range": end_of_struct
+                  }
+                ],
+                "textDocument": { "uri": "file:///[..]/src/lib.rs", "version": null }
+              }
+            ]
+          }
+        })),
+    );
+}
+
+#[test]
+fn test_code_action_kinds() {
+    let code = r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+use std::sync::Arc;
+use std::fmt;
+
+struct Foo {}
+"#;
+    let caps = ClientCapabilities {
+        text_document: Some(TextDocumentClientCapabilities {
+            code_action: Some(CodeActionCapability {
+                dynamic_registration: None,
+                code_action_literal_support: Some(CodeActionLiteralSupport {
+                    code_action_kind: CodeActionKindLiteralSupport {
+                        value_set: vec![
+                            code_action_kind::QUICKFIX.to_string(),
+                            code_action_kind::REFACTOR.to_string(),
+                            code_action_kind::SOURCE.to_string(),
+                        ],
+                    },
+                }),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let server = project_with_caps(code, ClientCaps::new(&caps, OffsetEncoding::Utf16));
+    server.wait_for_feedback("workspace loaded");
+    let params = |only: &str| CodeActionParams {
+        text_document: server.doc_id("src/lib.rs"),
+        range: Range::new(Position::new(3, 12), Position::new(3, 12)),
+        context: CodeActionContext {
+            diagnostics: Vec::new(),
+            only: Some(vec![only.to_string()]),
+        },
+    };
+    server.request::<CodeActionRequest>(params(code_action_kind::QUICKFIX), json!([]));
+    server.request::<CodeActionRequest>(params(code_action_kind::REFACTOR_EXTRACT), json!([]));
+    server.request::<CodeActionRequest>(
+        params(code_action_kind::SOURCE),
+        json!([
+            {
+              "command": {
+                "arguments": [
+                  {
+                    "cursorPosition": null,
+                    "label": "organize imports",
+                    "workspaceEdit": {
+                      "documentChanges": [
+                        {
+                          "edits": [
+                            {
+                              "newText": "use std::{fmt, sync::Arc};",
+                              "range": {
+                                "end": { "character": 13, "line": 1 },
+                                "start": { "character": 0, "line": 0 }
+                              }
+                            }
+                          ],
+                          "textDocument": { "uri": "file:///[..]/src/lib.rs", "version": null }
+                        }
+                      ]
+                    }
+                  }
+                ],
+                "command": "rust-analyzer.applySourceChange",
+                "title": "organize imports"
+              },
+              "kind": "source.organizeImports",
+              "title": "organize imports"
+            }
+        ]),
+    );
+}
+
+#[test]
+fn test_apply_source_change_command() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- src/lib.rs
+fn main() {}
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    let uri = server.doc_id("src/lib.rs").uri;
+    let change = json!({
+        "label": "rename main",
+        "workspaceEdit": {
+            "documentChanges": [
+                {
+                    "textDocument": { "uri": uri.as_str(), "version": null },
+                    "edits": [
+                        {
+                            "range": {
+                                "start": { "line": 0, "character": 3 },
+                                "end": { "line": 0, "character": 7 }
+                            },
+                            "newText": "start"
+                        }
+                    ]
+                }
+            ]
+        },
+        "cursorPosition": {
+            "textDocument": { "uri": uri.as_str() },
+            "position": { "line": 0, "character": 8 }
+        }
+    });
+    let params = || ExecuteCommandParams {
+        command: "rust-analyzer.applySourceChange".to_string(),
+        arguments: vec![change.clone()],
+    };
+    let (edits, cursors) = server.execute_command(params(), true);
+    assert_eq!(
+        edits,
+        vec![json!({
+            "label": "rename main",
+            "edit": change["workspaceEdit"],
+        })]
+    );
+    assert_eq!(cursors, vec![change["cursorPosition"].clone()]);
+
+    // The cursor is left alone if the client has rejected the edit.
+    let (edits, cursors) = server.execute_command(params(), false);
+    assert_eq!(edits.len(), 1);
+    assert!(cursors.is_empty());
+}
+
+#[test]
+fn test_rust_project_json() {
+    let server = project(
+        r#"
+//- rust-project.json
+{
+    "crates": [
+        {
+            "root_module": "core_lib/lib.rs",
+            "edition": "2018",
+            "cfg": ["feature=\"std\""]
+        },
+        {
+            "root_module": "app/main.rs",
+            "edition": "2018",
+            "deps": [{ "crate": 0, "name": "core_lib" }]
+        }
+    ]
+}
+
+//- core_lib/lib.rs
+pub fn helper() {}
+
+//- app/main.rs
+fn main() { core_lib::helper(); }
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    server.request::<GotoDefinition>(
+        TextDocumentPositionParams {
+            text_document: server.doc_id("app/main.rs"),
+            position: Position::new(0, 24),
+        },
+        json!([
+            {
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 18 }
+                },
+                "uri": "file:///[..]/core_lib/lib.rs"
+            }
+        ]),
+    );
+}
+
+#[test]
+fn test_bins_depend_on_own_lib() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo-bar"
+version = "0.0.0"
+
+//- src/lib.rs
+pub fn helper() {}
+
+//- src/bin/app.rs
+fn main() { foo_bar:: }
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    server.request::<Completion>(
+        CompletionParams {
+            text_document: server.doc_id("src/bin/app.rs"),
+            position: Position::new(0, 21),
+            context: None,
+        },
+        json!([
+            {
+                "filterText": "helper",
+                "insertText": "helper()$0",
+                "insertTextFormat": 2,
+                "kind": 3,
+                "label": "helper"
+            }
+        ]),
+    );
+}
+
+#[test]
+fn test_dev_dependencies() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+[dev-dependencies]
+helper = { path = "helper" }
+
+//- helper/Cargo.toml
+[package]
+name = "helper"
+version = "0.0.0"
+
+//- helper/src/lib.rs
+pub fn assist() {}
+
+//- src/lib.rs
+fn foo() { helper:: }
+#[cfg(test)]
+mod tests {
+    fn foo() { helper:: }
+}
+
+//- tests/it.rs
+fn foo() { helper:: }
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    let completion = |path: &str, line: u64, character: u64| {
+        let params = CompletionParams {
+            text_document: server.doc_id(path),
+            position: Position::new(line, character),
+            context: None,
+        };
+        let assist = json!([
+            {
+                "filterText": "assist",
+                "insertText": "assist()$0",
+                "insertTextFormat": 2,
+                "kind": 3,
+                "label": "assist"
+            }
+        ]);
+        (params, assist)
+    };
+    let (params, _) = completion("src/lib.rs", 0, 19);
+    server.request::<Completion>(params, json!([]));
+    let (params, assist) = completion("src/lib.rs", 3, 23);
+    server.request::<Completion>(params, assist);
+    let (params, assist) = completion("tests/it.rs", 0, 19);
+    server.request::<Completion>(params, assist);
+}
+
+#[test]
+fn test_non_member_packages_are_libraries() {
+    let server = project(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+[dependencies]
+vendored = { path = "vendor/vendored" }
+
+//- src/lib.rs
+pub fn foo() {}
+
+//- vendor/vendored/Cargo.toml
+[package]
+name = "vendored"
+version = "0.0.0"
+
+//- vendor/vendored/src/lib.rs
+pub fn vendored() {}
+"#,
+    );
+    server.wait_for_feedback("workspace loaded");
+    server.request::<AnalyzerStatus>(
+        (),
+        json!("[..]workspace roots: 2 (1 files)[..]/vendor/vendored (1 files)[..]"),
+    );
+}
+
+#[test]
+fn test_path_dependencies_outside_of_the_workspace() {
+    let server = project_in(
+        r#"
+//- app/Cargo.toml
+[package]
+name = "app"
+version = "0.0.0"
+
+[dependencies]
+sibling = { path = "../sibling" }
+
+//- app/src/main.rs
+fn main() { sibling::helper(); }
+
+//- sibling/Cargo.toml
+[package]
+name = "sibling"
+version = "0.0.0"
+
+//- sibling/src/lib.rs
+pub fn helper() {}
+"#,
+        "app",
+    );
+    server.wait_for_feedback("workspace loaded");
+    server.request::<AnalyzerStatus>((), json!("[..]/sibling (1 files)[..]"));
+    server.request::<GotoDefinition>(
+        TextDocumentPositionParams {
+            text_document: server.doc_id("app/src/main.rs"),
+            position: Position::new(0, 23),
+        },
+        json!([
+            {
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 18 }
+                },
+                "uri": "file:///[..]/sibling/src/lib.rs"
+            }
+        ]),
+    );
+}
+
+#[test]
+fn test_out_dirs_from_check() {
+    let config = ServerConfig {
+        cargo_features: CargoFeatures {
+            load_out_dirs_from_check: true,
+            ..CargoFeatures::default()
+        },
+        ..ServerConfig::default()
+    };
+    let server = project_with_config(
+        r#"
+//- Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- build.rs
+use std::{env, fs, path::Path};
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("generated.rs"), "pub fn generated() {}").unwrap();
+    println!("cargo:rustc-cfg=generated");
+}
+
+//- src/lib.rs
+include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+"#,
+        config,
+    );
+    server.wait_for_feedback("workspace loaded");
+    server.request::<AnalyzerStatus>((), json!("[..]/build/foo-[..]/out (1 files)[..]"));
+}
+
+#[test]
+fn test_cancel_request_only_cancels_that_request() {
+    let server = project(
+        r"
+//- lib.rs
+#[test]
+fn foo() {
+}
+",
+    );
+    server.wait_for_feedback("workspace loaded");
+    let params = || RunnablesParams {
+        text_document: server.doc_id("lib.rs"),
+        position: None,
+    };
+    let first = server.send_request_raw::<Runnables>(params());
+    let second = server.send_request_raw::<Runnables>(params());
+    server.notification::<Cancel>(CancelParams {
+        id: NumberOrString::Number(second),
+    });
+
+    let res = server.wait_for_response(first);
+    assert!(res.error.is_none(), "first request was canceled: {:?}", res);
+    assert!(res.result.is_some());
+
+    // The second request may have been answered before the cancellation
+    // arrived; otherwise it must be answered with `RequestCancelled`.
+    let res = server.wait_for_response(second);
+    if let Some(error) = res.error {
+        assert_eq!(error.code, ErrorCode::RequestCancelled as i32);
+    }
+}
+
+#[test]
+fn test_saving_a_closed_document_keeps_its_text_on_disk() {
+    let server = project(
+        r"
+//- lib.rs
+#[test]
+fn foo() {
+}
+",
+    );
+    server.wait_for_feedback("workspace loaded");
+    server.notification::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+        text_document: server.doc_id("lib.rs"),
+    });
+    server.notification::<DidSaveTextDocument>(DidSaveTextDocumentParams {
+        text_document: server.doc_id("lib.rs"),
+        text: Some("#[test]\nfn bar() {\n}\n".to_string()),
+    });
+    let id = server.send_request_raw::<Runnables>(RunnablesParams {
+        text_document: server.doc_id("lib.rs"),
+        position: None,
+    });
+    let res = server.wait_for_response(id);
+    let labels: Vec<_> = res
+        .result
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|runnable| runnable["label"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(labels, vec!["test foo", "cargo check --all"]);
+}
+
+#[test]
+fn test_shutdown_answers_pending_requests_first() {
+    let server = project(
+        r"
+//- lib.rs
+#[test]
+fn foo() {
+}
+",
+    );
+    server.wait_for_feedback("workspace loaded");
+    let ids: Vec<u64> = (0..4)
+        .map(|_| {
+            server.send_request_raw::<Runnables>(RunnablesParams {
+                text_document: server.doc_id("lib.rs"),
+                position: None,
+            })
+        })
+        .collect();
+    server.shutdown();
+    // Nothing is sent after the response to the shutdown request, so these
+    // must have been received before it.
+    for id in ids {
+        let res = server.wait_for_response(id);
+        if let Some(error) = res.error {
+            assert_eq!(error.code, ErrorCode::RequestCancelled as i32);
+        }
+    }
+}
@@ -8,23 +8,57 @@ use std::{
 
 use crossbeam_channel::{after, select, Receiver};
 use flexi_logger::Logger;
-use gen_lsp_server::{RawMessage, RawNotification, RawRequest};
+use gen_lsp_server::{RawMessage, RawNotification, RawRequest, RawResponse};
 use languageserver_types::{
-    notification::DidOpenTextDocument,
-    request::{Request, Shutdown},
-    DidOpenTextDocumentParams, TextDocumentIdentifier, TextDocumentItem, Url,
+    notification::{DidOpenTextDocument, Notification},
+    request::{ExecuteCommand, Request, Shutdown},
+    DidOpenTextDocumentParams, ExecuteCommandParams, TextDocumentIdentifier, TextDocumentItem, Url,
 };
 use serde::Serialize;
-use serde_json::{to_string_pretty, Value};
+use serde_json::{json, to_string_pretty, Value};
 use tempfile::TempDir;
 use thread_worker::{WorkerHandle, Worker};
 use test_utils::{parse_fixture, find_mismatch};
 
 use ra_lsp_server::{
-    main_loop, req,
+    main_loop, req, ClientCaps, ServerConfig,
 };
 
 pub fn project(fixture: &str) -> Server {
+    project_with_caps(fixture, ClientCaps::default())
+}
+
+/// Like `project`, but the server is started with the given configuration
+/// instead of the default one.
+pub fn project_with_config(fixture: &str, config: ServerConfig) -> Server {
+    project_with_caps_and_config(fixture, ClientCaps::default(), config)
+}
+
+/// Like `project`, but the server is started in the `root` subdirectory of
+/// the fixture, so that the other directories are outside of the workspace.
+pub fn project_in(fixture: &str, root: &str) -> Server {
+    let config = ServerConfig {
+        publish_decorations: true,
+        ..ServerConfig::default()
+    };
+    project_in_dir(fixture, root, ClientCaps::default(), config)
+}
+
+/// Like `project`, but the server talks to a client with the given
+/// capabilities.
+pub fn project_with_caps(fixture: &str, caps: ClientCaps) -> Server {
+    let config = ServerConfig {
+        publish_decorations: true,
+        ..ServerConfig::default()
+    };
+    project_with_caps_and_config(fixture, caps, config)
+}
+
+fn project_with_caps_and_config(fixture: &str, caps: ClientCaps, config: ServerConfig) -> Server {
+    project_in_dir(fixture, "", caps, config)
+}
+
+fn project_in_dir(fixture: &str, root: &str, caps: ClientCaps, config: ServerConfig) -> Server {
     static INIT: Once = Once::new();
     INIT.call_once(|| Logger::with_env_or_str(crate::LOG).start().unwrap());
 
@@ -37,11 +71,13 @@ pub fn project(fixture: &str) -> Server {
         fs::write(path.as_path(), entry.text.as_bytes()).unwrap();
         paths.push((path, entry.text));
     }
-    Server::new(tmp_dir, paths)
+    Server::new(tmp_dir, root, paths, caps, config)
 }
 
 pub struct Server {
     req_id: Cell<u64>,
+    /// Whether the shutdown request was sent already.
+    is_shut_down: Cell<bool>,
     messages: RefCell<Vec<RawMessage>>,
     dir: TempDir,
     worker: Option<Worker<RawMessage, RawMessage>>,
@@ -49,17 +85,24 @@ pub struct Server {
 }
 
 impl Server {
-    fn new(dir: TempDir, files: Vec<(PathBuf, String)>) -> Server {
-        let path = dir.path().to_path_buf();
+    fn new(
+        dir: TempDir,
+        root: &str,
+        files: Vec<(PathBuf, String)>,
+        caps: ClientCaps,
+        config: ServerConfig,
+    ) -> Server {
+        let path = dir.path().join(root);
         let (worker, watcher) = thread_worker::spawn::<RawMessage, RawMessage, _>(
             "test server",
             128,
             move |mut msg_receiver, mut msg_sender| {
-                main_loop(true, path, true, &mut msg_receiver, &mut msg_sender).unwrap()
+                main_loop(true, path, config, caps, &mut msg_receiver, &mut msg_sender).unwrap()
             },
         );
         let res = Server {
             req_id: Cell::new(1),
+            is_shut_down: Cell::new(false),
             dir,
             messages: Default::default(),
             worker: Some(worker),
@@ -108,6 +151,14 @@ impl Server {
         }
     }
 
+    pub fn notification<N>(&self, params: N::Params)
+    where
+        N: Notification,
+        N::Params: Serialize,
+    {
+        self.send_notification(RawNotification::new::<N>(&params))
+    }
+
     fn send_request<R>(&self, id: u64, params: R::Params) -> Value
     where
         R: Request,
@@ -138,6 +189,93 @@ impl Server {
         }
         panic!("no response");
     }
+    /// Sends a request without waiting for its response, which can be read
+    /// with `wait_for_response`. Returns the id of the request.
+    pub fn send_request_raw<R>(&self, params: R::Params) -> u64
+    where
+        R: Request,
+        R::Params: Serialize,
+    {
+        let id = self.req_id.get();
+        self.req_id.set(id + 1);
+        let r = RawRequest::new::<R>(id, &params);
+        self.worker
+            .as_ref()
+            .unwrap()
+            .send(RawMessage::Request(r))
+            .unwrap();
+        id
+    }
+    /// Sends the shutdown request and waits for its response. The responses
+    /// to the requests sent before it can still be read with
+    /// `wait_for_response`.
+    pub fn shutdown(&self) {
+        let id = self.send_request_raw::<Shutdown>(());
+        self.is_shut_down.set(true);
+        let res = self.wait_for_response(id);
+        assert!(res.error.is_none(), "{:?}", res);
+    }
+    /// Waits for the response to the request `id`, be it a result or an
+    /// error.
+    pub fn wait_for_response(&self, id: u64) -> RawResponse {
+        let f = |msg: &RawMessage| match msg {
+            RawMessage::Response(res) if res.id == id => Some(res.clone()),
+            _ => None,
+        };
+        if let Some(res) = self.messages.borrow().iter().find_map(f) {
+            return res;
+        }
+        loop {
+            let msg = self.recv().expect("no response");
+            if let Some(res) = f(&msg) {
+                return res;
+            }
+        }
+    }
+    /// Sends `workspace/executeCommand`, answering the `workspace/applyEdit`
+    /// requests of the server with `applied`. Returns the params of these
+    /// requests and of the `rust-analyzer/moveCursor` notifications.
+    pub fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+        applied: bool,
+    ) -> (Vec<Value>, Vec<Value>) {
+        let id = self.req_id.get();
+        self.req_id.set(id + 1);
+        let worker = self.worker.as_ref().unwrap();
+        let r = RawRequest::new::<ExecuteCommand>(id, &params);
+        worker.send(RawMessage::Request(r)).unwrap();
+        let mut edits = Vec::new();
+        let mut cursors = Vec::new();
+        while let Some(msg) = self.recv() {
+            match msg {
+                RawMessage::Request(req) => {
+                    assert_eq!(req.method, "workspace/applyEdit");
+                    edits.push(req.params);
+                    let result = json!({ "applied": applied });
+                    let resp = RawResponse {
+                        id: req.id,
+                        result: Some(result),
+                        error: None,
+                    };
+                    worker.send(RawMessage::Response(resp)).unwrap();
+                }
+                RawMessage::Notification(not) => {
+                    if not.method == "rust-analyzer/moveCursor" {
+                        cursors.push(not.params);
+                    }
+                }
+                RawMessage::Response(res) => {
+                    assert_eq!(res.id, id);
+                    if let Some(err) = res.error {
+                        panic!("error response: {:#?}", err);
+                    }
+                    return (edits, cursors);
+                }
+            }
+        }
+        panic!("no response");
+    }
     pub fn wait_for_feedback(&self, feedback: &str) {
         self.wait_for_feedback_n(feedback, 1)
     }
@@ -161,6 +299,33 @@ impl Server {
             }
         }
     }
+    /// Waits for the publication of diagnostics which come from `source`.
+    pub fn wait_for_diagnostics(&self, source: &str) -> req::PublishDiagnosticsParams {
+        let f = |msg: &RawMessage| match msg {
+            RawMessage::Notification(n) if n.method == "textDocument/publishDiagnostics" => {
+                let params = n.clone().cast::<req::PublishDiagnostics>().unwrap();
+                let from_source = params
+                    .diagnostics
+                    .iter()
+                    .any(|d| d.source.as_ref().map_or(false, |it| it == source));
+                if from_source {
+                    Some(params)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        if let Some(params) = self.messages.borrow().iter().find_map(f) {
+            return params;
+        }
+        loop {
+            let msg = self.recv().expect("no response");
+            if let Some(params) = f(&msg) {
+                return params;
+            }
+        }
+    }
     fn recv(&self) -> Option<RawMessage> {
         recv_timeout(&self.worker.as_ref().unwrap().out).map(|msg| {
             self.messages.borrow_mut().push(msg.clone());
@@ -178,7 +343,9 @@ impl Server {
 
 impl Drop for Server {
     fn drop(&mut self) {
-        self.send_request::<Shutdown>(666, ());
+        if !self.is_shut_down.get() {
+            self.send_request::<Shutdown>(666, ());
+        }
         let receiver = self.worker.take().unwrap().shutdown();
         while let Some(msg) = recv_timeout(&receiver) {
             drop(msg);
@@ -1,4 +1,7 @@
 pub mod visit;
+pub mod trivia;
+
+use ra_text_edit::{TextEdit, TextEditBuilder};
 
 use crate::{SyntaxNode, SyntaxNodeRef, TextRange, TextUnit};
 
@@ -24,3 +27,138 @@ pub fn generate<T>(seed: Option<T>, step: impl Fn(&T) -> Option<T>) -> impl Iter
         })
     })
 }
+
+/// Finds a minimal `TextEdit` which transforms the text of `from` into the
+/// text of `to`. The trees are compared structurally, so the edit touches only
+/// the subtrees which differ. This is useful for tree-rewriting code which
+/// should not send the whole file to the client.
+pub fn diff(from: SyntaxNodeRef, to: SyntaxNodeRef) -> TextEdit {
+    let mut builder = TextEditBuilder::default();
+    go(&mut builder, from, to);
+    return builder.finish();
+
+    fn go(builder: &mut TextEditBuilder, from: SyntaxNodeRef, to: SyntaxNodeRef) {
+        if from.kind() == to.kind() && text_eq(from, to) {
+            return;
+        }
+        if from.kind() != to.kind() || from.is_leaf() || to.is_leaf() {
+            builder.replace(from.range(), to.text().to_string());
+            return;
+        }
+
+        let from_children = from.children().collect::<Vec<_>>();
+        let to_children = to.children().collect::<Vec<_>>();
+        let same =
+            |(f, t): &(&SyntaxNodeRef, &SyntaxNodeRef)| f.kind() == t.kind() && text_eq(**f, **t);
+        let prefix = from_children
+            .iter()
+            .zip(to_children.iter())
+            .take_while(same)
+            .count();
+        let suffix = from_children[prefix..]
+            .iter()
+            .rev()
+            .zip(to_children[prefix..].iter().rev())
+            .take_while(same)
+            .count();
+        let from_mid = &from_children[prefix..from_children.len() - suffix];
+        let to_mid = &to_children[prefix..to_children.len() - suffix];
+
+        let pairwise = from_mid.len() == to_mid.len()
+            && from_mid
+                .iter()
+                .zip(to_mid.iter())
+                .all(|(f, t)| f.kind() == t.kind());
+        if pairwise {
+            for (f, t) in from_mid.iter().zip(to_mid.iter()) {
+                go(builder, *f, *t);
+            }
+            return;
+        }
+
+        let delete = match (from_mid.first(), from_mid.last()) {
+            (Some(first), Some(last)) => {
+                TextRange::from_to(first.range().start(), last.range().end())
+            }
+            _ => {
+                let offset = match from_children.get(prefix) {
+                    Some(next) => next.range().start(),
+                    None => from.range().end(),
+                };
+                TextRange::offset_len(offset, 0.into())
+            }
+        };
+        let mut insert = String::new();
+        for t in to_mid {
+            t.text().push_to(&mut insert);
+        }
+        builder.replace(delete, insert);
+    }
+
+    fn text_eq(from: SyntaxNodeRef, to: SyntaxNodeRef) -> bool {
+        let from = from.text();
+        let to = to.text();
+        from.len() == to.len()
+            && from
+                .chunks()
+                .flat_map(str::chars)
+                .eq(to.chunks().flat_map(str::chars))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SourceFileNode;
+
+    use super::*;
+
+    fn check_diff(before: &str, after: &str, expected_edits: &[(&str, &str)]) {
+        let from = SourceFileNode::parse(before);
+        let to = SourceFileNode::parse(after);
+        let edit = diff(from.syntax(), to.syntax());
+        assert_eq!(edit.apply(before), after);
+        let actual = edit
+            .as_atoms()
+            .iter()
+            .map(|atom| (&before[atom.delete], atom.insert.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected_edits);
+    }
+
+    #[test]
+    fn diff_identical_trees() {
+        check_diff("fn foo() { 1 + 1 }", "fn foo() { 1 + 1 }", &[]);
+    }
+
+    #[test]
+    fn diff_changes_only_modified_leaf() {
+        check_diff(
+            "fn foo() {}\nfn bar() { 92 }\nfn baz() {}\n",
+            "fn foo() {}\nfn bar() { 62 }\nfn baz() {}\n",
+            &[("92", "62")],
+        );
+    }
+
+    #[test]
+    fn diff_inserts_and_deletes_items() {
+        check_diff(
+            "struct A;\nstruct B;\n",
+            "struct A;\nstruct C;\nstruct B;\n",
+            &[("", "struct C;\n")],
+        );
+        check_diff(
+            "struct A;\nstruct B;\n",
+            "struct A;\n",
+            &[("struct B;\n", "")],
+        );
+    }
+
+    #[test]
+    fn diff_replaces_node_of_different_kind() {
+        check_diff(
+            "fn foo() { let x = 1; }",
+            "fn foo() { let x = (1, 2); }",
+            &[("1", "(1, 2)")],
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Binds comments and attributes to the items and statements they describe.
+//!
+//! The parser attaches only some of the leading comments to items (see
+//! `n_attached_trivias`), the rest end up as siblings of the node. Refactorings
+//! which move code around should move the comments as well, so this module
+//! reconstructs the association:
+//!
+//! - comments and attributes directly preceding a node (without a blank line
+//!   in between) belong to this node,
+//! - a comment on the same line after the node (possibly after a `,` or `;`)
+//!   belongs to the node as well.
+use crate::{
+    ast, AstNode, Direction, SyntaxNodeRef, TextRange, TextUnit,
+    SyntaxKind::{ATTR, COMMA, COMMENT, SEMI, WHITESPACE},
+};
+
+/// Comments and attributes which precede `node` and belong to it, in source
+/// order. Trivia which the parser has already put inside the node are not
+/// included.
+pub fn leading_trivia(node: SyntaxNodeRef) -> Vec<SyntaxNodeRef> {
+    let mut res = Vec::new();
+    let mut curr = node.prev_sibling();
+    while let Some(it) = curr {
+        match it.kind() {
+            WHITESPACE => {
+                if newlines(it) > 1 {
+                    break;
+                }
+            }
+            COMMENT => {
+                if is_trailing_comment(it) {
+                    break;
+                }
+                res.push(it);
+            }
+            ATTR => res.push(it),
+            _ => break,
+        }
+        curr = it.prev_sibling();
+    }
+    res.reverse();
+    res
+}
+
+/// A comment which follows `node` on the same line.
+pub fn trailing_comment(node: SyntaxNodeRef) -> Option<ast::Comment> {
+    let mut seen_separator = false;
+    let mut curr = node.next_sibling();
+    while let Some(it) = curr {
+        match it.kind() {
+            WHITESPACE if newlines(it) == 0 => (),
+            COMMA | SEMI if !seen_separator => seen_separator = true,
+            COMMENT => return ast::Comment::cast(it),
+            // The parser might have attached the comment to the next node.
+            _ => return it.first_child().and_then(ast::Comment::cast),
+        }
+        curr = it.next_sibling();
+    }
+    None
+}
+
+/// The range of `node` extended to cover its leading and trailing trivia.
+pub fn extended_range(node: SyntaxNodeRef) -> TextRange {
+    let start = match leading_trivia(node).first() {
+        Some(it) => it.range().start(),
+        None => own_start(node),
+    };
+    let end = match trailing_comment(node) {
+        Some(it) => it.syntax().range().end(),
+        None => node.range().end(),
+    };
+    TextRange::from_to(start, end)
+}
+
+/// Start of the node, skipping a comment of the previous node which the parser
+/// has attached to this one.
+fn own_start(node: SyntaxNodeRef) -> TextUnit {
+    match node.first_child() {
+        Some(comment) if comment.kind() == COMMENT && is_trailing_comment(comment) => comment
+            .siblings(Direction::Next)
+            .skip(1)
+            .find(|it| it.kind() != WHITESPACE)
+            .map(|it| it.range().start())
+            .unwrap_or_else(|| node.range().start()),
+        _ => node.range().start(),
+    }
+}
+
+fn is_trailing_comment(comment: SyntaxNodeRef) -> bool {
+    let prev = match comment.prev_sibling() {
+        Some(it) => it,
+        None => match comment.parent().and_then(|it| it.prev_sibling()) {
+            Some(it) => it,
+            None => return false,
+        },
+    };
+    let prev = if prev.kind() == WHITESPACE {
+        if newlines(prev) > 0 {
+            return false;
+        }
+        match prev.prev_sibling() {
+            Some(it) => it,
+            None => return false,
+        }
+    } else {
+        prev
+    };
+    prev.kind() != COMMENT
+}
+
+fn newlines(whitespace: SyntaxNodeRef) -> usize {
+    whitespace
+        .leaf_text()
+        .map_or(0, |text| text.matches('\n').count())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast, AstNode, SourceFileNode};
+
+    use super::*;
+
+    fn check_extended_range<'a, N: AstNode<'a>>(
+        file: &'a SourceFileNode,
+        name: &str,
+        expected: &str,
+    ) {
+        let node = file
+            .syntax()
+            .descendants()
+            .filter_map(N::cast)
+            .find(|it| {
+                it.syntax()
+                    .descendants()
+                    .filter_map(ast::Name::cast)
+                    .next()
+                    .map(|n| n.text() == name)
+                    == Some(true)
+            })
+            .unwrap();
+        let range = extended_range(node.syntax());
+        assert_eq!(&file.syntax().text().to_string()[range], expected);
+    }
+
+    #[test]
+    fn binds_leading_comments_and_attributes() {
+        let file = SourceFileNode::parse(
+            "
+fn foo() {}
+
+// about bar
+#[inline]
+fn bar() {}
+",
+        );
+        check_extended_range::<ast::FnDef>(&file, "bar", "// about bar\n#[inline]\nfn bar() {}");
+        check_extended_range::<ast::FnDef>(&file, "foo", "fn foo() {}");
+    }
+
+    #[test]
+    fn binds_trailing_comments() {
+        let file = SourceFileNode::parse(
+            "
+struct S {
+    // the first field
+    a: u32, // an `a`
+    b: u32,
+}
+struct A; // about A
+struct B;
+",
+        );
+        check_extended_range::<ast::NamedFieldDef>(
+            &file,
+            "a",
+            "// the first field\n    a: u32, // an `a`",
+        );
+        check_extended_range::<ast::NamedFieldDef>(&file, "b", "b: u32");
+        check_extended_range::<ast::StructDef>(&file, "A", "struct A; // about A");
+        check_extended_range::<ast::StructDef>(&file, "B", "struct B;");
+    }
+
+    #[test]
+    fn binds_comments_to_statements() {
+        let file = SourceFileNode::parse(
+            "
+fn foo() {
+    // the answer
+    let x = 92;
+}
+",
+        );
+        let stmt = file
+            .syntax()
+            .descendants()
+            .find_map(ast::LetStmt::cast)
+            .unwrap();
+        let trivia = leading_trivia(stmt.syntax());
+        assert_eq!(trivia.len(), 1);
+        assert_eq!(trivia[0].text(), "// the answer");
+    }
+}
@@ -7,6 +7,8 @@ use itertools::Itertools;
 
 pub use self::generated::*;
 use crate::{
+    algo::generate,
+    string_lexing,
     yellow::{RefRoot, SyntaxNodeChildren},
     SmolStr,
     SyntaxKind::*,
@@ -40,6 +42,10 @@ pub trait LoopBodyOwner<'a>: AstNode<'a> {
     fn loop_body(self) -> Option<Block<'a>> {
         child_opt(self)
     }
+
+    fn label(self) -> Option<Label<'a>> {
+        child_opt(self)
+    }
 }
 
 pub trait ArgListOwner<'a>: AstNode<'a> {
@@ -141,7 +147,7 @@ impl<'a> Attr<'a> {
         let tt = self.value()?;
         let (_bra, attr, _ket) = tt.syntax().children().collect_tuple()?;
         if attr.kind() == IDENT {
-            Some(attr.leaf_text().unwrap().clone())
+            attr.leaf_text().cloned()
         } else {
             None
         }
@@ -152,7 +158,7 @@ impl<'a> Attr<'a> {
         let (_bra, attr, args, _ket) = tt.syntax().children().collect_tuple()?;
         let args = TokenTree::cast(args)?;
         if attr.kind() == IDENT {
-            Some((attr.leaf_text().unwrap().clone(), args))
+            Some((attr.leaf_text()?.clone(), args))
         } else {
             None
         }
@@ -169,24 +175,73 @@ impl<'a> Char<'a> {
     pub fn text(&self) -> &SmolStr {
         &self.syntax().leaf_text().unwrap()
     }
+
+    /// The unescaped value, or `None` if the literal is malformed.
+    pub fn value(&self) -> Option<char> {
+        let text = self.text();
+        let value = string_lexing::unescape(text, string_lexing::parse_char_literal(text))?;
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    }
 }
 
 impl<'a> Byte<'a> {
     pub fn text(&self) -> &SmolStr {
         &self.syntax().leaf_text().unwrap()
     }
+
+    /// The unescaped value, or `None` if the literal is malformed.
+    pub fn value(&self) -> Option<u8> {
+        let text = self.text();
+        let value = string_lexing::unescape(text, string_lexing::parse_byte_literal(text))?;
+        match bytes_of(&value)?.as_slice() {
+            &[b] => Some(b),
+            _ => None,
+        }
+    }
 }
 
 impl<'a> ByteString<'a> {
     pub fn text(&self) -> &SmolStr {
         &self.syntax().leaf_text().unwrap()
     }
+
+    /// The unescaped value, or `None` if the literal is malformed.
+    pub fn value(&self) -> Option<Vec<u8>> {
+        let text = self.text();
+        let value = string_lexing::unescape(text, string_lexing::parse_byte_string_literal(text))?;
+        bytes_of(&value)
+    }
 }
 
 impl<'a> String<'a> {
     pub fn text(&self) -> &SmolStr {
         &self.syntax().leaf_text().unwrap()
     }
+
+    /// The unescaped value, or `None` if the literal is malformed.
+    pub fn value(&self) -> Option<RustString> {
+        let text = self.text();
+        string_lexing::unescape(text, string_lexing::parse_string_literal(text))
+    }
+}
+
+/// The bytes of an unescaped byte or byte string literal, where `\xFF` escapes
+/// are unescaped to the chars up to `U+00FF`.
+fn bytes_of(value: &str) -> Option<Vec<u8>> {
+    value
+        .chars()
+        .map(|c| {
+            if (c as u32) <= 0xFF {
+                Some(c as u8)
+            } else {
+                None
+            }
+        })
+        .collect()
 }
 
 impl<'a> Comment<'a> {
@@ -266,19 +321,26 @@ impl<'a> Whitespace<'a> {
 }
 
 impl<'a> Name<'a> {
+    /// Text of the identifier. Empty if the name is malformed.
     pub fn text(&self) -> SmolStr {
-        let ident = self.syntax().first_child().unwrap();
-        ident.leaf_text().unwrap().clone()
+        ident_text(self.syntax())
     }
 }
 
 impl<'a> NameRef<'a> {
+    /// Text of the identifier. Empty if the name is malformed.
     pub fn text(&self) -> SmolStr {
-        let ident = self.syntax().first_child().unwrap();
-        ident.leaf_text().unwrap().clone()
+        ident_text(self.syntax())
     }
 }
 
+fn ident_text(node: SyntaxNodeRef) -> SmolStr {
+    node.first_child()
+        .and_then(|ident| ident.leaf_text())
+        .cloned()
+        .unwrap_or_else(|| SmolStr::new(""))
+}
+
 impl<'a> ImplBlock<'a> {
     pub fn target_type(self) -> Option<TypeRef<'a>> {
         match self.target() {
@@ -320,12 +382,23 @@ impl<'a> LetStmt<'a> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElseBranch<'a> {
+    Block(Block<'a>),
+    /// `else if`
+    IfExpr(IfExpr<'a>),
+}
+
 impl<'a> IfExpr<'a> {
     pub fn then_branch(self) -> Option<Block<'a>> {
         self.blocks().nth(0)
     }
-    pub fn else_branch(self) -> Option<Block<'a>> {
-        self.blocks().nth(1)
+    pub fn else_branch(self) -> Option<ElseBranch<'a>> {
+        if let Some(block) = self.blocks().nth(1) {
+            return Some(ElseBranch::Block(block));
+        }
+        let else_if = child_opt(self)?;
+        Some(ElseBranch::IfExpr(else_if))
     }
     fn blocks(self) -> AstChildren<'a, Block<'a>> {
         children(self)
@@ -341,11 +414,8 @@ pub enum PathSegmentKind<'a> {
 }
 
 impl<'a> PathSegment<'a> {
-    pub fn parent_path(self) -> Path<'a> {
-        self.syntax()
-            .parent()
-            .and_then(Path::cast)
-            .expect("segments are always nested in paths")
+    pub fn parent_path(self) -> Option<Path<'a>> {
+        self.syntax().parent().and_then(Path::cast)
     }
 
     pub fn kind(self) -> Option<PathSegmentKind<'a>> {
@@ -361,6 +431,21 @@ impl<'a> PathSegment<'a> {
         };
         Some(res)
     }
+
+    /// The self type and the trait of a qualified segment, like `<T as Trait>`
+    /// in `<T as Trait>::Assoc`.
+    pub fn qualified_type(self) -> Option<(TypeRef<'a>, Option<PathType<'a>>)> {
+        if self.syntax().first_child()?.kind() != L_ANGLE {
+            return None;
+        }
+        let mut type_refs = self.syntax().children().filter_map(TypeRef::cast);
+        let self_type = type_refs.next()?;
+        let trait_type = match type_refs.next() {
+            Some(TypeRef::PathType(path_type)) => Some(path_type),
+            _ => None,
+        };
+        Some((self_type, trait_type))
+    }
 }
 
 impl<'a> Path<'a> {
@@ -376,11 +461,8 @@ impl<'a> UseTree<'a> {
 }
 
 impl<'a> UseTreeList<'a> {
-    pub fn parent_use_tree(self) -> UseTree<'a> {
-        self.syntax()
-            .parent()
-            .and_then(UseTree::cast)
-            .expect("UseTreeLists are always nested in UseTrees")
+    pub fn parent_use_tree(self) -> Option<UseTree<'a>> {
+        self.syntax().parent().and_then(UseTree::cast)
     }
 }
 
@@ -455,6 +537,18 @@ impl<'a> PointerType<'a> {
     }
 }
 
+impl<'a> ImplTraitType<'a> {
+    /// The traits in `impl A + B`. The parser nests each bound after the first
+    /// one into the `PathType` of the previous bound.
+    pub fn bounds(self) -> impl Iterator<Item = Path<'a>> + 'a {
+        let first = self.syntax().children().find_map(PathType::cast);
+        generate(first, |bound| {
+            bound.syntax().children().find_map(PathType::cast)
+        })
+        .filter_map(|bound| bound.path())
+    }
+}
+
 impl<'a> ReferenceType<'a> {
     pub fn is_mut(&self) -> bool {
         self.syntax().children().any(|n| n.kind() == MUT_KW)
@@ -467,6 +561,28 @@ impl<'a> RefExpr<'a> {
     }
 }
 
+impl<'a> LambdaExpr<'a> {
+    pub fn is_move(&self) -> bool {
+        self.syntax().children().any(|n| n.kind() == MOVE_KW)
+    }
+}
+
+impl<'a> FieldExpr<'a> {
+    /// The index of the accessed tuple field, like `1` in `t.1`.
+    pub fn index(&self) -> Option<usize> {
+        self.syntax()
+            .children()
+            .find(|n| n.kind() == INT_NUMBER)
+            .and_then(|n| n.leaf_text()?.parse().ok())
+    }
+}
+
+impl<'a> BlockExpr<'a> {
+    pub fn is_async(&self) -> bool {
+        self.syntax().children().any(|n| n.kind() == ASYNC_KW)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum PrefixOp {
     /// The `*` operator for dereferencing
@@ -504,7 +620,71 @@ pub enum BinOp {
     LesserTest,
     /// The `>` operator for comparison
     GreaterTest,
-    // TODO: lots of others
+    /// The `!=` operator for inequality testing
+    NegatedEqualityTest,
+    /// The `+` operator for addition
+    Addition,
+    /// The `-` operator for subtraction
+    Subtraction,
+    /// The `*` operator for multiplication
+    Multiplication,
+    /// The `/` operator for division
+    Division,
+    /// The `%` operator for remainder after division
+    Remainder,
+    /// The `<<` operator for left shift
+    LeftShift,
+    /// The `>>` operator for right shift
+    RightShift,
+    /// The `^` operator for bitwise XOR
+    BitwiseXor,
+    /// The `|` operator for bitwise OR
+    BitwiseOr,
+    /// The `&` operator for bitwise AND
+    BitwiseAnd,
+    /// The `=` operator for assignment
+    Assignment,
+    /// The `+=` operator for assignment after addition
+    AddAssign,
+    /// The `-=` operator for assignment after subtraction
+    SubAssign,
+    /// The `*=` operator for assignment after multiplication
+    MulAssign,
+    /// The `/=` operator for assignment after division
+    DivAssign,
+    /// The `%=` operator for assignment after remainder
+    RemAssign,
+    /// The `<<=` operator for assignment after left shift
+    ShlAssign,
+    /// The `>>=` operator for assignment after right shift
+    ShrAssign,
+    /// The `^=` operator for assignment after bitwise XOR
+    BitXorAssign,
+    /// The `|=` operator for assignment after bitwise OR
+    BitOrAssign,
+    /// The `&=` operator for assignment after bitwise AND
+    BitAndAssign,
+}
+
+impl BinOp {
+    /// Whether the operator assigns to its left operand, either plainly or
+    /// after applying an arithmetic or bitwise operation.
+    pub fn is_assignment(self) -> bool {
+        match self {
+            BinOp::Assignment
+            | BinOp::AddAssign
+            | BinOp::SubAssign
+            | BinOp::MulAssign
+            | BinOp::DivAssign
+            | BinOp::RemAssign
+            | BinOp::ShlAssign
+            | BinOp::ShrAssign
+            | BinOp::BitXorAssign
+            | BinOp::BitOrAssign
+            | BinOp::BitAndAssign => true,
+            _ => false,
+        }
+    }
 }
 
 impl<'a> BinExpr<'a> {
@@ -519,6 +699,28 @@ impl<'a> BinExpr<'a> {
                 GTEQ => Some(BinOp::GreaterEqualTest),
                 L_ANGLE => Some(BinOp::LesserTest),
                 R_ANGLE => Some(BinOp::GreaterTest),
+                NEQ => Some(BinOp::NegatedEqualityTest),
+                PLUS => Some(BinOp::Addition),
+                MINUS => Some(BinOp::Subtraction),
+                STAR => Some(BinOp::Multiplication),
+                SLASH => Some(BinOp::Division),
+                PERCENT => Some(BinOp::Remainder),
+                SHL => Some(BinOp::LeftShift),
+                SHR => Some(BinOp::RightShift),
+                CARET => Some(BinOp::BitwiseXor),
+                PIPE => Some(BinOp::BitwiseOr),
+                AMP => Some(BinOp::BitwiseAnd),
+                EQ => Some(BinOp::Assignment),
+                PLUSEQ => Some(BinOp::AddAssign),
+                MINUSEQ => Some(BinOp::SubAssign),
+                STAREQ => Some(BinOp::MulAssign),
+                SLASHEQ => Some(BinOp::DivAssign),
+                PERCENTEQ => Some(BinOp::RemAssign),
+                SHLEQ => Some(BinOp::ShlAssign),
+                SHREQ => Some(BinOp::ShrAssign),
+                CARETEQ => Some(BinOp::BitXorAssign),
+                PIPEEQ => Some(BinOp::BitOrAssign),
+                AMPEQ => Some(BinOp::BitAndAssign),
                 _ => None,
             })
             .next()
@@ -540,6 +742,156 @@ impl<'a> BinExpr<'a> {
     }
 }
 
+impl<'a> IndexExpr<'a> {
+    pub fn base(self) -> Option<Expr<'a>> {
+        children(self).nth(0)
+    }
+
+    pub fn index(self) -> Option<Expr<'a>> {
+        children(self).nth(1)
+    }
+}
+
+impl<'a> RangeExpr<'a> {
+    fn op(self) -> Option<SyntaxNodeRef<'a>> {
+        self.syntax()
+            .children()
+            .find(|it| it.kind() == DOTDOT || it.kind() == DOTDOTEQ)
+    }
+
+    /// Whether the range is `a..=b`.
+    pub fn is_inclusive(self) -> bool {
+        self.op().map_or(false, |op| op.kind() == DOTDOTEQ)
+    }
+
+    pub fn lhs(self) -> Option<Expr<'a>> {
+        let op = self.op()?;
+        self.syntax()
+            .children()
+            .take_while(|it| *it != op)
+            .find_map(Expr::cast)
+    }
+
+    pub fn rhs(self) -> Option<Expr<'a>> {
+        let op = self.op()?;
+        self.syntax()
+            .children()
+            .skip_while(|it| *it != op)
+            .find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug)]
+pub enum ArrayExprKind<'a> {
+    /// `[x; n]`
+    Repeat {
+        initializer: Option<Expr<'a>>,
+        repeat: Option<Expr<'a>>,
+    },
+    /// `[a, b, c]`
+    ElementList(AstChildren<'a, Expr<'a>>),
+}
+
+impl<'a> FieldPatList<'a> {
+    /// Whether the pattern ends with `..`, ignoring the remaining fields.
+    pub fn has_ellipsis(self) -> bool {
+        self.syntax().children().any(|it| it.kind() == DOTDOT)
+    }
+}
+
+impl<'a> BindPat<'a> {
+    pub fn is_ref(&self) -> bool {
+        self.syntax().children().any(|n| n.kind() == REF_KW)
+    }
+
+    pub fn is_mut(&self) -> bool {
+        self.syntax().children().any(|n| n.kind() == MUT_KW)
+    }
+
+    /// Whether this is `name @ ..`, which binds the rest of a slice.
+    pub fn is_rest(&self) -> bool {
+        self.syntax().children().any(|n| n.kind() == DOTDOT)
+    }
+}
+
+impl<'a> RefPat<'a> {
+    pub fn is_mut(&self) -> bool {
+        self.syntax().children().any(|n| n.kind() == MUT_KW)
+    }
+}
+
+impl<'a> TuplePat<'a> {
+    /// The number of patterns before the `..`, if there is one.
+    pub fn ellipsis_position(&self) -> Option<usize> {
+        self.syntax()
+            .children()
+            .filter(|n| n.kind() == DOTDOT || Pat::cast(*n).is_some())
+            .position(|n| n.kind() == DOTDOT)
+    }
+}
+
+impl<'a> LiteralPat<'a> {
+    /// Whether the literal is negated, like `-1`.
+    pub fn is_negative(&self) -> bool {
+        self.syntax().children().any(|n| n.kind() == MINUS)
+    }
+}
+
+impl<'a> RangePat<'a> {
+    pub fn start(self) -> Option<Pat<'a>> {
+        children(self).nth(0)
+    }
+
+    pub fn end(self) -> Option<Pat<'a>> {
+        children(self).nth(1)
+    }
+
+    /// Whether the range is `a..=b` or `a...b`.
+    pub fn is_inclusive(&self) -> bool {
+        self.syntax()
+            .children()
+            .any(|n| n.kind() == DOTDOTEQ || n.kind() == DOTDOTDOT)
+    }
+}
+
+#[derive(Debug)]
+pub enum SlicePatElement<'a> {
+    Pat(Pat<'a>),
+    /// `..`, or `name @ ..` if it's bound.
+    Rest(Option<BindPat<'a>>),
+}
+
+impl<'a> SlicePat<'a> {
+    pub fn elements(self) -> impl Iterator<Item = SlicePatElement<'a>> + 'a {
+        self.syntax().children().filter_map(|it| {
+            if it.kind() == DOTDOT {
+                return Some(SlicePatElement::Rest(None));
+            }
+            let pat = Pat::cast(it)?;
+            let element = match pat {
+                Pat::BindPat(bind_pat) if bind_pat.is_rest() => {
+                    SlicePatElement::Rest(Some(bind_pat))
+                }
+                _ => SlicePatElement::Pat(pat),
+            };
+            Some(element)
+        })
+    }
+}
+
+impl<'a> ArrayExpr<'a> {
+    pub fn kind(self) -> ArrayExprKind<'a> {
+        if self.syntax().children().any(|it| it.kind() == SEMI) {
+            ArrayExprKind::Repeat {
+                initializer: children(self).nth(0),
+                repeat: children(self).nth(1),
+            }
+        } else {
+            ArrayExprKind::ElementList(children(self))
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum SelfParamFlavor {
     /// self
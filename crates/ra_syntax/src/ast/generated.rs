@@ -141,6 +141,51 @@ impl<'a> ArrayType<'a> {
     }
 }
 
+// AssocTypeArg
+#[derive(Debug, Clone, Copy,)]
+pub struct AssocTypeArgNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type AssocTypeArg<'a> = AssocTypeArgNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<AssocTypeArgNode<R1>> for AssocTypeArgNode<R2> {
+    fn eq(&self, other: &AssocTypeArgNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for AssocTypeArgNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for AssocTypeArgNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for AssocTypeArg<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            ASSOC_TYPE_ARG => Some(AssocTypeArg { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> AssocTypeArgNode<R> {
+    pub fn borrowed(&self) -> AssocTypeArg {
+        AssocTypeArgNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> AssocTypeArgNode {
+        AssocTypeArgNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> AssocTypeArg<'a> {
+    pub fn name_ref(self) -> Option<NameRef<'a>> {
+        super::child_opt(self)
+    }
+
+    pub fn type_ref(self) -> Option<TypeRef<'a>> {
+        super::child_opt(self)
+    }
+}
+
 // Attr
 #[derive(Debug, Clone, Copy,)]
 pub struct AttrNode<R: TreeRoot<RaTypes> = OwnedRoot> {
@@ -182,6 +227,47 @@ impl<'a> Attr<'a> {
     }
 }
 
+// AwaitExpr
+#[derive(Debug, Clone, Copy,)]
+pub struct AwaitExprNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type AwaitExpr<'a> = AwaitExprNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<AwaitExprNode<R1>> for AwaitExprNode<R2> {
+    fn eq(&self, other: &AwaitExprNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for AwaitExprNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for AwaitExprNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for AwaitExpr<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            AWAIT_EXPR => Some(AwaitExpr { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> AwaitExprNode<R> {
+    pub fn borrowed(&self) -> AwaitExpr {
+        AwaitExprNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> AwaitExprNode {
+        AwaitExprNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> AwaitExpr<'a> {
+    pub fn expr(self) -> Option<Expr<'a>> {
+        super::child_opt(self)
+    }
+}
+
 // BinExpr
 #[derive(Debug, Clone, Copy,)]
 pub struct BinExprNode<R: TreeRoot<RaTypes> = OwnedRoot> {
@@ -255,7 +341,11 @@ impl<R: TreeRoot<RaTypes>> BindPatNode<R> {
 
 
 impl<'a> ast::NameOwner<'a> for BindPat<'a> {}
-impl<'a> BindPat<'a> {}
+impl<'a> BindPat<'a> {
+    pub fn pat(self) -> Option<Pat<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // Block
 #[derive(Debug, Clone, Copy,)]
@@ -343,6 +433,88 @@ impl<'a> BlockExpr<'a> {
     }
 }
 
+// BoxExpr
+#[derive(Debug, Clone, Copy,)]
+pub struct BoxExprNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type BoxExpr<'a> = BoxExprNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<BoxExprNode<R1>> for BoxExprNode<R2> {
+    fn eq(&self, other: &BoxExprNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for BoxExprNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for BoxExprNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for BoxExpr<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            BOX_EXPR => Some(BoxExpr { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> BoxExprNode<R> {
+    pub fn borrowed(&self) -> BoxExpr {
+        BoxExprNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> BoxExprNode {
+        BoxExprNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> BoxExpr<'a> {
+    pub fn expr(self) -> Option<Expr<'a>> {
+        super::child_opt(self)
+    }
+}
+
+// BoxPat
+#[derive(Debug, Clone, Copy,)]
+pub struct BoxPatNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type BoxPat<'a> = BoxPatNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<BoxPatNode<R1>> for BoxPatNode<R2> {
+    fn eq(&self, other: &BoxPatNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for BoxPatNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for BoxPatNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for BoxPat<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            BOX_PAT => Some(BoxPat { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> BoxPatNode<R> {
+    pub fn borrowed(&self) -> BoxPat {
+        BoxPatNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> BoxPatNode {
+        BoxPatNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> BoxPat<'a> {
+    pub fn pat(self) -> Option<Pat<'a>> {
+        super::child_opt(self)
+    }
+}
+
 // BreakExpr
 #[derive(Debug, Clone, Copy,)]
 pub struct BreakExprNode<R: TreeRoot<RaTypes> = OwnedRoot> {
@@ -382,6 +554,10 @@ impl<'a> BreakExpr<'a> {
     pub fn expr(self) -> Option<Expr<'a>> {
         super::child_opt(self)
     }
+
+    pub fn lifetime(self) -> Option<Lifetime<'a>> {
+        super::child_opt(self)
+    }
 }
 
 // Byte
@@ -704,7 +880,15 @@ impl<'a> ast::NameOwner<'a> for ConstDef<'a> {}
 impl<'a> ast::TypeParamsOwner<'a> for ConstDef<'a> {}
 impl<'a> ast::AttrsOwner<'a> for ConstDef<'a> {}
 impl<'a> ast::DocCommentsOwner<'a> for ConstDef<'a> {}
-impl<'a> ConstDef<'a> {}
+impl<'a> ConstDef<'a> {
+    pub fn type_ref(self) -> Option<TypeRef<'a>> {
+        super::child_opt(self)
+    }
+
+    pub fn body(self) -> Option<Expr<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // ContinueExpr
 #[derive(Debug, Clone, Copy,)]
@@ -741,7 +925,11 @@ impl<R: TreeRoot<RaTypes>> ContinueExprNode<R> {
 }
 
 
-impl<'a> ContinueExpr<'a> {}
+impl<'a> ContinueExpr<'a> {
+    pub fn lifetime(self) -> Option<Lifetime<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // DynTraitType
 #[derive(Debug, Clone, Copy,)]
@@ -923,7 +1111,6 @@ pub enum Expr<'a> {
     WhileExpr(WhileExpr<'a>),
     ContinueExpr(ContinueExpr<'a>),
     BreakExpr(BreakExpr<'a>),
-    Label(Label<'a>),
     BlockExpr(BlockExpr<'a>),
     ReturnExpr(ReturnExpr<'a>),
     MatchExpr(MatchExpr<'a>),
@@ -933,12 +1120,15 @@ pub enum Expr<'a> {
     MethodCallExpr(MethodCallExpr<'a>),
     FieldExpr(FieldExpr<'a>),
     TryExpr(TryExpr<'a>),
+    AwaitExpr(AwaitExpr<'a>),
     CastExpr(CastExpr<'a>),
     RefExpr(RefExpr<'a>),
     PrefixExpr(PrefixExpr<'a>),
+    BoxExpr(BoxExpr<'a>),
     RangeExpr(RangeExpr<'a>),
     BinExpr(BinExpr<'a>),
     Literal(Literal<'a>),
+    MacroCall(MacroCall<'a>),
 }
 
 impl<'a> AstNode<'a> for Expr<'a> {
@@ -955,7 +1145,6 @@ impl<'a> AstNode<'a> for Expr<'a> {
             WHILE_EXPR => Some(Expr::WhileExpr(WhileExpr { syntax })),
             CONTINUE_EXPR => Some(Expr::ContinueExpr(ContinueExpr { syntax })),
             BREAK_EXPR => Some(Expr::BreakExpr(BreakExpr { syntax })),
-            LABEL => Some(Expr::Label(Label { syntax })),
             BLOCK_EXPR => Some(Expr::BlockExpr(BlockExpr { syntax })),
             RETURN_EXPR => Some(Expr::ReturnExpr(ReturnExpr { syntax })),
             MATCH_EXPR => Some(Expr::MatchExpr(MatchExpr { syntax })),
@@ -965,12 +1154,15 @@ impl<'a> AstNode<'a> for Expr<'a> {
             METHOD_CALL_EXPR => Some(Expr::MethodCallExpr(MethodCallExpr { syntax })),
             FIELD_EXPR => Some(Expr::FieldExpr(FieldExpr { syntax })),
             TRY_EXPR => Some(Expr::TryExpr(TryExpr { syntax })),
+            AWAIT_EXPR => Some(Expr::AwaitExpr(AwaitExpr { syntax })),
             CAST_EXPR => Some(Expr::CastExpr(CastExpr { syntax })),
             REF_EXPR => Some(Expr::RefExpr(RefExpr { syntax })),
             PREFIX_EXPR => Some(Expr::PrefixExpr(PrefixExpr { syntax })),
+            BOX_EXPR => Some(Expr::BoxExpr(BoxExpr { syntax })),
             RANGE_EXPR => Some(Expr::RangeExpr(RangeExpr { syntax })),
             BIN_EXPR => Some(Expr::BinExpr(BinExpr { syntax })),
             LITERAL => Some(Expr::Literal(Literal { syntax })),
+            MACRO_CALL => Some(Expr::MacroCall(MacroCall { syntax })),
             _ => None,
         }
     }
@@ -987,7 +1179,6 @@ impl<'a> AstNode<'a> for Expr<'a> {
             Expr::WhileExpr(inner) => inner.syntax(),
             Expr::ContinueExpr(inner) => inner.syntax(),
             Expr::BreakExpr(inner) => inner.syntax(),
-            Expr::Label(inner) => inner.syntax(),
             Expr::BlockExpr(inner) => inner.syntax(),
             Expr::ReturnExpr(inner) => inner.syntax(),
             Expr::MatchExpr(inner) => inner.syntax(),
@@ -997,12 +1188,15 @@ impl<'a> AstNode<'a> for Expr<'a> {
             Expr::MethodCallExpr(inner) => inner.syntax(),
             Expr::FieldExpr(inner) => inner.syntax(),
             Expr::TryExpr(inner) => inner.syntax(),
+            Expr::AwaitExpr(inner) => inner.syntax(),
             Expr::CastExpr(inner) => inner.syntax(),
             Expr::RefExpr(inner) => inner.syntax(),
             Expr::PrefixExpr(inner) => inner.syntax(),
+            Expr::BoxExpr(inner) => inner.syntax(),
             Expr::RangeExpr(inner) => inner.syntax(),
             Expr::BinExpr(inner) => inner.syntax(),
             Expr::Literal(inner) => inner.syntax(),
+            Expr::MacroCall(inner) => inner.syntax(),
         }
     }
 }
@@ -1132,6 +1326,51 @@ impl<'a> FieldExpr<'a> {
     }
 }
 
+// FieldPat
+#[derive(Debug, Clone, Copy,)]
+pub struct FieldPatNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type FieldPat<'a> = FieldPatNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<FieldPatNode<R1>> for FieldPatNode<R2> {
+    fn eq(&self, other: &FieldPatNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for FieldPatNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for FieldPatNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for FieldPat<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            FIELD_PAT => Some(FieldPat { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> FieldPatNode<R> {
+    pub fn borrowed(&self) -> FieldPat {
+        FieldPatNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> FieldPatNode {
+        FieldPatNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> FieldPat<'a> {
+    pub fn name_ref(self) -> Option<NameRef<'a>> {
+        super::child_opt(self)
+    }
+
+    pub fn pat(self) -> Option<Pat<'a>> {
+        super::child_opt(self)
+    }
+}
+
 // FieldPatList
 #[derive(Debug, Clone, Copy,)]
 pub struct FieldPatListNode<R: TreeRoot<RaTypes> = OwnedRoot> {
@@ -1167,7 +1406,19 @@ impl<R: TreeRoot<RaTypes>> FieldPatListNode<R> {
 }
 
 
-impl<'a> FieldPatList<'a> {}
+impl<'a> FieldPatList<'a> {
+    pub fn field_pats(self) -> impl Iterator<Item = FieldPat<'a>> + 'a {
+        super::children(self)
+    }
+
+    pub fn bind_pats(self) -> impl Iterator<Item = BindPat<'a>> + 'a {
+        super::children(self)
+    }
+
+    pub fn box_pats(self) -> impl Iterator<Item = BoxPat<'a>> + 'a {
+        super::children(self)
+    }
+}
 
 // FnDef
 #[derive(Debug, Clone, Copy,)]
@@ -1617,7 +1868,11 @@ impl<R: TreeRoot<RaTypes>> LabelNode<R> {
 }
 
 
-impl<'a> Label<'a> {}
+impl<'a> Label<'a> {
+    pub fn lifetime(self) -> Option<Lifetime<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // LambdaExpr
 #[derive(Debug, Clone, Copy,)]
@@ -1659,6 +1914,10 @@ impl<'a> LambdaExpr<'a> {
         super::child_opt(self)
     }
 
+    pub fn ret_type(self) -> Option<RetType<'a>> {
+        super::child_opt(self)
+    }
+
     pub fn body(self) -> Option<Expr<'a>> {
         super::child_opt(self)
     }
@@ -1750,6 +2009,47 @@ impl<R: TreeRoot<RaTypes>> LifetimeNode<R> {
 
 impl<'a> Lifetime<'a> {}
 
+// LifetimeArg
+#[derive(Debug, Clone, Copy,)]
+pub struct LifetimeArgNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type LifetimeArg<'a> = LifetimeArgNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<LifetimeArgNode<R1>> for LifetimeArgNode<R2> {
+    fn eq(&self, other: &LifetimeArgNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for LifetimeArgNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for LifetimeArgNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for LifetimeArg<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            LIFETIME_ARG => Some(LifetimeArg { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> LifetimeArgNode<R> {
+    pub fn borrowed(&self) -> LifetimeArg {
+        LifetimeArgNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> LifetimeArgNode {
+        LifetimeArgNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> LifetimeArg<'a> {
+    pub fn lifetime(self) -> Option<Lifetime<'a>> {
+        super::child_opt(self)
+    }
+}
+
 // LifetimeParam
 #[derive(Debug, Clone, Copy,)]
 pub struct LifetimeParamNode<R: TreeRoot<RaTypes> = OwnedRoot> {
@@ -1828,6 +2128,47 @@ impl<R: TreeRoot<RaTypes>> LiteralNode<R> {
 
 impl<'a> Literal<'a> {}
 
+// LiteralPat
+#[derive(Debug, Clone, Copy,)]
+pub struct LiteralPatNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type LiteralPat<'a> = LiteralPatNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<LiteralPatNode<R1>> for LiteralPatNode<R2> {
+    fn eq(&self, other: &LiteralPatNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for LiteralPatNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for LiteralPatNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for LiteralPat<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            LITERAL_PAT => Some(LiteralPat { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> LiteralPatNode<R> {
+    pub fn borrowed(&self) -> LiteralPat {
+        LiteralPatNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> LiteralPatNode {
+        LiteralPatNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> LiteralPat<'a> {
+    pub fn literal(self) -> Option<Literal<'a>> {
+        super::child_opt(self)
+    }
+}
+
 // LoopExpr
 #[derive(Debug, Clone, Copy,)]
 pub struct LoopExprNode<R: TreeRoot<RaTypes> = OwnedRoot> {
@@ -2081,7 +2422,11 @@ impl<R: TreeRoot<RaTypes>> MatchGuardNode<R> {
 }
 
 
-impl<'a> MatchGuard<'a> {}
+impl<'a> MatchGuard<'a> {
+    pub fn expr(self) -> Option<Expr<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // MethodCallExpr
 #[derive(Debug, Clone, Copy,)]
@@ -2127,6 +2472,10 @@ impl<'a> MethodCallExpr<'a> {
     pub fn name_ref(self) -> Option<NameRef<'a>> {
         super::child_opt(self)
     }
+
+    pub fn type_arg_list(self) -> Option<TypeArgList<'a>> {
+        super::child_opt(self)
+    }
 }
 
 // Module
@@ -2181,6 +2530,7 @@ pub enum ModuleItem<'a> {
     EnumDef(EnumDef<'a>),
     FnDef(FnDef<'a>),
     TraitDef(TraitDef<'a>),
+    TraitAliasDef(TraitAliasDef<'a>),
     TypeDef(TypeDef<'a>),
     ImplBlock(ImplBlock<'a>),
     UseItem(UseItem<'a>),
@@ -2197,6 +2547,7 @@ impl<'a> AstNode<'a> for ModuleItem<'a> {
             ENUM_DEF => Some(ModuleItem::EnumDef(EnumDef { syntax })),
             FN_DEF => Some(ModuleItem::FnDef(FnDef { syntax })),
             TRAIT_DEF => Some(ModuleItem::TraitDef(TraitDef { syntax })),
+            TRAIT_ALIAS_DEF => Some(ModuleItem::TraitAliasDef(TraitAliasDef { syntax })),
             TYPE_DEF => Some(ModuleItem::TypeDef(TypeDef { syntax })),
             IMPL_BLOCK => Some(ModuleItem::ImplBlock(ImplBlock { syntax })),
             USE_ITEM => Some(ModuleItem::UseItem(UseItem { syntax })),
@@ -2213,6 +2564,7 @@ impl<'a> AstNode<'a> for ModuleItem<'a> {
             ModuleItem::EnumDef(inner) => inner.syntax(),
             ModuleItem::FnDef(inner) => inner.syntax(),
             ModuleItem::TraitDef(inner) => inner.syntax(),
+            ModuleItem::TraitAliasDef(inner) => inner.syntax(),
             ModuleItem::TypeDef(inner) => inner.syntax(),
             ModuleItem::ImplBlock(inner) => inner.syntax(),
             ModuleItem::UseItem(inner) => inner.syntax(),
@@ -2712,6 +3064,7 @@ impl<'a> ParenType<'a> {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Pat<'a> {
     RefPat(RefPat<'a>),
+    BoxPat(BoxPat<'a>),
     BindPat(BindPat<'a>),
     PlaceholderPat(PlaceholderPat<'a>),
     PathPat(PathPat<'a>),
@@ -2721,12 +3074,14 @@ pub enum Pat<'a> {
     TuplePat(TuplePat<'a>),
     SlicePat(SlicePat<'a>),
     RangePat(RangePat<'a>),
+    LiteralPat(LiteralPat<'a>),
 }
 
 impl<'a> AstNode<'a> for Pat<'a> {
     fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
         match syntax.kind() {
             REF_PAT => Some(Pat::RefPat(RefPat { syntax })),
+            BOX_PAT => Some(Pat::BoxPat(BoxPat { syntax })),
             BIND_PAT => Some(Pat::BindPat(BindPat { syntax })),
             PLACEHOLDER_PAT => Some(Pat::PlaceholderPat(PlaceholderPat { syntax })),
             PATH_PAT => Some(Pat::PathPat(PathPat { syntax })),
@@ -2736,12 +3091,14 @@ impl<'a> AstNode<'a> for Pat<'a> {
             TUPLE_PAT => Some(Pat::TuplePat(TuplePat { syntax })),
             SLICE_PAT => Some(Pat::SlicePat(SlicePat { syntax })),
             RANGE_PAT => Some(Pat::RangePat(RangePat { syntax })),
+            LITERAL_PAT => Some(Pat::LiteralPat(LiteralPat { syntax })),
             _ => None,
         }
     }
     fn syntax(self) -> SyntaxNodeRef<'a> {
         match self {
             Pat::RefPat(inner) => inner.syntax(),
+            Pat::BoxPat(inner) => inner.syntax(),
             Pat::BindPat(inner) => inner.syntax(),
             Pat::PlaceholderPat(inner) => inner.syntax(),
             Pat::PathPat(inner) => inner.syntax(),
@@ -2751,6 +3108,7 @@ impl<'a> AstNode<'a> for Pat<'a> {
             Pat::TuplePat(inner) => inner.syntax(),
             Pat::SlicePat(inner) => inner.syntax(),
             Pat::RangePat(inner) => inner.syntax(),
+            Pat::LiteralPat(inner) => inner.syntax(),
         }
     }
 }
@@ -2878,7 +3236,11 @@ impl<R: TreeRoot<RaTypes>> PathPatNode<R> {
 }
 
 
-impl<'a> PathPat<'a> {}
+impl<'a> PathPat<'a> {
+    pub fn path(self) -> Option<Path<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // PathSegment
 #[derive(Debug, Clone, Copy,)]
@@ -2919,6 +3281,10 @@ impl<'a> PathSegment<'a> {
     pub fn name_ref(self) -> Option<NameRef<'a>> {
         super::child_opt(self)
     }
+
+    pub fn type_arg_list(self) -> Option<TypeArgList<'a>> {
+        super::child_opt(self)
+    }
 }
 
 // PathType
@@ -3352,7 +3718,11 @@ impl<R: TreeRoot<RaTypes>> RefPatNode<R> {
 }
 
 
-impl<'a> RefPat<'a> {}
+impl<'a> RefPat<'a> {
+    pub fn pat(self) -> Option<Pat<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // ReferenceType
 #[derive(Debug, Clone, Copy,)]
@@ -3720,7 +4090,15 @@ impl<'a> ast::NameOwner<'a> for StaticDef<'a> {}
 impl<'a> ast::TypeParamsOwner<'a> for StaticDef<'a> {}
 impl<'a> ast::AttrsOwner<'a> for StaticDef<'a> {}
 impl<'a> ast::DocCommentsOwner<'a> for StaticDef<'a> {}
-impl<'a> StaticDef<'a> {}
+impl<'a> StaticDef<'a> {
+    pub fn type_ref(self) -> Option<TypeRef<'a>> {
+        super::child_opt(self)
+    }
+
+    pub fn body(self) -> Option<Expr<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // Stmt
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -3910,7 +4288,15 @@ impl<R: TreeRoot<RaTypes>> StructPatNode<R> {
 }
 
 
-impl<'a> StructPat<'a> {}
+impl<'a> StructPat<'a> {
+    pub fn field_pat_list(self) -> Option<FieldPatList<'a>> {
+        super::child_opt(self)
+    }
+
+    pub fn path(self) -> Option<Path<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // TokenTree
 #[derive(Debug, Clone, Copy,)]
@@ -3949,6 +4335,48 @@ impl<R: TreeRoot<RaTypes>> TokenTreeNode<R> {
 
 impl<'a> TokenTree<'a> {}
 
+// TraitAliasDef
+#[derive(Debug, Clone, Copy,)]
+pub struct TraitAliasDefNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type TraitAliasDef<'a> = TraitAliasDefNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<TraitAliasDefNode<R1>> for TraitAliasDefNode<R2> {
+    fn eq(&self, other: &TraitAliasDefNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for TraitAliasDefNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for TraitAliasDefNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for TraitAliasDef<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            TRAIT_ALIAS_DEF => Some(TraitAliasDef { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> TraitAliasDefNode<R> {
+    pub fn borrowed(&self) -> TraitAliasDef {
+        TraitAliasDefNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> TraitAliasDefNode {
+        TraitAliasDefNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> ast::VisibilityOwner<'a> for TraitAliasDef<'a> {}
+impl<'a> ast::NameOwner<'a> for TraitAliasDef<'a> {}
+impl<'a> ast::TypeParamsOwner<'a> for TraitAliasDef<'a> {}
+impl<'a> ast::AttrsOwner<'a> for TraitAliasDef<'a> {}
+impl<'a> ast::DocCommentsOwner<'a> for TraitAliasDef<'a> {}
+impl<'a> TraitAliasDef<'a> {}
+
 // TraitDef
 #[derive(Debug, Clone, Copy,)]
 pub struct TraitDefNode<R: TreeRoot<RaTypes> = OwnedRoot> {
@@ -3988,7 +4416,11 @@ impl<'a> ast::VisibilityOwner<'a> for TraitDef<'a> {}
 impl<'a> ast::NameOwner<'a> for TraitDef<'a> {}
 impl<'a> ast::AttrsOwner<'a> for TraitDef<'a> {}
 impl<'a> ast::DocCommentsOwner<'a> for TraitDef<'a> {}
-impl<'a> TraitDef<'a> {}
+impl<'a> TraitDef<'a> {
+    pub fn item_list(self) -> Option<ItemList<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // TryExpr
 #[derive(Debug, Clone, Copy,)]
@@ -4066,7 +4498,11 @@ impl<R: TreeRoot<RaTypes>> TupleExprNode<R> {
 }
 
 
-impl<'a> TupleExpr<'a> {}
+impl<'a> TupleExpr<'a> {
+    pub fn exprs(self) -> impl Iterator<Item = Expr<'a>> + 'a {
+        super::children(self)
+    }
+}
 
 // TuplePat
 #[derive(Debug, Clone, Copy,)]
@@ -4103,7 +4539,11 @@ impl<R: TreeRoot<RaTypes>> TuplePatNode<R> {
 }
 
 
-impl<'a> TuplePat<'a> {}
+impl<'a> TuplePat<'a> {
+    pub fn args(self) -> impl Iterator<Item = Pat<'a>> + 'a {
+        super::children(self)
+    }
+}
 
 // TupleStructPat
 #[derive(Debug, Clone, Copy,)]
@@ -4191,6 +4631,96 @@ impl<'a> TupleType<'a> {
     }
 }
 
+// TypeArg
+#[derive(Debug, Clone, Copy,)]
+pub struct TypeArgNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type TypeArg<'a> = TypeArgNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<TypeArgNode<R1>> for TypeArgNode<R2> {
+    fn eq(&self, other: &TypeArgNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for TypeArgNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for TypeArgNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for TypeArg<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            TYPE_ARG => Some(TypeArg { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> TypeArgNode<R> {
+    pub fn borrowed(&self) -> TypeArg {
+        TypeArgNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> TypeArgNode {
+        TypeArgNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> TypeArg<'a> {
+    pub fn type_ref(self) -> Option<TypeRef<'a>> {
+        super::child_opt(self)
+    }
+}
+
+// TypeArgList
+#[derive(Debug, Clone, Copy,)]
+pub struct TypeArgListNode<R: TreeRoot<RaTypes> = OwnedRoot> {
+    pub(crate) syntax: SyntaxNode<R>,
+}
+pub type TypeArgList<'a> = TypeArgListNode<RefRoot<'a>>;
+
+impl<R1: TreeRoot<RaTypes>, R2: TreeRoot<RaTypes>> PartialEq<TypeArgListNode<R1>> for TypeArgListNode<R2> {
+    fn eq(&self, other: &TypeArgListNode<R1>) -> bool { self.syntax == other.syntax }
+}
+impl<R: TreeRoot<RaTypes>> Eq for TypeArgListNode<R> {}
+impl<R: TreeRoot<RaTypes>> Hash for TypeArgListNode<R> {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.syntax.hash(state) }
+}
+
+impl<'a> AstNode<'a> for TypeArgList<'a> {
+    fn cast(syntax: SyntaxNodeRef<'a>) -> Option<Self> {
+        match syntax.kind() {
+            TYPE_ARG_LIST => Some(TypeArgList { syntax }),
+            _ => None,
+        }
+    }
+    fn syntax(self) -> SyntaxNodeRef<'a> { self.syntax }
+}
+
+impl<R: TreeRoot<RaTypes>> TypeArgListNode<R> {
+    pub fn borrowed(&self) -> TypeArgList {
+        TypeArgListNode { syntax: self.syntax.borrowed() }
+    }
+    pub fn owned(&self) -> TypeArgListNode {
+        TypeArgListNode { syntax: self.syntax.owned() }
+    }
+}
+
+
+impl<'a> TypeArgList<'a> {
+    pub fn type_args(self) -> impl Iterator<Item = TypeArg<'a>> + 'a {
+        super::children(self)
+    }
+
+    pub fn lifetime_args(self) -> impl Iterator<Item = LifetimeArg<'a>> + 'a {
+        super::children(self)
+    }
+
+    pub fn assoc_type_args(self) -> impl Iterator<Item = AssocTypeArg<'a>> + 'a {
+        super::children(self)
+    }
+}
+
 // TypeDef
 #[derive(Debug, Clone, Copy,)]
 pub struct TypeDefNode<R: TreeRoot<RaTypes> = OwnedRoot> {
@@ -4231,7 +4761,11 @@ impl<'a> ast::NameOwner<'a> for TypeDef<'a> {}
 impl<'a> ast::TypeParamsOwner<'a> for TypeDef<'a> {}
 impl<'a> ast::AttrsOwner<'a> for TypeDef<'a> {}
 impl<'a> ast::DocCommentsOwner<'a> for TypeDef<'a> {}
-impl<'a> TypeDef<'a> {}
+impl<'a> TypeDef<'a> {
+    pub fn type_ref(self) -> Option<TypeRef<'a>> {
+        super::child_opt(self)
+    }
+}
 
 // TypeParam
 #[derive(Debug, Clone, Copy,)]
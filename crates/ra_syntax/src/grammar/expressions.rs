@@ -133,6 +133,13 @@ enum Op {
     Composite(SyntaxKind, u8),
 }
 
+// test assignment_ops
+// fn foo() {
+//     x = 1;
+//     x %= 2;
+//     x <<= 3;
+//     x ^= 4;
+// }
 fn current_op(p: &Parser) -> (u8, Op) {
     if let Some(t) = p.next3() {
         match t {
@@ -148,6 +155,7 @@ fn current_op(p: &Parser) -> (u8, Op) {
             (MINUS, EQ) => return (1, Op::Composite(MINUSEQ, 2)),
             (STAR, EQ) => return (1, Op::Composite(STAREQ, 2)),
             (SLASH, EQ) => return (1, Op::Composite(SLASHEQ, 2)),
+            (PERCENT, EQ) => return (1, Op::Composite(PERCENTEQ, 2)),
             (PIPE, EQ) => return (1, Op::Composite(PIPEEQ, 2)),
             (AMP, EQ) => return (1, Op::Composite(AMPEQ, 2)),
             (CARET, EQ) => return (1, Op::Composite(CARETEQ, 2)),
@@ -212,7 +220,7 @@ fn expr_bp(p: &mut Parser, r: Restrictions, bp: u8) -> BlockLike {
 }
 
 const LHS_FIRST: TokenSet = token_set_union![
-    token_set![AMP, STAR, EXCL, DOTDOT, DOTDOTEQ, MINUS],
+    token_set![AMP, STAR, EXCL, DOTDOT, DOTDOTEQ, MINUS, BOX_KW],
     atom::ATOM_EXPR_FIRST,
 ];
 
@@ -241,6 +249,16 @@ fn lhs(p: &mut Parser, r: Restrictions) -> Option<(CompletedMarker, BlockLike)>
             p.bump();
             PREFIX_EXPR
         }
+        // test box_expr
+        // fn foo() {
+        //     let x = box 1i32;
+        //     let y = (box 1i32, box 2i32);
+        // }
+        BOX_KW => {
+            m = p.start();
+            p.bump();
+            BOX_EXPR
+        }
         // test full_range_expr
         // fn foo() { xs[..]; }
         DOTDOT | DOTDOTEQ => {
@@ -283,6 +301,7 @@ fn postfix_expr(
             // }
             L_PAREN if allow_calls => call_expr(p, lhs),
             L_BRACK if allow_calls => index_expr(p, lhs),
+            DOT if p.nth(1) == IDENT && p.nth_at_contextual_kw(1, "await") => await_expr(p, lhs),
             DOT if p.nth(1) == IDENT && (p.nth(2) == L_PAREN || p.nth(2) == COLONCOLON) => {
                 method_call_expr(p, lhs)
             }
@@ -303,6 +322,20 @@ fn postfix_expr(
     lhs
 }
 
+// test await_expr
+// async fn foo() {
+//     x.await;
+//     x.0.await;
+//     x.0().await?.hello();
+// }
+fn await_expr(p: &mut Parser, lhs: CompletedMarker) -> CompletedMarker {
+    assert!(p.at(DOT) && p.nth(1) == IDENT && p.nth_at_contextual_kw(1, "await"));
+    let m = lhs.precede(p);
+    p.bump();
+    p.bump_remap(AWAIT_KW);
+    m.complete(p, AWAIT_EXPR)
+}
+
 // test call_expr
 // fn foo() {
 //     let _ = f();
@@ -66,6 +66,9 @@ pub(super) fn atom_expr(p: &mut Parser, r: Restrictions) -> Option<(CompletedMar
     if let Some(m) = literal(p) {
         return Some((m, BlockLike::NotBlock));
     }
+    if is_async_block_start(p) {
+        return Some((async_block_expr(p), BlockLike::Block));
+    }
     if paths::is_path_start(p) || p.at(L_ANGLE) {
         return Some(path_expr(p, r));
     }
@@ -389,6 +392,25 @@ fn block_expr(p: &mut Parser, m: Option<Marker>) -> CompletedMarker {
     m.complete(p, BLOCK_EXPR)
 }
 
+fn is_async_block_start(p: &Parser) -> bool {
+    p.at(IDENT)
+        && p.at_contextual_kw("async")
+        && (p.nth(1) == L_CURLY || (p.nth(1) == MOVE_KW && p.nth(2) == L_CURLY))
+}
+
+// test async_block_expr
+// fn foo() {
+//     async {};
+//     async move { x.await };
+// }
+fn async_block_expr(p: &mut Parser) -> CompletedMarker {
+    assert!(is_async_block_start(p));
+    let m = p.start();
+    p.bump_remap(ASYNC_KW);
+    p.eat(MOVE_KW);
+    block_expr(p, Some(m))
+}
+
 // test return_expr
 // fn foo() {
 //     return;
@@ -89,6 +89,14 @@ pub(super) fn maybe_item(p: &mut Parser, flavor: ItemFlavor) -> MaybeItem {
     // modifiers
     has_mods |= p.eat(CONST_KW);
 
+    // test async_fn
+    // async fn foo() {}
+    // async unsafe fn bar() {}
+    if p.at(IDENT) && p.at_contextual_kw("async") && (p.nth(1) == FN_KW || p.nth(1) == UNSAFE_KW) {
+        p.bump_remap(ASYNC_KW);
+        has_mods = true;
+    }
+
     // test_err unsafe_block_in_mod
     // fn foo(){} unsafe { } fn bar(){}
     if p.at(UNSAFE_KW) && p.nth(1) != L_CURLY {
@@ -137,10 +145,7 @@ pub(super) fn maybe_item(p: &mut Parser, flavor: ItemFlavor) -> MaybeItem {
 
         // test unsafe_auto_trait
         // unsafe auto trait T {}
-        TRAIT_KW => {
-            traits::trait_def(p);
-            TRAIT_DEF
-        }
+        TRAIT_KW => traits::trait_def(p),
 
         // test unsafe_impl
         // unsafe impl Foo {}
@@ -2,11 +2,15 @@ use super::*;
 
 // test trait_item
 // trait T<U>: Hash + Clone where U: Copy {}
-pub(super) fn trait_def(p: &mut Parser) {
+pub(super) fn trait_def(p: &mut Parser) -> SyntaxKind {
     assert!(p.at(TRAIT_KW));
     p.bump();
     name_r(p, ITEM_RECOVERY_SET);
     type_params::opt_type_param_list(p);
+    if p.at(EQ) {
+        trait_alias_def(p);
+        return TRAIT_ALIAS_DEF;
+    }
     if p.at(COLON) {
         type_params::bounds(p);
     }
@@ -16,6 +20,19 @@ pub(super) fn trait_def(p: &mut Parser) {
     } else {
         p.error("expected `{`");
     }
+    TRAIT_DEF
+}
+
+// test trait_alias
+// trait Z<U> = T<U>;
+// trait Z<U> = T<U> where U: Copy;
+// trait Z<U> = where Self: T<U>;
+fn trait_alias_def(p: &mut Parser) {
+    assert!(p.at(EQ));
+    p.bump();
+    type_params::bounds_without_colon(p);
+    type_params::opt_where_clause(p);
+    p.expect(SEMI);
 }
 
 // test trait_item_list
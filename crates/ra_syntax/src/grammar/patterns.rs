@@ -1,7 +1,7 @@
 use super::*;
 
 pub(super) const PATTERN_FIRST: TokenSet = token_set_union![
-    token_set![REF_KW, MUT_KW, L_PAREN, L_BRACK, AMP, UNDERSCORE],
+    token_set![REF_KW, MUT_KW, L_PAREN, L_BRACK, AMP, UNDERSCORE, BOX_KW],
     expressions::LITERAL_FIRST,
     paths::PATH_FIRST,
 ];
@@ -45,26 +45,14 @@ fn atom_pat(p: &mut Parser, recovery_set: TokenSet) -> Option<CompletedMarker> {
         return Some(path_pat(p));
     }
 
-    // test literal_pattern
-    // fn main() {
-    //     match () {
-    //         -1 => (),
-    //         92 => (),
-    //         'c' => (),
-    //         "hello" => (),
-    //     }
-    // }
-    if p.at(MINUS) && (p.nth(1) == INT_NUMBER || p.nth(1) == FLOAT_NUMBER) {
-        p.bump();
-    }
-
-    if let Some(m) = expressions::literal(p) {
-        return Some(m);
+    if is_literal_pat_start(p) {
+        return Some(literal_pat(p));
     }
 
     let m = match la0 {
         UNDERSCORE => placeholder_pat(p),
         AMP => ref_pat(p),
+        BOX_KW => box_pat(p),
         L_PAREN => tuple_pat(p),
         L_BRACK => slice_pat(p),
         _ => {
@@ -100,6 +88,30 @@ fn path_pat(p: &mut Parser) -> CompletedMarker {
     m.complete(p, kind)
 }
 
+fn is_literal_pat_start(p: &mut Parser) -> bool {
+    p.at(MINUS) && (p.nth(1) == INT_NUMBER || p.nth(1) == FLOAT_NUMBER)
+        || p.at_ts(expressions::LITERAL_FIRST)
+}
+
+// test literal_pattern
+// fn main() {
+//     match () {
+//         -1 => (),
+//         92 => (),
+//         'c' => (),
+//         "hello" => (),
+//     }
+// }
+fn literal_pat(p: &mut Parser) -> CompletedMarker {
+    assert!(is_literal_pat_start(p));
+    let m = p.start();
+    if p.at(MINUS) {
+        p.bump();
+    }
+    expressions::literal(p);
+    m.complete(p, LITERAL_PAT)
+}
+
 // test tuple_pat_fields
 // fn foo() {
 //     let S() = ();
@@ -129,11 +141,16 @@ fn field_pat_list(p: &mut Parser) {
         match p.current() {
             DOTDOT => p.bump(),
             IDENT if p.nth(1) == COLON => {
-                p.bump();
+                let m = p.start();
+                name_ref(p);
                 p.bump();
                 pattern(p);
+                m.complete(p, FIELD_PAT);
             }
             L_CURLY => error_block(p, "expected ident"),
+            BOX_KW => {
+                box_pat(p);
+            }
             _ => {
                 bind_pat(p, false);
             }
@@ -169,6 +186,20 @@ fn ref_pat(p: &mut Parser) -> CompletedMarker {
     m.complete(p, REF_PAT)
 }
 
+// test box_pat
+// fn main() {
+//     let box i = ();
+//     let box Outer { box i, j: box Inner(box &x) } = ();
+//     let box ref mut i = ();
+// }
+fn box_pat(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(BOX_KW));
+    let m = p.start();
+    p.bump();
+    pattern(p);
+    m.complete(p, BOX_PAT)
+}
+
 // test tuple_pat
 // fn main() {
 //     let (a, b, ..) = ();
@@ -226,7 +257,15 @@ fn bind_pat(p: &mut Parser, with_at: bool) -> CompletedMarker {
     p.eat(MUT_KW);
     name(p);
     if with_at && p.eat(AT) {
-        pattern(p);
+        // test bind_pat_rest
+        // fn main() {
+        //     let [a, rest @ .., b] = [];
+        // }
+        if p.at(DOTDOT) {
+            p.bump();
+        } else {
+            pattern(p);
+        }
     }
     m.complete(p, BIND_PAT)
 }
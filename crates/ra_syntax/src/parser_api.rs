@@ -54,6 +54,11 @@ impl<'t> Parser<'t> {
         self.0.at_kw(t)
     }
 
+    /// Checks if the nth token is contextual keyword with text `t`.
+    pub(crate) fn nth_at_contextual_kw(&self, n: u32, t: &str) -> bool {
+        self.0.nth_at_kw(n, t)
+    }
+
     /// Starts a new node in the syntax tree. All nodes and tokens
     /// consumed between the `start` and the corresponding `Marker::complete`
     /// belong to the same node.
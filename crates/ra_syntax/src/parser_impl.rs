@@ -122,7 +122,11 @@ impl<'t> ParserImpl<'t> {
     }
 
     pub(super) fn at_kw(&self, t: &str) -> bool {
-        self.parser_input.token_text(self.pos) == t
+        self.nth_at_kw(0, t)
+    }
+
+    pub(super) fn nth_at_kw(&self, n: u32, t: &str) -> bool {
+        self.parser_input.token_text(self.pos + n) == t
     }
 
     /// Start parsing right behind the last event.
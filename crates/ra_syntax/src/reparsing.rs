@@ -96,9 +96,11 @@ fn find_reparsable_node(
             MATCH_ARM_LIST => grammar::match_arm_list,
             USE_TREE_LIST => grammar::use_tree_list,
             EXTERN_ITEM_LIST => grammar::extern_item_list,
-            TOKEN_TREE if node.first_child().unwrap().kind() == L_CURLY => grammar::token_tree,
+            TOKEN_TREE if node.first_child().map(|it| it.kind()) == Some(L_CURLY) => {
+                grammar::token_tree
+            }
             ITEM_LIST => {
-                let parent = node.parent().unwrap();
+                let parent = node.parent()?;
                 match parent.kind() {
                     IMPL_BLOCK => grammar::impl_item_list,
                     TRAIT_DEF => grammar::trait_item_list,
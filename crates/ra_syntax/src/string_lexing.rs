@@ -1,7 +1,9 @@
 mod parser;
 mod string;
+mod unescape;
 
 pub use self::{
     parser::{StringComponent, StringComponentKind},
     string::{parse_string_literal, parse_char_literal, parse_byte_literal, parse_byte_string_literal},
+    unescape::unescape,
 };
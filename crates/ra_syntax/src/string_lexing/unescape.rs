@@ -0,0 +1,87 @@
+use crate::string_lexing::{
+    StringComponentKind::*,
+    string::StringComponentIterator,
+};
+
+/// Computes the value of a string, char, byte or byte string literal from its
+/// components. Returns `None` if the literal is malformed: it's not closed, has
+/// a suffix or contains an invalid escape.
+pub fn unescape(src: &str, mut components: StringComponentIterator) -> Option<String> {
+    let mut res = String::new();
+    for component in &mut components {
+        let text = &src[component.range];
+        match component.kind {
+            CodePoint => res.push_str(text),
+            IgnoreNewline => (),
+            AsciiEscape => res.push(unescape_ascii(text)?),
+            AsciiCodeEscape => {
+                let code = u8::from_str_radix(text.get(2..)?, 16).ok()?;
+                res.push(code as char);
+            }
+            UnicodeEscape => {
+                if !text.starts_with("\\u{") || !text.ends_with('}') {
+                    return None;
+                }
+                let digits = text[3..text.len() - 1].replace('_', "");
+                let code = u32::from_str_radix(&digits, 16).ok()?;
+                res.push(std::char::from_u32(code)?);
+            }
+        }
+    }
+    if !components.has_closing_quote || components.suffix.is_some() {
+        return None;
+    }
+    Some(res)
+}
+
+fn unescape_ascii(text: &str) -> Option<char> {
+    let res = match text.chars().nth(1)? {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        '\\' => '\\',
+        '\'' => '\'',
+        '"' => '"',
+        _ => return None,
+    };
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::string_lexing::{parse_string_literal, parse_char_literal};
+
+    fn unescape_string(src: &str) -> Option<String> {
+        super::unescape(src, parse_string_literal(src))
+    }
+
+    fn unescape_char(src: &str) -> Option<String> {
+        super::unescape(src, parse_char_literal(src))
+    }
+
+    #[test]
+    fn test_unescape_string() {
+        assert_eq!(unescape_string(r#""foo""#), Some("foo".to_string()));
+        assert_eq!(
+            unescape_string(r#""a\nb\t\"c\"\\""#),
+            Some("a\nb\t\"c\"\\".to_string())
+        );
+        assert_eq!(
+            unescape_string(r#""\x41\u{1F600}""#),
+            Some("A😀".to_string())
+        );
+        assert_eq!(unescape_string("\"a\\\n    b\""), Some("ab".to_string()));
+        assert_eq!(unescape_string(r#""\q""#), None);
+        assert_eq!(unescape_string(r#""foo"#), None);
+        assert_eq!(unescape_string(r#""foo"bar"#), None);
+    }
+
+    #[test]
+    fn test_unescape_char() {
+        assert_eq!(unescape_char("'a'"), Some("a".to_string()));
+        assert_eq!(unescape_char(r"'\''"), Some("'".to_string()));
+        assert_eq!(unescape_char(r"'\u{0_41}'"), Some("A".to_string()));
+        assert_eq!(unescape_char(r"'\u{D800}'"), None);
+    }
+}
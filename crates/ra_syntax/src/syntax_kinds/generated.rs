@@ -58,6 +58,7 @@ pub enum SyntaxKind {
     CARETEQ,
     SLASHEQ,
     STAREQ,
+    PERCENTEQ,
     AMPAMP,
     PIPEPIPE,
     SHL,
@@ -99,9 +100,12 @@ pub enum SyntaxKind {
     LET_KW,
     MOVE_KW,
     RETURN_KW,
+    BOX_KW,
     AUTO_KW,
     DEFAULT_KW,
     UNION_KW,
+    ASYNC_KW,
+    AWAIT_KW,
     ERROR,
     IDENT,
     UNDERSCORE,
@@ -128,6 +132,7 @@ pub enum SyntaxKind {
     STATIC_DEF,
     CONST_DEF,
     TRAIT_DEF,
+    TRAIT_ALIAS_DEF,
     IMPL_BLOCK,
     TYPE_DEF,
     MACRO_CALL,
@@ -146,15 +151,18 @@ pub enum SyntaxKind {
     IMPL_TRAIT_TYPE,
     DYN_TRAIT_TYPE,
     REF_PAT,
+    BOX_PAT,
     BIND_PAT,
     PLACEHOLDER_PAT,
     PATH_PAT,
     STRUCT_PAT,
     FIELD_PAT_LIST,
+    FIELD_PAT,
     TUPLE_STRUCT_PAT,
     TUPLE_PAT,
     SLICE_PAT,
     RANGE_PAT,
+    LITERAL_PAT,
     TUPLE_EXPR,
     ARRAY_EXPR,
     PAREN_EXPR,
@@ -182,9 +190,11 @@ pub enum SyntaxKind {
     METHOD_CALL_EXPR,
     FIELD_EXPR,
     TRY_EXPR,
+    AWAIT_EXPR,
     CAST_EXPR,
     REF_EXPR,
     PREFIX_EXPR,
+    BOX_EXPR,
     RANGE_EXPR,
     BIN_EXPR,
     BLOCK,
@@ -265,9 +275,12 @@ impl SyntaxKind {
             | LET_KW
             | MOVE_KW
             | RETURN_KW
+            | BOX_KW
             | AUTO_KW
             | DEFAULT_KW
             | UNION_KW
+            | ASYNC_KW
+            | AWAIT_KW
                 => true,
             _ => false
         }
@@ -319,6 +332,7 @@ impl SyntaxKind {
             CARETEQ => &SyntaxInfo { name: "CARETEQ" },
             SLASHEQ => &SyntaxInfo { name: "SLASHEQ" },
             STAREQ => &SyntaxInfo { name: "STAREQ" },
+            PERCENTEQ => &SyntaxInfo { name: "PERCENTEQ" },
             AMPAMP => &SyntaxInfo { name: "AMPAMP" },
             PIPEPIPE => &SyntaxInfo { name: "PIPEPIPE" },
             SHL => &SyntaxInfo { name: "SHL" },
@@ -360,9 +374,12 @@ impl SyntaxKind {
             LET_KW => &SyntaxInfo { name: "LET_KW" },
             MOVE_KW => &SyntaxInfo { name: "MOVE_KW" },
             RETURN_KW => &SyntaxInfo { name: "RETURN_KW" },
+            BOX_KW => &SyntaxInfo { name: "BOX_KW" },
             AUTO_KW => &SyntaxInfo { name: "AUTO_KW" },
             DEFAULT_KW => &SyntaxInfo { name: "DEFAULT_KW" },
             UNION_KW => &SyntaxInfo { name: "UNION_KW" },
+            ASYNC_KW => &SyntaxInfo { name: "ASYNC_KW" },
+            AWAIT_KW => &SyntaxInfo { name: "AWAIT_KW" },
             ERROR => &SyntaxInfo { name: "ERROR" },
             IDENT => &SyntaxInfo { name: "IDENT" },
             UNDERSCORE => &SyntaxInfo { name: "UNDERSCORE" },
@@ -389,6 +406,7 @@ impl SyntaxKind {
             STATIC_DEF => &SyntaxInfo { name: "STATIC_DEF" },
             CONST_DEF => &SyntaxInfo { name: "CONST_DEF" },
             TRAIT_DEF => &SyntaxInfo { name: "TRAIT_DEF" },
+            TRAIT_ALIAS_DEF => &SyntaxInfo { name: "TRAIT_ALIAS_DEF" },
             IMPL_BLOCK => &SyntaxInfo { name: "IMPL_BLOCK" },
             TYPE_DEF => &SyntaxInfo { name: "TYPE_DEF" },
             MACRO_CALL => &SyntaxInfo { name: "MACRO_CALL" },
@@ -407,15 +425,18 @@ impl SyntaxKind {
             IMPL_TRAIT_TYPE => &SyntaxInfo { name: "IMPL_TRAIT_TYPE" },
             DYN_TRAIT_TYPE => &SyntaxInfo { name: "DYN_TRAIT_TYPE" },
             REF_PAT => &SyntaxInfo { name: "REF_PAT" },
+            BOX_PAT => &SyntaxInfo { name: "BOX_PAT" },
             BIND_PAT => &SyntaxInfo { name: "BIND_PAT" },
             PLACEHOLDER_PAT => &SyntaxInfo { name: "PLACEHOLDER_PAT" },
             PATH_PAT => &SyntaxInfo { name: "PATH_PAT" },
             STRUCT_PAT => &SyntaxInfo { name: "STRUCT_PAT" },
             FIELD_PAT_LIST => &SyntaxInfo { name: "FIELD_PAT_LIST" },
+            FIELD_PAT => &SyntaxInfo { name: "FIELD_PAT" },
             TUPLE_STRUCT_PAT => &SyntaxInfo { name: "TUPLE_STRUCT_PAT" },
             TUPLE_PAT => &SyntaxInfo { name: "TUPLE_PAT" },
             SLICE_PAT => &SyntaxInfo { name: "SLICE_PAT" },
             RANGE_PAT => &SyntaxInfo { name: "RANGE_PAT" },
+            LITERAL_PAT => &SyntaxInfo { name: "LITERAL_PAT" },
             TUPLE_EXPR => &SyntaxInfo { name: "TUPLE_EXPR" },
             ARRAY_EXPR => &SyntaxInfo { name: "ARRAY_EXPR" },
             PAREN_EXPR => &SyntaxInfo { name: "PAREN_EXPR" },
@@ -443,9 +464,11 @@ impl SyntaxKind {
             METHOD_CALL_EXPR => &SyntaxInfo { name: "METHOD_CALL_EXPR" },
             FIELD_EXPR => &SyntaxInfo { name: "FIELD_EXPR" },
             TRY_EXPR => &SyntaxInfo { name: "TRY_EXPR" },
+            AWAIT_EXPR => &SyntaxInfo { name: "AWAIT_EXPR" },
             CAST_EXPR => &SyntaxInfo { name: "CAST_EXPR" },
             REF_EXPR => &SyntaxInfo { name: "REF_EXPR" },
             PREFIX_EXPR => &SyntaxInfo { name: "PREFIX_EXPR" },
+            BOX_EXPR => &SyntaxInfo { name: "BOX_EXPR" },
             RANGE_EXPR => &SyntaxInfo { name: "RANGE_EXPR" },
             BIN_EXPR => &SyntaxInfo { name: "BIN_EXPR" },
             BLOCK => &SyntaxInfo { name: "BLOCK" },
@@ -526,6 +549,7 @@ impl SyntaxKind {
             "let" => LET_KW,
             "move" => MOVE_KW,
             "return" => RETURN_KW,
+            "box" => BOX_KW,
             _ => return None,
         };
         Some(kw)
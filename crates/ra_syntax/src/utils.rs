@@ -1,4 +1,4 @@
-use crate::{SourceFileNode, SyntaxKind, SyntaxNodeRef, WalkEvent, AstNode};
+use crate::{ast, SourceFileNode, SyntaxKind, SyntaxNodeRef, WalkEvent, AstNode};
 use std::fmt::Write;
 use std::str;
 
@@ -47,12 +47,53 @@ pub fn dump_tree(syntax: SyntaxNodeRef) -> String {
     buf
 }
 
-pub fn check_fuzz_invariants(text: &str) {
+/// Entry point for the fuzzing harness: parses arbitrary `text` and checks
+/// that the parser was lossless.
+pub fn parse_fuzz(text: &str) -> SourceFileNode {
     let file = SourceFileNode::parse(text);
+    assert_eq!(
+        file.syntax().text().to_string(),
+        text,
+        "\nsyntax tree text differs from the input:\n{}\n",
+        dump_tree(file.syntax()),
+    );
+    file
+}
+
+pub fn check_fuzz_invariants(text: &str) {
+    let file = parse_fuzz(text);
     let root = file.syntax();
     validate_block_structure(root);
     let _ = file.ast();
     let _ = file.errors();
+    check_ast_accessors(root);
+}
+
+/// Exercises the accessors of the typed AST, which must not panic even on
+/// the trees produced by error recovery.
+fn check_ast_accessors(root: SyntaxNodeRef) {
+    for node in root.descendants() {
+        if let Some(it) = ast::Name::cast(node) {
+            let _ = it.text();
+        }
+        if let Some(it) = ast::NameRef::cast(node) {
+            let _ = it.text();
+        }
+        if let Some(it) = ast::Attr::cast(node) {
+            let _ = it.as_atom();
+            let _ = it.as_call();
+        }
+        if let Some(it) = ast::PathSegment::cast(node) {
+            let _ = it.parent_path();
+            let _ = it.kind();
+        }
+        if let Some(it) = ast::UseTreeList::cast(node) {
+            let _ = it.parent_use_tree();
+        }
+        if let Some(it) = ast::Comment::cast(node) {
+            let _ = it.flavor();
+        }
+    }
 }
 
 pub(crate) fn validate_block_structure(root: SyntaxNodeRef) {
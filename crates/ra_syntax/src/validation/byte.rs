@@ -55,10 +55,10 @@ pub(super) fn validate_byte_component(
         AsciiCodeEscape => validate_byte_code_escape(text, range, errors),
         UnicodeEscape => errors.push(SyntaxError::new(UnicodeEscapeForbidden, range)),
         CodePoint => {
-            let c = text
-                .chars()
-                .next()
-                .expect("Code points should be one character long");
+            let c = match text.chars().next() {
+                Some(c) => c,
+                None => return,
+            };
 
             // These bytes must always be escaped
             if c == '\t' || c == '\r' || c == '\n' {
@@ -75,13 +75,13 @@ pub(super) fn validate_byte_component(
 }
 
 fn validate_byte_escape(text: &str, range: TextRange, errors: &mut Vec<SyntaxError>) {
-    if text.len() == 1 {
+    match text.chars().nth(1) {
         // Escape sequence consists only of leading `\`
-        errors.push(SyntaxError::new(EmptyByteEscape, range));
-    } else {
-        let escape_code = text.chars().skip(1).next().unwrap();
-        if !char::is_ascii_escape(escape_code) {
-            errors.push(SyntaxError::new(InvalidByteEscape, range));
+        None => errors.push(SyntaxError::new(EmptyByteEscape, range)),
+        Some(escape_code) => {
+            if !char::is_ascii_escape(escape_code) {
+                errors.push(SyntaxError::new(InvalidByteEscape, range));
+            }
         }
     }
 }
@@ -69,13 +69,13 @@ pub(super) fn validate_char_component(
 }
 
 fn validate_ascii_escape(text: &str, range: TextRange, errors: &mut Vec<SyntaxError>) {
-    if text.len() == 1 {
+    match text.chars().nth(1) {
         // Escape sequence consists only of leading `\` (only occurs at EOF, otherwise e.g. '\' is treated as an unclosed char containing a single quote `'`)
-        errors.push(SyntaxError::new(EmptyAsciiEscape, range));
-    } else {
-        let escape_code = text.chars().skip(1).next().unwrap();
-        if !is_ascii_escape(escape_code) {
-            errors.push(SyntaxError::new(InvalidAsciiEscape, range));
+        None => errors.push(SyntaxError::new(EmptyAsciiEscape, range)),
+        Some(escape_code) => {
+            if !is_ascii_escape(escape_code) {
+                errors.push(SyntaxError::new(InvalidAsciiEscape, range));
+            }
         }
     }
 }
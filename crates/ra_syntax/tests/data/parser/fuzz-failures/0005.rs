@@ -0,0 +1,2 @@
+use {a::{;
+fn f(&) { b'\'; '\
@@ -0,0 +1,3 @@
+trait Z<U> = T<U>;
+trait Z<U> = T<U> where U: Copy;
+trait Z<U> = where Self: T<U>;
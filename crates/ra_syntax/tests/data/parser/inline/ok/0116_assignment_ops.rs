@@ -0,0 +1,6 @@
+fn foo() {
+    x = 1;
+    x %= 2;
+    x <<= 3;
+    x ^= 4;
+}
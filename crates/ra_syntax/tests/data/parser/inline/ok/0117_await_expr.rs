@@ -0,0 +1,5 @@
+async fn foo() {
+    x.await;
+    x.0.await;
+    x.0().await?.hello();
+}
@@ -0,0 +1,224 @@
+use std::ops::Range;
+
+use text_unit::{TextRange, TextUnit};
+
+use crate::{TextEdit, TextEditBuilder};
+
+/// Beyond this number of inserted and deleted elements, the differing parts
+/// are replaced as a whole, as the diff needs memory quadratic in it.
+const MAX_EDIT_DISTANCE: isize = 1024;
+
+impl TextEdit {
+    /// Computes a compact edit which turns `before` into `after`. The lines
+    /// are compared first, and then the characters of the changed lines, so
+    /// that unchanged parts of the text are not touched.
+    pub fn diff(before: &str, after: &str) -> TextEdit {
+        let mut builder = TextEditBuilder::default();
+        let before_lines = line_offsets(before);
+        let after_lines = line_offsets(after);
+        let hunks = hunks(&lines(before, &before_lines), &lines(after, &after_lines));
+        for (old, new) in hunks {
+            let old_start = before_lines[old.start];
+            let old_text = &before[old_start..before_lines[old.end]];
+            let new_text = &after[after_lines[new.start]..after_lines[new.end]];
+            diff_chars(&mut builder, old_start, old_text, new_text);
+        }
+        builder.finish()
+    }
+}
+
+/// The offsets of the starts of the lines, and of the end of the text.
+fn line_offsets(text: &str) -> Vec<usize> {
+    let mut res = vec![0];
+    res.extend(text.match_indices('\n').map(|(idx, _)| idx + 1));
+    if *res.last().unwrap() != text.len() {
+        res.push(text.len());
+    }
+    res
+}
+
+/// The lines of the text, with their line endings.
+fn lines<'a>(text: &'a str, offsets: &[usize]) -> Vec<&'a str> {
+    offsets
+        .iter()
+        .zip(offsets.iter().skip(1))
+        .map(|(&start, &end)| &text[start..end])
+        .collect()
+}
+
+/// Diffs the changed lines of the text starting at `offset`.
+fn diff_chars(builder: &mut TextEditBuilder, offset: usize, old: &str, new: &str) {
+    let old_chars = old.char_indices().collect::<Vec<_>>();
+    let new_chars = new.char_indices().collect::<Vec<_>>();
+    let char_offset = |chars: &[(usize, char)], text: &str, idx: usize| {
+        chars.get(idx).map_or(text.len(), |&(offset, _)| offset)
+    };
+    let old_text = old_chars.iter().map(|&(_, c)| c).collect::<Vec<_>>();
+    let new_text = new_chars.iter().map(|&(_, c)| c).collect::<Vec<_>>();
+    for (o, n) in hunks(&old_text, &new_text) {
+        let delete = range(
+            offset,
+            char_offset(&old_chars, old, o.start),
+            char_offset(&old_chars, old, o.end),
+        );
+        let insert =
+            &new[char_offset(&new_chars, new, n.start)..char_offset(&new_chars, new, n.end)];
+        builder.replace(delete, insert.to_string());
+    }
+}
+
+fn range(offset: usize, start: usize, end: usize) -> TextRange {
+    TextRange::from_to(
+        TextUnit::from_usize(offset + start),
+        TextUnit::from_usize(offset + end),
+    )
+}
+
+/// The ranges of `a` and `b` which differ, in order.
+fn hunks<T: Eq>(a: &[T], b: &[T]) -> Vec<(Range<usize>, Range<usize>)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_end, b_end) = (a.len() - suffix, b.len() - suffix);
+    let common = common_subsequence(&a[prefix..a_end], &b[prefix..b_end])
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(x, y)| (x + prefix, y + prefix))
+        .chain(std::iter::once((a_end, b_end)));
+
+    let mut res = Vec::new();
+    let (mut prev_a, mut prev_b) = (prefix, prefix);
+    for (x, y) in common {
+        if x > prev_a || y > prev_b {
+            res.push((prev_a..x, prev_b..y));
+        }
+        prev_a = x + 1;
+        prev_b = y + 1;
+    }
+    res
+}
+
+/// The longest common subsequence of `a` and `b`, as the pairs of indices of
+/// the equal elements, computed with the Myers' algorithm. Returns `None` if
+/// the sequences differ too much.
+fn common_subsequence<T: Eq>(a: &[T], b: &[T]) -> Option<Vec<(usize, usize)>> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = n + m;
+    // `v[k]` is the furthest `x` reached on the diagonal `k = x - y`.
+    let idx = |k: isize| (k + max + 1) as usize;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    // The diagonals `-d - 1..=d + 1` of `v` before each step `d`.
+    let mut trace = Vec::new();
+    'search: for d in 0..=max {
+        if d > MAX_EDIT_DISTANCE {
+            return None;
+        }
+        trace.push(v[idx(-d - 1)..=idx(d + 1)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) {
+                v[idx(k + 1)]
+            } else {
+                v[idx(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut res = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let get = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = get(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            res.push((x as usize, y as usize));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    res.reverse();
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::arb_text;
+    use proptest::{proptest, proptest_helper};
+
+    fn check_diff(before: &str, after: &str, expected: &[(u32, u32, &str)]) {
+        let edit = TextEdit::diff(before, after);
+        assert_eq!(edit.apply(before), after);
+        let actual = edit
+            .as_atoms()
+            .iter()
+            .map(|atom| {
+                let range = atom.delete;
+                (
+                    range.start().to_usize() as u32,
+                    range.end().to_usize() as u32,
+                    atom.insert.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_diff() {
+        check_diff("", "", &[]);
+        check_diff("fn main() {}\n", "fn main() {}\n", &[]);
+        check_diff("fn main()  {}\n", "fn main() {}\n", &[(10, 11, "")]);
+        check_diff(
+            "a\nb\nc\nd\n",
+            "a\nB\nc\nd\ne\n",
+            &[(2, 3, "B"), (8, 8, "e\n")],
+        );
+        check_diff(
+            "let x=1;\nlet y=2;\n",
+            "let x = 1;\nlet y = 2;\n",
+            &[(5, 5, " "), (6, 6, " "), (14, 14, " "), (15, 15, " ")],
+        );
+    }
+
+    #[test]
+    fn test_diff_of_unrelated_texts() {
+        let before = (0..2000).map(|i| format!("{}\n", i)).collect::<String>();
+        let after = (0..2000)
+            .rev()
+            .map(|i| format!("{}\n", i))
+            .collect::<String>();
+        let edit = TextEdit::diff(&before, &after);
+        assert_eq!(edit.apply(&before), after);
+    }
+
+    proptest! {
+        #[test]
+        fn test_diff_applies(before in arb_text(), after in arb_text()) {
+            let edit = TextEdit::diff(&before, &after);
+            assert_eq!(edit.apply(&before), after);
+        }
+    }
+}
@@ -1,8 +1,9 @@
+mod diff;
 mod text_edit;
 pub mod text_utils;
 pub mod test_utils;
 
-pub use crate::text_edit::{TextEdit, TextEditBuilder};
+pub use crate::text_edit::{OverlappingEditsError, TextEdit, TextEditBuilder};
 
 use text_unit::{TextRange, TextUnit};
 
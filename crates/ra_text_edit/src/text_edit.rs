@@ -1,5 +1,8 @@
+use std::{fmt, mem};
+
 use crate::AtomTextEdit;
 use crate::text_utils::contains_offset_nonstrict;
+use ropey::Rope;
 use text_unit::{TextRange, TextUnit};
 
 #[derive(Debug, Clone)]
@@ -81,4 +84,246 @@ impl TextEdit {
         }
         Some(res)
     }
+
+    /// Applies the edit in place, without converting the rope to a string.
+    pub fn apply_to_rope(&self, rope: &mut Rope) {
+        // Going backwards keeps the offsets of the remaining atoms valid.
+        for atom in self.atoms.iter().rev() {
+            let start = rope.byte_to_char(atom.delete.start().to_usize());
+            let end = rope.byte_to_char(atom.delete.end().to_usize());
+            rope.remove(start..end);
+            rope.insert(start, &atom.insert);
+        }
+    }
+
+    /// Combines `self` with `other`, an edit of the text produced by `self`,
+    /// into a single edit of the original text.
+    pub fn compose(self, other: TextEdit) -> TextEdit {
+        // The intermediate text, in terms of the original one.
+        let mut pieces = Vec::new();
+        let mut prev = TextUnit::from(0);
+        for atom in self.atoms {
+            pieces.push(Piece::Kept(prev, Some(atom.delete.start())));
+            pieces.push(Piece::Inserted(atom.insert));
+            prev = atom.delete.end();
+        }
+        pieces.push(Piece::Kept(prev, None));
+
+        // The final text, in terms of the original one.
+        let mut res = Vec::new();
+        let mut prev = TextUnit::from(0);
+        for atom in other.atoms {
+            slice_pieces(&pieces, prev, Some(atom.delete.start()), &mut res);
+            res.push(Piece::Inserted(atom.insert));
+            prev = atom.delete.end();
+        }
+        slice_pieces(&pieces, prev, None, &mut res);
+
+        // Everything between the kept parts of the original text is replaced.
+        let mut atoms = Vec::new();
+        let mut offset = TextUnit::from(0);
+        let mut insert = String::new();
+        for piece in res {
+            if piece.len() == Some(TextUnit::from(0)) {
+                continue;
+            }
+            match piece {
+                Piece::Inserted(text) => insert.push_str(&text),
+                Piece::Kept(start, end) => {
+                    if start != offset || !insert.is_empty() {
+                        let range = TextRange::from_to(offset, start);
+                        let insert = mem::replace(&mut insert, String::new());
+                        atoms.push(AtomTextEdit::replace(range, insert));
+                    }
+                    match end {
+                        Some(end) => offset = end,
+                        None => break,
+                    }
+                }
+            }
+        }
+        TextEdit { atoms }
+    }
+
+    /// Combines two edits of the same text. At the same offset, the
+    /// insertions of `self` go first.
+    pub fn merge(self, other: TextEdit) -> Result<TextEdit, OverlappingEditsError> {
+        let mut atoms = self.atoms;
+        atoms.extend(other.atoms);
+        atoms.sort_by_key(|a| (a.delete.start(), a.delete.end()));
+        for (a1, a2) in atoms.iter().zip(atoms.iter().skip(1)) {
+            if a1.delete.end() > a2.delete.start() {
+                return Err(OverlappingEditsError {
+                    first: a1.delete,
+                    second: a2.delete,
+                });
+            }
+        }
+        Ok(TextEdit { atoms })
+    }
+}
+
+/// The edits passed to `TextEdit::merge` change the same part of the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlappingEditsError {
+    first: TextRange,
+    second: TextRange,
+}
+
+impl OverlappingEditsError {
+    /// The overlapping ranges, in the order of the text.
+    pub fn ranges(&self) -> (TextRange, TextRange) {
+        (self.first, self.second)
+    }
+}
+
+impl fmt::Display for OverlappingEditsError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "overlapping edits: {:?} and {:?}",
+            self.first, self.second
+        )
+    }
+}
+
+impl std::error::Error for OverlappingEditsError {}
+
+/// A part of an edited text.
+#[derive(Debug)]
+enum Piece {
+    /// A range of the original text, `None` is the end of the text.
+    Kept(TextUnit, Option<TextUnit>),
+    Inserted(String),
+}
+
+impl Piece {
+    fn len(&self) -> Option<TextUnit> {
+        match self {
+            Piece::Kept(start, end) => end.map(|end| end - *start),
+            Piece::Inserted(text) => Some(TextUnit::of_str(text)),
+        }
+    }
+
+    /// The part of the piece between the relative offsets `from` and `to`.
+    fn slice(&self, from: TextUnit, to: Option<TextUnit>) -> Piece {
+        match self {
+            Piece::Kept(start, end) => {
+                Piece::Kept(*start + from, to.map(|to| *start + to).or(*end))
+            }
+            Piece::Inserted(text) => {
+                let to = to.map_or(text.len(), TextUnit::to_usize);
+                Piece::Inserted(text[from.to_usize()..to].to_string())
+            }
+        }
+    }
+}
+
+/// Pushes the parts of `pieces` between the offsets `from` and `to` of the
+/// text they make up.
+fn slice_pieces(pieces: &[Piece], from: TextUnit, to: Option<TextUnit>, acc: &mut Vec<Piece>) {
+    let mut offset = TextUnit::from(0);
+    for piece in pieces {
+        if to.map_or(false, |to| offset >= to) {
+            break;
+        }
+        let piece_end = piece.len().map(|len| offset + len);
+        let start = offset.max(from);
+        let end = match (piece_end, to) {
+            (Some(piece_end), Some(to)) => Some(piece_end.min(to)),
+            (end, None) | (None, end) => end,
+        };
+        if end.map_or(true, |end| start < end) {
+            acc.push(piece.slice(start - offset, end.map(|end| end - offset)));
+        }
+        match piece_end {
+            Some(piece_end) => offset = piece_end,
+            None => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{arb_text_edit, arb_text_with_edit};
+    use proptest::{prelude::*, proptest, proptest_helper};
+
+    fn edit(atoms: &[(u32, u32, &str)]) -> TextEdit {
+        let atoms = atoms
+            .iter()
+            .map(|&(start, end, text)| {
+                let range = TextRange::from_to(start.into(), end.into());
+                AtomTextEdit::replace(range, text.to_string())
+            })
+            .collect();
+        TextEdit::from_atoms(atoms)
+    }
+
+    #[test]
+    fn test_compose() {
+        let first = edit(&[(4, 4, "foo"), (8, 10, "")]);
+        let second = edit(&[(0, 2, "x"), (5, 9, "bar")]);
+        let text = "0123456789ab";
+        let composed = first.clone().compose(second.clone());
+        assert_eq!(composed.apply(text), second.apply(&first.apply(text)));
+        assert_eq!(composed.apply(text), "x23fbar67ab");
+        assert_eq!(composed.as_atoms().len(), 3);
+    }
+
+    #[test]
+    fn test_merge() {
+        let text = "0123456789";
+        let merged = edit(&[(2, 4, "a")])
+            .merge(edit(&[(0, 1, "b"), (4, 4, "c")]))
+            .unwrap();
+        assert_eq!(merged.apply(text), "b1ac456789");
+
+        let err = edit(&[(2, 5, "a")])
+            .merge(edit(&[(4, 6, "b")]))
+            .unwrap_err();
+        assert_eq!(
+            err.ranges(),
+            (
+                TextRange::from_to(2.into(), 5.into()),
+                TextRange::from_to(4.into(), 6.into())
+            )
+        );
+    }
+
+    #[derive(Debug)]
+    struct ArbTwoEdits {
+        text: String,
+        first: TextEdit,
+        second: TextEdit,
+    }
+
+    fn arb_two_edits() -> BoxedStrategy<ArbTwoEdits> {
+        arb_text_with_edit()
+            .prop_flat_map(|x| {
+                let second = arb_text_edit(&x.edit.apply(&x.text));
+                (Just(x), second).prop_map(|(x, second)| ArbTwoEdits {
+                    text: x.text,
+                    first: x.edit,
+                    second,
+                })
+            })
+            .boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn test_compose_matches_sequential_apply(x in arb_two_edits()) {
+            let expected = x.second.apply(&x.first.apply(&x.text));
+            let composed = x.first.compose(x.second);
+            assert_eq!(composed.apply(&x.text), expected);
+        }
+
+        #[test]
+        fn test_apply_to_rope(x in arb_text_with_edit()) {
+            let mut rope = Rope::from_str(&x.text);
+            x.edit.apply_to_rope(&mut rope);
+            assert_eq!(rope.to_string(), x.edit.apply(&x.text));
+        }
+    }
 }
@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// The rules of the `.gitignore` and `.ignore` files which apply to a root:
+/// the ones in the root directory itself, and the ones in its ancestors up to
+/// the root of the git repository.
+///
+/// Ignore files in the subdirectories of the root are not read, as that would
+/// require a walk of the whole root whenever the filter is created.
+#[derive(Debug, Default)]
+pub(crate) struct IgnoreRules {
+    /// Ordered from the outermost directory to the innermost one, so that the
+    /// rules of nested directories take precedence.
+    files: Vec<IgnoreFile>,
+}
+
+#[derive(Debug)]
+struct IgnoreFile {
+    /// The directory of the ignore file; its patterns are relative to it.
+    dir: PathBuf,
+    globs: GlobSet,
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Debug)]
+struct Pattern {
+    negated: bool,
+    dir_only: bool,
+}
+
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+impl IgnoreRules {
+    pub(crate) fn load(root: &Path) -> IgnoreRules {
+        let mut dirs = Vec::new();
+        let mut in_repo = false;
+        for dir in root.ancestors() {
+            dirs.push(dir);
+            if dir.join(".git").exists() {
+                in_repo = true;
+                break;
+            }
+        }
+        // Outside of a git repository, only the root's own files apply.
+        if !in_repo {
+            dirs.truncate(1);
+        }
+
+        let mut files = Vec::new();
+        for dir in dirs.into_iter().rev() {
+            for name in IGNORE_FILES {
+                let text = match fs::read_to_string(dir.join(name)) {
+                    Ok(it) => it,
+                    Err(_) => continue,
+                };
+                if let Some(file) = IgnoreFile::parse(dir.to_path_buf(), &text) {
+                    files.push(file);
+                }
+            }
+        }
+        IgnoreRules { files }
+    }
+
+    /// Checks if the file or directory at the absolute `path` is ignored. The
+    /// last matching pattern wins, negated patterns re-include the path.
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for file in self.files.iter().rev() {
+            if let Some(negated) = file.matched(path, is_dir) {
+                return !negated;
+            }
+        }
+        false
+    }
+}
+
+impl IgnoreFile {
+    fn parse(dir: PathBuf, text: &str) -> Option<IgnoreFile> {
+        let mut builder = GlobSetBuilder::new();
+        let mut patterns = Vec::new();
+        for line in text.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let negated = line.starts_with('!');
+            let line = if negated {
+                &line[1..]
+            } else {
+                line.trim_start_matches('\\')
+            };
+            let dir_only = line.ends_with('/');
+            let line = line.trim_end_matches('/');
+            if line.is_empty() {
+                continue;
+            }
+            // Patterns with a slash are relative to the directory of the
+            // file, the other ones match at any depth.
+            let glob = if line.contains('/') {
+                line.trim_start_matches('/').to_string()
+            } else {
+                format!("**/{}", line)
+            };
+            match GlobBuilder::new(&glob).literal_separator(true).build() {
+                Ok(glob) => {
+                    builder.add(glob);
+                    patterns.push(Pattern { negated, dir_only });
+                }
+                Err(err) => log::warn!("invalid ignore pattern {:?}: {}", line, err),
+            }
+        }
+        if patterns.is_empty() {
+            return None;
+        }
+        let globs = builder.build().ok()?;
+        Some(IgnoreFile {
+            dir,
+            globs,
+            patterns,
+        })
+    }
+
+    /// Returns whether the last pattern matching `path` is negated, or `None`
+    /// if no pattern matches.
+    fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let rel_path = path.strip_prefix(&self.dir).ok()?;
+        self.globs
+            .matches(rel_path)
+            .into_iter()
+            .rev()
+            .map(|idx| &self.patterns[idx])
+            .find(|pattern| is_dir || !pattern.dir_only)
+            .map(|pattern| pattern.negated)
+    }
+}
@@ -6,7 +6,9 @@
 //! component which does this.
 //!
 //! It is also responsible for watching the disk for changes, and for merging
-//! editor state (modified, unsaved files) with disk state.
+//! editor state (modified, unsaved files) with disk state. The text of a file
+//! open in the editor is an overlay: it takes precedence over the contents of
+//! the file on disk until the overlay is removed.
 //! TODO: Some LSP clients support watching the disk, so this crate should
 //! to support custom watcher events (related to https://github.com/rust-analyzer/rust-analyzer/issues/131)
 //!
@@ -14,6 +16,7 @@
 //! which are watched for changes. Typically, there will be a root for each
 //! Cargo package.
 mod io;
+mod ignore;
 
 use std::{
     fmt,
@@ -22,40 +25,109 @@ use std::{
     cmp::Reverse,
     path::{Path, PathBuf},
     ffi::OsStr,
-    sync::Arc,
     fs,
+    sync::Arc,
 };
 
 use rustc_hash::{FxHashMap, FxHashSet};
 use relative_path::RelativePathBuf;
+use ropey::Rope;
 use crossbeam_channel::Receiver;
 use walkdir::DirEntry;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use thread_worker::WorkerHandle;
 use ra_arena::{Arena, RawId, impl_arena_id};
 
+use crate::ignore::IgnoreRules;
+
 pub use crate::io::TaskResult as VfsTask;
 
+/// `VfsFilter` decides which files and directories of a root are loaded.
+///
+/// `target` directories, hidden directories and files without the `.rs`
+/// extension are always skipped. On top of that, paths matching any of the
+/// exclude globs are skipped as well. Globs are matched against paths relative
+/// to the root. Paths ignored by `.gitignore` and `.ignore` files are checked
+/// separately, by each root.
+#[derive(Debug, Clone)]
+pub struct VfsFilter {
+    excludes: GlobSet,
+}
+
+impl Default for VfsFilter {
+    fn default() -> VfsFilter {
+        VfsFilter {
+            excludes: GlobSet::empty(),
+        }
+    }
+}
+
+impl VfsFilter {
+    pub fn new(exclude_globs: &[String]) -> Result<VfsFilter, globset::Error> {
+        let mut builder = GlobSetBuilder::new();
+        for glob in exclude_globs {
+            builder.add(Glob::new(glob)?);
+        }
+        let excludes = builder.build()?;
+        Ok(VfsFilter { excludes })
+    }
+
+    /// Checks if the directory at `rel_path` should be scanned.
+    pub fn include_dir(&self, rel_path: &Path) -> bool {
+        let is_ignored = match rel_path.file_name().and_then(|it| it.to_str()) {
+            Some(name) => name == "target" || name.starts_with('.'),
+            None => false,
+        };
+        !is_ignored && !self.excludes.is_match(rel_path)
+    }
+
+    /// Checks if the file at `rel_path` should be loaded.
+    pub fn include_file(&self, rel_path: &Path) -> bool {
+        has_rs_extension(rel_path) && !self.excludes.is_match(rel_path)
+    }
+}
+
 /// `RootFilter` is a predicate that checks if a file can belong to a root. If
 /// several filters match a file (nested dirs), the most nested one wins.
 struct RootFilter {
     root: PathBuf,
-    file_filter: fn(&Path) -> bool,
+    filter: Arc<VfsFilter>,
+    ignore: Arc<IgnoreRules>,
 }
 
 impl RootFilter {
-    fn new(root: PathBuf) -> RootFilter {
+    fn new(root: PathBuf, filter: Arc<VfsFilter>) -> RootFilter {
+        let ignore = Arc::new(IgnoreRules::load(&root));
         RootFilter {
             root,
-            file_filter: has_rs_extension,
+            filter,
+            ignore,
         }
     }
     /// Check if this root can contain `path`. NB: even if this returns
     /// true, the `path` might actually be conained in some nested root.
     fn can_contain(&self, path: &Path) -> Option<RelativePathBuf> {
-        if !(self.file_filter)(path) {
+        let path = path.strip_prefix(&self.root).ok()?;
+        if !self.filter.include_file(path) {
+            return None;
+        }
+        if !path
+            .ancestors()
+            .skip(1)
+            .all(|dir| self.filter.include_dir(dir))
+        {
+            return None;
+        }
+        let full_path = self.root.join(path);
+        if self.ignore.is_ignored(&full_path, false)
+            || full_path
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| *dir != self.root)
+                .any(|dir| self.ignore.is_ignored(dir, true))
+        {
             return None;
         }
-        let path = path.strip_prefix(&self.root).ok()?;
         RelativePathBuf::from_path(path).ok()
     }
 }
@@ -64,6 +136,36 @@ fn has_rs_extension(p: &Path) -> bool {
     p.extension() == Some(OsStr::new("rs"))
 }
 
+/// Normalizes the Windows specifics of `path`, so that the paths of a file
+/// which come from the editor, from Cargo and from the disk are equal: the
+/// long-path prefixes (`\\?\C:\`, `\\?\UNC\server\share`) are removed
+/// and the drive letter is uppercased. Other paths are returned unchanged.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let path_str = match path.to_str() {
+        Some(it) => it,
+        None => return path.to_path_buf(),
+    };
+    let mut res = if path_str.starts_with(r"\\?\UNC\") {
+        format!(r"\\{}", &path_str[8..])
+    } else if path_str.starts_with(r"\\?\") && has_drive_letter(&path_str[4..]) {
+        path_str[4..].to_string()
+    } else {
+        path_str.to_string()
+    };
+    if has_drive_letter(&res) {
+        res[..1].make_ascii_uppercase();
+    }
+    PathBuf::from(res)
+}
+
+fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes.len() == 2 || bytes[2] == b'\\' || bytes[2] == b'/')
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct VfsRoot(pub RawId);
 impl_arena_id!(VfsRoot);
@@ -75,13 +177,16 @@ impl_arena_id!(VfsFile);
 struct VfsFileData {
     root: VfsRoot,
     path: RelativePathBuf,
-    text: Arc<String>,
+    text: Rope,
+    /// The text comes from the editor rather than from the disk.
+    is_overlay: bool,
 }
 
 pub struct Vfs {
     roots: Arena<VfsRoot, RootFilter>,
     files: Arena<VfsFile, VfsFileData>,
     root2files: FxHashMap<VfsRoot, FxHashSet<VfsFile>>,
+    filter: Arc<VfsFilter>,
     pending_changes: Vec<VfsChange>,
     worker: io::Worker,
     worker_handle: WorkerHandle,
@@ -94,46 +199,131 @@ impl fmt::Debug for Vfs {
 }
 
 impl Vfs {
-    pub fn new(mut roots: Vec<PathBuf>) -> (Vfs, Vec<VfsRoot>) {
+    pub fn new(roots: Vec<PathBuf>, filter: VfsFilter) -> (Vfs, Vec<VfsRoot>) {
         let (worker, worker_handle) = io::start();
+        let filter = Arc::new(filter);
 
         let mut res = Vfs {
             roots: Arena::default(),
             files: Arena::default(),
             root2files: FxHashMap::default(),
+            filter,
             worker,
             worker_handle,
             pending_changes: Vec::new(),
         };
 
         // A hack to make nesting work.
+        let mut roots = roots
+            .iter()
+            .map(|it| normalize_path(it))
+            .collect::<Vec<_>>();
         roots.sort_by_key(|it| Reverse(it.as_os_str().len()));
-        for (i, path) in roots.iter().enumerate() {
-            let root = res.roots.alloc(RootFilter::new(path.clone()));
+        for path in roots {
+            let root = res
+                .roots
+                .alloc(RootFilter::new(path, Arc::clone(&res.filter)));
             res.root2files.insert(root, Default::default());
-            let nested = roots[..i]
-                .iter()
-                .filter(|it| it.starts_with(path))
-                .map(|it| it.clone())
-                .collect::<Vec<_>>();
-            let filter = move |entry: &DirEntry| {
-                if entry.file_type().is_file() {
-                    has_rs_extension(entry.path())
-                } else {
-                    nested.iter().all(|it| it != entry.path())
-                }
-            };
-            let task = io::Task {
-                root,
-                path: path.clone(),
-                filter: Box::new(filter),
-            };
-            res.worker.inp.send(task).unwrap();
+            res.scan_root(root);
         }
         let roots = res.roots.iter().map(|(id, _)| id).collect();
         (res, roots)
     }
 
+    /// Adds a new root, for example for a dependency which was added to the
+    /// project. Files of the enclosing roots which belong to the new root are
+    /// moved to it, keeping their current (possibly unsaved) text. Returns
+    /// `None` if the root already exists.
+    pub fn add_root(&mut self, path: PathBuf) -> Option<VfsRoot> {
+        let path = normalize_path(&path);
+        if self.roots.iter().any(|(_, data)| data.root == path) {
+            return None;
+        }
+        let root = self
+            .roots
+            .alloc(RootFilter::new(path.clone(), Arc::clone(&self.filter)));
+        self.root2files.insert(root, Default::default());
+
+        let moved = self
+            .root2files
+            .iter()
+            .filter(|&(&old_root, _)| {
+                old_root != root && path.starts_with(&self.roots[old_root].root)
+            })
+            .flat_map(|(_, files)| files.iter().cloned())
+            .filter(|&file| self.file2path(file).starts_with(&path))
+            .collect::<Vec<_>>();
+        for file in moved {
+            let full_path = self.file2path(file);
+            let old_root = self.files[file].root;
+            let old_path = self.files[file].path.clone();
+            let text = self.files[file].text.clone();
+            let is_overlay = self.files[file].is_overlay;
+            self.remove_file(file);
+            self.pending_changes.push(VfsChange::RemoveFile {
+                root: old_root,
+                file,
+                path: old_path,
+            });
+            if let Some(rel_path) = self.roots[root].can_contain(&full_path) {
+                let file = self.add_file(root, rel_path.clone(), text.clone());
+                self.files[file].is_overlay = is_overlay;
+                self.pending_changes.push(VfsChange::AddFile {
+                    root,
+                    file,
+                    path: rel_path,
+                    text,
+                });
+            }
+        }
+
+        self.scan_root(root);
+        Some(root)
+    }
+
+    /// Asks the background worker to load the files of `root`, skipping
+    /// directories of other roots nested in it.
+    fn scan_root(&mut self, root: VfsRoot) {
+        let path = self.roots[root].root.clone();
+        let nested = self
+            .roots
+            .iter()
+            .filter(|&(id, data)| id != root && data.root.starts_with(&path))
+            .map(|(_, data)| data.root.clone())
+            .collect::<Vec<_>>();
+        let root_filter = Arc::clone(&self.filter);
+        let ignore = Arc::clone(&self.roots[root].ignore);
+        let root_path = path.clone();
+        let filter = move |entry: &DirEntry| {
+            let rel_path = match entry.path().strip_prefix(&root_path) {
+                Ok(it) => it,
+                Err(_) => return false,
+            };
+            if entry.depth() > 0 && ignore.is_ignored(entry.path(), entry.file_type().is_dir()) {
+                return false;
+            }
+            if entry.file_type().is_file() {
+                root_filter.include_file(rel_path)
+            } else {
+                root_filter.include_dir(rel_path) && nested.iter().all(|it| it != entry.path())
+            }
+        };
+        let task = io::Task {
+            root,
+            path,
+            filter: Box::new(filter),
+        };
+        self.worker.inp.send(task).unwrap();
+    }
+
+    /// The path of each root together with the number of files in it.
+    pub fn root_file_counts(&self) -> Vec<(PathBuf, usize)> {
+        self.roots
+            .iter()
+            .map(|(id, data)| (data.root.clone(), self.root2files[&id].len()))
+            .collect()
+    }
+
     pub fn root2path(&self, root: VfsRoot) -> PathBuf {
         self.roots[root].root.clone()
     }
@@ -164,8 +354,8 @@ impl Vfs {
                 Some(file)
             } else {
                 let text = fs::read_to_string(path).unwrap_or_default();
-                let text = Arc::new(text);
-                let file = self.add_file(root, rel_path.clone(), Arc::clone(&text));
+                let text = Rope::from(text);
+                let file = self.add_file(root, rel_path.clone(), text.clone());
                 let change = VfsChange::AddFile {
                     file,
                     text,
@@ -193,12 +383,12 @@ impl Vfs {
             .collect::<FxHashMap<_, _>>();
         for (path, text) in task.files {
             if let Some(&file) = exising.get(&path) {
-                let text = Arc::clone(&self.files[file].text);
+                let text = self.files[file].text.clone();
                 files.push((file, path, text));
                 continue;
             }
-            let text = Arc::new(text);
-            let file = self.add_file(task.root, path.clone(), Arc::clone(&text));
+            let text = Rope::from(text);
+            let file = self.add_file(task.root, path.clone(), text.clone());
             files.push((file, path, text));
         }
 
@@ -209,56 +399,98 @@ impl Vfs {
         self.pending_changes.push(change);
     }
 
-    pub fn add_file_overlay(&mut self, path: &Path, text: String) -> Option<VfsFile> {
-        let mut res = None;
-        if let Some((root, path, file)) = self.find_root(path) {
-            let text = Arc::new(text);
-            let change = if let Some(file) = file {
-                res = Some(file);
-                self.change_file(file, Arc::clone(&text));
-                VfsChange::ChangeFile { file, text }
-            } else {
-                let file = self.add_file(root, path.clone(), Arc::clone(&text));
-                res = Some(file);
-                VfsChange::AddFile {
-                    file,
-                    text,
-                    root,
-                    path,
-                }
+    /// Sets the text of the file at `path` to the editor's one, which takes
+    /// precedence over the contents of the file on disk until the overlay is
+    /// removed. Returns `None` if no root can contain the file.
+    pub fn set_overlay(&mut self, path: &Path, text: String) -> Option<VfsFile> {
+        let (root, path, file) = self.find_root(path)?;
+        let text = Rope::from(text);
+        let (file, change) = if let Some(file) = file {
+            self.change_file(file, text.clone());
+            (file, VfsChange::ChangeFile { file, text })
+        } else {
+            let file = self.add_file(root, path.clone(), text.clone());
+            let change = VfsChange::AddFile {
+                file,
+                text,
+                root,
+                path,
             };
-            self.pending_changes.push(change);
-        }
-        res
+            (file, change)
+        };
+        self.files[file].is_overlay = true;
+        self.pending_changes.push(change);
+        Some(file)
     }
 
-    pub fn change_file_overlay(&mut self, path: &Path, new_text: String) {
+    /// Applies `edit` to the text of an overlaid file. As the text is a rope,
+    /// small edits of large files are cheap.
+    pub fn edit_overlay(&mut self, path: &Path, edit: impl FnOnce(&mut Rope)) {
         if let Some((_root, _path, file)) = self.find_root(path) {
             let file = file.expect("can't change a file which wasn't added");
-            let text = Arc::new(new_text);
-            self.change_file(file, Arc::clone(&text));
+            let mut text = self.files[file].text.clone();
+            edit(&mut text);
+            self.change_file(file, text.clone());
+            self.files[file].is_overlay = true;
             let change = VfsChange::ChangeFile { file, text };
             self.pending_changes.push(change);
         }
     }
 
-    pub fn remove_file_overlay(&mut self, path: &Path) -> Option<VfsFile> {
-        let mut res = None;
-        if let Some((root, path, file)) = self.find_root(path) {
-            let file = file.expect("can't remove a file which wasn't added");
-            res = Some(file);
-            let full_path = path.to_path(&self.roots[root].root);
-            let change = if let Ok(text) = fs::read_to_string(&full_path) {
-                let text = Arc::new(text);
-                self.change_file(file, Arc::clone(&text));
+    /// Removes the overlay of the file at `path`, reverting it to the
+    /// contents on disk. If the file does not exist on disk, it is removed.
+    pub fn remove_overlay(&mut self, path: &Path) -> Option<VfsFile> {
+        let (root, path, file) = self.find_root(path)?;
+        let file = file.expect("can't remove a file which wasn't added");
+        self.files[file].is_overlay = false;
+        let full_path = path.to_path(&self.roots[root].root);
+        let change = if let Ok(text) = fs::read_to_string(&full_path) {
+            let text = Rope::from(text);
+            self.change_file(file, text.clone());
+            VfsChange::ChangeFile { file, text }
+        } else {
+            self.remove_file(file);
+            VfsChange::RemoveFile { root, file, path }
+        };
+        self.pending_changes.push(change);
+        Some(file)
+    }
+
+    /// Handles a watcher event: the file at `path` was created, changed or
+    /// removed on disk. Events for overlaid files are ignored, as the editor
+    /// has the more recent text.
+    pub fn notify_changed(&mut self, path: &Path) {
+        let (root, rel_path, file) = match self.find_root(path) {
+            Some(it) => it,
+            None => return,
+        };
+        let text = fs::read_to_string(path).ok().map(Rope::from);
+        let change = match (file, text) {
+            (Some(file), _) if self.files[file].is_overlay => return,
+            (Some(file), Some(text)) => {
+                self.change_file(file, text.clone());
                 VfsChange::ChangeFile { file, text }
-            } else {
+            }
+            (Some(file), None) => {
                 self.remove_file(file);
-                VfsChange::RemoveFile { root, file, path }
-            };
-            self.pending_changes.push(change);
-        }
-        res
+                VfsChange::RemoveFile {
+                    root,
+                    file,
+                    path: rel_path,
+                }
+            }
+            (None, Some(text)) => {
+                let file = self.add_file(root, rel_path.clone(), text.clone());
+                VfsChange::AddFile {
+                    root,
+                    file,
+                    path: rel_path,
+                    text,
+                }
+            }
+            (None, None) => return,
+        };
+        self.pending_changes.push(change);
     }
 
     pub fn commit_changes(&mut self) -> Vec<VfsChange> {
@@ -271,14 +503,19 @@ impl Vfs {
         self.worker_handle.shutdown()
     }
 
-    fn add_file(&mut self, root: VfsRoot, path: RelativePathBuf, text: Arc<String>) -> VfsFile {
-        let data = VfsFileData { root, path, text };
+    fn add_file(&mut self, root: VfsRoot, path: RelativePathBuf, text: Rope) -> VfsFile {
+        let data = VfsFileData {
+            root,
+            path,
+            text,
+            is_overlay: false,
+        };
         let file = self.files.alloc(data);
         self.root2files.get_mut(&root).unwrap().insert(file);
         file
     }
 
-    fn change_file(&mut self, file: VfsFile, new_text: Arc<String>) {
+    fn change_file(&mut self, file: VfsFile, new_text: Rope) {
         self.files[file].text = new_text;
     }
 
@@ -292,10 +529,13 @@ impl Vfs {
     }
 
     fn find_root(&self, path: &Path) -> Option<(VfsRoot, RelativePathBuf, Option<VfsFile>)> {
+        let path = &normalize_path(path);
+        // If several roots can contain the file, the most nested one wins.
         let (root, path) = self
             .roots
             .iter()
-            .find_map(|(root, data)| data.can_contain(path).map(|it| (root, it)))?;
+            .filter_map(|(root, data)| data.can_contain(path).map(|it| (root, it)))
+            .min_by_key(|(_, rel_path)| rel_path.components().count())?;
         let file = self.root2files[&root]
             .iter()
             .map(|&it| it)
@@ -308,13 +548,13 @@ impl Vfs {
 pub enum VfsChange {
     AddRoot {
         root: VfsRoot,
-        files: Vec<(VfsFile, RelativePathBuf, Arc<String>)>,
+        files: Vec<(VfsFile, RelativePathBuf, Rope)>,
     },
     AddFile {
         root: VfsRoot,
         file: VfsFile,
         path: RelativePathBuf,
-        text: Arc<String>,
+        text: Rope,
     },
     RemoveFile {
         root: VfsRoot,
@@ -323,6 +563,6 @@ pub enum VfsChange {
     },
     ChangeFile {
         file: VfsFile,
-        text: Arc<String>,
+        text: Rope,
     },
 }
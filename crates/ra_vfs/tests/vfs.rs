@@ -1,11 +1,12 @@
 use std::{
     fs,
     collections::HashSet,
+    path::{Path, PathBuf},
 };
 
 use tempfile::tempdir;
 
-use ra_vfs::{Vfs, VfsChange};
+use ra_vfs::{normalize_path, Vfs, VfsChange, VfsFilter};
 
 #[test]
 fn test_vfs_works() -> std::io::Result<()> {
@@ -25,7 +26,7 @@ fn test_vfs_works() -> std::io::Result<()> {
     let a_root = dir.path().join("a");
     let b_root = dir.path().join("a/b");
 
-    let (mut vfs, _) = Vfs::new(vec![a_root, b_root]);
+    let (mut vfs, _) = Vfs::new(vec![a_root, b_root], VfsFilter::default());
     for _ in 0..2 {
         let task = vfs.task_receiver().recv().unwrap();
         vfs.handle_task(task);
@@ -40,7 +41,7 @@ fn test_vfs_works() -> std::io::Result<()> {
                     _ => panic!("unexpected change"),
                 };
                 files.into_iter().map(|(_id, path, text)| {
-                    let text: String = (&*text).clone();
+                    let text = text.to_string();
                     (format!("{}", path.display()), text)
                 })
             })
@@ -58,38 +59,48 @@ fn test_vfs_works() -> std::io::Result<()> {
         assert_eq!(files, expected_files);
     }
 
-    vfs.add_file_overlay(&dir.path().join("a/b/baz.rs"), "quux".to_string());
+    vfs.set_overlay(&dir.path().join("a/b/baz.rs"), "quux".to_string());
     let change = vfs.commit_changes().pop().unwrap();
     match change {
-        VfsChange::ChangeFile { text, .. } => assert_eq!(&*text, "quux"),
+        VfsChange::ChangeFile { text, .. } => assert_eq!(text, "quux"),
         _ => panic!("unexpected change"),
     }
 
-    vfs.change_file_overlay(&dir.path().join("a/b/baz.rs"), "m".to_string());
+    vfs.set_overlay(&dir.path().join("a/b/baz.rs"), "m".to_string());
     let change = vfs.commit_changes().pop().unwrap();
     match change {
-        VfsChange::ChangeFile { text, .. } => assert_eq!(&*text, "m"),
+        VfsChange::ChangeFile { text, .. } => assert_eq!(text, "m"),
         _ => panic!("unexpected change"),
     }
 
-    vfs.remove_file_overlay(&dir.path().join("a/b/baz.rs"));
+    vfs.edit_overlay(&dir.path().join("a/b/baz.rs"), |text| {
+        text.insert(0, "s");
+        text.insert(2, "n");
+    });
     let change = vfs.commit_changes().pop().unwrap();
     match change {
-        VfsChange::ChangeFile { text, .. } => assert_eq!(&*text, "nested hello"),
+        VfsChange::ChangeFile { text, .. } => assert_eq!(text, "smn"),
         _ => panic!("unexpected change"),
     }
 
-    vfs.add_file_overlay(&dir.path().join("a/b/spam.rs"), "spam".to_string());
+    vfs.remove_overlay(&dir.path().join("a/b/baz.rs"));
+    let change = vfs.commit_changes().pop().unwrap();
+    match change {
+        VfsChange::ChangeFile { text, .. } => assert_eq!(text, "nested hello"),
+        _ => panic!("unexpected change"),
+    }
+
+    vfs.set_overlay(&dir.path().join("a/b/spam.rs"), "spam".to_string());
     let change = vfs.commit_changes().pop().unwrap();
     match change {
         VfsChange::AddFile { text, path, .. } => {
-            assert_eq!(&*text, "spam");
+            assert_eq!(text, "spam");
             assert_eq!(path, "spam.rs");
         }
         _ => panic!("unexpected change"),
     }
 
-    vfs.remove_file_overlay(&dir.path().join("a/b/spam.rs"));
+    vfs.remove_overlay(&dir.path().join("a/b/spam.rs"));
     let change = vfs.commit_changes().pop().unwrap();
     match change {
         VfsChange::RemoveFile { .. } => (),
@@ -99,3 +110,232 @@ fn test_vfs_works() -> std::io::Result<()> {
     vfs.shutdown().unwrap();
     Ok(())
 }
+
+#[test]
+fn test_vfs_skips_excluded_paths() -> std::io::Result<()> {
+    let files = [
+        ("src/lib.rs", "lib"),
+        ("src/gen/big.rs", "generated"),
+        ("target/debug/build.rs", "build"),
+        (".git/hook.rs", "hook"),
+        ("README.md", "readme"),
+    ];
+
+    let dir = tempdir()?;
+    for (path, text) in files.iter() {
+        let file_path = dir.path().join(path);
+        fs::create_dir_all(file_path.parent().unwrap())?;
+        fs::write(file_path, text)?
+    }
+
+    let filter = VfsFilter::new(&["src/gen".to_string()]).unwrap();
+    let (mut vfs, _) = Vfs::new(vec![dir.path().to_path_buf()], filter);
+    let task = vfs.task_receiver().recv().unwrap();
+    vfs.handle_task(task);
+    let files = match vfs.commit_changes().pop().unwrap() {
+        VfsChange::AddRoot { files, .. } => files,
+        _ => panic!("unexpected change"),
+    };
+    let paths = files
+        .into_iter()
+        .map(|(_id, path, _text)| format!("{}", path.display()))
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["src/lib.rs".to_string()]);
+
+    assert!(vfs
+        .set_overlay(&dir.path().join("target/debug/build.rs"), "".to_string())
+        .is_none());
+
+    vfs.shutdown().unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_vfs_respects_ignore_files() -> std::io::Result<()> {
+    let files = [
+        (
+            ".gitignore",
+            "# vendored sources\nvendor/\n*.bak.rs\n!keep.bak.rs\n",
+        ),
+        (".ignore", "/src/scratch.rs\n"),
+        ("src/lib.rs", "lib"),
+        ("src/old.bak.rs", "old"),
+        ("src/keep.bak.rs", "keep"),
+        ("src/scratch.rs", "scratch"),
+        ("tests/scratch.rs", "not ignored"),
+        ("vendor/dep/lib.rs", "vendored"),
+    ];
+
+    let dir = tempdir()?;
+    for (path, text) in files.iter() {
+        let file_path = dir.path().join(path);
+        fs::create_dir_all(file_path.parent().unwrap())?;
+        fs::write(file_path, text)?
+    }
+
+    let (mut vfs, _) = Vfs::new(vec![dir.path().to_path_buf()], VfsFilter::default());
+    let task = vfs.task_receiver().recv().unwrap();
+    vfs.handle_task(task);
+    let files = match vfs.commit_changes().pop().unwrap() {
+        VfsChange::AddRoot { files, .. } => files,
+        _ => panic!("unexpected change"),
+    };
+    let mut paths = files
+        .into_iter()
+        .map(|(_id, path, _text)| format!("{}", path.display()))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            "src/keep.bak.rs".to_string(),
+            "src/lib.rs".to_string(),
+            "tests/scratch.rs".to_string(),
+        ]
+    );
+
+    assert!(vfs
+        .set_overlay(&dir.path().join("vendor/dep/lib.rs"), "".to_string())
+        .is_none());
+    assert!(vfs
+        .set_overlay(&dir.path().join("src/old.bak.rs"), "".to_string())
+        .is_none());
+
+    vfs.shutdown().unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_vfs_overlays_take_precedence_over_disk() -> std::io::Result<()> {
+    let dir = tempdir()?;
+    let foo_path = dir.path().join("foo.rs");
+    fs::write(&foo_path, "disk")?;
+
+    let (mut vfs, _) = Vfs::new(vec![dir.path().to_path_buf()], VfsFilter::default());
+    let task = vfs.task_receiver().recv().unwrap();
+    vfs.handle_task(task);
+    vfs.commit_changes();
+
+    // Without an overlay, watcher events update the text.
+    fs::write(&foo_path, "disk 2")?;
+    vfs.notify_changed(&foo_path);
+    match vfs.commit_changes().pop().unwrap() {
+        VfsChange::ChangeFile { text, .. } => assert_eq!(text, "disk 2"),
+        _ => panic!("unexpected change"),
+    }
+
+    // With an overlay, they are ignored.
+    let file = vfs.set_overlay(&foo_path, "editor".to_string()).unwrap();
+    vfs.commit_changes();
+    fs::write(&foo_path, "disk 3")?;
+    vfs.notify_changed(&foo_path);
+    assert!(vfs.commit_changes().is_empty());
+    fs::remove_file(&foo_path)?;
+    vfs.notify_changed(&foo_path);
+    assert!(vfs.commit_changes().is_empty());
+    assert_eq!(vfs.path2file(&foo_path), Some(file));
+
+    // Removing the overlay reverts to the contents on disk.
+    fs::write(&foo_path, "disk 4")?;
+    assert_eq!(vfs.remove_overlay(&foo_path), Some(file));
+    match vfs.commit_changes().pop().unwrap() {
+        VfsChange::ChangeFile { text, .. } => assert_eq!(text, "disk 4"),
+        _ => panic!("unexpected change"),
+    }
+    fs::remove_file(&foo_path)?;
+    vfs.notify_changed(&foo_path);
+    match vfs.commit_changes().pop().unwrap() {
+        VfsChange::RemoveFile { path, .. } => assert_eq!(path, "foo.rs"),
+        _ => panic!("unexpected change"),
+    }
+    assert_eq!(vfs.path2file(&foo_path), None);
+
+    // New files on disk are added.
+    let bar_path = dir.path().join("bar.rs");
+    fs::write(&bar_path, "bar")?;
+    vfs.notify_changed(&bar_path);
+    match vfs.commit_changes().pop().unwrap() {
+        VfsChange::AddFile { text, path, .. } => {
+            assert_eq!(text, "bar");
+            assert_eq!(path, "bar.rs");
+        }
+        _ => panic!("unexpected change"),
+    }
+
+    vfs.shutdown().unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_vfs_add_nested_root() -> std::io::Result<()> {
+    let files = [("a/foo.rs", "foo"), ("a/b/bar.rs", "bar")];
+
+    let dir = tempdir()?;
+    for (path, text) in files.iter() {
+        let file_path = dir.path().join(path);
+        fs::create_dir_all(file_path.parent().unwrap())?;
+        fs::write(file_path, text)?
+    }
+
+    let (mut vfs, _) = Vfs::new(vec![dir.path().join("a")], VfsFilter::default());
+    let task = vfs.task_receiver().recv().unwrap();
+    vfs.handle_task(task);
+    vfs.commit_changes();
+
+    let bar_path = dir.path().join("a/b/bar.rs");
+    vfs.set_overlay(&bar_path, "unsaved".to_string());
+    vfs.commit_changes();
+
+    let b_root = vfs.add_root(dir.path().join("a/b")).unwrap();
+    assert!(vfs.add_root(dir.path().join("a/b")).is_none());
+    let changes = vfs.commit_changes();
+    assert_eq!(changes.len(), 2);
+    match &changes[0] {
+        VfsChange::RemoveFile { path, .. } => assert_eq!(path, "b/bar.rs"),
+        _ => panic!("unexpected change"),
+    }
+    match &changes[1] {
+        VfsChange::AddFile {
+            root, path, text, ..
+        } => {
+            assert_eq!(*root, b_root);
+            assert_eq!(path, "bar.rs");
+            assert_eq!(text, "unsaved");
+        }
+        _ => panic!("unexpected change"),
+    }
+    assert_eq!(vfs.file2path(vfs.path2file(&bar_path).unwrap()), bar_path);
+
+    // The scan of the new root keeps the unsaved text.
+    let task = vfs.task_receiver().recv().unwrap();
+    vfs.handle_task(task);
+    match vfs.commit_changes().pop().unwrap() {
+        VfsChange::AddRoot { root, files } => {
+            assert_eq!(root, b_root);
+            assert_eq!(files.len(), 1);
+            assert_eq!(files[0].2, "unsaved");
+        }
+        _ => panic!("unexpected change"),
+    }
+
+    vfs.shutdown().unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_normalize_path() {
+    let cases = [
+        (r"c:\foo\bar.rs", r"C:\foo\bar.rs"),
+        (r"C:\foo\bar.rs", r"C:\foo\bar.rs"),
+        (r"d:", r"D:"),
+        (r"\\?\c:\foo\bar.rs", r"C:\foo\bar.rs"),
+        (r"\\?\UNC\server\share\bar.rs", r"\\server\share\bar.rs"),
+        (r"\\server\share\bar.rs", r"\\server\share\bar.rs"),
+        (r"\\?\Volume{1234}\bar.rs", r"\\?\Volume{1234}\bar.rs"),
+        ("/home/foo/bar.rs", "/home/foo/bar.rs"),
+        ("c:foo", "c:foo"),
+    ];
+    for (path, expected) in cases.iter() {
+        assert_eq!(normalize_path(Path::new(path)), PathBuf::from(expected));
+    }
+}
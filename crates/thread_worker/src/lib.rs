@@ -1,8 +1,10 @@
 //! Small utility to correctly spawn crossbeam-channel based worker threads.
 
-use std::thread;
+use std::{thread, time::Duration};
 
-use crossbeam_channel::{bounded, unbounded, Receiver, Sender, RecvError, SendError};
+use crossbeam_channel::{
+    bounded, unbounded, Receiver, Sender, RecvError, RecvTimeoutError, SendError,
+};
 use drop_bomb::DropBomb;
 
 pub struct Worker<I, O> {
@@ -13,6 +15,8 @@ pub struct Worker<I, O> {
 pub struct WorkerHandle {
     name: &'static str,
     thread: thread::JoinHandle<()>,
+    /// Disconnected when the thread finishes.
+    done: Receiver<()>,
     bomb: DropBomb,
 }
 
@@ -44,10 +48,15 @@ impl<I, O> Worker<I, O> {
 
 impl WorkerHandle {
     fn spawn(name: &'static str, f: impl FnOnce() + Send + 'static) -> WorkerHandle {
-        let thread = thread::spawn(f);
+        let (done_sender, done) = bounded::<()>(0);
+        let thread = thread::spawn(move || {
+            let _done_sender = done_sender;
+            f()
+        });
         WorkerHandle {
             name,
             thread,
+            done,
             bomb: DropBomb::new(format!("WorkerHandle {} was not shutdown", name)),
         }
     }
@@ -63,6 +72,19 @@ impl WorkerHandle {
         }
         res
     }
+
+    /// Like `shutdown`, but stops waiting after `timeout`. A thread which is
+    /// still running then is left detached.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> thread::Result<()> {
+        match self.done.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                log::warn!("{} did not finish in {:?}, detaching", self.name, timeout);
+                self.bomb.defuse();
+                Ok(())
+            }
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => self.shutdown(),
+        }
+    }
 }
 
 /// Sets up worker channels in a deadlock-avoind way.
@@ -0,0 +1,16 @@
+import * as vscode from 'vscode';
+
+import { Server } from '../server';
+
+// Shows the status report of the server in a new document, so that it can be
+// copied into bug reports.
+export async function handle() {
+    const status = await Server.client.sendRequest<string>(
+        'rust-analyzer/analyzerStatus',
+        null
+    );
+    const document = await vscode.workspace.openTextDocument({
+        content: status
+    });
+    return vscode.window.showTextDocument(document, vscode.ViewColumn.Two);
+}
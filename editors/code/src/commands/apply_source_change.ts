@@ -9,12 +9,54 @@ export interface SourceChange {
     cursorPosition?: lc.TextDocumentPositionParams;
 }
 
+interface SnippetTextEdit extends lc.TextEdit {
+    insertTextFormat?: lc.InsertTextFormat;
+}
+
+function isSnippet(edit: SnippetTextEdit): boolean {
+    return edit.insertTextFormat === lc.InsertTextFormat.Snippet;
+}
+
+// Applies `change` through the server: it sends the edit back with
+// `workspace/applyEdit`, and then moves the cursor. Assists, fixes and the
+// typing helpers all end up in `applyEdit` this way.
 export async function handle(change: SourceChange) {
-    const wsEdit = Server.client.protocol2CodeConverter.asWorkspaceEdit(change.workspaceEdit);
+    await Server.client.sendRequest('workspace/executeCommand', {
+        command: 'rust-analyzer.applySourceChange',
+        arguments: [change]
+    });
+}
+
+export interface ApplyEditParams {
+    label?: string;
+    edit: lc.WorkspaceEdit;
+}
+
+// Handles `workspace/applyEdit`, whose edit may contain snippets.
+export async function applyEdit(
+    params: ApplyEditParams
+): Promise<lc.ApplyWorkspaceEditResponse> {
+    // Snippets can't be a part of a `WorkspaceEdit`, they are inserted into
+    // the editor after the rest of the change is applied.
+    const edit = params.edit;
+    const snippetEdits: lc.TextDocumentEdit[] = [];
+    if (edit.documentChanges) {
+        edit.documentChanges = edit.documentChanges.filter(docChange => {
+            if (
+                lc.TextDocumentEdit.is(docChange) &&
+                docChange.edits.some(isSnippet)
+            ) {
+                snippetEdits.push(docChange);
+                return false;
+            }
+            return true;
+        });
+    }
+    const wsEdit = Server.client.protocol2CodeConverter.asWorkspaceEdit(edit);
     let created;
     let moved;
-    if (change.workspaceEdit.documentChanges) {
-        for (const docChange of change.workspaceEdit.documentChanges) {
+    if (edit.documentChanges) {
+        for (const docChange of edit.documentChanges) {
             if (lc.CreateFile.is(docChange)) {
                 created = docChange.uri;
             } else if (lc.RenameFile.is(docChange)) {
@@ -23,26 +65,50 @@ export async function handle(change: SourceChange) {
         }
     }
     const toOpen = created || moved;
-    const toReveal = change.cursorPosition;
-    await vscode.workspace.applyEdit(wsEdit);
+    const applied = await vscode.workspace.applyEdit(wsEdit);
+    if (!applied) {
+        return { applied };
+    }
+    for (const docEdit of snippetEdits) {
+        await insertSnippets(docEdit);
+    }
     if (toOpen) {
         const toOpenUri = vscode.Uri.parse(toOpen);
         const doc = await vscode.workspace.openTextDocument(toOpenUri);
         await vscode.window.showTextDocument(doc);
-    } else if (toReveal) {
-        const uri = Server.client.protocol2CodeConverter.asUri(
-            toReveal.textDocument.uri
-        );
-        const position = Server.client.protocol2CodeConverter.asPosition(
-            toReveal.position
-        );
-        const editor = vscode.window.activeTextEditor;
-        if (!editor || editor.document.uri.toString() !== uri.toString()) {
-            return;
-        }
-        if (!editor.selection.isEmpty) {
-            return;
-        }
-        editor.selection = new vscode.Selection(position, position);
+    }
+    return { applied };
+}
+
+// Handles `rust-analyzer/moveCursor`, which follows an applied edit.
+export function moveCursor(params: lc.TextDocumentPositionParams) {
+    const uri = Server.client.protocol2CodeConverter.asUri(
+        params.textDocument.uri
+    );
+    const position = Server.client.protocol2CodeConverter.asPosition(
+        params.position
+    );
+    const editor = vscode.window.activeTextEditor;
+    if (!editor || editor.document.uri.toString() !== uri.toString()) {
+        return;
+    }
+    if (!editor.selection.isEmpty) {
+        return;
+    }
+    editor.selection = new vscode.Selection(position, position);
+}
+
+async function insertSnippets(docEdit: lc.TextDocumentEdit) {
+    const uri = Server.client.protocol2CodeConverter.asUri(docEdit.textDocument.uri);
+    const doc = await vscode.workspace.openTextDocument(uri);
+    const editor = await vscode.window.showTextDocument(doc);
+    // The ranges refer to the original text, so the last edit goes first.
+    const edits = [...docEdit.edits].sort((a, b) => {
+        const lineDiff = b.range.start.line - a.range.start.line;
+        return lineDiff || b.range.start.character - a.range.start.character;
+    });
+    for (const edit of edits) {
+        const range = Server.client.protocol2CodeConverter.asRange(edit.range);
+        await editor.insertSnippet(new vscode.SnippetString(edit.newText), range);
     }
 }
@@ -0,0 +1,16 @@
+import * as vscode from 'vscode';
+
+import { Server } from '../server';
+
+interface CollectGarbageResult {
+    reclaimedBytes: number;
+}
+
+export async function handle() {
+    const result = await Server.client.sendRequest<CollectGarbageResult>(
+        'rust-analyzer/collectGarbage',
+        null
+    );
+    const kb = Math.round(result.reclaimedBytes / 1024);
+    vscode.window.showInformationMessage(`rust-analyzer freed ~${kb}kb`);
+}
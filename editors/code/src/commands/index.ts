@@ -1,19 +1,27 @@
+import * as analyzerStatus from './analyzer_status';
 import * as applySourceChange from './apply_source_change';
+import * as collectGarbage from './collect_garbage';
 import * as extendSelection from './extend_selection';
 import * as joinLines from './join_lines';
 import * as matchingBrace from './matching_brace';
 import * as onEnter from './on_enter';
 import * as parentModule from './parent_module';
+import * as reloadWorkspace from './reload_workspace';
 import * as runnables from './runnables';
+import * as showReferences from './show_references';
 import * as syntaxTree from './syntaxTree';
 
 export {
+    analyzerStatus,
     applySourceChange,
+    collectGarbage,
     extendSelection,
     joinLines,
     matchingBrace,
     parentModule,
+    reloadWorkspace,
     runnables,
+    showReferences,
     syntaxTree,
     onEnter
 };
@@ -0,0 +1,5 @@
+import { Server } from '../server';
+
+export async function handle() {
+    await Server.client.sendRequest<void>('rust-analyzer/reloadWorkspace', null);
+}
@@ -63,6 +63,13 @@ function createTask(spec: Runnable): vscode.Task {
     return t;
 }
 
+// Runs a runnable which the server has sent with a command, for example with
+// a hover action.
+export async function handleSingle(runnable: Runnable) {
+    const task = createTask(runnable);
+    return await vscode.tasks.executeTask(task);
+}
+
 let prevRunnable: RunnableQuickPick | undefined;
 export async function handle() {
     const editor = vscode.window.activeTextEditor;
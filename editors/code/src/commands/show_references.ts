@@ -0,0 +1,19 @@
+import * as vscode from 'vscode';
+import * as lc from 'vscode-languageclient';
+import { Server } from '../server';
+
+// The references are found by the server, when it sends the command with a
+// hover action.
+export async function handle(
+    uri: string,
+    position: lc.Position,
+    locations: lc.Location[]
+) {
+    const converter = Server.client.protocol2CodeConverter;
+    await vscode.commands.executeCommand(
+        'editor.action.showReferences',
+        vscode.Uri.parse(uri),
+        converter.asPosition(position),
+        locations.map(it => converter.asLocation(it))
+    );
+}
@@ -5,6 +5,8 @@ import { Server } from './server';
 export class Config {
     public highlightingOn = true;
     public raLspServerPath = 'ra_lsp_server';
+    public excludeGlobs: string[] = [];
+    public features: { [feature: string]: boolean } = {};
 
     constructor() {
         vscode.workspace.onDidChangeConfiguration(_ =>
@@ -26,5 +28,15 @@ export class Config {
         if (config.has('raLspServerPath')) {
             this.raLspServerPath = config.get('raLspServerPath') as string;
         }
+
+        if (config.has('excludeGlobs')) {
+            this.excludeGlobs = config.get('excludeGlobs') as string[];
+        }
+
+        if (config.has('features')) {
+            this.features = config.get('features') as {
+                [feature: string]: boolean;
+            };
+        }
     }
 }
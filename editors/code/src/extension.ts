@@ -49,16 +49,25 @@ export function activate(context: vscode.ExtensionContext) {
     registerCommand('ra-lsp.joinLines', commands.joinLines.handle);
     registerCommand('ra-lsp.parentModule', commands.parentModule.handle);
     registerCommand('ra-lsp.run', commands.runnables.handle);
-    registerCommand(
-        'ra-lsp.applySourceChange',
-        commands.applySourceChange.handle
-    );
+    registerCommand('ra-lsp.runSingle', commands.runnables.handleSingle);
+    registerCommand('ra-lsp.showReferences', commands.showReferences.handle);
+    registerCommand('ra-lsp.reloadWorkspace', commands.reloadWorkspace.handle);
+    registerCommand('ra-lsp.analyzerStatus', commands.analyzerStatus.handle);
+    registerCommand('ra-lsp.collectGarbage', commands.collectGarbage.handle);
     overrideCommand('type', commands.onEnter.handle);
 
     // Notifications are events triggered by the language server
     const allNotifications: Iterable<
         [string, lc.GenericNotificationHandler]
-    > = [['m/publishDecorations', notifications.publishDecorations.handle]];
+    > = [
+        ['m/publishDecorations', notifications.publishDecorations.handle],
+        ['rust-analyzer/moveCursor', commands.applySourceChange.moveCursor]
+    ];
+
+    // Requests are sent by the language server, and answered by vscode
+    const allRequests: Iterable<[string, lc.GenericRequestHandler<any, any>]> = [
+        ['workspace/applyEdit', commands.applySourceChange.applyEdit]
+    ];
 
     // The events below are plain old javascript events, triggered and handled by vscode
     vscode.window.onDidChangeActiveTextEditor(
@@ -80,7 +89,7 @@ export function activate(context: vscode.ExtensionContext) {
     );
 
     // Start the language server, finally!
-    Server.start(allNotifications);
+    Server.start(allNotifications, allRequests);
 }
 
 export function deactivate(): Thenable<void> {
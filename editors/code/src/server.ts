@@ -1,3 +1,4 @@
+import * as vscode from 'vscode';
 import * as lc from 'vscode-languageclient';
 
 import { Config } from './config';
@@ -9,7 +10,8 @@ export class Server {
     public static client: lc.LanguageClient;
 
     public static start(
-        notificationHandlers: Iterable<[string, lc.GenericNotificationHandler]>
+        notificationHandlers: Iterable<[string, lc.GenericNotificationHandler]>,
+        requestHandlers: Iterable<[string, lc.GenericRequestHandler<any, any>]>
     ) {
         const run: lc.Executable = {
             command: this.config.raLspServerPath,
@@ -20,9 +22,20 @@ export class Server {
             debug: run
         };
         const clientOptions: lc.LanguageClientOptions = {
-            documentSelector: [{ scheme: 'file', language: 'rust' }],
+            documentSelector: [
+                { scheme: 'file', language: 'rust' },
+                { scheme: 'untitled', language: 'rust' }
+            ],
             initializationOptions: {
-                publishDecorations: true
+                publishDecorations: true,
+                excludeGlobs: Server.config.excludeGlobs,
+                features: Server.config.features
+            },
+            synchronize: {
+                configurationSection: 'ra-lsp'
+            },
+            middleware: {
+                provideHover
             }
         };
 
@@ -56,11 +69,66 @@ export class Server {
                 }
             }
         };
+        Server.client.registerFeature(new ExperimentalFeatures());
         Server.client.onReady().then(() => {
             for (const [type, handler] of notificationHandlers) {
                 Server.client.onNotification(type, handler);
             }
+            // Replaces the default handler of `workspace/applyEdit`, which
+            // doesn't know about snippets.
+            for (const [type, handler] of requestHandlers) {
+                Server.client.onRequest(type, handler);
+            }
         });
         Server.client.start();
     }
 }
+
+// Tells the server that we can apply text edits with snippets, see
+// `commands/apply_source_change.ts`, and that we show the actions of hovers,
+// see `provideHover`.
+class ExperimentalFeatures implements lc.StaticFeature {
+    public fillClientCapabilities(capabilities: lc.ClientCapabilities) {
+        capabilities.experimental = {
+            ...capabilities.experimental,
+            snippetTextEdit: true,
+            hoverActions: true
+        };
+    }
+    public initialize() {}
+}
+
+interface HoverWithActions extends lc.Hover {
+    actions?: lc.Command[];
+}
+
+// Like the hover of `vscode-languageclient`, but the actions of the hover
+// are rendered as links to their commands.
+async function provideHover(
+    document: vscode.TextDocument,
+    position: vscode.Position,
+    token: vscode.CancellationToken
+): Promise<vscode.Hover | undefined> {
+    const params = Server.client.code2ProtocolConverter.asTextDocumentPositionParams(
+        document,
+        position
+    );
+    const result = await Server.client.sendRequest<HoverWithActions | null>(
+        'textDocument/hover',
+        params,
+        token
+    );
+    const hover = Server.client.protocol2CodeConverter.asHover(result);
+    if (!hover || !result || !result.actions || result.actions.length === 0) {
+        return hover;
+    }
+    const links = result.actions.map(action => {
+        const args = encodeURIComponent(JSON.stringify(action.arguments || []));
+        return `[${action.title}](command:${action.command}?${args})`;
+    });
+    const markdown = new vscode.MarkdownString(links.join(' | '));
+    // Command links only work in trusted markdown.
+    markdown.isTrusted = true;
+    hover.contents.push(markdown);
+    return hover;
+}